@@ -0,0 +1,202 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use filter::SosCascade;
+use filter::rbj::LowPass;
+use traits::{Analyzer, FloatConst, Processor};
+use window::{apply_window, Window};
+
+/// The rate, in Hz, the envelope is decimated to.
+const ENVELOPE_RATE: f64 = 200f64;
+/// The cutoff, in Hz, of the envelope filter.
+const ENVELOPE_CUTOFF: f64 = 50f64;
+/// The number of envelope samples in each analyzed frame.
+const FRAME_SIZE: usize = 512;
+/// The slowest and fastest modulation rates detected, in Hz.
+const MIN_RATE: f64 = 0.5f64;
+const MAX_RATE: f64 = 30f64;
+
+/// Detects amplitude modulation, measuring its rate and depth.
+///
+/// The envelope of the input is found by rectifying and lowpass filtering it,
+/// and decimated to about 200 Hz. Frames of 512 envelope samples, about 2.5
+/// seconds, are analyzed every half frame: the spectrum of each frame, with
+/// its mean removed, is searched for the strongest modulation from 0.5 to 30
+/// Hz, whose rate and amplitude are refined by parabolic interpolation. The
+/// depth is the amplitude of the modulation relative to the mean of the
+/// envelope, so a signal scaled by `1 + depth * sin(2 pi rate t)` measures
+/// `depth`, as with tremolo.
+pub struct AmDetector<T> {
+  sample_rate: T,
+  filter: SosCascade<T>,
+  decimation: usize,
+  // Input samples since the last envelope sample
+  count: usize,
+  envelope: Vec<T>,
+  rate: T,
+  depth: T
+}
+
+impl<T> AmDetector<T> where T: Float + FloatConst {
+  /// Creates a new `AmDetector` for signals at `sample_rate`, which must be
+  /// at least 200 Hz.
+  ///
+  /// Nothing is measured until the first frame has been analyzed.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::AmDetector;
+  /// use rasp::traits::Analyzer;
+  ///
+  /// let sample_rate = 8_000f32;
+  /// let mut detector = AmDetector::new(sample_rate);
+  /// for n in 0..40_000 {
+  ///   let t = n as f32 / sample_rate;
+  ///   let tremolo = 1f32 + 0.5f32 * (2f32 * ::std::f32::consts::PI * 6f32 * t).sin();
+  ///   detector.process(tremolo * (2f32 * ::std::f32::consts::PI * 440f32 * t).sin());
+  /// }
+  /// assert!((detector.rate() - 6f32).abs() < 0.2f32);
+  /// assert!((detector.depth() - 0.5f32).abs() < 0.05f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    debug_assert!(sample_rate >= num::cast(ENVELOPE_RATE).unwrap());
+    let cutoff: T = num::cast(ENVELOPE_CUTOFF).unwrap();
+    let mut filter = SosCascade::new();
+    filter.push_section(LowPass::coefficients(sample_rate, cutoff, num::cast(0.541_196f64).unwrap()));
+    filter.push_section(LowPass::coefficients(sample_rate, cutoff, num::cast(1.306_563f64).unwrap()));
+    let decimation: f64 = num::cast(sample_rate / num::cast(ENVELOPE_RATE).unwrap()).unwrap();
+    AmDetector {
+      sample_rate,
+      filter,
+      decimation: (decimation.round() as usize).max(1),
+      count: 0,
+      envelope: Vec::with_capacity(FRAME_SIZE),
+      rate: num::zero(),
+      depth: num::zero()
+    }
+  }
+
+  /// Returns the modulation rate, in Hz, measured in the last analyzed frame.
+  ///
+  /// Without any modulation, the rate is zero.
+  pub fn rate(&self) -> T {
+    self.rate
+  }
+
+  /// Returns the modulation depth, as a ratio of the mean envelope, measured
+  /// in the last analyzed frame.
+  pub fn depth(&self) -> T {
+    self.depth
+  }
+
+  /// Analyzes a complete frame of the envelope.
+  fn analyze(&mut self) {
+    let size: T = num::cast(FRAME_SIZE).unwrap();
+    let mean = self.envelope.iter().fold(T::zero(), |sum, level| sum + *level) / size;
+    self.rate = T::zero();
+    self.depth = T::zero();
+    if mean <= T::zero() {
+      return;
+    }
+
+    let mut frame: Vec<T> = self.envelope.iter().map(|level| *level - mean).collect();
+    apply_window(&mut frame, Window::Hann);
+    let mut buffer: Vec<Complex<T>> = frame.iter().map(|sample| Complex::new(*sample, T::zero())).collect();
+    fft::forward(&mut buffer);
+    let magnitude: Vec<T> = buffer[..FRAME_SIZE / 2].iter().map(|value| value.norm()).collect();
+
+    let envelope_rate = self.sample_rate / num::cast(self.decimation).unwrap();
+    let resolution = envelope_rate / size;
+    let bin = |rate: f64| -> usize { num::cast((num::cast::<f64, T>(rate).unwrap() / resolution).round()).unwrap() };
+    let (low, high) = (bin(MIN_RATE).max(1), bin(MAX_RATE).min(FRAME_SIZE / 2 - 2));
+    let peak = (low..high + 1).fold(low, |peak, k| if magnitude[k] > magnitude[peak] { k } else { peak });
+    if magnitude[peak] <= T::zero() {
+      return;
+    }
+
+    // Parabolic interpolation of the log magnitude refines the peak between
+    // bins
+    let floor: T = num::cast(1e-30f64).unwrap();
+    let (a, b, c) = (magnitude[peak - 1].max(floor).ln(), magnitude[peak].ln(), magnitude[peak + 1].max(floor).ln());
+    let curvature = a - T::two() * b + c;
+    let offset =
+      if curvature < T::zero() {
+        ((a - c) / (T::two() * curvature)).max(-T::one() / T::two()).min(T::one() / T::two())
+      }
+      else {
+        T::zero()
+      };
+    let amplitude = (b - (a - c) * offset / (T::two() * T::two())).exp();
+
+    // A windowed sine peaks at half its amplitude times the sum of the window
+    let gain = size / T::two();
+    self.rate = (num::cast::<usize, T>(peak).unwrap() + offset) * resolution;
+    self.depth = T::two() * amplitude / gain / mean;
+  }
+}
+
+impl<T> Analyzer<T> for AmDetector<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) {
+    let envelope = self.filter.process(sample.abs());
+    self.count += 1;
+    if self.count == self.decimation {
+      self.count = 0;
+      self.envelope.push(envelope);
+      if self.envelope.len() == FRAME_SIZE {
+        self.analyze();
+        self.envelope.drain(..FRAME_SIZE / 2);
+      }
+    }
+  }
+
+  fn clear(&mut self) {
+    self.filter.clear();
+    self.count = 0;
+    self.envelope.clear();
+    self.rate = T::zero();
+    self.depth = T::zero();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Measures a 1 kHz carrier, modulated at `rate` to `depth`, for 8
+  /// seconds.
+  fn measure(rate: f64, depth: f64) -> (f64, f64) {
+    let mut detector = AmDetector::new(16_000f64);
+    for n in 0..128_000 {
+      let t = n as f64 / 16_000f64;
+      let envelope = 1f64 + depth * (2f64 * ::std::f64::consts::PI * rate * t).sin();
+      detector.process(0.5f64 * envelope * (2f64 * ::std::f64::consts::PI * 1_000f64 * t).sin());
+    }
+    (detector.rate(), detector.depth())
+  }
+
+  #[test]
+  fn modulation() {
+    for &(rate, depth) in [(1.5f64, 0.2f64), (5f64, 0.5f64), (12.3f64, 0.8f64), (25f64, 0.1f64)].iter() {
+      let (measured_rate, measured_depth) = measure(rate, depth);
+      assert!((measured_rate - rate).abs() < 0.1f64);
+      assert!((measured_depth - depth).abs() < 0.05f64 * depth.max(0.2f64));
+    }
+    let (_, depth) = measure(5f64, 0f64);
+    assert!(depth < 0.01f64);
+  }
+
+  #[test]
+  fn clear() {
+    let mut detector = AmDetector::new(16_000f64);
+    assert_eq!((detector.rate(), detector.depth()), (0f64, 0f64));
+    detector.process_block(&vec![0f64; 64_000]);
+    assert_eq!((detector.rate(), detector.depth()), (0f64, 0f64));
+    let noise: Vec<f64> = (0..64_000).map(|n| ((n * 7_919 % 1_000) as f64 / 500f64) - 1f64).collect();
+    detector.process_block(&noise);
+    detector.clear();
+    assert_eq!((detector.rate(), detector.depth()), (0f64, 0f64));
+  }
+}
@@ -0,0 +1,127 @@
+use num;
+use num::traits::Float;
+
+/// Reduces an envelope to breakpoints, each a sample offset and a level,
+/// whose linear interpolation is within `tolerance` of every sample.
+///
+/// Breakpoints are chosen by the Ramer–Douglas–Peucker algorithm, measuring
+/// the error in level, so long ramps and steady segments collapse to their
+/// ends while fast changes keep as many breakpoints as they need. The first
+/// and last samples are always kept, and the breakpoints are in order of
+/// offset. An empty `buffer` has no breakpoints.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::analysis::{breakpoints_to_envelope, envelope_to_breakpoints};
+///
+/// let envelope: Vec<f32> = (0..100).map(|n| if n < 50 { n as f32 / 50f32 } else { 1f32 }).collect();
+/// let breakpoints = envelope_to_breakpoints(&envelope, 0.01f32);
+/// assert_eq!(breakpoints, vec![(0, 0f32), (50, 1f32), (99, 1f32)]);
+///
+/// let restored = breakpoints_to_envelope(&breakpoints, envelope.len());
+/// assert!(restored.iter().zip(envelope.iter()).all(|(a, b)| (a - b).abs() <= 0.01f32));
+/// ```
+pub fn envelope_to_breakpoints<T: Float>(buffer: &[T], tolerance: T) -> Vec<(usize, T)> {
+  if buffer.is_empty() {
+    return Vec::new();
+  }
+  let last = buffer.len() - 1;
+  let mut keep = vec![false; buffer.len()];
+  keep[0] = true;
+  keep[last] = true;
+
+  // Segments still to be simplified, split at their worst sample until every
+  // sample is within the tolerance
+  let mut segments = vec![(0, last)];
+  while let Some((start, end)) = segments.pop() {
+    let mut worst = (start, T::zero());
+    for n in start + 1..end {
+      let error = (buffer[n] - interpolate((start, buffer[start]), (end, buffer[end]), n)).abs();
+      if error > worst.1 {
+        worst = (n, error);
+      }
+    }
+    if worst.1 > tolerance {
+      keep[worst.0] = true;
+      segments.push((start, worst.0));
+      segments.push((worst.0, end));
+    }
+  }
+
+  buffer.iter()
+    .enumerate()
+    .filter(|&(n, _)| keep[n])
+    .map(|(n, level)| (n, *level))
+    .collect()
+}
+
+/// Renders `length` samples of the envelope through `breakpoints`, by linear
+/// interpolation.
+///
+/// The breakpoints must be in order of offset. The level is held before the
+/// first breakpoint and after the last, and an envelope without breakpoints
+/// is silent.
+pub fn breakpoints_to_envelope<T: Float>(breakpoints: &[(usize, T)], length: usize) -> Vec<T> {
+  let mut envelope = vec![T::zero(); length];
+  if breakpoints.is_empty() {
+    return envelope;
+  }
+  let mut segment = 0;
+  for (n, sample) in envelope.iter_mut().enumerate() {
+    while segment + 1 < breakpoints.len() && breakpoints[segment + 1].0 <= n {
+      segment += 1;
+    }
+    *sample =
+      if n <= breakpoints[segment].0 || segment + 1 == breakpoints.len() {
+        breakpoints[segment].1
+      }
+      else {
+        interpolate(breakpoints[segment], breakpoints[segment + 1], n)
+      };
+  }
+  envelope
+}
+
+/// Returns the level at offset `n` on the line between two breakpoints.
+fn interpolate<T: Float>(start: (usize, T), end: (usize, T), n: usize) -> T {
+  if end.0 == start.0 {
+    return start.1;
+  }
+  let offset: T = num::cast(n - start.0).unwrap();
+  let length: T = num::cast(end.0 - start.0).unwrap();
+  start.1 + (end.1 - start.1) * offset / length
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn reduction() {
+    let envelope: Vec<f64> = (0..1_000)
+      .map(|n| (-(n as f64) / 200f64).exp() * (1f64 + 0.3f64 * (n as f64 / 50f64).sin()))
+      .collect();
+    for tolerance in [0.001f64, 0.01f64, 0.1f64].iter() {
+      let breakpoints = envelope_to_breakpoints(&envelope, *tolerance);
+      assert_eq!(breakpoints.first(), Some(&(0, envelope[0])));
+      assert_eq!(breakpoints.last(), Some(&(999, envelope[999])));
+      assert!(breakpoints.windows(2).all(|pair| pair[0].0 < pair[1].0));
+      assert!(breakpoints.len() < 100);
+
+      let restored = breakpoints_to_envelope(&breakpoints, envelope.len());
+      assert!(restored.iter().zip(envelope.iter()).all(|(a, b)| (a - b).abs() <= *tolerance + 1e-12f64));
+    }
+    assert!(envelope_to_breakpoints(&envelope, 0.001f64).len() > envelope_to_breakpoints(&envelope, 0.1f64).len());
+  }
+
+  #[test]
+  fn edges() {
+    assert!(envelope_to_breakpoints::<f64>(&[], 0.1f64).is_empty());
+    assert_eq!(envelope_to_breakpoints(&[0.5f64], 0.1f64), vec![(0, 0.5f64)]);
+    assert_eq!(envelope_to_breakpoints(&[0.5f64; 10], 0f64), vec![(0, 0.5f64), (9, 0.5f64)]);
+
+    assert_eq!(breakpoints_to_envelope::<f64>(&[], 3), vec![0f64; 3]);
+    assert_eq!(breakpoints_to_envelope(&[(1, 1f64), (3, 0f64)], 5), vec![1f64, 1f64, 0.5f64, 0f64, 0f64]);
+  }
+}
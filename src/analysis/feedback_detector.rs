@@ -0,0 +1,461 @@
+use num;
+use num::traits::Float;
+
+use super::power_spectrum;
+use filter::rbj::BandStop;
+use traits::{FloatConst, Processor};
+use util;
+use window::{apply_window, Window};
+
+/// Bins either side of a peak covered by the main lobe of the Hann window.
+const MAIN_LOBE: usize = 2;
+/// Bins either side of the main lobe averaged as the neighborhood of a peak.
+const NEIGHBORHOOD: usize = 8;
+/// The level, in dB, below which peaks are ignored.
+const FLOOR: f64 = -60f64;
+
+/// A notch deployed by the suppressor.
+struct Notch<T> {
+  frequency: T,
+  filter: BandStop<T>
+}
+
+/// Detects, and optionally suppresses, acoustic feedback.
+///
+/// Feedback builds up as sustained narrowband ringing, so the input is
+/// analyzed in Hann windowed frames, and a spectral peak is reported when its
+/// level rises above the average of its neighboring bins by the threshold for
+/// a number of consecutive frames. The frequency of each peak is refined by
+/// parabolic interpolation.
+///
+/// When suppression is enabled, a narrow `BandStop` filter is deployed at each
+/// detected frequency, from a pool of notches. Once the pool is full, the
+/// oldest notch is moved to the new frequency. The output of the notches is
+/// analyzed, so feedback that a notch has not removed is detected again.
+pub struct FeedbackDetector<T> {
+  sample_rate: T,
+  frame_size: usize,
+  threshold: T,
+  persistence: usize,
+  suppression: bool,
+  max_notches: usize,
+  notch_q: T,
+  // Samples of the current frame
+  buffer: Vec<T>,
+  // Consecutive frames each bin has been ringing
+  counts: Vec<usize>,
+  detected: Vec<T>,
+  notches: Vec<Notch<T>>,
+  // Index of the oldest notch
+  oldest: usize,
+  output: T
+}
+
+impl<T> FeedbackDetector<T> where T: Float + FloatConst {
+  /// Creates a new `FeedbackDetector` analyzing frames of `frame_size`
+  /// samples, with suppression disabled.
+  ///
+  /// Peaks must rise 20 dB above their neighborhood for 8 frames to be
+  /// detected. The suppressor deploys up to 8 notches, with a q factor of 30.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `frame_size` is not a power of two, large enough to fit the
+  /// neighborhood of a peak, which is at least 64 samples.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::FeedbackDetector;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut detector = FeedbackDetector::new(16_000f32, 256);
+  /// detector.set_suppression(true);
+  /// for n in 0..16_000 {
+  ///   let phase = 2f32 * ::std::f32::consts::PI * 1_000f32 * n as f32 / 16_000f32;
+  ///   detector.process(phase.sin());
+  /// }
+  /// assert_eq!(detector.notches().len(), 1);
+  /// ```
+  pub fn new(sample_rate: T, frame_size: usize) -> Self {
+    assert!(frame_size.is_power_of_two() && frame_size >= 64);
+    FeedbackDetector {
+      sample_rate,
+      frame_size,
+      threshold: num::cast(20f64).unwrap(),
+      persistence: 8,
+      suppression: false,
+      max_notches: 8,
+      notch_q: num::cast(30f64).unwrap(),
+      buffer: Vec::with_capacity(frame_size),
+      counts: vec![0; frame_size / 2 + 1],
+      detected: Vec::new(),
+      notches: Vec::new(),
+      oldest: 0,
+      output: num::zero()
+    }
+  }
+
+  /// Sets how far, in dB, a peak must rise above its neighborhood to be
+  /// considered ringing.
+  ///
+  /// `threshold` must be positive, else it is not updated.
+  pub fn set_threshold(&mut self, threshold: T) {
+    if threshold > T::zero() && threshold.is_finite() {
+      self.threshold = threshold;
+    }
+  }
+
+  /// Returns how far, in dB, a peak must rise above its neighborhood to be
+  /// considered ringing.
+  pub fn get_threshold(&self) -> T {
+    self.threshold
+  }
+
+  /// Sets the number of consecutive frames a peak must be ringing to be
+  /// detected.
+  ///
+  /// `frames` must be positive, else it is not updated.
+  pub fn set_persistence(&mut self, frames: usize) {
+    if frames > 0 {
+      self.persistence = frames;
+    }
+  }
+
+  /// Returns the number of consecutive frames a peak must be ringing to be
+  /// detected.
+  pub fn get_persistence(&self) -> usize {
+    self.persistence
+  }
+
+  /// Sets whether notches are deployed at detected frequencies.
+  ///
+  /// Deployed notches are kept, but bypassed, while suppression is
+  /// disabled.
+  pub fn set_suppression(&mut self, suppression: bool) {
+    self.suppression = suppression;
+  }
+
+  /// Returns `true` if notches are deployed at detected frequencies.
+  pub fn is_suppressing(&self) -> bool {
+    self.suppression
+  }
+
+  /// Sets the size of the pool of notches.
+  ///
+  /// If `count` is less than the number of deployed notches, the newest
+  /// notches are kept.
+  pub fn set_max_notches(&mut self, count: usize) {
+    // The notches are put in order from oldest to newest, so the pool fills
+    // and replaces them in that order whether it grows or shrinks
+    self.notches.rotate_left(self.oldest);
+    self.oldest = 0;
+    if count < self.notches.len() {
+      let len = self.notches.len();
+      self.notches.drain(..len - count);
+    }
+    self.max_notches = count;
+  }
+
+  /// Returns the size of the pool of notches.
+  pub fn get_max_notches(&self) -> usize {
+    self.max_notches
+  }
+
+  /// Sets the q factor of deployed notches.
+  ///
+  /// `q` must be positive, else it is not updated. Notches already deployed
+  /// keep their q factor.
+  pub fn set_notch_q(&mut self, q: T) {
+    if q > T::zero() && q.is_finite() {
+      self.notch_q = q;
+    }
+  }
+
+  /// Returns the q factor of deployed notches.
+  pub fn get_notch_q(&self) -> T {
+    self.notch_q
+  }
+
+  /// Returns the frequencies, in Hz, detected in the last analyzed frame.
+  pub fn detected(&self) -> &[T] {
+    &self.detected
+  }
+
+  /// Returns the frequencies, in Hz, of the deployed notches.
+  pub fn notches(&self) -> Vec<T> {
+    self.notches.iter().map(|notch| notch.frequency).collect()
+  }
+
+  /// Analyzes a complete frame, and deploys notches at detected frequencies.
+  fn analyze(&mut self) {
+    let mut frame = self.buffer.clone();
+    apply_window(&mut frame, Window::Hann);
+    let power = power_spectrum(&frame);
+    self.buffer.clear();
+
+    // A windowed sine peaks at a quarter of its amplitude times the frame size
+    let scale: T = T::two() * T::two() / num::cast(self.frame_size).unwrap();
+    let floor: T = num::cast(FLOOR).unwrap();
+    let bins = power.len();
+
+    let mut counts = vec![0; bins];
+    self.detected.clear();
+    for k in MAIN_LOBE + 1..bins - MAIN_LOBE - 1 {
+      if power[k] < power[k - 1] || power[k] < power[k + 1]
+        || util::to_db(power[k].sqrt() * scale) < floor {
+        continue;
+      }
+
+      let (mut sum, mut count) = (T::zero(), 0);
+      let lower = k.saturating_sub(MAIN_LOBE + NEIGHBORHOOD);
+      let upper = (k + MAIN_LOBE + NEIGHBORHOOD).min(bins - 1);
+      for j in (lower..k - MAIN_LOBE).chain(k + MAIN_LOBE + 1..upper + 1) {
+        sum = sum + power[j];
+        count += 1;
+      }
+      let neighborhood = sum / num::cast(count).unwrap();
+      let ten: T = num::cast(10f64).unwrap();
+      let ringing = neighborhood <= T::zero()
+        || ten * (power[k] / neighborhood).log10() >= self.threshold;
+      if !ringing {
+        continue;
+      }
+
+      // Allows a peak to drift by a bin between frames
+      counts[k] = 1 + self.counts[k - 1].max(self.counts[k]).max(self.counts[k + 1]);
+      if counts[k] >= self.persistence {
+        self.detected.push(self.interpolate(&power, k));
+      }
+    }
+    self.counts = counts;
+
+    if self.suppression {
+      for i in 0..self.detected.len() {
+        self.deploy(self.detected[i]);
+      }
+    }
+  }
+
+  /// Returns the frequency of the peak at bin `k`, refined by fitting a
+  /// parabola to the log power of the bin and its neighbors.
+  fn interpolate(&self, power: &[T], k: usize) -> T {
+    let half: T = num::cast(0.5f64).unwrap();
+    let (a, b, c) = (power[k - 1].ln(), power[k].ln(), power[k + 1].ln());
+    let denominator = a - T::two() * b + c;
+    let offset =
+      if denominator.is_finite() && denominator != T::zero() {
+        half * (a - c) / denominator
+      }
+      else {
+        T::zero()
+      };
+    let bin: T = num::cast(k).unwrap();
+    let frame_size: T = num::cast(self.frame_size).unwrap();
+    (bin + offset) * self.sample_rate / frame_size
+  }
+
+  /// Deploys a notch at `frequency`, unless one already covers it.
+  fn deploy(&mut self, frequency: T) {
+    let bandwidth = frequency / self.notch_q;
+    let covered = self.notches.iter()
+      .any(|notch| (notch.frequency - frequency).abs() < bandwidth);
+    if covered || self.max_notches == 0 {
+      return;
+    }
+
+    let mut filter = BandStop::new();
+    filter.set_coefficients(self.sample_rate, frequency, self.notch_q);
+    let notch = Notch { frequency, filter };
+    if self.notches.len() < self.max_notches {
+      self.notches.push(notch);
+    }
+    else {
+      self.notches[self.oldest] = notch;
+      self.oldest = (self.oldest + 1) % self.notches.len();
+    }
+  }
+}
+
+impl<T> Processor<T> for FeedbackDetector<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let mut output = sample;
+    if self.suppression {
+      for notch in self.notches.iter_mut() {
+        output = notch.filter.process(output);
+      }
+    }
+
+    self.buffer.push(output);
+    if self.buffer.len() == self.frame_size {
+      self.analyze();
+    }
+    self.output = output;
+    self.output
+  }
+
+  /// Clears the analysis, and removes every deployed notch.
+  fn clear(&mut self) {
+    self.buffer.clear();
+    for count in self.counts.iter_mut() {
+      *count = 0;
+    }
+    self.detected.clear();
+    self.notches.clear();
+    self.oldest = 0;
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+  use ::traits::Processor;
+
+  /// Returns quiet white noise from a linear congruential generator.
+  fn noise(length: usize) -> Vec<f32> {
+    let mut state = 12_345u32;
+    (0..length)
+      .map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        0.01f32 * ((state >> 8) as f32 / (1u32 << 23) as f32 - 1f32)
+      })
+      .collect()
+  }
+
+  /// Returns noise with a tone at `frequency` from sample `start`.
+  fn ringing(sample_rate: f32, frequency: f32, start: usize, length: usize) -> Vec<f32> {
+    noise(length).into_iter().enumerate()
+      .map(|(n, sample)| {
+        if n < start {
+          return sample;
+        }
+        sample + 0.5f32 * (2f32 * PI * frequency * n as f32 / sample_rate).sin()
+      })
+      .collect()
+  }
+
+  #[test]
+  #[should_panic]
+  fn frame_size_not_power_of_two() {
+    FeedbackDetector::new(16_000f32, 96);
+  }
+
+  #[test]
+  fn noise_is_not_detected() {
+    let mut detector = FeedbackDetector::new(16_000f32, 256);
+    for sample in noise(16_000) {
+      detector.process(sample);
+    }
+    assert!(detector.detected().is_empty());
+  }
+
+  #[test]
+  fn detects_sustained_tone() {
+    let sample_rate = 16_000f32;
+    let mut detector = FeedbackDetector::new(sample_rate, 256);
+
+    // A tone lasting fewer frames than the persistence is ignored
+    for sample in ringing(sample_rate, 1_234f32, 14_464, 16_000) {
+      detector.process(sample);
+    }
+    assert!(detector.detected().is_empty());
+
+    for sample in ringing(sample_rate, 1_234f32, 0, 16_000) {
+      detector.process(sample);
+    }
+    assert_eq!(detector.detected().len(), 1);
+    assert!((detector.detected()[0] - 1_234f32).abs() < 5f32);
+    assert!(detector.notches().is_empty());
+  }
+
+  #[test]
+  fn suppresses_tone() {
+    let sample_rate = 16_000f32;
+    let mut detector = FeedbackDetector::new(sample_rate, 256);
+    detector.set_suppression(true);
+
+    let input = ringing(sample_rate, 2_100f32, 0, 32_000);
+    let output: Vec<f32> = input.iter().map(|sample| detector.process(*sample)).collect();
+    assert_eq!(detector.notches().len(), 1);
+
+    let energy = |samples: &[f32]| samples.iter().fold(0f32, |sum, x| sum + x * x);
+    assert!(energy(&output[16_000..]) < 0.01f32 * energy(&input[16_000..]));
+  }
+
+  #[test]
+  fn notch_pool() {
+    let sample_rate = 16_000f32;
+    let mut detector = FeedbackDetector::new(sample_rate, 256);
+    detector.set_suppression(true);
+    detector.set_max_notches(2);
+
+    for frequency in [1_000f32, 3_000f32, 5_000f32].iter() {
+      for sample in ringing(sample_rate, *frequency, 0, 8_000) {
+        detector.process(sample);
+      }
+    }
+
+    // The oldest notch is replaced
+    let notches = detector.notches();
+    assert_eq!(notches.len(), 2);
+    assert!((notches[0] - 5_000f32).abs() < 10f32);
+    assert!((notches[1] - 3_000f32).abs() < 10f32);
+
+    detector.set_max_notches(1);
+    assert!((detector.notches()[0] - 5_000f32).abs() < 10f32);
+
+    detector.clear();
+    assert!(detector.notches().is_empty());
+  }
+
+  #[test]
+  fn resized_notch_pool() {
+    let sample_rate = 16_000f32;
+    let mut detector = FeedbackDetector::new(sample_rate, 256);
+    detector.set_suppression(true);
+    detector.set_max_notches(3);
+    let ring = |detector: &mut FeedbackDetector<f32>, frequency: f32| {
+      for sample in ringing(sample_rate, frequency, 0, 8_000) {
+        detector.process(sample);
+      }
+    };
+    let near = |notch: f32, frequency: f32| (notch - frequency).abs() < 10f32;
+
+    // Once the pool has wrapped, shrinking it keeps the newest notches, and
+    // a new tone then replaces the oldest of those
+    for frequency in [1_000f32, 2_000f32, 3_000f32, 4_000f32].iter() {
+      ring(&mut detector, *frequency);
+    }
+    detector.set_max_notches(2);
+    let notches = detector.notches();
+    assert_eq!(notches.len(), 2);
+    assert!(near(notches[0], 3_000f32) && near(notches[1], 4_000f32));
+
+    ring(&mut detector, 5_000f32);
+    let notches = detector.notches();
+    assert_eq!(notches.len(), 2);
+    assert!(near(notches[0], 5_000f32) && near(notches[1], 4_000f32));
+
+    // Growing it adds notches, and once it is full again the oldest are
+    // replaced first
+    detector.set_max_notches(4);
+    for frequency in [6_000f32, 7_000f32, 7_500f32, 1_500f32].iter() {
+      ring(&mut detector, *frequency);
+    }
+    let mut notches = detector.notches();
+    notches.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(notches.len(), 4);
+    for (notch, frequency) in notches.iter().zip([1_500f32, 6_000f32, 7_000f32, 7_500f32].iter()) {
+      assert!(near(*notch, *frequency));
+    }
+
+    detector.clear();
+    assert!(detector.notches().is_empty());
+  }
+}
@@ -0,0 +1,212 @@
+use num;
+use num::traits::Float;
+
+use traits::StereoAnalyzer;
+
+/// The level, as a linear ratio, below which points are not normalized.
+const FLOOR: f64 = 1e-6f64;
+
+/// Prepares stereo samples for a goniometer, or vectorscope, display.
+///
+/// Stereo pairs are rotated by 45 degrees into mid and side, so a mono
+/// signal draws a vertical line, a signal only in one channel a diagonal, and
+/// a signal with the channels out of polarity a horizontal line. Each point
+/// is `(side, mid)`, with `side = (right - left) / sqrt(2)` and
+/// `mid = (left + right) / sqrt(2)`.
+///
+/// Only one in every `decimation` pairs is kept, in a ring of the latest
+/// points, which a display pulls at its own rate. Subsampling does not need
+/// filtering here, as the display only draws the shape of the point cloud,
+/// not a waveform. When normalization is enabled, the pulled points are
+/// scaled so the furthest from the center lies on the edge of the unit
+/// square, making quiet signals as visible as loud ones.
+#[derive(Clone)]
+pub struct Goniometer<T> {
+  decimation: usize,
+  normalization: bool,
+  // Pairs since the last kept point
+  count: usize,
+  ring: Vec<(T, T)>,
+  write_ptr: usize,
+  len: usize,
+  points: Vec<(T, T)>
+}
+
+impl<T> Goniometer<T> where T: Float {
+  /// Creates a new `Goniometer` keeping the latest `capacity` points, one in
+  /// every `decimation` stereo pairs, with normalization enabled.
+  ///
+  /// `capacity` and `decimation` must be at least one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::Goniometer;
+  /// use rasp::traits::StereoAnalyzer;
+  ///
+  /// let mut goniometer = Goniometer::new(512, 4);
+  /// let left = vec![0.25f32; 64];
+  /// let right = vec![0.25f32; 64];
+  /// goniometer.process_block(&left, &right);
+  ///
+  /// // A mono signal draws a vertical line
+  /// let points = goniometer.points();
+  /// assert_eq!(points.len(), 16);
+  /// assert!(points.iter().all(|&(side, mid)| side == 0f32 && (mid - 1f32).abs() < 1e-6f32));
+  /// ```
+  pub fn new(capacity: usize, decimation: usize) -> Self {
+    debug_assert!(capacity > 0 && decimation > 0);
+    Goniometer {
+      decimation,
+      normalization: true,
+      count: 0,
+      ring: vec![(num::zero(), num::zero()); capacity],
+      write_ptr: 0,
+      len: 0,
+      points: Vec::with_capacity(capacity)
+    }
+  }
+
+  /// Returns the largest number of points kept.
+  pub fn capacity(&self) -> usize {
+    self.ring.len()
+  }
+
+  /// Returns the number of points kept.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  /// Returns `true` if no points are kept.
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Sets how many stereo pairs are measured for every point kept.
+  ///
+  /// `decimation` must be at least one, else it is not updated.
+  pub fn set_decimation(&mut self, decimation: usize) {
+    if decimation > 0 {
+      self.decimation = decimation;
+      self.count = 0;
+    }
+  }
+
+  /// Returns how many stereo pairs are measured for every point kept.
+  pub fn get_decimation(&self) -> usize {
+    self.decimation
+  }
+
+  /// Sets whether pulled points are normalized to their peak.
+  pub fn set_normalization(&mut self, normalization: bool) {
+    self.normalization = normalization;
+  }
+
+  /// Returns `true` if pulled points are normalized to their peak.
+  pub fn get_normalization(&self) -> bool {
+    self.normalization
+  }
+
+  /// Returns the largest coordinate of any kept point, before
+  /// normalization.
+  pub fn peak(&self) -> T {
+    (0..self.len)
+      .map(|n| self.kept(n))
+      .fold(T::zero(), |peak, (side, mid)| peak.max(side.abs()).max(mid.abs()))
+  }
+
+  /// Returns the kept points, from oldest to newest, as `(side, mid)`.
+  pub fn points(&mut self) -> &[(T, T)] {
+    let peak = self.peak();
+    let gain =
+      if self.normalization && peak > num::cast(FLOOR).unwrap() {
+        T::one() / peak
+      }
+      else {
+        T::one()
+      };
+    self.points.clear();
+    for n in 0..self.len {
+      let (side, mid) = self.kept(n);
+      self.points.push((side * gain, mid * gain));
+    }
+    &self.points
+  }
+
+  /// Returns the `n`th kept point, counting from the oldest.
+  fn kept(&self, n: usize) -> (T, T) {
+    let capacity = self.ring.len();
+    self.ring[(self.write_ptr + capacity - self.len + n) % capacity]
+  }
+}
+
+impl<T> StereoAnalyzer<T> for Goniometer<T> where T: Float {
+  fn process(&mut self, left: T, right: T) {
+    if self.count == 0 {
+      let scale = T::one() / (T::one() + T::one()).sqrt();
+      self.ring[self.write_ptr] = ((right - left) * scale, (left + right) * scale);
+      self.write_ptr = (self.write_ptr + 1) % self.ring.len();
+      self.len = (self.len + 1).min(self.ring.len());
+    }
+    self.count = (self.count + 1) % self.decimation;
+  }
+
+  fn clear(&mut self) {
+    self.count = 0;
+    self.write_ptr = 0;
+    self.len = 0;
+    self.points.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rotation() {
+    let mut goniometer = Goniometer::new(4, 1);
+    goniometer.set_normalization(false);
+    assert!(!goniometer.get_normalization());
+    let half = 0.5f64.sqrt();
+
+    goniometer.process(1f64, 0f64);
+    goniometer.process(0f64, 1f64);
+    goniometer.process(1f64, -1f64);
+    goniometer.process(0.5f64, 0.5f64);
+    let expected = [(-half, half), (half, half), (-2f64 * half, 0f64), (0f64, half)];
+    for (point, expected) in goniometer.points().iter().zip(expected.iter()) {
+      assert!((point.0 - expected.0).abs() < 1e-12f64 && (point.1 - expected.1).abs() < 1e-12f64);
+    }
+  }
+
+  #[test]
+  fn ring() {
+    let mut goniometer = Goniometer::new(3, 2);
+    assert!(goniometer.is_empty());
+    let left: Vec<f64> = (0..10).map(|n| n as f64).collect();
+    goniometer.process_block(&left, &left);
+
+    // One in every two pairs is kept, and only the latest three
+    assert_eq!(goniometer.len(), 3);
+    assert_eq!(goniometer.capacity(), 3);
+    let mids: Vec<f64> = goniometer.points().iter().map(|point| point.1).collect();
+    assert_eq!(mids, vec![4f64 / 8f64, 6f64 / 8f64, 1f64]);
+    assert!((goniometer.peak() - 8f64 * 2f64.sqrt()).abs() < 1e-12f64);
+
+    goniometer.set_decimation(0);
+    assert_eq!(goniometer.get_decimation(), 2);
+    goniometer.clear();
+    assert!(goniometer.points().is_empty());
+  }
+
+  #[test]
+  fn normalization() {
+    let mut goniometer = Goniometer::new(16, 1);
+    // Silence is not raised to full scale
+    goniometer.process_block(&[1e-9f64; 4], &[0f64; 4]);
+    assert!(goniometer.points().iter().all(|point| point.1.abs() < 1e-6f64));
+    goniometer.process_block(&[0.01f64; 4], &[-0.01f64; 4]);
+    assert!(goniometer.points().iter().any(|point| (point.0 + 1f64).abs() < 1e-12f64));
+  }
+}
@@ -0,0 +1,270 @@
+use num;
+use num::traits::Float;
+
+use traits::FloatConst;
+use util;
+use window::{apply_window, Window};
+
+/// The oversampling factor used to find the true peak.
+const OVERSAMPLING: usize = 4;
+/// The number of taps of each phase of the interpolation filter.
+const TAPS: usize = 13;
+
+/// The levels measured by `Headroom`, with every level in dB.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeadroomReport<T> {
+  /// The largest sample magnitude, in dBFS.
+  pub peak: T,
+  /// The largest magnitude between samples, in dBTP.
+  pub true_peak: T,
+  /// The RMS level of every sample analyzed, in dBFS.
+  pub rms: T,
+  /// The difference between the true peak and RMS levels.
+  pub crest_factor: T,
+  /// The gain to apply to reach the targets.
+  pub gain: T,
+  /// Whether the gain is limited by the headroom rather than the target RMS
+  /// level.
+  pub peak_limited: bool
+}
+
+/// Measures the levels of a signal and suggests a gain to stage it.
+///
+/// The true peak is found by interpolating the signal four times with a
+/// windowed sinc filter, so peaks between samples, which are clipped by
+/// converters and codecs, are included. The RMS level is integrated over
+/// every sample since the analyzer was created or cleared.
+///
+/// The suggested gain places the true peak the target headroom below full
+/// scale. When a target RMS level is set, the gain reaches it instead, unless
+/// that would leave less than the target headroom.
+pub struct Headroom<T> {
+  headroom: T,
+  target_rms: Option<T>,
+  // Polyphase interpolation filter, one set of taps for each phase
+  phases: Vec<Vec<T>>,
+  history: Vec<T>,
+  write_ptr: usize,
+  peak: T,
+  true_peak: T,
+  energy: T,
+  length: usize
+}
+
+impl<T> Headroom<T> where T: Float + FloatConst {
+  /// Creates a new `Headroom` targeting 1 dB of headroom, without a target
+  /// RMS level.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::Headroom;
+  ///
+  /// let samples: Vec<f32> = (0..4_410).map(|n| 0.25f32 * (n as f32 * 0.1f32).sin()).collect();
+  ///
+  /// let mut headroom = Headroom::new();
+  /// headroom.add(&samples);
+  /// let report = headroom.report();
+  /// assert!((report.true_peak - -12.04f32).abs() < 0.1f32);
+  /// assert!((report.gain - 11.04f32).abs() < 0.1f32);
+  /// ```
+  pub fn new() -> Self {
+    let length = OVERSAMPLING * (TAPS - 1) + 1;
+    let center: T = num::cast((length - 1) / 2).unwrap();
+    let factor: T = num::cast(OVERSAMPLING).unwrap();
+    let mut taps: Vec<T> = (0..length)
+      .map(|n| {
+        let x = (num::cast::<usize, T>(n).unwrap() - center) / factor;
+        if x == T::zero() {
+          T::one()
+        }
+        else {
+          (T::pi() * x).sin() / (T::pi() * x)
+        }
+      })
+      .collect();
+    apply_window(&mut taps, Window::Hann);
+
+    let phases = (0..OVERSAMPLING)
+      .map(|phase| (0..TAPS).map(|k| taps.get(k * OVERSAMPLING + phase).cloned().unwrap_or_else(T::zero)).collect())
+      .collect();
+
+    Headroom {
+      headroom: T::one(),
+      target_rms: None,
+      phases,
+      history: vec![T::zero(); TAPS],
+      write_ptr: 0,
+      peak: T::zero(),
+      true_peak: T::zero(),
+      energy: T::zero(),
+      length: 0
+    }
+  }
+
+  /// Sets the headroom, in dB, to leave below full scale.
+  ///
+  /// `headroom` must not be negative, else it is not updated.
+  pub fn set_headroom(&mut self, headroom: T) {
+    if headroom >= T::zero() && headroom.is_finite() {
+      self.headroom = headroom;
+    }
+  }
+
+  /// Returns the headroom, in dB, to leave below full scale.
+  pub fn get_headroom(&self) -> T {
+    self.headroom
+  }
+
+  /// Sets the RMS level, in dBFS, to reach, or `None` to only target the
+  /// headroom.
+  ///
+  /// `target_rms` must be finite, else it is not updated.
+  pub fn set_target_rms(&mut self, target_rms: Option<T>) {
+    match target_rms {
+      Some(level) if !level.is_finite() => {},
+      _ => self.target_rms = target_rms
+    }
+  }
+
+  /// Returns the RMS level, in dBFS, to reach, if any.
+  pub fn get_target_rms(&self) -> Option<T> {
+    self.target_rms
+  }
+
+  /// Adds samples to the analysis.
+  pub fn add(&mut self, samples: &[T]) {
+    for sample in samples {
+      self.history[self.write_ptr] = *sample;
+      self.write_ptr = (self.write_ptr + 1) % TAPS;
+
+      for taps in &self.phases {
+        // The newest sample is multiplied by the first tap
+        let mut interpolated = T::zero();
+        for (k, tap) in taps.iter().enumerate() {
+          let index = (self.write_ptr + TAPS - 1 - k) % TAPS;
+          interpolated = interpolated + *tap * self.history[index];
+        }
+        self.true_peak = self.true_peak.max(interpolated.abs());
+      }
+
+      self.peak = self.peak.max(sample.abs());
+      self.energy = self.energy + *sample * *sample;
+      self.length += 1;
+    }
+  }
+
+  /// Returns the levels measured so far, and the suggested gain.
+  ///
+  /// The suggested gain is zero until any signal has been analyzed.
+  pub fn report(&self) -> HeadroomReport<T> {
+    let peak = util::to_db(self.peak);
+    // The true peak is never less than the sample peak
+    let true_peak = util::to_db(self.true_peak.max(self.peak));
+    let rms = if self.length > 0 {
+      util::to_db((self.energy / num::cast(self.length).unwrap()).sqrt())
+    }
+    else {
+      util::to_db(T::zero())
+    };
+
+    let floor: T = util::to_db(T::zero());
+    let (gain, peak_limited) = if true_peak <= floor {
+      (T::zero(), false)
+    }
+    else {
+      let peak_gain = -self.headroom - true_peak;
+      match self.target_rms {
+        Some(target) if target - rms <= peak_gain => (target - rms, false),
+        _ => (peak_gain, true)
+      }
+    };
+
+    HeadroomReport {
+      peak,
+      true_peak,
+      rms,
+      crest_factor: true_peak - rms,
+      gain,
+      peak_limited
+    }
+  }
+
+  /// Clears every level measured.
+  pub fn clear(&mut self) {
+    for sample in self.history.iter_mut() {
+      *sample = T::zero();
+    }
+    self.write_ptr = 0;
+    self.peak = T::zero();
+    self.true_peak = T::zero();
+    self.energy = T::zero();
+    self.length = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+
+  /// Returns a sine at a quarter of the sample rate, whose samples all fall
+  /// 3 dB below its peak.
+  fn quarter_rate_sine(amplitude: f32) -> Vec<f32> {
+    (0..1_000).map(|n| amplitude * (PI / 2f32 * n as f32 + PI / 4f32).sin()).collect()
+  }
+
+  #[test]
+  fn silence() {
+    let mut headroom = Headroom::new();
+    headroom.add(&[0f32; 64]);
+    let report = headroom.report();
+    assert_eq!(report.peak, -120f32);
+    assert_eq!(report.gain, 0f32);
+  }
+
+  #[test]
+  fn true_peak() {
+    let mut headroom = Headroom::new();
+    headroom.add(&quarter_rate_sine(0.5f32));
+    let report = headroom.report();
+    assert!((report.peak - -9.03f32).abs() < 0.05f32);
+    assert!((report.true_peak - -6.02f32).abs() < 0.2f32);
+    assert!((report.rms - -9.03f32).abs() < 0.05f32);
+    assert!((report.crest_factor - 3.01f32).abs() < 0.2f32);
+    assert!((report.gain - (-1f32 - report.true_peak)).abs() < 1e-4f32);
+    assert!(report.peak_limited);
+  }
+
+  #[test]
+  fn target_rms() {
+    let mut headroom = Headroom::new();
+    headroom.add(&quarter_rate_sine(0.1f32));
+
+    // Reachable without exceeding the headroom
+    headroom.set_target_rms(Some(-18f32));
+    let report = headroom.report();
+    assert!((report.rms + report.gain - -18f32).abs() < 1e-4f32);
+    assert!(!report.peak_limited);
+
+    // Too loud for the headroom, so the true peak is placed at the ceiling
+    headroom.set_headroom(3f32);
+    headroom.set_target_rms(Some(-3f32));
+    let report = headroom.report();
+    assert!((report.true_peak + report.gain - -3f32).abs() < 1e-4f32);
+    assert!(report.peak_limited);
+
+    headroom.set_target_rms(Some(::std::f32::NAN));
+    assert_eq!(headroom.get_target_rms(), Some(-3f32));
+    headroom.set_headroom(-1f32);
+    assert_eq!(headroom.get_headroom(), 3f32);
+  }
+
+  #[test]
+  fn clear() {
+    let mut headroom = Headroom::new();
+    headroom.add(&quarter_rate_sine(0.5f32));
+    headroom.clear();
+    assert_eq!(headroom.report().true_peak, -120f32);
+  }
+}
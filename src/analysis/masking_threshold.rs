@@ -0,0 +1,313 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use traits::FloatConst;
+use util::scales::frequency_to_bark;
+use window::{apply_window, Window};
+
+/// The power, relative to full scale, below which bins are treated as
+/// silent.
+const FLOOR: f64 = 1e-20f64;
+
+/// Returns the absolute threshold of hearing at `frequency`, in Hz, in dB
+/// SPL, by Terhardt's approximation.
+fn absolute_threshold(frequency: f64) -> f64 {
+  let khz = frequency.max(20f64) / 1_000f64;
+  3.64f64 * khz.powf(-0.8f64) - 6.5f64 * (-0.6f64 * (khz - 3.3f64).powi(2)).exp() + 1e-3f64 * khz.powi(4)
+}
+
+/// Returns the spread of masking, in dB, from a masker onto a band `dz` Bark
+/// above it, by Schroeder's spreading function.
+fn spreading(dz: f64) -> f64 {
+  15.81f64 + 7.5f64 * (dz + 0.474f64) - 17.5f64 * (1f64 + (dz + 0.474f64).powi(2)).sqrt()
+}
+
+/// Estimates the masking threshold of each frame of a signal.
+///
+/// This is a simplified form of Johnston's perceptual model. Each frame is
+/// Hann windowed and its power spectrum grouped into critical bands, one Bark
+/// wide. The energy of every band is spread onto its neighbors, falling 25
+/// dB per Bark below the masker and 10 dB per Bark above it. The spread
+/// energy is lowered by an offset that depends on how tonal each band is, as
+/// measured by its spectral flatness, since tones mask less than noise:
+/// 14.5 dB plus the band number for a tone, and 5.5 dB for noise. The
+/// threshold of each band is divided evenly between its bins, and is never
+/// below the absolute threshold of hearing.
+///
+/// Thresholds are powers, in dB relative to a bin holding a full-scale sine.
+/// The absolute threshold depends on the playback level, set as the sound
+/// pressure level of a full-scale sine, which defaults to 96 dB SPL, so the
+/// quietest 16-bit signal is at the threshold of hearing.
+///
+/// Signal below the threshold is inaudible, which a perceptual meter can
+/// report, or a noise gate or bit reduction can exploit.
+pub struct MaskingThreshold<T> {
+  frame_size: usize,
+  reference: T,
+  // The critical band of each bin, and the number of bins in each band
+  bands: Vec<usize>,
+  band_sizes: Vec<usize>,
+  // The absolute threshold of each bin, in dB SPL
+  absolute: Vec<f64>,
+  spectrum: Vec<T>,
+  threshold: Vec<T>,
+  // Samples not yet analyzed
+  buffer: Vec<T>
+}
+
+impl<T> MaskingThreshold<T> where T: Float + FloatConst {
+  /// Creates a new `MaskingThreshold` for frames of `frame_size` samples at
+  /// `sample_rate`.
+  ///
+  /// `frame_size` must be a power of two. The threshold is at the absolute
+  /// threshold of hearing until a frame is analyzed.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::MaskingThreshold;
+  ///
+  /// let frame: Vec<f32> = (0..1_024)
+  ///   .map(|n| (2f32 * ::std::f32::consts::PI * 1_000f32 * n as f32 / 44_100f32).sin())
+  ///   .collect();
+  ///
+  /// let mut masking = MaskingThreshold::new(44_100f32, 1_024);
+  /// let threshold = masking.analyze(&frame).to_vec();
+  /// assert_eq!(threshold.len(), 513);
+  ///
+  /// // A partial 30 dB below the tone, next to it, is masked
+  /// let bin = (1_100f32 * 1_024f32 / 44_100f32).round() as usize;
+  /// assert!(threshold[bin] > -30f32);
+  /// ```
+  pub fn new(sample_rate: T, frame_size: usize) -> Self {
+    debug_assert!(frame_size.is_power_of_two() && frame_size > 1);
+    let sample_rate: f64 = num::cast(sample_rate).unwrap();
+    let bins = frame_size / 2 + 1;
+    let frequencies: Vec<f64> = (0..bins).map(|k| k as f64 * sample_rate / frame_size as f64).collect();
+    let bands: Vec<usize> = frequencies.iter().map(|f| frequency_to_bark(*f).max(0f64).floor() as usize).collect();
+    let mut band_sizes = vec![0; bands[bins - 1] + 1];
+    for band in bands.iter() {
+      band_sizes[*band] += 1;
+    }
+
+    let mut masking = MaskingThreshold {
+      frame_size,
+      reference: num::cast(96f64).unwrap(),
+      bands,
+      band_sizes,
+      absolute: frequencies.iter().map(|f| absolute_threshold(*f)).collect(),
+      spectrum: vec![num::zero(); bins],
+      threshold: vec![num::zero(); bins],
+      buffer: Vec::new()
+    };
+    masking.clear();
+    masking
+  }
+
+  /// Sets the sound pressure level of a full-scale sine, in dB SPL.
+  pub fn set_reference(&mut self, reference: T) {
+    self.reference = reference;
+  }
+
+  /// Returns the sound pressure level of a full-scale sine, in dB SPL.
+  pub fn get_reference(&self) -> T {
+    self.reference
+  }
+
+  /// Analyzes one `frame`, of the frame size, returning the masking
+  /// threshold of each bin, from DC to Nyquist, in dB.
+  pub fn analyze(&mut self, frame: &[T]) -> &[T] {
+    debug_assert!(frame.len() == self.frame_size);
+    let mut windowed = frame.to_vec();
+    apply_window(&mut windowed, Window::Hann);
+    let mut buffer: Vec<Complex<T>> = windowed.iter().map(|sample| Complex::new(*sample, T::zero())).collect();
+    fft::forward(&mut buffer);
+
+    // The peak bin of a full-scale sine is 0 dB through the Hann window
+    let scale = (self.frame_size as f64 / 4f64).powi(2);
+    let power: Vec<f64> = buffer[..self.spectrum.len()].iter()
+      .map(|value| {
+        let norm: f64 = num::cast(value.norm_sqr()).unwrap();
+        (norm / scale).max(FLOOR)
+      })
+      .collect();
+    for (level, power) in self.spectrum.iter_mut().zip(power.iter()) {
+      *level = num::cast(10f64 * power.log10()).unwrap();
+    }
+
+    let mut energy = vec![0f64; self.band_sizes.len()];
+    for (band, power) in self.bands.iter().zip(power.iter()) {
+      energy[*band] += *power;
+    }
+
+    // The spectral flatness of each band, from 0 dB for noise, is taken as
+    // fully tonal at -60 dB
+    let mut log_sum = vec![0f64; self.band_sizes.len()];
+    for (band, power) in self.bands.iter().zip(power.iter()) {
+      log_sum[*band] += power.ln();
+    }
+    let tonality: Vec<f64> = (0..energy.len())
+      .map(|band| {
+        let size = self.band_sizes[band].max(1) as f64;
+        let flatness = 10f64 * ((log_sum[band] / size).exp() / (energy[band] / size)).log10();
+        (flatness / -60f64).clamp(0f64, 1f64)
+      })
+      .collect();
+
+    let reference: f64 = num::cast(self.reference).unwrap();
+    let thresholds: Vec<f64> = (0..energy.len())
+      .map(|band| {
+        let spread: f64 = energy.iter().enumerate()
+          .map(|(masker, e)| e * 10f64.powf(spreading(band as f64 - masker as f64) / 10f64))
+          .sum();
+        let offset = tonality[band] * (14.5f64 + band as f64) + (1f64 - tonality[band]) * 5.5f64;
+        10f64 * (spread / self.band_sizes[band].max(1) as f64).max(FLOOR).log10() - offset
+      })
+      .collect();
+    for ((threshold, band), absolute) in self.threshold.iter_mut().zip(self.bands.iter()).zip(self.absolute.iter()) {
+      *threshold = num::cast(thresholds[*band].max(absolute - reference)).unwrap();
+    }
+    &self.threshold
+  }
+
+  /// Adds `samples` to the analysis, analyzing every complete frame,
+  /// overlapped by half, so `threshold()` follows the latest frame.
+  pub fn add(&mut self, samples: &[T]) {
+    self.buffer.extend_from_slice(samples);
+    let hop = self.frame_size / 2;
+    while self.buffer.len() >= self.frame_size {
+      let frame = self.buffer[..self.frame_size].to_vec();
+      self.analyze(&frame);
+      self.buffer.drain(..hop);
+    }
+  }
+
+  /// Returns the masking threshold of each bin of the latest frame, in dB.
+  pub fn threshold(&self) -> &[T] {
+    &self.threshold
+  }
+
+  /// Returns the power of each bin of the latest frame, in dB on the same
+  /// scale as the threshold.
+  pub fn spectrum(&self) -> &[T] {
+    &self.spectrum
+  }
+
+  /// Returns the critical band, in Bark, that `bin` is grouped into.
+  pub fn band(&self, bin: usize) -> usize {
+    self.bands[bin]
+  }
+
+  /// Clears the analysis, returning the threshold to the absolute threshold
+  /// of hearing.
+  pub fn clear(&mut self) {
+    let reference: f64 = num::cast(self.reference).unwrap();
+    let floor: T = num::cast(10f64 * FLOOR.log10()).unwrap();
+    for ((threshold, level), absolute) in self.threshold.iter_mut().zip(self.spectrum.iter_mut()).zip(self.absolute.iter()) {
+      *threshold = num::cast(absolute - reference).unwrap();
+      *level = floor;
+    }
+    self.buffer.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use generator::Calibration;
+  use traits::Generator;
+
+  fn sine(frequency: f64, amplitude: f64) -> Vec<f64> {
+    (0..2_048)
+      .map(|n| amplitude * (2f64 * ::std::f64::consts::PI * frequency * n as f64 / 48_000f64).sin())
+      .collect()
+  }
+
+  fn bin(frequency: f64) -> usize {
+    (frequency * 2_048f64 / 48_000f64).round() as usize
+  }
+
+  // A frequency centered on a bin, near 1 kHz
+  const TONE: f64 = 43f64 * 48_000f64 / 2_048f64;
+
+  #[test]
+  fn scales() {
+    assert!((absolute_threshold(1_000f64) - 3.37f64).abs() < 0.01f64);
+    assert!(absolute_threshold(50f64) > 35f64 && absolute_threshold(3_300f64) < -4f64);
+    assert!(spreading(0f64).abs() < 0.1f64);
+    assert!(spreading(3f64) > spreading(-3f64));
+  }
+
+  #[test]
+  fn silence() {
+    let mut masking = MaskingThreshold::new(48_000f64, 2_048);
+    let absolute = masking.threshold().to_vec();
+    assert!((absolute[bin(1_000f64)] - (3.37f64 - 96f64)).abs() < 0.1f64);
+    assert_eq!(masking.analyze(&vec![0f64; 2_048]).to_vec(), absolute);
+
+    // The absolute threshold follows the playback level
+    masking.set_reference(76f64);
+    assert_eq!(masking.get_reference(), 76f64);
+    masking.clear();
+    assert!((masking.threshold()[bin(1_000f64)] - absolute[bin(1_000f64)] - 20f64).abs() < 1e-9f64);
+  }
+
+  #[test]
+  fn tone() {
+    let mut masking = MaskingThreshold::new(48_000f64, 2_048);
+    let threshold = masking.analyze(&sine(TONE, 0.5f64)).to_vec();
+    let spectrum = masking.spectrum().to_vec();
+    let peak = bin(TONE);
+    assert!((spectrum[peak] + 6.02f64).abs() < 0.1f64);
+
+    // A tone masks more above its frequency than below, and is well above
+    // its own threshold
+    assert!(threshold[peak] < spectrum[peak] - 20f64);
+    assert!(threshold[peak] > -60f64);
+    let band = masking.band(peak);
+    let below = (0..peak).rev().find(|k| masking.band(*k) == band - 3).unwrap();
+    let above = (peak..1_025).find(|k| masking.band(*k) == band + 3).unwrap();
+    assert!(threshold[above] > threshold[below] + 20f64);
+
+    // Far from the tone, only the threshold of hearing is left
+    assert_eq!(threshold[bin(12_000f64)], masking.threshold()[bin(12_000f64)]);
+    assert!((threshold[bin(12_000f64)] - (absolute_threshold(12_000f64) - 96f64)).abs() < 1e-9f64);
+  }
+
+  #[test]
+  fn noise_masks_more_than_tones() {
+    // Noise has a threshold closer to its level
+    let mut masking = MaskingThreshold::new(48_000f64, 2_048);
+    let mut pink = Calibration::pink_noise(48_000f64, -20f64);
+    let mut noise = vec![0f64; 2_048];
+    pink.generate_block(&mut noise);
+    let peak = bin(TONE);
+    let band = masking.band(peak);
+    let level = |masking: &MaskingThreshold<f64>| {
+      let power: f64 = (0..1_025)
+        .filter(|k| masking.band(*k) == band)
+        .map(|k| 10f64.powf(masking.spectrum()[k] / 10f64))
+        .sum();
+      10f64 * power.log10()
+    };
+
+    masking.analyze(&noise);
+    let noise_margin = level(&masking) - masking.threshold()[peak];
+    masking.analyze(&sine(TONE, 0.5f64));
+    let tone_margin = level(&masking) - masking.threshold()[peak];
+    assert!(tone_margin > noise_margin + 10f64);
+  }
+
+  #[test]
+  fn streaming() {
+    let mut masking = MaskingThreshold::new(48_000f64, 2_048);
+    let signal = sine(TONE, 0.5f64);
+    masking.add(&signal[..1_000]);
+    assert!(masking.threshold()[bin(1_000f64)] < -90f64);
+    masking.add(&signal[1_000..]);
+    let streamed = masking.threshold().to_vec();
+    assert_eq!(streamed, masking.analyze(&signal).to_vec());
+  }
+}
@@ -0,0 +1,310 @@
+use num;
+use num::traits::Float;
+
+use util::scales::{frequency_to_mel, mel_to_frequency};
+
+/// The mel scale and band normalization of a `MelFilterbank`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MelNormalization {
+  /// The logarithmic mel scale of HTK, with every band peaking at one.
+  Htk,
+  /// The mel scale of Slaney's Auditory Toolbox, linear below 1 kHz, with
+  /// every band of equal area, so each measures the power density of its
+  /// band rather than its total power.
+  Slaney
+}
+
+/// A bank of triangular filters mapping a power spectrum onto the mel scale.
+///
+/// The bands are spaced evenly in mels between the lowest and highest
+/// frequencies, and each is a triangle over the bins of the spectrum, rising
+/// linearly from the center of the band below to its own center and falling
+/// to the center of the band above. Neighboring bands overlap by half, so
+/// with `Htk` normalization the weights of every bin between the first and
+/// last centers add to one.
+///
+/// The power spectrum holds the `fft_size / 2 + 1` bins from DC to Nyquist,
+/// as the squared magnitudes of a real FFT, and the log of the band energies
+/// is the usual input of a DCT computing mel frequency cepstral
+/// coefficients.
+pub struct MelFilterbank<T> {
+  sample_rate: T,
+  fft_size: usize,
+  bands: usize,
+  min_frequency: T,
+  max_frequency: T,
+  normalization: MelNormalization,
+  frequencies: Vec<T>,
+  // The first bin of each band, and its weights from that bin on
+  filters: Vec<(usize, Vec<T>)>,
+  energies: Vec<T>
+}
+
+impl<T> MelFilterbank<T> where T: Float {
+  /// Creates a new `MelFilterbank` of `bands` bands, from DC to Nyquist, for
+  /// the power spectra of `fft_size` samples at `sample_rate`, with `Htk`
+  /// normalization.
+  ///
+  /// `fft_size` and `bands` must be at least one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::{MelFilterbank, MelNormalization};
+  ///
+  /// let mut filterbank = MelFilterbank::new(16_000f32, 512, 40);
+  /// filterbank.set_range(20f32, 8_000f32);
+  /// filterbank.set_normalization(MelNormalization::Slaney);
+  /// assert_eq!(filterbank.bins(), 257);
+  ///
+  /// let power = vec![1f32; 257];
+  /// let energies = filterbank.apply(&power);
+  /// assert_eq!(energies.len(), 40);
+  /// ```
+  pub fn new(sample_rate: T, fft_size: usize, bands: usize) -> Self {
+    debug_assert!(fft_size > 0 && bands > 0);
+    let mut filterbank = MelFilterbank {
+      sample_rate,
+      fft_size,
+      bands,
+      min_frequency: T::zero(),
+      max_frequency: sample_rate / (T::one() + T::one()),
+      normalization: MelNormalization::Htk,
+      frequencies: Vec::with_capacity(bands),
+      filters: Vec::with_capacity(bands),
+      energies: vec![num::zero(); bands]
+    };
+    filterbank.design();
+    filterbank
+  }
+
+  /// Returns the number of bands.
+  pub fn bands(&self) -> usize {
+    self.bands
+  }
+
+  /// Returns the number of bins of the power spectra.
+  pub fn bins(&self) -> usize {
+    self.fft_size / 2 + 1
+  }
+
+  /// Sets the lower edge of the lowest band, and the upper edge of the
+  /// highest band, in Hz.
+  ///
+  /// `min_frequency` must not be negative, and must be below
+  /// `max_frequency`, which must not be above Nyquist, else they are not
+  /// updated.
+  pub fn set_range(&mut self, min_frequency: T, max_frequency: T) {
+    if min_frequency >= T::zero()
+      && min_frequency < max_frequency
+      && max_frequency <= self.sample_rate / (T::one() + T::one()) {
+      self.min_frequency = min_frequency;
+      self.max_frequency = max_frequency;
+      self.design();
+    }
+  }
+
+  /// Returns the lower edge of the lowest band, and the upper edge of the
+  /// highest band, in Hz.
+  pub fn get_range(&self) -> (T, T) {
+    (self.min_frequency, self.max_frequency)
+  }
+
+  /// Sets the mel scale and band normalization.
+  pub fn set_normalization(&mut self, normalization: MelNormalization) {
+    self.normalization = normalization;
+    self.design();
+  }
+
+  /// Returns the mel scale and band normalization.
+  pub fn get_normalization(&self) -> MelNormalization {
+    self.normalization
+  }
+
+  /// Returns the center frequency of each band, in Hz, from lowest to
+  /// highest.
+  pub fn get_frequencies(&self) -> &[T] {
+    &self.frequencies
+  }
+
+  /// Returns the weight of a `bin` in a `band`.
+  pub fn weight(&self, band: usize, bin: usize) -> T {
+    let (first, ref weights) = self.filters[band];
+    if bin >= first && bin < first + weights.len() {
+      weights[bin - first]
+    }
+    else {
+      T::zero()
+    }
+  }
+
+  /// Returns the energy of each band in a `power` spectrum, from lowest to
+  /// highest.
+  ///
+  /// `power` must hold `bins()` bins.
+  pub fn apply(&mut self, power: &[T]) -> &[T] {
+    debug_assert_eq!(power.len(), self.bins());
+    for (energy, &(first, ref weights)) in self.energies.iter_mut().zip(self.filters.iter()) {
+      *energy = weights.iter()
+        .zip(power[first..].iter())
+        .fold(T::zero(), |sum, (weight, power)| sum + *weight * *power);
+    }
+    &self.energies
+  }
+
+  /// Returns the band energies computed by the last call to `apply`.
+  pub fn energies(&self) -> &[T] {
+    &self.energies
+  }
+
+  fn design(&mut self) {
+    let normalization = self.normalization;
+    let to_mel = |frequency: T| match normalization {
+      MelNormalization::Htk => frequency_to_mel(num::cast(frequency).unwrap()),
+      MelNormalization::Slaney => frequency_to_slaney_mel(num::cast(frequency).unwrap())
+    };
+    let to_frequency = |mel: f64| match normalization {
+      MelNormalization::Htk => mel_to_frequency(mel),
+      MelNormalization::Slaney => slaney_mel_to_frequency(mel)
+    };
+    let low: f64 = to_mel(self.min_frequency);
+    let high: f64 = to_mel(self.max_frequency);
+
+    // The band edges, with the centers of every band in between
+    let edges: Vec<f64> = (0..self.bands + 2)
+      .map(|n| to_frequency(low + (high - low) * n as f64 / (self.bands + 1) as f64))
+      .collect();
+    let bin_width = num::cast::<T, f64>(self.sample_rate).unwrap() / self.fft_size as f64;
+
+    self.frequencies.clear();
+    self.filters.clear();
+    for edge in edges.windows(3) {
+      let (lower, center, upper) = (edge[0], edge[1], edge[2]);
+      let gain = match self.normalization {
+        MelNormalization::Htk => 1f64,
+        MelNormalization::Slaney => 2f64 / (upper - lower)
+      };
+      let first = (lower / bin_width).ceil() as usize;
+      let last = ((upper / bin_width).floor() as usize).min(self.fft_size / 2);
+      let weights = (first..last + 1)
+        .map(|bin| {
+          let frequency = bin as f64 * bin_width;
+          let weight =
+            if frequency <= center {
+              (frequency - lower) / (center - lower)
+            }
+            else {
+              (upper - frequency) / (upper - center)
+            };
+          num::cast(weight.max(0f64) * gain).unwrap()
+        })
+        .collect();
+      self.frequencies.push(num::cast(center).unwrap());
+      self.filters.push((first, weights));
+    }
+  }
+}
+
+/// The frequency, in Hz, above which the Slaney mel scale is logarithmic.
+const SLANEY_CORNER: f64 = 1_000f64;
+
+/// The width, in Hz, of a mel below the corner of the Slaney mel scale.
+const SLANEY_STEP: f64 = 200f64 / 3f64;
+
+/// Converts a frequency, in Hz, to mels on the Slaney scale.
+fn frequency_to_slaney_mel(frequency: f64) -> f64 {
+  if frequency < SLANEY_CORNER {
+    frequency / SLANEY_STEP
+  }
+  else {
+    SLANEY_CORNER / SLANEY_STEP + (frequency / SLANEY_CORNER).ln() * 27f64 / 6.4f64.ln()
+  }
+}
+
+/// Converts mels on the Slaney scale to a frequency, in Hz.
+fn slaney_mel_to_frequency(mel: f64) -> f64 {
+  let corner = SLANEY_CORNER / SLANEY_STEP;
+  if mel < corner {
+    mel * SLANEY_STEP
+  }
+  else {
+    SLANEY_CORNER * ((mel - corner) * 6.4f64.ln() / 27f64).exp()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn htk() {
+    let mut filterbank = MelFilterbank::new(16_000f64, 1_024, 24);
+    assert_eq!((filterbank.bands(), filterbank.bins()), (24, 513));
+    assert_eq!(filterbank.get_range(), (0f64, 8_000f64));
+
+    // Centers are evenly spaced in mels
+    let mels: Vec<f64> = filterbank.get_frequencies().iter().map(|f| frequency_to_mel(*f)).collect();
+    let step = mels[1] - mels[0];
+    assert!(mels.windows(2).all(|pair| (pair[1] - pair[0] - step).abs() < 1e-9f64));
+
+    // The overlapping triangles add to one between the first and last centers
+    let centers = filterbank.get_frequencies().to_vec();
+    for bin in 0..filterbank.bins() {
+      let frequency = bin as f64 * 16_000f64 / 1_024f64;
+      if frequency >= centers[0] && frequency <= centers[23] {
+        let sum: f64 = (0..24).map(|band| filterbank.weight(band, bin)).sum();
+        assert!((sum - 1f64).abs() < 1e-9f64);
+      }
+    }
+
+    // A single bin only reaches the two bands around it
+    let mut power = vec![0f64; 513];
+    power[64] = 1f64;
+    let energies = filterbank.apply(&power).to_vec();
+    assert_eq!(energies.iter().filter(|energy| **energy > 0f64).count(), 2);
+    assert_eq!(filterbank.energies(), &energies[..]);
+  }
+
+  #[test]
+  fn slaney() {
+    let mut filterbank = MelFilterbank::new(22_050f64, 2_048, 40);
+    filterbank.set_normalization(MelNormalization::Slaney);
+    filterbank.set_range(0f64, 11_025f64);
+    assert_eq!(filterbank.get_normalization(), MelNormalization::Slaney);
+
+    // Linear spacing below 1 kHz, of 200 / 3 Hz per mel
+    let centers = filterbank.get_frequencies();
+    let step = centers[1] - centers[0];
+    assert!((centers[2] - centers[1] - step).abs() < 1e-9f64);
+    assert!(centers[30] - centers[29] > 2f64 * step);
+
+    // Every band has an area of one, so a flat power density measures one
+    let bin_width = 22_050f64 / 2_048f64;
+    let energies = filterbank.apply(&vec![bin_width; 1_025]).to_vec();
+    assert!(energies[5..].iter().all(|energy| (energy - 1f64).abs() < 0.05f64));
+
+    for mel in [0f64, 10f64, 15f64, 40f64].iter() {
+      assert!((frequency_to_slaney_mel(slaney_mel_to_frequency(*mel)) - mel).abs() < 1e-9f64);
+    }
+    assert!((slaney_mel_to_frequency(15f64) - 1_000f64).abs() < 1e-9f64);
+  }
+
+  #[test]
+  fn range() {
+    let mut filterbank = MelFilterbank::new(48_000f64, 2_048, 32);
+    filterbank.set_range(300f64, 4_000f64);
+    let (first, last) = (filterbank.get_frequencies()[0], filterbank.get_frequencies()[31]);
+    assert!(first > 300f64 && last < 4_000f64);
+    for bin in 0..filterbank.bins() {
+      let frequency = bin as f64 * 48_000f64 / 2_048f64;
+      if !(300f64..=4_000f64).contains(&frequency) {
+        assert!((0..32).all(|band| filterbank.weight(band, bin) == 0f64));
+      }
+    }
+
+    // Invalid ranges are not updated
+    filterbank.set_range(4_000f64, 300f64);
+    filterbank.set_range(0f64, 30_000f64);
+    assert_eq!(filterbank.get_range(), (300f64, 4_000f64));
+  }
+}
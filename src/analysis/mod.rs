@@ -1,11 +1,102 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use traits::FloatConst;
+use window::{apply_window, Window};
+
+mod am_detector;
+mod breakpoints;
+mod feedback_detector;
+mod goniometer;
+mod headroom;
 mod leaky_integrator;
+mod masking_threshold;
+mod mel_filterbank;
+mod mono_compatibility;
+mod octave_filter_bank;
 mod peak_detector;
+mod pitch_detector;
+mod polarity_checker;
 mod rms_detector;
+mod room_modes;
+mod spectrum_match;
+mod spl_meter;
+
+pub use self::am_detector::AmDetector               as AmDetector;
+pub use self::breakpoints::{breakpoints_to_envelope, envelope_to_breakpoints};
+pub use self::feedback_detector::FeedbackDetector   as FeedbackDetector;
+pub use self::goniometer::Goniometer                as Goniometer;
+pub use self::headroom::Headroom                    as Headroom;
+pub use self::headroom::HeadroomReport              as HeadroomReport;
+pub use self::leaky_integrator::LeakyIntegrator     as LeakyIntegrator;
+pub use self::masking_threshold::MaskingThreshold   as MaskingThreshold;
+pub use self::mel_filterbank::MelFilterbank         as MelFilterbank;
+pub use self::mel_filterbank::MelNormalization      as MelNormalization;
+pub use self::mono_compatibility::MonoCompatibility as MonoCompatibility;
+pub use self::octave_filter_bank::OctaveFilterBank  as OctaveFilterBank;
+pub use self::peak_detector::PeakEnvDetector        as PeakEnvDetector;
+pub use self::pitch_detector::PitchDetector         as PitchDetector;
+pub use self::polarity_checker::PolarityChecker     as PolarityChecker;
+pub use self::polarity_checker::PolarityReport      as PolarityReport;
+pub use self::rms_detector::RmsEnvDetector          as RmsEnvDetector;
+pub use self::room_modes::RoomMode                  as RoomMode;
+pub use self::room_modes::RoomModes                 as RoomModes;
+pub use self::spectrum_match::SpectrumMatch         as SpectrumMatch;
+pub use self::spl_meter::FrequencyWeighting         as FrequencyWeighting;
+pub use self::spl_meter::SplMeter                   as SplMeter;
+pub use self::spl_meter::TimeWeighting              as TimeWeighting;
+
+/// The long-term average power spectrum of a signal.
+struct AverageSpectrum<T> {
+  // Samples not yet analyzed
+  buffer: Vec<T>,
+  power: Vec<T>,
+  frames: usize
+}
+
+impl<T> AverageSpectrum<T> where T: Float + FloatConst {
+  fn new(bins: usize) -> Self {
+    AverageSpectrum {
+      buffer: Vec::new(),
+      power: vec![num::zero(); bins],
+      frames: 0
+    }
+  }
+
+  /// Analyzes every complete frame, overlapped by half, in the buffer.
+  fn add(&mut self, samples: &[T], frame_size: usize) {
+    self.buffer.extend_from_slice(samples);
+    let hop = (frame_size / 2).max(1);
+    while self.buffer.len() >= frame_size {
+      let mut frame = self.buffer[..frame_size].to_vec();
+      apply_window(&mut frame, Window::Hann);
+      for (total, power) in self.power.iter_mut().zip(power_spectrum(&frame)) {
+        *total = *total + power;
+      }
+      self.frames += 1;
+      self.buffer.drain(..hop);
+    }
+  }
 
-pub use self::leaky_integrator::LeakyIntegrator as LeakyIntegrator;
-pub use self::peak_detector::PeakEnvDetector    as PeakEnvDetector;
-pub use self::rms_detector::RmsEnvDetector      as RmsEnvDetector;
+  fn clear(&mut self) {
+    self.buffer.clear();
+    for power in self.power.iter_mut() {
+      *power = num::zero();
+    }
+    self.frames = 0;
+  }
+}
 
+/// Returns the power of each bin, from DC to Nyquist, of the DFT of `frame`.
+///
+/// The length of `frame` must be a power of two.
+fn power_spectrum<T: Float + FloatConst>(frame: &[T]) -> Vec<T> {
+  let mut buffer: Vec<Complex<T>> = frame.iter().map(|sample| Complex::new(*sample, T::zero())).collect();
+  fft::forward(&mut buffer);
+  buffer.iter().take(frame.len() / 2 + 1).map(|value| value.norm_sqr()).collect()
+}
 
 /* Notes on envelope detection, also known as envelope following
 
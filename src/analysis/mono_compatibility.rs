@@ -0,0 +1,169 @@
+use num;
+use num::traits::Float;
+
+use super::AverageSpectrum;
+use traits::FloatConst;
+use util;
+
+/// Measures how much of a stereo signal cancels when folded to mono.
+///
+/// The mid, `(left + right) / 2`, and side, `(left - right) / 2`, signals are
+/// analyzed in Hann windowed frames, overlapped by half, and their power
+/// spectra are averaged over every frame. The loss of each bin is the power
+/// of the mid relative to the total power of the mid and side, in dB. Content
+/// that is identical in both channels loses nothing, uncorrelated content
+/// loses 3 dB, and content with opposite polarity cancels completely, which is
+/// reported as -120 dB.
+pub struct MonoCompatibility<T> {
+  frame_size: usize,
+  mid: AverageSpectrum<T>,
+  side: AverageSpectrum<T>
+}
+
+impl<T> MonoCompatibility<T> where T: Float + FloatConst {
+  /// Creates a new `MonoCompatibility` analyzing frames of `frame_size`
+  /// samples.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `frame_size` is not a power of two, at least two.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::MonoCompatibility;
+  ///
+  /// let left: Vec<f32> = (0..4_096).map(|n| (n as f32 * 0.1f32).sin()).collect();
+  /// let right: Vec<f32> = left.iter().map(|sample| -sample).collect();
+  ///
+  /// let mut meter = MonoCompatibility::new(256);
+  /// meter.add(&left, &right);
+  /// assert_eq!(meter.band_loss(44_100f32, 0f32, 22_050f32), -120f32);
+  /// ```
+  pub fn new(frame_size: usize) -> Self {
+    assert!(frame_size.is_power_of_two() && frame_size > 1);
+    let bins = frame_size / 2 + 1;
+    MonoCompatibility {
+      frame_size,
+      mid: AverageSpectrum::new(bins),
+      side: AverageSpectrum::new(bins)
+    }
+  }
+
+  /// Adds stereo samples to the analysis.
+  ///
+  /// `left` and `right` must be the same length.
+  pub fn add(&mut self, left: &[T], right: &[T]) {
+    debug_assert_eq!(left.len(), right.len());
+    let half: T = num::cast(0.5f64).unwrap();
+    let mid: Vec<T> = left.iter().zip(right.iter()).map(|(l, r)| (*l + *r) * half).collect();
+    let side: Vec<T> = left.iter().zip(right.iter()).map(|(l, r)| (*l - *r) * half).collect();
+    self.mid.add(&mid, self.frame_size);
+    self.side.add(&side, self.frame_size);
+  }
+
+  /// Returns the loss, in dB, for each bin from DC to Nyquist.
+  ///
+  /// Bins are spaced by the sample rate divided by the frame size. Bins
+  /// without any power lose nothing.
+  pub fn loss(&self) -> Vec<T> {
+    self.mid.power.iter().zip(self.side.power.iter())
+      .map(|(mid, side)| Self::ratio(*mid, *side))
+      .collect()
+  }
+
+  /// Returns the loss, in dB, of the bins from `low` up to `high` Hz.
+  ///
+  /// Bands narrower than the bin spacing may not contain any bins, and lose
+  /// nothing.
+  pub fn band_loss(&self, sample_rate: T, low: T, high: T) -> T {
+    let spacing = sample_rate / num::cast(self.frame_size).unwrap();
+    let (mid, side) = self.mid.power.iter().zip(self.side.power.iter()).enumerate()
+      .filter(|&(k, _)| {
+        let frequency = spacing * num::cast(k).unwrap();
+        frequency >= low && frequency < high
+      })
+      .fold((T::zero(), T::zero()), |(m, s), (_, (mid, side))| (m + *mid, s + *side));
+    Self::ratio(mid, side)
+  }
+
+  /// Returns the center frequency, in Hz, and loss, in dB, of each octave
+  /// band, centered on 1 kHz, from 31.25 Hz up to the last band below
+  /// Nyquist.
+  pub fn octave_bands(&self, sample_rate: T) -> Vec<(T, T)> {
+    let edge = T::two().sqrt();
+    let nyquist = sample_rate / T::two();
+    (-5..5)
+      .map(|octave| num::cast::<f64, T>(1_000f64 * 2f64.powi(octave)).unwrap())
+      .filter(|center| *center * edge <= nyquist)
+      .map(|center| (center, self.band_loss(sample_rate, center / edge, center * edge)))
+      .collect()
+  }
+
+  /// Clears the analysis.
+  pub fn clear(&mut self) {
+    self.mid.clear();
+    self.side.clear();
+  }
+
+  fn ratio(mid: T, side: T) -> T {
+    let total = mid + side;
+    if total > T::zero() { util::to_db((mid / total).sqrt()) } else { T::zero() }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns white noise from a linear congruential generator.
+  fn noise(length: usize, seed: u32) -> Vec<f32> {
+    let mut state = seed;
+    (0..length)
+      .map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (state >> 8) as f32 / (1u32 << 23) as f32 - 1f32
+      })
+      .collect()
+  }
+
+  #[test]
+  #[should_panic]
+  fn frame_size_not_power_of_two() {
+    MonoCompatibility::<f32>::new(96);
+  }
+
+  #[test]
+  fn correlation() {
+    let left = noise(4_096, 1);
+    let mut meter = MonoCompatibility::new(64);
+    assert!(meter.loss().iter().all(|loss| *loss == 0f32));
+
+    meter.add(&left, &left);
+    assert!(meter.loss().iter().all(|loss| loss.abs() < 1e-5f32));
+
+    // Uncorrelated channels lose 3 dB on average
+    meter.clear();
+    meter.add(&left, &noise(4_096, 2));
+    assert!((meter.band_loss(8_000f32, 0f32, 4_000f32) + 3.01f32).abs() < 0.3f32);
+  }
+
+  #[test]
+  fn delayed_channel() {
+    // A delay of 4 samples cancels at 1 kHz
+    let sample_rate = 8_000f32;
+    let left = noise(16_384, 1);
+    let right: Vec<f32> = (0..left.len()).map(|n| if n < 4 { 0f32 } else { left[n - 4] }).collect();
+    let mut meter = MonoCompatibility::new(256);
+    meter.add(&left, &right);
+
+    let loss = meter.loss();
+    assert!(loss[2].abs() < 0.5f32);
+    assert!(loss[32] < -20f32);
+    assert!(meter.band_loss(sample_rate, 900f32, 1_100f32) < -10f32);
+
+    let bands = meter.octave_bands(sample_rate);
+    assert_eq!(bands.len(), 7);
+    assert!(bands[0].0 == 31.25f32 && bands[6].0 == 2_000f32);
+  }
+}
@@ -0,0 +1,269 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{BiquadCoefficients, SosCascade};
+use traits::{Analyzer, FloatConst, FrequencyResponse, Processor};
+
+/// The order of the Butterworth prototype of each band.
+const ORDER: usize = 3;
+
+/// The lowest band edge, in Hz.
+const LOWEST: f64 = 20f64;
+
+/// The highest band edge, as a ratio of the sample rate, above which the
+/// bilinear transform warps the bands too far.
+const HIGHEST: f64 = 0.45f64;
+
+/// A bank of octave, or fractional octave, band filters measuring the level
+/// of each band.
+///
+/// The bands follow ANSI S1.11 and IEC 61260, with base ten center
+/// frequencies around 1 kHz, so the third octave bands are centered at the
+/// nominal 25, 31.5, 40 Hz, and so on. Each band is a sixth order
+/// Butterworth band-pass, designed from a third order prototype with its
+/// edges prewarped, so every band is 3 dB down at its edges and about 18 dB
+/// down at the centers of its neighbors, for third octaves. The bank holds
+/// every band whose lower edge is at least 20 Hz and whose upper edge is
+/// below 45% of the sample rate.
+///
+/// The power of every band is averaged with an exponential time constant,
+/// one second by default, and levels are in dB relative to a full scale
+/// square wave, so a full scale sine in the middle of a band reads -3 dB,
+/// and pink noise reads the same level in every band.
+pub struct OctaveFilterBank<T> {
+  sample_rate: T,
+  frequencies: Vec<T>,
+  filters: Vec<SosCascade<T>>,
+  power: Vec<T>,
+  levels: Vec<T>,
+  time_constant: T,
+  coefficient: T
+}
+
+impl<T> OctaveFilterBank<T> where T: Float + FloatConst {
+  /// Creates a new `OctaveFilterBank` of bands `1 / bands_per_octave`
+  /// octaves wide, for signals at `sample_rate`.
+  ///
+  /// `bands_per_octave` must be at least one; 1 and 3 give the standard
+  /// octave and third octave banks.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::OctaveFilterBank;
+  /// use rasp::traits::Analyzer;
+  ///
+  /// let mut bank = OctaveFilterBank::new(48_000f32, 3);
+  /// assert_eq!(bank.bands(), 29);
+  /// assert!((bank.get_frequencies()[0] - 25f32).abs() < 0.2f32);
+  ///
+  /// bank.process_block(&[0.5f32; 64]);
+  /// let levels = bank.levels();
+  /// assert_eq!(levels.len(), 29);
+  /// ```
+  pub fn new(sample_rate: T, bands_per_octave: usize) -> Self {
+    debug_assert!(bands_per_octave > 0);
+    let fs: f64 = num::cast(sample_rate).unwrap();
+    let ratio = 10f64.powf(0.3f64);
+    let b = bands_per_octave as f64;
+    let half_band = ratio.powf(1f64 / (2f64 * b));
+
+    // Centers are at integer steps of a band from 1 kHz for odd fractions,
+    // and halfway between for even ones
+    let offset = if bands_per_octave % 2 == 1 { 0f64 } else { 0.5f64 };
+    let center = |index: i32| 1_000f64 * ratio.powf((index as f64 + offset) / b);
+    let mut index = 0;
+    while center(index - 1) / half_band >= LOWEST {
+      index -= 1;
+    }
+
+    let mut frequencies = Vec::new();
+    let mut filters = Vec::new();
+    while center(index) * half_band < HIGHEST * fs {
+      let frequency = center(index);
+      frequencies.push(num::cast(frequency).unwrap());
+      filters.push(band_pass(fs, frequency / half_band, frequency * half_band));
+      index += 1;
+    }
+
+    let bands = frequencies.len();
+    let mut bank = OctaveFilterBank {
+      sample_rate,
+      frequencies,
+      filters,
+      power: vec![num::zero(); bands],
+      levels: vec![num::zero(); bands],
+      time_constant: num::zero(),
+      coefficient: num::zero()
+    };
+    bank.set_time_constant(T::one());
+    bank.clear();
+    bank
+  }
+
+  /// Returns the number of bands.
+  pub fn bands(&self) -> usize {
+    self.filters.len()
+  }
+
+  /// Returns the exact center frequency of each band, in Hz, from lowest to
+  /// highest.
+  pub fn get_frequencies(&self) -> &[T] {
+    &self.frequencies
+  }
+
+  /// Returns the filter of a `band`.
+  pub fn filter(&self, band: usize) -> &SosCascade<T> {
+    &self.filters[band]
+  }
+
+  /// Sets the time constant, in seconds, of the level averaging.
+  ///
+  /// `time_constant` must be positive, else it is not updated.
+  pub fn set_time_constant(&mut self, time_constant: T) {
+    if time_constant > T::zero() && time_constant.is_finite() {
+      self.time_constant = time_constant;
+      self.coefficient = T::one() - (-T::one() / (time_constant * self.sample_rate)).exp();
+    }
+  }
+
+  /// Returns the time constant, in seconds, of the level averaging.
+  pub fn get_time_constant(&self) -> T {
+    self.time_constant
+  }
+
+  /// Returns the level of each band, in dB, from lowest to highest.
+  pub fn levels(&mut self) -> &[T] {
+    let ten: T = num::cast(10f64).unwrap();
+    let floor: T = num::cast(-200f64).unwrap();
+    for (level, power) in self.levels.iter_mut().zip(self.power.iter()) {
+      *level =
+        if *power > T::zero() {
+          (ten * power.log10()).max(floor)
+        }
+        else {
+          floor
+        };
+    }
+    &self.levels
+  }
+}
+
+impl<T> Analyzer<T> for OctaveFilterBank<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) {
+    for (filter, power) in self.filters.iter_mut().zip(self.power.iter_mut()) {
+      let output = filter.process(sample);
+      *power = *power + (output * output - *power) * self.coefficient;
+    }
+  }
+
+  fn clear(&mut self) {
+    for (filter, power) in self.filters.iter_mut().zip(self.power.iter_mut()) {
+      filter.clear();
+      *power = T::zero();
+    }
+  }
+}
+
+/// Designs a Butterworth band-pass from `low` to `high`, in Hz, with unity
+/// gain at its center.
+fn band_pass<T: Float + FloatConst>(sample_rate: f64, low: f64, high: f64) -> SosCascade<T> {
+  let prewarp = |frequency: f64| 2f64 * sample_rate * (::std::f64::consts::PI * frequency / sample_rate).tan();
+  let (low, high) = (prewarp(low), prewarp(high));
+  let (bandwidth, center) = (high - low, (low * high).sqrt());
+
+  // Each prototype pole becomes a pair of band-pass poles, solving
+  // s^2 - p B s + w0^2 = 0, and the bilinear transform maps them to the
+  // z-plane, with the zeros at DC and infinity mapped to either end of the
+  // unit circle
+  let mut poles = Vec::new();
+  for k in 0..ORDER {
+    let angle = ::std::f64::consts::PI * (2 * k + ORDER + 1) as f64 / (2 * ORDER) as f64;
+    let p = Complex::from_polar(&1f64, &angle) * bandwidth;
+    let root = (p * p - 4f64 * center * center).sqrt();
+    for s in [(p + root) / 2f64, (p - root) / 2f64].iter() {
+      let s = *s / (2f64 * sample_rate);
+      poles.push((Complex::new(1f64, 0f64) + s) / (Complex::new(1f64, 0f64) - s));
+    }
+  }
+
+  let mut cascade = SosCascade::new();
+  for pole in poles.iter().filter(|pole| pole.im > 0f64) {
+    cascade.push_section(BiquadCoefficients {
+      b0: T::one(),
+      b1: T::zero(),
+      b2: -T::one(),
+      a1: num::cast(-2f64 * pole.re).unwrap(),
+      a2: num::cast(pole.norm_sqr()).unwrap()
+    });
+  }
+  let center: T = num::cast(sample_rate / ::std::f64::consts::PI * (center / (2f64 * sample_rate)).atan()).unwrap();
+  let gain = cascade.magnitude_at(center, num::cast(sample_rate).unwrap());
+  cascade.set_gain(T::one() / gain);
+  cascade
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use generator::Calibration;
+  use traits::{Generator, PoleZero};
+  use util;
+
+  #[test]
+  fn bands() {
+    let octaves = OctaveFilterBank::<f64>::new(48_000f64, 1);
+    let nominal = [31.5f64, 63f64, 125f64, 250f64, 500f64, 1_000f64, 2_000f64, 4_000f64, 8_000f64];
+    assert_eq!(octaves.bands(), nominal.len());
+    for (frequency, nominal) in octaves.get_frequencies().iter().zip(nominal.iter()) {
+      assert!((frequency / nominal - 1f64).abs() < 0.02f64);
+    }
+    assert_eq!(OctaveFilterBank::<f64>::new(96_000f64, 1).bands(), 10);
+
+    let thirds = OctaveFilterBank::<f64>::new(48_000f64, 3);
+    let centers = thirds.get_frequencies();
+    assert_eq!(centers[16], 1_000f64);
+    assert!(centers.windows(2).all(|pair| (pair[1] / pair[0] - 10f64.powf(0.1f64)).abs() < 1e-9f64));
+
+    // Unity gain at the center, -3 dB at the edges, and far down at the
+    // neighboring bands
+    let half_band = 10f64.powf(0.05f64);
+    for band in 0..thirds.bands() {
+      let (filter, center) = (thirds.filter(band), centers[band]);
+      assert!(filter.is_stable());
+      assert!(util::to_db(filter.magnitude_at(center, 48_000f64)).abs() < 0.1f64);
+      assert!((util::to_db(filter.magnitude_at(center * half_band, 48_000f64)) + 3f64).abs() < 0.1f64);
+      assert!((util::to_db(filter.magnitude_at(center / half_band, 48_000f64)) + 3f64).abs() < 0.1f64);
+      assert!(util::to_db(filter.magnitude_at(center / half_band.powi(2), 48_000f64)) < -15f64);
+    }
+  }
+
+  #[test]
+  fn levels() {
+    let mut bank = OctaveFilterBank::new(48_000f64, 3);
+    bank.set_time_constant(0.1f64);
+    assert_eq!(bank.get_time_constant(), 0.1f64);
+    let sine: Vec<f64> = (0..48_000)
+      .map(|n| (2f64 * ::std::f64::consts::PI * 1_000f64 * n as f64 / 48_000f64).sin())
+      .collect();
+    bank.process_block(&sine);
+    let levels = bank.levels().to_vec();
+    assert!((levels[16] + 3.01f64).abs() < 0.05f64);
+    assert!(levels[15] < -20f64 && levels[17] < -20f64);
+
+    // Pink noise has the same power in every octave
+    let mut bank = OctaveFilterBank::new(48_000f64, 1);
+    bank.set_time_constant(10f64);
+    let mut noise = vec![0f64; 480_000];
+    Calibration::pink_noise(48_000f64, -20f64).generate_block(&mut noise);
+    bank.process_block(&noise);
+    let levels = bank.levels().to_vec();
+    for level in levels[1..9].iter() {
+      assert!((level - levels[5]).abs() < 1.5f64);
+    }
+
+    bank.clear();
+    assert!(bank.levels().iter().all(|level| *level == -200f64));
+  }
+}
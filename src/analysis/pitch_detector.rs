@@ -0,0 +1,236 @@
+use num;
+use num::traits::Float;
+
+use traits::{Analyzer, FloatConst};
+
+/// The default threshold of the normalized difference below which a lag is
+/// taken as the period.
+const THRESHOLD: f64 = 0.15f64;
+
+/// A monophonic pitch detector, using the YIN algorithm.
+///
+/// The difference between the signal and itself delayed by each lag, over a
+/// window as long as the longest period, dips at the period and at each of
+/// its multiples. Normalizing each difference by its mean over the shorter
+/// lags removes the dip at zero lag, so the first lag whose normalized
+/// difference falls below the threshold is the period, refined between
+/// samples by parabolic interpolation. The analysis is repeated every
+/// quarter of the longest period, over the most recent samples.
+///
+/// When no lag falls below the threshold, such as for silence, noise, or
+/// chords, the signal is unvoiced and no frequency is detected.
+///
+/// [de Cheveigné and Kawahara, YIN, a fundamental frequency estimator for
+/// speech and music](http://audition.ens.fr/adc/pdf/2002_JASA_YIN.pdf)
+pub struct PitchDetector<T> {
+  sample_rate: T,
+  min_lag: usize,
+  max_lag: usize,
+  threshold: T,
+  // Samples not yet analyzed, with the window and the longest lag before
+  buffer: Vec<T>,
+  difference: Vec<T>,
+  frequency: Option<T>,
+  clarity: T
+}
+
+impl<T> PitchDetector<T> where T: Float + FloatConst {
+  /// Creates a new `PitchDetector` for signals at `sample_rate`, detecting
+  /// frequencies from `min_frequency` to `max_frequency`, in Hz.
+  ///
+  /// `min_frequency` must be positive and below `max_frequency`, which must
+  /// be below a quarter of the sample rate. Nothing is detected until the
+  /// first window has been analyzed, twice the longest period.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::PitchDetector;
+  /// use rasp::traits::Analyzer;
+  ///
+  /// let sample_rate = 16_000f32;
+  /// let mut detector = PitchDetector::new(sample_rate, 60f32, 1_000f32);
+  /// for n in 0..2_000 {
+  ///   let phase = 2f32 * ::std::f32::consts::PI * 220f32 * n as f32 / sample_rate;
+  ///   detector.process(phase.sin());
+  /// }
+  /// assert!((detector.frequency().unwrap() - 220f32).abs() < 0.5f32);
+  /// ```
+  pub fn new(sample_rate: T, min_frequency: T, max_frequency: T) -> Self {
+    let four: T = num::cast(4f64).unwrap();
+    debug_assert!(min_frequency > T::zero() && min_frequency < max_frequency && max_frequency < sample_rate / four);
+    let max_lag = num::cast::<T, usize>((sample_rate / min_frequency).ceil()).unwrap();
+    let min_lag = num::cast::<T, usize>((sample_rate / max_frequency).floor()).unwrap().max(2);
+    PitchDetector {
+      sample_rate,
+      min_lag,
+      max_lag,
+      threshold: num::cast(THRESHOLD).unwrap(),
+      buffer: Vec::with_capacity(2 * max_lag),
+      difference: vec![T::zero(); max_lag + 2],
+      frequency: None,
+      clarity: T::zero()
+    }
+  }
+
+  /// Sets the threshold of the normalized difference, from 0 to 1, below
+  /// which a lag is taken as the period.
+  ///
+  /// Lower thresholds only detect clearly periodic signals, and higher ones
+  /// also noisier signals, at the risk of octave errors. `threshold` must be
+  /// within 0 and 1, else it is not updated.
+  pub fn set_threshold(&mut self, threshold: T) {
+    if threshold > T::zero() && threshold < T::one() {
+      self.threshold = threshold;
+    }
+  }
+
+  /// Returns the threshold of the normalized difference.
+  pub fn get_threshold(&self) -> T {
+    self.threshold
+  }
+
+  /// Returns the frequency, in Hz, detected in the last analyzed window, or
+  /// `None` if it was unvoiced.
+  pub fn frequency(&self) -> Option<T> {
+    self.frequency
+  }
+
+  /// Returns how periodic the last analyzed window was, from 0 for noise or
+  /// silence to 1 for a perfectly periodic signal.
+  pub fn clarity(&self) -> T {
+    self.clarity
+  }
+
+  /// Returns the number of samples between each analysis.
+  pub fn hop(&self) -> usize {
+    (self.max_lag / 4).max(1)
+  }
+
+  /// Analyzes the window of the last samples.
+  fn analyze(&mut self) {
+    let window = self.max_lag;
+    let (frame, difference) = (&self.buffer, &mut self.difference);
+
+    // The cumulative mean normalized difference, up to one past the longest
+    // lag for the interpolation
+    let mut sum = T::zero();
+    difference[0] = T::one();
+    for lag in 1..self.max_lag + 2 {
+      let power = frame[..window.min(frame.len() - lag)].iter().zip(frame[lag..].iter())
+        .fold(T::zero(), |power, (a, b)| power + (*a - *b) * (*a - *b));
+      sum = sum + power;
+      difference[lag] =
+        if sum > T::zero() {
+          power * num::cast(lag).unwrap() / sum
+        }
+        else {
+          T::one()
+        };
+    }
+
+    // The first dip below the threshold, followed down to its minimum
+    let mut lag = self.min_lag;
+    while lag <= self.max_lag && difference[lag] >= self.threshold {
+      lag += 1;
+    }
+    if lag > self.max_lag {
+      self.frequency = None;
+      self.clarity = (T::one() - (self.min_lag..self.max_lag + 1).fold(T::one(), |min, lag| min.min(difference[lag]))).max(T::zero());
+      return;
+    }
+    while lag < self.max_lag && difference[lag + 1] < difference[lag] {
+      lag += 1;
+    }
+
+    let (a, b, c) = (difference[lag - 1], difference[lag], difference[lag + 1]);
+    let curvature = a - T::two() * b + c;
+    let offset =
+      if curvature > T::zero() {
+        ((a - c) / (T::two() * curvature)).max(-T::one() / T::two()).min(T::one() / T::two())
+      }
+      else {
+        T::zero()
+      };
+    let period = num::cast::<usize, T>(lag).unwrap() + offset;
+    self.frequency = Some(self.sample_rate / period);
+    self.clarity = (T::one() - b).max(T::zero());
+  }
+}
+
+impl<T> Analyzer<T> for PitchDetector<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) {
+    self.buffer.push(sample);
+    if self.buffer.len() == 2 * self.max_lag {
+      self.analyze();
+      let hop = self.hop();
+      self.buffer.drain(..hop);
+    }
+  }
+
+  fn clear(&mut self) {
+    self.buffer.clear();
+    self.frequency = None;
+    self.clarity = T::zero();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the frequency detected in `seconds` of `signal` at 16 kHz.
+  fn detect<F: Fn(f64) -> f64>(signal: F, seconds: f64) -> Option<f64> {
+    let mut detector = PitchDetector::new(16_000f64, 50f64, 1_500f64);
+    for n in 0..(seconds * 16_000f64) as usize {
+      detector.process(signal(n as f64 / 16_000f64));
+    }
+    detector.frequency()
+  }
+
+  #[test]
+  fn tones() {
+    let tau = 2f64 * ::std::f64::consts::PI;
+    for frequency in [55f64, 110f64, 233.1f64, 440f64, 1_234f64].iter() {
+      let detected = detect(|t| (tau * frequency * t).sin(), 0.2f64).unwrap();
+      assert!((detected / frequency - 1f64).abs() < 3e-3f64);
+    }
+
+    // A sawtooth, rich in harmonics, is detected at its fundamental rather
+    // than at a harmonic
+    let saw = |t: f64| 2f64 * (150f64 * t).fract() - 1f64;
+    assert!((detect(saw, 0.2f64).unwrap() / 150f64 - 1f64).abs() < 5e-3f64);
+
+    // As is a tone with a missing fundamental
+    let missing = |t: f64| (tau * 400f64 * t).sin() + (tau * 600f64 * t).sin() + (tau * 800f64 * t).sin();
+    assert!((detect(missing, 0.2f64).unwrap() / 200f64 - 1f64).abs() < 2e-3f64);
+  }
+
+  #[test]
+  fn unvoiced() {
+    assert_eq!(detect(|_| 0f64, 0.2f64), None);
+
+    // Uniform noise from a linear congruential generator
+    let mut detector = PitchDetector::new(16_000f64, 50f64, 1_500f64);
+    let mut state = 1u32;
+    for _ in 0..3_200 {
+      state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+      detector.process(state as f64 / ::std::u32::MAX as f64 - 0.5f64);
+    }
+    assert_eq!(detector.frequency(), None);
+    assert!(detector.clarity() < 0.5f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut detector = PitchDetector::new(44_100f32, 80f32, 800f32);
+    detector.set_threshold(0f32);
+    detector.set_threshold(1f32);
+    assert_eq!(detector.get_threshold(), 0.15f32);
+    assert_eq!(detector.hop(), 138);
+
+    detector.process_block(&vec![0.5f32; 2_000]);
+    detector.clear();
+    assert_eq!((detector.frequency(), detector.clarity()), (None, 0f32));
+  }
+}
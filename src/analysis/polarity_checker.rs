@@ -0,0 +1,149 @@
+use num;
+use num::traits::Float;
+
+use fft;
+use generator::Calibration;
+use traits::{FloatConst, Generator};
+
+/// The result of a `PolarityChecker`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PolarityReport<T> {
+  /// Whether the response is inverted in polarity from the stimulus.
+  pub inverted: bool,
+  /// The delay of the response behind the stimulus, in samples.
+  pub delay: usize,
+  /// The normalized correlation at the delay, from zero, for an unrelated
+  /// response, to one, for a delayed copy of the stimulus.
+  pub correlation: T
+}
+
+/// Checks the polarity and delay of a captured response to a stimulus.
+///
+/// The stimulus, such as pink noise played through a speaker, and the
+/// response captured by a microphone or at the end of a signal chain, are
+/// cross-correlated. The lag with the largest correlation, up to the maximum
+/// delay, is the broadband delay of the response, and the sign of the
+/// correlation there gives its polarity. A low correlation means the
+/// response is too noisy or filtered for the result to be trusted.
+pub struct PolarityChecker<T> {
+  max_delay: usize,
+  stimulus: Vec<T>,
+  response: Vec<T>
+}
+
+impl<T> PolarityChecker<T> where T: Float + FloatConst {
+  /// Creates a new `PolarityChecker` searching delays up to `max_delay`
+  /// samples.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::PolarityChecker;
+  ///
+  /// let stimulus = PolarityChecker::stimulus(48_000f32, 4_800);
+  /// let response: Vec<f32> = (0..4_800)
+  ///   .map(|n| if n < 100 { 0f32 } else { -0.5f32 * stimulus[n - 100] })
+  ///   .collect();
+  ///
+  /// let mut checker = PolarityChecker::new(480);
+  /// checker.add(&stimulus, &response);
+  /// let report = checker.report().unwrap();
+  /// assert!(report.inverted);
+  /// assert_eq!(report.delay, 100);
+  /// ```
+  pub fn new(max_delay: usize) -> Self {
+    PolarityChecker {
+      max_delay,
+      stimulus: Vec::new(),
+      response: Vec::new()
+    }
+  }
+
+  /// Returns `length` samples of pink noise at -20 dBFS, a stimulus with
+  /// equal energy in every octave.
+  pub fn stimulus(sample_rate: T, length: usize) -> Vec<T> {
+    let mut noise = Calibration::pink_noise(sample_rate, num::cast(-20f64).unwrap());
+    let mut samples = vec![T::zero(); length];
+    noise.generate_block(&mut samples);
+    samples
+  }
+
+  /// Adds `stimulus` and the captured `response` to the analysis.
+  ///
+  /// `stimulus` and `response` must be the same length.
+  pub fn add(&mut self, stimulus: &[T], response: &[T]) {
+    debug_assert_eq!(stimulus.len(), response.len());
+    self.stimulus.extend_from_slice(stimulus);
+    self.response.extend_from_slice(response);
+  }
+
+  /// Returns the polarity and delay of the response, or `None` if either
+  /// signal is silent.
+  pub fn report(&self) -> Option<PolarityReport<T>> {
+    let energy = |signal: &[T]| signal.iter().fold(T::zero(), |sum, sample| sum + *sample * *sample);
+    let scale = (energy(&self.stimulus) * energy(&self.response)).sqrt();
+    if scale <= T::zero() {
+      return None;
+    }
+
+    let correlation = fft::cross_correlation(&self.stimulus, &self.response);
+    correlation.iter()
+      .take(self.max_delay + 1)
+      .enumerate()
+      .fold(None, |best: Option<(usize, T)>, (lag, value)| {
+        match best {
+          Some((_, peak)) if peak.abs() >= value.abs() => best,
+          _ => Some((lag, *value))
+        }
+      })
+      .map(|(delay, peak)| PolarityReport {
+        inverted: peak < T::zero(),
+        delay,
+        correlation: peak.abs() / scale
+      })
+  }
+
+  /// Clears the analysis.
+  pub fn clear(&mut self) {
+    self.stimulus.clear();
+    self.response.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Biquad2;
+  use filter::rbj::HighPass;
+  use traits::Processor;
+
+  #[test]
+  fn polarity_and_delay() {
+    let stimulus = PolarityChecker::stimulus(48_000f64, 9_600);
+
+    // A speaker as a high-pass filter, 2 meters away, in polarity
+    let mut speaker = Biquad2::new();
+    speaker.load_coefficients(HighPass::coefficients(48_000f64, 80f64, 0.7f64));
+    let response: Vec<f64> = (0..stimulus.len())
+      .map(|n| speaker.process(if n < 280 { 0f64 } else { stimulus[n - 280] }))
+      .collect();
+
+    let mut checker = PolarityChecker::new(2_400);
+    checker.add(&stimulus[..4_800], &response[..4_800]);
+    checker.add(&stimulus[4_800..], &response[4_800..]);
+    let report = checker.report().unwrap();
+    assert!(!report.inverted);
+    assert_eq!(report.delay, 280);
+    assert!(report.correlation > 0.5f64);
+
+    // Delays beyond the search are not found
+    let mut checker = PolarityChecker::new(100);
+    checker.add(&stimulus, &response);
+    assert!(checker.report().unwrap().correlation < 0.5f64);
+
+    checker.clear();
+    assert_eq!(checker.report(), None);
+    checker.add(&stimulus, &vec![0f64; stimulus.len()]);
+    assert_eq!(checker.report(), None);
+  }
+}
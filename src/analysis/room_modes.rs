@@ -0,0 +1,267 @@
+use num;
+use num::traits::Float;
+
+use filter::BiquadCoefficients;
+use filter::rbj::Peak;
+use traits::FloatConst;
+use util;
+
+/// A narrow resonance found by `RoomModes`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RoomMode<T> {
+  /// The center frequency of the resonance, in Hz.
+  pub frequency: T,
+  /// The q factor of the resonance, measured between the frequencies where
+  /// the level is halfway, in dB, between the peak and its neighborhood.
+  pub q: T,
+  /// The level of the peak above its neighborhood, in dB.
+  pub depth: T
+}
+
+impl<T> RoomMode<T> where T: Float + FloatConst {
+  /// Returns coefficients of a peaking filter cutting the resonance by its
+  /// depth.
+  pub fn coefficients(&self, sample_rate: T) -> BiquadCoefficients<T> {
+    Peak::coefficients(sample_rate, self.frequency, -self.depth, self.q)
+  }
+}
+
+/// Finds narrow low frequency resonances in a measured response.
+///
+/// The magnitude response is sampled on a linear grid over the analyzed
+/// range, and each local maximum that rises above the median level within an
+/// octave either side of it by at least the threshold is reported as a mode.
+/// The bandwidth of a mode is measured where the level is halfway between the
+/// peak and its neighborhood, which is how the q factor of a peaking filter
+/// is defined, so the suggested notch cancels the resonance.
+///
+/// The response of an impulse is computed with a direct DFT at each grid
+/// frequency, so impulses should be kept to a few seconds.
+pub struct RoomModes<T> {
+  sample_rate: T,
+  min_frequency: T,
+  max_frequency: T,
+  resolution: T,
+  threshold: T
+}
+
+impl<T> RoomModes<T> where T: Float + FloatConst {
+  /// Creates a new `RoomModes` analyzing from 20 Hz to 300 Hz, on a grid of
+  /// 0.5 Hz, with a threshold of 6 dB.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::RoomModes;
+  ///
+  /// let impulse = vec![1f32];
+  /// let modes = RoomModes::new(44_100f32).analyze(&impulse);
+  /// assert!(modes.is_empty());
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    RoomModes {
+      sample_rate,
+      min_frequency: num::cast(20f64).unwrap(),
+      max_frequency: num::cast(300f64).unwrap(),
+      resolution: num::cast(0.5f64).unwrap(),
+      threshold: num::cast(6f64).unwrap()
+    }
+  }
+
+  /// Sets the range of frequencies analyzed, in Hz.
+  ///
+  /// `min_frequency` must be positive and less than `max_frequency`, which
+  /// is clipped to Nyquist, else the range is not updated.
+  pub fn set_range(&mut self, min_frequency: T, max_frequency: T) {
+    let nyquist = self.sample_rate / T::two();
+    if min_frequency > T::zero() && min_frequency < max_frequency.min(nyquist) {
+      self.min_frequency = min_frequency;
+      self.max_frequency = max_frequency.min(nyquist);
+    }
+  }
+
+  /// Returns the range of frequencies analyzed, in Hz.
+  pub fn get_range(&self) -> (T, T) {
+    (self.min_frequency, self.max_frequency)
+  }
+
+  /// Sets the spacing, in Hz, of the frequencies the response is sampled
+  /// at.
+  ///
+  /// `resolution` must be positive, else it is not updated.
+  pub fn set_resolution(&mut self, resolution: T) {
+    if resolution > T::zero() && resolution.is_finite() {
+      self.resolution = resolution;
+    }
+  }
+
+  /// Returns the spacing, in Hz, of the frequencies the response is sampled
+  /// at.
+  pub fn get_resolution(&self) -> T {
+    self.resolution
+  }
+
+  /// Sets how far, in dB, a peak must rise above its neighborhood to be
+  /// reported.
+  ///
+  /// `threshold` must be positive, else it is not updated.
+  pub fn set_threshold(&mut self, threshold: T) {
+    if threshold > T::zero() && threshold.is_finite() {
+      self.threshold = threshold;
+    }
+  }
+
+  /// Returns how far, in dB, a peak must rise above its neighborhood to be
+  /// reported.
+  pub fn get_threshold(&self) -> T {
+    self.threshold
+  }
+
+  /// Finds the modes in a measured impulse response, in order of frequency.
+  pub fn analyze(&self, impulse: &[T]) -> Vec<RoomMode<T>> {
+    let frequencies = self.grid();
+    let levels: Vec<T> = frequencies.iter()
+      .map(|frequency| {
+        let w = T::two() * T::pi() * *frequency / self.sample_rate;
+        let (mut re, mut im) = (T::zero(), T::zero());
+        for (n, sample) in impulse.iter().enumerate() {
+          let phase = w * num::cast(n).unwrap();
+          re = re + *sample * phase.cos();
+          im = im - *sample * phase.sin();
+        }
+        util::to_db((re * re + im * im).sqrt())
+      })
+      .collect();
+    self.analyze_spectrum(&frequencies, &levels)
+  }
+
+  /// Finds the modes in a measured magnitude response, in order of
+  /// frequency.
+  ///
+  /// `levels` are in dB, at each of `frequencies`, which must be in
+  /// increasing order and as long as `levels`. Only frequencies in the
+  /// analyzed range are considered.
+  pub fn analyze_spectrum(&self, frequencies: &[T], levels: &[T]) -> Vec<RoomMode<T>> {
+    debug_assert_eq!(frequencies.len(), levels.len());
+    let half: T = num::cast(0.5f64).unwrap();
+    let span = T::two();
+    let last = levels.len().saturating_sub(1);
+    let mut modes = Vec::new();
+
+    for i in 1..last {
+      let frequency = frequencies[i];
+      let level = levels[i];
+      if frequency < self.min_frequency || frequency > self.max_frequency
+        || level <= levels[i - 1] || level < levels[i + 1] {
+        continue;
+      }
+
+      let neighborhood = median(frequencies.iter().zip(levels.iter())
+        .filter(|&(f, _)| *f >= frequency / span && *f <= frequency * span)
+        .map(|(_, level)| *level)
+        .collect());
+      let depth = level - neighborhood;
+      if depth < self.threshold {
+        continue;
+      }
+
+      // Finds where the level falls halfway to the neighborhood
+      let midpoint = level - depth * half;
+      let crossing = |j: usize, k: usize| {
+        let t = (levels[j] - midpoint) / (levels[j] - levels[k]);
+        frequencies[j] + (frequencies[k] - frequencies[j]) * t
+      };
+      let lower = (1..i + 1).rev().find(|j| levels[j - 1] <= midpoint).map(|j| crossing(j, j - 1));
+      let upper = (i..last).find(|j| levels[j + 1] <= midpoint).map(|j| crossing(j, j + 1));
+
+      if let (Some(lower), Some(upper)) = (lower, upper) {
+        modes.push(RoomMode {
+          frequency,
+          q: frequency / (upper - lower),
+          depth
+        });
+      }
+    }
+    modes
+  }
+
+  /// Returns the frequencies the response of an impulse is sampled at.
+  fn grid(&self) -> Vec<T> {
+    // Includes a frequency either side of the range, so peaks at its edges
+    // can be found
+    let mut frequencies = Vec::new();
+    let mut frequency = (self.min_frequency - self.resolution).max(self.resolution);
+    let end = self.max_frequency + self.resolution;
+    while frequency <= end {
+      frequencies.push(frequency);
+      frequency = frequency + self.resolution;
+    }
+    frequencies
+  }
+}
+
+/// Returns the median of `values`.
+fn median<T: Float>(mut values: Vec<T>) -> T {
+  values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+  let middle = values.len() / 2;
+  if values.len().is_multiple_of(2) {
+    (values[middle - 1] + values[middle]) / (T::one() + T::one())
+  }
+  else {
+    values[middle]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Biquad2;
+  use filter::rbj::Peak;
+  use ::traits::Processor;
+
+  /// Returns the impulse response of resonances at 50 Hz and 120 Hz.
+  fn room(sample_rate: f32) -> Vec<f32> {
+    let mut first = Peak::new();
+    first.set_coefficients(sample_rate, 50f32, 12f32, 8f32);
+    let mut second = Peak::new();
+    second.set_coefficients(sample_rate, 120f32, 9f32, 10f32);
+    (0..sample_rate as usize)
+      .map(|n| second.process(first.process(if n == 0 { 1f32 } else { 0f32 })))
+      .collect()
+  }
+
+  #[test]
+  fn flat() {
+    let impulse = vec![0.5f32];
+    assert!(RoomModes::new(8_000f32).analyze(&impulse).is_empty());
+  }
+
+  #[test]
+  fn finds_modes() {
+    let sample_rate = 8_000f32;
+    let modes = RoomModes::new(sample_rate).analyze(&room(sample_rate));
+    assert_eq!(modes.len(), 2);
+
+    assert!((modes[0].frequency - 50f32).abs() < 1f32);
+    assert!((modes[0].depth - 12f32).abs() < 1f32);
+    assert!((modes[0].q - 8f32).abs() < 1f32);
+    assert!((modes[1].frequency - 120f32).abs() < 1f32);
+    assert!((modes[1].depth - 9f32).abs() < 1f32);
+    assert!((modes[1].q - 10f32).abs() < 1.5f32);
+  }
+
+  #[test]
+  fn correction() {
+    let sample_rate = 8_000f32;
+    let analyzer = RoomModes::new(sample_rate);
+    let mut impulse = room(sample_rate);
+    for mode in analyzer.analyze(&impulse) {
+      let mut notch = Biquad2::new();
+      notch.load_coefficients(mode.coefficients(sample_rate));
+      for sample in impulse.iter_mut() {
+        *sample = notch.process(*sample);
+      }
+    }
+    assert!(analyzer.analyze(&impulse).is_empty());
+  }
+}
@@ -0,0 +1,230 @@
+use num;
+use num::traits::Float;
+
+use super::AverageSpectrum;
+use filter::to_minimum_phase;
+use traits::FloatConst;
+use util;
+use window::{apply_window, Window};
+
+/// Matches the long-term average spectrum of a source to a reference.
+///
+/// The source and reference are analyzed in Hann windowed frames, overlapped
+/// by half, and their power spectra are averaged over every frame. The
+/// difference between the averages gives the correction needed to make the
+/// source sound like the reference, which is limited to a settable range and
+/// designed into a linear-phase FIR filter.
+pub struct SpectrumMatch<T> {
+  frame_size: usize,
+  range: T,
+  source: AverageSpectrum<T>,
+  reference: AverageSpectrum<T>
+}
+
+impl<T> SpectrumMatch<T> where T: Float + FloatConst {
+  /// Creates a new `SpectrumMatch` analyzing frames of `frame_size`
+  /// samples, with a correction range of 12 dB.
+  ///
+  /// `frame_size` sets the length of the designed filter.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `frame_size` is not a power of two, at least two.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::SpectrumMatch;
+  ///
+  /// let mut matcher = SpectrumMatch::<f32>::new(256);
+  /// let source = vec![0f32; 1024];
+  /// let reference = vec![0f32; 1024];
+  /// matcher.add_source(&source);
+  /// matcher.add_reference(&reference);
+  ///
+  /// let fir = matcher.design_fir();
+  /// assert_eq!(fir.len(), 257);
+  /// ```
+  pub fn new(frame_size: usize) -> Self {
+    assert!(frame_size.is_power_of_two() && frame_size > 1);
+    let bins = frame_size / 2 + 1;
+    SpectrumMatch {
+      frame_size,
+      range: num::cast(12f64).unwrap(),
+      source: AverageSpectrum::new(bins),
+      reference: AverageSpectrum::new(bins)
+    }
+  }
+
+  /// Sets the largest boost or cut of the correction, in dB.
+  ///
+  /// `range` must be non-negative, else the range is not updated.
+  pub fn set_range(&mut self, range: T) {
+    if range >= T::zero() && range.is_finite() {
+      self.range = range;
+    }
+  }
+
+  /// Returns the largest boost or cut of the correction, in dB.
+  pub fn get_range(&self) -> T {
+    self.range
+  }
+
+  /// Adds samples of the source to its average spectrum.
+  pub fn add_source(&mut self, samples: &[T]) {
+    self.source.add(samples, self.frame_size);
+  }
+
+  /// Adds samples of the reference to its average spectrum.
+  pub fn add_reference(&mut self, samples: &[T]) {
+    self.reference.add(samples, self.frame_size);
+  }
+
+  /// Returns the correction, in dB, for each bin from DC to Nyquist.
+  ///
+  /// Bins are spaced by the sample rate divided by the frame size. Until
+  /// both the source and reference have been analyzed, the correction is
+  /// flat.
+  pub fn correction(&self) -> Vec<T> {
+    let ten: T = num::cast(10f64).unwrap();
+    let source_frames: T = num::cast(self.source.frames).unwrap();
+    let reference_frames: T = num::cast(self.reference.frames).unwrap();
+    let analyzed = self.source.frames > 0 && self.reference.frames > 0;
+
+    self.source.power.iter().zip(self.reference.power.iter())
+      .map(|(&source, &reference)| {
+        if !analyzed {
+          return T::zero();
+        }
+        let source = source / source_frames;
+        let reference = reference / reference_frames;
+        let difference =
+          if source > T::zero() && reference > T::zero() {
+            ten * (reference / source).log10()
+          }
+          else if reference > T::zero() {
+            self.range
+          }
+          else if source > T::zero() {
+            -self.range
+          }
+          else {
+            T::zero()
+          };
+        difference.max(-self.range).min(self.range)
+      })
+      .collect()
+  }
+
+  /// Designs a linear-phase FIR filter applying the correction.
+  ///
+  /// The filter is `frame_size + 1` taps long, and delays its input by
+  /// `frame_size / 2` samples. The taps can be applied with a `filter::Fir`.
+  pub fn design_fir(&self) -> Vec<T> {
+    let gains: Vec<T> = self.correction().into_iter().map(util::to_sample).collect();
+    let size = self.frame_size;
+    let size_float: T = num::cast(size).unwrap();
+    let center: T = num::cast(size / 2).unwrap();
+
+    // The inverse DFT of the zero-phase response, centered in the filter
+    let mut taps: Vec<T> = (0..size + 1)
+      .map(|n| {
+        let n: T = num::cast(n).unwrap();
+        let mut tap = gains[0];
+        for (k, gain) in gains.iter().enumerate().skip(1) {
+          let k_float: T = num::cast(k).unwrap();
+          let phase = T::two() * T::pi() * k_float * (n - center) / size_float;
+          // The Nyquist bin has no mirrored bin
+          let weight = if 2 * k == size { T::one() } else { T::two() };
+          tap = tap + weight * *gain * phase.cos();
+        }
+        tap / size_float
+      })
+      .collect();
+
+    apply_window(&mut taps, Window::Hann);
+    taps
+  }
+
+  /// Designs a minimum phase FIR filter applying the correction.
+  ///
+  /// The filter has the length and magnitude response of `design_fir()`,
+  /// without its latency and pre-ringing.
+  pub fn design_minimum_phase_fir(&self) -> Vec<T> {
+    to_minimum_phase(&self.design_fir())
+  }
+
+  /// Clears both average spectra.
+  pub fn clear(&mut self) {
+    self.source.clear();
+    self.reference.clear();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::rbj::LowPass;
+  use ::traits::Processor;
+
+  /// Returns white noise from a linear congruential generator.
+  fn noise(length: usize) -> Vec<f32> {
+    let mut state = 12_345u32;
+    (0..length)
+      .map(|_| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        (state >> 8) as f32 / (1u32 << 23) as f32 - 1f32
+      })
+      .collect()
+  }
+
+  #[test]
+  #[should_panic]
+  fn frame_size_not_power_of_two() {
+    SpectrumMatch::<f32>::new(96);
+  }
+
+  #[test]
+  fn flat_until_analyzed() {
+    let mut matcher = SpectrumMatch::<f32>::new(64);
+    matcher.add_source(&noise(32));
+    assert!(matcher.correction().iter().all(|gain| *gain == 0f32));
+
+    // A flat correction designs a delayed impulse, shaped by the window
+    let fir = matcher.design_fir();
+    assert!((fir[32] - 1f32).abs() < 1e-5f32);
+  }
+
+  #[test]
+  fn matches_lowpassed_source() {
+    let sample_rate = 44_100f32;
+    let reference = noise(16_384);
+    let mut filter = LowPass::new();
+    filter.set_coefficients(sample_rate, 2_000f32, ::std::f32::consts::FRAC_1_SQRT_2);
+    let source: Vec<f32> = reference.iter().map(|x| filter.process(*x)).collect();
+
+    let mut matcher = SpectrumMatch::new(64);
+    matcher.set_range(6f32);
+    matcher.add_source(&source);
+    matcher.add_reference(&reference);
+
+    // Low frequencies are unchanged, and high frequencies are boosted as far
+    // as the range allows
+    let correction = matcher.correction();
+    assert_eq!(correction.len(), 33);
+    assert!(correction[1].abs() < 1f32);
+    assert!((correction[20] - 6f32).abs() < 1e-6f32);
+
+    let fir = matcher.design_fir();
+    for n in 1..32 {
+      assert!((fir[32 - n] - fir[32 + n]).abs() < 1e-6f32);
+    }
+
+    // The minimum phase filter starts at once, with the same total energy
+    let minimum = matcher.design_minimum_phase_fir();
+    let energy = |taps: &[f32]| taps.iter().map(|tap| tap * tap).sum::<f32>();
+    assert_eq!(minimum.len(), fir.len());
+    assert!(energy(&minimum[..8]) > energy(&fir[..24]));
+    assert!((energy(&minimum) / energy(&fir) - 1f32).abs() < 0.05f32);
+  }
+}
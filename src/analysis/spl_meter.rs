@@ -0,0 +1,339 @@
+use num;
+use num::traits::Float;
+
+use filter::{BiquadCoefficients, SosCascade};
+use traits::{Analyzer, FloatConst, FrequencyResponse, Processor};
+
+/// The corner frequencies, in Hz, of the A and C weightings, from IEC 61672.
+const CORNERS: [f64; 4] = [20.598_997f64, 107.652_65f64, 737.862_23f64, 12_194.217f64];
+
+/// The frequency weighting of a `SplMeter`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FrequencyWeighting {
+  /// The A weighting, which follows the sensitivity of hearing at low levels.
+  A,
+  /// The C weighting, which is flat over most of the audible range.
+  C,
+  /// No weighting.
+  Z
+}
+
+/// The exponential time weighting of a `SplMeter`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeWeighting {
+  /// A 125 millisecond time constant.
+  Fast,
+  /// A 1 second time constant.
+  Slow,
+  /// A 35 millisecond time constant while the level rises, and 1.5 seconds
+  /// while it falls.
+  Impulse
+}
+
+/// A sound level meter.
+///
+/// The signal is frequency weighted, following IEC 61672, and its power is
+/// both averaged with an exponential time weighting, for the current level,
+/// and integrated since the last reset, for the equivalent continuous level,
+/// Leq. The weighting filters are bilinear transforms of the analog
+/// weightings, with each corner prewarped, so they are accurate at common
+/// sample rates up to a few kHz, and within the Class 1 tolerances of the
+/// standard up to 10 kHz at 48 kHz. Every level is in dB, relative to
+/// the level of a full scale square wave plus the calibration offset, which
+/// should be the sound pressure level at that power, so that the meter reads
+/// dB SPL.
+pub struct SplMeter<T> {
+  sample_rate: T,
+  frequency_weighting: FrequencyWeighting,
+  time_weighting: TimeWeighting,
+  filter: SosCascade<T>,
+  calibration: T,
+  // Exponentially averaged power, and its coefficients
+  power: T,
+  attack: T,
+  release: T,
+  // Integrated power since the last reset
+  energy: T,
+  samples: usize,
+  maximum: T
+}
+
+impl<T> SplMeter<T> where T: Float + FloatConst {
+  /// Creates a new `SplMeter` for signals at `sample_rate`, with A frequency
+  /// weighting, Fast time weighting, and no calibration offset.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::{SplMeter, TimeWeighting};
+  /// use rasp::traits::Analyzer;
+  ///
+  /// let mut meter = SplMeter::new(48_000f32);
+  /// meter.set_time_weighting(TimeWeighting::Slow);
+  ///
+  /// // A full scale sine was recorded from a 94 dB SPL calibrator
+  /// meter.set_calibration(97f32);
+  ///
+  /// meter.process_block(&[0.5f32, -0.5f32, 0.25f32, -0.25f32]);
+  /// let level = meter.level();
+  /// let leq = meter.leq();
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut meter = SplMeter {
+      sample_rate,
+      frequency_weighting: FrequencyWeighting::A,
+      time_weighting: TimeWeighting::Fast,
+      filter: weighting(FrequencyWeighting::A, sample_rate),
+      calibration: num::zero(),
+      power: num::zero(),
+      attack: num::zero(),
+      release: num::zero(),
+      energy: num::zero(),
+      samples: 0,
+      maximum: num::zero()
+    };
+    meter.set_time_weighting(TimeWeighting::Fast);
+    meter
+  }
+
+  /// Sets the frequency weighting, which clears the meter.
+  pub fn set_frequency_weighting(&mut self, weighting: FrequencyWeighting) {
+    self.frequency_weighting = weighting;
+    self.filter = self::weighting(weighting, self.sample_rate);
+    self.clear();
+  }
+
+  /// Returns the frequency weighting.
+  pub fn get_frequency_weighting(&self) -> FrequencyWeighting {
+    self.frequency_weighting
+  }
+
+  /// Sets the time weighting of the current level.
+  pub fn set_time_weighting(&mut self, weighting: TimeWeighting) {
+    let (attack, release) =
+      match weighting {
+        TimeWeighting::Fast => (0.125f64, 0.125f64),
+        TimeWeighting::Slow => (1f64, 1f64),
+        TimeWeighting::Impulse => (0.035f64, 1.5f64)
+      };
+    let sample_rate = self.sample_rate;
+    let coefficient = |time: f64| {
+      let time: T = num::cast(time).unwrap();
+      T::one() - (-T::one() / (time * sample_rate)).exp()
+    };
+    self.time_weighting = weighting;
+    self.attack = coefficient(attack);
+    self.release = coefficient(release);
+  }
+
+  /// Returns the time weighting of the current level.
+  pub fn get_time_weighting(&self) -> TimeWeighting {
+    self.time_weighting
+  }
+
+  /// Sets the calibration offset, in dB, which is added to every level.
+  pub fn set_calibration(&mut self, offset: T) {
+    self.calibration = offset;
+  }
+
+  /// Returns the calibration offset, in dB.
+  pub fn get_calibration(&self) -> T {
+    self.calibration
+  }
+
+  /// Sets the calibration offset so the current Leq reads `level`.
+  ///
+  /// Measure a reference source, such as a 94 dB SPL calibrator, and then
+  /// calibrate to its level. The offset is not updated if nothing has been
+  /// measured.
+  pub fn calibrate(&mut self, level: T) {
+    if self.energy > T::zero() {
+      self.calibration = level - (self.leq() - self.calibration);
+    }
+  }
+
+  /// Returns the current time weighted level, in dB.
+  pub fn level(&self) -> T {
+    self.to_level(self.power)
+  }
+
+  /// Returns the highest time weighted level since the last reset, in dB.
+  pub fn max_level(&self) -> T {
+    self.to_level(self.maximum)
+  }
+
+  /// Returns the equivalent continuous level since the last reset, in dB.
+  pub fn leq(&self) -> T {
+    if self.samples == 0 {
+      return self.to_level(T::zero());
+    }
+    let samples: T = num::cast(self.samples).unwrap();
+    self.to_level(self.energy / samples)
+  }
+
+  /// Returns the duration of the Leq integration, in seconds.
+  pub fn duration(&self) -> T {
+    let samples: T = num::cast(self.samples).unwrap();
+    samples / self.sample_rate
+  }
+
+  /// Restarts the Leq integration and the maximum level, keeping the current
+  /// level.
+  pub fn reset(&mut self) {
+    self.energy = T::zero();
+    self.samples = 0;
+    self.maximum = self.power;
+  }
+
+  fn to_level(&self, power: T) -> T {
+    let ten: T = num::cast(10f64).unwrap();
+    let floor: T = num::cast(-200f64).unwrap();
+    if power > T::zero() {
+      (ten * power.log10()).max(floor) + self.calibration
+    }
+    else {
+      floor + self.calibration
+    }
+  }
+}
+
+impl<T> Analyzer<T> for SplMeter<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) {
+    let weighted = self.filter.process(sample);
+    let power = weighted * weighted;
+    let coefficient = if power > self.power { self.attack } else { self.release };
+    self.power = self.power + (power - self.power) * coefficient;
+    self.energy = self.energy + power;
+    self.samples += 1;
+    self.maximum = self.maximum.max(self.power);
+  }
+
+  fn clear(&mut self) {
+    self.filter.clear();
+    self.power = T::zero();
+    self.reset();
+  }
+}
+
+/// Returns the filter of a frequency `weighting` at `sample_rate`, with unity
+/// gain at 1 kHz.
+fn weighting<T: Float + FloatConst>(weighting: FrequencyWeighting, sample_rate: T) -> SosCascade<T> {
+  let fs: f64 = num::cast(sample_rate).unwrap();
+  // Each real analog pole is prewarped to its corner, and mapped to the
+  // z-plane by the bilinear transform
+  let pole = |corner: f64| {
+    let tangent = (::std::f64::consts::PI * corner.min(0.45f64 * fs) / fs).tan();
+    (1f64 - tangent) / (1f64 + tangent)
+  };
+  let section = |zero: f64, poles: (f64, f64)| {
+    BiquadCoefficients {
+      b0: T::one(),
+      b1: num::cast(-2f64 * zero).unwrap(),
+      b2: num::cast(zero * zero).unwrap(),
+      a1: num::cast(-(poles.0 + poles.1)).unwrap(),
+      a2: num::cast(poles.0 * poles.1).unwrap()
+    }
+  };
+
+  // Zeros at DC map to 1, and those at infinity to -1
+  let mut filter = SosCascade::new();
+  let (low, high) = (pole(CORNERS[0]), pole(CORNERS[3]));
+  match weighting {
+    FrequencyWeighting::A => {
+      filter.push_section(section(1f64, (low, low)));
+      filter.push_section(section(1f64, (pole(CORNERS[1]), pole(CORNERS[2]))));
+      filter.push_section(section(-1f64, (high, high)));
+    },
+    FrequencyWeighting::C => {
+      filter.push_section(section(1f64, (low, low)));
+      filter.push_section(section(-1f64, (high, high)));
+    },
+    FrequencyWeighting::Z => return filter
+  }
+  let gain = filter.magnitude_at(num::cast(1_000f64).unwrap(), sample_rate);
+  filter.set_gain(T::one() / gain);
+  filter
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use util;
+
+  /// Returns the Leq of a full scale sine at `frequency`.
+  fn leq(meter: &mut SplMeter<f64>, frequency: f64) -> f64 {
+    meter.clear();
+    let sine: Vec<f64> = (0..48_000)
+      .map(|n| (2f64 * ::std::f64::consts::PI * frequency * n as f64 / 48_000f64).sin())
+      .collect();
+    meter.process_block(&sine[..24_000]);
+    meter.reset();
+    meter.process_block(&sine[24_000..]);
+    meter.leq()
+  }
+
+  #[test]
+  fn frequency_weighting() {
+    let mut meter = SplMeter::new(48_000f64);
+    let half = util::to_db(0.5f64) / 2f64;
+
+    // The weightings are close to IEC 61672 up to 4 kHz, and well within its
+    // tolerances above
+    let a = [(31.5f64, -39.4f64, 0.3f64),
+             (100f64, -19.1f64, 0.3f64),
+             (1_000f64, 0f64, 0.01f64),
+             (4_000f64, 1f64, 0.3f64),
+             (10_000f64, -2.5f64, 1f64)];
+    for &(frequency, gain, tolerance) in a.iter() {
+      assert!((leq(&mut meter, frequency) - half - gain).abs() < tolerance);
+    }
+
+    meter.set_frequency_weighting(FrequencyWeighting::C);
+    assert_eq!(meter.get_frequency_weighting(), FrequencyWeighting::C);
+    let c = [(31.5f64, -3f64, 0.3f64),
+             (100f64, -0.3f64, 0.3f64),
+             (1_000f64, 0f64, 0.01f64),
+             (10_000f64, -4.4f64, 1f64)];
+    for &(frequency, gain, tolerance) in c.iter() {
+      assert!((leq(&mut meter, frequency) - half - gain).abs() < tolerance);
+    }
+
+    meter.set_frequency_weighting(FrequencyWeighting::Z);
+    assert!((leq(&mut meter, 31.5f64) - half).abs() < 0.01f64);
+  }
+
+  #[test]
+  fn time_weighting() {
+    let mut meter = SplMeter::new(48_000f64);
+    meter.set_frequency_weighting(FrequencyWeighting::Z);
+    assert_eq!(meter.get_time_weighting(), TimeWeighting::Fast);
+
+    // A level step reaches 1 - 1/e of its power after one time constant
+    meter.process_block(&vec![1f64; 6_000]);
+    assert!((meter.level() - 10f64 * (1f64 - (-1f64).exp()).log10()).abs() < 0.01f64);
+
+    meter.set_time_weighting(TimeWeighting::Impulse);
+    meter.process_block(&vec![1f64; 48_000]);
+    assert!(meter.level().abs() < 0.01f64);
+    meter.process_block(&vec![0f64; 72_000]);
+    assert!((meter.level() + 10f64 * (1f64).exp().log10()).abs() < 0.01f64);
+    assert!(meter.max_level().abs() < 0.01f64);
+
+    meter.clear();
+    assert_eq!(meter.level(), -200f64);
+    assert_eq!(meter.duration(), 0f64);
+  }
+
+  #[test]
+  fn calibration() {
+    let mut meter = SplMeter::new(48_000f64);
+    meter.calibrate(94f64);
+    assert_eq!(meter.get_calibration(), 0f64);
+
+    leq(&mut meter, 1_000f64);
+    meter.calibrate(94f64);
+    assert!((meter.leq() - 94f64).abs() < 1e-9f64);
+    assert!((meter.get_calibration() - 94f64 - 10f64 * 2f64.log10()).abs() < 0.01f64);
+    assert_eq!(meter.duration(), 0.5f64);
+  }
+}
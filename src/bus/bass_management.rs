@@ -0,0 +1,262 @@
+use num;
+use num::traits::Float;
+use std::mem;
+
+use delay::Delay;
+use filter::Crossover;
+use traits::{FloatConst, Processor};
+use util;
+
+/// The longest alignment delay, in seconds, which covers a difference in
+/// distance of about 17 meters.
+const MAX_DELAY: f64 = 0.05f64;
+
+/// The alignment of one output.
+struct Alignment<T> {
+  delay: Delay<T>,
+  inverted: bool
+}
+
+/// Bass management for a multichannel loudspeaker layout.
+///
+/// Each satellite channel is split by a fourth order Linkwitz-Riley
+/// crossover. The high band feeds its satellite, and the low bands of every
+/// satellite are summed with the LFE channel into a single subwoofer output,
+/// so small satellites are spared the bass they cannot reproduce. The LFE
+/// channel is low passed by the same crossover, and played 10 dB louder, as
+/// its in-band gain is specified.
+///
+/// The outputs are the satellites, in input order, followed by the
+/// subwoofer. Each can be delayed and inverted in polarity, to align
+/// loudspeakers at different distances from the listener.
+pub struct BassManagement<T> {
+  sample_rate: T,
+  frequency: T,
+  // A crossover for each satellite, and one for the LFE channel
+  crossovers: Vec<Crossover<T>>,
+  lfe_gain_db: T,
+  lfe_gain: T,
+  alignments: Vec<Alignment<T>>,
+  // A frame of the satellite inputs, and of the outputs, for each sample of a
+  // block
+  frame: Vec<T>,
+  managed: Vec<T>
+}
+
+impl<T> BassManagement<T> where T: Float + FloatConst {
+  /// Creates a new `BassManagement` for `satellites` channels at
+  /// `sample_rate`, crossing over at 80 Hz.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::BassManagement;
+  ///
+  /// // A 5.1 layout, with five satellites and a subwoofer
+  /// let mut manager = BassManagement::new(48_000f32, 5);
+  /// manager.set_delay(5, 2.5f32);
+  ///
+  /// let mut outputs = [0f32; 6];
+  /// manager.process(&[1f32, 0f32, 0f32, 0f32, 0f32], 0f32, &mut outputs);
+  /// ```
+  pub fn new(sample_rate: T, satellites: usize) -> Self {
+    let frequency: T = num::cast(80f64).unwrap();
+    let max_delay: usize = num::cast((sample_rate * num::cast(MAX_DELAY).unwrap()).ceil()).unwrap();
+    let mut manager = BassManagement {
+      sample_rate,
+      frequency,
+      crossovers: (0..satellites + 1).map(|_| Crossover::new(sample_rate, &[frequency])).collect(),
+      lfe_gain_db: num::zero(),
+      lfe_gain: num::one(),
+      alignments: (0..satellites + 1)
+        .map(|_| Alignment {
+          delay: Delay::new(0, max_delay),
+          inverted: false
+        })
+        .collect(),
+      frame: vec![num::zero(); satellites],
+      managed: vec![num::zero(); satellites + 1]
+    };
+    manager.set_lfe_gain(num::cast(10f64).unwrap());
+    manager
+  }
+
+  /// Returns the number of satellite channels.
+  pub fn satellites(&self) -> usize {
+    self.alignments.len() - 1
+  }
+
+  /// Sets the crossover frequency, in Hz, clearing the crossovers.
+  ///
+  /// `frequency` must be positive and below Nyquist, else it is not updated.
+  pub fn set_frequency(&mut self, frequency: T) {
+    if frequency > T::zero() && frequency < self.sample_rate / T::two() {
+      self.frequency = frequency;
+      for crossover in self.crossovers.iter_mut() {
+        *crossover = Crossover::new(self.sample_rate, &[frequency]);
+      }
+    }
+  }
+
+  /// Returns the crossover frequency, in Hz.
+  pub fn get_frequency(&self) -> T {
+    self.frequency
+  }
+
+  /// Sets the gain of the LFE channel in the subwoofer output, in dB.
+  pub fn set_lfe_gain(&mut self, gain: T) {
+    self.lfe_gain_db = gain;
+    self.lfe_gain = util::to_sample(gain);
+  }
+
+  /// Returns the gain of the LFE channel in the subwoofer output, in dB.
+  pub fn get_lfe_gain(&self) -> T {
+    self.lfe_gain_db
+  }
+
+  /// Sets the delay of `output`, in milliseconds, rounded to the nearest
+  /// sample.
+  ///
+  /// The delay is clipped to 50 milliseconds.
+  pub fn set_delay(&mut self, output: usize, delay: T) {
+    let samples = (delay.max(T::zero()) * self.sample_rate / num::cast(1_000f64).unwrap()).round();
+    self.alignments[output].delay.set_delay(num::cast(samples).unwrap_or(0));
+  }
+
+  /// Returns the delay of `output`, in milliseconds.
+  pub fn get_delay(&self, output: usize) -> T {
+    let samples: T = num::cast(self.alignments[output].delay.get_delay()).unwrap();
+    samples * num::cast(1_000f64).unwrap() / self.sample_rate
+  }
+
+  /// Sets whether `output` is inverted in polarity.
+  pub fn set_inverted(&mut self, output: usize, inverted: bool) {
+    self.alignments[output].inverted = inverted;
+  }
+
+  /// Returns `true` if `output` is inverted in polarity.
+  pub fn is_inverted(&self, output: usize) -> bool {
+    self.alignments[output].inverted
+  }
+
+  /// Processes one frame of the `satellites` and `lfe` inputs into
+  /// `outputs`, which holds every satellite followed by the subwoofer.
+  pub fn process(&mut self, satellites: &[T], lfe: T, outputs: &mut [T]) {
+    debug_assert!(satellites.len() == self.satellites() && outputs.len() == self.alignments.len());
+    let count = self.satellites();
+    let mut subwoofer = T::zero();
+    for ((crossover, sample), output) in self.crossovers.iter_mut().zip(satellites.iter()).zip(outputs.iter_mut()) {
+      let bands = crossover.tick(*sample);
+      subwoofer = subwoofer + bands[0];
+      *output = bands[1];
+    }
+    subwoofer = subwoofer + self.crossovers[count].tick(lfe * self.lfe_gain)[0];
+    outputs[count] = subwoofer;
+
+    for (alignment, output) in self.alignments.iter_mut().zip(outputs.iter_mut()) {
+      let delayed = alignment.delay.process(*output);
+      *output = if alignment.inverted { -delayed } else { delayed };
+    }
+  }
+
+  /// Processes a block of separate `satellites` channels and the `lfe`
+  /// channel into separate `outputs`.
+  pub fn process_block(&mut self, satellites: &[&[T]], lfe: &[T], outputs: &mut [&mut [T]]) {
+    let mut frame = mem::take(&mut self.frame);
+    let mut managed = mem::take(&mut self.managed);
+    for (n, lfe) in lfe.iter().enumerate() {
+      for (sample, satellite) in frame.iter_mut().zip(satellites.iter()) {
+        *sample = satellite[n];
+      }
+      self.process(&frame, *lfe, &mut managed);
+      for (output, sample) in outputs.iter_mut().zip(managed.iter()) {
+        output[n] = *sample;
+      }
+    }
+    self.frame = frame;
+    self.managed = managed;
+  }
+
+  /// Clears the crossovers and delays.
+  pub fn clear(&mut self) {
+    for crossover in self.crossovers.iter_mut() {
+      crossover.clear();
+    }
+    for alignment in self.alignments.iter_mut() {
+      alignment.delay.clear();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the peak of each output for a sine at `frequency` fed to the
+  /// first satellite, or to the LFE channel.
+  fn peaks(manager: &mut BassManagement<f64>, frequency: f64, lfe: bool) -> Vec<f64> {
+    let mut peaks = vec![0f64; manager.satellites() + 1];
+    let mut outputs = vec![0f64; manager.satellites() + 1];
+    for n in 0..48_000 {
+      let sample = (2f64 * ::std::f64::consts::PI * frequency * n as f64 / 48_000f64).sin();
+      let mut inputs = vec![0f64; manager.satellites()];
+      let lfe = if lfe { sample } else { inputs[0] = sample; 0f64 };
+      manager.process(&inputs, lfe, &mut outputs);
+      if n >= 24_000 {
+        for (peak, output) in peaks.iter_mut().zip(outputs.iter()) {
+          *peak = peak.max(output.abs());
+        }
+      }
+    }
+    peaks
+  }
+
+  #[test]
+  fn routing() {
+    let mut manager = BassManagement::new(48_000f64, 2);
+    let low = peaks(&mut manager, 20f64, false);
+    assert!(low[0] < 0.01f64 && low[1] == 0f64 && (low[2] - 1f64).abs() < 0.01f64);
+    let high = peaks(&mut manager, 2_000f64, false);
+    assert!((high[0] - 1f64).abs() < 0.01f64 && high[2] < 0.001f64);
+
+    // The low and high bands are 6 dB down at the crossover
+    let crossover = peaks(&mut manager, 80f64, false);
+    assert!((crossover[0] - 0.5f64).abs() < 0.01f64 && (crossover[2] - 0.5f64).abs() < 0.01f64);
+
+    let lfe = peaks(&mut manager, 20f64, true);
+    assert!((lfe[2] - 10f64.powf(0.5f64)).abs() < 0.03f64);
+    manager.set_lfe_gain(0f64);
+    assert_eq!(manager.get_lfe_gain(), 0f64);
+    let lfe = peaks(&mut manager, 20f64, true);
+    assert!((lfe[2] - 1f64).abs() < 0.01f64);
+  }
+
+  #[test]
+  fn alignment() {
+    let mut manager = BassManagement::new(1_000f64, 1);
+    manager.set_frequency(1_000f64);
+    assert_eq!(manager.get_frequency(), 80f64);
+    manager.set_frequency(100f64);
+    manager.set_lfe_gain(0f64);
+    manager.set_delay(1, 3f64);
+    assert_eq!(manager.get_delay(1), 3f64);
+    manager.set_delay(0, 1_000f64);
+    assert_eq!(manager.get_delay(0), 50f64);
+    manager.set_delay(0, 0f64);
+    manager.set_inverted(1, true);
+    assert!(manager.is_inverted(1));
+
+    let lfe = vec![1f64, 0f64, 0f64, 0f64, 0f64];
+    let satellites = vec![0f64; 5];
+    let mut satellite = vec![0f64; 5];
+    let mut subwoofer = vec![0f64; 5];
+    manager.process_block(&[&satellites], &lfe, &mut [&mut satellite, &mut subwoofer]);
+    assert_eq!(subwoofer[..3].to_vec(), vec![0f64; 3]);
+    assert!(subwoofer[3] < 0f64);
+
+    manager.clear();
+    let mut outputs = [0f64; 2];
+    manager.process(&[0f64], 0f64, &mut outputs);
+    assert_eq!(outputs, [0f64, 0f64]);
+  }
+}
@@ -0,0 +1,268 @@
+use num;
+use num::traits::Float;
+
+/// A mixing matrix, mapping frames of one channel layout to another.
+///
+/// Each output is the sum of every input, weighted by the linear gain at its
+/// row and input column. When gains change, the matrix glides from its
+/// current gains to the new ones over a short fade, so layouts can be
+/// switched while audio plays without clicks.
+///
+/// Presets cover folding a stereo signal down to mono, converting between
+/// left and right and mid and side, and downmixing 5.1 to stereo.
+pub struct ChannelMatrix<T> {
+  inputs: usize,
+  outputs: usize,
+  // Gains of each output row, in input order
+  gains: Vec<T>,
+  target: Vec<T>,
+  // The change of each gain per sample while fading
+  step: Vec<T>,
+  fade_length: usize,
+  remaining: usize
+}
+
+impl<T> ChannelMatrix<T> where T: Float {
+  /// Creates a new `ChannelMatrix` from `inputs` to `outputs` channels, for
+  /// signals at `sample_rate`.
+  ///
+  /// Each input feeds the output of the same number at unity gain, and gain
+  /// changes fade over 10 milliseconds.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::ChannelMatrix;
+  ///
+  /// // Swap left and right
+  /// let mut matrix = ChannelMatrix::new(44_100f32, 2, 2);
+  /// matrix.set_matrix(&[&[0f32, 1f32], &[1f32, 0f32]]);
+  /// let mut output = [0f32; 2];
+  /// for _ in 0..441 {
+  ///   matrix.process(&[1f32, 0f32], &mut output);
+  /// }
+  /// assert_eq!(output, [0f32, 1f32]);
+  /// ```
+  pub fn new(sample_rate: T, inputs: usize, outputs: usize) -> Self {
+    let gains: Vec<T> = (0..outputs * inputs)
+      .map(|index| if index / inputs == index % inputs { T::one() } else { T::zero() })
+      .collect();
+    let mut matrix = ChannelMatrix {
+      inputs,
+      outputs,
+      target: gains.clone(),
+      step: vec![num::zero(); gains.len()],
+      gains,
+      fade_length: 1,
+      remaining: 0
+    };
+    matrix.set_fade(sample_rate * num::cast(0.01f64).unwrap());
+    matrix
+  }
+
+  /// Creates a matrix summing a stereo input to a mono output, each input
+  /// contributing half.
+  pub fn stereo_to_mono(sample_rate: T) -> Self {
+    let half: T = num::cast(0.5f64).unwrap();
+    ChannelMatrix::preset(sample_rate, 2, &[&[half, half]])
+  }
+
+  /// Creates a matrix encoding left and right into mid, their mean, and
+  /// side, half their difference.
+  pub fn mid_side(sample_rate: T) -> Self {
+    let half: T = num::cast(0.5f64).unwrap();
+    ChannelMatrix::preset(sample_rate, 2, &[&[half, half], &[half, -half]])
+  }
+
+  /// Creates a matrix decoding mid and side back into left and right, the
+  /// inverse of `mid_side()`.
+  pub fn mid_side_to_stereo(sample_rate: T) -> Self {
+    let one = T::one();
+    ChannelMatrix::preset(sample_rate, 2, &[&[one, one], &[one, -one]])
+  }
+
+  /// Creates a matrix downmixing 5.1 to stereo, with the input channels
+  /// ordered left, right, center, LFE, left surround, and right surround.
+  ///
+  /// The center and each surround are mixed in 3 dB down, as recommended by
+  /// ITU-R BS.775, and the LFE channel is left out.
+  pub fn downmix_5_1(sample_rate: T) -> Self {
+    let one = T::one();
+    let zero = T::zero();
+    let minus_3db: T = num::cast(0.5f64.sqrt()).unwrap();
+    ChannelMatrix::preset(sample_rate, 6, &[
+      &[one, zero, minus_3db, zero, minus_3db, zero],
+      &[zero, one, minus_3db, zero, zero, minus_3db]
+    ])
+  }
+
+  /// Creates a matrix starting at the gains of `matrix`.
+  fn preset(sample_rate: T, inputs: usize, matrix: &[&[T]]) -> Self {
+    let mut preset = ChannelMatrix::new(sample_rate, inputs, matrix.len());
+    preset.set_matrix(matrix);
+    preset.gains.clone_from(&preset.target);
+    preset.remaining = 0;
+    preset
+  }
+
+  /// Returns the number of input channels.
+  pub fn inputs(&self) -> usize {
+    self.inputs
+  }
+
+  /// Returns the number of output channels.
+  pub fn outputs(&self) -> usize {
+    self.outputs
+  }
+
+  /// Sets the time gain changes fade over, in samples.
+  ///
+  /// `fade_length` must be greater than zero, else the fade time is not
+  /// updated.
+  pub fn set_fade(&mut self, fade_length: T) {
+    if fade_length > T::zero() && fade_length.is_finite() {
+      self.fade_length = num::cast(fade_length.ceil()).unwrap_or(1);
+    }
+  }
+
+  /// Sets the gain from `input` to `output`, as a linear factor.
+  pub fn set_gain(&mut self, output: usize, input: usize, gain: T) {
+    self.target[output * self.inputs + input] = gain;
+    self.start_fade();
+  }
+
+  /// Returns the gain from `input` to `output`, that the matrix is fading
+  /// to if it is fading.
+  pub fn get_gain(&self, output: usize, input: usize) -> T {
+    self.target[output * self.inputs + input]
+  }
+
+  /// Sets every gain, from a row of input gains for each output.
+  ///
+  /// Gains missing from `matrix` are set to zero.
+  pub fn set_matrix(&mut self, matrix: &[&[T]]) {
+    for (output, row) in self.target.chunks_mut(self.inputs).enumerate() {
+      for (input, gain) in row.iter_mut().enumerate() {
+        *gain = matrix.get(output).and_then(|row| row.get(input)).cloned().unwrap_or_else(T::zero);
+      }
+    }
+    self.start_fade();
+  }
+
+  /// Starts fading from the current gains to the target gains.
+  fn start_fade(&mut self) {
+    let length: T = num::cast(self.fade_length).unwrap();
+    for ((step, gain), target) in self.step.iter_mut().zip(self.gains.iter()).zip(self.target.iter()) {
+      *step = (*target - *gain) / length;
+    }
+    self.remaining = self.fade_length;
+  }
+
+  /// Mixes one frame of `input` into `output`.
+  ///
+  /// `input` must hold a sample for every input channel, and `output` one
+  /// for every output channel.
+  pub fn process(&mut self, input: &[T], output: &mut [T]) {
+    debug_assert!(input.len() == self.inputs && output.len() == self.outputs);
+    if self.remaining > 0 {
+      self.remaining -= 1;
+      if self.remaining == 0 {
+        self.gains.clone_from(&self.target);
+      }
+      else {
+        for (gain, step) in self.gains.iter_mut().zip(self.step.iter()) {
+          *gain = *gain + *step;
+        }
+      }
+    }
+
+    if self.inputs == 0 {
+      for sample in output.iter_mut() {
+        *sample = T::zero();
+      }
+      return;
+    }
+    for (sample, row) in output.iter_mut().zip(self.gains.chunks(self.inputs)) {
+      *sample = row.iter().zip(input.iter()).fold(T::zero(), |sum, (gain, x)| sum + *gain * *x);
+    }
+  }
+
+  /// Mixes a block of interleaved input frames into interleaved output
+  /// frames.
+  pub fn process_interleaved(&mut self, input: &[T], output: &mut [T]) {
+    debug_assert!(self.inputs >= 1 && self.outputs >= 1);
+    debug_assert_eq!(input.len() / self.inputs, output.len() / self.outputs);
+    for (input, output) in input.chunks(self.inputs).zip(output.chunks_mut(self.outputs)) {
+      self.process(input, output);
+    }
+  }
+
+  /// Mixes a block of separate input channels into separate output
+  /// channels.
+  pub fn process_block(&mut self, inputs: &[&[T]], outputs: &mut [&mut [T]]) {
+    debug_assert!(inputs.len() == self.inputs && outputs.len() == self.outputs);
+    let length = outputs.first().map(|output| output.len()).unwrap_or(0);
+    let mut frame = vec![T::zero(); self.inputs];
+    let mut mixed = vec![T::zero(); self.outputs];
+    for n in 0..length {
+      for (sample, input) in frame.iter_mut().zip(inputs.iter()) {
+        *sample = input[n];
+      }
+      self.process(&frame, &mut mixed);
+      for (output, sample) in outputs.iter_mut().zip(mixed.iter()) {
+        output[n] = *sample;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn presets() {
+    let mut output = [0f64; 2];
+    ChannelMatrix::mid_side(48_000f64).process(&[1f64, 0.5f64], &mut output);
+    assert_eq!(output, [0.75f64, 0.25f64]);
+    ChannelMatrix::mid_side_to_stereo(48_000f64).process(&[0.75f64, 0.25f64], &mut output);
+    assert_eq!(output, [1f64, 0.5f64]);
+
+    let mut mono = [0f64];
+    ChannelMatrix::stereo_to_mono(48_000f64).process(&[1f64, 0.5f64], &mut mono);
+    assert_eq!(mono, [0.75f64]);
+
+    let mut downmix = ChannelMatrix::downmix_5_1(48_000f64);
+    assert_eq!((downmix.inputs(), downmix.outputs()), (6, 2));
+    downmix.process(&[0f64, 1f64, 0.5f64, 1f64, 0f64, 0.5f64], &mut output);
+    assert!((output[0] - 0.5f64 * 0.5f64.sqrt()).abs() < 1e-12f64);
+    assert!((output[1] - 1f64 - 0.5f64.sqrt()).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn fades() {
+    let mut matrix = ChannelMatrix::new(1_000f64, 1, 2);
+    assert_eq!(matrix.get_gain(0, 0), 1f64);
+    assert_eq!(matrix.get_gain(1, 0), 0f64);
+
+    // From unity to silence, and silence to unity, over 10 samples
+    matrix.set_gain(0, 0, 0f64);
+    matrix.set_gain(1, 0, 1f64);
+    let mut output = [0f64; 20];
+    matrix.process_interleaved(&[1f64; 10], &mut output);
+    assert!((output[0] - 0.9f64).abs() < 1e-12f64);
+    assert!((output[1] - 0.1f64).abs() < 1e-12f64);
+    assert!((output[8] - 0.5f64).abs() < 1e-12f64);
+    assert_eq!((output[18], output[19]), (0f64, 1f64));
+
+    matrix.set_fade(0f64);
+    matrix.set_fade(1f64);
+    matrix.set_matrix(&[&[0.5f64]]);
+    let input = [1f64, 2f64];
+    let mut left = [0f64; 2];
+    let mut right = [0f64; 2];
+    matrix.process_block(&[&input], &mut [&mut left, &mut right]);
+    assert_eq!(left, [0.5f64, 1f64]);
+    assert_eq!(right, [0f64, 0f64]);
+  }
+}
@@ -0,0 +1,191 @@
+use num;
+use num::traits::Float;
+
+use analysis::{PeakEnvDetector, RmsEnvDetector};
+use filter::weighting::KWeighting;
+use traits::{FloatConst, Processor, StereoAnalyzer};
+use util;
+
+/// A snapshot of a `Meter`, with every level in dB.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeterReading<T> {
+  /// The left and right peak levels
+  pub peak: (T, T),
+  /// The left and right RMS levels
+  pub rms: (T, T),
+  /// The momentary loudness, in LUFS
+  pub loudness: T
+}
+
+/// A stereo level meter.
+///
+/// The peak level falls back with a 300 millisecond release, and the RMS
+/// level is integrated over 300 milliseconds. The momentary loudness follows
+/// ITU-R BS.1770, measuring the power over the last 400 milliseconds after
+/// the `filter::weighting::KWeighting` pre-filter.
+pub struct Meter<T> {
+  peak: (PeakEnvDetector<T>, PeakEnvDetector<T>),
+  rms: (RmsEnvDetector<T>, RmsEnvDetector<T>),
+  weighting: (KWeighting<T>, KWeighting<T>),
+  // K-weighted power over the loudness window
+  power: Vec<T>,
+  power_sum: T,
+  write_ptr: usize
+}
+
+impl<T> Meter<T> where T: Float + FloatConst {
+  /// Creates a new `Meter` for signals at `sample_rate`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::bus::Meter;
+  ///
+  /// let sample_rate = 44_100f32;
+  ///
+  /// let mut meter1: Meter<f32> = Meter::new(sample_rate);
+  /// let mut meter2: Meter<f64> = Meter::new(sample_rate as f64);
+  /// let mut meter3 = Meter::<f32>::new(sample_rate);
+  /// let mut meter4 = Meter::<f64>::new(sample_rate as f64);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let release = sample_rate * num::cast(0.3f64).unwrap();
+    let peak = || {
+      // The attack is left instantaneous
+      let mut detector = PeakEnvDetector::new();
+      detector.set_release(release);
+      detector
+    };
+    let rms = || {
+      let mut detector = RmsEnvDetector::new();
+      detector.set_attack(release);
+      detector.set_release(release);
+      detector
+    };
+
+    let window: usize = num::cast((sample_rate * num::cast(0.4f64).unwrap()).round()).unwrap();
+    Meter {
+      peak: (peak(), peak()),
+      rms: (rms(), rms()),
+      weighting: (KWeighting::new(sample_rate), KWeighting::new(sample_rate)),
+      power: vec![num::zero(); window.max(1)],
+      power_sum: num::zero(),
+      write_ptr: 0
+    }
+  }
+
+  /// Returns the current levels.
+  pub fn reading(&self) -> MeterReading<T> {
+    let ten: T = num::cast(10f64).unwrap();
+    let offset: T = num::cast(0.691f64).unwrap();
+    let floor: T = num::cast(-120f64).unwrap();
+
+    let length: T = num::cast(self.power.len()).unwrap();
+    let mean = self.power_sum / length;
+    let loudness =
+      if mean > T::zero() {
+        (ten * mean.log10() - offset).max(floor)
+      }
+      else {
+        floor
+      };
+
+    MeterReading {
+      peak: (util::to_db(self.peak.0.last_out()), util::to_db(self.peak.1.last_out())),
+      rms: (util::to_db(self.rms.0.last_out()), util::to_db(self.rms.1.last_out())),
+      loudness
+    }
+  }
+}
+
+impl<T> StereoAnalyzer<T> for Meter<T> where T: Float + FloatConst {
+  fn process(&mut self, left: T, right: T) {
+    self.peak.0.process(left);
+    self.peak.1.process(right);
+    self.rms.0.process(left);
+    self.rms.1.process(right);
+
+    let weighted_l = self.weighting.0.process(left);
+    let weighted_r = self.weighting.1.process(right);
+    let power = weighted_l * weighted_l + weighted_r * weighted_r;
+
+    // The running sum drifts with rounding, so it is kept from going below
+    // zero, and recomputed from the window once per window
+    self.power_sum = (self.power_sum + power - self.power[self.write_ptr]).max(T::zero());
+    self.power[self.write_ptr] = power;
+    self.write_ptr = (self.write_ptr + 1) % self.power.len();
+    if self.write_ptr == 0 {
+      self.power_sum = self.power.iter().fold(T::zero(), |sum, power| sum + *power);
+    }
+  }
+
+  fn clear(&mut self) {
+    self.peak.0.clear();
+    self.peak.1.clear();
+    self.rms.0.clear();
+    self.rms.1.clear();
+    self.weighting.0.clear();
+    self.weighting.1.clear();
+    for power in self.power.iter_mut() {
+      *power = num::zero();
+    }
+    self.power_sum = num::zero();
+    self.write_ptr = 0;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+
+  #[test]
+  fn drift() {
+    // The running power of loud material does not linger in the reading of
+    // quiet material after it, once the window has passed
+    let sample_rate = 48_000f32;
+    let mut meter = Meter::new(sample_rate);
+    let mut state = 1u32;
+    for _ in 0..sample_rate as usize {
+      state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+      let sample = 8f32 * (state as f32 / ::std::u32::MAX as f32 - 0.5f32);
+      meter.process(sample, -sample);
+    }
+    for _ in 0..sample_rate as usize {
+      meter.process(0.01f32, 0.01f32);
+      assert!(meter.power_sum >= 0f32);
+    }
+    let exact = meter.power.iter().fold(0f32, |sum, power| sum + power);
+    assert!((meter.power_sum / exact - 1f32).abs() < 1e-3f32);
+  }
+
+  #[test]
+  fn new() {
+    let meter = Meter::new(44_100f32);
+    let reading = meter.reading();
+    assert!((reading.peak.0 - -120f32).abs() < 1e-6f32);
+    assert!((reading.rms.1 - -120f32).abs() < 1e-6f32);
+    assert!((reading.loudness - -120f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn sine_levels() {
+    // A full scale 1 kHz sine in one channel reads about -3 LUFS
+    let sample_rate = 48_000f32;
+    let mut meter = Meter::new(sample_rate);
+    for n in 0..sample_rate as usize {
+      let sample = (2f32 * PI * 1_000f32 * n as f32 / sample_rate).sin();
+      meter.process(sample, 0f32);
+    }
+
+    let reading = meter.reading();
+    assert!(reading.peak.0.abs() < 0.1f32);
+    assert!((reading.rms.0 - -3.01f32).abs() < 0.2f32);
+    assert!((reading.loudness - -3.01f32).abs() < 0.2f32);
+    assert!((reading.peak.1 - -120f32).abs() < 1e-6f32);
+
+    meter.clear();
+    assert!((meter.reading().loudness - -120f32).abs() < 1e-6f32);
+  }
+}
@@ -0,0 +1,647 @@
+use num;
+use num::traits::Float;
+use std::mem;
+
+use bus::{pan_gains, Meter, MeterReading, Monitor, PanLaw};
+use traits::{FloatConst, StereoAnalyzer, StereoProcessor};
+use util;
+
+/// The level of one input feeding an auxiliary send.
+struct Send<T> {
+  level: T,
+  gain: T,
+  pre_fader: bool
+}
+
+/// The settings of one mixer input.
+struct Channel<T> {
+  gain: T,
+  pan: T,
+  mute: bool,
+  solo: bool,
+  sends: Vec<Send<T>>,
+  // Fader gain, and pan gains for each side
+  fader: T,
+  pan_l: T,
+  pan_r: T
+}
+
+/// An auxiliary bus, processed by an effect shared between all inputs.
+struct Return<T: Float> {
+  effect: Box<dyn StereoProcessor<T>>,
+  gain_db: T,
+  gain: T,
+  // The summed input to the effect
+  bus: (T, T)
+}
+
+/// A stereo summing mixer.
+///
+/// Each mono input has its own gain, pan, mute, and solo. While any input is
+/// soloed, only soloed inputs are heard. The inputs are summed into a stereo
+/// master bus with its own gain, which can optionally be soft clipped.
+///
+/// Auxiliary sends route a level of any input to an effect shared by every
+/// input, such as a single reverb, and the output of the effect is returned
+/// to the master bus. Each send is taken either before or after the input's
+/// fader. Muted or silenced inputs feed none of their sends.
+///
+/// Once enabled, a `Meter` measures every input, after its fader and pan,
+/// and the master bus, so the levels can be read at any time. An optional
+/// `Monitor` section follows the master meter, so monitoring changes only
+/// what is heard.
+pub struct Mixer<T: Float> {
+  channels: Vec<Channel<T>>,
+  returns: Vec<Return<T>>,
+  meters: Vec<Meter<T>>,
+  master_meter: Option<Meter<T>>,
+  monitor: Option<Monitor<T>>,
+  pan_law: PanLaw,
+  master_gain: T,
+  master: T,
+  soft_clip: bool,
+  // A sample of each input, gathered for each frame of a block
+  frame: Vec<T>,
+  output: (T, T)
+}
+
+impl<T> Mixer<T> where T: Float + FloatConst {
+  /// Creates a new `Mixer` with `channels` inputs.
+  ///
+  /// Every input is centered at unity gain, using the constant power pan law.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::bus::Mixer;
+  ///
+  /// let mut mixer1: Mixer<f32> = Mixer::new(4);
+  /// let mut mixer2: Mixer<f64> = Mixer::new(4);
+  /// let mut mixer3 = Mixer::<f32>::new(4);
+  /// let mut mixer4 = Mixer::<f64>::new(4);
+  /// ```
+  pub fn new(channels: usize) -> Self {
+    let mut mixer =
+      Mixer {
+        channels: (0..channels)
+          .map(|_| Channel {
+            gain: num::zero(),
+            pan: num::zero(),
+            mute: false,
+            solo: false,
+            sends: Vec::new(),
+            fader: num::zero(),
+            pan_l: num::zero(),
+            pan_r: num::zero()
+          })
+          .collect(),
+        returns: Vec::new(),
+        meters: Vec::new(),
+        master_meter: None,
+        monitor: None,
+        pan_law: PanLaw::ConstantPower,
+        master_gain: num::zero(),
+        master: num::one(),
+        soft_clip: false,
+        frame: vec![num::zero(); channels],
+        output: (num::zero(), num::zero())
+      };
+    for channel in 0..channels {
+      mixer.update(channel);
+    }
+    mixer
+  }
+
+  /// Returns the number of inputs.
+  pub fn len(&self) -> usize {
+    self.channels.len()
+  }
+
+  /// Returns `true` if there are no inputs.
+  pub fn is_empty(&self) -> bool {
+    self.channels.is_empty()
+  }
+
+  /// Sets the gain of an input, in dB.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `channel` is not less than `len()`.
+  pub fn set_gain(&mut self, channel: usize, gain: T) {
+    self.channels[channel].gain = gain;
+    self.update(channel);
+  }
+
+  /// Returns the gain of an input, in dB.
+  pub fn get_gain(&self, channel: usize) -> T {
+    self.channels[channel].gain
+  }
+
+  /// Sets the pan position of an input.
+  ///
+  /// `pan` is clipped to the range `[-1, 1]`.
+  pub fn set_pan(&mut self, channel: usize, pan: T) {
+    self.channels[channel].pan = pan.max(-T::one()).min(T::one());
+    self.update(channel);
+  }
+
+  /// Returns the pan position of an input.
+  pub fn get_pan(&self, channel: usize) -> T {
+    self.channels[channel].pan
+  }
+
+  /// Mutes or unmutes an input.
+  pub fn set_mute(&mut self, channel: usize, mute: bool) {
+    self.channels[channel].mute = mute;
+  }
+
+  /// Returns `true` if an input is muted.
+  pub fn is_muted(&self, channel: usize) -> bool {
+    self.channels[channel].mute
+  }
+
+  /// Solos or unsolos an input.
+  pub fn set_solo(&mut self, channel: usize, solo: bool) {
+    self.channels[channel].solo = solo;
+  }
+
+  /// Returns `true` if an input is soloed.
+  pub fn is_soloed(&self, channel: usize) -> bool {
+    self.channels[channel].solo
+  }
+
+  /// Sets the pan law used by every input.
+  pub fn set_pan_law(&mut self, pan_law: PanLaw) {
+    self.pan_law = pan_law;
+    for channel in 0..self.channels.len() {
+      self.update(channel);
+    }
+  }
+
+  /// Returns the pan law used by every input.
+  pub fn get_pan_law(&self) -> PanLaw {
+    self.pan_law
+  }
+
+  /// Sets the gain of the master bus, in dB.
+  pub fn set_master_gain(&mut self, gain: T) {
+    self.master_gain = gain;
+    self.master = util::to_sample(gain);
+  }
+
+  /// Returns the gain of the master bus, in dB.
+  pub fn get_master_gain(&self) -> T {
+    self.master_gain
+  }
+
+  /// Enables or disables soft clipping of the master bus.
+  ///
+  /// The soft clipper is a `tanh` curve, which is close to linear for small
+  /// signals and limits the output to the range `(-1, 1)`.
+  pub fn set_soft_clip(&mut self, soft_clip: bool) {
+    self.soft_clip = soft_clip;
+  }
+
+  /// Returns `true` if the master bus is soft clipped.
+  pub fn is_soft_clipped(&self) -> bool {
+    self.soft_clip
+  }
+
+  /// Adds an auxiliary send processed by `effect`, and returns the index of
+  /// the send.
+  ///
+  /// Every input feeds the new send post-fader, at a level of negative
+  /// infinity dB, and the effect is returned to the master bus at unity gain.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::Mixer;
+  /// use rasp::effects::Crossfeed;
+  ///
+  /// let mut mixer = Mixer::<f32>::new(2);
+  /// let send = mixer.add_send(Box::new(Crossfeed::new()));
+  /// mixer.set_send_level(0, send, -6f32);
+  /// mixer.set_send_pre_fader(1, send, true);
+  /// ```
+  pub fn add_send(&mut self, effect: Box<dyn StereoProcessor<T>>) -> usize {
+    for channel in self.channels.iter_mut() {
+      channel.sends.push(
+        Send {
+          level: T::neg_infinity(),
+          gain: num::zero(),
+          pre_fader: false
+        });
+    }
+    self.returns.push(
+      Return {
+        effect,
+        gain_db: num::zero(),
+        gain: num::one(),
+        bus: (num::zero(), num::zero())
+      });
+    self.returns.len() - 1
+  }
+
+  /// Returns the number of auxiliary sends.
+  pub fn sends(&self) -> usize {
+    self.returns.len()
+  }
+
+  /// Sets the level, in dB, at which an input feeds a send.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `channel` is not less than `len()`, or if `send` is not less
+  /// than `sends()`.
+  pub fn set_send_level(&mut self, channel: usize, send: usize, level: T) {
+    let send = &mut self.channels[channel].sends[send];
+    send.level = level;
+    send.gain = util::to_sample(level);
+  }
+
+  /// Returns the level, in dB, at which an input feeds a send.
+  pub fn get_send_level(&self, channel: usize, send: usize) -> T {
+    self.channels[channel].sends[send].level
+  }
+
+  /// Sets whether an input feeds a send before, or after, its fader.
+  ///
+  /// A pre-fader send is not affected by the input's gain, which is useful
+  /// for feeding an effect independently of the dry mix.
+  pub fn set_send_pre_fader(&mut self, channel: usize, send: usize, pre_fader: bool) {
+    self.channels[channel].sends[send].pre_fader = pre_fader;
+  }
+
+  /// Returns `true` if an input feeds a send before its fader.
+  pub fn is_send_pre_fader(&self, channel: usize, send: usize) -> bool {
+    self.channels[channel].sends[send].pre_fader
+  }
+
+  /// Sets the gain, in dB, at which the effect of a send is returned to the
+  /// master bus.
+  pub fn set_return_gain(&mut self, send: usize, gain: T) {
+    let send = &mut self.returns[send];
+    send.gain_db = gain;
+    send.gain = util::to_sample(gain);
+  }
+
+  /// Returns the gain, in dB, at which the effect of a send is returned to
+  /// the master bus.
+  pub fn get_return_gain(&self, send: usize) -> T {
+    self.returns[send].gain_db
+  }
+
+  /// Enables metering of every input and the master bus, for signals at
+  /// `sample_rate`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::Mixer;
+  ///
+  /// let mut mixer = Mixer::<f32>::new(2);
+  /// mixer.enable_meters(44_100f32);
+  /// mixer.process(&[0.5f32, 0.25f32]);
+  ///
+  /// let bridge: Vec<f32> = (0..mixer.len())
+  ///   .filter_map(|channel| mixer.channel_meter(channel))
+  ///   .map(|reading| reading.peak.0)
+  ///   .collect();
+  /// assert!(bridge[0] > bridge[1]);
+  /// ```
+  pub fn enable_meters(&mut self, sample_rate: T) {
+    self.meters = (0..self.channels.len()).map(|_| Meter::new(sample_rate)).collect();
+    self.master_meter = Some(Meter::new(sample_rate));
+  }
+
+  /// Disables metering.
+  pub fn disable_meters(&mut self) {
+    self.meters.clear();
+    self.master_meter = None;
+  }
+
+  /// Returns the levels of an input, or `None` if metering is disabled.
+  pub fn channel_meter(&self, channel: usize) -> Option<MeterReading<T>> {
+    self.meters.get(channel).map(|meter| meter.reading())
+  }
+
+  /// Returns the levels of the master bus, or `None` if metering is
+  /// disabled.
+  pub fn master_meter(&self) -> Option<MeterReading<T>> {
+    self.master_meter.as_ref().map(|meter| meter.reading())
+  }
+
+  /// Enables the monitoring section of the master bus, for signals at
+  /// `sample_rate`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::Mixer;
+  ///
+  /// let mut mixer = Mixer::<f32>::new(2);
+  /// mixer.enable_monitor(44_100f32);
+  /// if let Some(monitor) = mixer.monitor_mut() {
+  ///   monitor.set_dim(true);
+  /// }
+  /// ```
+  pub fn enable_monitor(&mut self, sample_rate: T) {
+    self.monitor = Some(Monitor::new(sample_rate));
+  }
+
+  /// Disables the monitoring section.
+  pub fn disable_monitor(&mut self) {
+    self.monitor = None;
+  }
+
+  /// Returns the monitoring section, or `None` if it is disabled.
+  pub fn monitor_mut(&mut self) -> Option<&mut Monitor<T>> {
+    self.monitor.as_mut()
+  }
+
+  fn update(&mut self, channel: usize) {
+    let channel = &mut self.channels[channel];
+    let (left, right) = pan_gains(channel.pan, self.pan_law);
+    channel.fader = util::to_sample(channel.gain);
+    channel.pan_l = left;
+    channel.pan_r = right;
+  }
+
+  /// Mixes one sample from each input and outputs the pair of master bus
+  /// samples.
+  ///
+  /// `inputs` must have one sample for each input.
+  pub fn process(&mut self, inputs: &[T]) -> (T, T) {
+    debug_assert_eq!(inputs.len(), self.channels.len());
+    let soloing = self.channels.iter().any(|channel| channel.solo);
+
+    for send in self.returns.iter_mut() {
+      send.bus = (num::zero(), num::zero());
+    }
+
+    let mut out_l = T::zero();
+    let mut out_r = T::zero();
+    for (i, (channel, sample)) in self.channels.iter().zip(inputs.iter()).enumerate() {
+      if channel.mute || (soloing && !channel.solo) {
+        if let Some(meter) = self.meters.get_mut(i) {
+          meter.process(T::zero(), T::zero());
+        }
+        continue;
+      }
+      let left = channel.pan_l * *sample;
+      let right = channel.pan_r * *sample;
+      out_l = out_l + channel.fader * left;
+      out_r = out_r + channel.fader * right;
+
+      if let Some(meter) = self.meters.get_mut(i) {
+        meter.process(channel.fader * left, channel.fader * right);
+      }
+
+      for (send, aux) in channel.sends.iter().zip(self.returns.iter_mut()) {
+        let gain = if send.pre_fader { send.gain } else { send.gain * channel.fader };
+        aux.bus = (aux.bus.0 + gain * left, aux.bus.1 + gain * right);
+      }
+    }
+
+    for send in self.returns.iter_mut() {
+      let (left, right) = send.effect.process(send.bus.0, send.bus.1);
+      out_l = out_l + send.gain * left;
+      out_r = out_r + send.gain * right;
+    }
+
+    out_l = out_l * self.master;
+    out_r = out_r * self.master;
+    if self.soft_clip {
+      out_l = out_l.tanh();
+      out_r = out_r.tanh();
+    }
+    if let Some(ref mut meter) = self.master_meter {
+      meter.process(out_l, out_r);
+    }
+    if let Some(ref mut monitor) = self.monitor {
+      let (left, right) = monitor.process(out_l, out_r);
+      out_l = left;
+      out_r = right;
+    }
+
+    self.output = (out_l, out_r);
+    self.output
+  }
+
+  /// Mixes a block from each input into the `left` and `right` outputs,
+  /// calling `process()` on each sample.
+  ///
+  /// `inputs` must have one block for each input, and every block must be
+  /// as long as the outputs.
+  pub fn process_block(&mut self, inputs: &[&[T]], left: &mut [T], right: &mut [T]) {
+    debug_assert_eq!(inputs.len(), self.channels.len());
+    debug_assert!(left.len() == right.len());
+    debug_assert!(inputs.iter().all(|input| input.len() == left.len()));
+
+    let mut frame = mem::take(&mut self.frame);
+    for n in 0..left.len() {
+      for (sample, input) in frame.iter_mut().zip(inputs.iter()) {
+        *sample = input[n];
+      }
+      let (out_l, out_r) = self.process(&frame);
+      left[n] = out_l;
+      right[n] = out_r;
+    }
+    self.frame = frame;
+  }
+
+  /// Resets memory of every send effect and meter, and the last output, to
+  /// zero.
+  pub fn clear(&mut self) {
+    for send in self.returns.iter_mut() {
+      send.effect.clear();
+    }
+    for meter in self.meters.iter_mut().chain(self.master_meter.iter_mut()) {
+      meter.clear();
+    }
+    if let Some(ref mut monitor) = self.monitor {
+      monitor.clear();
+    }
+    self.output = (num::zero(), num::zero());
+  }
+
+  /// Returns the last computed pair of output samples.
+  pub fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn new() {
+    let mut mixer = Mixer::new(2);
+    assert_eq!(mixer.len(), 2);
+
+    let (left, right) = mixer.process(&[1f32, 0f32]);
+    assert!((left - ::std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6f32);
+    assert!((left - right).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn gain_and_pan() {
+    let mut mixer = Mixer::new(2);
+    mixer.set_pan(0, -1f32);
+    mixer.set_pan(1, 1f32);
+    mixer.set_gain(1, -20f32);
+
+    let (left, right) = mixer.process(&[0.5f32, 1f32]);
+    assert!((left - 0.5f32).abs() < 1e-6f32);
+    assert!((right - 0.1f32).abs() < 1e-6f32);
+
+    mixer.set_master_gain(-6.0206f32);
+    let (left, right) = mixer.process(&[0.5f32, 1f32]);
+    assert!((left - 0.25f32).abs() < 1e-4f32);
+    assert!((right - 0.05f32).abs() < 1e-4f32);
+  }
+
+  #[test]
+  fn mute_and_solo() {
+    let mut mixer = Mixer::new(3);
+    mixer.set_pan_law(PanLaw::Linear);
+    let inputs = [1f32, 2f32, 4f32];
+
+    mixer.set_mute(0, true);
+    let (left, _) = mixer.process(&inputs);
+    assert!((left - 3f32).abs() < 1e-6f32);
+
+    // Soloing silences every other input
+    mixer.set_solo(1, true);
+    let (left, _) = mixer.process(&inputs);
+    assert!((left - 1f32).abs() < 1e-6f32);
+
+    // Mute takes precedence over solo
+    mixer.set_solo(0, true);
+    let (left, _) = mixer.process(&inputs);
+    assert!((left - 1f32).abs() < 1e-6f32);
+  }
+
+  /// Doubles its input, to show where sends are mixed.
+  struct Double;
+
+  impl StereoProcessor<f32> for Double {
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+      (2f32 * left, 2f32 * right)
+    }
+    fn clear(&mut self) {}
+    fn last_out(&self) -> (f32, f32) { (0f32, 0f32) }
+  }
+
+  #[test]
+  fn sends() {
+    let mut mixer = Mixer::new(2);
+    mixer.set_pan_law(PanLaw::Linear);
+    mixer.set_pan(0, -1f32);
+    mixer.set_pan(1, 1f32);
+    let send = mixer.add_send(Box::new(Double));
+    assert_eq!(mixer.sends(), 1);
+
+    // Sends are silent until their level is set
+    let (left, right) = mixer.process(&[1f32, 1f32]);
+    assert!((left - 1f32).abs() < 1e-6f32);
+    assert!((right - 1f32).abs() < 1e-6f32);
+
+    mixer.set_send_level(0, send, 0f32);
+    let (left, right) = mixer.process(&[1f32, 1f32]);
+    assert!((left - 3f32).abs() < 1e-6f32);
+    assert!((right - 1f32).abs() < 1e-6f32);
+
+    // Post-fader sends follow the fader, pre-fader sends do not
+    mixer.set_gain(0, -120f32);
+    let (left, _) = mixer.process(&[1f32, 1f32]);
+    assert!(left.abs() < 1e-6f32);
+
+    mixer.set_send_pre_fader(0, send, true);
+    let (left, _) = mixer.process(&[1f32, 1f32]);
+    assert!((left - 2f32).abs() < 1e-6f32);
+
+    mixer.set_return_gain(send, -6.0206f32);
+    let (left, _) = mixer.process(&[1f32, 1f32]);
+    assert!((left - 1f32).abs() < 1e-4f32);
+
+    // Muted inputs feed no sends
+    mixer.set_mute(0, true);
+    let (left, _) = mixer.process(&[1f32, 1f32]);
+    assert!(left.abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn meters() {
+    let mut mixer = Mixer::new(2);
+    assert!(mixer.master_meter().is_none());
+
+    mixer.enable_meters(44_100f32);
+    mixer.set_pan(0, -1f32);
+    mixer.set_mute(1, true);
+    mixer.process(&[1f32, 1f32]);
+
+    let reading = mixer.channel_meter(0).unwrap();
+    assert!(reading.peak.0.abs() < 1e-4f32);
+    assert!((reading.peak.1 - -120f32).abs() < 1e-4f32);
+    let reading = mixer.channel_meter(1).unwrap();
+    assert!((reading.peak.0 - -120f32).abs() < 1e-4f32);
+    let reading = mixer.master_meter().unwrap();
+    assert!(reading.peak.0.abs() < 1e-4f32);
+
+    mixer.clear();
+    let reading = mixer.master_meter().unwrap();
+    assert!((reading.peak.0 - -120f32).abs() < 1e-4f32);
+
+    mixer.disable_meters();
+    assert!(mixer.channel_meter(0).is_none());
+  }
+
+  #[test]
+  fn monitor() {
+    let mut mixer = Mixer::new(1);
+    mixer.set_pan(0, -1f32);
+    mixer.enable_meters(44_100f32);
+    mixer.enable_monitor(44_100f32);
+    mixer.monitor_mut().unwrap().set_swap(true);
+    mixer.clear();
+
+    // The meters are not affected by monitoring
+    let (left, right) = mixer.process(&[1f32]);
+    assert!(left.abs() < 1e-6f32);
+    assert!((right - 1f32).abs() < 1e-6f32);
+    assert!(mixer.master_meter().unwrap().peak.0.abs() < 1e-4f32);
+  }
+
+  #[test]
+  fn soft_clip() {
+    let mut mixer = Mixer::new(1);
+    mixer.set_pan(0, -1f32);
+    mixer.set_soft_clip(true);
+
+    let (left, _) = mixer.process(&[3f32]);
+    assert!(left < 1f32 && left > 0.99f32);
+    let (left, _) = mixer.process(&[0.01f32]);
+    assert!((left - 0.01f32).abs() < 1e-5f32);
+  }
+
+  #[test]
+  fn process_block() {
+    let mut mixer = Mixer::new(2);
+    mixer.set_pan(0, -1f32);
+    mixer.set_pan(1, 1f32);
+
+    let a = [1f32, 2f32, 3f32];
+    let b = [-1f32, -2f32, -3f32];
+    let mut left = [0f32; 3];
+    let mut right = [0f32; 3];
+    mixer.process_block(&[&a, &b], &mut left, &mut right);
+    for n in 0..3 {
+      assert!((left[n] - a[n]).abs() < 1e-6f32);
+      assert!((right[n] - b[n]).abs() < 1e-6f32);
+    }
+  }
+}
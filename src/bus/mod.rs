@@ -0,0 +1,94 @@
+//! Mixing and gain staging for combining many sources into a stereo bus,
+//! mapping between channel layouts, running mono processors over many
+//! channels, tapping signals for metering, and aligning the speakers they
+//! feed.
+//!
+//! Pan positions range from `-1`, hard left, through `0`, center, to `1`,
+//! hard right. Gains are given in dB.
+
+mod bass_management;
+mod channel_matrix;
+mod meter;
+mod mixer;
+mod monitor;
+mod multi_channel;
+mod speaker_align;
+mod tap;
+
+pub use self::bass_management::BassManagement as BassManagement;
+pub use self::channel_matrix::ChannelMatrix   as ChannelMatrix;
+pub use self::meter::Meter                    as Meter;
+pub use self::meter::MeterReading             as MeterReading;
+pub use self::mixer::Mixer                    as Mixer;
+pub use self::monitor::Monitor                as Monitor;
+pub use self::multi_channel::MultiChannel     as MultiChannel;
+pub use self::speaker_align::SpeakerAlign     as SpeakerAlign;
+pub use self::tap::Tap                        as Tap;
+pub use self::tap::TapPoint                   as TapPoint;
+
+use num;
+use num::traits::Float;
+
+use traits::FloatConst;
+
+/// The relationship between pan position and the gain of each side.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PanLaw {
+  /// The gains sum to one, so a centered source is 6 dB down on each side.
+  Linear,
+  /// The squared gains sum to one, so a centered source is 3 dB down on each
+  /// side and its power is constant across the stereo field.
+  ConstantPower
+}
+
+/// Returns the left and right gains for a `pan` position using `law`.
+///
+/// `pan` is clipped to the range `[-1, 1]`.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::bus::{pan_gains, PanLaw};
+///
+/// let (left, right) = pan_gains(0f32, PanLaw::ConstantPower);
+/// assert!((left - right).abs() < 1e-6f32);
+/// assert!((left * left + right * right - 1f32).abs() < 1e-6f32);
+/// ```
+pub fn pan_gains<T: Float + FloatConst>(pan: T, law: PanLaw) -> (T, T) {
+  let one = T::one();
+  let half: T = num::cast(0.5f64).unwrap();
+  // Position from zero, at the left, to one, at the right
+  let position = (pan.max(-one).min(one) + one) * half;
+
+  match law {
+    PanLaw::Linear => (one - position, position),
+    PanLaw::ConstantPower => {
+      let angle = position * T::pi() * half;
+      (angle.cos(), angle.sin())
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn pan_laws() {
+    let (left, right) = pan_gains(-1f32, PanLaw::ConstantPower);
+    assert!((left - 1f32).abs() < 1e-6f32);
+    assert!(right.abs() < 1e-6f32);
+
+    let (left, right) = pan_gains(2f32, PanLaw::Linear);
+    assert!(left.abs() < 1e-6f32);
+    assert!((right - 1f32).abs() < 1e-6f32);
+
+    let (left, right) = pan_gains(0f32, PanLaw::Linear);
+    assert!((left - 0.5f32).abs() < 1e-6f32);
+    assert!((right - 0.5f32).abs() < 1e-6f32);
+
+    let (left, right) = pan_gains(0f32, PanLaw::ConstantPower);
+    assert!((left - ::std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6f32);
+    assert!((right - ::std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6f32);
+  }
+}
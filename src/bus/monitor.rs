@@ -0,0 +1,225 @@
+use num;
+use num::traits::Float;
+
+use traits::{FloatConst, StereoProcessor};
+use util;
+
+/// The level of the dimmed output, in dB.
+const DIM_LEVEL: f64 = -20f64;
+
+/// A switch whose state is faded linearly between zero and one.
+struct Toggle<T> {
+  on: bool,
+  value: T
+}
+
+impl<T> Toggle<T> where T: Float {
+  fn new() -> Self {
+    Toggle { on: false, value: num::zero() }
+  }
+
+  fn tick(&mut self, step: T) -> T {
+    self.value =
+      if self.on {
+        (self.value + step).min(T::one())
+      }
+      else {
+        (self.value - step).max(T::zero())
+      };
+    self.value
+  }
+
+  fn snap(&mut self) {
+    self.value = if self.on { T::one() } else { T::zero() };
+  }
+}
+
+/// Monitoring controls for a stereo bus.
+///
+/// The output can be dimmed by 20 dB, folded down to mono, inverted in
+/// polarity, and swapped left for right. Each control is faded in and out
+/// over a short time to avoid clicks.
+pub struct Monitor<T> {
+  dim: Toggle<T>,
+  mono: Toggle<T>,
+  invert: Toggle<T>,
+  swap: Toggle<T>,
+  // The change of each fade per sample
+  step: T,
+  // The gain of the dim control, as a sample value
+  dim_level: T,
+  output: (T, T)
+}
+
+impl<T> Monitor<T> where T: Float + FloatConst {
+  /// Creates a new `Monitor`, with every control off, for signals at
+  /// `sample_rate`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::bus::Monitor;
+  ///
+  /// let sample_rate = 44_100f32;
+  ///
+  /// let mut monitor1: Monitor<f32> = Monitor::new(sample_rate);
+  /// let mut monitor2: Monitor<f64> = Monitor::new(sample_rate as f64);
+  /// let mut monitor3 = Monitor::<f32>::new(sample_rate);
+  /// let mut monitor4 = Monitor::<f64>::new(sample_rate as f64);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut monitor =
+      Monitor {
+        dim: Toggle::new(),
+        mono: Toggle::new(),
+        invert: Toggle::new(),
+        swap: Toggle::new(),
+        step: num::one(),
+        dim_level: util::to_sample(num::cast(DIM_LEVEL).unwrap()),
+        output: (num::zero(), num::zero())
+      };
+    // 10 millisecond fades
+    monitor.set_fade(sample_rate * num::cast(0.01f64).unwrap());
+    monitor
+  }
+
+  /// Sets the fade time of every control, in samples.
+  ///
+  /// `fade_length` must be greater than zero, else the fade time is not
+  /// updated.
+  pub fn set_fade(&mut self, fade_length: T) {
+    if fade_length > num::zero() && fade_length.is_finite() {
+      self.step = T::one() / fade_length;
+    }
+  }
+
+  /// Dims the output by 20 dB.
+  pub fn set_dim(&mut self, dim: bool) {
+    self.dim.on = dim;
+  }
+
+  /// Returns `true` if the output is dimmed.
+  pub fn is_dimmed(&self) -> bool {
+    self.dim.on
+  }
+
+  /// Folds the output down to mono, by sending the average of both sides to
+  /// each side.
+  pub fn set_mono(&mut self, mono: bool) {
+    self.mono.on = mono;
+  }
+
+  /// Returns `true` if the output is folded down to mono.
+  pub fn is_mono(&self) -> bool {
+    self.mono.on
+  }
+
+  /// Inverts the polarity of both sides of the output.
+  pub fn set_polarity_invert(&mut self, invert: bool) {
+    self.invert.on = invert;
+  }
+
+  /// Returns `true` if the polarity of the output is inverted.
+  pub fn is_polarity_inverted(&self) -> bool {
+    self.invert.on
+  }
+
+  /// Swaps the left and right sides of the output.
+  pub fn set_swap(&mut self, swap: bool) {
+    self.swap.on = swap;
+  }
+
+  /// Returns `true` if the left and right sides of the output are swapped.
+  pub fn is_swapped(&self) -> bool {
+    self.swap.on
+  }
+}
+
+impl<T> StereoProcessor<T> for Monitor<T> where T: Float + FloatConst {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    let one = T::one();
+    let two = T::two();
+    let step = self.step;
+
+    let swap = self.swap.tick(step);
+    let (left, right) = (left + swap * (right - left), right + swap * (left - right));
+
+    let mono = self.mono.tick(step);
+    let center = (left + right) / two;
+    let (left, right) = (left + mono * (center - left), right + mono * (center - right));
+
+    let gain = (one - two * self.invert.tick(step))
+             * (one + self.dim.tick(step) * (self.dim_level - one));
+
+    self.output = (gain * left, gain * right);
+    self.output
+  }
+
+  /// Resets the last output to zero, and completes every fade.
+  fn clear(&mut self) {
+    for toggle in [&mut self.dim, &mut self.mono, &mut self.invert, &mut self.swap].iter_mut() {
+      toggle.snap();
+    }
+    self.output = (num::zero(), num::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::StereoProcessor;
+
+  #[test]
+  fn new() {
+    let mut monitor = Monitor::new(44_100f32);
+    let (left, right) = monitor.process(0.5f32, -0.25f32);
+    assert!((left - 0.5f32).abs() < 1e-6f32);
+    assert!((right - -0.25f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn controls() {
+    let mut monitor = Monitor::new(44_100f32);
+
+    monitor.set_swap(true);
+    monitor.clear();
+    let (left, right) = monitor.process(1f32, 0f32);
+    assert!(left.abs() < 1e-6f32);
+    assert!((right - 1f32).abs() < 1e-6f32);
+
+    monitor.set_swap(false);
+    monitor.set_mono(true);
+    monitor.clear();
+    let (left, right) = monitor.process(1f32, 0f32);
+    assert!((left - 0.5f32).abs() < 1e-6f32);
+    assert!((right - 0.5f32).abs() < 1e-6f32);
+
+    monitor.set_mono(false);
+    monitor.set_polarity_invert(true);
+    monitor.set_dim(true);
+    monitor.clear();
+    let (left, right) = monitor.process(1f32, 0.5f32);
+    assert!((left - -0.1f32).abs() < 1e-6f32);
+    assert!((right - -0.05f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn fades() {
+    let mut monitor = Monitor::new(44_100f32);
+    monitor.set_fade(4f32);
+    monitor.set_polarity_invert(true);
+
+    // The output is faded through zero, rather than jumping
+    let expected = [0.5f32, 0f32, -0.5f32, -1f32, -1f32];
+    for value in expected.iter() {
+      let (left, right) = monitor.process(1f32, 1f32);
+      assert!((left - value).abs() < 1e-6f32);
+      assert!((right - value).abs() < 1e-6f32);
+    }
+  }
+}
@@ -0,0 +1,153 @@
+use std::marker::PhantomData;
+
+use num::traits::Float;
+
+use traits::Processor;
+
+/// A mono processor run over any number of channels.
+///
+/// Each channel is processed by its own copy of the prototype processor, so
+/// their states stay separate. Copies are made the first time a block holds
+/// a channel that has none yet, and kept when later blocks hold fewer
+/// channels, so any mono effect handles whatever layout it is given without
+/// a vector of processors kept alongside.
+pub struct MultiChannel<T, P> {
+  prototype: P,
+  processors: Vec<P>,
+  sample: PhantomData<T>
+}
+
+impl<T, P> MultiChannel<T, P> where T: Float, P: Processor<T> + Clone {
+  /// Creates a new `MultiChannel` copying `prototype` for each channel.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::MultiChannel;
+  /// use rasp::delay::Delay;
+  ///
+  /// let mut delays = MultiChannel::new(Delay::new(1, 10));
+  /// let mut left = vec![1f32, 0f32];
+  /// let mut right = vec![0f32, 2f32];
+  /// let mut center = vec![3f32, 0f32];
+  /// delays.process_block(&mut [&mut left, &mut right, &mut center]);
+  /// assert_eq!(delays.channels(), 3);
+  /// assert_eq!(left, vec![0f32, 1f32]);
+  /// assert_eq!(right, vec![0f32, 0f32]);
+  /// assert_eq!(center, vec![0f32, 3f32]);
+  /// ```
+  pub fn new(prototype: P) -> Self {
+    MultiChannel {
+      prototype,
+      processors: Vec::new(),
+      sample: PhantomData
+    }
+  }
+
+  /// Returns the number of channels with a processor.
+  pub fn channels(&self) -> usize {
+    self.processors.len()
+  }
+
+  /// Returns the processor of `channel`, if it has been made.
+  pub fn channel(&self, channel: usize) -> Option<&P> {
+    self.processors.get(channel)
+  }
+
+  /// Returns the processor of `channel`, if it has been made, to change it
+  /// alone.
+  pub fn channel_mut(&mut self, channel: usize) -> Option<&mut P> {
+    self.processors.get_mut(channel)
+  }
+
+  /// Applies `update` to the processor of every channel, and to the
+  /// prototype of channels made later, such as to set a parameter for all
+  /// of them.
+  pub fn update<F>(&mut self, mut update: F) where F: FnMut(&mut P) {
+    update(&mut self.prototype);
+    for processor in self.processors.iter_mut() {
+      update(processor);
+    }
+  }
+
+  /// Makes processors for up to `channels` channels.
+  fn reserve(&mut self, channels: usize) {
+    while self.processors.len() < channels {
+      self.processors.push(self.prototype.clone());
+    }
+  }
+
+  /// Processes a block of separate channels, each with its own processor.
+  pub fn process_block(&mut self, channels: &mut [&mut [T]]) {
+    self.reserve(channels.len());
+    for (processor, samples) in self.processors.iter_mut().zip(channels.iter_mut()) {
+      for sample in samples.iter_mut() {
+        *sample = processor.process(*sample);
+      }
+    }
+  }
+
+  /// Processes a block of `channels` interleaved channels, each with its own
+  /// processor.
+  ///
+  /// The length of `samples` must be a multiple of `channels`. If `channels`
+  /// is zero, the samples are left unaltered.
+  pub fn process_interleaved(&mut self, samples: &mut [T], channels: usize) {
+    if channels == 0 {
+      return;
+    }
+    debug_assert!(samples.len().is_multiple_of(channels));
+    self.reserve(channels);
+    for frame in samples.chunks_mut(channels) {
+      for (processor, sample) in self.processors.iter_mut().zip(frame.iter_mut()) {
+        *sample = processor.process(*sample);
+      }
+    }
+  }
+
+  /// Clears the memory of every channel.
+  pub fn clear(&mut self) {
+    for processor in self.processors.iter_mut() {
+      processor.clear();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::OnePole;
+
+  #[test]
+  fn separate_states() {
+    let mut filters = MultiChannel::new(OnePole::new());
+    filters.update(|filter| filter.set_coefficients(0.5f64, -0.5f64));
+
+    let mut samples = vec![1f64, 0f64, 0f64, 0f64];
+    filters.process_interleaved(&mut samples, 2);
+    assert_eq!(samples, vec![0.5f64, 0f64, 0.25f64, 0f64]);
+
+    // Later channels start from the prototype, with its parameters
+    let mut first = vec![0f64];
+    let mut second = vec![0f64];
+    let mut third = vec![1f64];
+    filters.process_block(&mut [&mut first, &mut second, &mut third]);
+    assert_eq!(filters.channels(), 3);
+    assert_eq!((first[0], second[0], third[0]), (0.125f64, 0f64, 0.5f64));
+
+    // Channels left out of a block keep their state
+    let mut mono = vec![0f64];
+    filters.process_block(&mut [&mut mono]);
+    assert_eq!(filters.channel(2).unwrap().last_out(), 0.5f64);
+
+    filters.channel_mut(0).unwrap().set_coefficients(1f64, 0f64);
+    filters.clear();
+    let mut samples = vec![1f64, 1f64];
+    filters.process_interleaved(&mut samples, 2);
+    assert_eq!(samples, vec![1f64, 0.5f64]);
+
+    // Without any channels, nothing is processed
+    filters.process_interleaved(&mut samples, 0);
+    assert_eq!(samples, vec![1f64, 0.5f64]);
+  }
+}
@@ -0,0 +1,270 @@
+use num;
+use num::traits::Float;
+use std::mem;
+
+use delay::LinearDelay;
+use spatial::SPEED_OF_SOUND;
+use traits::{FloatConst, Processor};
+use util;
+
+/// The longest delay of each speaker, in seconds, which covers a difference
+/// in distance of about 34 meters.
+const MAX_DELAY: f64 = 0.1f64;
+
+/// The delay and trim of one speaker, with the fade towards new settings.
+struct Speaker<T> {
+  delay: LinearDelay<T>,
+  delay_target: f32,
+  delay_step: f32,
+  gain: T,
+  trim: T,
+  gain_step: T,
+  remaining: usize
+}
+
+/// Delay and gain alignment for the outputs feeding a set of speakers.
+///
+/// Each channel has a fractional delay, set in milliseconds or as the
+/// distance sound travels in that time, and a gain trim in dB. Speakers
+/// placed at different distances from the listener are aligned by delaying
+/// the nearer ones, so every wavefront arrives together, and trimming the
+/// louder ones. Changes fade over a short time, so settings can be adjusted
+/// while listening, with the delay gliding rather than jumping.
+pub struct SpeakerAlign<T> {
+  sample_rate: T,
+  speakers: Vec<Speaker<T>>,
+  fade_length: usize,
+  // A sample of each speaker, gathered for each frame of a block
+  frame: Vec<T>
+}
+
+impl<T> SpeakerAlign<T> where T: Float + FloatConst {
+  /// Creates a new `SpeakerAlign` for `channels` speakers at `sample_rate`,
+  /// each without delay or trim.
+  ///
+  /// Changes fade over 10 milliseconds.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::bus::SpeakerAlign;
+  ///
+  /// // The left speaker is 3 meters away, the right speaker 3.5 meters
+  /// let mut align = SpeakerAlign::new(48_000f32, 2);
+  /// align.align_distances(&[3f32, 3.5f32]);
+  /// align.set_trim(1, 1.5f32);
+  ///
+  /// let mut frame = [1f32, 1f32];
+  /// align.process(&mut frame);
+  /// ```
+  pub fn new(sample_rate: T, channels: usize) -> Self {
+    let max_delay: usize = num::cast((sample_rate * num::cast(MAX_DELAY).unwrap()).ceil()).unwrap();
+    let mut align = SpeakerAlign {
+      sample_rate,
+      speakers: (0..channels)
+        .map(|_| Speaker {
+          delay: LinearDelay::new(0f32, max_delay),
+          delay_target: 0f32,
+          delay_step: 0f32,
+          gain: num::one(),
+          trim: num::zero(),
+          gain_step: num::zero(),
+          remaining: 0
+        })
+        .collect(),
+      fade_length: 1,
+      frame: vec![num::zero(); channels]
+    };
+    align.set_fade(sample_rate * num::cast(0.01f64).unwrap());
+    align
+  }
+
+  /// Returns the number of speakers.
+  pub fn channels(&self) -> usize {
+    self.speakers.len()
+  }
+
+  /// Sets the time changes fade over, in samples.
+  ///
+  /// `fade_length` must be greater than zero, else the fade time is not
+  /// updated.
+  pub fn set_fade(&mut self, fade_length: T) {
+    if fade_length > T::zero() && fade_length.is_finite() {
+      self.fade_length = num::cast(fade_length.ceil()).unwrap_or(1);
+    }
+  }
+
+  /// Sets the delay of `channel`, in milliseconds.
+  ///
+  /// The delay is clipped to 100 milliseconds.
+  pub fn set_delay(&mut self, channel: usize, delay: T) {
+    let thousand: T = num::cast(1_000f64).unwrap();
+    let samples: f32 = num::cast(delay * self.sample_rate / thousand).unwrap_or(0f32);
+    let speaker = &mut self.speakers[channel];
+    let max_delay = speaker.delay.get_max_delay() as f32;
+    speaker.delay_target = samples.max(0f32).min(max_delay);
+    self.start_fade(channel);
+  }
+
+  /// Returns the delay of `channel`, in milliseconds, that it is fading to
+  /// if it is fading.
+  pub fn get_delay(&self, channel: usize) -> T {
+    let thousand: T = num::cast(1_000f64).unwrap();
+    let samples: T = num::cast(self.speakers[channel].delay_target).unwrap();
+    samples * thousand / self.sample_rate
+  }
+
+  /// Sets the delay of `channel` to the time sound takes to travel
+  /// `distance`, in meters.
+  pub fn set_distance(&mut self, channel: usize, distance: T) {
+    let thousand: T = num::cast(1_000f64).unwrap();
+    let speed_of_sound: T = num::cast(SPEED_OF_SOUND).unwrap();
+    self.set_delay(channel, distance / speed_of_sound * thousand);
+  }
+
+  /// Returns the distance sound travels in the delay of `channel`, in
+  /// meters.
+  pub fn get_distance(&self, channel: usize) -> T {
+    let thousand: T = num::cast(1_000f64).unwrap();
+    let speed_of_sound: T = num::cast(SPEED_OF_SOUND).unwrap();
+    self.get_delay(channel) / thousand * speed_of_sound
+  }
+
+  /// Sets the delay of every speaker from its `distances` to the listener,
+  /// in meters, so sound from each arrives with sound from the farthest.
+  pub fn align_distances(&mut self, distances: &[T]) {
+    debug_assert!(distances.len() == self.speakers.len());
+    let farthest = distances.iter().fold(T::zero(), |farthest, distance| farthest.max(*distance));
+    for (channel, distance) in distances.iter().enumerate() {
+      self.set_distance(channel, farthest - *distance);
+    }
+  }
+
+  /// Sets the gain trim of `channel`, in dB.
+  pub fn set_trim(&mut self, channel: usize, trim: T) {
+    self.speakers[channel].trim = trim;
+    self.start_fade(channel);
+  }
+
+  /// Returns the gain trim of `channel`, in dB.
+  pub fn get_trim(&self, channel: usize) -> T {
+    self.speakers[channel].trim
+  }
+
+  /// Starts fading `channel` from its current delay and gain to its new
+  /// settings.
+  fn start_fade(&mut self, channel: usize) {
+    let length = self.fade_length;
+    let speaker = &mut self.speakers[channel];
+    speaker.delay_step = (speaker.delay_target - speaker.delay.get_delay()) / length as f32;
+    let length: T = num::cast(length).unwrap();
+    speaker.gain_step = (util::to_sample(speaker.trim) - speaker.gain) / length;
+    speaker.remaining = self.fade_length;
+  }
+
+  /// Aligns one `frame`, holding a sample for every speaker, in place.
+  pub fn process(&mut self, frame: &mut [T]) {
+    debug_assert!(frame.len() == self.speakers.len());
+    for (speaker, sample) in self.speakers.iter_mut().zip(frame.iter_mut()) {
+      if speaker.remaining > 0 {
+        speaker.remaining -= 1;
+        if speaker.remaining == 0 {
+          speaker.delay.set_delay(speaker.delay_target);
+          speaker.gain = util::to_sample(speaker.trim);
+        }
+        else {
+          let delay = speaker.delay.get_delay() + speaker.delay_step;
+          speaker.delay.set_delay(delay);
+          speaker.gain = speaker.gain + speaker.gain_step;
+        }
+      }
+      *sample = speaker.delay.process(*sample) * speaker.gain;
+    }
+  }
+
+  /// Aligns a block of interleaved frames in place.
+  pub fn process_interleaved(&mut self, samples: &mut [T]) {
+    debug_assert!(!self.speakers.is_empty());
+    let channels = self.speakers.len();
+    for frame in samples.chunks_mut(channels) {
+      self.process(frame);
+    }
+  }
+
+  /// Aligns a block of separate channels in place.
+  pub fn process_block(&mut self, channels: &mut [&mut [T]]) {
+    debug_assert!(channels.len() == self.speakers.len());
+    let length = channels.first().map(|channel| channel.len()).unwrap_or(0);
+    let mut frame = mem::take(&mut self.frame);
+    for n in 0..length {
+      for (sample, channel) in frame.iter_mut().zip(channels.iter()) {
+        *sample = channel[n];
+      }
+      self.process(&mut frame);
+      for (channel, sample) in channels.iter_mut().zip(frame.iter()) {
+        channel[n] = *sample;
+      }
+    }
+    self.frame = frame;
+  }
+
+  /// Clears the delay line of every speaker.
+  pub fn clear(&mut self) {
+    for speaker in self.speakers.iter_mut() {
+      speaker.delay.clear();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn alignment() {
+    let mut align = SpeakerAlign::new(1_000f64, 2);
+    align.set_fade(1f64);
+    align.set_delay(0, 2.5f64);
+    align.set_trim(1, -6f64);
+    assert_eq!((align.get_delay(0), align.get_trim(1)), (2.5f64, -6f64));
+
+    let mut left = [1f64, 0f64, 0f64, 0f64, 0f64];
+    let mut right = [1f64, 0f64, 0f64, 0f64, 0f64];
+    align.process_block(&mut [&mut left, &mut right]);
+    // Half a sample of delay spreads the impulse over the 2nd and 3rd samples
+    assert_eq!(left[..2].to_vec(), vec![0f64, 0f64]);
+    assert!((left[2] - 0.5f64).abs() < 1e-6f64 && (left[3] - 0.5f64).abs() < 1e-6f64);
+    assert!((right[0] - util::to_sample(-6f64)).abs() < 1e-12f64);
+
+    // Delays are clipped to 100 milliseconds
+    align.set_delay(1, 1_000f64);
+    assert_eq!(align.get_delay(1), 100f64);
+  }
+
+  #[test]
+  fn distances() {
+    let mut align = SpeakerAlign::new(48_000f64, 3);
+    align.align_distances(&[2f64, 3.5f64, 3f64]);
+    assert!((align.get_distance(0) - 1.5f64).abs() < 1e-6f64);
+    assert_eq!(align.get_distance(1), 0f64);
+    assert!((align.get_delay(2) - 0.5f64 / 343f64 * 1_000f64).abs() < 1e-6f64);
+  }
+
+  #[test]
+  fn fades() {
+    let mut align = SpeakerAlign::new(1_000f64, 1);
+    align.set_trim(0, -120f64);
+
+    // The gain falls linearly to silence over 10 samples
+    let mut samples = [1f64; 12];
+    align.process_interleaved(&mut samples);
+    assert!((samples[0] - 0.9f64).abs() < 1e-12f64);
+    assert!((samples[4] - 0.5f64).abs() < 1e-12f64);
+    assert_eq!(samples[9..].to_vec(), vec![0f64; 3]);
+
+    align.clear();
+    align.set_trim(0, 0f64);
+    align.process(&mut [0f64]);
+    assert_eq!(align.channels(), 1);
+  }
+}
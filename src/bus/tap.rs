@@ -0,0 +1,181 @@
+use num::traits::Float;
+
+use render::ProcessContext;
+use traits::Processor;
+
+/// Where a `Tap` measures the signal.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TapPoint {
+  /// The input of the processor.
+  Pre,
+  /// The output of the processor.
+  Post
+}
+
+/// A processor with an analyzer tapping its input or output.
+///
+/// The analyzer is given every sample at the tap point, and its own output
+/// is discarded, so the signal passes through as if only the processor were
+/// there. Any `Processor` can analyze, such as an envelope detector, and is
+/// read back through `analyzer()`, so a metering point can be declared
+/// anywhere in a chain of processors without restructuring it.
+#[derive(Clone)]
+pub struct Tap<P, A> {
+  processor: P,
+  analyzer: A,
+  point: TapPoint
+}
+
+impl<P, A> Tap<P, A> {
+  /// Creates a new `Tap` running `analyzer` on the signal at `point` of
+  /// `processor`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::analysis::PeakEnvDetector;
+  /// use rasp::bus::{Tap, TapPoint};
+  /// use rasp::filter::Biquad2;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = Biquad2::new();
+  /// filter.set_coefficients(0.5f32, 0f32, 0f32, 0f32, 0f32);
+  /// let mut pre = Tap::new(filter.clone(), PeakEnvDetector::new(), TapPoint::Pre);
+  /// let mut post = Tap::new(filter, PeakEnvDetector::new(), TapPoint::Post);
+  ///
+  /// assert_eq!(pre.process(1f32), 0.5f32);
+  /// assert_eq!(post.process(1f32), 0.5f32);
+  /// assert_eq!(pre.analyzer().last_out(), 1f32);
+  /// assert_eq!(post.analyzer().last_out(), 0.5f32);
+  /// ```
+  pub fn new(processor: P, analyzer: A, point: TapPoint) -> Self {
+    Tap {
+      processor,
+      analyzer,
+      point
+    }
+  }
+
+  /// Returns the processor.
+  pub fn processor(&self) -> &P {
+    &self.processor
+  }
+
+  /// Returns the processor, to change its parameters.
+  pub fn processor_mut(&mut self) -> &mut P {
+    &mut self.processor
+  }
+
+  /// Returns the analyzer, to read its measurements.
+  pub fn analyzer(&self) -> &A {
+    &self.analyzer
+  }
+
+  /// Returns the analyzer, to change its parameters.
+  pub fn analyzer_mut(&mut self) -> &mut A {
+    &mut self.analyzer
+  }
+
+  /// Sets where the signal is measured.
+  pub fn set_point(&mut self, point: TapPoint) {
+    self.point = point;
+  }
+
+  /// Returns where the signal is measured.
+  pub fn get_point(&self) -> TapPoint {
+    self.point
+  }
+
+  /// Returns the processor and the analyzer, consuming the tap.
+  pub fn into_inner(self) -> (P, A) {
+    (self.processor, self.analyzer)
+  }
+}
+
+impl<T, P, A> Processor<T> for Tap<P, A> where T: Float, P: Processor<T>, A: Processor<T> {
+  fn process(&mut self, sample: T) -> T {
+    match self.point {
+      TapPoint::Pre => {
+        self.analyzer.process(sample);
+        self.processor.process(sample)
+      },
+      TapPoint::Post => {
+        let output = self.processor.process(sample);
+        self.analyzer.process(output);
+        output
+      }
+    }
+  }
+
+  fn process_context(&mut self, samples: &mut [T], context: &ProcessContext<T>) -> T {
+    if context.bypass {
+      return self.last_out();
+    }
+    if self.point == TapPoint::Pre {
+      for sample in samples.iter() {
+        self.analyzer.process(*sample);
+      }
+    }
+    let output = self.processor.process_context(samples, context);
+    if self.point == TapPoint::Post {
+      for sample in samples.iter() {
+        self.analyzer.process(*sample);
+      }
+    }
+    output
+  }
+
+  fn clear(&mut self) {
+    self.processor.clear();
+    self.analyzer.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.processor.last_out()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use analysis::RmsEnvDetector;
+  use delay::Delay;
+
+  #[test]
+  fn transparent() {
+    let mut delay = Delay::new(2, 2);
+    let mut tap = Tap::new(Delay::new(2, 2), RmsEnvDetector::new(), TapPoint::Post);
+
+    let input = [1f64, -0.5f64, 0.25f64, 0f64, 0f64];
+    let mut block = input.to_vec();
+    tap.process_block(&mut block);
+    for (sample, output) in input.iter().zip(block.iter()) {
+      assert_eq!(delay.process(*sample), *output);
+    }
+    assert_eq!(tap.analyzer().last_out(), 0.25f64);
+
+    // Before the delay, the analyzer sees the latest input
+    tap.set_point(TapPoint::Pre);
+    assert_eq!(tap.get_point(), TapPoint::Pre);
+    assert_eq!(tap.process(0.75f64), 0f64);
+    assert_eq!(tap.analyzer().last_out(), 0.75f64);
+
+    tap.clear();
+    assert_eq!((tap.last_out(), tap.analyzer().last_out()), (0f64, 0f64));
+    let (processor, _) = tap.into_inner();
+    assert_eq!(processor.last_out(), 0f64);
+  }
+
+  #[test]
+  fn boxed() {
+    let mut chain: Vec<Box<dyn Processor<f32>>> = vec![
+      Box::new(Tap::new(Delay::new(1, 1), RmsEnvDetector::new(), TapPoint::Pre)),
+      Box::new(Delay::new(1, 1))
+    ];
+    let mut block = vec![1f32, 0f32, 0f32];
+    for processor in chain.iter_mut() {
+      processor.process_block(&mut block);
+    }
+    assert_eq!(block, vec![0f32, 0f32, 1f32]);
+  }
+}
@@ -1,12 +1,15 @@
 use num;
 use num::traits::Float;
 
+use super::DelayState;
 use traits::{
   Processor,
+  StateSnapshot,
   TappableDelayLine
 };
 
 /// A time-varying, linear interpolating delay line.
+#[derive(Clone)]
 pub struct LinearDelay<T> {
   memory: Vec<T>,
   output: T,
@@ -134,7 +137,7 @@ impl<T> LinearDelay<T> where T: Float {
       self.do_next_out = false
     }
 
-    return self.next_out;
+    self.next_out
   }
 }
 
@@ -168,6 +171,27 @@ impl<T> Processor<T> for LinearDelay<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for LinearDelay<T> where T: Float {
+  type State = DelayState<T>;
+
+  fn snapshot(&self) -> Self::State {
+    DelayState {
+      memory: self.memory.clone(),
+      write_ptr: self.write_ptr,
+      output: self.output
+    }
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.memory.clone_from(&state.memory);
+    self.write_ptr = state.write_ptr;
+    self.output = state.output;
+    self.do_next_out = true;
+    let delay = self.delay;
+    self.set_delay(delay);
+  }
+}
+
 impl<T> TappableDelayLine<T> for LinearDelay<T> where T: Float {
   fn tap_out(&self, tap_delay: usize) -> T {
     let mut tap: isize = self.write_ptr as isize - tap_delay as isize - 1;
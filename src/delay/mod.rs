@@ -7,10 +7,15 @@ use num::traits::Float;
 
 use traits::{
   Processor,
+  StateSnapshot,
   TappableDelayLine
 };
 
 /// A time-varying delay line.
+///
+/// Like `LinearDelay`, the delay line is generic over its sample type, so it
+/// can be used in `f32` or `f64` processing chains.
+#[derive(Clone)]
 pub struct Delay<T> {
   memory: Vec<T>,
   output: T,
@@ -21,6 +26,18 @@ pub struct Delay<T> {
   delay: usize
 }
 
+/// The saved memory of a `Delay` or `LinearDelay`.
+///
+/// The snapshot holds the contents of the delay line, but not the delay, so
+/// a restored line reads at its current delay. A snapshot restored into a
+/// line of another maximum delay replaces its length too.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DelayState<T> {
+  memory: Vec<T>,
+  write_ptr: usize,
+  output: T
+}
+
 impl<T> Delay<T> where T: Float {
   /// Creates a delay line.
   ///
@@ -64,9 +81,7 @@ impl<T> Delay<T> where T: Float {
   /// Set the maximum delay-line length, in samples.
   pub fn set_max_delay(&mut self, delay: usize) {
     if delay < self.memory.len() { return; }
-    else {
-      self.memory.resize(delay + 1, num::zero());
-    }
+    self.memory.resize(delay + 1, num::zero());
   }
 
   /// Returns the maximum delay-line length, in samples.
@@ -99,7 +114,7 @@ impl<T> Delay<T> where T: Float {
     self.delay
   }
 
-  /// Returns the value that will be output by the next call to `tick()`.
+  /// Returns the value that will be output by the next call to `process()`.
   pub fn next_out(&self) -> T {
     self.memory[self.read_ptr]
   }
@@ -131,6 +146,26 @@ impl<T> Processor<T> for Delay<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for Delay<T> where T: Float {
+  type State = DelayState<T>;
+
+  fn snapshot(&self) -> Self::State {
+    DelayState {
+      memory: self.memory.clone(),
+      write_ptr: self.write_ptr,
+      output: self.output
+    }
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.memory.clone_from(&state.memory);
+    self.write_ptr = state.write_ptr;
+    self.output = state.output;
+    let delay = self.delay;
+    self.set_delay(delay);
+  }
+}
+
 impl<T> TappableDelayLine<T> for Delay<T> where T: Float {
   fn tap_out(&self, tap_delay: usize) -> T {
     let mut tap: isize = self.write_ptr as isize - tap_delay as isize - 1;
@@ -162,7 +197,7 @@ impl<T> TappableDelayLine<T> for Delay<T> where T: Float {
 mod tests {
   use super::*;
   use std::f32::EPSILON;
-  use ::traits::{Processor, TappableDelayLine};
+  use ::traits::{Processor, StateSnapshot, TappableDelayLine};
 
   #[test]
   fn new() {
@@ -267,4 +302,49 @@ mod tests {
       assert!((*sample - delay.process(0f32)).abs() < EPSILON);
     }
   }
-}
\ No newline at end of file
+
+  /// Writes a ramp into any tappable delay line and reads it back.
+  fn tap_ramp<T: Float, D: TappableDelayLine<T>>(delay: &mut D, length: usize) -> Vec<T> {
+    for i in 0..length {
+      delay.tap_in(num::cast(i).unwrap(), i);
+    }
+    delay.add_to(T::one(), 0);
+    (0..length).map(|i| delay.tap_out(i)).collect()
+  }
+
+  #[test]
+  fn f64_taps() {
+    let mut delay = Delay::<f64>::new(4, 4095);
+    let taps = tap_ramp(&mut delay, 4);
+    assert_eq!(taps, vec![1f64, 1f64, 2f64, 3f64]);
+
+    let output: Vec<f64> = (0..4).map(|_| delay.process(0f64)).collect();
+    assert_eq!(output, vec![3f64, 2f64, 1f64, 1f64]);
+  }
+
+  #[test]
+  fn snapshot() {
+    let mut delay = Delay::<f32>::new(3, 10);
+    for i in 0..5 {
+      delay.process(i as f32);
+    }
+    let snapshot = delay.snapshot();
+    let expected: Vec<f32> = (0..6).map(|_| delay.process(0f32)).collect();
+
+    // The delay is not part of the snapshot
+    delay.set_delay(1);
+    delay.restore(&snapshot);
+    assert_eq!(delay.last_out(), 1f32);
+    delay.set_delay(3);
+    let output: Vec<f32> = (0..6).map(|_| delay.process(0f32)).collect();
+    assert_eq!(output, expected);
+    assert_eq!(output[..3].to_vec(), vec![2f32, 3f32, 4f32]);
+
+    let mut linear = LinearDelay::<f32>::new(1.5f32, 10);
+    linear.process(2f32);
+    let snapshot = linear.snapshot();
+    let expected = (linear.process(0f32), linear.process(0f32));
+    linear.restore(&snapshot);
+    assert_eq!((linear.process(0f32), linear.process(0f32)), expected);
+  }
+}
@@ -0,0 +1,224 @@
+use num;
+use num::traits::Float;
+
+use delay::LinearDelay;
+use traits::{FloatConst, Processor};
+use util;
+
+/// The lowest frequency a comb can be tuned to, in Hz.
+const MIN_FREQUENCY: f64 = 20f64;
+
+/// A feedback comb tuned to a note.
+struct Comb<T> {
+  delay: LinearDelay<T>,
+  feedback: T
+}
+
+/// A bank of feedback combs tuned to a chord, also known as a resonator
+/// chord.
+///
+/// Each comb is a delay line, of one period of its note, fed back on itself,
+/// so it rings at the note and each of its harmonics. The combs run in
+/// parallel on the input, and their sum is mixed with the dry signal. The
+/// decay sets how long each comb takes to fall by 60 dB, and the output of
+/// each comb is scaled so its resonances peak at unity gain, whatever the
+/// decay.
+///
+/// Notes are MIDI note numbers, which can be parsed from note names with
+/// `util::parse_note()`. Notes lower than 20 Hz are tuned to 20 Hz. Without
+/// any notes, the input is not altered.
+pub struct CombChorusBank<T> {
+  sample_rate: T,
+  notes: Vec<T>,
+  combs: Vec<Comb<T>>,
+  decay: T,
+  mix: T,
+  output: T
+}
+
+impl<T> CombChorusBank<T> where T: Float + FloatConst {
+  /// Creates a new `CombChorusBank` for signals at `sample_rate`, without any
+  /// notes, a decay of 1 second, and an even mix.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::CombChorusBank;
+  /// use rasp::traits::Processor;
+  /// use rasp::util;
+  ///
+  /// let chord: Vec<f32> = ["C3", "E3", "G3"].iter()
+  ///   .map(|name| util::parse_note(name).unwrap())
+  ///   .collect();
+  ///
+  /// let mut bank = CombChorusBank::new(44_100f32);
+  /// bank.set_notes(&chord);
+  /// bank.set_decay(2f32);
+  ///
+  /// let mut block = vec![0f32; 256];
+  /// block[0] = 1f32;
+  /// bank.process_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    CombChorusBank {
+      sample_rate,
+      notes: Vec::new(),
+      combs: Vec::new(),
+      decay: T::one(),
+      mix: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets the notes of the chord, as MIDI note numbers, clearing the combs.
+  pub fn set_notes(&mut self, notes: &[T]) {
+    let min_frequency: T = num::cast(MIN_FREQUENCY).unwrap();
+    let max_delay: usize = num::cast((self.sample_rate / min_frequency).ceil()).unwrap();
+    self.notes = notes.to_vec();
+    self.combs = notes.iter()
+      .map(|note| {
+        let frequency = util::midi_to_frequency(*note).max(min_frequency);
+        let period: f32 = num::cast(self.sample_rate / frequency).unwrap();
+        Comb {
+          delay: LinearDelay::new(period, max_delay),
+          feedback: num::zero()
+        }
+      })
+      .collect();
+    self.update_feedback();
+  }
+
+  /// Returns the notes of the chord, as MIDI note numbers.
+  pub fn get_notes(&self) -> &[T] {
+    &self.notes
+  }
+
+  /// Sets the time, in seconds, for the combs to decay by 60 dB.
+  ///
+  /// `decay` must be positive, else it is not updated.
+  pub fn set_decay(&mut self, decay: T) {
+    if decay > T::zero() && decay.is_finite() {
+      self.decay = decay;
+      self.update_feedback();
+    }
+  }
+
+  /// Returns the time, in seconds, for the combs to decay by 60 dB.
+  pub fn get_decay(&self) -> T {
+    self.decay
+  }
+
+  /// Sets the mix of the combs with the dry signal, from 0 for only the dry
+  /// signal to 1 for only the combs.
+  ///
+  /// `mix` must be within 0 and 1, else it is not updated.
+  pub fn set_mix(&mut self, mix: T) {
+    if mix >= T::zero() && mix <= T::one() {
+      self.mix = mix;
+    }
+  }
+
+  /// Returns the mix of the combs with the dry signal.
+  pub fn get_mix(&self) -> T {
+    self.mix
+  }
+
+  fn update_feedback(&mut self) {
+    // Each pass through a comb falls by its share of 60 dB
+    let sixty: T = num::cast(-60f64).unwrap();
+    for comb in self.combs.iter_mut() {
+      let period: T = num::cast(comb.delay.get_delay()).unwrap();
+      comb.feedback = util::to_sample(sixty * period / (self.decay * self.sample_rate));
+    }
+  }
+}
+
+impl<T> Processor<T> for CombChorusBank<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let wet = self.combs.iter_mut().fold(T::zero(), |sum, comb| {
+      let y = sample + comb.feedback * comb.delay.next_out();
+      comb.delay.process(y);
+      sum + y * (T::one() - comb.feedback)
+    });
+    let wet = if self.combs.is_empty() { sample } else { wet / num::cast(self.combs.len()).unwrap() };
+    self.output = sample + (wet - sample) * self.mix;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for comb in self.combs.iter_mut() {
+      comb.delay.clear();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::Processor;
+
+  /// Returns the steady state peak output of `bank` for a sine.
+  fn level(bank: &mut CombChorusBank<f64>, sample_rate: f64, frequency: f64) -> f64 {
+    bank.clear();
+    let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+    (0..sample_rate as usize)
+      .map(|n| bank.process((w * n as f64).sin()).abs())
+      .skip(sample_rate as usize / 2)
+      .fold(0f64, f64::max)
+  }
+
+  #[test]
+  fn resonates_at_notes() {
+    let sample_rate = 44_100f64;
+    let mut bank = CombChorusBank::new(sample_rate);
+    bank.set_notes(&[69f64]);
+    bank.set_mix(1f64);
+    bank.set_decay(0.5f64);
+
+    // The note and its harmonics ring at unity gain, other pitches are cut
+    assert!((level(&mut bank, sample_rate, 440f64) - 1f64).abs() < 0.05f64);
+    assert!((level(&mut bank, sample_rate, 880f64) - 1f64).abs() < 0.05f64);
+    assert!(level(&mut bank, sample_rate, 466.16f64) < 0.2f64);
+  }
+
+  #[test]
+  fn decay() {
+    let sample_rate = 8_000f64;
+    let mut bank = CombChorusBank::new(sample_rate);
+    bank.set_notes(&[57f64]);
+    bank.set_mix(1f64);
+    bank.set_decay(0.25f64);
+
+    // Rings on after a tone at the note stops
+    let w = 2f64 * ::std::f64::consts::PI * 220f64 / sample_rate;
+    let response: Vec<f64> = (0..8_000)
+      .map(|n| bank.process(if n < 4_000 { (w * n as f64).sin() } else { 0f64 }).abs())
+      .collect();
+    let start = response[4_000..4_100].iter().cloned().fold(0f64, f64::max);
+    let end = response[6_000..6_100].iter().cloned().fold(0f64, f64::max);
+    assert!((util::to_db(end / start) - -60f64).abs() < 3f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut bank = CombChorusBank::new(44_100f32);
+    assert!((bank.process(1f32) - 1f32).abs() < 1e-6f32);
+
+    bank.set_notes(&[60f32, 64f32]);
+    assert_eq!(bank.get_notes(), &[60f32, 64f32]);
+    bank.set_mix(2f32);
+    assert_eq!(bank.get_mix(), 0.5f32);
+    bank.set_decay(-1f32);
+    assert_eq!(bank.get_decay(), 1f32);
+
+    bank.set_mix(0f32);
+    assert!((bank.process(0.25f32) - 0.25f32).abs() < 1e-6f32);
+    bank.clear();
+    assert_eq!(bank.last_out(), 0f32);
+  }
+}
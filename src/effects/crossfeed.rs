@@ -0,0 +1,189 @@
+use num;
+use num::traits::Float;
+
+use delay::LinearDelay;
+use filter::rbj::{HighShelf, LowPass};
+use traits::{FloatConst, Processor, StereoProcessor};
+use util;
+
+/// A Bauer-style headphone crossfeed.
+///
+/// Each output channel is the sum of its own input and a low-passed, delayed,
+/// and attenuated copy of the opposite input, approximating the acoustic path
+/// from a loudspeaker to the far ear. The direct path is boosted by a
+/// high-shelf at the crossfeed cutoff, and the sum is normalized, so a signal
+/// common to both channels passes with a flat magnitude response.
+pub struct Crossfeed<T> {
+  direct_l: HighShelf<T>,
+  direct_r: HighShelf<T>,
+  lowpass_l: LowPass<T>,
+  lowpass_r: LowPass<T>,
+  delay_l: LinearDelay<T>,
+  delay_r: LinearDelay<T>,
+  // Linear gain of the crossfed signal
+  feed: T,
+  // Normalization gain applied to each output
+  gain: T,
+  output: (T, T)
+}
+
+impl<T> Crossfeed<T> where T: Float + FloatConst {
+  /// Creates a new `Crossfeed`.
+  ///
+  /// The crossfeed will be initialized in a state that does not alter the
+  /// input signal, until `set_coefficients()` is called.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::effects::Crossfeed;
+  ///
+  /// let mut crossfeed1: Crossfeed<f32> = Crossfeed::new();
+  /// let mut crossfeed2: Crossfeed<f64> = Crossfeed::new();
+  /// let mut crossfeed3 = Crossfeed::<f32>::new();
+  /// let mut crossfeed4 = Crossfeed::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    Crossfeed {
+      direct_l: HighShelf::new(),
+      direct_r: HighShelf::new(),
+      lowpass_l: LowPass::new(),
+      lowpass_r: LowPass::new(),
+      delay_l: LinearDelay::new(0f32, 0),
+      delay_r: LinearDelay::new(0f32, 0),
+      feed: num::zero(),
+      gain: num::one(),
+      output: (num::zero(), num::zero())
+    }
+  }
+
+  /// Sets the crossfeed parameters.
+  ///
+  /// `cutoff_frequency` is the corner of the crossfeed low-pass, `feed_db` is
+  /// the level of the crossfed signal relative to the direct signal, and
+  /// `delay_time` is the interaural delay in seconds. Classic settings are a
+  /// 700 Hz cutoff, -4.5 dB to -9.5 dB of feed, and a 0.3 millisecond delay.
+  /// These values are not validated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::Crossfeed;
+  ///
+  /// let mut crossfeed = Crossfeed::new();
+  /// crossfeed.set_coefficients(44_100f32, 700f32, -4.5f32, 0.0003f32);
+  /// ```
+  pub fn set_coefficients(&mut self,
+                          sample_rate: T,
+                          cutoff_frequency: T,
+                          feed_db: T,
+                          delay_time: T)
+  {
+    let one: T = T::one();
+    let q: T = num::cast(::std::f64::consts::FRAC_1_SQRT_2).unwrap();
+
+    self.feed = util::to_sample(feed_db);
+    self.gain = one / (one + self.feed);
+
+    let shelf_db = util::to_db(one + self.feed);
+    self.direct_l.set_coefficients(sample_rate, cutoff_frequency, shelf_db, one);
+    self.direct_r.set_coefficients(sample_rate, cutoff_frequency, shelf_db, one);
+    self.lowpass_l.set_coefficients(sample_rate, cutoff_frequency, q);
+    self.lowpass_r.set_coefficients(sample_rate, cutoff_frequency, q);
+
+    let delay: f32 = num::cast(delay_time * sample_rate).unwrap();
+    for delay_line in [&mut self.delay_l, &mut self.delay_r].iter_mut() {
+      delay_line.set_max_delay(delay.ceil() as usize + 1);
+      delay_line.set_delay(delay);
+    }
+
+    self.clear();
+  }
+}
+
+impl<T> StereoProcessor<T> for Crossfeed<T> where T: Float {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    // The crossfed signal for each ear comes from the opposite channel
+    let cross_l = self.delay_r.process(self.lowpass_r.process(right));
+    let cross_r = self.delay_l.process(self.lowpass_l.process(left));
+
+    let out_l = (self.direct_l.process(left)  + self.feed * cross_l) * self.gain;
+    let out_r = (self.direct_r.process(right) + self.feed * cross_r) * self.gain;
+    self.output = (out_l, out_r);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.direct_l.clear();
+    self.direct_r.clear();
+    self.lowpass_l.clear();
+    self.lowpass_r.clear();
+    self.delay_l.clear();
+    self.delay_r.clear();
+    self.output = (num::zero(), num::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+  use ::traits::StereoProcessor;
+
+  #[test]
+  fn new() {
+    let mut crossfeed = Crossfeed::new();
+    let (left, right) = crossfeed.process(0.5f32, -0.25f32);
+    assert!((left - 0.5f32).abs() < EPSILON);
+    assert!((right - -0.25f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn common_signal_is_unchanged_at_dc() {
+    let mut crossfeed = Crossfeed::new();
+    crossfeed.set_coefficients(44_100f32, 700f32, -4.5f32, 0.0003f32);
+
+    let mut output = (0f32, 0f32);
+    for _ in 0..4410 {
+      output = crossfeed.process(1f32, 1f32);
+    }
+    assert!((output.0 - 1f32).abs() < 1e-4f32);
+    assert!((output.1 - 1f32).abs() < 1e-4f32);
+  }
+
+  #[test]
+  fn crossfed_signal_is_delayed() {
+    let sample_rate = 44_100f32;
+    let delay_time = 0.0003f32;
+    let mut crossfeed = Crossfeed::new();
+    crossfeed.set_coefficients(sample_rate, 700f32, -4.5f32, delay_time);
+
+    let mut left  = vec![0f32; 64]; left[0] = 1f32;
+    let mut right = vec![0f32; 64];
+    crossfeed.process_block(&mut left, &mut right);
+
+    // Nothing reaches the opposite ear before the interaural delay
+    let delay_samples = (delay_time * sample_rate).floor() as usize;
+    for sample in right[..delay_samples].iter() {
+      assert!(sample.abs() < EPSILON);
+    }
+    assert!(right[delay_samples..].iter().any(|sample| sample.abs() > 1e-4f32));
+  }
+
+  #[test]
+  fn clear() {
+    let mut crossfeed = Crossfeed::new();
+    crossfeed.set_coefficients(44_100f32, 700f32, -4.5f32, 0.0003f32);
+    crossfeed.process(1f32, -1f32);
+    crossfeed.clear();
+
+    let (left, right) = crossfeed.last_out();
+    assert!((left - 0f32).abs() < EPSILON);
+    assert!((right - 0f32).abs() < EPSILON);
+  }
+}
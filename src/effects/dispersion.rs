@@ -0,0 +1,219 @@
+use num;
+use num::traits::Float;
+
+use filter::AllpassDelay;
+use traits::{FloatConst, Processor};
+
+/// A dispersion effect, smearing transients into chirps like those of a
+/// spring reverb.
+///
+/// The input passes through a long cascade of identical allpass stages, each
+/// an `AllpassDelay` of `stretch` samples, so the magnitude response stays
+/// flat while the delay varies with frequency. A click comes out as a tone
+/// sweeping through the spectrum, since each frequency arrives after its own
+/// delay. A positive amount delays low frequencies the most, for a falling
+/// chirp, and a negative amount delays high frequencies the most, for a
+/// rising chirp. Stretching the stages repeats the delay curve `stretch`
+/// times below Nyquist, for the repeated chirps of a spring.
+///
+/// Each stage delays by `stretch` samples on average, so the whole cascade
+/// delays by `stages * stretch` samples on average, and springs typically
+/// need around 100 stages.
+#[derive(Clone)]
+pub struct Dispersion<T> {
+  stages: Vec<AllpassDelay<T>>,
+  stretch: usize,
+  amount: T,
+  output: T
+}
+
+impl<T> Dispersion<T> where T: Float + FloatConst {
+  /// Creates a new `Dispersion` cascading `stages` allpass stages of
+  /// `stretch` samples, with an amount of one half.
+  ///
+  /// `stages` and `stretch` must be at least one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::Dispersion;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut dispersion = Dispersion::new(100, 2);
+  /// dispersion.set_amount(0.6f32);
+  ///
+  /// // Low frequencies arrive much later than high frequencies
+  /// let low = dispersion.group_delay(100f32, 44_100f32);
+  /// let high = dispersion.group_delay(10_000f32, 44_100f32);
+  /// assert!(low > 4f32 * high);
+  ///
+  /// let output = dispersion.process(1f32);
+  /// ```
+  pub fn new(stages: usize, stretch: usize) -> Self {
+    debug_assert!(stages >= 1 && stretch >= 1);
+    let mut dispersion = Dispersion {
+      stages: Vec::with_capacity(stages),
+      stretch,
+      amount: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    };
+    dispersion.set_stages(stages);
+    dispersion
+  }
+
+  /// Sets the number of allpass stages, which scales the spread of the
+  /// delays.
+  ///
+  /// `stages` must be at least one, else it is not updated. Added stages
+  /// start silent.
+  pub fn set_stages(&mut self, stages: usize) {
+    if stages >= 1 {
+      let (stretch, amount) = (self.stretch, self.amount);
+      self.stages.truncate(stages);
+      while self.stages.len() < stages {
+        let mut stage = AllpassDelay::new(stretch, stretch);
+        stage.set_gain(amount);
+        self.stages.push(stage);
+      }
+    }
+  }
+
+  /// Returns the number of allpass stages.
+  pub fn get_stages(&self) -> usize {
+    self.stages.len()
+  }
+
+  /// Returns the delay, in samples, of each allpass stage.
+  pub fn get_stretch(&self) -> usize {
+    self.stretch
+  }
+
+  /// Sets the coefficient of every stage, from -1 to 1, which sets how much
+  /// the delay varies with frequency, and which frequencies are delayed the
+  /// most.
+  ///
+  /// `amount` must be greater than -1 and less than 1, else it is not
+  /// updated. An amount of zero delays every frequency equally.
+  pub fn set_amount(&mut self, amount: T) {
+    if amount.abs() < T::one() {
+      self.amount = amount;
+      for stage in self.stages.iter_mut() {
+        stage.set_gain(amount);
+      }
+    }
+  }
+
+  /// Returns the coefficient of every stage.
+  pub fn get_amount(&self) -> T {
+    self.amount
+  }
+
+  /// Returns the delay, in samples, of the envelope of a tone at
+  /// `frequency`, in Hz, through every stage.
+  pub fn group_delay(&self, frequency: T, sample_rate: T) -> T {
+    let stretch: T = num::cast(self.stretch).unwrap();
+    let stages: T = num::cast(self.stages.len()).unwrap();
+    let (g, w) = (self.amount, T::two() * T::pi() * frequency / sample_rate);
+    let stage = stretch * (T::one() - g * g) / (T::one() - T::two() * g * (stretch * w).cos() + g * g);
+    stage * stages
+  }
+}
+
+impl<T> Processor<T> for Dispersion<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.output = self.stages.iter_mut().fold(sample, |sample, stage| stage.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for stage in self.stages.iter_mut() {
+      stage.clear();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the sample where a tone burst at `frequency` peaks through
+  /// `dispersion`.
+  fn arrival(dispersion: &mut Dispersion<f64>, frequency: f64) -> usize {
+    dispersion.clear();
+    let (length, burst) = (8_192, 512);
+    let mut envelope = 0f64;
+    let mut peak = (0, 0f64);
+    for n in 0..length {
+      let input =
+        if n < burst {
+          let window = 0.5f64 - 0.5f64 * (2f64 * ::std::f64::consts::PI * n as f64 / burst as f64).cos();
+          window * (2f64 * ::std::f64::consts::PI * frequency * n as f64 / 48_000f64).sin()
+        }
+        else {
+          0f64
+        };
+      let output = dispersion.process(input);
+      envelope = envelope.max(output.abs()) * 0.99f64;
+      if envelope > peak.1 {
+        peak = (n, envelope);
+      }
+    }
+    peak.0 - burst / 2
+  }
+
+  #[test]
+  fn chirp() {
+    let mut dispersion = Dispersion::new(64, 1);
+    dispersion.set_amount(0.7f64);
+    let (low, high) = (dispersion.group_delay(500f64, 48_000f64), dispersion.group_delay(12_000f64, 48_000f64));
+    assert!(low > 200f64 && high < 32f64);
+
+    // Tone bursts arrive after their group delay
+    for frequency in [500f64, 12_000f64].iter() {
+      let expected = dispersion.group_delay(*frequency, 48_000f64);
+      let measured = arrival(&mut dispersion, *frequency) as f64;
+      assert!((measured - expected).abs() < 0.1f64 * expected + 8f64);
+    }
+
+    // A negative amount reverses the chirp
+    dispersion.set_amount(-0.7f64);
+    assert!(dispersion.group_delay(12_000f64, 48_000f64) > dispersion.group_delay(500f64, 48_000f64));
+  }
+
+  #[test]
+  fn flat_magnitude() {
+    let mut dispersion = Dispersion::new(32, 3);
+    dispersion.set_amount(0.5f64);
+    let energy: f64 = (0..20_000)
+      .map(|n| dispersion.process(if n == 0 { 1f64 } else { 0f64 }).powi(2))
+      .sum();
+    assert!((energy - 1f64).abs() < 1e-6f64);
+
+    // Without any amount, the cascade is a plain delay
+    dispersion.set_amount(0f64);
+    dispersion.clear();
+    dispersion.set_stages(4);
+    let output: Vec<f64> = (0..16).map(|n| dispersion.process(if n == 0 { 1f64 } else { 0f64 })).collect();
+    assert_eq!(output[12], 1f64);
+    assert_eq!(dispersion.group_delay(1_000f64, 48_000f64), 12f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut dispersion = Dispersion::<f32>::new(8, 2);
+    dispersion.set_amount(1f32);
+    assert_eq!(dispersion.get_amount(), 0.5f32);
+    dispersion.set_stages(0);
+    assert_eq!(dispersion.get_stages(), 8);
+    dispersion.set_stages(12);
+    assert_eq!((dispersion.get_stages(), dispersion.get_stretch()), (12, 2));
+    dispersion.process(1f32);
+    dispersion.clear();
+    assert_eq!(dispersion.last_out(), 0f32);
+  }
+}
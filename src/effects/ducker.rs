@@ -0,0 +1,172 @@
+use num;
+use num::traits::Float;
+
+use analysis::PeakEnvDetector;
+use traits::Processor;
+
+/// A ducker of the wet path of an effect, keyed from its dry input.
+///
+/// The peak envelope of the dry signal attenuates the wet signal, so echoes
+/// and tails fall back while the dry signal plays, and swell in the gaps,
+/// keeping transients and words clear of their own reverb. The depth sets
+/// how far the wet signal falls for a full scale dry signal, and quieter
+/// dry signals duck in proportion to their level. The attack sets how fast
+/// the wet signal falls, and the release how fast it swells back.
+///
+/// `Reverb` and `Echo` duck their wet path with one, without any depth by
+/// default.
+pub struct Ducker<T> {
+  sample_rate: T,
+  detector: PeakEnvDetector<T>,
+  attack: T,
+  release: T,
+  depth: T,
+  gain: T
+}
+
+impl<T> Ducker<T> where T: Float {
+  /// Creates a new `Ducker` for signals at `sample_rate`, with an attack of
+  /// 10 milliseconds, a release of 250 milliseconds, and no depth.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::Ducker;
+  ///
+  /// let mut ducker = Ducker::new(44_100f32);
+  /// ducker.set_depth(0.8f32);
+  /// ducker.set_release(0.4f32);
+  ///
+  /// // A loud dry signal pushes the wet signal down
+  /// let mut wet = 1f32;
+  /// for _ in 0..4_410 {
+  ///   wet = ducker.process(1f32, 1f32);
+  /// }
+  /// assert!(wet < 0.25f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut ducker = Ducker {
+      sample_rate,
+      detector: PeakEnvDetector::new(),
+      attack: num::cast(0.01f64).unwrap(),
+      release: num::cast(0.25f64).unwrap(),
+      depth: num::zero(),
+      gain: T::one()
+    };
+    let (attack, release) = (ducker.attack, ducker.release);
+    ducker.set_attack(attack);
+    ducker.set_release(release);
+    ducker
+  }
+
+  /// Sets the time, in seconds, for the wet signal to fall as the dry
+  /// signal rises.
+  ///
+  /// `attack` must be positive, else it is not updated.
+  pub fn set_attack(&mut self, attack: T) {
+    if attack > T::zero() && attack.is_finite() {
+      self.attack = attack;
+      self.detector.set_attack(attack * self.sample_rate);
+    }
+  }
+
+  /// Returns the attack time, in seconds.
+  pub fn get_attack(&self) -> T {
+    self.attack
+  }
+
+  /// Sets the time, in seconds, for the wet signal to swell back as the dry
+  /// signal falls.
+  ///
+  /// `release` must be positive, else it is not updated.
+  pub fn set_release(&mut self, release: T) {
+    if release > T::zero() && release.is_finite() {
+      self.release = release;
+      self.detector.set_release(release * self.sample_rate);
+    }
+  }
+
+  /// Returns the release time, in seconds.
+  pub fn get_release(&self) -> T {
+    self.release
+  }
+
+  /// Sets how far the wet signal falls for a full scale dry signal, from 0
+  /// for no ducking to 1 for silence.
+  ///
+  /// `depth` must be within 0 and 1, else it is not updated.
+  pub fn set_depth(&mut self, depth: T) {
+    if depth >= T::zero() && depth <= T::one() {
+      self.depth = depth;
+    }
+  }
+
+  /// Returns how far the wet signal falls for a full scale dry signal.
+  pub fn get_depth(&self) -> T {
+    self.depth
+  }
+
+  /// Returns the last gain applied to the wet signal.
+  pub fn get_gain(&self) -> T {
+    self.gain
+  }
+
+  /// Returns the `wet` sample ducked by the envelope of the `dry` sample.
+  pub fn process(&mut self, dry: T, wet: T) -> T {
+    let envelope = self.detector.process(dry).min(T::one());
+    self.gain = T::one() - self.depth * envelope;
+    wet * self.gain
+  }
+
+  /// Clears the envelope of the dry signal.
+  pub fn clear(&mut self) {
+    self.detector.clear();
+    self.gain = T::one();
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ducking() {
+    let sample_rate = 1_000f64;
+    let mut ducker = Ducker::new(sample_rate);
+    ducker.set_depth(0.5f64);
+    ducker.set_attack(0.005f64);
+    ducker.set_release(0.1f64);
+
+    // The wet signal falls quickly while the dry signal plays
+    for _ in 0..50 {
+      ducker.process(1f64, 1f64);
+    }
+    assert!((ducker.get_gain() - 0.5f64).abs() < 1e-3f64);
+
+    // And swells back over the release
+    let released: Vec<f64> = (0..300).map(|_| ducker.process(0f64, 1f64)).collect();
+    assert!(released.windows(2).all(|pair| pair[1] >= pair[0]));
+    assert!(released[99] > 0.8f64 && released[99] < 0.85f64);
+    assert!(released[299] > 0.97f64);
+
+    // Quieter dry signals duck less
+    ducker.clear();
+    for _ in 0..50 {
+      ducker.process(-0.5f64, 1f64);
+    }
+    assert!((ducker.get_gain() - 0.75f64).abs() < 1e-3f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut ducker = Ducker::new(44_100f32);
+    assert_eq!(ducker.process(1f32, 0.5f32), 0.5f32);
+    ducker.set_attack(0f32);
+    ducker.set_release(::std::f32::INFINITY);
+    ducker.set_depth(1.5f32);
+    assert_eq!((ducker.get_attack(), ducker.get_release(), ducker.get_depth()), (0.01f32, 0.25f32, 0f32));
+    ducker.set_depth(1f32);
+    ducker.clear();
+    assert_eq!(ducker.get_gain(), 1f32);
+  }
+}
@@ -0,0 +1,288 @@
+use num;
+use num::traits::Float;
+
+use delay::Delay;
+use effects::Ducker;
+use filter::OnePole;
+use filter::design::Band;
+use render::ProcessContext;
+use traits::{FloatConst, Processor};
+
+/// A feedback echo.
+///
+/// The input is delayed and fed back on itself, so each echo repeats the last
+/// one, quieter by the feedback and darker by the damping, a lowpass inside
+/// the loop much like the losses of tape and analog delays. The echoes are
+/// mixed with the dry signal, and can be ducked by the dry signal, through
+/// `ducker_mut()`, so they fill the gaps between phrases rather than
+/// clouding them.
+///
+/// The time can instead be synced to a tempo, set in beats, and follows the
+/// tempo of the transport of each block processed with `process_context()`.
+pub struct Echo<T: Float> {
+  sample_rate: T,
+  line: Delay<T>,
+  filter: OnePole<T>,
+  ducker: Ducker<T>,
+  time: T,
+  sync: Option<T>,
+  feedback: T,
+  damping: T,
+  mix: T,
+  output: T
+}
+
+impl<T> Echo<T> where T: Float + FloatConst {
+  /// Creates a new `Echo` for signals at `sample_rate`, with echoes up to
+  /// `max_time` seconds apart, and a time of a quarter second, or
+  /// `max_time` if shorter, a feedback of 0.4, damping at 6 kHz, and an
+  /// even mix.
+  ///
+  /// `max_time` must be positive.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::Echo;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut echo = Echo::new(44_100f32, 2f32);
+  /// echo.set_time(0.375f32);
+  /// echo.set_feedback(0.6f32);
+  ///
+  /// // Pull the echoes back while playing
+  /// echo.ducker_mut().set_depth(0.8f32);
+  ///
+  /// let mut block = vec![0f32; 256];
+  /// block[0] = 1f32;
+  /// echo.process_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T, max_time: T) -> Self {
+    debug_assert!(max_time > T::zero());
+    let max_delay: usize = num::cast((max_time * sample_rate).ceil()).unwrap();
+    let mut echo = Echo {
+      sample_rate,
+      line: Delay::new(1, max_delay.max(1)),
+      filter: OnePole::new(),
+      ducker: Ducker::new(sample_rate),
+      time: max_time,
+      sync: None,
+      feedback: num::cast(0.4f64).unwrap(),
+      damping: num::zero(),
+      mix: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    };
+    let time = max_time.min(num::cast(0.25f64).unwrap());
+    echo.set_time(time);
+    let damping = sample_rate * num::cast(0.49f64).unwrap();
+    echo.set_damping(damping.min(num::cast(6_000f64).unwrap()));
+    echo
+  }
+
+  /// Sets the time, in seconds, between echoes.
+  ///
+  /// `time` must be positive, else it is not updated, and is clipped to the
+  /// maximum time.
+  pub fn set_time(&mut self, time: T) {
+    if time > T::zero() {
+      let max_delay: T = num::cast(self.line.get_max_delay()).unwrap();
+      let delay = (time * self.sample_rate).round().max(T::one()).min(max_delay);
+      self.line.set_delay(num::cast(delay).unwrap());
+      self.time = delay / self.sample_rate;
+    }
+  }
+
+  /// Returns the time, in seconds, between echoes, rounded to whole samples.
+  pub fn get_time(&self) -> T {
+    self.time
+  }
+
+  /// Sets the time between echoes in beats, such as 0.75 for a dotted
+  /// eighth note, or `None` to keep the time set in seconds.
+  ///
+  /// The time follows the tempo of the transport in the context of each
+  /// block, and is clipped to the maximum time. `beats` must be positive,
+  /// else the sync is not updated.
+  pub fn set_sync(&mut self, beats: Option<T>) {
+    match beats {
+      Some(beats) if !(beats > T::zero() && beats.is_finite()) => {},
+      _ => self.sync = beats
+    }
+  }
+
+  /// Returns the time between echoes in beats, if synced to a tempo.
+  pub fn get_sync(&self) -> Option<T> {
+    self.sync
+  }
+
+  /// Sets the gain of each echo relative to the last.
+  ///
+  /// `feedback` must be at least zero and less than one, else it is not
+  /// updated.
+  pub fn set_feedback(&mut self, feedback: T) {
+    if feedback >= T::zero() && feedback < T::one() {
+      self.feedback = feedback;
+    }
+  }
+
+  /// Returns the gain of each echo relative to the last.
+  pub fn get_feedback(&self) -> T {
+    self.feedback
+  }
+
+  /// Sets the cutoff frequency, in Hz, of the lowpass darkening each echo.
+  ///
+  /// `damping` must be positive and below Nyquist, else it is not updated.
+  pub fn set_damping(&mut self, damping: T) {
+    if damping > T::zero() && damping < self.sample_rate / T::two() {
+      self.damping = damping;
+      self.filter.set_cutoff(Band::LowPass, self.sample_rate, damping);
+    }
+  }
+
+  /// Returns the cutoff frequency, in Hz, of the lowpass darkening each
+  /// echo.
+  pub fn get_damping(&self) -> T {
+    self.damping
+  }
+
+  /// Sets the mix of the echoes with the dry signal, from 0 for only the dry
+  /// signal to 1 for only the echoes.
+  ///
+  /// `mix` must be within 0 and 1, else it is not updated.
+  pub fn set_mix(&mut self, mix: T) {
+    if mix >= T::zero() && mix <= T::one() {
+      self.mix = mix;
+    }
+  }
+
+  /// Returns the mix of the echoes with the dry signal.
+  pub fn get_mix(&self) -> T {
+    self.mix
+  }
+
+  /// Returns the ducker of the echoes, keyed from the dry signal.
+  pub fn ducker(&self) -> &Ducker<T> {
+    &self.ducker
+  }
+
+  /// Returns the ducker of the echoes to set its depth, attack and release.
+  pub fn ducker_mut(&mut self) -> &mut Ducker<T> {
+    &mut self.ducker
+  }
+}
+
+impl<T> Processor<T> for Echo<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let delayed = self.line.next_out();
+    let feedback = self.filter.process(delayed) * self.feedback;
+    self.line.process(sample + feedback);
+    let wet = self.ducker.process(sample, delayed);
+    self.output = sample + (wet - sample) * self.mix;
+    self.output
+  }
+
+  fn process_context(&mut self, samples: &mut [T], context: &ProcessContext<T>) -> T {
+    if let (Some(beats), Some(transport)) = (self.sync, context.transport) {
+      let seconds: T = num::cast(60f64).unwrap();
+      self.set_time(beats * seconds / transport.get_tempo());
+    }
+    if context.bypass {
+      self.last_out()
+    }
+    else {
+      self.process_block(samples)
+    }
+  }
+
+  fn clear(&mut self) {
+    self.line.clear();
+    self.filter.clear();
+    self.ducker.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use transport::Transport;
+
+  #[test]
+  fn echoes() {
+    let mut echo = Echo::new(1_000f64, 1f64);
+    echo.set_mix(1f64);
+    echo.set_time(0.1f64);
+    echo.set_feedback(0.5f64);
+    echo.set_damping(499f64);
+    let output: Vec<f64> = (0..400).map(|n| echo.process(if n == 0 { 1f64 } else { 0f64 })).collect();
+    assert!((output[100] - 1f64).abs() < 1e-12f64);
+    let second: f64 = output[190..210].iter().sum();
+    let third: f64 = output[290..310].iter().sum();
+    assert!((second - 0.5f64).abs() < 1e-3f64);
+    assert!((third - 0.25f64).abs() < 1e-3f64);
+  }
+
+  #[test]
+  fn ducking() {
+    // Echoes fall back under a dry signal
+    let mut echo = Echo::new(1_000f64, 1f64);
+    echo.set_mix(1f64);
+    echo.set_time(0.05f64);
+    echo.ducker_mut().set_depth(1f64);
+    echo.ducker_mut().set_attack(0.001f64);
+    let output: Vec<f64> = (0..200).map(|_| echo.process(1f64)).collect();
+    assert!(output[150].abs() < 0.05f64);
+  }
+
+  #[test]
+  fn sync() {
+    // A synced echo follows the tempo of the transport in the context
+    let mut echo = Echo::new(1_000f64, 2f64);
+    echo.set_sync(Some(0.5f64));
+    let mut transport = Transport::new(1_000f64);
+    let mut block = vec![0f64; 8];
+    let mut context = ProcessContext::new(1_000f64, block.len());
+    context.transport = Some(&transport);
+    echo.process_context(&mut block, &context);
+    assert_eq!(echo.get_time(), 0.25f64);
+
+    transport.set_tempo(40f64);
+    let mut context = ProcessContext::new(1_000f64, block.len());
+    context.transport = Some(&transport);
+    echo.process_context(&mut block, &context);
+    assert_eq!(echo.get_time(), 0.75f64);
+
+    // Without a transport, or sync, the time is kept
+    echo.set_sync(Some(-1f64));
+    assert_eq!(echo.get_sync(), Some(0.5f64));
+    echo.set_sync(None);
+    echo.set_time(0.1f64);
+    echo.process_context(&mut block, &context);
+    assert_eq!((echo.get_sync(), echo.get_time()), (None, 0.1f64));
+  }
+
+  #[test]
+  fn parameters() {
+    let mut echo = Echo::new(44_100f32, 0.1f32);
+    assert_eq!((echo.get_time(), echo.get_feedback(), echo.get_mix()), (0.1f32, 0.4f32, 0.5f32));
+    echo.set_time(1f32);
+    assert_eq!(echo.get_time(), 0.1f32);
+    echo.set_time(0f32);
+    echo.set_feedback(1f32);
+    echo.set_damping(30_000f32);
+    echo.set_mix(2f32);
+    assert_eq!((echo.get_time(), echo.get_feedback()), (0.1f32, 0.4f32));
+    assert_eq!((echo.get_damping(), echo.get_mix()), (6_000f32, 0.5f32));
+
+    // Without any mix, the input is not altered
+    echo.set_mix(0f32);
+    assert_eq!(echo.process(0.25f32), 0.25f32);
+    echo.clear();
+    assert_eq!(echo.last_out(), 0f32);
+  }
+}
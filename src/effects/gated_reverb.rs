@@ -0,0 +1,254 @@
+use num;
+use num::traits::Float;
+
+use analysis::PeakEnvDetector;
+use effects::Reverb;
+use traits::{FloatConst, Processor};
+use util;
+
+/// A gated reverb, whose dense tail is cut off shortly after the dry signal
+/// falls silent.
+///
+/// The classic drum sound of the eighties: a large, bright `Reverb` behind a
+/// gate keyed from the dry input rather than the reverb itself, so the tail
+/// stays open at full level while the dry signal is above the threshold,
+/// holds for the hold time after it falls below, then closes over the
+/// release time. The gate opens at once with each hit, and closes linearly,
+/// so the tail ends abruptly instead of fading away.
+///
+/// The reverb can be reached through `reverb_mut()` to set its size, decay
+/// and damping, while the mix of the whole effect is set here.
+pub struct GatedReverb<T: Float> {
+  sample_rate: T,
+  reverb: Reverb<T>,
+  detector: PeakEnvDetector<T>,
+  threshold: T,
+  hold: T,
+  release: T,
+  held: usize,
+  gain: T,
+  mix: T,
+  output: T
+}
+
+impl<T> GatedReverb<T> where T: Float + FloatConst {
+  /// Creates a new `GatedReverb` for signals at `sample_rate`, with a
+  /// threshold of -30 dB, a hold of 250 milliseconds, a release of 50
+  /// milliseconds, a large room with a decay of 3 seconds, and an even mix.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::GatedReverb;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut gated = GatedReverb::new(44_100f32);
+  /// gated.set_threshold(-24f32);
+  /// gated.set_hold(0.3f32);
+  /// gated.set_release(0.02f32);
+  /// gated.reverb_mut().set_size(1.8f32);
+  ///
+  /// let mut block = vec![0f32; 256];
+  /// block[0] = 1f32;
+  /// gated.process_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut reverb = Reverb::new(sample_rate);
+    reverb.set_mix(T::one());
+    reverb.set_size(num::cast(1.5f64).unwrap());
+    reverb.set_decay(num::cast(3f64).unwrap());
+
+    // The detector follows the peaks of the dry signal, over about a cycle
+    // of its lowest notes
+    let mut detector = PeakEnvDetector::new();
+    detector.set_attack(T::one());
+    detector.set_release(sample_rate * num::cast(0.02f64).unwrap());
+    GatedReverb {
+      sample_rate,
+      reverb,
+      detector,
+      threshold: num::cast(-30f64).unwrap(),
+      hold: num::cast(0.25f64).unwrap(),
+      release: num::cast(0.05f64).unwrap(),
+      held: 0,
+      gain: num::zero(),
+      mix: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    }
+  }
+
+  /// Returns the reverb, whose mix is kept at one.
+  pub fn reverb(&self) -> &Reverb<T> {
+    &self.reverb
+  }
+
+  /// Returns the reverb to set its size, decay and damping.
+  ///
+  /// Its mix should be left at one, since the dry signal is mixed by the
+  /// gated reverb.
+  pub fn reverb_mut(&mut self) -> &mut Reverb<T> {
+    &mut self.reverb
+  }
+
+  /// Sets the level, in dB, above which the dry signal opens the gate.
+  ///
+  /// `threshold` must be finite, else it is not updated.
+  pub fn set_threshold(&mut self, threshold: T) {
+    if threshold.is_finite() {
+      self.threshold = threshold;
+    }
+  }
+
+  /// Returns the level, in dB, above which the dry signal opens the gate.
+  pub fn get_threshold(&self) -> T {
+    self.threshold
+  }
+
+  /// Sets the time, in seconds, the gate stays open after the dry signal
+  /// falls below the threshold.
+  ///
+  /// `hold` must be at least zero, else it is not updated.
+  pub fn set_hold(&mut self, hold: T) {
+    if hold >= T::zero() && hold.is_finite() {
+      self.hold = hold;
+    }
+  }
+
+  /// Returns the hold time, in seconds.
+  pub fn get_hold(&self) -> T {
+    self.hold
+  }
+
+  /// Sets the time, in seconds, for the gate to close after the hold.
+  ///
+  /// `release` must be positive, else it is not updated.
+  pub fn set_release(&mut self, release: T) {
+    if release > T::zero() && release.is_finite() {
+      self.release = release;
+    }
+  }
+
+  /// Returns the release time, in seconds.
+  pub fn get_release(&self) -> T {
+    self.release
+  }
+
+  /// Sets the mix of the gated reverb with the dry signal, from 0 for only
+  /// the dry signal to 1 for only the gated reverb.
+  ///
+  /// `mix` must be within 0 and 1, else it is not updated.
+  pub fn set_mix(&mut self, mix: T) {
+    if mix >= T::zero() && mix <= T::one() {
+      self.mix = mix;
+    }
+  }
+
+  /// Returns the mix of the gated reverb with the dry signal.
+  pub fn get_mix(&self) -> T {
+    self.mix
+  }
+
+  /// Returns the gain of the gate, from 0 when closed to 1 when open.
+  pub fn get_gain(&self) -> T {
+    self.gain
+  }
+}
+
+impl<T> Processor<T> for GatedReverb<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let envelope = self.detector.process(sample);
+    if util::to_db(envelope) >= self.threshold {
+      self.held = num::cast((self.hold * self.sample_rate).round()).unwrap();
+      self.gain = T::one();
+    }
+    else if self.held > 0 {
+      self.held -= 1;
+    }
+    else {
+      self.gain = (self.gain - T::one() / (self.release * self.sample_rate)).max(T::zero());
+    }
+
+    let wet = self.reverb.process(sample) * self.gain;
+    self.output = sample + (wet - sample) * self.mix;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.reverb.clear();
+    self.detector.clear();
+    self.held = 0;
+    self.gain = T::zero();
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn gate() {
+    let sample_rate = 1_000f64;
+    let mut gated = GatedReverb::new(sample_rate);
+    gated.set_mix(1f64);
+    gated.set_hold(0.1f64);
+    gated.set_release(0.05f64);
+
+    // A short burst opens the gate, which holds, then closes over the release
+    let output: Vec<f64> = (0..1_000)
+      .map(|n| gated.process(if n < 20 { (0.5f64 * n as f64).sin() } else { 0f64 }))
+      .collect();
+    let energy = |range: ::std::ops::Range<usize>| output[range].iter().map(|sample| sample * sample).sum::<f64>();
+    assert!(energy(60..120) > 0f64);
+    assert!(energy(300..1_000) == 0f64);
+    assert_eq!(gated.get_gain(), 0f64);
+
+    // The tail of an ungated reverb is still ringing then
+    let mut reverb = Reverb::new(sample_rate);
+    reverb.set_mix(1f64);
+    reverb.set_size(1.5f64);
+    reverb.set_decay(3f64);
+    let tail: f64 = (0..1_000)
+      .map(|n| reverb.process(if n < 20 { (0.5f64 * n as f64).sin() } else { 0f64 }))
+      .skip(300)
+      .map(|sample| sample * sample)
+      .sum();
+    assert!(tail > 0.1f64 * energy(60..120));
+  }
+
+  #[test]
+  fn keyed_from_dry() {
+    // The gate stays open while the dry signal plays, however long it rings
+    let mut gated = GatedReverb::new(1_000f64);
+    gated.set_hold(0f64);
+    let mut gains = Vec::new();
+    for n in 0..2_000 {
+      gated.process(if n < 1_500 { (0.5f64 * n as f64).cos() } else { 0f64 });
+      gains.push(gated.get_gain());
+    }
+    assert!(gains[..1_500].iter().all(|gain| *gain == 1f64));
+    assert_eq!(gains[1_999], 0f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut gated = GatedReverb::new(44_100f32);
+    gated.set_threshold(::std::f32::NAN);
+    gated.set_hold(-1f32);
+    gated.set_release(0f32);
+    gated.set_mix(2f32);
+    assert_eq!((gated.get_threshold(), gated.get_hold()), (-30f32, 0.25f32));
+    assert_eq!((gated.get_release(), gated.get_mix()), (0.05f32, 0.5f32));
+    assert_eq!((gated.reverb().get_decay(), gated.reverb().get_mix()), (3f32, 1f32));
+
+    // Without any mix, the input is not altered
+    gated.set_mix(0f32);
+    assert_eq!(gated.process(0.25f32), 0.25f32);
+    gated.clear();
+    assert_eq!((gated.last_out(), gated.get_gain()), (0f32, 0f32));
+  }
+}
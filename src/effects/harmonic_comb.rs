@@ -0,0 +1,276 @@
+use num;
+use num::traits::Float;
+
+use analysis::PitchDetector;
+use delay::LinearDelay;
+use traits::{Analyzer, FloatConst, Processor};
+
+/// The largest feedback of the comb when emphasizing harmonics, at full
+/// depth.
+const MAX_FEEDBACK: f64 = 0.95f64;
+
+/// What a `HarmonicComb` does to the harmonics of its input.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum HarmonicMode {
+  /// Resonates at the harmonics with a feedback comb, normalized to unity
+  /// gain at the harmonics, attenuating everything between them.
+  Emphasize,
+  /// Notches the harmonics with a feedforward comb, normalized to unity
+  /// gain between them.
+  Suppress
+}
+
+/// A comb filter tuned to the pitch of its input.
+///
+/// A `PitchDetector` tracks the fundamental of a monophonic source, and the
+/// delay of the comb follows its period through a fractional `LinearDelay`,
+/// so the peaks or notches of the comb stay on the harmonics as the pitch
+/// moves. Emphasizing the harmonics brings a voice or instrument forward
+/// from noise and reverberation, while suppressing them removes the source
+/// and keeps everything else, such as for a de-harmonizer.
+///
+/// The delay glides to each new period to avoid zipper noise, and holds the
+/// last period while the input is unvoiced. Until the first pitch is
+/// detected, the input passes unaltered. The detection lags the input by up
+/// to twice the longest period.
+pub struct HarmonicComb<T> {
+  sample_rate: T,
+  detector: PitchDetector<T>,
+  delay: LinearDelay<T>,
+  mode: HarmonicMode,
+  depth: T,
+  glide: T,
+  glide_coefficient: T,
+  // The delay of the comb, in samples, once a pitch has been detected
+  period: Option<T>,
+  output: T
+}
+
+impl<T> HarmonicComb<T> where T: Float + FloatConst {
+  /// Creates a new `HarmonicComb` for signals at `sample_rate`, tracking
+  /// pitches from `min_frequency` to `max_frequency`, in Hz, emphasizing
+  /// harmonics at full depth, with a glide of 10 milliseconds.
+  ///
+  /// The frequencies must be valid for a `PitchDetector`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::{HarmonicComb, HarmonicMode};
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut comb = HarmonicComb::new(44_100f32, 80f32, 1_000f32);
+  /// comb.set_mode(HarmonicMode::Suppress);
+  /// comb.set_depth(0.8f32);
+  ///
+  /// let mut block: Vec<f32> = (0..4_096).map(|n| (0.05f32 * n as f32).sin()).collect();
+  /// comb.process_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T, min_frequency: T, max_frequency: T) -> Self {
+    let max_delay: usize = num::cast((sample_rate / min_frequency).ceil()).unwrap();
+    let mut comb = HarmonicComb {
+      sample_rate,
+      detector: PitchDetector::new(sample_rate, min_frequency, max_frequency),
+      delay: LinearDelay::new(0f32, max_delay + 2),
+      mode: HarmonicMode::Emphasize,
+      depth: T::one(),
+      glide: T::zero(),
+      glide_coefficient: T::zero(),
+      period: None,
+      output: num::zero()
+    };
+    comb.set_glide(num::cast(0.01f64).unwrap());
+    comb
+  }
+
+  /// Returns the pitch detector, to read the detected pitch.
+  pub fn detector(&self) -> &PitchDetector<T> {
+    &self.detector
+  }
+
+  /// Returns the pitch detector, to set its threshold.
+  pub fn detector_mut(&mut self) -> &mut PitchDetector<T> {
+    &mut self.detector
+  }
+
+  /// Sets whether the harmonics are emphasized or suppressed, clearing the
+  /// comb when the mode changes.
+  pub fn set_mode(&mut self, mode: HarmonicMode) {
+    if mode != self.mode {
+      self.mode = mode;
+      self.delay.clear();
+    }
+  }
+
+  /// Returns whether the harmonics are emphasized or suppressed.
+  pub fn get_mode(&self) -> HarmonicMode {
+    self.mode
+  }
+
+  /// Sets the depth of the comb, from 0, which leaves the input unaltered,
+  /// to 1, which notches the harmonics entirely when suppressing them.
+  ///
+  /// `depth` must be within 0 and 1, else it is not updated.
+  pub fn set_depth(&mut self, depth: T) {
+    if depth >= T::zero() && depth <= T::one() {
+      self.depth = depth;
+    }
+  }
+
+  /// Returns the depth of the comb.
+  pub fn get_depth(&self) -> T {
+    self.depth
+  }
+
+  /// Sets the time, in seconds, for the delay to glide to a new period.
+  ///
+  /// `glide` must be at least zero, else it is not updated. Without any
+  /// glide, the delay jumps to each new period.
+  pub fn set_glide(&mut self, glide: T) {
+    if glide >= T::zero() && glide.is_finite() {
+      self.glide = glide;
+      self.glide_coefficient =
+        if glide > T::zero() {
+          (-T::one() / (glide * self.sample_rate)).exp()
+        }
+        else {
+          T::zero()
+        };
+    }
+  }
+
+  /// Returns the glide time, in seconds.
+  pub fn get_glide(&self) -> T {
+    self.glide
+  }
+
+  /// Returns the frequency, in Hz, the comb is currently tuned to, or `None`
+  /// until the first pitch is detected.
+  pub fn get_frequency(&self) -> Option<T> {
+    self.period.map(|period| self.sample_rate / period)
+  }
+}
+
+impl<T> Processor<T> for HarmonicComb<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.detector.process(sample);
+    if let Some(frequency) = self.detector.frequency() {
+      let target = self.sample_rate / frequency;
+      self.period = Some(match self.period {
+        Some(period) => target + (period - target) * self.glide_coefficient,
+        None => target
+      });
+    }
+
+    let period = match self.period {
+      Some(period) => period,
+      None => {
+        self.delay.process(sample);
+        self.output = sample;
+        return self.output;
+      }
+    };
+    self.delay.set_delay(num::cast(period).unwrap());
+    let delayed = self.delay.next_out();
+    self.output =
+      match self.mode {
+        HarmonicMode::Emphasize => {
+          let feedback = self.depth * num::cast(MAX_FEEDBACK).unwrap();
+          let resonance = sample + feedback * delayed;
+          self.delay.process(resonance);
+          resonance * (T::one() - feedback)
+        },
+        HarmonicMode::Suppress => {
+          self.delay.process(sample);
+          (sample - self.depth * delayed) / (T::one() + self.depth)
+        }
+      };
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.detector.clear();
+    self.delay.clear();
+    self.period = None;
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the amplitude of `frequency` in `signal` at `sample_rate`.
+  fn amplitude(signal: &[f64], frequency: f64, sample_rate: f64) -> f64 {
+    let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+    let (re, im) = signal.iter().enumerate().fold((0f64, 0f64), |(re, im), (n, sample)| {
+      (re + sample * (w * n as f64).cos(), im - sample * (w * n as f64).sin())
+    });
+    2f64 * (re * re + im * im).sqrt() / signal.len() as f64
+  }
+
+  /// Processes a sawtooth at 200 Hz, with an inharmonic tone, through
+  /// `comb`, returning the second half of the output.
+  fn process(comb: &mut HarmonicComb<f64>) -> (Vec<f64>, Vec<f64>) {
+    let sample_rate = 16_000f64;
+    let inharmonic = 200f64 * 2f64.sqrt();
+    let input: Vec<f64> = (0..32_000).map(|n| {
+      let t = n as f64 / sample_rate;
+      0.5f64 * (2f64 * (200f64 * t).fract() - 1f64) + 0.05f64 * (2f64 * ::std::f64::consts::PI * inharmonic * t).sin()
+    }).collect();
+    let output: Vec<f64> = input.iter().map(|sample| comb.process(*sample)).collect();
+    (input[16_000..].to_vec(), output[16_000..].to_vec())
+  }
+
+  #[test]
+  fn emphasize() {
+    let mut comb = HarmonicComb::new(16_000f64, 60f64, 1_000f64);
+    let (input, output) = process(&mut comb);
+    assert!((comb.get_frequency().unwrap() / 200f64 - 1f64).abs() < 1e-3f64);
+
+    // The harmonics are kept, and the inharmonic tone attenuated
+    let inharmonic = 200f64 * 2f64.sqrt();
+    for harmonic in [200f64, 400f64, 600f64].iter() {
+      let gain = amplitude(&output, *harmonic, 16_000f64) / amplitude(&input, *harmonic, 16_000f64);
+      assert!((gain - 1f64).abs() < 0.1f64);
+    }
+    let gain = amplitude(&output, inharmonic, 16_000f64) / amplitude(&input, inharmonic, 16_000f64);
+    assert!(gain < 0.1f64);
+  }
+
+  #[test]
+  fn suppress() {
+    let mut comb = HarmonicComb::new(16_000f64, 60f64, 1_000f64);
+    comb.set_mode(HarmonicMode::Suppress);
+    let (input, output) = process(&mut comb);
+
+    // The harmonics are removed, and the inharmonic tone kept
+    let inharmonic = 200f64 * 2f64.sqrt();
+    for harmonic in [200f64, 400f64, 600f64].iter() {
+      let gain = amplitude(&output, *harmonic, 16_000f64) / amplitude(&input, *harmonic, 16_000f64);
+      assert!(gain < 0.05f64);
+    }
+    let gain = amplitude(&output, inharmonic, 16_000f64) / amplitude(&input, inharmonic, 16_000f64);
+    assert!(gain > 0.8f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut comb = HarmonicComb::new(44_100f32, 80f32, 1_000f32);
+    comb.set_depth(2f32);
+    comb.set_glide(-1f32);
+    assert_eq!((comb.get_depth(), comb.get_glide(), comb.get_mode()), (1f32, 0.01f32, HarmonicMode::Emphasize));
+    assert_eq!(comb.detector().get_threshold(), 0.15f32);
+    comb.detector_mut().set_threshold(0.2f32);
+
+    // Until a pitch is detected, the input passes unaltered
+    assert_eq!(comb.process(0.25f32), 0.25f32);
+    assert_eq!(comb.get_frequency(), None);
+    comb.clear();
+    assert_eq!(comb.last_out(), 0f32);
+  }
+}
@@ -0,0 +1,180 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use filter::{FastConvolver, ParametricEq};
+use traits::{FloatConst, FrequencyResponse, Processor};
+use window::{apply_window, Window};
+
+/// A linear phase equalizer.
+///
+/// The magnitude response of a `ParametricEq` is sampled densely, and its
+/// inverse transform, centered and shaped by a Blackman window, is the kernel
+/// of a symmetric FIR filter, which is run by a `FastConvolver`. Every
+/// frequency is delayed by the same amount, so the bands shape the spectrum
+/// without the phase shifts of the minimum phase biquads, at the cost of
+/// latency and pre-ringing around sharp features.
+///
+/// Longer kernels resolve narrower and lower bands, and the latency is half
+/// the kernel plus the block size of the convolution.
+pub struct LinearPhaseEq<T> {
+  kernel: Vec<T>,
+  convolver: FastConvolver<T>
+}
+
+impl<T> LinearPhaseEq<T> where T: Float + FloatConst {
+  /// Creates a new `LinearPhaseEq` with the magnitude response of `eq`, as a
+  /// kernel of `taps` samples, convolved in blocks of `block_size` samples.
+  ///
+  /// `taps` must be odd, and at least 3, so the kernel has a center sample,
+  /// and `block_size` must be at least one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::LinearPhaseEq;
+  /// use rasp::filter::{EqBandType, ParametricEq};
+  /// use rasp::traits::{FrequencyResponse, Processor};
+  ///
+  /// let mut bands = ParametricEq::new(48_000f32);
+  /// bands.push_band(EqBandType::Peak, 1_000f32, 6f32, 1f32);
+  /// bands.push_band(EqBandType::HighShelf, 8_000f32, -3f32, 1f32);
+  ///
+  /// let mut eq = LinearPhaseEq::new(&bands, 2_047, 256);
+  /// assert_eq!(eq.latency(), 1_023 + 256);
+  ///
+  /// let gain = eq.magnitude_at(1_000f32, 48_000f32);
+  /// assert!((gain - bands.magnitude_at(1_000f32, 48_000f32)).abs() < 0.01f32);
+  ///
+  /// let output = eq.process(0.5f32);
+  /// ```
+  pub fn new(eq: &ParametricEq<T>, taps: usize, block_size: usize) -> Self {
+    let kernel = design(eq, taps);
+    LinearPhaseEq {
+      convolver: FastConvolver::new(&kernel, block_size),
+      kernel
+    }
+  }
+
+  /// Redesigns the kernel from the magnitude response of `eq`, keeping its
+  /// length.
+  ///
+  /// The convolution restarts, so the output is silent for the latency.
+  pub fn set_eq(&mut self, eq: &ParametricEq<T>) {
+    self.kernel = design(eq, self.kernel.len());
+    self.convolver.set_taps(&self.kernel);
+  }
+
+  /// Returns the kernel.
+  pub fn kernel(&self) -> &[T] {
+    &self.kernel
+  }
+
+  /// Returns the delay of the output, in samples, of every frequency.
+  pub fn latency(&self) -> usize {
+    self.kernel.len() / 2 + self.convolver.latency()
+  }
+}
+
+/// Designs a linear phase kernel of `taps` samples with the magnitude
+/// response of `eq`.
+fn design<T: Float + FloatConst>(eq: &ParametricEq<T>, taps: usize) -> Vec<T> {
+  debug_assert!(taps % 2 == 1 && taps >= 3);
+  let size = (4 * taps).next_power_of_two();
+  let sample_rate = eq.get_sample_rate();
+  let bin_width = sample_rate / num::cast(size).unwrap();
+
+  // The zero phase spectrum is real and even, so its inverse transform is
+  // real and symmetric around the first sample
+  let mut spectrum: Vec<Complex<T>> = (0..size)
+    .map(|k| {
+      let bin = if k <= size / 2 { k } else { size - k };
+      let frequency = bin_width * num::cast(bin).unwrap();
+      Complex::new(eq.magnitude_at(frequency, sample_rate), T::zero())
+    })
+    .collect();
+  fft::inverse(&mut spectrum);
+
+  let center = taps / 2;
+  let mut kernel: Vec<T> = (0..taps)
+    .map(|n| spectrum[(n + size - center) % size].re)
+    .collect();
+  apply_window(&mut kernel, Window::Blackman);
+  kernel
+}
+
+impl<T> Processor<T> for LinearPhaseEq<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.convolver.process(sample)
+  }
+
+  fn clear(&mut self) {
+    self.convolver.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.convolver.last_out()
+  }
+}
+
+impl<T> FrequencyResponse<T> for LinearPhaseEq<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    let w = T::two() * T::pi() * frequency / sample_rate;
+    self.kernel.iter()
+      .enumerate()
+      .fold(Complex::new(T::zero(), T::zero()), |sum, (n, tap)| {
+        let phase = -w * num::cast(n).unwrap();
+        sum + Complex::from_polar(tap, &phase)
+      })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::EqBandType;
+  use util;
+
+  fn bands() -> ParametricEq<f64> {
+    let mut bands = ParametricEq::new(48_000f64);
+    bands.push_band(EqBandType::LowShelf, 200f64, 4f64, 1f64);
+    bands.push_band(EqBandType::Peak, 2_000f64, -6f64, 2f64);
+    bands.push_band(EqBandType::HighShelf, 10_000f64, 3f64, 1f64);
+    bands
+  }
+
+  #[test]
+  fn response() {
+    let bands = bands();
+    let eq = LinearPhaseEq::new(&bands, 4_095, 512);
+
+    // The magnitude follows the bands, and every frequency is delayed by
+    // half the kernel
+    for frequency in [100f64, 500f64, 2_000f64, 5_000f64, 15_000f64].iter() {
+      let expected = util::to_db(bands.magnitude_at(*frequency, 48_000f64));
+      assert!((util::to_db(eq.magnitude_at(*frequency, 48_000f64)) - expected).abs() < 0.1f64);
+    }
+    let kernel = eq.kernel();
+    assert!((0..kernel.len() / 2).all(|n| (kernel[n] - kernel[kernel.len() - 1 - n]).abs() < 1e-12f64));
+    assert_eq!(eq.latency(), 2_047 + 512);
+  }
+
+  #[test]
+  fn process() {
+    let mut eq = LinearPhaseEq::new(&ParametricEq::new(48_000f64), 255, 64);
+    let mut block = vec![0f64; 512];
+    block[0] = 1f64;
+    eq.process_block(&mut block);
+
+    // Without bands, the kernel is a delayed impulse
+    let latency = eq.latency();
+    assert!((block[latency] - 1f64).abs() < 1e-9f64);
+    assert!(block.iter().enumerate().all(|(n, sample)| n == latency || sample.abs() < 1e-9f64));
+
+    eq.set_eq(&bands());
+    assert!((util::to_db(eq.magnitude_at(2_000f64, 48_000f64)) + 6f64).abs() < 1f64);
+    eq.clear();
+    assert_eq!(eq.last_out(), 0f64);
+  }
+}
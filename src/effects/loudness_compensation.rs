@@ -0,0 +1,224 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::Biquad2;
+use filter::rbj::{HighShelf, LowShelf};
+use traits::{FloatConst, FrequencyResponse, Processor};
+
+/// The parameters of the ISO 226:2003 equal-loudness contours, `(frequency,
+/// exponent, magnitude, threshold)`, at the frequencies the shelves are
+/// matched at, and the 1 kHz reference.
+const LOW: (f64, f64, f64, f64) = (25f64, 0.506f64, -27.2f64, 68.7f64);
+const REFERENCE: (f64, f64, f64, f64) = (1_000f64, 0.25f64, 0f64, 2.4f64);
+const HIGH: (f64, f64, f64, f64) = (12_500f64, 0.301f64, -3.1f64, 12.3f64);
+
+/// The corner and slope of the low shelf, fit to the contours from 20 to 70
+/// phon.
+const LOW_SHELF: (f64, f64) = (125f64, 0.8f64);
+
+/// The corner and slope of the high shelf.
+const HIGH_SHELF: (f64, f64) = (10_000f64, 1f64);
+
+/// The range of loudness levels the contours are defined over, in phon.
+const MIN_LEVEL: f64 = 20f64;
+const MAX_LEVEL: f64 = 90f64;
+
+/// Returns the sound pressure level, in dB SPL, of a tone with the contour
+/// `parameters` at a loudness of `level` phon.
+fn contour((_, exponent, magnitude, threshold): (f64, f64, f64, f64), level: f64) -> f64 {
+  let a = 4.47e-3f64 * (10f64.powf(0.025f64 * level) - 1.15f64)
+        + (0.4f64 * 10f64.powf((threshold + magnitude) / 10f64 - 9f64)).powf(exponent);
+  10f64 / exponent * a.log10() - magnitude + 94f64
+}
+
+/// Returns the boost, in dB, a tone with the contour `parameters` needs at
+/// `listening` phon to sound as loud, relative to 1 kHz, as at `reference`
+/// phon.
+fn boost(parameters: (f64, f64, f64, f64), listening: f64, reference: f64) -> f64 {
+  let relative = |level: f64| contour(parameters, level) - contour(REFERENCE, level);
+  relative(listening) - relative(reference)
+}
+
+/// A loudness-compensated monitoring filter, or "loudness button".
+///
+/// The ear loses sensitivity to low, and to a lesser degree high,
+/// frequencies as the level falls, so a mix balanced at a reference level
+/// sounds thin when monitored quietly. This filter applies the difference
+/// between the ISO 226:2003 equal-loudness contours at the listening level
+/// and at the reference level, so the tonal balance heard at the listening
+/// level matches the reference. The tilt is approximated by a low shelf,
+/// matched to the contours at 25 Hz, and a high shelf, matched at 12.5 kHz,
+/// which follow the contours within 3 dB from 20 phon up. At 1 kHz, the gain
+/// is unity.
+///
+/// Levels are loudness levels in phon, which equal the level in dB SPL of a
+/// 1 kHz tone. The reference defaults to 83 phon, the usual calibration for
+/// mixing, and monitoring at the reference leaves the signal unaltered.
+pub struct LoudnessCompensation<T> {
+  sample_rate: T,
+  reference_level: T,
+  listening_level: T,
+  low: Biquad2<T>,
+  high: Biquad2<T>,
+  output: T
+}
+
+impl<T> LoudnessCompensation<T> where T: Float + FloatConst {
+  /// Creates a new `LoudnessCompensation` for signals at `sample_rate`, with
+  /// the listening level at the reference level.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::LoudnessCompensation;
+  /// use rasp::traits::{FrequencyResponse, Processor};
+  ///
+  /// // Monitoring 30 dB below the reference boosts the bass
+  /// let mut loudness = LoudnessCompensation::new(48_000f64);
+  /// loudness.set_listening_level(53f64);
+  /// assert!(loudness.magnitude_at(50f64, 48_000f64) > 2f64);
+  ///
+  /// let output = loudness.process(1f64);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let level: T = num::cast(83f64).unwrap();
+    let mut loudness = LoudnessCompensation {
+      sample_rate,
+      reference_level: level,
+      listening_level: level,
+      low: Biquad2::new(),
+      high: Biquad2::new(),
+      output: num::zero()
+    };
+    loudness.update();
+    loudness
+  }
+
+  /// Sets the reference level the signal was balanced at, in phon.
+  ///
+  /// The level is clipped to the 20 to 90 phon range of the contours.
+  pub fn set_reference_level(&mut self, level: T) {
+    self.reference_level = Self::clip(level);
+    self.update();
+  }
+
+  /// Returns the reference level, in phon.
+  pub fn get_reference_level(&self) -> T {
+    self.reference_level
+  }
+
+  /// Sets the level the signal is monitored at, in phon.
+  ///
+  /// The level is clipped to the 20 to 90 phon range of the contours.
+  pub fn set_listening_level(&mut self, level: T) {
+    self.listening_level = Self::clip(level);
+    self.update();
+  }
+
+  /// Returns the listening level, in phon.
+  pub fn get_listening_level(&self) -> T {
+    self.listening_level
+  }
+
+  fn clip(level: T) -> T {
+    level.max(num::cast(MIN_LEVEL).unwrap()).min(num::cast(MAX_LEVEL).unwrap())
+  }
+
+  /// Loads the shelves for the current levels, without clearing them, so
+  /// levels can be changed while processing.
+  fn update(&mut self) {
+    let listening: f64 = num::cast(self.listening_level).unwrap();
+    let reference: f64 = num::cast(self.reference_level).unwrap();
+    let low_gain: T = num::cast(boost(LOW, listening, reference)).unwrap();
+    let high_gain: T = num::cast(boost(HIGH, listening, reference)).unwrap();
+
+    // The high shelf is kept below Nyquist at low sample rates
+    let quarter: T = num::cast(0.25f64).unwrap();
+    let high_frequency = (self.sample_rate * quarter).min(num::cast(HIGH_SHELF.0).unwrap());
+    self.low.load_coefficients(LowShelf::coefficients(self.sample_rate,
+                                                      num::cast(LOW_SHELF.0).unwrap(),
+                                                      low_gain,
+                                                      num::cast(LOW_SHELF.1).unwrap()));
+    self.high.load_coefficients(HighShelf::coefficients(self.sample_rate,
+                                                        high_frequency,
+                                                        high_gain,
+                                                        num::cast(HIGH_SHELF.1).unwrap()));
+  }
+}
+
+impl<T> Processor<T> for LoudnessCompensation<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.output = self.high.process(self.low.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.low.clear();
+    self.high.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for LoudnessCompensation<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    self.low.response_at(frequency, sample_rate) * self.high.response_at(frequency, sample_rate)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use util;
+
+  fn db(loudness: &LoudnessCompensation<f64>, frequency: f64) -> f64 {
+    util::to_db(loudness.magnitude_at(frequency, 48_000f64))
+  }
+
+  #[test]
+  fn contours() {
+    // A 1 kHz tone is as loud in phon as its level in dB SPL, and the
+    // threshold of hearing rises steeply at low frequencies
+    assert!((contour(REFERENCE, 40f64) - 40f64).abs() < 0.1f64);
+    assert!((contour(LOW, 40f64) - 93.9f64).abs() < 0.1f64);
+    assert!((boost(LOW, 40f64, 83f64) - 21.2f64).abs() < 0.1f64);
+    assert!((boost(HIGH, 40f64, 83f64) - 6.6f64).abs() < 0.1f64);
+  }
+
+  #[test]
+  fn tilt() {
+    let mut loudness = LoudnessCompensation::new(48_000f64);
+    for frequency in [25f64, 100f64, 1_000f64, 12_500f64].iter() {
+      assert!(db(&loudness, *frequency).abs() < 1e-9f64);
+    }
+
+    loudness.set_listening_level(40f64);
+    assert!((db(&loudness, 25f64) - boost(LOW, 40f64, 83f64)).abs() < 1f64);
+    assert!((db(&loudness, 12_500f64) - boost(HIGH, 40f64, 83f64)).abs() < 1.5f64);
+    assert!(db(&loudness, 1_000f64).abs() < 0.5f64);
+    let quiet = db(&loudness, 100f64);
+    loudness.set_listening_level(60f64);
+    assert!(db(&loudness, 100f64) > 3f64 && db(&loudness, 100f64) < quiet);
+
+    // Listening above the reference cuts the bass, and levels are clipped
+    loudness.set_reference_level(0f64);
+    assert_eq!(loudness.get_reference_level(), 20f64);
+    assert_eq!(loudness.get_listening_level(), 60f64);
+    assert!(db(&loudness, 25f64) < -10f64);
+  }
+
+  #[test]
+  fn process() {
+    let mut loudness = LoudnessCompensation::new(48_000f64);
+    loudness.set_listening_level(50f64);
+    let output = (0..48_000).fold(0f64, |_, _| loudness.process(1f64));
+    assert!((output - util::to_sample(db(&loudness, 0f64))).abs() < 1e-6f64);
+    assert!(db(&loudness, 0f64) > 10f64);
+    loudness.clear();
+    assert_eq!(loudness.last_out(), 0f64);
+  }
+}
@@ -0,0 +1,30 @@
+//! Audio effects built from the filter, delay, and analysis components.
+
+mod comb_chorus_bank;
+mod crossfeed;
+mod dispersion;
+mod ducker;
+mod echo;
+mod gated_reverb;
+mod harmonic_comb;
+mod linear_phase_eq;
+mod loudness_compensation;
+mod pitch_shifter;
+mod reverb;
+mod shimmer_reverb;
+mod stereo_rotate;
+
+pub use self::comb_chorus_bank::CombChorusBank            as CombChorusBank;
+pub use self::crossfeed::Crossfeed                        as Crossfeed;
+pub use self::dispersion::Dispersion                      as Dispersion;
+pub use self::ducker::Ducker                              as Ducker;
+pub use self::echo::Echo                                  as Echo;
+pub use self::gated_reverb::GatedReverb                   as GatedReverb;
+pub use self::harmonic_comb::HarmonicComb                 as HarmonicComb;
+pub use self::harmonic_comb::HarmonicMode                 as HarmonicMode;
+pub use self::linear_phase_eq::LinearPhaseEq              as LinearPhaseEq;
+pub use self::loudness_compensation::LoudnessCompensation as LoudnessCompensation;
+pub use self::pitch_shifter::PitchShifter                 as PitchShifter;
+pub use self::reverb::Reverb                              as Reverb;
+pub use self::shimmer_reverb::ShimmerReverb               as ShimmerReverb;
+pub use self::stereo_rotate::StereoRotate                 as StereoRotate;
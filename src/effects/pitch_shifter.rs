@@ -0,0 +1,183 @@
+use num;
+use num::traits::Float;
+
+use traits::{FloatConst, Processor};
+
+/// The length, in seconds, of the window swept by the read taps.
+const WINDOW: f64 = 0.05f64;
+
+/// A pitch shifter reading a delay line with two sweeping taps.
+///
+/// Each tap reads the recent input at a delay that sweeps through a short
+/// window, faster or slower than real time, which transposes the signal.
+/// When a tap reaches the end of the window it jumps back, so the two taps
+/// are half a window apart and crossfaded with complementary sine windows,
+/// each silent as it jumps. The shifter delays its input by about half the
+/// window, 25 ms, and the crossfades blur transients, which suits pads and
+/// reverb tails rather than percussive material.
+#[derive(Clone)]
+pub struct PitchShifter<T> {
+  memory: Vec<T>,
+  write_ptr: usize,
+  window: T,
+  phase: T,
+  ratio: T,
+  shift: T,
+  output: T
+}
+
+impl<T> PitchShifter<T> where T: Float + FloatConst {
+  /// Creates a new `PitchShifter` for signals at `sample_rate`, shifting by
+  /// an octave.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::PitchShifter;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut shifter = PitchShifter::new(44_100f32);
+  /// shifter.set_shift(-5f32);
+  /// let output = shifter.process(0.5f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let window = sample_rate * num::cast(WINDOW).unwrap();
+    let length: usize = num::cast(window.ceil()).unwrap();
+    let mut shifter = PitchShifter {
+      memory: vec![num::zero(); length + 2],
+      write_ptr: 0,
+      window,
+      phase: num::zero(),
+      ratio: T::one(),
+      shift: num::zero(),
+      output: num::zero()
+    };
+    shifter.set_shift(num::cast(12f64).unwrap());
+    shifter
+  }
+
+  /// Sets the shift, in semitones, positive to raise the pitch and negative
+  /// to lower it.
+  ///
+  /// `shift` must be finite, else it is not updated.
+  pub fn set_shift(&mut self, shift: T) {
+    if shift.is_finite() {
+      let twelve: T = num::cast(12f64).unwrap();
+      self.shift = shift;
+      self.ratio = T::two().powf(shift / twelve);
+    }
+  }
+
+  /// Returns the shift, in semitones.
+  pub fn get_shift(&self) -> T {
+    self.shift
+  }
+
+  /// Returns the ratio of the output to input frequencies.
+  pub fn get_ratio(&self) -> T {
+    self.ratio
+  }
+
+  /// Returns the input `delay` samples ago, interpolated linearly.
+  fn read(&self, delay: T) -> T {
+    let length = self.memory.len();
+    let whole = delay.floor();
+    let fraction = delay - whole;
+    let whole: usize = num::cast(whole).unwrap();
+    let first = (self.write_ptr + length - whole % length) % length;
+    let second = (first + length - 1) % length;
+    self.memory[first] + (self.memory[second] - self.memory[first]) * fraction
+  }
+}
+
+impl<T> Processor<T> for PitchShifter<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.memory[self.write_ptr] = sample;
+
+    let half: T = num::cast(0.5f64).unwrap();
+    let mut output = T::zero();
+    for offset in [T::zero(), half].iter() {
+      let phase = self.phase + *offset;
+      let phase = phase - phase.floor();
+      let gain = (T::pi() * phase).sin();
+      output = output + self.read(phase * self.window) * gain * gain;
+    }
+
+    // Raising the pitch shortens the delay, reading faster than real time
+    let phase = self.phase + (T::one() - self.ratio) / self.window;
+    self.phase = phase - phase.floor();
+    self.write_ptr = (self.write_ptr + 1) % self.memory.len();
+    self.output = output;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for sample in self.memory.iter_mut() {
+      *sample = T::zero();
+    }
+    self.phase = T::zero();
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the power of `samples` at `frequency`, with the Goertzel
+  /// algorithm.
+  fn power(samples: &[f64], frequency: f64, sample_rate: f64) -> f64 {
+    let coefficient = 2f64 * (2f64 * ::std::f64::consts::PI * frequency / sample_rate).cos();
+    let (mut s1, mut s2) = (0f64, 0f64);
+    for sample in samples {
+      let s0 = sample + coefficient * s1 - s2;
+      s2 = s1;
+      s1 = s0;
+    }
+    s1 * s1 + s2 * s2 - coefficient * s1 * s2
+  }
+
+  #[test]
+  fn octave() {
+    let sample_rate = 16_000f64;
+    let w = 2f64 * ::std::f64::consts::PI * 440f64 / sample_rate;
+    let mut shifter = PitchShifter::new(sample_rate);
+    assert!((shifter.get_ratio() - 2f64).abs() < 1e-12f64);
+    let output: Vec<f64> = (0..16_000).map(|n| shifter.process((w * n as f64).sin())).collect();
+    let output = &output[4_000..];
+    assert!(power(output, 880f64, sample_rate) > 10f64 * power(output, 440f64, sample_rate));
+
+    // Shifting down a fifth
+    shifter.set_shift(-7f64);
+    shifter.clear();
+    let output: Vec<f64> = (0..16_000).map(|n| shifter.process((w * n as f64).sin())).collect();
+    let output = &output[4_000..];
+    let fifth = 440f64 * 2f64.powf(-7f64 / 12f64);
+    assert!(power(output, fifth, sample_rate) > 10f64 * power(output, 440f64, sample_rate));
+  }
+
+  #[test]
+  fn unison() {
+    // Without a shift, the input is only delayed by half the window
+    let mut shifter = PitchShifter::new(1_000f64);
+    shifter.set_shift(0f64);
+    let output: Vec<f64> = (0..64).map(|n| shifter.process(if n == 0 { 1f64 } else { 0f64 })).collect();
+    assert!((output[25] - 1f64).abs() < 1e-12f64);
+    assert!(output.iter().enumerate().all(|(n, sample)| n == 25 || sample.abs() < 1e-12f64));
+  }
+
+  #[test]
+  fn parameters() {
+    let mut shifter = PitchShifter::new(44_100f32);
+    assert_eq!(shifter.get_shift(), 12f32);
+    shifter.set_shift(::std::f32::NAN);
+    assert_eq!(shifter.get_shift(), 12f32);
+    shifter.process(1f32);
+    shifter.clear();
+    assert_eq!(shifter.last_out(), 0f32);
+  }
+}
@@ -0,0 +1,335 @@
+use num;
+use num::traits::Float;
+
+use delay::Delay;
+use effects::Ducker;
+use filter::{AllpassDelay, OnePole};
+use filter::design::Band;
+use traits::{FloatConst, Processor};
+use util;
+
+/// The delays of the feedback lines, in seconds, at a size of one, chosen
+/// without common factors so their echoes do not line up.
+const LINES: [f64; 4] = [0.0297f64, 0.0371f64, 0.0411f64, 0.0437f64];
+
+/// The delays of the input diffusers, in seconds, at a size of one.
+const DIFFUSERS: [f64; 2] = [0.0047f64, 0.0017f64];
+
+/// The largest size of the room.
+const MAX_SIZE: f64 = 2f64;
+
+/// A feedback delay network reverb.
+///
+/// The input is smeared by two allpass diffusers, then fed to four delay
+/// lines, each fed back into every other through an orthogonal Hadamard
+/// matrix, which keeps the energy of the network while mixing the echoes
+/// into a dense tail. Each line is attenuated so the tail falls by 60 dB
+/// over the decay time, and low-passed at the damping frequency, so high
+/// frequencies die away sooner, as they do in real rooms.
+///
+/// The size scales every delay, from small rooms to large halls, and the
+/// reverb is mixed with the dry signal. The reverb can be ducked by the dry
+/// signal, through `ducker_mut()`, to keep it out of the way of transients.
+pub struct Reverb<T: Float> {
+  sample_rate: T,
+  diffusers: Vec<AllpassDelay<T>>,
+  lines: Vec<Delay<T>>,
+  filters: Vec<OnePole<T>>,
+  gains: Vec<T>,
+  ducker: Ducker<T>,
+  size: T,
+  decay: T,
+  damping: T,
+  mix: T,
+  output: T
+}
+
+impl<T> Reverb<T> where T: Float + FloatConst {
+  /// Creates a new `Reverb` for signals at `sample_rate`, with a size of
+  /// one, a decay of 2 seconds, damping at 8 kHz, and an even mix.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::Reverb;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut reverb = Reverb::new(44_100f32);
+  /// reverb.set_decay(3.5f32);
+  /// reverb.set_damping(5_000f32);
+  /// reverb.set_size(1.5f32);
+  ///
+  /// let mut block = vec![0f32; 256];
+  /// block[0] = 1f32;
+  /// reverb.process_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let seconds = |delay: f64| -> usize { num::cast(num::cast::<T, f64>(sample_rate).unwrap() * delay * MAX_SIZE).unwrap() };
+    let mut reverb = Reverb {
+      sample_rate,
+      diffusers: DIFFUSERS.iter().map(|delay| AllpassDelay::new(1, seconds(*delay).max(1))).collect(),
+      lines: LINES.iter().map(|delay| Delay::new(1, seconds(*delay))).collect(),
+      filters: LINES.iter().map(|_| OnePole::new()).collect(),
+      gains: vec![num::zero(); LINES.len()],
+      ducker: Ducker::new(sample_rate),
+      size: T::one(),
+      decay: T::two(),
+      damping: num::cast(8_000f64).unwrap(),
+      mix: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    };
+    for diffuser in reverb.diffusers.iter_mut() {
+      diffuser.set_gain(num::cast(0.6f64).unwrap());
+    }
+    reverb.update_delays();
+    let damping = reverb.damping.min(sample_rate * num::cast(0.49f64).unwrap());
+    reverb.set_damping(damping);
+    reverb
+  }
+
+  /// Sets the size of the room, which scales every delay, from 0.1 to 2.
+  ///
+  /// `size` must be within 0.1 and 2, else it is not updated.
+  pub fn set_size(&mut self, size: T) {
+    if size >= num::cast(0.1f64).unwrap() && size <= num::cast(MAX_SIZE).unwrap() {
+      self.size = size;
+      self.update_delays();
+    }
+  }
+
+  /// Returns the size of the room.
+  pub fn get_size(&self) -> T {
+    self.size
+  }
+
+  /// Sets the time, in seconds, for the tail to decay by 60 dB.
+  ///
+  /// `decay` must be positive, else it is not updated.
+  pub fn set_decay(&mut self, decay: T) {
+    if decay > T::zero() && decay.is_finite() {
+      self.decay = decay;
+      self.update_gains();
+    }
+  }
+
+  /// Returns the time, in seconds, for the tail to decay by 60 dB.
+  pub fn get_decay(&self) -> T {
+    self.decay
+  }
+
+  /// Sets the frequency, in Hz, above which the tail decays faster.
+  ///
+  /// `damping` must be positive and below Nyquist, else it is not updated.
+  pub fn set_damping(&mut self, damping: T) {
+    if damping > T::zero() && damping < self.sample_rate / T::two() {
+      self.damping = damping;
+      for filter in self.filters.iter_mut() {
+        filter.set_cutoff(Band::LowPass, self.sample_rate, damping);
+      }
+    }
+  }
+
+  /// Returns the frequency, in Hz, above which the tail decays faster.
+  pub fn get_damping(&self) -> T {
+    self.damping
+  }
+
+  /// Sets the mix of the reverb with the dry signal, from 0 for only the dry
+  /// signal to 1 for only the reverb.
+  ///
+  /// `mix` must be within 0 and 1, else it is not updated.
+  pub fn set_mix(&mut self, mix: T) {
+    if mix >= T::zero() && mix <= T::one() {
+      self.mix = mix;
+    }
+  }
+
+  /// Returns the mix of the reverb with the dry signal.
+  pub fn get_mix(&self) -> T {
+    self.mix
+  }
+
+  /// Returns the ducker of the reverb, keyed from the dry signal.
+  pub fn ducker(&self) -> &Ducker<T> {
+    &self.ducker
+  }
+
+  /// Returns the ducker of the reverb to set its depth, attack and release.
+  pub fn ducker_mut(&mut self) -> &mut Ducker<T> {
+    &mut self.ducker
+  }
+
+  /// Returns the number of delay lines in the network.
+  pub fn lines(&self) -> usize {
+    self.lines.len()
+  }
+
+  /// Returns the next output for `sample`, passing the damped feedback of
+  /// each line through `feedback`, called with the index of the line, before
+  /// it is mixed back into the network.
+  ///
+  /// This places other processing inside the tail, such as the pitch shifter
+  /// of a `ShimmerReverb`. `feedback` must not raise the level of the line,
+  /// else the tail may grow instead of decaying.
+  pub fn process_with<F>(&mut self, sample: T, mut feedback: F) -> T
+    where F: FnMut(usize, T) -> T
+  {
+    let diffused = self.diffusers.iter_mut().fold(sample, |sample, diffuser| diffuser.process(sample));
+
+    let mut outputs = [T::zero(); 4];
+    let mut returns = [T::zero(); 4];
+    for (i, line) in self.lines.iter().enumerate() {
+      outputs[i] = line.next_out();
+      returns[i] = feedback(i, self.filters[i].process(outputs[i]) * self.gains[i]);
+    }
+
+    // The Hadamard matrix of order four, scaled to be orthogonal
+    let half: T = num::cast(0.5f64).unwrap();
+    let (a, b, c, d) = (returns[0], returns[1], returns[2], returns[3]);
+    let mixed = [(a + b + c + d) * half, (a - b + c - d) * half, (a + b - c - d) * half, (a - b - c + d) * half];
+    for (line, mixed) in self.lines.iter_mut().zip(mixed.iter()) {
+      line.process(diffused + *mixed);
+    }
+
+    let wet = (outputs[0] - outputs[1] + outputs[2] - outputs[3]) * half;
+    let wet = self.ducker.process(sample, wet);
+    self.output = sample + (wet - sample) * self.mix;
+    self.output
+  }
+
+  fn update_delays(&mut self) {
+    let scale = num::cast::<T, f64>(self.sample_rate * self.size).unwrap();
+    let samples = |delay: f64| -> usize {
+      let delay = scale * delay;
+      (delay.round() as usize).max(1)
+    };
+    for (diffuser, delay) in self.diffusers.iter_mut().zip(DIFFUSERS.iter()) {
+      diffuser.set_delay(samples(*delay));
+    }
+    for (line, delay) in self.lines.iter_mut().zip(LINES.iter()) {
+      line.set_delay(samples(*delay));
+    }
+    self.update_gains();
+  }
+
+  fn update_gains(&mut self) {
+    // Each pass through a line falls by its share of 60 dB
+    let sixty: T = num::cast(-60f64).unwrap();
+    for (gain, line) in self.gains.iter_mut().zip(self.lines.iter()) {
+      let delay: T = num::cast(line.get_delay()).unwrap();
+      *gain = util::to_sample(sixty * delay / (self.decay * self.sample_rate));
+    }
+  }
+}
+
+impl<T> Processor<T> for Reverb<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.process_with(sample, |_, feedback| feedback)
+  }
+
+  fn clear(&mut self) {
+    for diffuser in self.diffusers.iter_mut() {
+      diffuser.clear();
+    }
+    for (line, filter) in self.lines.iter_mut().zip(self.filters.iter_mut()) {
+      line.clear();
+      filter.clear();
+    }
+    self.ducker.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the energy of `samples`.
+  fn energy(samples: &[f64]) -> f64 {
+    samples.iter().map(|sample| sample * sample).sum()
+  }
+
+  #[test]
+  fn decay() {
+    let sample_rate = 16_000f64;
+    let mut reverb = Reverb::new(sample_rate);
+    reverb.set_mix(1f64);
+    reverb.set_decay(1f64);
+    reverb.set_damping(7_900f64);
+    let mut response = vec![0f64; 32_000];
+    response[0] = 1f64;
+    reverb.process_block(&mut response);
+
+    // The tail falls by about 60 dB every decay time
+    let early = energy(&response[4_000..8_000]);
+    let late = energy(&response[20_000..24_000]);
+    assert!((10f64 * (late / early).log10() - -60f64).abs() < 6f64);
+    assert!(response.iter().all(|sample| sample.is_finite()));
+
+    // A longer decay rings longer
+    reverb.set_decay(3f64);
+    reverb.clear();
+    let mut longer = vec![0f64; 32_000];
+    longer[0] = 1f64;
+    reverb.process_block(&mut longer);
+    assert!(energy(&longer[20_000..24_000]) > 100f64 * late);
+  }
+
+  #[test]
+  fn damping() {
+    // High frequencies fade before low ones
+    let sample_rate = 16_000f64;
+    let tail = |frequency: f64| {
+      let mut reverb = Reverb::new(sample_rate);
+      reverb.set_mix(1f64);
+      reverb.set_damping(1_000f64);
+      let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+      let output: Vec<f64> = (0..16_000)
+        .map(|n| reverb.process(if n < 1_600 { (w * n as f64).sin() } else { 0f64 }))
+        .collect();
+      energy(&output[8_000..])
+    };
+    assert!(tail(100f64) > 100f64 * tail(4_000f64));
+  }
+
+  #[test]
+  fn ducking() {
+    // The tail is held down while the dry signal plays, and swells after
+    let sample_rate = 16_000f64;
+    let tail = |depth: f64| {
+      let mut reverb = Reverb::new(sample_rate);
+      reverb.set_mix(1f64);
+      reverb.ducker_mut().set_depth(depth);
+      let output: Vec<f64> = (0..16_000)
+        .map(|n| reverb.process(if n < 8_000 { 0.9f64 * (0.1f64 * n as f64).sin() } else { 0f64 }))
+        .collect();
+      (energy(&output[4_000..8_000]), energy(&output[12_000..]))
+    };
+    let (plain, ducked) = (tail(0f64), tail(0.9f64));
+    assert!(ducked.0 < 0.1f64 * plain.0);
+    assert!(ducked.1 > 0.5f64 * plain.1);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut reverb = Reverb::new(44_100f32);
+    reverb.set_size(3f32);
+    reverb.set_decay(0f32);
+    reverb.set_damping(30_000f32);
+    reverb.set_mix(-1f32);
+    assert_eq!((reverb.get_size(), reverb.get_decay()), (1f32, 2f32));
+    assert_eq!((reverb.get_damping(), reverb.get_mix()), (8_000f32, 0.5f32));
+
+    // Without any mix, the input is not altered
+    reverb.set_mix(0f32);
+    reverb.set_size(0.1f32);
+    assert_eq!(reverb.get_size(), 0.1f32);
+    assert_eq!(reverb.process(0.25f32), 0.25f32);
+    reverb.clear();
+    assert_eq!(reverb.last_out(), 0f32);
+  }
+}
@@ -0,0 +1,200 @@
+use num;
+use num::traits::Float;
+
+use effects::{PitchShifter, Reverb};
+use traits::{FloatConst, Processor};
+
+/// A shimmer reverb, whose tail rises in pitch as it decays.
+///
+/// Each feedback line of a `Reverb` passes through a `PitchShifter`, so
+/// every pass around the network is transposed again, an octave up by
+/// default, building the shimmering, organ-like overtones heard on ambient
+/// guitars and pads. The feedback crossfades each line from its plain tail
+/// to its shifted tail, so the network keeps decaying at any amount, and
+/// the damping of the reverb keeps the highest transpositions from piling
+/// up.
+///
+/// The reverb can be reached through `reverb_mut()` to set its size, decay,
+/// damping and ducking, while the mix of the whole effect is set here.
+pub struct ShimmerReverb<T: Float> {
+  reverb: Reverb<T>,
+  shifters: Vec<PitchShifter<T>>,
+  feedback: T,
+  mix: T,
+  output: T
+}
+
+impl<T> ShimmerReverb<T> where T: Float + FloatConst {
+  /// Creates a new `ShimmerReverb` for signals at `sample_rate`, shifting
+  /// by an octave, with a feedback of one half, a decay of 4 seconds, and an
+  /// even mix.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::ShimmerReverb;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut shimmer = ShimmerReverb::new(44_100f32);
+  /// shimmer.set_shift(7f32);
+  /// shimmer.set_feedback(0.6f32);
+  /// shimmer.reverb_mut().set_damping(6_000f32);
+  ///
+  /// let mut block = vec![0f32; 256];
+  /// block[0] = 1f32;
+  /// shimmer.process_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut reverb = Reverb::new(sample_rate);
+    reverb.set_mix(T::one());
+    reverb.set_decay(num::cast(4f64).unwrap());
+    let shifters = (0..reverb.lines()).map(|_| PitchShifter::new(sample_rate)).collect();
+    ShimmerReverb {
+      reverb,
+      shifters,
+      feedback: num::cast(0.5f64).unwrap(),
+      mix: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    }
+  }
+
+  /// Returns the reverb, whose mix is kept at one.
+  pub fn reverb(&self) -> &Reverb<T> {
+    &self.reverb
+  }
+
+  /// Returns the reverb to set its size, decay and damping.
+  ///
+  /// Its mix should be left at one, since the dry signal is mixed by the
+  /// shimmer.
+  pub fn reverb_mut(&mut self) -> &mut Reverb<T> {
+    &mut self.reverb
+  }
+
+  /// Sets the shift, in semitones, of each pass through the feedback.
+  ///
+  /// `shift` must be finite, else it is not updated.
+  pub fn set_shift(&mut self, shift: T) {
+    for shifter in self.shifters.iter_mut() {
+      shifter.set_shift(shift);
+    }
+  }
+
+  /// Returns the shift, in semitones, of each pass through the feedback.
+  pub fn get_shift(&self) -> T {
+    self.shifters[0].get_shift()
+  }
+
+  /// Sets how much of the tail is shifted on each pass, from 0 for a plain
+  /// reverb to 1 for only the shifted tail.
+  ///
+  /// `feedback` must be within 0 and 1, else it is not updated.
+  pub fn set_feedback(&mut self, feedback: T) {
+    if feedback >= T::zero() && feedback <= T::one() {
+      self.feedback = feedback;
+    }
+  }
+
+  /// Returns how much of the tail is shifted on each pass.
+  pub fn get_feedback(&self) -> T {
+    self.feedback
+  }
+
+  /// Sets the mix of the effect with the dry signal, from 0 for only the dry
+  /// signal to 1 for only the effect.
+  ///
+  /// `mix` must be within 0 and 1, else it is not updated.
+  pub fn set_mix(&mut self, mix: T) {
+    if mix >= T::zero() && mix <= T::one() {
+      self.mix = mix;
+    }
+  }
+
+  /// Returns the mix of the effect with the dry signal.
+  pub fn get_mix(&self) -> T {
+    self.mix
+  }
+}
+
+impl<T> Processor<T> for ShimmerReverb<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let (shifters, amount) = (&mut self.shifters, self.feedback);
+    let wet = self.reverb.process_with(sample, |line, feedback| {
+      feedback + (shifters[line].process(feedback) - feedback) * amount
+    });
+    self.output = sample + (wet - sample) * self.mix;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.reverb.clear();
+    for shifter in self.shifters.iter_mut() {
+      shifter.clear();
+    }
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the power of `samples` at `frequency`, with the Goertzel
+  /// algorithm.
+  fn power(samples: &[f64], frequency: f64, sample_rate: f64) -> f64 {
+    let coefficient = 2f64 * (2f64 * ::std::f64::consts::PI * frequency / sample_rate).cos();
+    let (mut s1, mut s2) = (0f64, 0f64);
+    for sample in samples {
+      let s0 = sample + coefficient * s1 - s2;
+      s2 = s1;
+      s1 = s0;
+    }
+    s1 * s1 + s2 * s2 - coefficient * s1 * s2
+  }
+
+  /// Returns the tail of `shimmer` after a burst at 220 Hz.
+  fn tail(shimmer: &mut ShimmerReverb<f64>) -> Vec<f64> {
+    let w = 2f64 * ::std::f64::consts::PI * 220f64 / 16_000f64;
+    let output: Vec<f64> = (0..32_000)
+      .map(|n| shimmer.process(if n < 4_000 { (w * n as f64).sin() } else { 0f64 }))
+      .collect();
+    output[8_000..].to_vec()
+  }
+
+  #[test]
+  fn shimmer() {
+    let mut shimmer = ShimmerReverb::new(16_000f64);
+    shimmer.set_mix(1f64);
+    shimmer.set_feedback(0f64);
+    let plain = tail(&mut shimmer);
+
+    // The feedback builds an octave above the input in the tail
+    shimmer.clear();
+    shimmer.set_feedback(1f64);
+    let shimmering = tail(&mut shimmer);
+    let ratio = |tail: &[f64]| power(tail, 440f64, 16_000f64) / power(tail, 220f64, 16_000f64);
+    assert!(ratio(&shimmering) > 4f64 * ratio(&plain));
+    assert!(shimmering.iter().all(|sample| sample.is_finite() && sample.abs() < 10f64));
+  }
+
+  #[test]
+  fn parameters() {
+    let mut shimmer = ShimmerReverb::new(44_100f32);
+    assert_eq!((shimmer.get_shift(), shimmer.get_feedback(), shimmer.get_mix()), (12f32, 0.5f32, 0.5f32));
+    assert_eq!((shimmer.reverb().get_decay(), shimmer.reverb().get_mix()), (4f32, 1f32));
+    shimmer.set_feedback(1.5f32);
+    shimmer.set_mix(2f32);
+    shimmer.set_shift(::std::f32::INFINITY);
+    assert_eq!((shimmer.get_shift(), shimmer.get_feedback(), shimmer.get_mix()), (12f32, 0.5f32, 0.5f32));
+
+    // Without any mix, the input is not altered
+    shimmer.set_mix(0f32);
+    assert_eq!(shimmer.process(0.25f32), 0.25f32);
+    shimmer.clear();
+    assert_eq!(shimmer.last_out(), 0f32);
+  }
+}
@@ -0,0 +1,209 @@
+use num::traits::Float;
+
+use filter::Crossover;
+use traits::{FloatConst, StereoProcessor};
+
+/// A stereo image rotation, with balance.
+///
+/// The left and right samples are treated as a vector and rotated by an
+/// angle, which turns the whole stereo field, rather than narrowing or
+/// widening it: the image keeps its width, but every source moves across it
+/// together. Positive angles rotate towards the right, so 45 degrees moves a
+/// centered source hard right, and a source hard left to the center. This
+/// corrects recordings whose image leans to one side, such as off-axis
+/// coincident pairs, without the loss of width of panning both channels.
+///
+/// The signal can be split into bands with a `Crossover`, to rotate each
+/// band by its own angle, for images that lean differently at low and high
+/// frequencies. The bands are summed after the rotation, so without any
+/// rotation the output only differs from the input by the phase of the
+/// crossover.
+///
+/// The balance then attenuates one channel, leaving the other untouched,
+/// unlike a pan law which also boosts the other side.
+pub struct StereoRotate<T> {
+  crossovers: (Crossover<T>, Crossover<T>),
+  angles: Vec<T>,
+  rotations: Vec<(T, T)>,
+  balance: T,
+  output: (T, T)
+}
+
+impl<T> StereoRotate<T> where T: Float + FloatConst {
+  /// Creates a new `StereoRotate` for signals at `sample_rate`, splitting
+  /// the signal into bands at each of `frequencies`, without any rotation or
+  /// balance.
+  ///
+  /// `frequencies` must be in increasing order, and below Nyquist. Without
+  /// any frequencies, the whole signal is rotated as one band.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::effects::StereoRotate;
+  /// use rasp::traits::StereoProcessor;
+  ///
+  /// // Bring the low end back to the center, leaving the rest alone
+  /// let mut rotate = StereoRotate::new(44_100f32, &[250f32]);
+  /// rotate.set_rotation(0, -10f32);
+  /// rotate.set_balance(0.1f32);
+  ///
+  /// let (left, right) = rotate.process(0.5f32, 0.25f32);
+  /// ```
+  pub fn new(sample_rate: T, frequencies: &[T]) -> Self {
+    let bands = frequencies.len() + 1;
+    StereoRotate {
+      crossovers: (Crossover::new(sample_rate, frequencies), Crossover::new(sample_rate, frequencies)),
+      angles: vec![T::zero(); bands],
+      rotations: vec![(T::one(), T::zero()); bands],
+      balance: T::zero(),
+      output: (T::zero(), T::zero())
+    }
+  }
+
+  /// Returns the number of bands.
+  pub fn bands(&self) -> usize {
+    self.angles.len()
+  }
+
+  /// Sets the rotation of `band`, in degrees, positive towards the right.
+  ///
+  /// `band` must be less than the number of bands, and `angle` finite, else
+  /// the rotation is not updated.
+  pub fn set_rotation(&mut self, band: usize, angle: T) {
+    if band < self.angles.len() && angle.is_finite() {
+      let radians = angle.to_radians();
+      self.angles[band] = angle;
+      self.rotations[band] = (radians.cos(), radians.sin());
+    }
+  }
+
+  /// Sets the rotation of every band, in degrees.
+  ///
+  /// `angle` must be finite, else the rotations are not updated.
+  pub fn set_rotation_all(&mut self, angle: T) {
+    for band in 0..self.angles.len() {
+      self.set_rotation(band, angle);
+    }
+  }
+
+  /// Returns the rotation of `band`, in degrees.
+  pub fn get_rotation(&self, band: usize) -> T {
+    self.angles[band]
+  }
+
+  /// Sets the balance, from -1 to 1, where negative values attenuate the
+  /// right channel and positive values the left, down to silence at either
+  /// end.
+  ///
+  /// `balance` must be within -1 and 1, else it is not updated.
+  pub fn set_balance(&mut self, balance: T) {
+    if balance.abs() <= T::one() {
+      self.balance = balance;
+    }
+  }
+
+  /// Returns the balance.
+  pub fn get_balance(&self) -> T {
+    self.balance
+  }
+
+  /// Returns the gains of the left and right channels set by the balance.
+  pub fn balance_gains(&self) -> (T, T) {
+    (T::one() - self.balance.max(T::zero()), T::one() + self.balance.min(T::zero()))
+  }
+}
+
+impl<T> StereoProcessor<T> for StereoRotate<T> where T: Float + FloatConst {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    let (mut sum_l, mut sum_r) = (T::zero(), T::zero());
+    {
+      let bands_l = self.crossovers.0.tick(left);
+      let bands_r = self.crossovers.1.tick(right);
+      for ((l, r), &(cos, sin)) in bands_l.iter().zip(bands_r.iter()).zip(self.rotations.iter()) {
+        sum_l = sum_l + *l * cos - *r * sin;
+        sum_r = sum_r + *l * sin + *r * cos;
+      }
+    }
+    let (gain_l, gain_r) = self.balance_gains();
+    self.output = (sum_l * gain_l, sum_r * gain_r);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.crossovers.0.clear();
+    self.crossovers.1.clear();
+    self.output = (T::zero(), T::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rotation() {
+    let mut rotate = StereoRotate::new(48_000f64, &[]);
+    assert_eq!(rotate.process(0.5f64, -0.25f64), (0.5f64, -0.25f64));
+
+    // A centered source moves hard right, and a hard left source to the
+    // center
+    rotate.set_rotation(0, 45f64);
+    let (left, right) = rotate.process(1f64, 1f64);
+    assert!(left.abs() < 1e-12f64 && (right - 2f64.sqrt()).abs() < 1e-12f64);
+    let (left, right) = rotate.process(1f64, 0f64);
+    assert!((left - right).abs() < 1e-12f64);
+
+    // The rotation keeps the power of the signal
+    rotate.set_rotation(0, -30f64);
+    let (left, right) = rotate.process(0.3f64, 0.8f64);
+    assert!((left * left + right * right - 0.73f64).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn bands() {
+    // Only the low band is rotated
+    let sample_rate = 48_000f64;
+    let mut rotate = StereoRotate::new(sample_rate, &[500f64]);
+    assert_eq!(rotate.bands(), 2);
+    rotate.set_rotation(0, 45f64);
+    let level = |rotate: &mut StereoRotate<f64>, frequency: f64| {
+      rotate.clear();
+      let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+      let (mut power_l, mut power_r) = (0f64, 0f64);
+      for n in 0..9_600 {
+        let sample = (w * n as f64).sin();
+        let (left, right) = rotate.process(sample, sample);
+        if n >= 4_800 {
+          power_l += left * left;
+          power_r += right * right;
+        }
+      }
+      (power_l, power_r)
+    };
+    let (low_l, low_r) = level(&mut rotate, 50f64);
+    assert!(low_l < 1e-3f64 * low_r);
+    let (high_l, high_r) = level(&mut rotate, 8_000f64);
+    assert!((high_l / high_r - 1f64).abs() < 1e-2f64);
+  }
+
+  #[test]
+  fn balance() {
+    let mut rotate = StereoRotate::new(48_000f32, &[]);
+    rotate.set_balance(0.25f32);
+    assert_eq!(rotate.process(1f32, 1f32), (0.75f32, 1f32));
+    rotate.set_balance(-1f32);
+    assert_eq!(rotate.process(1f32, 1f32), (1f32, 0f32));
+
+    rotate.set_balance(2f32);
+    rotate.set_rotation(1, 10f32);
+    rotate.set_rotation_all(::std::f32::NAN);
+    assert_eq!((rotate.get_balance(), rotate.get_rotation(0)), (-1f32, 0f32));
+    rotate.clear();
+    assert_eq!(rotate.last_out(), (0f32, 0f32));
+  }
+}
@@ -0,0 +1,267 @@
+use num;
+use num::traits::Float;
+
+use traits::{Envelope, Generator};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stage {
+  Idle,
+  Attack,
+  Decay,
+  Sustain,
+  Release
+}
+
+/// A linear attack, decay, sustain, release envelope generator.
+///
+/// Opening the gate ramps the envelope from its current level up to one over
+/// the attack length, then down to the sustain level over the decay length.
+/// The sustain level is held until the gate is closed, at which point the
+/// envelope ramps down to zero over the release length.
+pub struct Adsr<T> {
+  stage: Stage,
+  // Segment lengths, in samples
+  attack: T,
+  decay: T,
+  release: T,
+  sustain: T,
+  // The per-sample decrement of the current release segment
+  release_rate: T,
+  output: T
+}
+
+impl<T> Adsr<T> where T: Float {
+  /// Creates a new `Adsr` envelope.
+  ///
+  /// The envelope will be initialized idle, with all segment lengths set to
+  /// zero and the sustain level set to one, so it behaves like a gate.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::envelope::Adsr;
+  ///
+  /// let mut envelope1: Adsr<f32> = Adsr::new();
+  /// let mut envelope2: Adsr<f64> = Adsr::new();
+  /// let mut envelope3 = Adsr::<f32>::new();
+  /// let mut envelope4 = Adsr::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    Adsr {
+      stage: Stage::Idle,
+      attack: num::zero(),
+      decay: num::zero(),
+      release: num::zero(),
+      sustain: num::one(),
+      release_rate: num::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets the attack length, in samples.
+  ///
+  /// `attack_length` must be non-negative and finite, else the attack is not
+  /// updated.
+  pub fn set_attack(&mut self, attack_length: T) {
+    if attack_length >= num::zero() && attack_length.is_finite() {
+      self.attack = attack_length;
+    }
+  }
+
+  /// Sets the decay length, in samples.
+  ///
+  /// `decay_length` must be non-negative and finite, else the decay is not
+  /// updated.
+  pub fn set_decay(&mut self, decay_length: T) {
+    if decay_length >= num::zero() && decay_length.is_finite() {
+      self.decay = decay_length;
+    }
+  }
+
+  /// Sets the sustain level, where `0 <= level <= 1`.
+  ///
+  /// If `level` is out of range, the sustain level is not updated.
+  pub fn set_sustain(&mut self, level: T) {
+    if level >= num::zero() && level <= num::one() {
+      self.sustain = level;
+    }
+  }
+
+  /// Sets the release length, in samples.
+  ///
+  /// `release_length` must be non-negative and finite, else the release is
+  /// not updated.
+  pub fn set_release(&mut self, release_length: T) {
+    if release_length >= num::zero() && release_length.is_finite() {
+      self.release = release_length;
+    }
+  }
+
+  /// Returns `true` until the envelope has fully released.
+  pub fn is_active(&self) -> bool {
+    self.stage != Stage::Idle
+  }
+}
+
+impl<T> Generator<T> for Adsr<T> where T: Float {
+  fn tick(&mut self) -> T {
+    let one: T = num::one();
+    match self.stage {
+      Stage::Idle => {
+        self.output = num::zero();
+      },
+      Stage::Attack => {
+        self.output = self.output + one / self.attack.max(one);
+        if self.output >= one {
+          self.output = one;
+          self.stage = Stage::Decay;
+        }
+      },
+      Stage::Decay => {
+        self.output = self.output - (one - self.sustain) / self.decay.max(one);
+        if self.output <= self.sustain {
+          self.output = self.sustain;
+          self.stage = Stage::Sustain;
+        }
+      },
+      Stage::Sustain => {
+        self.output = self.sustain;
+      },
+      Stage::Release => {
+        self.output = self.output - self.release_rate;
+        if self.output <= num::zero() {
+          self.output = num::zero();
+          self.stage = Stage::Idle;
+        }
+      }
+    }
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.stage = Stage::Idle;
+    self.release_rate = num::zero();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> Envelope<T> for Adsr<T> where T: Float {
+  fn gate_on(&mut self) {
+    self.stage = Stage::Attack;
+  }
+
+  fn gate_off(&mut self) {
+    if self.stage != Stage::Idle {
+      self.release_rate = self.output / self.release.max(num::one());
+      self.stage = Stage::Release;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+  use ::envelope::GateEvent;
+  use ::traits::{Envelope, Generator};
+
+  #[test]
+  fn new() {
+    let mut envelope = Adsr::<f32>::new();
+    assert!(!envelope.is_active());
+    assert!((envelope.tick() - 0f32).abs() < EPSILON);
+
+    // Zero length segments act as a gate
+    envelope.gate_on();
+    assert!((envelope.tick() - 1f32).abs() < EPSILON);
+    assert!((envelope.tick() - 1f32).abs() < EPSILON);
+    envelope.gate_off();
+    assert!((envelope.tick() - 0f32).abs() < EPSILON);
+    assert!(!envelope.is_active());
+  }
+
+  #[test]
+  fn tick() {
+    let expected = vec![
+      0.25f32, 0.5f32, 0.75f32, 1f32,   // attack
+      0.75f32, 0.5f32,                  // decay
+      0.5f32, 0.5f32,                   // sustain
+      0.25f32, 0f32, 0f32               // release
+    ];
+    let mut envelope = Adsr::new();
+    envelope.set_attack(4f32);
+    envelope.set_decay(2f32);
+    envelope.set_sustain(0.5f32);
+    envelope.set_release(2f32);
+
+    envelope.gate_on();
+    for (i, sample) in expected.iter().enumerate() {
+      if i == 8 {
+        envelope.gate_off();
+      }
+      let actual = envelope.tick();
+      println!("{:.6} - {:.6} = {:.6}", sample, actual, sample - actual);
+      assert!((sample - actual).abs() < EPSILON);
+    }
+    assert!(!envelope.is_active());
+  }
+
+  #[test]
+  fn invalid_parameters() {
+    let mut envelope = Adsr::new();
+    envelope.set_attack(-1f32);
+    envelope.set_decay(::std::f32::INFINITY);
+    envelope.set_sustain(1.5f32);
+    envelope.set_release(::std::f32::NAN);
+
+    envelope.gate_on();
+    assert!((envelope.tick() - 1f32).abs() < EPSILON);
+    assert!((envelope.tick() - 1f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn render() {
+    let mut envelope = Adsr::new();
+    envelope.set_attack(2f32);
+    envelope.set_release(2f32);
+
+    let mut block = vec![1f32; 8];
+    envelope.render(&mut block, &[GateEvent::on(3), GateEvent::off(5)]);
+
+    let expected = vec![0f32, 0f32, 0f32, 0.5f32, 1f32, 0.5f32, 0f32, 0f32];
+    for (sample, actual) in expected.iter().zip(block.iter()) {
+      println!("{:.6} - {:.6} = {:.6}", sample, actual, sample - actual);
+      assert!((sample - actual).abs() < EPSILON);
+    }
+  }
+
+  #[test]
+  fn render_ignores_events_beyond_block() {
+    let mut envelope = Adsr::<f32>::new();
+    let mut block = vec![0f32; 4];
+    envelope.render(&mut block, &[GateEvent::on(4)]);
+
+    assert!(!envelope.is_active());
+    for sample in block.iter() {
+      assert!((sample - 0f32).abs() < EPSILON);
+    }
+  }
+
+  #[test]
+  fn clear() {
+    let mut envelope = Adsr::new();
+    envelope.set_attack(4f32);
+    envelope.gate_on();
+    envelope.tick();
+    assert!((envelope.last_out() - 0.25f32).abs() < EPSILON);
+
+    envelope.clear();
+    assert!(!envelope.is_active());
+    assert!((envelope.last_out() - 0f32).abs() < EPSILON);
+  }
+}
@@ -0,0 +1,196 @@
+use num;
+use num::traits::Float;
+
+use traits::{Envelope, Generator};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Stage {
+  Idle,
+  Attack,
+  Hold,
+  Release
+}
+
+/// A linear attack, release envelope generator.
+///
+/// Opening the gate ramps the envelope from its current level up to one over
+/// the attack length, and the envelope stays at one until the gate is
+/// closed. Closing the gate ramps the envelope down to zero over the release
+/// length.
+pub struct Ar<T> {
+  stage: Stage,
+  // Segment lengths, in samples
+  attack: T,
+  release: T,
+  // The per-sample decrement of the current release segment
+  release_rate: T,
+  output: T
+}
+
+impl<T> Ar<T> where T: Float {
+  /// Creates a new `Ar` envelope.
+  ///
+  /// The envelope will be initialized idle, with both segment lengths set to
+  /// zero, so it behaves like a gate.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::envelope::Ar;
+  ///
+  /// let mut envelope1: Ar<f32> = Ar::new();
+  /// let mut envelope2: Ar<f64> = Ar::new();
+  /// let mut envelope3 = Ar::<f32>::new();
+  /// let mut envelope4 = Ar::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    Ar {
+      stage: Stage::Idle,
+      attack: num::zero(),
+      release: num::zero(),
+      release_rate: num::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets the attack length, in samples.
+  ///
+  /// `attack_length` must be non-negative and finite, else the attack is not
+  /// updated.
+  pub fn set_attack(&mut self, attack_length: T) {
+    if attack_length >= num::zero() && attack_length.is_finite() {
+      self.attack = attack_length;
+    }
+  }
+
+  /// Sets the release length, in samples.
+  ///
+  /// `release_length` must be non-negative and finite, else the release is
+  /// not updated.
+  pub fn set_release(&mut self, release_length: T) {
+    if release_length >= num::zero() && release_length.is_finite() {
+      self.release = release_length;
+    }
+  }
+
+  /// Returns `true` until the envelope has fully released.
+  pub fn is_active(&self) -> bool {
+    self.stage != Stage::Idle
+  }
+}
+
+impl<T> Generator<T> for Ar<T> where T: Float {
+  fn tick(&mut self) -> T {
+    let one: T = num::one();
+    match self.stage {
+      Stage::Idle => {
+        self.output = num::zero();
+      },
+      Stage::Attack => {
+        self.output = self.output + one / self.attack.max(one);
+        if self.output >= one {
+          self.output = one;
+          self.stage = Stage::Hold;
+        }
+      },
+      Stage::Hold => {
+        self.output = one;
+      },
+      Stage::Release => {
+        self.output = self.output - self.release_rate;
+        if self.output <= num::zero() {
+          self.output = num::zero();
+          self.stage = Stage::Idle;
+        }
+      }
+    }
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.stage = Stage::Idle;
+    self.release_rate = num::zero();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> Envelope<T> for Ar<T> where T: Float {
+  fn gate_on(&mut self) {
+    self.stage = Stage::Attack;
+  }
+
+  fn gate_off(&mut self) {
+    if self.stage != Stage::Idle {
+      self.release_rate = self.output / self.release.max(num::one());
+      self.stage = Stage::Release;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+  use ::envelope::GateEvent;
+  use ::traits::{Envelope, Generator};
+
+  #[test]
+  fn tick() {
+    let expected = vec![
+      0.25f32, 0.5f32, 0.75f32, 1f32,   // attack
+      1f32, 1f32,                       // hold
+      0.75f32, 0.5f32, 0.25f32, 0f32,   // release
+      0f32
+    ];
+    let mut envelope = Ar::new();
+    envelope.set_attack(4f32);
+    envelope.set_release(4f32);
+
+    envelope.gate_on();
+    for (i, sample) in expected.iter().enumerate() {
+      if i == 6 {
+        envelope.gate_off();
+      }
+      let actual = envelope.tick();
+      println!("{:.6} - {:.6} = {:.6}", sample, actual, sample - actual);
+      assert!((sample - actual).abs() < EPSILON);
+    }
+    assert!(!envelope.is_active());
+  }
+
+  #[test]
+  fn release_during_attack() {
+    let mut envelope = Ar::new();
+    envelope.set_attack(4f32);
+    envelope.set_release(2f32);
+
+    envelope.gate_on();
+    envelope.tick();
+    envelope.tick();
+    envelope.gate_off();
+    assert!((envelope.tick() - 0.25f32).abs() < EPSILON);
+    assert!((envelope.tick() - 0f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn render() {
+    let mut envelope = Ar::new();
+    envelope.set_attack(2f32);
+    envelope.set_release(2f32);
+
+    // Retriggering mid-block continues from the current level
+    let mut block = vec![0f32; 8];
+    envelope.render(&mut block, &[GateEvent::on(1), GateEvent::off(3), GateEvent::on(4)]);
+
+    let expected = vec![0f32, 0.5f32, 1f32, 0.5f32, 1f32, 1f32, 1f32, 1f32];
+    for (sample, actual) in expected.iter().zip(block.iter()) {
+      println!("{:.6} - {:.6} = {:.6}", sample, actual, sample - actual);
+      assert!((sample - actual).abs() < EPSILON);
+    }
+  }
+}
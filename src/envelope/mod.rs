@@ -0,0 +1,60 @@
+//! Gated envelope generators.
+//!
+//! An envelope is driven by a gate. Opening the gate with `gate_on()` starts
+//! the attack, and closing it with `gate_off()` starts the release. Samples
+//! are generated one at a time with `tick()`, or a block at a time with
+//! `render()`, which applies `GateEvent`s at their sample offsets within the
+//! block.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::envelope::{Adsr, GateEvent};
+//! use rasp::traits::Envelope;
+//!
+//! let sample_rate = 44100f32;
+//! let mut envelope = Adsr::new();
+//! envelope.set_attack(0.01f32 * sample_rate);  // 10 millisecond attack
+//! envelope.set_decay(0.1f32 * sample_rate);    // 100 millisecond decay
+//! envelope.set_sustain(0.7f32);
+//! envelope.set_release(0.3f32 * sample_rate);  // 300 millisecond release
+//!
+//! // The note starts 100 samples into the block and ends 300 samples later
+//! let mut block = vec![0f32; 512];
+//! envelope.render(&mut block, &[GateEvent::on(100), GateEvent::off(400)]);
+//! assert_eq!(block[99], 0f32);
+//! assert!(block[100] > 0f32);
+//! ```
+
+mod adsr;
+mod ar;
+
+pub use self::adsr::Adsr as Adsr;
+pub use self::ar::Ar     as Ar;
+
+/// A change of gate state at a sample offset within a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct GateEvent {
+  /// The offset, in samples, from the start of the block.
+  pub offset: usize,
+  /// Whether the gate is opened (`true`) or closed (`false`).
+  pub on: bool
+}
+
+impl GateEvent {
+  /// Creates an event that opens the gate at `offset`.
+  pub fn on(offset: usize) -> Self {
+    GateEvent {
+      offset,
+      on: true
+    }
+  }
+
+  /// Creates an event that closes the gate at `offset`.
+  pub fn off(offset: usize) -> Self {
+    GateEvent {
+      offset,
+      on: false
+    }
+  }
+}
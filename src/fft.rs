@@ -0,0 +1,129 @@
+//! A radix-2 fast Fourier transform, shared by the block based components,
+//! and the cross-correlation built on it.
+
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use traits::FloatConst;
+
+/// Transforms `buffer` in place, from time to frequency.
+///
+/// The length of `buffer` must be a power of two.
+pub fn forward<T: Float + FloatConst>(buffer: &mut [Complex<T>]) {
+  transform(buffer, -T::one());
+}
+
+/// Transforms `buffer` in place, from frequency to time, scaled so it
+/// reverses `forward()`.
+///
+/// The length of `buffer` must be a power of two.
+pub fn inverse<T: Float + FloatConst>(buffer: &mut [Complex<T>]) {
+  transform(buffer, T::one());
+  let scale = T::one() / num::cast(buffer.len()).unwrap();
+  for value in buffer.iter_mut() {
+    *value = *value * scale;
+  }
+}
+
+/// Returns the cross-correlation of `a` and `b` at each lag of `b` behind
+/// `a`, from zero up to the length of `b`, so element `k` is the sum of
+/// `a[n] * b[n + k]` over every `n`.
+pub fn cross_correlation<T: Float + FloatConst>(a: &[T], b: &[T]) -> Vec<T> {
+  let size = (a.len() + b.len()).next_power_of_two();
+  let spectrum = |signal: &[T]| {
+    let mut buffer = vec![Complex::new(T::zero(), T::zero()); size];
+    for (value, sample) in buffer.iter_mut().zip(signal.iter()) {
+      value.re = *sample;
+    }
+    forward(&mut buffer);
+    buffer
+  };
+  let mut buffer: Vec<Complex<T>> = spectrum(a).iter().zip(spectrum(b).iter())
+    .map(|(a, b)| a.conj() * *b)
+    .collect();
+  inverse(&mut buffer);
+  buffer.iter().take(b.len()).map(|value| value.re).collect()
+}
+
+/// An iterative, decimation in time transform, with the sign of the twiddle
+/// factor exponents given by `direction`.
+fn transform<T: Float + FloatConst>(buffer: &mut [Complex<T>], direction: T) {
+  let size = buffer.len();
+  debug_assert!(size.is_power_of_two());
+  if size < 2 {
+    return;
+  }
+
+  // Bit reversed ordering
+  let bits = size.trailing_zeros();
+  for i in 0..size {
+    let j = i.reverse_bits() >> (usize::BITS - bits);
+    if j > i {
+      buffer.swap(i, j);
+    }
+  }
+
+  let mut length = 2;
+  while length <= size {
+    let angle = direction * T::two() * T::pi() / num::cast(length).unwrap();
+    let step = Complex::new(angle.cos(), angle.sin());
+    for start in (0..size).step_by(length) {
+      let mut twiddle = Complex::new(T::one(), T::zero());
+      for k in 0..length / 2 {
+        let even = buffer[start + k];
+        let odd = buffer[start + k + length / 2] * twiddle;
+        buffer[start + k] = even + odd;
+        buffer[start + k + length / 2] = even - odd;
+        twiddle = twiddle * step;
+      }
+    }
+    length *= 2;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn matches_dft() {
+    let size = 16;
+    let signal: Vec<Complex<f64>> = (0..size)
+      .map(|n| Complex::new((n as f64 * 0.7f64).sin(), (n as f64 * 0.3f64).cos()))
+      .collect();
+    let mut spectrum = signal.clone();
+    forward(&mut spectrum);
+
+    for (k, bin) in spectrum.iter().enumerate() {
+      let expected = signal.iter().enumerate().fold(Complex::new(0f64, 0f64), |sum, (n, x)| {
+        let angle = -2f64 * ::std::f64::consts::PI * (k * n) as f64 / size as f64;
+        sum + x * Complex::new(angle.cos(), angle.sin())
+      });
+      assert!((bin - expected).norm() < 1e-9f64);
+    }
+
+    inverse(&mut spectrum);
+    for (x, y) in signal.iter().zip(spectrum.iter()) {
+      assert!((x - y).norm() < 1e-12f64);
+    }
+  }
+
+  #[test]
+  fn trivial_sizes() {
+    let mut single = vec![Complex::new(0.5f32, 0f32)];
+    forward(&mut single);
+    assert_eq!(single[0], Complex::new(0.5f32, 0f32));
+  }
+
+  #[test]
+  fn correlation() {
+    let a = [1f64, 2f64, -1f64];
+    let b = [0f64, 0f64, 1f64, 2f64, -1f64];
+    let expected = [-1f64, 0f64, 6f64, 0f64, -1f64];
+    for (x, y) in cross_correlation(&a, &b).iter().zip(expected.iter()) {
+      assert!((x - y).abs() < 1e-12f64);
+    }
+    assert!(cross_correlation::<f64>(&a, &[]).is_empty());
+  }
+}
@@ -0,0 +1,161 @@
+use num;
+use num::traits::Float;
+
+use delay::{Delay, DelayState};
+use traits::{Processor, StateSnapshot};
+use util;
+
+/// A Schroeder allpass filter, built around a delay line.
+///
+/// The filter combines a feedback and a feedforward comb sharing one delay
+/// line, `y[n] = -g·x[n] + x[n-M] + g·y[n-M]`, so its magnitude response is
+/// flat while its impulse response is a train of echoes decaying by `g`
+/// every `M` samples. Chained with mutually prime delays, such sections
+/// diffuse transients into a dense wash before a reverb, without coloring
+/// its tone.
+///
+/// Unlike the RBJ all-pass biquad, which shifts the phase around a single
+/// frequency, this filter shifts it around every multiple of the sample rate
+/// divided by the delay.
+#[derive(Clone)]
+pub struct AllpassDelay<T> {
+  delay: Delay<T>,
+  gain: T,
+  output: T
+}
+
+impl<T> AllpassDelay<T> where T: Float {
+  /// Creates a new `AllpassDelay` delaying by `delay` samples, up to
+  /// `max_delay` samples, with a coefficient of one half.
+  ///
+  /// `delay` must be at least one, and is clipped if it is greater than
+  /// `max_delay`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::AllpassDelay;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut allpass = AllpassDelay::new(2, 100);
+  /// let mut samples = vec![0f32; 5];
+  /// samples[0] = 1f32;
+  /// allpass.process_block(&mut samples);
+  /// assert_eq!(samples, vec![-0.5f32, 0f32, 0.75f32, 0f32, 0.375f32]);
+  /// ```
+  pub fn new(delay: usize, max_delay: usize) -> Self {
+    debug_assert!(delay >= 1 && max_delay >= 1);
+    AllpassDelay {
+      delay: Delay::new(delay, max_delay),
+      gain: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets the delay, in samples.
+  ///
+  /// `delay` must be at least one, else it is not updated. It is clipped if
+  /// it is greater than the maximum delay.
+  pub fn set_delay(&mut self, delay: usize) {
+    if delay >= 1 {
+      self.delay.set_delay(delay);
+    }
+  }
+
+  /// Returns the delay, in samples.
+  pub fn get_delay(&self) -> usize {
+    self.delay.get_delay()
+  }
+
+  /// Returns the maximum delay, in samples.
+  pub fn get_max_delay(&self) -> usize {
+    self.delay.get_max_delay()
+  }
+
+  /// Sets the coefficient `g`, which sets how slowly the echoes decay.
+  ///
+  /// `gain` must be greater than -1 and less than 1, else it is not updated.
+  pub fn set_gain(&mut self, gain: T) {
+    if gain.abs() < T::one() {
+      self.gain = gain;
+    }
+  }
+
+  /// Returns the coefficient `g`.
+  pub fn get_gain(&self) -> T {
+    self.gain
+  }
+}
+
+impl<T> Processor<T> for AllpassDelay<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let delayed = self.delay.next_out();
+    let input = util::flush_denormal(sample + self.gain * delayed);
+    self.delay.process(input);
+    self.output = delayed - self.gain * input;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.delay.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> StateSnapshot for AllpassDelay<T> where T: Float {
+  type State = (DelayState<T>, T);
+
+  fn snapshot(&self) -> Self::State {
+    (self.delay.snapshot(), self.output)
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.delay.restore(&state.0);
+    self.output = state.1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::Processor;
+
+  #[test]
+  fn flat_magnitude() {
+    // The energy of the impulse response is that of the impulse
+    let mut allpass = AllpassDelay::new(7, 10);
+    allpass.set_gain(0.7f64);
+    let energy: f64 = (0..10_000)
+      .map(|n| allpass.process(if n == 0 { 1f64 } else { 0f64 }).powi(2))
+      .sum();
+    assert!((energy - 1f64).abs() < 1e-9f64);
+
+    // A steady tone passes at unity gain
+    for gain in [-0.6f64, 0.3f64, 0.9f64].iter() {
+      let mut allpass = AllpassDelay::new(13, 20);
+      allpass.set_gain(*gain);
+      let output: Vec<f64> = (0..20_000).map(|n| allpass.process((n as f64 * 0.37f64).sin())).collect();
+      let peak = output[10_000..].iter().fold(0f64, |peak, sample| peak.max(sample.abs()));
+      assert!((peak - 1f64).abs() < 1e-3f64);
+    }
+  }
+
+  #[test]
+  fn parameters() {
+    let mut allpass = AllpassDelay::<f32>::new(5, 100);
+    allpass.set_gain(-1f32);
+    assert_eq!(allpass.get_gain(), 0.5f32);
+    allpass.set_delay(0);
+    assert_eq!(allpass.get_delay(), 5);
+    allpass.set_delay(200);
+    assert_eq!(allpass.get_delay(), allpass.get_max_delay());
+    allpass.process(1f32);
+    allpass.clear();
+    assert_eq!(allpass.last_out(), 0f32);
+    assert_eq!(allpass.process(0f32), 0f32);
+  }
+}
@@ -1,9 +1,12 @@
 //! A biquad is a second-order recursive filter.
 
 use num;
+use num::Complex;
 use num::traits::Float;
 
-use traits::Processor;
+use filter::response::{evaluate, zeros_and_poles};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
+use util;
 
 /* Notes on biquads
   - A biquad is a recursive second-order IIR filter and is often used as a
@@ -17,6 +20,56 @@ use traits::Processor;
       it good for floating point signals since values can't overflow.
 */
 
+/// A set of normalized biquad coefficients.
+///
+/// `b0`, `b1`, `b2` are feedforwards, or zeroes, and `a1`, `a2` are
+/// feedbacks, or poles. `a0` is normalized to one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiquadCoefficients<T> {
+  pub b0: T,
+  pub b1: T,
+  pub b2: T,
+  pub a1: T,
+  pub a2: T
+}
+
+impl<T> FrequencyResponse<T> for BiquadCoefficients<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for BiquadCoefficients<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2]).0
+  }
+}
+
+/// The order of error feedback used to shape rounding errors in `Biquad1`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ErrorFeedback {
+  /// Rounding errors are not fed back.
+  None,
+  /// The last rounding error is fed back, shaping the error by
+  /// `1 - z^-1`.
+  FirstOrder,
+  /// The last two rounding errors are fed back, shaping the error by
+  /// `(1 - z^-1)^2`.
+  SecondOrder
+}
+
+/// Returns the sum of `a` and `b`, and the rounding error of the sum.
+#[inline]
+fn two_sum<T: Float>(a: T, b: T) -> (T, T) {
+  let sum = a + b;
+  let b_virtual = sum - a;
+  (sum, (a - (sum - b_virtual)) + (b - b_virtual))
+}
+
 /// A biquad filter in direct from 1.
 ///
 /// This implementation uses a [Direct Form I](https://en.wikipedia.org/wiki/Digital_biquad_filter#Direct_Form_1)
@@ -26,11 +79,21 @@ use traits::Processor;
 ///
 /// It has two feedforward coefficients, `b1` and `b2`, and two feedback
 /// coefficients, `a1` and `a2`.
+///
+/// Rounding errors accumulate in the feedback path, which is significant in
+/// `f32` for low cutoff frequencies at high sample rates. Error feedback can
+/// be enabled to measure the rounding error of each output and feed it back
+/// into the next, shaping the error away from low frequencies.
+#[derive(Clone)]
 pub struct Biquad1<T> {
   x_z1: T,
   x_z2: T,
   y_z1: T,
   y_z2: T,
+  // Rounding errors of previous outputs
+  e_z1: T,
+  e_z2: T,
+  error_feedback: ErrorFeedback,
   pub b0: T,
   pub b1: T,
   pub b2: T,
@@ -61,6 +124,9 @@ impl<T> Biquad1<T> where T: Float {
       x_z2: num::zero(),
       y_z1: num::zero(),
       y_z2: num::zero(),
+      e_z1: num::zero(),
+      e_z2: num::zero(),
+      error_feedback: ErrorFeedback::None,
       b0: num::one(),
       b1: num::zero(),
       b2: num::zero(),
@@ -80,17 +146,85 @@ impl<T> Biquad1<T> where T: Float {
     self.a1 = a1;
     self.a2 = a2;
   }
+
+  /// Sets all filter coefficients at once from a `BiquadCoefficients`.
+  ///
+  /// The filter memory is not cleared.
+  pub fn load_coefficients(&mut self, coefficients: BiquadCoefficients<T>) {
+    self.set_coefficients(coefficients.b0,
+                          coefficients.b1,
+                          coefficients.b2,
+                          coefficients.a1,
+                          coefficients.a2);
+  }
+
+  /// Sets the order of error feedback.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::{Biquad1, ErrorFeedback};
+  ///
+  /// let mut filter = Biquad1::<f32>::new();
+  /// filter.set_error_feedback(ErrorFeedback::FirstOrder);
+  /// ```
+  pub fn set_error_feedback(&mut self, error_feedback: ErrorFeedback) {
+    self.error_feedback = error_feedback;
+    self.e_z1 = num::zero();
+    self.e_z2 = num::zero();
+  }
+
+  /// Returns the order of error feedback.
+  pub fn get_error_feedback(&self) -> ErrorFeedback {
+    self.error_feedback
+  }
+
+  /// Computes the output with error feedback, and stores its rounding error.
+  fn process_error_feedback(&mut self, sample: T) -> T {
+    let feedback =
+      match self.error_feedback {
+        ErrorFeedback::None => T::zero(),
+        ErrorFeedback::FirstOrder => self.e_z1,
+        ErrorFeedback::SecondOrder => self.e_z1 + self.e_z1 - self.e_z2
+      };
+
+    let terms = [
+      (self.b0, sample),
+      (self.b1, self.x_z1),
+      (self.b2, self.x_z2),
+      (-self.a1, self.y_z1),
+      (-self.a2, self.y_z2)
+    ];
+    let mut output = feedback;
+    let mut error = T::zero();
+    for &(coefficient, value) in terms.iter() {
+      let product = coefficient * value;
+      let (sum, sum_error) = two_sum(output, product);
+      output = sum;
+      error = error + sum_error + coefficient.mul_add(value, -product);
+    }
+
+    self.e_z2 = self.e_z1;
+    self.e_z1 = error;
+    output
+  }
 }
 
 impl<T> Processor<T> for Biquad1<T> where T: Float {
   fn process(&mut self, sample: T) -> T {
-    let output = self.b0 * sample
-      + self.b1 * self.x_z1 + self.b2 * self.x_z2
-      - self.a1 * self.y_z1 - self.a2 * self.y_z2;
+    let output =
+      if self.error_feedback == ErrorFeedback::None {
+        self.b0 * sample
+          + self.b1 * self.x_z1 + self.b2 * self.x_z2
+          - self.a1 * self.y_z1 - self.a2 * self.y_z2
+      }
+      else {
+        self.process_error_feedback(sample)
+      };
     self.x_z2 = self.x_z1;
     self.x_z1 = sample;
     self.y_z2 = self.y_z1;
-    self.y_z1 = output;
+    self.y_z1 = util::flush_denormal(output);
     output
   }
 
@@ -99,6 +233,8 @@ impl<T> Processor<T> for Biquad1<T> where T: Float {
     self.x_z2 = num::zero();
     self.y_z1 = num::zero();
     self.y_z2 = num::zero();
+    self.e_z1 = num::zero();
+    self.e_z2 = num::zero();
   }
 
   fn last_out(&self) -> T {
@@ -106,6 +242,39 @@ impl<T> Processor<T> for Biquad1<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for Biquad1<T> where T: Float {
+  type State = [T; 6];
+
+  fn snapshot(&self) -> Self::State {
+    [self.x_z1, self.x_z2, self.y_z1, self.y_z2, self.e_z1, self.e_z2]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.x_z1 = state[0];
+    self.x_z2 = state[1];
+    self.y_z1 = state[2];
+    self.y_z2 = state[3];
+    self.e_z1 = state[4];
+    self.e_z2 = state[5];
+  }
+}
+
+impl<T> FrequencyResponse<T> for Biquad1<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for Biquad1<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2]).0
+  }
+}
+
 /// A biquad filter in transposed direct form 2.
 ///
 /// This implementation uses a Transposed [Direct Form II](https://en.wikipedia.org/wiki/Digital_biquad_filter#Direct_Form_2)
@@ -115,6 +284,7 @@ impl<T> Processor<T> for Biquad1<T> where T: Float {
 ///
 /// It has two feedforward coefficients, `b1` and `b2`, and two feedback
 /// coefficients, `a1` and `a2`.
+#[derive(Clone, Debug, PartialEq)]
 pub struct Biquad2<T> {
   z1: T,
   z2: T,
@@ -167,13 +337,24 @@ impl<T> Biquad2<T> where T: Float {
     self.a1 = a1;
     self.a2 = a2;
   }
+
+  /// Sets all filter coefficients at once from a `BiquadCoefficients`.
+  ///
+  /// The filter memory is not cleared.
+  pub fn load_coefficients(&mut self, coefficients: BiquadCoefficients<T>) {
+    self.set_coefficients(coefficients.b0,
+                          coefficients.b1,
+                          coefficients.b2,
+                          coefficients.a1,
+                          coefficients.a2);
+  }
 }
 
 impl<T> Processor<T> for Biquad2<T> where T: Float {
   fn process(&mut self, sample: T) -> T {
     self.output = self.b0 * sample + self.z1;
-    self.z1 = self.b1 * sample + self.z2 - self.a1 * self.output;
-    self.z2 = self.b2 * sample - self.a2 * self.output;
+    self.z1 = util::flush_denormal(self.b1 * sample + self.z2 - self.a1 * self.output);
+    self.z2 = util::flush_denormal(self.b2 * sample - self.a2 * self.output);
     self.output
   }
 
@@ -188,6 +369,36 @@ impl<T> Processor<T> for Biquad2<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for Biquad2<T> where T: Float {
+  type State = [T; 3];
+
+  fn snapshot(&self) -> Self::State {
+    [self.z1, self.z2, self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.z1 = state[0];
+    self.z2 = state[1];
+    self.output = state[2];
+  }
+}
+
+impl<T> FrequencyResponse<T> for Biquad2<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for Biquad2<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one(), self.a1, self.a2]).0
+  }
+}
+
 #[cfg(test)]
 mod form1 {
   use super::*;
@@ -250,13 +461,51 @@ mod form1 {
       assert!((expected[i] - actual[i]).abs() < EPSILON);
     }
   }
+
+  #[test]
+  fn error_feedback() {
+    // A 30 Hz lowpass at 192 kHz, with coefficients rounded to f32
+    let sample_rate = 192_000f64;
+    let w0 = 2f64 * ::std::f64::consts::PI * 30f64 / sample_rate;
+    let alpha = w0.sin() / (2f64 * ::std::f64::consts::FRAC_1_SQRT_2);
+    let a0 = 1f64 + alpha;
+    let round = |value: f64| value as f32 as f64;
+    let b0 = round((1f64 - w0.cos()) / 2f64 / a0);
+    let b1 = round((1f64 - w0.cos()) / a0);
+    let a1 = round(-2f64 * w0.cos() / a0);
+    let a2 = round((1f64 - alpha) / a0);
+
+    let mut reference = Biquad1::<f64>::new();
+    reference.set_coefficients(b0, b1, b0, a1, a2);
+
+    let mut errors = Vec::new();
+    let orders = [ErrorFeedback::None, ErrorFeedback::FirstOrder, ErrorFeedback::SecondOrder];
+    for order in orders.iter() {
+      let mut biquad = Biquad1::<f32>::new();
+      biquad.set_coefficients(b0 as f32, b1 as f32, b0 as f32, a1 as f32, a2 as f32);
+      biquad.set_error_feedback(*order);
+      assert_eq!(biquad.get_error_feedback(), *order);
+      reference.clear();
+
+      let mut error = 0f64;
+      for n in 0..sample_rate as usize {
+        let input = round(0.5f64 * (2f64 * ::std::f64::consts::PI * 20f64 * n as f64 / sample_rate).sin());
+        let difference = biquad.process(input as f32) as f64 - reference.process(input);
+        error += difference * difference;
+      }
+      errors.push(error.sqrt());
+    }
+
+    assert!(errors[1] < errors[0] * 1e-2f64);
+    assert!(errors[2] < errors[1]);
+  }
 }
 
 #[cfg(test)]
 mod form2 {
   use super::*;
   use std::f32::EPSILON;
-  use ::traits::Processor;
+  use ::traits::{Processor, StateSnapshot};
 
   #[test]
   fn process() {
@@ -314,4 +563,41 @@ mod form2 {
       assert!((expected[i] - actual[i]).abs() < EPSILON);
     }
   }
-}
\ No newline at end of file
+
+  #[test]
+  fn denormals() {
+    // The tail of a decaying pole passes through the subnormal range, unless
+    // it is flushed to zero
+    let mut filter = Biquad2::new();
+    filter.set_coefficients(1f32, 0f32, 0f32, -0.5f32, 0f32);
+    filter.process(1f32);
+    let tail: Vec<f32> = (0..200).map(|_| filter.process(0f32)).collect();
+    let subnormals = tail.iter().filter(|sample| **sample != 0f32 && !sample.is_normal()).count();
+    if cfg!(feature = "denormal-protection") {
+      assert_eq!(subnormals, 0);
+    }
+    else {
+      assert_eq!(subnormals, 23);
+    }
+    assert_eq!(filter.last_out(), 0f32);
+  }
+
+  #[test]
+  fn snapshot() {
+    let mut filter = Biquad2::new();
+    filter.set_coefficients(0.5f64, 0.2f64, 0.1f64, -0.3f64, 0.2f64);
+    filter.process(1f64);
+    let snapshot = filter.snapshot();
+    let expected: Vec<f64> = (0..4).map(|_| filter.process(0f64)).collect();
+
+    // Restoring keeps the coefficients
+    filter.clear();
+    filter.b0 = 0f64;
+    filter.restore(&snapshot);
+    assert_eq!(filter.b0, 0f64);
+    assert_eq!(filter.last_out(), 0.5f64);
+    filter.b0 = 0.5f64;
+    let output: Vec<f64> = (0..4).map(|_| filter.process(0f64)).collect();
+    assert_eq!(output, expected);
+  }
+}
@@ -0,0 +1,283 @@
+use num;
+use num::traits::Float;
+
+/// The number of fractional bits of the fixed point registers.
+const FRACTION_BITS: i32 = 24;
+
+/// Converts a sample to the fixed point format of the registers.
+fn to_fixed<T: Float>(sample: T) -> i64 {
+  let scale: T = num::cast(1i64 << FRACTION_BITS).unwrap();
+  num::cast((sample * scale).round()).unwrap_or(0)
+}
+
+/// Converts a register to a sample, removing the gain of the filter.
+fn to_sample<T: Float>(register: i64, gain: T) -> T {
+  let scale: T = num::cast(1i64 << FRACTION_BITS).unwrap();
+  num::cast::<i64, T>(register).unwrap() / (scale * gain)
+}
+
+/// Checks that the growth of the registers fits in 64 bits, leaving 8 bits
+/// for input over full scale.
+fn fits(factor: usize, stages: usize) -> bool {
+  let growth = stages as f64 * (factor as f64).log2().ceil();
+  growth + f64::from(FRACTION_BITS) + 8f64 < 63f64
+}
+
+/// A cascaded integrator-comb (CIC) decimator.
+///
+/// A CIC filter reduces the sample rate by an integer `factor` using only
+/// additions, which suits embedded targets without fast multipliers. It has
+/// the response of `stages` cascaded moving averages of `factor` samples,
+/// computed as integrators at the input rate, followed by combs at the output
+/// rate. More stages reject more of the aliased content, but droop more
+/// across the passband.
+///
+/// Samples are converted to 24-bit fixed point and the registers wrap around,
+/// as in hardware, so the integrators never lose precision however long they
+/// run. The output is normalized to unity gain at DC.
+pub struct CicDecimator<T> {
+  factor: usize,
+  gain: T,
+  integrators: Vec<i64>,
+  // The previous input of each comb
+  combs: Vec<i64>,
+  phase: usize,
+  output: T
+}
+
+impl<T> CicDecimator<T> where T: Float {
+  /// Creates a new `CicDecimator` reducing the sample rate by `factor`, with
+  /// `stages` integrators and combs.
+  ///
+  /// `factor` and `stages` must be at least one, and the registers must
+  /// grow by less than 31 bits, which is `stages * log2(factor)`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::CicDecimator;
+  ///
+  /// let mut decimator = CicDecimator::new(4, 3);
+  /// let output = decimator.decimate(&[0.5f32; 64]);
+  /// assert_eq!(output.len(), 16);
+  /// assert_eq!(output[15], 0.5f32);
+  /// ```
+  pub fn new(factor: usize, stages: usize) -> Self {
+    debug_assert!(factor >= 1 && stages >= 1);
+    debug_assert!(fits(factor, stages));
+    CicDecimator {
+      factor,
+      gain: num::cast((factor as f64).powi(stages as i32)).unwrap(),
+      integrators: vec![0; stages],
+      combs: vec![0; stages],
+      phase: 0,
+      output: num::zero()
+    }
+  }
+
+  /// Returns the decimation factor.
+  pub fn get_factor(&self) -> usize {
+    self.factor
+  }
+
+  /// Processes a sample, returning an output sample once every `factor`
+  /// samples.
+  pub fn process(&mut self, sample: T) -> Option<T> {
+    let mut x = to_fixed(sample);
+    for integrator in self.integrators.iter_mut() {
+      *integrator = integrator.wrapping_add(x);
+      x = *integrator;
+    }
+
+    self.phase += 1;
+    if self.phase < self.factor {
+      return None;
+    }
+    self.phase = 0;
+
+    for previous in self.combs.iter_mut() {
+      let y = x.wrapping_sub(*previous);
+      *previous = x;
+      x = y;
+    }
+    self.output = to_sample(x, self.gain);
+    Some(self.output)
+  }
+
+  /// Returns the decimated `input`, which has one sample for every `factor`
+  /// input samples.
+  pub fn decimate(&mut self, input: &[T]) -> Vec<T> {
+    input.iter().filter_map(|sample| self.process(*sample)).collect()
+  }
+
+  /// Resets every register to zero.
+  pub fn clear(&mut self) {
+    for register in self.integrators.iter_mut().chain(self.combs.iter_mut()) {
+      *register = 0;
+    }
+    self.phase = 0;
+    self.output = num::zero();
+  }
+
+  /// Returns the last output sample.
+  pub fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+/// A cascaded integrator-comb (CIC) interpolator.
+///
+/// The counterpart of the `CicDecimator`, raising the sample rate by an
+/// integer `factor`, with combs at the input rate, followed by zero stuffing
+/// and integrators at the output rate. The output is normalized to unity gain
+/// at DC.
+pub struct CicInterpolator<T> {
+  gain: T,
+  combs: Vec<i64>,
+  integrators: Vec<i64>,
+  outputs: Vec<T>
+}
+
+impl<T> CicInterpolator<T> where T: Float {
+  /// Creates a new `CicInterpolator` raising the sample rate by `factor`,
+  /// with `stages` combs and integrators.
+  ///
+  /// `factor` and `stages` must be at least one, and the registers must
+  /// grow by less than 31 bits, which is `stages * log2(factor)`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::CicInterpolator;
+  ///
+  /// let mut interpolator = CicInterpolator::new(4, 3);
+  /// let output = interpolator.interpolate(&[0.5f32; 16]);
+  /// assert_eq!(output.len(), 64);
+  /// assert_eq!(output[63], 0.5f32);
+  /// ```
+  pub fn new(factor: usize, stages: usize) -> Self {
+    debug_assert!(factor >= 1 && stages >= 1);
+    debug_assert!(fits(factor, stages));
+    CicInterpolator {
+      gain: num::cast((factor as f64).powi(stages as i32 - 1)).unwrap(),
+      combs: vec![0; stages],
+      integrators: vec![0; stages],
+      outputs: vec![num::zero(); factor]
+    }
+  }
+
+  /// Returns the interpolation factor.
+  pub fn get_factor(&self) -> usize {
+    self.outputs.len()
+  }
+
+  /// Processes a sample, returning the `factor` output samples it is
+  /// interpolated to.
+  pub fn process(&mut self, sample: T) -> &[T] {
+    let mut x = to_fixed(sample);
+    for previous in self.combs.iter_mut() {
+      let y = x.wrapping_sub(*previous);
+      *previous = x;
+      x = y;
+    }
+
+    for (n, output) in self.outputs.iter_mut().enumerate() {
+      // The input is followed by zeros at the higher rate
+      let mut y = if n == 0 { x } else { 0 };
+      for integrator in self.integrators.iter_mut() {
+        *integrator = integrator.wrapping_add(y);
+        y = *integrator;
+      }
+      *output = to_sample(y, self.gain);
+    }
+    &self.outputs
+  }
+
+  /// Returns the interpolated `input`, which has `factor` samples for every
+  /// input sample.
+  pub fn interpolate(&mut self, input: &[T]) -> Vec<T> {
+    let mut output = Vec::with_capacity(input.len() * self.outputs.len());
+    for sample in input {
+      output.extend_from_slice(self.process(*sample));
+    }
+    output
+  }
+
+  /// Resets every register to zero.
+  pub fn clear(&mut self) {
+    for register in self.combs.iter_mut().chain(self.integrators.iter_mut()) {
+      *register = 0;
+    }
+    for output in self.outputs.iter_mut() {
+      *output = num::zero();
+    }
+  }
+
+  /// Returns the last output sample.
+  pub fn last_out(&self) -> T {
+    self.outputs[self.outputs.len() - 1]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Applies `stages` moving averages of `length` samples.
+  fn moving_averages(input: &[f64], length: usize, stages: usize) -> Vec<f64> {
+    let mut signal = input.to_vec();
+    for _ in 0..stages {
+      signal = (0..signal.len())
+        .map(|n| signal[n.saturating_sub(length - 1)..n + 1].iter().sum::<f64>() / length as f64)
+        .collect();
+    }
+    signal
+  }
+
+  #[test]
+  fn decimator() {
+    let (factor, stages) = (5, 3);
+    let input: Vec<f64> = (0..200).map(|n| (n as f64 * 0.07f64).sin() * 0.8f64).collect();
+    let expected = moving_averages(&input, factor, stages);
+    let output = CicDecimator::new(factor, stages).decimate(&input);
+    assert_eq!(output.len(), 40);
+    for (m, sample) in output.iter().enumerate() {
+      assert!((sample - expected[m * factor + factor - 1]).abs() < 1e-6f64);
+    }
+  }
+
+  #[test]
+  fn interpolator() {
+    let (factor, stages) = (4, 2);
+    let input: Vec<f64> = (0..50).map(|n| (n as f64 * 0.2f64).cos() * 0.5f64).collect();
+    // Zero stuffed, then averaged and scaled by the factor
+    let mut stuffed = vec![0f64; input.len() * factor];
+    for (n, sample) in input.iter().enumerate() {
+      stuffed[n * factor] = *sample * factor as f64;
+    }
+    let expected = moving_averages(&stuffed, factor, stages);
+    let output = CicInterpolator::new(factor, stages).interpolate(&input);
+    assert_eq!(output.len(), expected.len());
+    for (sample, expected) in output.iter().zip(expected.iter()) {
+      assert!((sample - expected).abs() < 1e-6f64);
+    }
+  }
+
+  #[test]
+  fn long_running() {
+    // The integrators wrap around without affecting the output
+    let mut decimator = CicDecimator::new(16, 4);
+    for _ in 0..100_000 {
+      decimator.process(0.9f32);
+    }
+    assert!((decimator.last_out() - 0.9f32).abs() < 1e-6f32);
+
+    decimator.clear();
+    assert_eq!(decimator.decimate(&[0f32; 16]), vec![0f32]);
+
+    let mut interpolator = CicInterpolator::new(3, 2);
+    interpolator.process(1f32);
+    interpolator.clear();
+    assert_eq!(interpolator.process(0f32), &[0f32, 0f32, 0f32]);
+  }
+}
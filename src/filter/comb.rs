@@ -0,0 +1,213 @@
+use num;
+use num::traits::Float;
+
+use delay::{Delay, DelayState};
+use traits::{Processor, StateSnapshot};
+use util;
+
+/// The form of a `Comb` filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CombMode {
+  /// Adds the delayed input, `y[n] = x[n] + g·x[n-M]`, notching the
+  /// spectrum at odd multiples of half the delay frequency for positive gains.
+  FeedForward,
+  /// Adds the delayed output, `y[n] = x[n] + g·y[n-M]`, resonating at
+  /// multiples of the delay frequency for positive gains.
+  Feedback
+}
+
+/// A comb filter, adding a delayed copy of its input or output.
+///
+/// The peaks and notches of a comb filter are spaced evenly across the
+/// spectrum, at multiples of the sample rate divided by the delay length,
+/// which gives them their name. Combs are the building block of flangers, and feedback
+/// combs of the early Schroeder reverbs.
+///
+/// The delay is an integer number of samples held in a `Delay`, so it can be
+/// changed up to its maximum without allocating.
+#[derive(Clone)]
+pub struct Comb<T> {
+  delay: Delay<T>,
+  mode: CombMode,
+  gain: T,
+  output: T
+}
+
+impl<T> Comb<T> where T: Float {
+  /// Creates a new `Comb` filter of the given form, delaying by `delay`
+  /// samples, up to `max_delay` samples, with a gain of one half.
+  ///
+  /// `delay` must be at least one, and is clipped if it is greater than
+  /// `max_delay`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::{Comb, CombMode};
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut comb = Comb::new(2, 100, CombMode::Feedback);
+  /// let mut samples = vec![0f32; 7];
+  /// samples[0] = 1f32;
+  /// comb.process_block(&mut samples);
+  /// assert_eq!(samples, vec![1f32, 0f32, 0.5f32, 0f32, 0.25f32, 0f32, 0.125f32]);
+  /// ```
+  pub fn new(delay: usize, max_delay: usize, mode: CombMode) -> Self {
+    debug_assert!(delay >= 1 && max_delay >= 1);
+    Comb {
+      delay: Delay::new(delay, max_delay),
+      mode,
+      gain: num::cast(0.5f64).unwrap(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets the delay, in samples.
+  ///
+  /// `delay` must be at least one, else it is not updated. It is clipped if
+  /// it is greater than the maximum delay.
+  pub fn set_delay(&mut self, delay: usize) {
+    if delay >= 1 {
+      self.delay.set_delay(delay);
+    }
+  }
+
+  /// Returns the delay, in samples.
+  pub fn get_delay(&self) -> usize {
+    self.delay.get_delay()
+  }
+
+  /// Returns the maximum delay, in samples.
+  pub fn get_max_delay(&self) -> usize {
+    self.delay.get_max_delay()
+  }
+
+  /// Sets the gain of the delayed signal.
+  ///
+  /// `gain` must be greater than -1 and less than 1, else it is not updated.
+  /// Negative gains swap the peaks and notches of the response.
+  pub fn set_gain(&mut self, gain: T) {
+    if gain.abs() < T::one() {
+      self.gain = gain;
+    }
+  }
+
+  /// Returns the gain of the delayed signal.
+  pub fn get_gain(&self) -> T {
+    self.gain
+  }
+
+  /// Sets the form of the filter, keeping the delayed signal.
+  pub fn set_mode(&mut self, mode: CombMode) {
+    self.mode = mode;
+  }
+
+  /// Returns the form of the filter.
+  pub fn get_mode(&self) -> CombMode {
+    self.mode
+  }
+}
+
+impl<T> Processor<T> for Comb<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.output =
+      match self.mode {
+        CombMode::FeedForward => sample + self.gain * self.delay.process(sample),
+        CombMode::Feedback => {
+          let output = util::flush_denormal(sample + self.gain * self.delay.next_out());
+          self.delay.process(output);
+          output
+        }
+      };
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.delay.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> StateSnapshot for Comb<T> where T: Float {
+  type State = (DelayState<T>, T);
+
+  fn snapshot(&self) -> Self::State {
+    (self.delay.snapshot(), self.output)
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.delay.restore(&state.0);
+    self.output = state.1;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::{Processor, StateSnapshot};
+
+  fn impulse_response(comb: &mut Comb<f64>, length: usize) -> Vec<f64> {
+    (0..length).map(|n| comb.process(if n == 0 { 1f64 } else { 0f64 })).collect()
+  }
+
+  #[test]
+  fn feed_forward() {
+    let mut comb = Comb::new(3, 10, CombMode::FeedForward);
+    comb.set_gain(-0.5f64);
+    assert_eq!(impulse_response(&mut comb, 8), vec![1f64, 0f64, 0f64, -0.5f64, 0f64, 0f64, 0f64, 0f64]);
+
+    // A delay of one sample, at unity gain, nulls the Nyquist frequency
+    let mut comb = Comb::new(1, 10, CombMode::FeedForward);
+    comb.set_gain(0.999_999f64);
+    let output: Vec<f64> = (0..16).map(|n| comb.process(if n % 2 == 0 { 1f64 } else { -1f64 })).collect();
+    assert!(output[1..].iter().all(|sample| sample.abs() < 1e-5f64));
+  }
+
+  #[test]
+  fn feedback() {
+    let mut comb = Comb::new(2, 10, CombMode::Feedback);
+    comb.set_gain(0.9f64);
+    let response = impulse_response(&mut comb, 2_000);
+    assert!((response[4] - 0.81f64).abs() < 1e-12f64);
+    assert_eq!(response[5], 0f64);
+    assert!(response[1_990].abs() < 1e-40f64);
+
+    // Delays are clipped to the maximum, and cannot be zero
+    comb.clear();
+    comb.set_delay(20);
+    assert_eq!(comb.get_delay(), 10);
+    comb.set_delay(0);
+    assert_eq!(comb.get_delay(), 10);
+    let response = impulse_response(&mut comb, 21);
+    assert!((response[10] - 0.9f64).abs() < 1e-12f64);
+    assert!((response[20] - 0.81f64).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut comb = Comb::<f32>::new(5, 100, CombMode::Feedback);
+    comb.set_gain(1f32);
+    assert_eq!(comb.get_gain(), 0.5f32);
+    comb.set_mode(CombMode::FeedForward);
+    assert_eq!(comb.get_mode(), CombMode::FeedForward);
+    assert_eq!(comb.get_max_delay(), 100);
+    comb.process(1f32);
+    comb.clear();
+    assert_eq!(comb.last_out(), 0f32);
+  }
+
+  #[test]
+  fn snapshot() {
+    let mut comb = Comb::new(3, 10, CombMode::Feedback);
+    comb.process(1f64);
+    let snapshot = comb.snapshot();
+    let expected = impulse_response(&mut comb, 10);
+    comb.restore(&snapshot);
+    assert_eq!(comb.last_out(), 1f64);
+    assert_eq!(impulse_response(&mut comb, 10), expected);
+  }
+}
@@ -0,0 +1,213 @@
+use num;
+use num::traits::Float;
+
+use filter::Biquad2;
+use filter::rbj::{AllPass, HighPass, LowPass};
+use traits::{FloatConst, Processor};
+
+/// A fourth order Linkwitz-Riley split, as two cascaded Butterworth biquads
+/// for each of the low and high outputs.
+struct Split<T> {
+  lowpass: [Biquad2<T>; 2],
+  highpass: [Biquad2<T>; 2]
+}
+
+/// Splits a signal into bands with Linkwitz-Riley crossovers.
+///
+/// Each crossover is a fourth order Linkwitz-Riley split, so the bands either
+/// side of it are 6 dB down at the crossover frequency and in phase. The
+/// signal is split at the lowest frequency first, with the high output split
+/// again at each higher frequency. Every band below a split is passed through
+/// an all-pass filter matching the phase of that split, so the bands sum to a
+/// signal with a flat magnitude response, which is only delayed in phase.
+pub struct Crossover<T> {
+  frequencies: Vec<T>,
+  splits: Vec<Split<T>>,
+  // The all-pass filters of each band, for every split above it
+  compensation: Vec<Vec<Biquad2<T>>>,
+  outputs: Vec<T>
+}
+
+impl<T> Crossover<T> where T: Float + FloatConst {
+  /// Creates a new `Crossover` splitting signals at `sample_rate` at each of
+  /// `frequencies`, into one more band than there are frequencies.
+  ///
+  /// `frequencies` must be in increasing order, and below Nyquist.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::Crossover;
+  ///
+  /// let mut crossover = Crossover::new(44_100f32, &[200f32, 2_000f32, 8_000f32]);
+  /// assert_eq!(crossover.bands(), 4);
+  ///
+  /// let bands = crossover.tick(1f32);
+  /// assert_eq!(bands.len(), 4);
+  /// ```
+  pub fn new(sample_rate: T, frequencies: &[T]) -> Self {
+    debug_assert!(frequencies.windows(2).all(|pair| pair[0] < pair[1]));
+    debug_assert!(frequencies.iter().all(|f| *f > T::zero() && *f < sample_rate / T::two()));
+    let q: T = num::cast(0.5f64.sqrt()).unwrap();
+
+    let splits = frequencies.iter()
+      .map(|frequency| {
+        let lowpass = LowPass::coefficients(sample_rate, *frequency, q);
+        let highpass = HighPass::coefficients(sample_rate, *frequency, q);
+        let mut split = Split {
+          lowpass: [Biquad2::new(), Biquad2::new()],
+          highpass: [Biquad2::new(), Biquad2::new()]
+        };
+        for biquad in split.lowpass.iter_mut() {
+          biquad.load_coefficients(lowpass);
+        }
+        for biquad in split.highpass.iter_mut() {
+          biquad.load_coefficients(highpass);
+        }
+        split
+      })
+      .collect();
+
+    // A fourth order Linkwitz-Riley split sums to a second order all-pass
+    let compensation = (0..frequencies.len() + 1)
+      .map(|band| {
+        frequencies.iter().skip(band + 1)
+          .map(|frequency| {
+            let mut allpass = Biquad2::new();
+            allpass.load_coefficients(AllPass::coefficients(sample_rate, *frequency, q));
+            allpass
+          })
+          .collect()
+      })
+      .collect();
+
+    Crossover {
+      frequencies: frequencies.to_vec(),
+      splits,
+      compensation,
+      outputs: vec![T::zero(); frequencies.len() + 1]
+    }
+  }
+
+  /// Returns the number of bands.
+  pub fn bands(&self) -> usize {
+    self.outputs.len()
+  }
+
+  /// Returns the crossover frequencies, in Hz.
+  pub fn get_frequencies(&self) -> &[T] {
+    &self.frequencies
+  }
+
+  /// Splits a sample, returning the output of each band from lowest to
+  /// highest.
+  pub fn tick(&mut self, sample: T) -> &[T] {
+    let mut remainder = sample;
+    for (band, split) in self.splits.iter_mut().enumerate() {
+      let low = split.lowpass.iter_mut().fold(remainder, |x, biquad| biquad.process(x));
+      remainder = split.highpass.iter_mut().fold(remainder, |x, biquad| biquad.process(x));
+      self.outputs[band] = self.compensation[band].iter_mut().fold(low, |x, allpass| allpass.process(x));
+    }
+    let last = self.outputs.len() - 1;
+    self.outputs[last] = remainder;
+    &self.outputs
+  }
+
+  /// Splits a block of samples into a buffer for each band, from lowest to
+  /// highest.
+  ///
+  /// There must be a buffer for each band, and each must be at least as long
+  /// as `input`.
+  pub fn process_block(&mut self, input: &[T], outputs: &mut [&mut [T]]) {
+    debug_assert_eq!(outputs.len(), self.bands());
+    debug_assert!(outputs.iter().all(|output| output.len() >= input.len()));
+    for (n, sample) in input.iter().enumerate() {
+      self.tick(*sample);
+      for (output, band) in outputs.iter_mut().zip(self.outputs.iter()) {
+        output[n] = *band;
+      }
+    }
+  }
+
+  /// Clears the memory of every filter.
+  pub fn clear(&mut self) {
+    for split in self.splits.iter_mut() {
+      for biquad in split.lowpass.iter_mut().chain(split.highpass.iter_mut()) {
+        biquad.clear();
+      }
+    }
+    for allpass in self.compensation.iter_mut().flat_map(|band| band.iter_mut()) {
+      allpass.clear();
+    }
+    for output in self.outputs.iter_mut() {
+      *output = T::zero();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the magnitude of a signal at `frequency`.
+  fn magnitude(samples: &[f64], sample_rate: f64, frequency: f64) -> f64 {
+    let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+    let (mut re, mut im) = (0f64, 0f64);
+    for (n, sample) in samples.iter().enumerate() {
+      re += sample * (w * n as f64).cos();
+      im -= sample * (w * n as f64).sin();
+    }
+    (re * re + im * im).sqrt()
+  }
+
+  #[test]
+  fn single_band() {
+    let mut crossover = Crossover::new(44_100f32, &[]);
+    assert_eq!(crossover.bands(), 1);
+    assert_eq!(crossover.tick(0.5f32), &[0.5f32]);
+  }
+
+  #[test]
+  fn flat_sum() {
+    let sample_rate = 44_100f64;
+    let mut crossover = Crossover::new(sample_rate, &[100f64, 1_000f64, 5_000f64]);
+    let mut bands = vec![vec![0f64; 8_192]; 4];
+    let mut impulse = vec![0f64; 8_192];
+    impulse[0] = 1f64;
+    {
+      let mut outputs: Vec<&mut [f64]> = bands.iter_mut().map(|band| band.as_mut_slice()).collect();
+      crossover.process_block(&impulse, &mut outputs);
+    }
+
+    let sum: Vec<f64> = (0..impulse.len()).map(|n| bands.iter().map(|band| band[n]).sum()).collect();
+    for frequency in [30f64, 100f64, 440f64, 1_000f64, 3_000f64, 5_000f64, 12_000f64].iter() {
+      assert!((magnitude(&sum, sample_rate, *frequency) - 1f64).abs() < 1e-3f64);
+    }
+
+    // Each band is 6 dB down at its crossovers
+    assert!((magnitude(&bands[0], sample_rate, 100f64) - 0.5f64).abs() < 1e-3f64);
+    assert!((magnitude(&bands[1], sample_rate, 100f64) - 0.5f64).abs() < 0.05f64);
+    assert!((magnitude(&bands[3], sample_rate, 5_000f64) - 0.5f64).abs() < 1e-3f64);
+  }
+
+  #[test]
+  fn separation() {
+    let sample_rate = 44_100f64;
+    let mut crossover = Crossover::new(sample_rate, &[200f64, 2_000f64]);
+    let mut levels = vec![0f64; 3];
+    for n in 0..44_100 {
+      let sample = (2f64 * ::std::f64::consts::PI * 700f64 * n as f64 / sample_rate).sin();
+      let bands = crossover.tick(sample);
+      if n > 22_050 {
+        for (level, band) in levels.iter_mut().zip(bands.iter()) {
+          *level = level.max(band.abs());
+        }
+      }
+    }
+    assert!(levels[1] > 0.9f64);
+    assert!(levels[0] < 0.1f64 && levels[2] < 0.1f64);
+
+    crossover.clear();
+    assert_eq!(crossover.tick(0f64), &[0f64, 0f64, 0f64]);
+  }
+}
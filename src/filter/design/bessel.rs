@@ -0,0 +1,144 @@
+use num::Complex;
+use num::traits::Float;
+
+use filter::SosCascade;
+use filter::design::{sections, validate, Band, DesignError};
+
+/// The highest order whose prototype poles are found accurately.
+const MAX_ORDER: usize = 20;
+
+/// Returns the coefficients, in ascending powers, of the reverse Bessel
+/// polynomial of `order`.
+fn polynomial(order: usize) -> Vec<f64> {
+  // a(k) = (2n - k)! / (2^(n - k) k! (n - k)!), computed by the ratio of
+  // consecutive coefficients
+  let n = order as f64;
+  let mut coefficients = vec![1f64; order + 1];
+  for k in (0..order).rev() {
+    let k_float = k as f64;
+    coefficients[k] = coefficients[k + 1] * (2f64 * n - k_float) * (k_float + 1f64)
+      / (2f64 * (n - k_float));
+  }
+  coefficients
+}
+
+/// Evaluates a polynomial, in ascending powers, at `s`.
+fn evaluate(coefficients: &[f64], s: Complex<f64>) -> Complex<f64> {
+  coefficients.iter().rev().fold(Complex::new(0f64, 0f64), |sum, c| sum * s + *c)
+}
+
+/// Returns the roots of a monic polynomial, in ascending powers, using the
+/// Durand-Kerner method.
+fn roots(coefficients: &[f64]) -> Vec<Complex<f64>> {
+  let order = coefficients.len() - 1;
+  let radius = coefficients[0].abs().powf(1f64 / order as f64);
+  // Starts from points spread around a circle, away from the symmetry of the
+  // real axis
+  let mut roots: Vec<Complex<f64>> = (0..order)
+    .map(|k| Complex::from_polar(&radius, &(2f64 * ::std::f64::consts::PI * k as f64 / order as f64 + 0.4f64)))
+    .collect();
+  for _ in 0..1_000 {
+    let mut change = 0f64;
+    for i in 0..order {
+      let denominator = (0..order)
+        .filter(|j| *j != i)
+        .fold(Complex::new(1f64, 0f64), |product, j| product * (roots[i] - roots[j]));
+      let step = evaluate(coefficients, roots[i]) / denominator;
+      roots[i] -= step;
+      change = change.max(step.norm() / radius);
+    }
+    if change < 1e-15f64 {
+      break;
+    }
+  }
+  roots
+}
+
+/// Designs a Bessel filter as a cascade of second-order sections.
+///
+/// The group delay of a Bessel filter is maximally flat, so the shape of
+/// signals in the passband is preserved, and the step response barely
+/// overshoots, at the cost of a gentle rolloff. The poles are normalized so
+/// the gain is -3 dB at `cutoff_frequency`. The bilinear transform compresses
+/// the response near Nyquist, so the group delay is only flat well below it.
+/// Orders up to 20 can be designed.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::{bessel, Band};
+///
+/// let sections = bessel(Band::LowPass, 4, 1_000f64, 10f64).unwrap();
+/// assert_eq!(sections.len(), 2);
+/// ```
+pub fn bessel<T: Float>(band: Band,
+                        order: usize,
+                        sample_rate: T,
+                        cutoff_frequency: T)
+                        -> Result<SosCascade<T>, DesignError>
+{
+  validate(order, sample_rate, cutoff_frequency)?;
+  if order > MAX_ORDER {
+    return Err(DesignError::InvalidOrder);
+  }
+
+  let coefficients = polynomial(order);
+
+  // Finds the frequency where the gain of the prototype, a0 / |p(jw)|, falls
+  // to -3 dB, which is monotonic in frequency
+  let target = 2f64 * coefficients[0] * coefficients[0];
+  let power = |w: f64| evaluate(&coefficients, Complex::new(0f64, w)).norm_sqr();
+  let (mut low, mut high) = (0f64, 1f64);
+  while power(high) < target {
+    high *= 2f64;
+  }
+  for _ in 0..200 {
+    let middle = 0.5f64 * (low + high);
+    if power(middle) < target { low = middle; } else { high = middle; }
+  }
+  let cutoff = 0.5f64 * (low + high);
+
+  let poles: Vec<Complex<f64>> = roots(&coefficients).into_iter().map(|p| p / cutoff).collect();
+  Ok(sections(band, sample_rate, cutoff_frequency, &poles, &[], 1f64))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::design::tests::{is_stable, magnitude};
+  use ::traits::Processor;
+
+  #[test]
+  fn polynomials() {
+    assert_eq!(polynomial(1), vec![1f64, 1f64]);
+    assert_eq!(polynomial(3), vec![15f64, 15f64, 6f64, 1f64]);
+    assert_eq!(polynomial(4), vec![105f64, 105f64, 45f64, 10f64, 1f64]);
+  }
+
+  #[test]
+  fn cutoff() {
+    let sample_rate = 48_000f64;
+    for order in 1..MAX_ORDER + 1 {
+      let sections = bessel(Band::LowPass, order, sample_rate, 2_000f64).unwrap();
+      assert_eq!(sections.len(), order.div_ceil(2));
+      assert!(is_stable(&sections));
+      assert!((magnitude(&sections, sample_rate, 0f64) - 1f64).abs() < 1e-9f64);
+      let level = 20f64 * magnitude(&sections, sample_rate, 2_000f64).log10();
+      assert!((level + 3.0103f64).abs() < 1e-3f64);
+    }
+
+    let sections = bessel(Band::HighPass, 3, sample_rate, 2_000f64).unwrap();
+    assert!((magnitude(&sections, sample_rate, 24_000f64) - 1f64).abs() < 1e-9f64);
+    assert_eq!(bessel(Band::LowPass, MAX_ORDER + 1, sample_rate, 2_000f64), Err(DesignError::InvalidOrder));
+  }
+
+  #[test]
+  fn step_response() {
+    // A fourth order Bessel filter overshoots by less than 1%
+    let mut cascade = bessel(Band::LowPass, 4, 48_000f64, 500f64).unwrap();
+    let peak = (0..4_800)
+      .map(|_| cascade.process(1f64))
+      .fold(0f64, f64::max);
+    assert!(peak > 1f64 && peak < 1.01f64);
+  }
+}
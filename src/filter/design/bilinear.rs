@@ -0,0 +1,207 @@
+use num::Complex;
+use num::traits::Float;
+
+use filter::{BiquadCoefficients, SosCascade};
+use traits::FloatConst;
+use super::{sections, validate, Band, DesignError};
+
+/// The coefficients of an analog biquad, in increasing powers of `s`.
+///
+/// The transfer function is
+/// `H(s) = (b0 + b1 s + b2 s^2) / (a0 + a1 s + a2 s^2)`, with `s` in rad/s.
+/// First-order sections have `b2` and `a2` equal to zero.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnalogBiquad<T> {
+  pub b0: T,
+  pub b1: T,
+  pub b2: T,
+  pub a0: T,
+  pub a1: T,
+  pub a2: T
+}
+
+impl<T> AnalogBiquad<T> where T: Float + FloatConst {
+  /// Returns the response of the analog filter at `frequency`, in Hz.
+  pub fn response_at(&self, frequency: T) -> Complex<T> {
+    let s = Complex::new(T::zero(), T::two() * T::pi() * frequency);
+    let polynomial = |c0: T, c1: T, c2: T| s * s * c2 + s * c1 + c0;
+    polynomial(self.b0, self.b1, self.b2) / polynomial(self.a0, self.a1, self.a2)
+  }
+}
+
+/// Returns the analog frequency, in rad/s, that the bilinear transform at
+/// `sample_rate` maps to `frequency`, in Hz.
+///
+/// The bilinear transform compresses the whole analog frequency axis below
+/// Nyquist, so an analog filter designed at the pre-warped frequency has its
+/// cutoff, or center, exactly at `frequency` once transformed.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::prewarp;
+///
+/// // Low frequencies are barely warped
+/// let w = prewarp(48_000f64, 100f64);
+/// assert!((w / (2f64 * std::f64::consts::PI * 100f64) - 1f64).abs() < 1e-4f64);
+/// ```
+pub fn prewarp<T: Float + FloatConst>(sample_rate: T, frequency: T) -> T {
+  T::two() * sample_rate * (T::pi() * frequency / sample_rate).tan()
+}
+
+/// Maps an `analog` biquad to a digital biquad at `sample_rate`, with the
+/// bilinear transform `s = 2 fs (1 - z^-1) / (1 + z^-1)`.
+///
+/// The transform keeps the order and stability of the analog filter, and
+/// maps DC to DC and infinity to Nyquist, warping the frequencies in between
+/// as described by `prewarp()`. First-order analog sections give first-order
+/// digital sections, with `b2` and `a2` equal to zero.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::{bilinear, prewarp, AnalogBiquad};
+/// use rasp::traits::FrequencyResponse;
+///
+/// // An analog resonance, with a Q of 4, placed at 1 kHz
+/// let w0 = prewarp(48_000f64, 1_000f64);
+/// let analog = AnalogBiquad { b0: 0f64, b1: w0 / 4f64, b2: 0f64, a0: w0 * w0, a1: w0 / 4f64, a2: 1f64 };
+/// let digital = bilinear(48_000f64, &analog);
+/// assert!((digital.magnitude_at(1_000f64, 48_000f64) - 1f64).abs() < 1e-9f64);
+/// ```
+pub fn bilinear<T: Float>(sample_rate: T, analog: &AnalogBiquad<T>) -> BiquadCoefficients<T> {
+  let two = T::one() + T::one();
+  let k = two * sample_rate;
+  if analog.b2 == T::zero() && analog.a2 == T::zero() {
+    let a0 = analog.a0 + analog.a1 * k;
+    return BiquadCoefficients {
+      b0: (analog.b0 + analog.b1 * k) / a0,
+      b1: (analog.b0 - analog.b1 * k) / a0,
+      b2: T::zero(),
+      a1: (analog.a0 - analog.a1 * k) / a0,
+      a2: T::zero()
+    };
+  }
+
+  // Each power of s becomes a polynomial in z^-1, over (1 + z^-1)^2
+  let k2 = k * k;
+  let a0 = analog.a0 + analog.a1 * k + analog.a2 * k2;
+  BiquadCoefficients {
+    b0: (analog.b0 + analog.b1 * k + analog.b2 * k2) / a0,
+    b1: two * (analog.b0 - analog.b2 * k2) / a0,
+    b2: (analog.b0 - analog.b1 * k + analog.b2 * k2) / a0,
+    a1: two * (analog.a0 - analog.a2 * k2) / a0,
+    a2: (analog.a0 - analog.a1 * k + analog.a2 * k2) / a0
+  }
+}
+
+/// Returns the poles of a Butterworth lowpass prototype of `order`, with a
+/// cutoff of 1 rad/s.
+///
+/// The poles are evenly spread over the left half of the unit circle, and
+/// the prototype has no finite zeros.
+pub fn butterworth_prototype(order: usize) -> Vec<Complex<f64>> {
+  (0..order)
+    .map(|k| {
+      let theta = ::std::f64::consts::PI * (2 * k + order + 1) as f64 / (2 * order) as f64;
+      Complex::from_polar(&1f64, &theta)
+    })
+    .collect()
+}
+
+/// Designs a cascade of second-order sections from the `poles` and `zeros`
+/// of a custom lowpass prototype, with a cutoff of 1 rad/s, and an overall
+/// `gain`.
+///
+/// This is the last step of every designer here: the prototype is
+/// transformed to the `band`, placed at `cutoff_frequency` by pre-warping,
+/// and mapped to the z-plane with the bilinear transform. Complex poles and
+/// zeros must come in conjugate pairs, every pole must be in the left half
+/// of the s-plane, and zeros missing from `zeros` are at infinity. Each
+/// section has unity gain at DC, for a lowpass, or Nyquist, for a highpass.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::{butterworth_prototype, prototype_sections, Band};
+/// use rasp::traits::FrequencyResponse;
+///
+/// let poles = butterworth_prototype(4);
+/// let cascade = prototype_sections(Band::HighPass, 48_000f64, 200f64, &poles, &[], 1f64).unwrap();
+/// assert_eq!(cascade.len(), 2);
+/// assert!((cascade.magnitude_at(200f64, 48_000f64) - 0.5f64.sqrt()).abs() < 1e-9f64);
+/// ```
+pub fn prototype_sections<T: Float>(band: Band,
+                                    sample_rate: T,
+                                    cutoff_frequency: T,
+                                    poles: &[Complex<f64>],
+                                    zeros: &[Complex<f64>],
+                                    gain: f64)
+                                    -> Result<SosCascade<T>, DesignError>
+{
+  validate(poles.len(), sample_rate, cutoff_frequency)?;
+  if zeros.len() > poles.len() {
+    return Err(DesignError::InvalidOrder);
+  }
+  let valid = poles.iter().all(|p| p.re < 0f64 && p.norm().is_finite())
+    && zeros.iter().all(|z| z.norm().is_finite())
+    && gain.is_finite();
+  if !valid {
+    return Err(DesignError::InvalidParameter);
+  }
+  Ok(sections(band, sample_rate, cutoff_frequency, poles, zeros, gain))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::rbj::{HighPass, LowPass};
+  use traits::FrequencyResponse;
+
+  #[test]
+  fn transform() {
+    // The RBJ lowpass is the bilinear transform of the analog prototype at
+    // the pre-warped cutoff
+    let (sample_rate, q) = (44_100f64, 0.9f64);
+    let w0 = prewarp(sample_rate, 5_000f64);
+    let analog = AnalogBiquad { b0: w0 * w0, b1: 0f64, b2: 0f64, a0: w0 * w0, a1: w0 / q, a2: 1f64 };
+    let digital = bilinear(sample_rate, &analog);
+    let expected = LowPass::coefficients(sample_rate, 5_000f64, q);
+    for (a, b) in [digital.b0, digital.b1, digital.b2, digital.a1, digital.a2].iter()
+      .zip([expected.b0, expected.b1, expected.b2, expected.a1, expected.a2].iter()) {
+      assert!((a - b).abs() < 1e-12f64);
+    }
+
+    // The analog and digital responses match at the pre-warped frequency
+    let analog_gain = analog.response_at(w0 / (2f64 * ::std::f64::consts::PI)).norm();
+    assert!((digital.magnitude_at(5_000f64, sample_rate) - analog_gain).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn first_order() {
+    let w0 = prewarp(48_000f64, 1_000f64);
+    let analog = AnalogBiquad { b0: 0f64, b1: 1f64, b2: 0f64, a0: w0, a1: 1f64, a2: 0f64 };
+    let digital = bilinear(48_000f64, &analog);
+    assert_eq!((digital.b2, digital.a2), (0f64, 0f64));
+    assert!((digital.b0 + digital.b1).abs() < 1e-12f64);
+    assert!((digital.magnitude_at(1_000f64, 48_000f64) - 0.5f64.sqrt()).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn prototypes() {
+    let poles = butterworth_prototype(5);
+    assert!(poles.iter().all(|p| (p.norm() - 1f64).abs() < 1e-12f64 && p.re < 0f64));
+
+    // A second order Butterworth is the RBJ filter with a Q of 1 / sqrt(2)
+    let cascade = prototype_sections(Band::HighPass, 48_000f64, 300f64, &butterworth_prototype(2), &[], 1f64).unwrap();
+    let expected = HighPass::coefficients(48_000f64, 300f64, ::std::f64::consts::FRAC_1_SQRT_2);
+    for frequency in [50f64, 300f64, 2_000f64].iter() {
+      let rbj = expected.magnitude_at(*frequency, 48_000f64);
+      assert!((cascade.magnitude_at(*frequency, 48_000f64) - rbj).abs() < 1e-9f64);
+    }
+
+    let unstable = [Complex::new(1f64, 0f64)];
+    assert_eq!(prototype_sections::<f64>(Band::LowPass, 48_000f64, 1_000f64, &unstable, &[], 1f64).unwrap_err(), DesignError::InvalidParameter);
+    assert_eq!(prototype_sections::<f64>(Band::LowPass, 48_000f64, 1_000f64, &[], &[], 1f64).unwrap_err(), DesignError::InvalidOrder);
+  }
+}
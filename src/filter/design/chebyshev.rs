@@ -0,0 +1,174 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::SosCascade;
+use filter::design::{sections, validate, Band, DesignError};
+
+/// Returns the poles of a Chebyshev Type I prototype with ripple factor
+/// `epsilon`, with a passband edge of 1 rad/s.
+fn poles(order: usize, epsilon: f64) -> Vec<Complex<f64>> {
+  let n = order as f64;
+  let mu = (1f64 / epsilon).asinh() / n;
+  (0..order)
+    .map(|k| {
+      let theta = ::std::f64::consts::PI * (2 * k + 1) as f64 / (2f64 * n);
+      Complex::new(-mu.sinh() * theta.sin(), mu.cosh() * theta.cos())
+    })
+    .collect()
+}
+
+/// Designs a Chebyshev Type I filter as a cascade of second-order sections.
+///
+/// The passband ripples by `ripple` dB, and ends at `cutoff_frequency`, where
+/// the gain is `-ripple` dB. Beyond it the response falls monotonically,
+/// faster than a Butterworth filter of the same order. In the passband, the
+/// gain peaks at 0 dB, so filters of even order have a gain of `-ripple` dB
+/// at DC, for a lowpass, or Nyquist, for a highpass.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::{chebyshev1, Band};
+/// use rasp::traits::Processor;
+///
+/// let mut cascade = chebyshev1(Band::LowPass, 6, 96_000f32, 20_000f32, 0.5f32).unwrap();
+/// assert_eq!(cascade.len(), 3);
+///
+/// let output = cascade.process(1f32);
+/// ```
+pub fn chebyshev1<T: Float>(band: Band,
+                            order: usize,
+                            sample_rate: T,
+                            cutoff_frequency: T,
+                            ripple: T)
+                            -> Result<SosCascade<T>, DesignError>
+{
+  validate(order, sample_rate, cutoff_frequency)?;
+  if !(ripple > T::zero() && ripple.is_finite()) {
+    return Err(DesignError::InvalidParameter);
+  }
+
+  let ripple: f64 = num::cast(ripple).unwrap();
+  let epsilon = (10f64.powf(ripple / 10f64) - 1f64).sqrt();
+  let gain = if order.is_multiple_of(2) { 1f64 / (1f64 + epsilon * epsilon).sqrt() } else { 1f64 };
+  Ok(sections(band, sample_rate, cutoff_frequency, &poles(order, epsilon), &[], gain))
+}
+
+/// Designs a Chebyshev Type II filter as a cascade of second-order sections.
+///
+/// The passband is maximally flat, and the stopband, which begins at
+/// `cutoff_frequency`, ripples with a gain of at most `-attenuation` dB.
+/// Unlike the other designers, the cutoff is the edge of the stopband rather
+/// than the passband.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::{chebyshev2, Band, DesignError};
+///
+/// let sections = chebyshev2(Band::LowPass, 5, 44_100f64, 4_000f64, 60f64).unwrap();
+/// assert_eq!(sections.len(), 3);
+///
+/// let error = chebyshev2(Band::LowPass, 5, 44_100f64, 4_000f64, 0f64);
+/// assert_eq!(error, Err(DesignError::InvalidParameter));
+/// ```
+pub fn chebyshev2<T: Float>(band: Band,
+                            order: usize,
+                            sample_rate: T,
+                            cutoff_frequency: T,
+                            attenuation: T)
+                            -> Result<SosCascade<T>, DesignError>
+{
+  validate(order, sample_rate, cutoff_frequency)?;
+  if !(attenuation > T::zero() && attenuation.is_finite()) {
+    return Err(DesignError::InvalidParameter);
+  }
+
+  // The inverse Chebyshev prototype inverts the poles of a Type I prototype,
+  // and places zeros on the imaginary axis beyond the stopband edge
+  let attenuation: f64 = num::cast(attenuation).unwrap();
+  let epsilon = 1f64 / (10f64.powf(attenuation / 10f64) - 1f64).sqrt();
+  let one = Complex::new(1f64, 0f64);
+  let poles: Vec<Complex<f64>> = poles(order, epsilon).into_iter().map(|p| one / p).collect();
+  let zeros: Vec<Complex<f64>> = (0..order)
+    .filter(|k| 2 * k + 1 != order)
+    .map(|k| {
+      let theta = ::std::f64::consts::PI * (2 * k + 1) as f64 / (2f64 * order as f64);
+      Complex::new(0f64, 1f64 / theta.cos())
+    })
+    .collect();
+  Ok(sections(band, sample_rate, cutoff_frequency, &poles, &zeros, 1f64))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::design::tests::{is_stable, magnitude};
+
+  fn db(magnitude: f64) -> f64 {
+    20f64 * magnitude.log10()
+  }
+
+  #[test]
+  fn type1_lowpass() {
+    let sample_rate = 48_000f64;
+    for order in 1..9 {
+      let sections = chebyshev1(Band::LowPass, order, sample_rate, 1_000f64, 1f64).unwrap();
+      assert_eq!(sections.len(), order.div_ceil(2));
+      assert!(is_stable(&sections));
+
+      let dc = if order.is_multiple_of(2) { -1f64 } else { 0f64 };
+      assert!((db(magnitude(&sections, sample_rate, 0f64)) - dc).abs() < 1e-6f64);
+      assert!((db(magnitude(&sections, sample_rate, 1_000f64)) + 1f64).abs() < 1e-6f64);
+      for frequency in (1..100).map(|f| f as f64 * 10f64) {
+        let level = db(magnitude(&sections, sample_rate, frequency));
+        assert!(level < 1e-6f64 && level > -1f64 - 1e-6f64);
+      }
+    }
+
+    // Sharper than a Butterworth of the same order, which is at -48 dB
+    let sections = chebyshev1(Band::LowPass, 4, sample_rate, 1_000f64, 1f64).unwrap();
+    assert!(db(magnitude(&sections, sample_rate, 4_000f64)) < -55f64);
+  }
+
+  #[test]
+  fn type1_highpass() {
+    let sample_rate = 48_000f64;
+    let sections = chebyshev1(Band::HighPass, 5, sample_rate, 10_000f64, 0.5f64).unwrap();
+    assert!(is_stable(&sections));
+    assert!(db(magnitude(&sections, sample_rate, 24_000f64)).abs() < 1e-6f64);
+    assert!((db(magnitude(&sections, sample_rate, 10_000f64)) + 0.5f64).abs() < 1e-6f64);
+    assert!(db(magnitude(&sections, sample_rate, 2_000f64)) < -60f64);
+  }
+
+  #[test]
+  fn type2() {
+    let sample_rate = 48_000f64;
+    for order in 1..9 {
+      let sections = chebyshev2(Band::LowPass, order, sample_rate, 2_000f64, 40f64).unwrap();
+      assert!(is_stable(&sections));
+      assert!(db(magnitude(&sections, sample_rate, 0f64)).abs() < 1e-6f64);
+      assert!((db(magnitude(&sections, sample_rate, 2_000f64)) + 40f64).abs() < 1e-3f64);
+      for frequency in (0..220).map(|f| 2_000f64 + f as f64 * 100f64) {
+        assert!(db(magnitude(&sections, sample_rate, frequency)) < -40f64 + 1e-3f64);
+      }
+    }
+
+    let sections = chebyshev2(Band::HighPass, 6, sample_rate, 500f64, 60f64).unwrap();
+    assert!(is_stable(&sections));
+    assert!(db(magnitude(&sections, sample_rate, 24_000f64)).abs() < 1e-6f64);
+    for frequency in (1..50).map(|f| f as f64 * 10f64) {
+      assert!(db(magnitude(&sections, sample_rate, frequency)) < -60f64 + 1e-3f64);
+    }
+  }
+
+  #[test]
+  fn invalid_specifications() {
+    assert_eq!(chebyshev1(Band::LowPass, 0, 48_000f32, 1_000f32, 1f32), Err(DesignError::InvalidOrder));
+    assert_eq!(chebyshev1(Band::LowPass, 4, 48_000f32, 24_000f32, 1f32), Err(DesignError::InvalidFrequency));
+    assert_eq!(chebyshev1(Band::LowPass, 4, 0f32, 1_000f32, 1f32), Err(DesignError::InvalidFrequency));
+    assert_eq!(chebyshev1(Band::LowPass, 4, 48_000f32, 1_000f32, -1f32), Err(DesignError::InvalidParameter));
+    assert_eq!(chebyshev2(Band::HighPass, 4, 48_000f32, 1_000f32, ::std::f32::NAN), Err(DesignError::InvalidParameter));
+  }
+}
@@ -0,0 +1,199 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use traits::FloatConst;
+use window::{apply_window, Window};
+use super::DesignError;
+
+/// How many times longer than the filter the frequency grid of the design
+/// is, to keep the truncated ideal response from aliasing.
+const OVERSAMPLING: usize = 64;
+
+/// Designs a linear-phase FIR differentiator, whose output is the rate of
+/// change of its input, per second.
+///
+/// The ideal response, `j 2 pi f`, is truncated to `num_taps` taps and shaped
+/// by `window`, which trades the accuracy at low frequencies for the width of
+/// the band where the response is accurate, away from Nyquist. Odd numbers of
+/// taps delay the input by a whole number of samples, but have no gain at
+/// Nyquist, and even numbers delay it by a half sample, which suits
+/// estimating velocity between samples. The filter delays its input by
+/// `(num_taps - 1) / 2` samples.
+///
+/// `num_taps` must be at least two, else `DesignError::InvalidOrder` is
+/// returned.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::Fir;
+/// use rasp::filter::design::fir_differentiator;
+/// use rasp::traits::FrequencyResponse;
+/// use rasp::window::Window;
+///
+/// let taps = fir_differentiator(64, 48_000f64, Window::Blackman).unwrap();
+/// let velocity = Fir::new(&taps).magnitude_at(1_000f64, 48_000f64);
+/// assert!((velocity / (2f64 * std::f64::consts::PI * 1_000f64) - 1f64).abs() < 0.01f64);
+/// ```
+pub fn fir_differentiator<T>(num_taps: usize, sample_rate: T, window: Window) -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  fir_fractional(num_taps, sample_rate, T::one(), window)
+}
+
+/// Designs a linear-phase FIR integrator, whose output is the integral of
+/// its input over time, in seconds.
+///
+/// The ideal response, `1 / (j 2 pi f)`, is infinite at DC, so the
+/// integrator has no gain there, and is only accurate above a few times
+/// `sample_rate / num_taps`, where the windowed response of the truncated
+/// filter settles. Offsets in the input are not accumulated, so it suits
+/// integrating band-limited signals, such as the impulse trains of BLIT
+/// synthesis into sawtooth waves. The filter delays its input by
+/// `(num_taps - 1) / 2` samples.
+///
+/// `num_taps` must be at least two, else `DesignError::InvalidOrder` is
+/// returned.
+pub fn fir_integrator<T>(num_taps: usize, sample_rate: T, window: Window) -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  fir_fractional(num_taps, sample_rate, -T::one(), window)
+}
+
+/// Designs an FIR filter of fractional `order`, whose ideal response is
+/// `(j 2 pi f)^order`.
+///
+/// Positive orders differentiate, and negative orders integrate, with the
+/// magnitude rising or falling by `6 * order` dB per octave and a constant
+/// phase of `order` quarter turns on top of the delay of `(num_taps - 1) /
+/// 2` samples. Orders of one and minus one are the same as
+/// `fir_differentiator()` and `fir_integrator()`. The ideal response is
+/// sampled on a dense frequency grid, without gain at DC, and its inverse
+/// transform is truncated and shaped by `window`.
+///
+/// `num_taps` must be at least two, else `DesignError::InvalidOrder` is
+/// returned, and `order` must be finite, else
+/// `DesignError::InvalidParameter` is returned.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::Fir;
+/// use rasp::filter::design::fir_fractional;
+/// use rasp::traits::FrequencyResponse;
+/// use rasp::window::Window;
+///
+/// // A half-order integrator turns white noise into a pink spectrum
+/// let taps = fir_fractional(255, 48_000f64, -0.5f64, Window::Blackman).unwrap();
+/// let filter = Fir::new(&taps);
+/// let slope = filter.magnitude_at(4_000f64, 48_000f64) / filter.magnitude_at(2_000f64, 48_000f64);
+/// assert!((slope - 0.5f64.sqrt()).abs() < 0.01f64);
+/// ```
+pub fn fir_fractional<T>(num_taps: usize, sample_rate: T, order: T, window: Window) -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  if num_taps < 2 {
+    return Err(DesignError::InvalidOrder);
+  }
+  if !(sample_rate > T::zero() && sample_rate.is_finite()) {
+    return Err(DesignError::InvalidFrequency);
+  }
+  if !order.is_finite() {
+    return Err(DesignError::InvalidParameter);
+  }
+
+  let size = (OVERSAMPLING * num_taps).next_power_of_two();
+  let delay = num::cast::<usize, f64>(num_taps - 1).unwrap() / 2f64;
+  let order: f64 = num::cast(order).unwrap();
+  let pi = ::std::f64::consts::PI;
+
+  // The ideal response, delayed to the center of the filter, is conjugate
+  // symmetric, so its inverse transform is real
+  let mut spectrum: Vec<Complex<f64>> = (0..size)
+    .map(|k| {
+      if k == 0 {
+        return Complex::new(0f64, 0f64);
+      }
+      let bin = if k <= size / 2 { k as f64 } else { k as f64 - size as f64 };
+      let w = 2f64 * pi * bin / size as f64;
+      let phase = order * pi / 2f64 * w.signum() - w * delay;
+      let value = Complex::from_polar(&w.abs().powf(order), &phase);
+      // The Nyquist bin has no mirrored bin
+      if 2 * k == size { Complex::new(value.re, 0f64) } else { value }
+    })
+    .collect();
+  fft::inverse(&mut spectrum);
+
+  // Per sample, to per second
+  let scale = num::cast::<T, f64>(sample_rate).unwrap().powf(order);
+  let mut taps: Vec<T> = spectrum.iter()
+    .take(num_taps)
+    .map(|value| num::cast(value.re * scale).unwrap())
+    .collect();
+  apply_window(&mut taps, window);
+  Ok(taps)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Fir;
+  use traits::FrequencyResponse;
+
+  /// Returns the response of `taps` at `frequency`, without the delay of
+  /// their center.
+  fn response(taps: &[f64], frequency: f64, sample_rate: f64) -> Complex<f64> {
+    let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+    let delay = (taps.len() - 1) as f64 / 2f64;
+    Fir::new(taps).response_at(frequency, sample_rate) * Complex::from_polar(&1f64, &(w * delay))
+  }
+
+  #[test]
+  fn differentiator() {
+    let sample_rate = 48_000f64;
+    for num_taps in [63, 64].iter() {
+      let taps = fir_differentiator(*num_taps, sample_rate, Window::Blackman).unwrap();
+      assert!((0..taps.len()).all(|n| (taps[n] + taps[taps.len() - 1 - n]).abs() < 1e-9f64));
+      for frequency in [1_000f64, 4_000f64, 8_000f64].iter() {
+        let expected = 2f64 * ::std::f64::consts::PI * frequency;
+        let response = response(&taps, *frequency, sample_rate);
+        assert!((response.im / expected - 1f64).abs() < 0.01f64);
+        assert!(response.re.abs() < 1e-6f64 * expected);
+      }
+    }
+  }
+
+  #[test]
+  fn integrator() {
+    let sample_rate = 48_000f64;
+    let taps = fir_integrator(255, sample_rate, Window::Hann).unwrap();
+    for frequency in [2_000f64, 4_000f64, 8_000f64].iter() {
+      let expected = -1f64 / (2f64 * ::std::f64::consts::PI * frequency);
+      let response = response(&taps, *frequency, sample_rate);
+      assert!((response.im / expected - 1f64).abs() < 0.01f64);
+    }
+    assert!(Fir::new(&taps).magnitude_at(0f64, sample_rate) < 1e-9f64);
+  }
+
+  #[test]
+  fn fractional() {
+    let sample_rate = 48_000f64;
+    let taps = fir_fractional(255, sample_rate, 0.5f64, Window::Blackman).unwrap();
+    let response = response(&taps, 4_000f64, sample_rate);
+    let expected = (2f64 * ::std::f64::consts::PI * 4_000f64).sqrt();
+    assert!((response.norm() / expected - 1f64).abs() < 0.01f64);
+    assert!((response.arg() - ::std::f64::consts::FRAC_PI_4).abs() < 0.01f64);
+
+    let differentiator = fir_differentiator(31, sample_rate, Window::Hann).unwrap();
+    assert_eq!(fir_fractional(31, sample_rate, 1f64, Window::Hann).unwrap(), differentiator);
+  }
+
+  #[test]
+  fn invalid_specifications() {
+    assert_eq!(fir_differentiator(1, 48_000f64, Window::Hann), Err(DesignError::InvalidOrder));
+    assert_eq!(fir_integrator(31, 0f64, Window::Hann), Err(DesignError::InvalidFrequency));
+    assert_eq!(fir_fractional(31, 48_000f64, ::std::f64::NAN, Window::Hann), Err(DesignError::InvalidParameter));
+  }
+}
@@ -0,0 +1,217 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::SosCascade;
+use filter::design::{sections, validate, Band, DesignError};
+
+/// Returns the descending sequence of moduli of the Landen transformation of
+/// `k`, until they are negligible.
+fn landen(k: f64) -> Vec<f64> {
+  let mut moduli = Vec::new();
+  let mut k = k;
+  while k > 1e-15f64 {
+    k = (k / (1f64 + (1f64 - k * k).sqrt())).powi(2);
+    moduli.push(k);
+  }
+  moduli
+}
+
+/// Evaluates `w(u)` from its value at a modulus of zero, `w0`, by ascending
+/// Landen transformations.
+fn ascend(w0: Complex<f64>, k: f64) -> Complex<f64> {
+  landen(k).iter().rev()
+    .fold(w0, |w, v| w * (1f64 + v) / (w * w * *v + 1f64))
+}
+
+/// The Jacobi elliptic function cd, with `u` normalized to the quarter period.
+fn cde(u: Complex<f64>, k: f64) -> Complex<f64> {
+  ascend((u * ::std::f64::consts::FRAC_PI_2).cos(), k)
+}
+
+/// The Jacobi elliptic function sn, with `u` normalized to the quarter period.
+fn sne(u: Complex<f64>, k: f64) -> Complex<f64> {
+  ascend((u * ::std::f64::consts::FRAC_PI_2).sin(), k)
+}
+
+/// The inverse of `sne()`.
+fn asne(w: Complex<f64>, k: f64) -> Complex<f64> {
+  let moduli = landen(k);
+  let mut previous = k;
+  let mut w = w;
+  for v in moduli {
+    let root = (Complex::new(1f64, 0f64) - w * w * previous * previous).sqrt();
+    w = w / (root + 1f64) * 2f64 / (1f64 + v);
+    previous = v;
+  }
+  // sn is cd shifted by a quarter period
+  Complex::new(1f64, 0f64) - w.acos() / ::std::f64::consts::FRAC_PI_2
+}
+
+/// Solves the degree equation for the selectivity, the ratio of the passband
+/// edge to the stopband edge, of an elliptic filter of `order` with
+/// discrimination `k1`.
+fn selectivity(order: usize, k1: f64) -> f64 {
+  let k1_prime = (1f64 - k1 * k1).sqrt();
+  let product = (1..order / 2 + 1)
+    .map(|i| sne(Complex::new((2 * i - 1) as f64 / order as f64, 0f64), k1_prime).re)
+    .fold(1f64, |product, sn| product * sn);
+  let k_prime = k1_prime.powi(order as i32) * product.powi(4);
+  (1f64 - k_prime * k_prime).sqrt()
+}
+
+/// Designs an elliptic, or Cauer, filter as a cascade of second-order sections.
+///
+/// Both the passband and the stopband ripple, which gives the steepest
+/// transition of any filter of the same order. The passband ripples by
+/// `ripple` dB, and ends at `cutoff_frequency`, where the gain is `-ripple`
+/// dB. The stopband gain is at most `-attenuation` dB, and the order sets how
+/// soon the stopband begins, which is returned, in Hz, with the sections. As
+/// with the Chebyshev Type I designer, filters of even order have a gain of
+/// `-ripple` dB at DC, for a lowpass, or Nyquist, for a highpass.
+///
+/// Returns `DesignError::Unrealizable` if the attenuation is not greater than
+/// the ripple.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::design::{elliptic, Band, DesignError};
+///
+/// let (sections, stopband) = elliptic(Band::LowPass, 12, 96_000f64, 20_000f64, 0.1f64, 100f64).unwrap();
+/// assert_eq!(sections.len(), 6);
+/// assert!(stopband > 20_000f64 && stopband < 24_000f64);
+///
+/// let error = elliptic(Band::LowPass, 8, 96_000f64, 20_000f64, 3f64, 1f64);
+/// assert_eq!(error.unwrap_err(), DesignError::Unrealizable);
+/// ```
+pub fn elliptic<T: Float>(band: Band,
+                          order: usize,
+                          sample_rate: T,
+                          cutoff_frequency: T,
+                          ripple: T,
+                          attenuation: T)
+                          -> Result<(SosCascade<T>, T), DesignError>
+{
+  validate(order, sample_rate, cutoff_frequency)?;
+  let valid = ripple > T::zero() && ripple.is_finite()
+    && attenuation > T::zero() && attenuation.is_finite();
+  if !valid {
+    return Err(DesignError::InvalidParameter);
+  }
+  if attenuation <= ripple {
+    return Err(DesignError::Unrealizable);
+  }
+
+  // Based on the design by Sophocles Orfanidis, "Lecture Notes on Elliptic
+  // Filter Design", which places the poles and zeros with Jacobi elliptic
+  // functions computed by Landen transformations
+  let ripple: f64 = num::cast(ripple).unwrap();
+  let attenuation: f64 = num::cast(attenuation).unwrap();
+  let epsilon_pass = (10f64.powf(ripple / 10f64) - 1f64).sqrt();
+  let epsilon_stop = (10f64.powf(attenuation / 10f64) - 1f64).sqrt();
+  let k1 = epsilon_pass / epsilon_stop;
+  let k = selectivity(order, k1);
+
+  let j = Complex::new(0f64, 1f64);
+  let v0 = (-j * asne(j / epsilon_pass, k1) / order as f64).re;
+  let mut poles = Vec::new();
+  let mut zeros = Vec::new();
+  for i in 1..order / 2 + 1 {
+    let u = Complex::new((2 * i - 1) as f64 / order as f64, 0f64);
+    let zeta = cde(u, k);
+    let zero = j / (zeta * k);
+    let pole = j * cde(u - j * v0, k);
+    zeros.push(zero);
+    zeros.push(zero.conj());
+    poles.push(pole);
+    poles.push(pole.conj());
+  }
+  if order % 2 == 1 {
+    let pole = j * sne(j * v0, k);
+    poles.push(Complex::new(pole.re, 0f64));
+  }
+
+  if k <= 0f64 || k >= 1f64 || poles.iter().any(|p| !(p.re < 0f64 && p.norm().is_finite())) {
+    return Err(DesignError::Unrealizable);
+  }
+
+  let gain = if order.is_multiple_of(2) { 1f64 / (1f64 + epsilon_pass * epsilon_pass).sqrt() } else { 1f64 };
+  let cascade = sections(band, sample_rate, cutoff_frequency, &poles, &zeros, gain);
+
+  // The stopband edge of the prototype is at 1 / k rad/s, which is mapped
+  // through the same pre-warping as the cutoff
+  let sample_rate: f64 = num::cast(sample_rate).unwrap();
+  let cutoff_frequency: f64 = num::cast(cutoff_frequency).unwrap();
+  let warped = (::std::f64::consts::PI * cutoff_frequency / sample_rate).tan();
+  let edge = match band { Band::LowPass => warped / k, Band::HighPass => warped * k };
+  let stopband = edge.atan() * sample_rate / ::std::f64::consts::PI;
+  Ok((cascade, num::cast(stopband).unwrap()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::design::tests::{is_stable, magnitude};
+
+  fn db(magnitude: f64) -> f64 {
+    20f64 * magnitude.log10()
+  }
+
+  #[test]
+  fn elliptic_functions() {
+    // With a modulus of zero, the functions are trigonometric
+    let u = Complex::new(0.3f64, 0f64);
+    assert!((sne(u, 0f64).re - (0.3f64 * ::std::f64::consts::FRAC_PI_2).sin()).abs() < 1e-12f64);
+
+    // Inverse
+    for k in [0.1f64, 0.5f64, 0.99f64].iter() {
+      let w = sne(u, *k);
+      assert!((asne(w, *k) - u).norm() < 1e-9f64);
+    }
+  }
+
+  #[test]
+  fn lowpass() {
+    let sample_rate = 48_000f64;
+    for order in 1..11 {
+      let (sections, stopband) =
+        elliptic(Band::LowPass, order, sample_rate, 5_000f64, 0.5f64, 60f64).unwrap();
+      assert_eq!(sections.len(), order.div_ceil(2));
+      assert!(is_stable(&sections));
+      assert!(stopband > 5_000f64 && stopband < 24_000f64);
+
+      let dc = if order % 2 == 0 { -0.5f64 } else { 0f64 };
+      assert!((db(magnitude(&sections, sample_rate, 0f64)) - dc).abs() < 1e-6f64);
+      assert!((db(magnitude(&sections, sample_rate, 5_000f64)) + 0.5f64).abs() < 1e-6f64);
+      for frequency in (0..500).map(|f| f as f64 * 10f64) {
+        let level = db(magnitude(&sections, sample_rate, frequency));
+        assert!(level < 1e-6f64 && level > -0.5f64 - 1e-6f64);
+      }
+      for frequency in (0..100).map(|f| stopband + f as f64 * (24_000f64 - stopband) / 100f64) {
+        assert!(db(magnitude(&sections, sample_rate, frequency)) < -60f64 + 1e-3f64);
+      }
+    }
+  }
+
+  #[test]
+  fn highpass() {
+    let sample_rate = 48_000f64;
+    let (sections, stopband) =
+      elliptic(Band::HighPass, 5, sample_rate, 1_000f64, 1f64, 80f64).unwrap();
+    assert!(is_stable(&sections));
+    assert!(stopband < 1_000f64);
+    assert!(db(magnitude(&sections, sample_rate, 24_000f64)).abs() < 1e-6f64);
+    assert!((db(magnitude(&sections, sample_rate, 1_000f64)) + 1f64).abs() < 1e-6f64);
+    for frequency in (1..100).map(|f| f as f64 * stopband / 100f64) {
+      assert!(db(magnitude(&sections, sample_rate, frequency)) < -80f64 + 1e-3f64);
+    }
+  }
+
+  #[test]
+  fn unrealizable() {
+    assert_eq!(elliptic(Band::LowPass, 4, 48_000f32, 1_000f32, 1f32, 1f32).unwrap_err(), DesignError::Unrealizable);
+    assert_eq!(elliptic(Band::LowPass, 4, 48_000f32, 1_000f32, 0f32, 40f32).unwrap_err(), DesignError::InvalidParameter);
+    assert_eq!(elliptic(Band::LowPass, 0, 48_000f32, 1_000f32, 1f32, 40f32).unwrap_err(), DesignError::InvalidOrder);
+  }
+}
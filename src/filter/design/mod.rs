@@ -0,0 +1,195 @@
+//! Designers for higher order IIR filters, and windowed-sinc FIR filters.
+//!
+//! Each IIR designer places the poles and zeros of a normalized analog
+//! prototype, transforms it to the requested response, and maps it to the
+//! z-plane with the bilinear transform, pre-warping the cutoff frequency so it
+//! is matched exactly. The result is a `SosCascade` of second-order sections,
+//! in order of increasing pole radius, that can be processed directly or
+//! loaded into the biquad types. Odd orders include a first-order section,
+//! with `b2` and `a2` equal to zero.
+//!
+//! The same steps are exposed for custom designs: `prewarp()` and
+//! `bilinear()` map analog biquads to digital ones, and
+//! `prototype_sections()` designs a cascade from the poles and zeros of any
+//! lowpass prototype, such as `butterworth_prototype()`.
+//!
+//! The FIR designers return the taps of a linear-phase filter, for a `Fir`,
+//! including differentiators, integrators and their fractional orders.
+
+use std::error::Error;
+use std::fmt;
+
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{BiquadCoefficients, SosCascade};
+
+mod bessel;
+mod bilinear;
+mod chebyshev;
+mod differentiator;
+mod elliptic;
+mod sinc;
+
+pub use self::bessel::bessel;
+pub use self::bilinear::{bilinear, butterworth_prototype, prewarp, prototype_sections, AnalogBiquad};
+pub use self::chebyshev::{chebyshev1, chebyshev2};
+pub use self::differentiator::{fir_differentiator, fir_fractional, fir_integrator};
+pub use self::elliptic::elliptic;
+pub use self::sinc::{fir_sinc, fir_sinc_bandpass, fir_sinc_bandstop, fir_sinc_highpass};
+
+/// The response of a designed filter.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Band {
+  LowPass,
+  HighPass
+}
+
+/// The reason a filter specification could not be designed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DesignError {
+  /// The order, or number of taps, is zero, or not supported by the design.
+  InvalidOrder,
+  /// The sample rate is not positive, or the cutoff frequency is not between
+  /// zero and Nyquist.
+  InvalidFrequency,
+  /// A ripple or attenuation is not positive.
+  InvalidParameter,
+  /// The parameters are valid, but no filter can meet them together.
+  Unrealizable
+}
+
+impl fmt::Display for DesignError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    let description =
+      match *self {
+        DesignError::InvalidOrder => "filter order is not supported by the design",
+        DesignError::InvalidFrequency => "cutoff frequency must be between zero and Nyquist",
+        DesignError::InvalidParameter => "ripple and attenuation must be positive",
+        DesignError::Unrealizable => "no filter meets the specification"
+      };
+    f.write_str(description)
+  }
+}
+
+impl Error for DesignError {}
+
+/// Checks the parameters shared by every designer.
+fn validate<T: Float>(order: usize, sample_rate: T, cutoff_frequency: T) -> Result<(), DesignError> {
+  if order == 0 {
+    return Err(DesignError::InvalidOrder);
+  }
+  let nyquist = sample_rate / (T::one() + T::one());
+  let valid = sample_rate > T::zero() && sample_rate.is_finite()
+    && cutoff_frequency > T::zero() && cutoff_frequency < nyquist;
+  if !valid {
+    return Err(DesignError::InvalidFrequency);
+  }
+  Ok(())
+}
+
+/// Returns `true` if `value` is treated as real when pairing roots.
+fn is_real(value: Complex<f64>) -> bool {
+  value.im.abs() < 1e-9f64
+}
+
+/// Maps the poles and zeros of a lowpass prototype, with a cutoff of 1
+/// rad/s, to second-order sections of a digital filter.
+///
+/// Zeros missing from `zeros` are at infinity. Each section has unity gain at
+/// DC, for a lowpass, or Nyquist, for a highpass, and the cascade has an
+/// overall `gain`.
+fn sections<T: Float>(band: Band,
+                      sample_rate: T,
+                      cutoff_frequency: T,
+                      poles: &[Complex<f64>],
+                      zeros: &[Complex<f64>],
+                      gain: f64)
+                      -> SosCascade<T>
+{
+  let sample_rate: f64 = num::cast(sample_rate).unwrap();
+  let cutoff_frequency: f64 = num::cast(cutoff_frequency).unwrap();
+  let k = (::std::f64::consts::PI * cutoff_frequency / sample_rate).tan();
+  let one = Complex::new(1f64, 0f64);
+
+  // The lowpass to highpass transform inverts the s-plane, moving zeros at
+  // infinity to DC, which the bilinear transform maps to either end of the
+  // unit circle
+  let (reference, infinite_zero) =
+    match band {
+      Band::LowPass => (1f64, -1f64),
+      Band::HighPass => (-1f64, 1f64)
+    };
+  let transform = |s: Complex<f64>| {
+    let s = match band { Band::LowPass => s, Band::HighPass => one / s };
+    (one + s * k) / (one - s * k)
+  };
+
+  let poles: Vec<Complex<f64>> = poles.iter().map(|p| transform(*p)).collect();
+  let mut zeros: Vec<Complex<f64>> = zeros.iter().map(|z| transform(*z)).collect();
+  while zeros.len() < poles.len() {
+    zeros.push(Complex::new(infinite_zero, 0f64));
+  }
+
+  let mut pole_pairs: Vec<Complex<f64>> = poles.iter().cloned().filter(|p| !is_real(*p) && p.im > 0f64).collect();
+  let real_poles: Vec<f64> = poles.iter().filter(|p| is_real(**p)).map(|p| p.re).collect();
+  let mut zero_pairs: Vec<Complex<f64>> = zeros.iter().cloned().filter(|z| !is_real(*z) && z.im > 0f64).collect();
+  let mut real_zeros: Vec<f64> = zeros.iter().filter(|z| is_real(**z)).map(|z| z.re).collect();
+  pole_pairs.sort_by(|a, b| a.norm().partial_cmp(&b.norm()).unwrap());
+
+  // (b1, b2, a1, a2) of each section
+  let mut polynomials: Vec<(f64, f64, f64, f64)> = Vec::new();
+  for p in real_poles {
+    let z = real_zeros.pop().unwrap_or(infinite_zero);
+    polynomials.push((-z, 0f64, -p, 0f64));
+  }
+  for p in pole_pairs {
+    // Pairs each pole with the nearest remaining zero
+    let nearest = (0..zero_pairs.len())
+      .min_by(|&i, &j| (zero_pairs[i] - p).norm().partial_cmp(&(zero_pairs[j] - p).norm()).unwrap());
+    let (b1, b2) =
+      match nearest {
+        Some(i) => {
+          let z = zero_pairs.remove(i);
+          (-2f64 * z.re, z.norm_sqr())
+        },
+        None => {
+          let z1 = real_zeros.pop().unwrap_or(infinite_zero);
+          let z2 = real_zeros.pop().unwrap_or(infinite_zero);
+          (-(z1 + z2), z1 * z2)
+        }
+      };
+    polynomials.push((b1, b2, -2f64 * p.re, p.norm_sqr()));
+  }
+
+  let mut cascade = SosCascade::new();
+  for &(b1, b2, a1, a2) in polynomials.iter() {
+    let scale = (1f64 + a1 * reference + a2) / (1f64 + b1 * reference + b2);
+    cascade.push_section(BiquadCoefficients {
+      b0: num::cast(scale).unwrap(),
+      b1: num::cast(scale * b1).unwrap(),
+      b2: num::cast(scale * b2).unwrap(),
+      a1: num::cast(a1).unwrap(),
+      a2: num::cast(a2).unwrap()
+    });
+  }
+  cascade.set_gain(num::cast(gain).unwrap());
+  cascade
+}
+
+#[cfg(test)]
+mod tests {
+  use filter::SosCascade;
+  use traits::FrequencyResponse;
+
+  /// Returns the magnitude response of a cascade at `frequency`.
+  pub fn magnitude(cascade: &SosCascade<f64>, sample_rate: f64, frequency: f64) -> f64 {
+    cascade.magnitude_at(frequency, sample_rate)
+  }
+
+  /// Returns `true` if every pole of the cascade is inside the unit circle.
+  pub fn is_stable(cascade: &SosCascade<f64>) -> bool {
+    cascade.sections().iter().all(|c| c.a2.abs() < 1f64 && c.a1.abs() < 1f64 + c.a2)
+  }
+}
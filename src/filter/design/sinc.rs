@@ -0,0 +1,199 @@
+use num;
+use num::traits::Float;
+
+use traits::FloatConst;
+use window::{apply_window, Window};
+use super::{validate, DesignError};
+
+/// Returns the windowed ideal lowpass response, normalized to unity gain at
+/// DC.
+fn lowpass<T: Float + FloatConst>(num_taps: usize, sample_rate: T, cutoff_frequency: T, window: Window) -> Vec<T> {
+  let center: T = num::cast(num_taps - 1).unwrap();
+  let center = center / T::two();
+  let fc = T::two() * cutoff_frequency / sample_rate;
+  let mut taps: Vec<T> = (0..num_taps)
+    .map(|n| {
+      let x = num::cast::<usize, T>(n).unwrap() - center;
+      if x == T::zero() {
+        fc
+      }
+      else {
+        (T::pi() * fc * x).sin() / (T::pi() * x)
+      }
+    })
+    .collect();
+  // A window of one sample is undefined for most shapes
+  if num_taps > 1 {
+    apply_window(&mut taps, window);
+  }
+  let sum = taps.iter().fold(T::zero(), |sum, tap| sum + *tap);
+  taps.into_iter().map(|tap| tap / sum).collect()
+}
+
+/// Subtracts `taps` from a unit impulse at their center.
+fn invert<T: Float>(taps: Vec<T>) -> Vec<T> {
+  let center = taps.len() / 2;
+  taps.into_iter()
+    .enumerate()
+    .map(|(n, tap)| if n == center { T::one() - tap } else { -tap })
+    .collect()
+}
+
+/// Checks the frequencies of a band, in increasing order.
+fn validate_band<T: Float>(num_taps: usize, sample_rate: T, low: T, high: T) -> Result<(), DesignError> {
+  validate(num_taps, sample_rate, low)?;
+  validate(num_taps, sample_rate, high)?;
+  if low >= high {
+    return Err(DesignError::InvalidFrequency);
+  }
+  Ok(())
+}
+
+/// Designs a linear-phase lowpass FIR filter with the windowed-sinc method.
+///
+/// The ideal lowpass response at `cutoff_frequency` is truncated to
+/// `num_taps` taps, shaped by `window`, and normalized to unity gain at DC.
+/// The window trades the width of the transition band for the attenuation of
+/// the stopband, from the narrow transition but poor attenuation of a
+/// `Rectangular` window to the wide transition and deep attenuation of a
+/// `BlackmanHarris` window. The filter delays its input by `(num_taps - 1) /
+/// 2` samples, and the taps can be applied with a `filter::Fir`.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::Fir;
+/// use rasp::filter::design::fir_sinc;
+/// use rasp::window::Window;
+///
+/// let taps = fir_sinc(101, 44_100f32, 1_000f32, Window::Blackman).unwrap();
+/// let filter = Fir::new(&taps);
+/// assert_eq!(filter.len(), 101);
+/// ```
+pub fn fir_sinc<T>(num_taps: usize,
+                   sample_rate: T,
+                   cutoff_frequency: T,
+                   window: Window)
+                   -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  validate(num_taps, sample_rate, cutoff_frequency)?;
+  Ok(lowpass(num_taps, sample_rate, cutoff_frequency, window))
+}
+
+/// Designs a linear-phase highpass FIR filter with the windowed-sinc method,
+/// by subtracting the lowpass from a unit impulse.
+///
+/// `num_taps` must be odd, else the response is zero at Nyquist and
+/// `DesignError::InvalidOrder` is returned.
+pub fn fir_sinc_highpass<T>(num_taps: usize,
+                            sample_rate: T,
+                            cutoff_frequency: T,
+                            window: Window)
+                            -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  validate(num_taps, sample_rate, cutoff_frequency)?;
+  if num_taps.is_multiple_of(2) {
+    return Err(DesignError::InvalidOrder);
+  }
+  Ok(invert(lowpass(num_taps, sample_rate, cutoff_frequency, window)))
+}
+
+/// Designs a linear-phase bandpass FIR filter with the windowed-sinc method,
+/// passing from `low_frequency` to `high_frequency`, as the difference of
+/// two lowpass filters.
+pub fn fir_sinc_bandpass<T>(num_taps: usize,
+                            sample_rate: T,
+                            low_frequency: T,
+                            high_frequency: T,
+                            window: Window)
+                            -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  validate_band(num_taps, sample_rate, low_frequency, high_frequency)?;
+  let high = lowpass(num_taps, sample_rate, high_frequency, window);
+  let low = lowpass(num_taps, sample_rate, low_frequency, window);
+  Ok(high.into_iter().zip(low).map(|(h, l)| h - l).collect())
+}
+
+/// Designs a linear-phase bandstop FIR filter with the windowed-sinc method,
+/// rejecting from `low_frequency` to `high_frequency`, by subtracting the
+/// bandpass from a unit impulse.
+///
+/// `num_taps` must be odd, else the response is zero at Nyquist and
+/// `DesignError::InvalidOrder` is returned.
+pub fn fir_sinc_bandstop<T>(num_taps: usize,
+                            sample_rate: T,
+                            low_frequency: T,
+                            high_frequency: T,
+                            window: Window)
+                            -> Result<Vec<T>, DesignError>
+  where T: Float + FloatConst
+{
+  if num_taps.is_multiple_of(2) {
+    validate_band(num_taps, sample_rate, low_frequency, high_frequency)?;
+    return Err(DesignError::InvalidOrder);
+  }
+  fir_sinc_bandpass(num_taps, sample_rate, low_frequency, high_frequency, window).map(invert)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the magnitude response of `taps`, in dB.
+  fn response(taps: &[f64], sample_rate: f64, frequency: f64) -> f64 {
+    let w = 2f64 * ::std::f64::consts::PI * frequency / sample_rate;
+    let (mut re, mut im) = (0f64, 0f64);
+    for (n, tap) in taps.iter().enumerate() {
+      re += tap * (w * n as f64).cos();
+      im -= tap * (w * n as f64).sin();
+    }
+    20f64 * (re * re + im * im).sqrt().log10()
+  }
+
+  #[test]
+  fn lowpass() {
+    let sample_rate = 48_000f64;
+    let taps = fir_sinc(129, sample_rate, 4_000f64, Window::Blackman).unwrap();
+    // Linear phase
+    assert!((0..taps.len()).all(|n| (taps[n] - taps[taps.len() - 1 - n]).abs() < 1e-12f64));
+    assert!(response(&taps, sample_rate, 0f64).abs() < 1e-9f64);
+    assert!(response(&taps, sample_rate, 2_000f64).abs() < 0.01f64);
+    assert!((response(&taps, sample_rate, 4_000f64) - -6.02f64).abs() < 0.1f64);
+    assert!(response(&taps, sample_rate, 6_000f64) < -70f64);
+  }
+
+  #[test]
+  fn highpass() {
+    let sample_rate = 48_000f64;
+    let taps = fir_sinc_highpass(129, sample_rate, 4_000f64, Window::Hamming).unwrap();
+    assert!(response(&taps, sample_rate, 24_000f64).abs() < 0.01f64);
+    assert!(response(&taps, sample_rate, 1_000f64) < -40f64);
+    assert_eq!(fir_sinc_highpass(128, sample_rate, 4_000f64, Window::Hamming), Err(DesignError::InvalidOrder));
+  }
+
+  #[test]
+  fn band() {
+    let sample_rate = 48_000f64;
+    let bandpass = fir_sinc_bandpass(201, sample_rate, 2_000f64, 6_000f64, Window::Blackman).unwrap();
+    assert!(response(&bandpass, sample_rate, 4_000f64).abs() < 0.01f64);
+    assert!(response(&bandpass, sample_rate, 500f64) < -60f64);
+    assert!(response(&bandpass, sample_rate, 10_000f64) < -60f64);
+
+    let bandstop = fir_sinc_bandstop(201, sample_rate, 2_000f64, 6_000f64, Window::Blackman).unwrap();
+    assert!(response(&bandstop, sample_rate, 4_000f64) < -60f64);
+    assert!(response(&bandstop, sample_rate, 500f64).abs() < 0.01f64);
+    assert!(response(&bandstop, sample_rate, 12_000f64).abs() < 0.01f64);
+  }
+
+  #[test]
+  fn invalid_specifications() {
+    assert_eq!(fir_sinc(0, 48_000f32, 1_000f32, Window::Hann), Err(DesignError::InvalidOrder));
+    assert_eq!(fir_sinc(31, 48_000f32, 30_000f32, Window::Hann), Err(DesignError::InvalidFrequency));
+    assert_eq!(fir_sinc_bandpass(31, 48_000f32, 5_000f32, 1_000f32, Window::Hann), Err(DesignError::InvalidFrequency));
+    assert_eq!(fir_sinc_bandstop(32, 48_000f32, 1_000f32, 5_000f32, Window::Hann), Err(DesignError::InvalidOrder));
+    assert_eq!(fir_sinc(1, 48_000f32, 1_000f32, Window::Hann), Ok(vec![1f32]));
+  }
+}
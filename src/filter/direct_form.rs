@@ -0,0 +1,208 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::response::evaluate;
+use traits::{FloatConst, FrequencyResponse, Processor, StateSnapshot};
+use util;
+
+/// A single channel IIR filter of any order, from numerator and denominator
+/// coefficients.
+///
+/// This implementation uses a Transposed Direct Form II realization, the
+/// generalization of `Biquad2` to any order, with the following equations:
+///
+/// `y[n] = b0*x[n] + s0[n-1]; sk[n] = b(k+1)*x[n] + s(k+1)[n-1] - a(k+1)*y[n];`
+///
+/// The coefficients are in increasing powers of `z^-1`, as returned by the
+/// `filter` designers of MATLAB and SciPy, and are normalized so `a0` is one.
+/// High orders are sensitive to rounding in their coefficients, so filters
+/// above fourth order are usually better run as a `SosCascade`.
+#[derive(Clone)]
+pub struct DirectForm2T<T> {
+  b: Vec<T>,
+  a: Vec<T>,
+  state: Vec<T>,
+  output: T
+}
+
+impl<T> DirectForm2T<T> where T: Float {
+  /// Creates a new `DirectForm2T` filter with numerator coefficients `b` and
+  /// denominator coefficients `a`.
+  ///
+  /// `a` must not be empty, and `a[0]` must not be zero.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::DirectForm2T;
+  /// use rasp::traits::Processor;
+  ///
+  /// // A one-pole lowpass, y[n] = 0.5*x[n] + 0.5*y[n-1], with a0 of 2
+  /// let mut filter = DirectForm2T::new(&[1f32], &[2f32, -1f32]);
+  /// assert_eq!(filter.process(1f32), 0.5f32);
+  /// assert_eq!(filter.process(0f32), 0.25f32);
+  /// ```
+  pub fn new(b: &[T], a: &[T]) -> Self {
+    let mut filter = DirectForm2T {
+      b: Vec::new(),
+      a: Vec::new(),
+      state: Vec::new(),
+      output: num::zero()
+    };
+    filter.set_coefficients(b, a);
+    filter
+  }
+
+  /// Sets the numerator coefficients `b` and denominator coefficients `a`.
+  ///
+  /// `a` must not be empty, and `a[0]` must not be zero. The filter memory is
+  /// kept if the order does not change, and is cleared otherwise.
+  pub fn set_coefficients(&mut self, b: &[T], a: &[T]) {
+    debug_assert!(!a.is_empty() && a[0] != T::zero());
+    let length = b.len().max(a.len()).max(1);
+    let a0 = a[0];
+    let normalize = |coefficients: &[T]| {
+      (0..length)
+        .map(|k| coefficients.get(k).map(|c| *c / a0).unwrap_or_else(T::zero))
+        .collect::<Vec<T>>()
+    };
+    self.b = normalize(b);
+    self.a = normalize(a);
+    if self.state.len() != length - 1 {
+      self.state = vec![num::zero(); length - 1];
+    }
+  }
+
+  /// Returns the normalized numerator coefficients.
+  pub fn numerator(&self) -> &[T] {
+    &self.b
+  }
+
+  /// Returns the normalized denominator coefficients, starting with one.
+  pub fn denominator(&self) -> &[T] {
+    &self.a
+  }
+
+  /// Returns the order of the filter, the length of the longer of the
+  /// numerator and denominator, less one.
+  pub fn order(&self) -> usize {
+    self.state.len()
+  }
+}
+
+impl<T> Processor<T> for DirectForm2T<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let order = self.state.len();
+    self.output = self.b[0] * sample + self.state.first().cloned().unwrap_or_else(T::zero);
+    for k in 0..order {
+      let next = if k + 1 < order { self.state[k + 1] } else { T::zero() };
+      self.state[k] = util::flush_denormal(self.b[k + 1] * sample + next - self.a[k + 1] * self.output);
+    }
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for state in self.state.iter_mut() {
+      *state = num::zero();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> StateSnapshot for DirectForm2T<T> where T: Float {
+  type State = (Vec<T>, T);
+
+  fn snapshot(&self) -> Self::State {
+    (self.state.clone(), self.output)
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    debug_assert!(state.0.len() == self.state.len());
+    self.state.copy_from_slice(&state.0);
+    self.output = state.1;
+  }
+}
+
+impl<T> FrequencyResponse<T> for DirectForm2T<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&self.b, &self.a, frequency, sample_rate)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Biquad2;
+  use filter::design::{chebyshev1, Band};
+  use filter::rbj::LowPass;
+
+  #[test]
+  fn matches_biquad() {
+    let c = LowPass::coefficients(48_000f64, 2_000f64, 0.9f64);
+    let mut biquad = Biquad2::new();
+    biquad.load_coefficients(c);
+    // Unnormalized coefficients, as scaled by a0 of 4
+    let mut filter = DirectForm2T::new(&[4f64 * c.b0, 4f64 * c.b1, 4f64 * c.b2], &[4f64, 4f64 * c.a1, 4f64 * c.a2]);
+    assert_eq!(filter.order(), 2);
+    assert_eq!(filter.denominator()[0], 1f64);
+    for n in 0..64 {
+      let sample = (n as f64 * 0.3f64).sin();
+      assert!((filter.process(sample) - biquad.process(sample)).abs() < 1e-12f64);
+    }
+  }
+
+  #[test]
+  fn high_order() {
+    // The product of a designed cascade, expanded into one polynomial
+    let mut cascade = chebyshev1(Band::LowPass, 6, 48_000f64, 4_000f64, 1f64).unwrap();
+    let multiply = |p: &[f64], q: &[f64]| {
+      let mut product = vec![0f64; p.len() + q.len() - 1];
+      for (i, x) in p.iter().enumerate() {
+        for (j, y) in q.iter().enumerate() {
+          product[i + j] += x * y;
+        }
+      }
+      product
+    };
+    let (mut b, mut a) = (vec![cascade.get_gain()], vec![1f64]);
+    for c in cascade.coefficients() {
+      b = multiply(&b, &[c.b0, c.b1, c.b2]);
+      a = multiply(&a, &[1f64, c.a1, c.a2]);
+    }
+
+    let mut filter = DirectForm2T::new(&b, &a);
+    assert_eq!(filter.order(), 6);
+    for frequency in [100f64, 3_000f64, 10_000f64].iter() {
+      let expected = cascade.magnitude_at(*frequency, 48_000f64);
+      assert!((filter.magnitude_at(*frequency, 48_000f64) - expected).abs() < 1e-9f64);
+    }
+    for n in 0..256 {
+      let sample = if n == 0 { 1f64 } else { 0f64 };
+      assert!((filter.process(sample) - cascade.process(sample)).abs() < 1e-9f64);
+    }
+
+    let state = filter.snapshot();
+    let next = filter.process(0f64);
+    filter.restore(&state);
+    assert_eq!(filter.process(0f64), next);
+    filter.clear();
+    assert_eq!(filter.process(0f64), 0f64);
+  }
+
+  #[test]
+  fn fir() {
+    // Without feedback, the filter is an FIR, and a gain alone has no memory
+    let mut filter = DirectForm2T::new(&[0.5f32, 0.5f32], &[1f32]);
+    let mut samples = [1f32, 0f32, 0f32];
+    filter.process_block(&mut samples);
+    assert_eq!(samples, [0.5f32, 0.5f32, 0f32]);
+    filter.set_coefficients(&[2f32], &[1f32]);
+    assert_eq!((filter.order(), filter.process(1f32)), (0, 2f32));
+    assert_eq!(filter.numerator(), &[2f32]);
+  }
+}
@@ -0,0 +1,188 @@
+use num;
+use num::traits::Float;
+
+use traits::{FloatConst, Processor};
+use util;
+
+/// A one-pole smoother whose cutoff rises with the distance to its input.
+///
+/// A fixed one-pole trades lag for smoothing: a low cutoff hides the steps
+/// and jitter of a control signal, but takes long to follow real changes.
+/// Here the coefficient of the one-pole is raised by the distance between
+/// the input and the output, scaled by the sensitivity, so a large change
+/// is followed almost at once, while small changes, such as noise or the
+/// steps of a quantized control, are smoothed at the base cutoff. This is
+/// the one-pole form of Andrew Simper's dynamic smoothing.
+///
+/// The sensitivity is relative to the range of the input, so a control from
+/// 0 to 1 suits a sensitivity around 1 to 10, and a control in Hz one far
+/// smaller. Without any sensitivity, the smoother is a plain one-pole.
+#[derive(Clone)]
+pub struct DynamicSmoother<T> {
+  sample_rate: T,
+  cutoff: T,
+  sensitivity: T,
+  base: T,
+  coefficient: T,
+  output: T
+}
+
+impl<T> DynamicSmoother<T> where T: Float + FloatConst {
+  /// Creates a new `DynamicSmoother` for signals at `sample_rate`, with a
+  /// base cutoff of 2 Hz and a sensitivity of 2.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::DynamicSmoother;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut smoother = DynamicSmoother::new(44_100f32);
+  /// smoother.set_cutoff(1f32);
+  /// smoother.set_sensitivity(5f32);
+  ///
+  /// // A jump of the control is followed within a few milliseconds
+  /// let mut value = 0f32;
+  /// for _ in 0..441 {
+  ///   value = smoother.process(1f32);
+  /// }
+  /// assert!(value > 0.9f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut smoother = DynamicSmoother {
+      sample_rate,
+      cutoff: T::two(),
+      sensitivity: T::two(),
+      base: num::zero(),
+      coefficient: num::zero(),
+      output: num::zero()
+    };
+    let cutoff = smoother.cutoff;
+    smoother.set_cutoff(cutoff);
+    smoother
+  }
+
+  /// Sets the cutoff frequency, in Hz, while the input is still.
+  ///
+  /// `cutoff` must be positive and below Nyquist, else it is not updated.
+  pub fn set_cutoff(&mut self, cutoff: T) {
+    if cutoff > T::zero() && cutoff < self.sample_rate / T::two() {
+      let g = (T::pi() * cutoff / self.sample_rate).tan();
+      self.cutoff = cutoff;
+      self.base = T::two() * g / (T::one() + g);
+      self.coefficient = self.base;
+    }
+  }
+
+  /// Returns the cutoff frequency, in Hz, while the input is still.
+  pub fn get_cutoff(&self) -> T {
+    self.cutoff
+  }
+
+  /// Sets how much the distance to the input raises the coefficient of the
+  /// one-pole.
+  ///
+  /// `sensitivity` must be at least zero, else it is not updated.
+  pub fn set_sensitivity(&mut self, sensitivity: T) {
+    if sensitivity >= T::zero() && sensitivity.is_finite() {
+      self.sensitivity = sensitivity;
+    }
+  }
+
+  /// Returns how much the distance to the input raises the coefficient of
+  /// the one-pole.
+  pub fn get_sensitivity(&self) -> T {
+    self.sensitivity
+  }
+
+  /// Returns the coefficient of the one-pole at the last sample, from the
+  /// base coefficient up to one, where the input passes unsmoothed.
+  pub fn get_coefficient(&self) -> T {
+    self.coefficient
+  }
+
+  /// Sets the output to `value` at once, such as when a control is first
+  /// set.
+  pub fn reset(&mut self, value: T) {
+    self.output = value;
+  }
+}
+
+impl<T> Processor<T> for DynamicSmoother<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let distance = (sample - self.output).abs();
+    self.coefficient = (self.base + self.sensitivity * distance).min(T::one());
+    self.output = util::flush_denormal(self.output + (sample - self.output) * self.coefficient);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.coefficient = self.base;
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the number of samples `smoother` takes to reach 90% of a step
+  /// of `size`.
+  fn rise_time(smoother: &mut DynamicSmoother<f64>, size: f64) -> usize {
+    smoother.clear();
+    (1..100_000).find(|_| smoother.process(size) >= 0.9f64 * size).unwrap()
+  }
+
+  #[test]
+  fn adapts() {
+    let mut smoother = DynamicSmoother::new(48_000f64);
+    smoother.set_cutoff(2f64);
+    smoother.set_sensitivity(2f64);
+
+    // Large steps are followed much faster than small ones
+    let large = rise_time(&mut smoother, 0.1f64);
+    let small = rise_time(&mut smoother, 1e-5f64);
+    assert!(large < 100 && small > 8_000);
+
+    // Without sensitivity, the rise time does not depend on the step, and is
+    // that of a one-pole at 2 Hz, rising by 90% in ln(10) / (2 pi 2 Hz)
+    // seconds
+    smoother.set_sensitivity(0f64);
+    let base = rise_time(&mut smoother, 0.1f64);
+    assert_eq!(base, rise_time(&mut smoother, 1e-5f64));
+    let expected = 10f64.ln() / (2f64 * ::std::f64::consts::PI * 2f64) * 48_000f64;
+    assert!((base as f64 / expected - 1f64).abs() < 0.01f64);
+  }
+
+  #[test]
+  fn smooths_jitter() {
+    // Small noise around a held value is smoothed at the base cutoff
+    let mut smoother = DynamicSmoother::new(48_000f64);
+    smoother.reset(0.5f64);
+    let mut peak = 0f64;
+    for n in 0..48_000 {
+      let jitter = if n % 2 == 0 { 0.001f64 } else { -0.001f64 };
+      peak = peak.max((smoother.process(0.5f64 + jitter) - 0.5f64).abs());
+      assert!(smoother.get_coefficient() < 0.01f64);
+    }
+    assert!(peak < 1e-5f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut smoother = DynamicSmoother::new(44_100f32);
+    smoother.set_cutoff(0f32);
+    smoother.set_cutoff(30_000f32);
+    smoother.set_sensitivity(-1f32);
+    assert_eq!((smoother.get_cutoff(), smoother.get_sensitivity()), (2f32, 2f32));
+
+    smoother.reset(1f32);
+    assert_eq!(smoother.last_out(), 1f32);
+    smoother.clear();
+    assert_eq!(smoother.last_out(), 0f32);
+  }
+}
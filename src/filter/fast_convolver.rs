@@ -0,0 +1,187 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use traits::{FloatConst, Processor};
+
+/// A finite impulse response filter, convolving blocks of samples with the
+/// fast Fourier transform.
+///
+/// Input is collected into blocks of `block_size` samples, which are
+/// convolved with the taps at once by multiplying their spectra, and the tail
+/// of each convolution is overlapped and added to the following blocks. This
+/// costs a few operations per sample for each doubling of the filter length,
+/// where a `Fir` costs one multiplication per tap, so filters and impulse
+/// responses thousands of taps long can run in real time.
+///
+/// The output is delayed by `block_size` samples, as every block must be
+/// complete before it is convolved. Larger blocks are more efficient, while
+/// smaller blocks reduce the latency.
+pub struct FastConvolver<T> {
+  block_size: usize,
+  taps: usize,
+  // Spectrum of the zero-padded taps
+  spectrum: Vec<Complex<T>>,
+  buffer: Vec<Complex<T>>,
+  input: Vec<T>,
+  // The block being output, then the tail added to the following blocks
+  output: Vec<T>,
+  overlap: Vec<T>,
+  position: usize,
+  last: T
+}
+
+impl<T> FastConvolver<T> where T: Float + FloatConst {
+  /// Creates a new `FastConvolver` with the given `taps`, processing blocks
+  /// of `block_size` samples.
+  ///
+  /// `block_size` must be at least one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::FastConvolver;
+  /// use rasp::traits::Processor;
+  ///
+  /// let taps = vec![0.5f32; 4_096];
+  /// let mut convolver = FastConvolver::new(&taps, 256);
+  /// assert_eq!(convolver.latency(), 256);
+  ///
+  /// let mut block = vec![0f32; 512];
+  /// block[0] = 1f32;
+  /// convolver.process_block(&mut block);
+  /// assert!((block[256] - 0.5f32).abs() < 1e-6f32);
+  /// ```
+  pub fn new(taps: &[T], block_size: usize) -> Self {
+    debug_assert!(block_size >= 1);
+    let mut convolver = FastConvolver {
+      block_size,
+      taps: 0,
+      spectrum: Vec::new(),
+      buffer: Vec::new(),
+      input: vec![num::zero(); block_size],
+      output: vec![num::zero(); block_size],
+      overlap: Vec::new(),
+      position: 0,
+      last: num::zero()
+    };
+    convolver.set_taps(taps);
+    convolver
+  }
+
+  /// Sets the taps of the filter, clearing the previous input.
+  pub fn set_taps(&mut self, taps: &[T]) {
+    let size = (self.block_size + taps.len()).next_power_of_two();
+    self.taps = taps.len();
+    self.spectrum = (0..size)
+      .map(|n| Complex::new(taps.get(n).cloned().unwrap_or_else(T::zero), T::zero()))
+      .collect();
+    fft::forward(&mut self.spectrum);
+    self.buffer = vec![Complex::new(T::zero(), T::zero()); size];
+    self.overlap = vec![num::zero(); size];
+    self.clear();
+  }
+
+  /// Returns the number of taps.
+  pub fn len(&self) -> usize {
+    self.taps
+  }
+
+  /// Returns `true` if the filter has no taps.
+  pub fn is_empty(&self) -> bool {
+    self.taps == 0
+  }
+
+  /// Returns the delay of the output, in samples, which is the block size.
+  pub fn latency(&self) -> usize {
+    self.block_size
+  }
+
+  /// Convolves the collected block of input.
+  fn convolve(&mut self) {
+    for (n, value) in self.buffer.iter_mut().enumerate() {
+      let sample = self.input.get(n).cloned().unwrap_or_else(T::zero);
+      *value = Complex::new(sample, T::zero());
+    }
+    fft::forward(&mut self.buffer);
+    for (value, tap) in self.buffer.iter_mut().zip(self.spectrum.iter()) {
+      *value = *value * *tap;
+    }
+    fft::inverse(&mut self.buffer);
+
+    // Adds the result to the tail of the previous blocks, then outputs its
+    // start and keeps the rest
+    let size = self.buffer.len();
+    for (sum, value) in self.overlap.iter_mut().zip(self.buffer.iter()) {
+      *sum = *sum + value.re;
+    }
+    self.output.copy_from_slice(&self.overlap[..self.block_size]);
+    self.overlap.rotate_left(self.block_size);
+    for sum in self.overlap[size - self.block_size..].iter_mut() {
+      *sum = T::zero();
+    }
+  }
+}
+
+impl<T> Processor<T> for FastConvolver<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.input[self.position] = sample;
+    self.last = self.output[self.position];
+    self.position += 1;
+    if self.position == self.block_size {
+      self.convolve();
+      self.position = 0;
+    }
+    self.last
+  }
+
+  fn clear(&mut self) {
+    for sample in self.input.iter_mut().chain(self.output.iter_mut()).chain(self.overlap.iter_mut()) {
+      *sample = T::zero();
+    }
+    self.position = 0;
+    self.last = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.last
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Fir;
+  use ::traits::Processor;
+
+  #[test]
+  fn matches_fir() {
+    let taps: Vec<f64> = (0..300).map(|n| ((n * 31 % 17) as f64 - 8f64) / 8f64).collect();
+    let input: Vec<f64> = (0..1_000).map(|n| (n as f64 * 0.13f64).sin() + ((n * 7 % 5) as f64 - 2f64) * 0.1f64).collect();
+    for block_size in [1, 64, 100, 512].iter() {
+      let mut convolver = FastConvolver::new(&taps, *block_size);
+      let mut fir = Fir::new(&taps);
+      let expected: Vec<f64> = input.iter().map(|sample| fir.process(*sample)).collect();
+      let output: Vec<f64> = input.iter().map(|sample| convolver.process(*sample)).collect();
+      assert!(output[..*block_size].iter().all(|sample| *sample == 0f64));
+      for (sample, expected) in output[*block_size..].iter().zip(expected.iter()) {
+        assert!((sample - expected).abs() < 1e-9f64);
+      }
+    }
+  }
+
+  #[test]
+  fn set_taps() {
+    let mut convolver = FastConvolver::new(&[1f32], 4);
+    convolver.set_taps(&[0f32, 2f32]);
+    assert_eq!(convolver.len(), 2);
+    let mut block = vec![1f32, 0f32, 0f32, 0f32, 0f32, 0f32, 0f32, 0f32];
+    convolver.process_block(&mut block);
+    assert!((block[5] - 2f32).abs() < 1e-6f32);
+
+    convolver.clear();
+    assert_eq!(convolver.last_out(), 0f32);
+    assert_eq!(convolver.process(0f32), 0f32);
+  }
+}
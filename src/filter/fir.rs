@@ -0,0 +1,168 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::response::evaluate;
+use traits::{FloatConst, FrequencyResponse, Processor};
+
+/// A single channel, finite impulse response filter.
+///
+/// A `Fir` filter uses the following equation:
+///
+/// `y[n] = h[0]*x[n] + h[1]*x[n-1] + ... + h[N-1]*x[n-N+1]`
+///
+/// Previous input is held in a circular buffer that is stored twice, back to
+/// back, so the last `N` samples are always contiguous and each sample only
+/// writes two values, however long the filter is.
+#[derive(Clone)]
+pub struct Fir<T> {
+  taps: Vec<T>,
+  // Two copies of the circular buffer, the newest sample first
+  memory: Vec<T>,
+  read_ptr: usize,
+  output: T
+}
+
+impl<T> Fir<T> where T: Float {
+  /// Creates a new `Fir` filter with the given `taps`.
+  ///
+  /// A filter without any taps outputs silence.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::Fir;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = Fir::new(&[0.5f32, 0.5f32]);
+  /// assert_eq!(filter.process(1f32), 0.5f32);
+  /// assert_eq!(filter.process(0f32), 0.5f32);
+  /// assert_eq!(filter.process(0f32), 0f32);
+  /// ```
+  pub fn new(taps: &[T]) -> Self {
+    let mut filter = Fir {
+      taps: Vec::new(),
+      memory: Vec::new(),
+      read_ptr: 0,
+      output: num::zero()
+    };
+    filter.set_taps(taps);
+    filter
+  }
+
+  /// Sets the taps of the filter.
+  ///
+  /// The previous input is kept if the filter length does not change, and is
+  /// cleared otherwise. Setting taps of the same length does not allocate.
+  pub fn set_taps(&mut self, taps: &[T]) {
+    if taps.len() != self.taps.len() {
+      self.memory = vec![num::zero(); 2 * taps.len()];
+      self.read_ptr = 0;
+    }
+    self.taps.clear();
+    self.taps.extend_from_slice(taps);
+  }
+
+  /// Returns the taps of the filter.
+  pub fn get_taps(&self) -> &[T] {
+    &self.taps
+  }
+
+  /// Returns the number of taps.
+  pub fn len(&self) -> usize {
+    self.taps.len()
+  }
+
+  /// Returns `true` if the filter has no taps.
+  pub fn is_empty(&self) -> bool {
+    self.taps.is_empty()
+  }
+}
+
+impl<T> Processor<T> for Fir<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let length = self.taps.len();
+    if length == 0 {
+      return num::zero();
+    }
+
+    // Steps back, so the newest sample precedes the previous ones
+    self.read_ptr = if self.read_ptr == 0 { length - 1 } else { self.read_ptr - 1 };
+    self.memory[self.read_ptr] = sample;
+    self.memory[self.read_ptr + length] = sample;
+
+    let history = &self.memory[self.read_ptr..self.read_ptr + length];
+    self.output = self.taps.iter().zip(history.iter())
+      .fold(T::zero(), |sum, (tap, x)| sum + *tap * *x);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for sample in self.memory.iter_mut() {
+      *sample = num::zero();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for Fir<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&self.taps, &[T::one()], frequency, sample_rate)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::Processor;
+
+  #[test]
+  fn impulse_response() {
+    let taps = vec![0.1f32, -0.4f32, 0.7f32, 0.2f32, -0.05f32];
+    let mut filter = Fir::new(&taps);
+    let mut samples = vec![0f32; 8];
+    samples[0] = 1f32;
+    filter.process_block(&mut samples);
+    assert_eq!(&samples[..5], &taps[..]);
+    assert!(samples[5..].iter().all(|sample| *sample == 0f32));
+  }
+
+  #[test]
+  fn convolution() {
+    let taps = vec![0.25f64, 0.5f64, -0.125f64];
+    let input: Vec<f64> = (0..32).map(|n| (n as f64 * 0.37f64).sin()).collect();
+    let mut filter = Fir::new(&taps);
+    for (n, sample) in input.iter().enumerate() {
+      let expected = (0..taps.len())
+        .filter(|k| *k <= n)
+        .fold(0f64, |sum, k| sum + taps[k] * input[n - k]);
+      assert!((filter.process(*sample) - expected).abs() < 1e-12f64);
+    }
+  }
+
+  #[test]
+  fn set_taps() {
+    let mut filter = Fir::new(&[1f32, 0f32]);
+    filter.process(1f32);
+    // The same length keeps the previous input
+    filter.set_taps(&[0f32, 1f32]);
+    assert_eq!(filter.process(0f32), 1f32);
+
+    filter.set_taps(&[0f32, 0f32, 1f32]);
+    assert_eq!(filter.len(), 3);
+    assert_eq!(filter.process(0f32), 0f32);
+
+    filter.process(1f32);
+    filter.clear();
+    assert_eq!(filter.last_out(), 0f32);
+    assert_eq!(filter.process(0f32), 0f32);
+
+    let mut empty = Fir::<f32>::new(&[]);
+    assert!(empty.is_empty());
+    assert_eq!(empty.process(1f32), 0f32);
+  }
+}
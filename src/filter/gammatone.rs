@@ -0,0 +1,226 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use traits::FloatConst;
+use util;
+use util::scales::{erb_bandwidth, erb_to_frequency, frequency_to_erb};
+
+/// The order of each gammatone filter.
+const ORDER: usize = 4;
+
+/// The bandwidth of a fourth order gammatone filter, in ERB, whose
+/// equivalent rectangular bandwidth is one ERB.
+const BANDWIDTH: f64 = 1.019f64;
+
+/// One channel of the filterbank, as a cascade of complex one-pole filters.
+#[derive(Clone)]
+struct Channel<T> {
+  pole: Complex<T>,
+  gain: T,
+  state: [Complex<T>; ORDER]
+}
+
+/// A bank of gammatone filters, spaced evenly on the ERB-rate scale.
+///
+/// The gammatone filter models the response of the basilar membrane at one
+/// place along the cochlea, and its bandwidth follows the equivalent
+/// rectangular bandwidth of the auditory filter at its center frequency, so
+/// the bank resolves low frequencies finely and high frequencies coarsely, as
+/// hearing does. Each filter is fourth order, implemented as Hohmann's
+/// cascade of complex one-pole filters, so every channel also has an analytic
+/// output whose magnitude is the envelope of the channel. Each channel has
+/// unity gain at its center frequency.
+#[derive(Clone)]
+pub struct GammatoneFilterbank<T> {
+  frequencies: Vec<T>,
+  channels: Vec<Channel<T>>,
+  outputs: Vec<T>,
+  envelopes: Vec<T>
+}
+
+impl<T> GammatoneFilterbank<T> where T: Float + FloatConst {
+  /// Creates a new `GammatoneFilterbank` of `channels` filters, with center
+  /// frequencies spaced evenly on the ERB-rate scale from `low` to `high`,
+  /// in Hz.
+  ///
+  /// There must be at least one channel, `low` must be positive, and `high`
+  /// must be at least `low` and below Nyquist. A single channel is centered
+  /// at `low`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::GammatoneFilterbank;
+  ///
+  /// let mut bank = GammatoneFilterbank::new(16_000f32, 100f32, 6_000f32, 32);
+  /// assert_eq!(bank.channels(), 32);
+  ///
+  /// let outputs = bank.tick(1f32);
+  /// assert_eq!(outputs.len(), 32);
+  /// ```
+  pub fn new(sample_rate: T, low: T, high: T, channels: usize) -> Self {
+    debug_assert!(channels > 0);
+    debug_assert!(low > T::zero() && low <= high && high < sample_rate / T::two());
+    let low_erb = frequency_to_erb(low);
+    let step =
+      if channels > 1 {
+        (frequency_to_erb(high) - low_erb) / num::cast(channels - 1).unwrap()
+      }
+      else {
+        T::zero()
+      };
+    let frequencies: Vec<T> = (0..channels)
+      .map(|channel| erb_to_frequency(low_erb + step * num::cast(channel).unwrap()))
+      .collect();
+
+    let zero = Complex::new(T::zero(), T::zero());
+    let bandwidth: T = num::cast(BANDWIDTH).unwrap();
+    let channels = frequencies.iter()
+      .map(|frequency| {
+        let radius = (-T::two() * T::pi() * bandwidth * erb_bandwidth(*frequency) / sample_rate).exp();
+        let angle = T::two() * T::pi() * *frequency / sample_rate;
+        Channel {
+          pole: Complex::from_polar(&radius, &angle),
+          gain: T::one() - radius,
+          state: [zero; ORDER]
+        }
+      })
+      .collect();
+
+    GammatoneFilterbank {
+      outputs: vec![num::zero(); frequencies.len()],
+      envelopes: vec![num::zero(); frequencies.len()],
+      frequencies,
+      channels
+    }
+  }
+
+  /// Returns the number of channels.
+  pub fn channels(&self) -> usize {
+    self.channels.len()
+  }
+
+  /// Returns the center frequency of each channel, in Hz, from lowest to
+  /// highest.
+  pub fn get_frequencies(&self) -> &[T] {
+    &self.frequencies
+  }
+
+  /// Filters a sample, returning the output of each channel from lowest to
+  /// highest.
+  pub fn tick(&mut self, sample: T) -> &[T] {
+    for ((channel, output), envelope) in self.channels.iter_mut().zip(self.outputs.iter_mut()).zip(self.envelopes.iter_mut()) {
+      let mut value = Complex::new(sample, T::zero());
+      for state in channel.state.iter_mut() {
+        value = value * channel.gain + channel.pole * *state;
+        *state = Complex::new(util::flush_denormal(value.re), util::flush_denormal(value.im));
+        value = *state;
+      }
+      // The filter passes only positive frequencies, which hold half the
+      // amplitude of a real signal
+      *output = T::two() * value.re;
+      *envelope = T::two() * value.norm();
+    }
+    &self.outputs
+  }
+
+  /// Returns the envelope of each channel after the last sample, from lowest
+  /// to highest.
+  pub fn envelopes(&self) -> &[T] {
+    &self.envelopes
+  }
+
+  /// Filters a block of samples into a buffer for each channel, from lowest
+  /// to highest.
+  ///
+  /// There must be a buffer for each channel, and each must be at least as
+  /// long as `input`.
+  pub fn process_block(&mut self, input: &[T], outputs: &mut [&mut [T]]) {
+    debug_assert_eq!(outputs.len(), self.channels());
+    debug_assert!(outputs.iter().all(|output| output.len() >= input.len()));
+    for (n, sample) in input.iter().enumerate() {
+      self.tick(*sample);
+      for (output, channel) in outputs.iter_mut().zip(self.outputs.iter()) {
+        output[n] = *channel;
+      }
+    }
+  }
+
+  /// Clears the memory of every filter.
+  pub fn clear(&mut self) {
+    let zero = Complex::new(T::zero(), T::zero());
+    for channel in self.channels.iter_mut() {
+      channel.state = [zero; ORDER];
+    }
+    for (output, envelope) in self.outputs.iter_mut().zip(self.envelopes.iter_mut()) {
+      *output = T::zero();
+      *envelope = T::zero();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use util;
+
+  /// Returns the peak output and the final envelope of each channel for a
+  /// sine at `frequency`.
+  fn response(bank: &mut GammatoneFilterbank<f64>, frequency: f64) -> (Vec<f64>, Vec<f64>) {
+    bank.clear();
+    let mut peaks = vec![0f64; bank.channels()];
+    for n in 0..16_000 {
+      let sample = (2f64 * ::std::f64::consts::PI * frequency * n as f64 / 16_000f64).sin();
+      let outputs = bank.tick(sample).to_vec();
+      if n >= 8_000 {
+        for (peak, output) in peaks.iter_mut().zip(outputs.iter()) {
+          *peak = peak.max(output.abs());
+        }
+      }
+    }
+    (peaks, bank.envelopes().to_vec())
+  }
+
+  #[test]
+  fn spacing() {
+    let bank = GammatoneFilterbank::new(16_000f64, 100f64, 4_000f64, 12);
+    let frequencies = bank.get_frequencies();
+    assert!((frequencies[0] - 100f64).abs() < 1e-9f64 && (frequencies[11] - 4_000f64).abs() < 1e-9f64);
+    let steps: Vec<f64> = frequencies.windows(2).map(|pair| frequency_to_erb(pair[1]) - frequency_to_erb(pair[0])).collect();
+    assert!(steps.iter().all(|step| (step - steps[0]).abs() < 1e-9f64));
+    let single = GammatoneFilterbank::new(16_000f64, 500f64, 500f64, 1);
+    assert!(single.channels() == 1 && (single.get_frequencies()[0] - 500f64).abs() < 1e-9f64);
+  }
+
+  #[test]
+  fn selectivity() {
+    let mut bank = GammatoneFilterbank::new(16_000f64, 200f64, 4_000f64, 16);
+    let center = bank.get_frequencies()[8];
+    let (peaks, envelopes) = response(&mut bank, center);
+
+    // Unity gain at the center, with a steady envelope
+    assert!((peaks[8] - 1f64).abs() < 0.02f64);
+    assert!((envelopes[8] - 1f64).abs() < 0.02f64);
+    let loudest = (0..16).max_by(|a, b| peaks[*a].partial_cmp(&peaks[*b]).unwrap()).unwrap();
+    assert_eq!(loudest, 8);
+
+    // Two ERBs away, the response is far down
+    let (peaks, _) = response(&mut bank, center + 2f64 * erb_bandwidth(center));
+    assert!(util::to_db(peaks[8]) < -20f64);
+  }
+
+  #[test]
+  fn block() {
+    let mut bank = GammatoneFilterbank::new(16_000f64, 100f64, 1_000f64, 2);
+    let input = [1f64, 0f64, 0f64, 0f64];
+    let mut low = [0f64; 4];
+    let mut high = [0f64; 4];
+    bank.process_block(&input, &mut [&mut low, &mut high]);
+    assert!(low.iter().chain(high.iter()).any(|sample| *sample != 0f64));
+    let mut copy = bank.clone();
+    assert_eq!(bank.tick(0f64), copy.tick(0f64));
+    bank.clear();
+    assert_eq!(bank.tick(0f64), &[0f64, 0f64]);
+  }
+}
@@ -0,0 +1,219 @@
+//! Utilities preparing impulse responses for convolution.
+//!
+//! Impulse responses supplied by users are often recorded at another sample
+//! rate, padded with silence, cut off abruptly, or at an arbitrary level.
+//! These functions trim, fade, normalize and resample them, so they can be
+//! loaded into a `FastConvolver` or `PartitionedConvolver` at the rate of
+//! the session. Each function leaves an empty impulse response empty.
+
+use num;
+use num::traits::Float;
+
+use traits::FloatConst;
+
+/// The number of zero crossings, at the lower of the two rates, on each side
+/// of the interpolation kernel of `resample()`.
+const ZERO_CROSSINGS: usize = 32;
+
+/// The level to which `normalize()` scales an impulse response.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Normalization {
+  /// The largest sample is one.
+  Peak,
+  /// The sum of squared samples is one, so white noise is filtered at unity
+  /// gain.
+  Energy
+}
+
+/// Returns the part of `ir` from the first to the last sample whose level is
+/// at least `threshold`, in dB relative to the peak.
+///
+/// Leading silence otherwise delays the convolution, and trailing silence
+/// only costs processing. An impulse response of only zeros is trimmed to
+/// nothing.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::ir;
+///
+/// let response = [0f32, 0.0001f32, 1f32, -0.5f32, 0.01f32, 0.0001f32, 0f32];
+/// assert_eq!(ir::trim_silence(&response, -60f32), &[1f32, -0.5f32, 0.01f32]);
+/// ```
+pub fn trim_silence<T: Float>(ir: &[T], threshold: T) -> &[T] {
+  let peak = ir.iter().fold(T::zero(), |peak, sample| peak.max(sample.abs()));
+  if peak == T::zero() {
+    return &ir[..0];
+  }
+  let twenty: T = num::cast(20f64).unwrap();
+  let ten: T = num::cast(10f64).unwrap();
+  let floor = peak * ten.powf(threshold / twenty);
+  let audible = |sample: &T| sample.abs() >= floor && *sample != T::zero();
+  let start = ir.iter().position(&audible).unwrap_or(0);
+  let end = ir.iter().rposition(&audible).map_or(0, |end| end + 1);
+  &ir[start..end]
+}
+
+/// Fades out the last `length` samples of `ir` with a half cosine, reaching
+/// zero at the last sample, so a truncated tail does not end in a click.
+///
+/// `length` is clipped to the length of `ir`.
+pub fn fade_out<T: Float + FloatConst>(ir: &mut [T], length: usize) {
+  let length = length.min(ir.len());
+  let start = ir.len() - length;
+  let length_float: T = num::cast(length).unwrap();
+  for (n, sample) in ir[start..].iter_mut().enumerate() {
+    let phase = T::pi() * num::cast::<usize, T>(n + 1).unwrap() / length_float;
+    *sample = *sample * (T::one() + phase.cos()) / T::two();
+  }
+}
+
+/// Scales `ir` in place to the `normalization`, returning the gain applied.
+///
+/// An impulse response of only zeros is left unchanged, with a gain of one.
+pub fn normalize<T: Float>(ir: &mut [T], normalization: Normalization) -> T {
+  let level =
+    match normalization {
+      Normalization::Peak => ir.iter().fold(T::zero(), |peak, sample| peak.max(sample.abs())),
+      Normalization::Energy => ir.iter().fold(T::zero(), |sum, sample| sum + *sample * *sample).sqrt()
+    };
+  if level == T::zero() {
+    return T::one();
+  }
+  let gain = T::one() / level;
+  for sample in ir.iter_mut() {
+    *sample = *sample * gain;
+  }
+  gain
+}
+
+/// Resamples `ir`, recorded at `from_rate`, to `to_rate`, by band-limited
+/// interpolation.
+///
+/// Each output sample is interpolated with a Blackman windowed sinc, with
+/// its cutoff at the lower Nyquist frequency, so downsampling removes the
+/// frequencies that would alias. The samples are scaled by the ratio of the
+/// rates, so the resampled response filters with the same gain at every
+/// frequency both rates can represent, and its length is scaled by the ratio
+/// too, rounded up.
+///
+/// Both rates must be positive.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::ir;
+///
+/// let response = vec![1f32, 0.5f32, 0.25f32, 0.125f32];
+/// let resampled = ir::resample(&response, 44_100f32, 48_000f32);
+/// assert_eq!(resampled.len(), 5);
+/// ```
+pub fn resample<T: Float + FloatConst>(ir: &[T], from_rate: T, to_rate: T) -> Vec<T> {
+  debug_assert!(from_rate > T::zero() && to_rate > T::zero());
+  let ratio = to_rate / from_rate;
+  let length = (num::cast::<usize, T>(ir.len()).unwrap() * ratio).ceil();
+  let length: usize = num::cast(length).unwrap();
+
+  // The cutoff, relative to the input rate, and the half width of the
+  // kernel, in input samples
+  let cutoff = ratio.min(T::one());
+  let width = num::cast::<usize, T>(ZERO_CROSSINGS).unwrap() / cutoff;
+  let gain = cutoff / ratio;
+
+  (0..length)
+    .map(|m| {
+      let position = num::cast::<usize, T>(m).unwrap() / ratio;
+      let first = (position - width).ceil().max(T::zero());
+      let last = (position + width).floor().min(num::cast(ir.len() - 1).unwrap());
+      let (first, last): (usize, usize) = (num::cast(first).unwrap(), num::cast(last).unwrap());
+      (first..last + 1).fold(T::zero(), |sum, n| {
+        let distance = position - num::cast(n).unwrap();
+        sum + ir[n] * kernel(distance * cutoff, distance / width)
+      }) * gain
+    })
+    .collect()
+}
+
+/// Returns the windowed sinc at `x` zero crossings, where the window spans
+/// `u` from -1 to 1.
+fn kernel<T: Float + FloatConst>(x: T, u: T) -> T {
+  let sinc =
+    if x == T::zero() {
+      T::one()
+    }
+    else {
+      (T::pi() * x).sin() / (T::pi() * x)
+    };
+  let a0: T = num::cast(0.42f64).unwrap();
+  let a1: T = num::cast(0.5f64).unwrap();
+  let a2: T = num::cast(0.08f64).unwrap();
+  let theta = T::pi() * u;
+  sinc * (a0 + a1 * theta.cos() + a2 * (T::two() * theta).cos())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Fir;
+  use traits::FrequencyResponse;
+
+  #[test]
+  fn trim() {
+    let response = [0f64, 0f64, 0.5f64, -1f64, 0.1f64, 0.0001f64, 0f64];
+    assert_eq!(trim_silence(&response, -90f64), &[0.5f64, -1f64, 0.1f64, 0.0001f64]);
+    assert_eq!(trim_silence(&response, -20f64), &[0.5f64, -1f64, 0.1f64]);
+    assert!(trim_silence(&[0f64; 8], -60f64).is_empty());
+    assert!(trim_silence::<f64>(&[], -60f64).is_empty());
+  }
+
+  #[test]
+  fn fade() {
+    let mut response = vec![1f64; 8];
+    fade_out(&mut response, 4);
+    assert_eq!(&response[..4], &[1f64; 4]);
+    assert!(response[4..].windows(2).all(|pair| pair[1] < pair[0]));
+    assert!((response[5] - 0.5f64).abs() < 1e-12f64);
+    assert!(response[7].abs() < 1e-12f64);
+
+    // The fade is clipped to the impulse response
+    let mut response = vec![1f64; 2];
+    fade_out(&mut response, 16);
+    assert!((response[0] - 0.5f64).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn normalization() {
+    let mut response = vec![0.5f64, -0.25f64, 0.125f64];
+    assert_eq!(normalize(&mut response, Normalization::Peak), 2f64);
+    assert_eq!(response, vec![1f64, -0.5f64, 0.25f64]);
+
+    normalize(&mut response, Normalization::Energy);
+    let energy: f64 = response.iter().map(|sample| sample * sample).sum();
+    assert!((energy - 1f64).abs() < 1e-12f64);
+
+    let mut silence = vec![0f64; 4];
+    assert_eq!(normalize(&mut silence, Normalization::Peak), 1f64);
+    assert_eq!(silence, vec![0f64; 4]);
+  }
+
+  #[test]
+  fn resampling() {
+    // A decaying low-pass response keeps its gain at low frequencies
+    let response: Vec<f64> = (0..256).map(|n| 0.01f64 * 0.99f64.powi(n)).collect();
+    let original = Fir::new(&response);
+    for to_rate in [96_000f64, 44_100f64, 24_000f64].iter() {
+      let resampled = resample(&response, 48_000f64, *to_rate);
+      assert_eq!(resampled.len(), (256f64 * to_rate / 48_000f64).ceil() as usize);
+      let resampled = Fir::new(&resampled);
+      for frequency in [0f64, 100f64, 1_000f64].iter() {
+        let expected = original.magnitude_at(*frequency, 48_000f64);
+        assert!((resampled.magnitude_at(*frequency, *to_rate) / expected - 1f64).abs() < 0.02f64);
+      }
+    }
+
+    // The same rate leaves the samples unchanged
+    let same = resample(&response, 48_000f64, 48_000f64);
+    assert!(same.iter().zip(response.iter()).all(|(a, b)| (a - b).abs() < 1e-12f64));
+    assert!(resample::<f64>(&[], 44_100f64, 48_000f64).is_empty());
+  }
+}
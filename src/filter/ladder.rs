@@ -0,0 +1,309 @@
+use num;
+use num::traits::Float;
+
+use traits::{FloatConst, Processor, StateSnapshot};
+use util;
+
+/// The feedback gain at which the ladder self-oscillates.
+const OSCILLATION: f64 = 4f64;
+
+/// The highest resonance, where the ladder oscillates strongly.
+const MAX_RESONANCE: f64 = 1.25f64;
+
+/// A Moog-style four pole ladder lowpass filter.
+///
+/// Four one-pole lowpass stages are cascaded, and the output is fed back,
+/// inverted, to the input, which raises a resonant peak at the cutoff as the
+/// passband is lowered. Resonance is set from 0 to 1, where the filter is on
+/// the edge of self-oscillation, and above, up to 1.25, where it oscillates
+/// at the cutoff without any input.
+///
+/// Like `SvfTpt`, each stage is a trapezoidal integrator, following the
+/// topology-preserving transform, and the feedback is solved without a unit
+/// delay, so the cutoff and resonance are accurate up to Nyquist and can be
+/// modulated every sample. With saturation enabled, the input of every stage
+/// passes through `tanh`, like the transistors of the analog ladder, which
+/// adds warmth to loud signals and bounds the self-oscillation. Without
+/// saturation, the filter is linear, and resonance above 1 grows without
+/// bound.
+///
+/// [Based on the derivation by Vadim Zavalishin](https://www.native-instruments.com/fileadmin/ni_media/downloads/pdf/VAFilterDesign_2.1.0.pdf)
+#[derive(Clone)]
+pub struct Ladder<T> {
+  sample_rate: T,
+  resonance: T,
+  saturation: bool,
+  drive_db: T,
+  drive: T,
+  // Coefficients
+  g: T,
+  k: T,
+  // Integrator memory of each stage
+  state: [T; 4],
+  output: T
+}
+
+impl<T> Ladder<T> where T: Float + FloatConst {
+  /// Creates a new `Ladder` filter, without saturation, resonance, or
+  /// drive.
+  ///
+  /// The filter will be initialized in a state that outputs silence.
+  /// `set_coefficients()` must be called, with valid arguments, to make the
+  /// filter functional.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::Ladder;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = Ladder::new();
+  /// filter.set_coefficients(44_100f32, 800f32, 0.7f32);
+  /// filter.set_saturation(true);
+  /// filter.set_drive(6f32);
+  ///
+  /// let output = filter.process(0.5f32);
+  /// ```
+  pub fn new() -> Self {
+    Ladder {
+      sample_rate: num::one(),
+      resonance: num::zero(),
+      saturation: false,
+      drive_db: num::zero(),
+      drive: num::one(),
+      g: num::zero(),
+      k: num::zero(),
+      state: [num::zero(); 4],
+      output: num::zero()
+    }
+  }
+
+  /// Sets filter coefficients from the `sample_rate`, `cutoff_frequency`,
+  /// and `resonance`.
+  ///
+  /// The cutoff frequency is clipped to just below Nyquist, and the
+  /// resonance to between 0 and 1.25. The filter memory is not cleared, so
+  /// this may be called while processing.
+  pub fn set_coefficients(&mut self, sample_rate: T, cutoff_frequency: T, resonance: T) {
+    self.sample_rate = sample_rate;
+    self.resonance = resonance.max(T::zero()).min(num::cast(MAX_RESONANCE).unwrap());
+    self.k = self.resonance * num::cast(OSCILLATION).unwrap();
+    self.set_cutoff(cutoff_frequency);
+  }
+
+  fn set_cutoff(&mut self, cutoff_frequency: T) {
+    let nyquist: T = self.sample_rate * num::cast(0.499f64).unwrap();
+    let cutoff = cutoff_frequency.max(T::zero()).min(nyquist);
+    self.g = (T::pi() * cutoff / self.sample_rate).tan();
+  }
+
+  /// Returns the resonance.
+  pub fn get_resonance(&self) -> T {
+    self.resonance
+  }
+
+  /// Sets whether the input of every stage saturates.
+  pub fn set_saturation(&mut self, saturation: bool) {
+    self.saturation = saturation;
+  }
+
+  /// Returns `true` if the input of every stage saturates.
+  pub fn get_saturation(&self) -> bool {
+    self.saturation
+  }
+
+  /// Sets the drive, in dB, into the saturating stages.
+  ///
+  /// The input is raised by the drive and the output lowered by it, so quiet
+  /// signals pass at the same level, and loud signals saturate sooner. The
+  /// drive has no effect without saturation.
+  pub fn set_drive(&mut self, drive: T) {
+    self.drive_db = drive;
+    self.drive = util::to_sample(drive);
+  }
+
+  /// Returns the drive, in dB.
+  pub fn get_drive(&self) -> T {
+    self.drive_db
+  }
+
+  /// Processes a contiguous sequence of samples in place, using a separate
+  /// cutoff frequency for each sample.
+  ///
+  /// This is safe for audio rate modulation. `cutoffs` must be as long as
+  /// `samples`, and the last cutoff is kept after processing. Returns the
+  /// last processed sample.
+  pub fn modulate_cutoff(&mut self, samples: &mut [T], cutoffs: &[T]) -> T {
+    debug_assert_eq!(samples.len(), cutoffs.len());
+    for (sample, cutoff) in samples.iter_mut().zip(cutoffs.iter()) {
+      self.set_cutoff(*cutoff);
+      *sample = self.process(*sample);
+    }
+    self.output
+  }
+}
+
+impl<T> Processor<T> for Ladder<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    let one = T::one();
+    let gain = self.g / (one + self.g);
+
+    // The output of the linear ladder is found first, from the memory of
+    // each stage, to resolve the zero-delay feedback. With saturation, the
+    // stages run at the driven level
+    let sample = if self.saturation { sample * self.drive } else { sample };
+    let estimate = self.state.iter().fold(T::zero(), |sum, state| sum * gain + *state / (one + self.g));
+    let output = (gain.powi(4) * sample + estimate) / (one + self.k * gain.powi(4));
+
+    let mut input = sample - self.k * output;
+    for state in self.state.iter_mut() {
+      if self.saturation {
+        input = input.tanh();
+      }
+      let v = (input - *state) * gain;
+      input = v + *state;
+      *state = util::flush_denormal(input + v);
+    }
+
+    self.output = if self.saturation { input / self.drive } else { input };
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.state = [num::zero(); 4];
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> StateSnapshot for Ladder<T> where T: Float + FloatConst {
+  type State = [T; 5];
+
+  fn snapshot(&self) -> Self::State {
+    [self.state[0], self.state[1], self.state[2], self.state[3], self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.state.copy_from_slice(&state[..4]);
+    self.output = state[4];
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the steady state amplitude of a sine at `frequency`.
+  fn amplitude(filter: &mut Ladder<f64>, frequency: f64, level: f64) -> f64 {
+    filter.clear();
+    let mut peak = 0f64;
+    for n in 0..24_000 {
+      let output = filter.process(level * (2f64 * ::std::f64::consts::PI * frequency * n as f64 / 48_000f64).sin());
+      if n > 12_000 {
+        peak = peak.max(output.abs());
+      }
+    }
+    peak
+  }
+
+  #[test]
+  fn response() {
+    let mut filter = Ladder::new();
+    assert_eq!(filter.process(1f64), 0f64);
+    filter.set_coefficients(48_000f64, 1_000f64, 0f64);
+
+    // Each stage is 3 dB down at the cutoff, and the rolloff is 24 dB per
+    // octave
+    assert!((amplitude(&mut filter, 20f64, 1f64) - 1f64).abs() < 1e-3f64);
+    assert!((amplitude(&mut filter, 1_000f64, 1f64) - 0.25f64).abs() < 1e-3f64);
+    assert!(util::to_db(amplitude(&mut filter, 8_000f64, 1f64)) < -70f64);
+
+    // Resonance lowers the passband and peaks at the cutoff
+    filter.set_coefficients(48_000f64, 1_000f64, 0.9f64);
+    let passband = amplitude(&mut filter, 20f64, 1f64);
+    assert!((passband - 1f64 / 4.6f64).abs() < 1e-3f64);
+    assert!(amplitude(&mut filter, 1_000f64, 1f64) > 2f64 * passband);
+    filter.set_coefficients(48_000f64, 1_000f64, 2f64);
+    assert_eq!(filter.get_resonance(), 1.25f64);
+  }
+
+  #[test]
+  fn self_oscillation() {
+    let mut filter = Ladder::new();
+    filter.set_coefficients(48_000f64, 500f64, 1.1f64);
+    filter.set_saturation(true);
+    assert!(filter.get_saturation());
+
+    // An impulse starts an oscillation that saturation keeps bounded
+    filter.process(1f64);
+    let mut peak = 0f64;
+    for n in 0..48_000 {
+      let output = filter.process(0f64);
+      assert!(output.abs() < 10f64);
+      if n > 24_000 {
+        peak = peak.max(output.abs());
+      }
+    }
+    assert!(peak > 0.1f64);
+
+    let state = filter.snapshot();
+    let next = filter.process(0f64);
+    filter.restore(&state);
+    assert_eq!(filter.process(0f64), next);
+  }
+
+  #[test]
+  fn saturation() {
+    let mut filter = Ladder::new();
+    filter.set_coefficients(48_000f64, 1_000f64, 0f64);
+    filter.set_saturation(true);
+    filter.set_drive(12f64);
+    assert_eq!(filter.get_drive(), 12f64);
+
+    // Quiet signals pass as without saturation, while loud signals are
+    // compressed
+    assert!((amplitude(&mut filter, 20f64, 0.001f64) - 0.001f64).abs() < 1e-5f64);
+    assert!(amplitude(&mut filter, 20f64, 4f64) < 1f64);
+
+    let mut samples = vec![1f64; 64];
+    filter.modulate_cutoff(&mut samples, &vec![20_000f64; 64]);
+    assert!(samples.iter().all(|sample| sample.is_finite()));
+  }
+
+  #[test]
+  fn drive() {
+    // Drive only changes the level into the saturating stages, so the decay
+    // of an impulse and the resonant peak of quiet signals stay the same
+    let mut filter = Ladder::new();
+    filter.set_coefficients(48_000f64, 1_000f64, 0.5f64);
+    filter.set_saturation(true);
+    let mut responses = Vec::new();
+    for drive in [0f64, 12f64].iter() {
+      filter.set_drive(*drive);
+      filter.clear();
+      filter.process(0.001f64);
+      let tail = (0..48_000).fold(0f64, |_, _| filter.process(0f64));
+      assert!(tail.abs() < 1e-100f64);
+      responses.push(amplitude(&mut filter, 1_000f64, 0.001f64));
+    }
+    assert!((responses[1] / responses[0] - 1f64).abs() < 1e-3f64);
+  }
+
+  #[test]
+  #[cfg(feature = "denormal-protection")]
+  fn denormals() {
+    // The stages decay into the subnormal range after an impulse, and are
+    // flushed to zero instead
+    let mut filter = Ladder::new();
+    filter.set_coefficients(48_000f32, 1_000f32, 0.5f32);
+    filter.process(1f32);
+    for _ in 0..48_000 {
+      filter.process(0f32);
+      assert!(filter.snapshot()[..4].iter().all(|state| *state == 0f32 || state.is_normal()));
+    }
+    assert_eq!(filter.snapshot(), [0f32; 5]);
+  }
+}
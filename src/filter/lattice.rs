@@ -0,0 +1,405 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::response::evaluate;
+use traits::{FloatConst, FrequencyResponse, Processor};
+use util;
+
+/// Converts `reflection` coefficients to the polynomial, in increasing
+/// powers of `z^-1` and starting with one, of the same lattice, by the
+/// Levinson step-up recursion.
+///
+/// The polynomial is the prediction error filter of linear prediction, and
+/// the denominator of the all-pole filter that resynthesizes the signal.
+pub fn reflection_to_polynomial<T: Float>(reflection: &[T]) -> Vec<T> {
+  reflection.iter().fold(vec![T::one()], |polynomial, k| {
+    let order = polynomial.len();
+    (0..order + 1)
+      .map(|i| {
+        let forward = if i < order { polynomial[i] } else { T::zero() };
+        let backward = if i > 0 { polynomial[order - i] } else { T::zero() };
+        forward + *k * backward
+      })
+      .collect()
+  })
+}
+
+/// Converts a `polynomial`, in increasing powers of `z^-1`, to the
+/// reflection coefficients of its lattice, by the step-down recursion.
+///
+/// The polynomial is normalized by its first coefficient. Every reflection
+/// coefficient is between -1 and 1 if, and only if, the roots of the
+/// polynomial are inside the unit circle. Returns `None` if the first
+/// coefficient is zero, or a reflection coefficient is -1 or 1, where the
+/// recursion breaks down.
+pub fn polynomial_to_reflection<T: Float>(polynomial: &[T]) -> Option<Vec<T>> {
+  let a0 = *polynomial.first()?;
+  if a0 == T::zero() {
+    return None;
+  }
+  let mut polynomial: Vec<T> = polynomial.iter().map(|a| *a / a0).collect();
+  let mut reflection = vec![T::zero(); polynomial.len() - 1];
+  for m in (1..polynomial.len()).rev() {
+    let k = polynomial[m];
+    reflection[m - 1] = k;
+    let scale = T::one() - k * k;
+    if scale == T::zero() {
+      return None;
+    }
+    polynomial = (0..m).map(|i| (polynomial[i] - k * polynomial[m - i]) / scale).collect();
+  }
+  Some(reflection)
+}
+
+/// A single channel FIR filter in lattice form.
+///
+/// Each stage of the lattice has one reflection coefficient, `k`, which
+/// mixes the forward and delayed backward signals:
+///
+/// `f(m)[n] = f(m-1)[n] + k(m)*g(m-1)[n-1]; g(m)[n] = k(m)*f(m-1)[n] + g(m-1)[n-1];`
+///
+/// The output is the forward signal of the last stage, so the filter is the
+/// polynomial of `reflection_to_polynomial()`, with a first tap of one. It
+/// is the prediction error, or whitening, filter of linear prediction when
+/// the reflection coefficients come from an LPC analysis. Without stages,
+/// the input is not altered.
+#[derive(Clone)]
+pub struct LatticeFir<T> {
+  reflection: Vec<T>,
+  // The delayed backward signal of each stage
+  backward: Vec<T>,
+  output: T
+}
+
+impl<T> LatticeFir<T> where T: Float {
+  /// Creates a new `LatticeFir` with one stage for each `reflection`
+  /// coefficient.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::LatticeFir;
+  /// use rasp::traits::Processor;
+  ///
+  /// // 1 + 0.5z^-1
+  /// let mut filter = LatticeFir::new(&[0.5f32]);
+  /// assert_eq!(filter.process(1f32), 1f32);
+  /// assert_eq!(filter.process(0f32), 0.5f32);
+  /// ```
+  pub fn new(reflection: &[T]) -> Self {
+    let mut filter = LatticeFir {
+      reflection: Vec::new(),
+      backward: Vec::new(),
+      output: num::zero()
+    };
+    filter.set_reflection(reflection);
+    filter
+  }
+
+  /// Sets the reflection coefficients.
+  ///
+  /// The filter memory is kept if the number of stages does not change, and
+  /// is cleared otherwise.
+  pub fn set_reflection(&mut self, reflection: &[T]) {
+    if reflection.len() != self.reflection.len() {
+      self.backward = vec![num::zero(); reflection.len()];
+    }
+    self.reflection = reflection.to_vec();
+  }
+
+  /// Returns the reflection coefficients.
+  pub fn get_reflection(&self) -> &[T] {
+    &self.reflection
+  }
+}
+
+impl<T> Processor<T> for LatticeFir<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let mut forward = sample;
+    let mut backward = sample;
+    for (k, delayed) in self.reflection.iter().zip(self.backward.iter_mut()) {
+      let next_forward = forward + *k * *delayed;
+      let next_backward = *k * forward + *delayed;
+      *delayed = util::flush_denormal(backward);
+      forward = next_forward;
+      backward = next_backward;
+    }
+    self.output = forward;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for delayed in self.backward.iter_mut() {
+      *delayed = num::zero();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for LatticeFir<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&reflection_to_polynomial(&self.reflection), &[T::one()], frequency, sample_rate)
+  }
+}
+
+/// A single channel IIR filter in lattice-ladder form.
+///
+/// The poles are set by an all-pole lattice, run from the last stage to the
+/// first, with one reflection coefficient for each stage:
+///
+/// `f(m-1)[n] = f(m)[n] - k(m)*g(m-1)[n-1]; g(m)[n] = k(m)*f(m-1)[n] + g(m-1)[n-1];`
+///
+/// The zeros are set by the ladder, whose taps weight the backward signal of
+/// every stage into the output. With a single ladder tap of one, the filter
+/// is the all-pole synthesis filter of linear prediction. The filter is
+/// stable whenever every reflection coefficient is between -1 and 1, and
+/// the structure is far less sensitive to rounding than a direct form of
+/// the same order.
+#[derive(Clone)]
+pub struct LatticeLadder<T> {
+  reflection: Vec<T>,
+  ladder: Vec<T>,
+  // The delayed backward signal of each stage below the last
+  backward: Vec<T>,
+  output: T
+}
+
+impl<T> LatticeLadder<T> where T: Float {
+  /// Creates a new `LatticeLadder` with one stage for each `reflection`
+  /// coefficient, and the `ladder` taps, one more than the stages.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::LatticeLadder;
+  /// use rasp::traits::Processor;
+  ///
+  /// // 1 / (1 - 0.5z^-1)
+  /// let mut filter = LatticeLadder::new(&[-0.5f32], &[1f32, 0f32]);
+  /// assert_eq!(filter.process(1f32), 1f32);
+  /// assert_eq!(filter.process(0f32), 0.5f32);
+  /// ```
+  pub fn new(reflection: &[T], ladder: &[T]) -> Self {
+    let mut filter = LatticeLadder {
+      reflection: Vec::new(),
+      ladder: Vec::new(),
+      backward: Vec::new(),
+      output: num::zero()
+    };
+    filter.set_coefficients(reflection, ladder);
+    filter
+  }
+
+  /// Creates a new `LatticeLadder` with the transfer function of numerator
+  /// `b` and denominator `a`, in increasing powers of `z^-1`.
+  ///
+  /// The numerator must not be longer than the denominator. Returns `None` if
+  /// the denominator cannot be converted to reflection coefficients.
+  pub fn from_coefficients(b: &[T], a: &[T]) -> Option<Self> {
+    if b.len() > a.len() {
+      return None;
+    }
+    let reflection = polynomial_to_reflection(a)?;
+    let a0 = a[0];
+
+    // The ladder taps weight the backward polynomial of each stage, solved
+    // from the highest power down
+    let mut numerator: Vec<T> = (0..a.len()).map(|i| b.get(i).map(|c| *c / a0).unwrap_or_else(T::zero)).collect();
+    let mut ladder = vec![T::zero(); a.len()];
+    for m in (0..a.len()).rev() {
+      let backward = reflection_to_polynomial(&reflection[..m]);
+      let tap = numerator[m];
+      for (i, coefficient) in backward.iter().enumerate() {
+        numerator[m - i] = numerator[m - i] - tap * *coefficient;
+      }
+      ladder[m] = tap;
+    }
+    Some(LatticeLadder::new(&reflection, &ladder))
+  }
+
+  /// Sets the `reflection` coefficients and `ladder` taps, one more than the
+  /// reflection coefficients.
+  ///
+  /// The filter memory is kept if the number of stages does not change, and
+  /// is cleared otherwise.
+  pub fn set_coefficients(&mut self, reflection: &[T], ladder: &[T]) {
+    debug_assert!(ladder.len() == reflection.len() + 1);
+    if reflection.len() != self.reflection.len() {
+      self.backward = vec![num::zero(); reflection.len()];
+    }
+    self.reflection = reflection.to_vec();
+    self.ladder = ladder.to_vec();
+  }
+
+  /// Sets the reflection coefficients, keeping the ladder taps.
+  ///
+  /// There must be as many coefficients as stages.
+  pub fn set_reflection(&mut self, reflection: &[T]) {
+    debug_assert!(reflection.len() == self.reflection.len());
+    self.reflection.copy_from_slice(reflection);
+  }
+
+  /// Returns the reflection coefficients.
+  pub fn get_reflection(&self) -> &[T] {
+    &self.reflection
+  }
+
+  /// Returns the ladder taps.
+  pub fn get_ladder(&self) -> &[T] {
+    &self.ladder
+  }
+
+  /// Returns the numerator and denominator of the transfer function, in
+  /// increasing powers of `z^-1`.
+  pub fn coefficients(&self) -> (Vec<T>, Vec<T>) {
+    let mut numerator = vec![T::zero(); self.ladder.len()];
+    for (m, tap) in self.ladder.iter().enumerate() {
+      let backward = reflection_to_polynomial(&self.reflection[..m]);
+      for (i, coefficient) in backward.iter().enumerate() {
+        numerator[m - i] = numerator[m - i] + *tap * *coefficient;
+      }
+    }
+    (numerator, reflection_to_polynomial(&self.reflection))
+  }
+}
+
+impl<T> Processor<T> for LatticeLadder<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let stages = self.reflection.len();
+    let mut forward = sample;
+    let mut output = T::zero();
+    for m in (1..stages + 1).rev() {
+      let k = self.reflection[m - 1];
+      forward = forward - k * self.backward[m - 1];
+      let backward = k * forward + self.backward[m - 1];
+      output = output + self.ladder[m] * backward;
+      // The delayed signal of this stage has been used by the stage above
+      if m < stages {
+        self.backward[m] = util::flush_denormal(backward);
+      }
+    }
+    if stages > 0 {
+      self.backward[0] = util::flush_denormal(forward);
+    }
+    self.output = output + self.ladder[0] * forward;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for delayed in self.backward.iter_mut() {
+      *delayed = num::zero();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for LatticeLadder<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    let (numerator, denominator) = self.coefficients();
+    evaluate(&numerator, &denominator, frequency, sample_rate)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Fir;
+  use filter::design::{elliptic, Band};
+
+  #[test]
+  fn conversions() {
+    let reflection = [0.5f64, -0.3f64, 0.2f64];
+    let polynomial = reflection_to_polynomial(&reflection);
+    assert_eq!(polynomial.len(), 4);
+    assert_eq!(polynomial[0], 1f64);
+    let recovered = polynomial_to_reflection(&polynomial).unwrap();
+    assert!(recovered.iter().zip(reflection.iter()).all(|(a, b)| (a - b).abs() < 1e-12f64));
+
+    // Scaled polynomials are normalized, and the recursion can break down
+    let scaled: Vec<f64> = polynomial.iter().map(|a| a * 3f64).collect();
+    assert!((polynomial_to_reflection(&scaled).unwrap()[2] - 0.2f64).abs() < 1e-12f64);
+    assert_eq!(polynomial_to_reflection(&[1f64, 0f64, 1f64]), None);
+    assert_eq!(polynomial_to_reflection(&[0f64, 1f64]), None);
+    assert_eq!(polynomial_to_reflection::<f64>(&[1f64]), Some(vec![]));
+  }
+
+  #[test]
+  fn lattice_fir() {
+    let reflection = [0.5f64, -0.3f64, 0.2f64];
+    let mut lattice = LatticeFir::new(&reflection);
+    let mut direct = Fir::new(&reflection_to_polynomial(&reflection));
+    for n in 0..32 {
+      let sample = (n as f64 * 0.7f64).sin();
+      assert!((lattice.process(sample) - direct.process(sample)).abs() < 1e-12f64);
+    }
+    assert!((lattice.magnitude_at(1_000f64, 8_000f64) - direct.magnitude_at(1_000f64, 8_000f64)).abs() < 1e-12f64);
+    assert_eq!(lattice.get_reflection(), &reflection);
+
+    lattice.clear();
+    lattice.set_reflection(&[]);
+    assert_eq!(lattice.process(0.25f64), 0.25f64);
+  }
+
+  #[test]
+  fn lattice_ladder() {
+    // A high order elliptic filter, expanded into one polynomial
+    let (cascade, _) = elliptic(Band::LowPass, 8, 48_000f64, 6_000f64, 0.5f64, 60f64).unwrap();
+    let multiply = |p: &[f64], q: &[f64]| {
+      let mut product = vec![0f64; p.len() + q.len() - 1];
+      for (i, x) in p.iter().enumerate() {
+        for (j, y) in q.iter().enumerate() {
+          product[i + j] += x * y;
+        }
+      }
+      product
+    };
+    let (mut b, mut a) = (vec![cascade.get_gain()], vec![1f64]);
+    for c in cascade.coefficients() {
+      b = multiply(&b, &[c.b0, c.b1, c.b2]);
+      a = multiply(&a, &[1f64, c.a1, c.a2]);
+    }
+
+    let mut lattice = LatticeLadder::from_coefficients(&b, &a).unwrap();
+    assert!(lattice.get_reflection().iter().all(|k| k.abs() < 1f64));
+    assert_eq!(lattice.get_ladder().len(), 9);
+    let (numerator, denominator) = lattice.coefficients();
+    assert!(numerator.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1e-12f64));
+    assert!(denominator.iter().zip(a.iter()).all(|(x, y)| (x - y).abs() < 1e-9f64));
+
+    let mut cascade = cascade;
+    for n in 0..512 {
+      let sample = if n == 0 { 1f64 } else { 0f64 };
+      let expected = cascade.process(sample);
+      assert!((lattice.process(sample) - expected).abs() < 1e-9f64);
+    }
+    for frequency in [100f64, 1_000f64, 5_000f64].iter() {
+      let expected = cascade.magnitude_at(*frequency, 48_000f64);
+      assert!((lattice.magnitude_at(*frequency, 48_000f64) - expected).abs() < 1e-6f64);
+    }
+    assert_eq!(LatticeLadder::from_coefficients(&[1f64, 1f64], &[1f64]).map(|_| ()), None);
+  }
+
+  #[test]
+  fn synthesis_inverts_analysis() {
+    // The all-pole lattice undoes the prediction error filter
+    let reflection = [0.9f64, -0.6f64, 0.3f64, -0.1f64];
+    let mut analysis = LatticeFir::new(&reflection);
+    let mut synthesis = LatticeLadder::new(&reflection, &[1f64, 0f64, 0f64, 0f64, 0f64]);
+    for n in 0..64 {
+      let sample = (n as f64 * 0.37f64).cos();
+      assert!((synthesis.process(analysis.process(sample)) - sample).abs() < 1e-12f64);
+    }
+    synthesis.set_reflection(&[0f64; 4]);
+    synthesis.clear();
+    assert_eq!((synthesis.process(0.5f64), synthesis.last_out()), (0.5f64, 0.5f64));
+  }
+}
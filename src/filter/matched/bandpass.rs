@@ -0,0 +1,64 @@
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use traits::{FloatConst, Processor};
+
+use super::design;
+
+/// A band-pass biquad filter matched to its analog prototype.
+///
+/// This filter has a constant peak gain at 0db, like `rbj::BandPass2`.
+pub struct BandPass<T> {
+  biquad: Biquad2<T>
+}
+
+impl<T> BandPass<T> where T: Float + FloatConst {
+  /// Creates a new `BandPass` biquad filter.
+  pub fn new() -> Self {
+    BandPass {
+      biquad: Biquad2::<T>::new()
+    }
+  }
+
+  /// Set filter coefficients.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `center_frequency`, and `q` factor. These values are not validated.
+  pub fn set_coefficients(&mut self,
+                          sample_rate: T,
+                          center_frequency: T,
+                          q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, center_frequency, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      center_frequency: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
+  {
+    let zero = T::zero();
+    let one = T::one();
+    design(sample_rate, center_frequency, &[zero, one / q, zero], &[one, one / q, one])
+  }
+}
+
+impl<T> Processor<T> for BandPass<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.biquad.process(sample)
+  }
+
+  fn clear(&mut self) {
+    self.biquad.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.biquad.last_out()
+  }
+}
@@ -0,0 +1,62 @@
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use traits::{FloatConst, Processor};
+
+use super::design;
+
+/// A high-pass biquad filter matched to its analog prototype.
+pub struct HighPass<T> {
+  biquad: Biquad2<T>
+}
+
+impl<T> HighPass<T> where T: Float + FloatConst {
+  /// Creates a new `HighPass` biquad filter.
+  pub fn new() -> Self {
+    HighPass {
+      biquad: Biquad2::<T>::new()
+    }
+  }
+
+  /// Set filter coefficients.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `cutoff_frequency`, and `q` factor. These values are not validated.
+  pub fn set_coefficients(&mut self,
+                          sample_rate: T,
+                          cutoff_frequency: T,
+                          q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, cutoff_frequency, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      cutoff_frequency: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
+  {
+    let zero = T::zero();
+    let one = T::one();
+    design(sample_rate, cutoff_frequency, &[zero, zero, one], &[one, one / q, one])
+  }
+}
+
+impl<T> Processor<T> for HighPass<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.biquad.process(sample)
+  }
+
+  fn clear(&mut self) {
+    self.biquad.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.biquad.last_out()
+  }
+}
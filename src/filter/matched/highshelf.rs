@@ -0,0 +1,69 @@
+use num;
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use traits::{FloatConst, Processor};
+
+use super::{design, shelf_q};
+
+/// A high-shelf biquad filter matched to its analog prototype.
+pub struct HighShelf<T> {
+  biquad: Biquad2<T>
+}
+
+impl<T> HighShelf<T> where T: Float + FloatConst {
+  /// Creates a new `HighShelf` biquad filter.
+  pub fn new() -> Self {
+    HighShelf {
+      biquad: Biquad2::<T>::new()
+    }
+  }
+
+  /// Set filter coefficients.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `cutoff_frequency`, `db_gain`, and `shelf_slope` factor. These values are
+  /// not validated.
+  pub fn set_coefficients(&mut self,
+                          sample_rate: T,
+                          cutoff_frequency: T,
+                          db_gain: T,
+                          shelf_slope: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, cutoff_frequency, db_gain, shelf_slope);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      cutoff_frequency: T,
+                      db_gain: T,
+                      shelf_slope: T)
+                      -> BiquadCoefficients<T>
+  {
+    let ten: T = num::cast(10f64).unwrap();
+    let forty: T = num::cast(40f64).unwrap();
+    let a = ten.powf(db_gain / forty);
+    let one = T::one();
+    let b = a.sqrt() / shelf_q(a, shelf_slope);
+    design(sample_rate, cutoff_frequency, &[a, a * b, a * a], &[a, b, one])
+  }
+}
+
+impl<T> Processor<T> for HighShelf<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.biquad.process(sample)
+  }
+
+  fn clear(&mut self) {
+    self.biquad.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.biquad.last_out()
+  }
+}
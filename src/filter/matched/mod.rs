@@ -0,0 +1,183 @@
+//! A set of biquad filters matched to their analog prototypes, after
+//! [Martin Vicanek's "Matched Second Order Digital Filters"](https://vicanek.de/articles/BiquadFits.pdf).
+//!
+//! The bilinear transform used by the cookbook filters in `rbj` compresses
+//! the whole analog frequency axis below Nyquist, so responses cramp as they
+//! approach it: a peak at 15 kHz at 44.1 kHz is much narrower than asked
+//! for, and a low-pass filter falls to zero at Nyquist. These filters place
+//! their poles by the impulse invariant transform instead, then choose zeros
+//! that match the magnitude of the analog prototype at DC, at the cutoff or
+//! center frequency, and at Nyquist, so the response stays close to the
+//! analog one up to Nyquist. The high-pass filter keeps both of its zeros at
+//! DC, matching the magnitude at the cutoff alone.
+//!
+//! Each filter has the same parameters as its cookbook counterpart.
+
+mod bandpass;
+mod highpass;
+mod highshelf;
+mod lowpass;
+mod lowshelf;
+mod peak;
+
+pub use self::bandpass::BandPass   as BandPass;
+pub use self::highpass::HighPass   as HighPass;
+pub use self::highshelf::HighShelf as HighShelf;
+pub use self::lowpass::LowPass     as LowPass;
+pub use self::lowshelf::LowShelf   as LowShelf;
+pub use self::peak::Peak           as Peak;
+
+use num::traits::Float;
+
+use filter::BiquadCoefficients;
+use traits::FloatConst;
+
+/// Returns the squared magnitude of the polynomial in `s` with
+/// `coefficients` in increasing powers, at `s = j * w`.
+fn magnitude_squared<T: Float>(coefficients: &[T; 3], w: T) -> T {
+  let re = coefficients[0] - coefficients[2] * w * w;
+  let im = coefficients[1] * w;
+  re * re + im * im
+}
+
+/// Computes the coefficients of a biquad matched to the analog prototype
+/// with `numerator` and `denominator` coefficients in increasing powers of
+/// `s`, normalized to a frequency of one, which is placed at `frequency`.
+fn design<T>(sample_rate: T, frequency: T, numerator: &[T; 3], denominator: &[T; 3]) -> BiquadCoefficients<T>
+  where T: Float + FloatConst
+{
+  let one = T::one();
+  let two = T::two();
+  let four = two * two;
+  let w0 = two * T::pi() * frequency / sample_rate;
+
+  // Poles by the impulse invariant transform, z = e^(s * T)
+  let natural = (denominator[0] / denominator[2]).sqrt() * w0;
+  let damping = denominator[1] / (two * (denominator[0] * denominator[2]).sqrt());
+  let decay = (-damping * natural).exp();
+  let a1 =
+    if damping < one {
+      -two * decay * (natural * (one - damping * damping).sqrt()).cos()
+    }
+    else {
+      -two * decay * (natural * (damping * damping - one).sqrt()).cosh()
+    };
+  let a2 = decay * decay;
+
+  // Squared magnitudes are linear in these terms of the coefficients, with
+  // the poles giving the denominator
+  let a_0 = (one + a1 + a2) * (one + a1 + a2);
+  let a_1 = (one - a1 + a2) * (one - a1 + a2);
+  let a_2 = -four * a2;
+  let phi_1 = (w0 / two).sin().powi(2);
+  let phi_0 = one - phi_1;
+  let phi_2 = four * phi_0 * phi_1;
+
+  let gain = |w: T| magnitude_squared(numerator, w) / magnitude_squared(denominator, w);
+  let poles_at_w0 = a_0 * phi_0 + a_1 * phi_1 + a_2 * phi_2;
+  let zero = T::zero();
+  let (b0, b1, b2) =
+    if numerator[0] == zero && numerator[1] == zero {
+      // A high-pass keeps both zeros at DC, for its rolloff, and matches the
+      // gain at `w0` alone
+      let b0 = (gain(one) * poles_at_w0).sqrt() / (four * phi_1);
+      (b0, -two * b0, b0)
+    }
+    else {
+      // Otherwise the gains at DC, `w0`, and Nyquist are matched, and the
+      // squared magnitude is factored into minimum-phase zeros
+      let b_0 = gain(zero) * a_0;
+      let b_1 = gain(T::pi() / w0) * a_1;
+      let b_2 = (gain(one) * poles_at_w0 - b_0 * phi_0 - b_1 * phi_1) / phi_2;
+      let w = (b_0.sqrt() + b_1.sqrt()) / two;
+      let b0 = (w + (w * w + b_2).max(zero).sqrt()) / two;
+      let b2 = if b0 > zero { -b_2 / (four * b0) } else { zero };
+      (b0, (b_0.sqrt() - b_1.sqrt()) / two, b2)
+    };
+
+  BiquadCoefficients { b0, b1, b2, a1, a2 }
+}
+
+/// Returns the Q factor of a shelf with `shelf_slope` and a gain of `a`, the
+/// square root of its linear gain.
+fn shelf_q<T: Float>(a: T, shelf_slope: T) -> T {
+  let one = T::one();
+  one / ((a + one / a) * (one / shelf_slope - one) + one + one).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::rbj;
+  use traits::FrequencyResponse;
+  use util;
+
+  const SAMPLE_RATE: f64 = 48_000f64;
+
+  /// Returns the largest difference, in dB, between `coefficients` and the
+  /// analog prototype with `numerator` and `denominator` centered at
+  /// `frequency`, from 20 Hz up to Nyquist, wherever the prototype is above
+  /// -20 dB.
+  fn deviation(coefficients: BiquadCoefficients<f64>,
+               frequency: f64,
+               numerator: &[f64; 3],
+               denominator: &[f64; 3]) -> f64 {
+    (1..=1_200).fold(0f64, |deviation, n| {
+      let f = 20f64 * n as f64;
+      let w = f / frequency;
+      let analog = (magnitude_squared(numerator, w) / magnitude_squared(denominator, w)).sqrt();
+      let digital = coefficients.magnitude_at(f, SAMPLE_RATE);
+      if util::to_db(analog) < -20f64 { return deviation; }
+      deviation.max((util::to_db(digital) - util::to_db(analog)).abs())
+    })
+  }
+
+  #[test]
+  fn peak() {
+    // A 12 dB peak at 15 kHz, with a Q of 2
+    let a = 10f64.powf(12f64 / 40f64);
+    let (numerator, denominator) = ([1f64, a / 2f64, 1f64], [1f64, 1f64 / (a * 2f64), 1f64]);
+    let matched = Peak::coefficients(SAMPLE_RATE, 15_000f64, 12f64, 2f64);
+    assert!((util::to_db(matched.magnitude_at(15_000f64, SAMPLE_RATE)) - 12f64).abs() < 1e-9f64);
+    assert!(deviation(matched, 15_000f64, &numerator, &denominator) < 1f64);
+
+    // The cookbook peak cramps
+    let cramped = rbj::Peak::coefficients(SAMPLE_RATE, 15_000f64, 12f64, 2f64);
+    assert!(deviation(cramped, 15_000f64, &numerator, &denominator) > 3f64);
+  }
+
+  #[test]
+  fn lowpass_highpass() {
+    let q = 0.5f64.sqrt();
+    let denominator = [1f64, 1f64 / q, 1f64];
+    let lowpass = LowPass::coefficients(SAMPLE_RATE, 16_000f64, q);
+    assert!(deviation(lowpass, 16_000f64, &[1f64, 0f64, 0f64], &denominator) < 1f64);
+    assert!((lowpass.magnitude_at(0f64, SAMPLE_RATE) - 1f64).abs() < 1e-9f64);
+    // Rather than falling to zero at Nyquist, as the cookbook low-pass does
+    let nyquist = 1f64 / (1f64 + 1.5f64.powi(4)).sqrt();
+    assert!((lowpass.magnitude_at(24_000f64, SAMPLE_RATE) - nyquist).abs() < 1e-9f64);
+
+    let highpass = HighPass::coefficients(SAMPLE_RATE, 1_000f64, q);
+    assert!(deviation(highpass, 1_000f64, &[0f64, 0f64, 1f64], &denominator) < 0.5f64);
+    assert!(highpass.magnitude_at(0f64, SAMPLE_RATE) < 1e-9f64);
+
+    let bandpass = BandPass::coefficients(SAMPLE_RATE, 12_000f64, 1f64);
+    assert!(deviation(bandpass, 12_000f64, &[0f64, 1f64, 0f64], &[1f64, 1f64, 1f64]) < 1.5f64);
+    assert!((bandpass.magnitude_at(12_000f64, SAMPLE_RATE) - 1f64).abs() < 1e-9f64);
+  }
+
+  #[test]
+  fn shelves() {
+    let a = 10f64.powf(-9f64 / 40f64);
+    let b = a.sqrt() / shelf_q(a, 1f64);
+    let low = LowShelf::coefficients(SAMPLE_RATE, 10_000f64, -9f64, 1f64);
+    assert!(deviation(low, 10_000f64, &[a * a, a * b, a], &[1f64, b, a]) < 0.5f64);
+    assert!((util::to_db(low.magnitude_at(0f64, SAMPLE_RATE)) + 9f64).abs() < 1e-9f64);
+
+    let high = HighShelf::coefficients(SAMPLE_RATE, 10_000f64, -9f64, 1f64);
+    assert!(deviation(high, 10_000f64, &[a, a * b, a * a], &[a, b, 1f64]) < 0.5f64);
+
+    // Without gain, a slope of one is a Butterworth Q
+    assert!((shelf_q(1f64, 1f64) - 0.5f64.sqrt()).abs() < 1e-12f64);
+  }
+}
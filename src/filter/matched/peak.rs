@@ -0,0 +1,68 @@
+use num;
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use traits::{FloatConst, Processor};
+
+use super::design;
+
+/// A peaking biquad filter matched to its analog prototype.
+pub struct Peak<T> {
+  biquad: Biquad2<T>
+}
+
+impl<T> Peak<T> where T: Float + FloatConst {
+  /// Creates a new `Peak` biquad filter.
+  pub fn new() -> Self {
+    Peak {
+      biquad: Biquad2::<T>::new()
+    }
+  }
+
+  /// Set filter coefficients.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `center_frequency`, `db_gain`, and `q` factor. These values are not
+  /// validated.
+  pub fn set_coefficients(&mut self,
+                          sample_rate: T,
+                          center_frequency: T,
+                          db_gain: T,
+                          q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, center_frequency, db_gain, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      center_frequency: T,
+                      db_gain: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
+  {
+    let ten: T = num::cast(10f64).unwrap();
+    let forty: T = num::cast(40f64).unwrap();
+    let a = ten.powf(db_gain / forty);
+    let one = T::one();
+    design(sample_rate, center_frequency, &[one, a / q, one], &[one, one / (a * q), one])
+  }
+}
+
+impl<T> Processor<T> for Peak<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.biquad.process(sample)
+  }
+
+  fn clear(&mut self) {
+    self.biquad.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.biquad.last_out()
+  }
+}
@@ -0,0 +1,348 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+
+use num;
+use num::traits::Float;
+
+use traits::Processor;
+
+/// A sample in the window of a `MedianFilter`, ordered by value, then by
+/// arrival so equal values remain distinct. NaN is ordered above every other
+/// value, so the order stays total and every entry can be found again.
+#[derive(Clone, Copy)]
+struct Entry<T> {
+  value: T,
+  sequence: usize
+}
+
+impl<T: Float> PartialEq for Entry<T> {
+  fn eq(&self, other: &Self) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl<T: Float> Eq for Entry<T> {}
+
+impl<T: Float> PartialOrd for Entry<T> {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl<T: Float> Ord for Entry<T> {
+  fn cmp(&self, other: &Self) -> Ordering {
+    let value = match (self.value.is_nan(), other.value.is_nan()) {
+      (true, true) => Ordering::Equal,
+      (true, false) => Ordering::Greater,
+      (false, true) => Ordering::Less,
+      (false, false) => self.value.partial_cmp(&other.value).unwrap()
+    };
+    value.then(self.sequence.cmp(&other.sequence))
+  }
+}
+
+/// A streaming median, or percentile, filter.
+///
+/// The output is the median of the last `length` samples, which removes
+/// spikes shorter than half the window without smearing them into their
+/// neighbors like a linear filter does. Any other percentile can be selected
+/// instead, such as the 0th for a running minimum.
+///
+/// The window is split between two ordered sets, the samples up to the
+/// selected rank and the samples above it, so each sample is added and
+/// removed in `O(log n)` time. The window starts filled with zeros. A NaN
+/// sample ranks above every other sample until it leaves the window.
+pub struct MedianFilter<T> {
+  percentile: T,
+  rank: usize,
+  history: Vec<Entry<T>>,
+  write_ptr: usize,
+  sequence: usize,
+  // Samples up to and including the selected rank
+  lower: BTreeSet<Entry<T>>,
+  upper: BTreeSet<Entry<T>>,
+  output: T
+}
+
+impl<T> MedianFilter<T> where T: Float {
+  /// Creates a new `MedianFilter` with a window of `length` samples.
+  ///
+  /// `length` must be odd, so the median is a sample of the window. It can
+  /// be changed later with `set_length()`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::MedianFilter;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut samples = vec![1f32, 1f32, 9f32, 1f32, 1f32];
+  /// let mut filter = MedianFilter::new(3);
+  /// filter.process_block(&mut samples);
+  /// assert_eq!(samples, vec![0f32, 1f32, 1f32, 1f32, 1f32]);
+  /// ```
+  pub fn new(length: usize) -> Self {
+    debug_assert!(length % 2 == 1);
+    let mut filter = MedianFilter {
+      percentile: num::cast(50f64).unwrap(),
+      rank: length / 2,
+      history: Vec::with_capacity(length),
+      write_ptr: 0,
+      sequence: 0,
+      lower: BTreeSet::new(),
+      upper: BTreeSet::new(),
+      output: T::zero()
+    };
+    for _ in 0..length {
+      let entry = filter.next_entry(T::zero());
+      filter.history.push(entry);
+      filter.upper.insert(entry);
+    }
+    filter.balance();
+    filter
+  }
+
+  /// Returns the length of the window, in samples.
+  pub fn len(&self) -> usize {
+    self.history.len()
+  }
+
+  /// Returns `true` if the window holds no samples.
+  pub fn is_empty(&self) -> bool {
+    self.history.is_empty()
+  }
+
+  /// Sets the length of the window, in samples, keeping the most recent
+  /// samples, and filling the window with zeros before them when it grows.
+  ///
+  /// `length` must be odd, else the window is not updated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::MedianFilter;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = MedianFilter::new(5);
+  /// for sample in [4f32, 1f32, 3f32].iter() {
+  ///   filter.process(*sample);
+  /// }
+  /// assert_eq!(filter.last_out(), 1f32);
+  ///
+  /// filter.set_length(3);
+  /// assert_eq!(filter.last_out(), 3f32);
+  /// ```
+  pub fn set_length(&mut self, length: usize) {
+    if length % 2 != 1 {
+      return;
+    }
+    let current = self.history.len();
+    let values: Vec<T> = (0..length)
+      .map(|n| if n + current >= length {
+        self.history[(self.write_ptr + n + current - length) % current].value
+      }
+      else {
+        T::zero()
+      })
+      .collect();
+
+    self.lower.clear();
+    self.upper.clear();
+    self.history.clear();
+    for value in values {
+      let entry = self.next_entry(value);
+      self.history.push(entry);
+      self.upper.insert(entry);
+    }
+    self.write_ptr = 0;
+    let percentile = self.percentile;
+    self.rank = 0;
+    self.balance();
+    self.set_percentile(percentile);
+  }
+
+  /// Sets the percentile of the window to output, from 0 for the minimum to
+  /// 100 for the maximum.
+  ///
+  /// The percentile is rounded to the nearest sample of the window.
+  /// `percentile` must be within 0 and 100, else it is not updated.
+  pub fn set_percentile(&mut self, percentile: T) {
+    let hundred: T = num::cast(100f64).unwrap();
+    if percentile >= T::zero() && percentile <= hundred {
+      let last: T = num::cast(self.len() - 1).unwrap();
+      self.percentile = percentile;
+      self.rank = num::cast((percentile / hundred * last).round()).unwrap();
+      self.balance();
+    }
+  }
+
+  /// Returns the percentile of the window to output.
+  pub fn get_percentile(&self) -> T {
+    self.percentile
+  }
+
+  fn next_entry(&mut self, value: T) -> Entry<T> {
+    let entry = Entry { value, sequence: self.sequence };
+    self.sequence = self.sequence.wrapping_add(1);
+    entry
+  }
+
+  /// Moves samples between the sets until the lower set ends at the rank.
+  fn balance(&mut self) {
+    while self.lower.len() > self.rank + 1 {
+      let largest = *self.lower.iter().next_back().unwrap();
+      self.lower.remove(&largest);
+      self.upper.insert(largest);
+    }
+    while self.lower.len() < self.rank + 1 {
+      let smallest = *self.upper.iter().next().unwrap();
+      self.upper.remove(&smallest);
+      self.lower.insert(smallest);
+    }
+    self.output = self.lower.iter().next_back().unwrap().value;
+  }
+}
+
+impl<T> Processor<T> for MedianFilter<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let entry = self.next_entry(sample);
+    let expired = ::std::mem::replace(&mut self.history[self.write_ptr], entry);
+    self.write_ptr = (self.write_ptr + 1) % self.history.len();
+
+    if !self.lower.remove(&expired) {
+      self.upper.remove(&expired);
+    }
+    match self.lower.iter().next_back() {
+      Some(largest) if entry <= *largest => self.lower.insert(entry),
+      _ => self.upper.insert(entry)
+    };
+    self.balance();
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.lower.clear();
+    self.upper.clear();
+    for index in 0..self.history.len() {
+      let entry = self.next_entry(T::zero());
+      self.history[index] = entry;
+      self.upper.insert(entry);
+    }
+    self.write_ptr = 0;
+    self.balance();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::Processor;
+
+  /// Returns the percentile of `window` by sorting it.
+  fn sorted_rank(window: &[f32], rank: usize) -> f32 {
+    let mut sorted = window.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted[rank]
+  }
+
+  #[test]
+  fn matches_sorting() {
+    let length = 7;
+    // A deterministic sequence with repeated values
+    let input: Vec<f32> = (0..500).map(|n| ((n * 37 % 101) as f32 / 10f32).floor()).collect();
+    let mut filter = MedianFilter::new(length);
+    let mut window = vec![0f32; length];
+    for sample in input.iter() {
+      window.remove(0);
+      window.push(*sample);
+      assert_eq!(filter.process(*sample), sorted_rank(&window, length / 2));
+    }
+  }
+
+  #[test]
+  fn percentiles() {
+    let mut filter = MedianFilter::new(5);
+    for sample in [3f32, 1f32, 4f32, 1f32, 5f32].iter() {
+      filter.process(*sample);
+    }
+    assert_eq!(filter.last_out(), 3f32);
+
+    filter.set_percentile(0f32);
+    assert_eq!(filter.last_out(), 1f32);
+    filter.set_percentile(100f32);
+    assert_eq!(filter.last_out(), 5f32);
+    filter.set_percentile(75f32);
+    assert_eq!(filter.last_out(), 4f32);
+
+    filter.set_percentile(101f32);
+    assert_eq!(filter.get_percentile(), 75f32);
+  }
+
+  #[test]
+  fn length() {
+    let input: Vec<f32> = (0..200).map(|n| ((n * 53 % 97) as f32 / 10f32).floor()).collect();
+    let mut filter = MedianFilter::new(3);
+    filter.set_percentile(25f32);
+    let mut window = vec![0f32; 3];
+    for (n, sample) in input.iter().enumerate() {
+      // The window shrinks and grows, keeping the recent samples
+      let length = match n {
+        50 => 9,
+        120 => 5,
+        _ => window.len()
+      };
+      if length != window.len() {
+        filter.set_length(length);
+        while window.len() < length {
+          window.insert(0, 0f32);
+        }
+        let excess = window.len() - length;
+        window.drain(..excess);
+      }
+      window.remove(0);
+      window.push(*sample);
+      let rank = (0.25f32 * (length - 1) as f32).round() as usize;
+      assert_eq!(filter.process(*sample), sorted_rank(&window, rank));
+    }
+
+    filter.set_length(4);
+    assert_eq!((filter.len(), filter.get_percentile()), (5, 25f32));
+  }
+
+  #[test]
+  fn nan() {
+    let mut filter = MedianFilter::new(5);
+    for sample in [1f32, 2f32, ::std::f32::NAN, 3f32, 4f32].iter() {
+      filter.process(*sample);
+    }
+    assert_eq!(filter.last_out(), 3f32);
+
+    // Once the NaN has left the window, the filter tracks the input again
+    for n in 0..50 {
+      let sample = (n * 7 % 11) as f32;
+      filter.process(sample);
+    }
+    let window: Vec<f32> = (45..50).map(|n| (n * 7 % 11) as f32).collect();
+    assert_eq!(filter.last_out(), sorted_rank(&window, 2));
+    assert_eq!(filter.lower.len() + filter.upper.len(), 5);
+  }
+
+  #[test]
+  fn despikes() {
+    let mut samples = vec![0.5f32; 64];
+    samples[20] = 10f32;
+    samples[40] = -10f32;
+    samples[41] = -10f32;
+    let mut filter = MedianFilter::new(5);
+    filter.process_block(&mut samples);
+    assert!(samples[4..].iter().all(|sample| *sample == 0.5f32));
+
+    filter.clear();
+    assert_eq!(filter.last_out(), 0f32);
+    assert_eq!(filter.len(), 5);
+  }
+}
@@ -0,0 +1,134 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use traits::FloatConst;
+
+/// How many times longer than the impulse response the spectrum is, to keep
+/// the cepstrum from aliasing.
+const OVERSAMPLING: usize = 8;
+
+/// The level, relative to the peak of the magnitude response, below which
+/// the magnitude is floored before taking its log.
+const FLOOR: f64 = 1e-10f64;
+
+/// Converts an `impulse_response`, such as a linear phase or mixed phase
+/// FIR, to the minimum phase FIR of the same length with the same magnitude
+/// response.
+///
+/// The minimum phase filter concentrates its energy at the start, so it
+/// trades the latency and pre-ringing of a linear phase filter for a phase
+/// response that varies with frequency, which suits corrections like those
+/// of `SpectrumMatch`, or impulse responses prepared for convolution, where
+/// leading silence and pre-echo are unwanted.
+///
+/// The conversion uses the cepstral method: the real cepstrum of the
+/// magnitude response is folded onto positive time, so the log spectrum it
+/// returns to has all its zeros inside the unit circle. Zeros on the unit
+/// circle are floored 200 dB below the peak, and the result is truncated to
+/// the original length, so the magnitude match is close, rather than exact,
+/// for responses with deep notches.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::to_minimum_phase;
+///
+/// // A zero outside the unit circle is reflected inside
+/// let taps = to_minimum_phase(&[1f64, 2.5f64, 1f64]);
+/// assert!((taps[0] - 2f64).abs() < 1e-6f64);
+/// assert!((taps[1] - 2f64).abs() < 1e-6f64);
+/// assert!((taps[2] - 0.5f64).abs() < 1e-6f64);
+/// ```
+pub fn to_minimum_phase<T: Float + FloatConst>(impulse_response: &[T]) -> Vec<T> {
+  let length = impulse_response.len();
+  if length == 0 {
+    return Vec::new();
+  }
+  let size = (OVERSAMPLING * length).next_power_of_two().max(2);
+  let zero = Complex::new(T::zero(), T::zero());
+
+  let mut buffer = vec![zero; size];
+  for (value, sample) in buffer.iter_mut().zip(impulse_response.iter()) {
+    value.re = *sample;
+  }
+  fft::forward(&mut buffer);
+  let peak = buffer.iter().fold(T::zero(), |peak, value| peak.max(value.norm()));
+  if peak == T::zero() {
+    return vec![T::zero(); length];
+  }
+  let floor = peak * num::cast(FLOOR).unwrap();
+  for value in buffer.iter_mut() {
+    *value = Complex::new(value.norm().max(floor).ln(), T::zero());
+  }
+
+  // The real cepstrum is even, and folding its negative time onto positive
+  // time makes it causal, which is the cepstrum of the minimum phase filter
+  fft::inverse(&mut buffer);
+  for value in buffer[1..size / 2].iter_mut() {
+    *value = *value * T::two();
+  }
+  for value in buffer[size / 2 + 1..].iter_mut() {
+    *value = zero;
+  }
+  fft::forward(&mut buffer);
+  for value in buffer.iter_mut() {
+    *value = value.exp();
+  }
+  fft::inverse(&mut buffer);
+  buffer.iter().take(length).map(|value| value.re).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::{Fir, polynomial_to_reflection};
+  use traits::FrequencyResponse;
+  use util;
+  use window::{apply_window, Window};
+
+  #[test]
+  fn linear_phase() {
+    // A windowed sinc low-pass, symmetric around its center
+    let mut taps: Vec<f64> = (0..63)
+      .map(|n| {
+        let t = n as f64 - 31f64;
+        if t == 0f64 { 0.25f64 } else { (0.25f64 * ::std::f64::consts::PI * t).sin() / (::std::f64::consts::PI * t) }
+      })
+      .collect();
+    apply_window(&mut taps, Window::Hann);
+    let minimum = to_minimum_phase(&taps);
+    assert_eq!(minimum.len(), 63);
+
+    // The magnitude is kept in the pass band and the transition
+    let (linear, minimum_fir) = (Fir::new(&taps), Fir::new(&minimum));
+    for frequency in [0f64, 1_000f64, 3_000f64, 5_000f64, 6_000f64].iter() {
+      let expected = util::to_db(linear.magnitude_at(*frequency, 48_000f64));
+      assert!((util::to_db(minimum_fir.magnitude_at(*frequency, 48_000f64)) - expected).abs() < 0.1f64);
+    }
+
+    // The energy arrives well before the center of the linear phase filter
+    let energy = |taps: &[f64]| taps.iter().map(|tap| tap * tap).sum::<f64>();
+    assert!(energy(&minimum[..16]) > 0.9f64 * energy(&minimum));
+    assert!(energy(&taps[..16]) < 0.01f64 * energy(&taps));
+  }
+
+  #[test]
+  fn zeros() {
+    // Every zero of a minimum phase filter is inside the unit circle
+    let minimum = to_minimum_phase(&[0.5f64, -1f64, 3f64, 0.25f64, -2f64]);
+    let reflection = polynomial_to_reflection(&minimum).unwrap();
+    assert!(reflection.iter().all(|k| k.abs() < 1f64));
+
+    // Already minimum phase filters are unchanged
+    let taps = [1f64, -0.9f64, 0.2f64];
+    for (tap, expected) in to_minimum_phase(&taps).iter().zip(taps.iter()) {
+      assert!((tap - expected).abs() < 1e-9f64);
+    }
+
+    assert!(to_minimum_phase::<f64>(&[]).is_empty());
+    assert_eq!(to_minimum_phase(&[0f64; 4]), vec![0f64; 4]);
+    assert!((to_minimum_phase(&[-0.5f64])[0] - 0.5f64).abs() < 1e-12f64);
+  }
+}
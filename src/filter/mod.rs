@@ -1,14 +1,110 @@
+//! Digital filters.
+//!
+//! Only the zero-delay feedback filters, `OnePoleTpt`, `SvfTpt` and
+//! `Ladder`, are safe for modulating the cutoff every sample, using
+//! `modulate_cutoff()`. Most other filters are direct form structures, whose
+//! memory holds past outputs that no longer match when their coefficients
+//! change, which causes transients and can be unstable when the coefficients
+//! are recomputed at audio rate. The Chamberlin `StateVariable` tolerates
+//! slow modulation, but becomes unstable at high cutoff frequencies.
+
+pub mod design;
+pub mod ir;
+pub mod matched;
 pub mod rbj;
+pub mod weighting;
 
+mod allpass_delay;
 mod biquad;
+mod cic;
+mod comb;
+mod crossover;
+mod direct_form;
+mod dynamic_smoother;
+mod fast_convolver;
+mod fir;
+mod gammatone;
+mod ladder;
+mod lattice;
+mod median;
+mod minimum_phase;
+mod moving_average;
 mod one_pole;
+mod one_pole_tpt;
 mod one_zero;
+mod parametric_eq;
+mod partitioned_convolver;
+mod pinking;
+mod precision;
+mod response;
+mod savitzky_golay;
+mod smoothed_biquad;
+mod sos_cascade;
+mod state_variable;
+mod svf_tpt;
 mod two_pole;
 mod two_zero;
 
-pub use self::biquad::Biquad1   as Biquad1;
-pub use self::biquad::Biquad2   as Biquad2;
-pub use self::one_pole::OnePole as OnePole;
-pub use self::one_zero::OneZero as OneZero;
-pub use self::two_pole::TwoPole as TwoPole;
-pub use self::two_zero::TwoZero as TwoZero;
+pub use self::allpass_delay::AllpassDelay                 as AllpassDelay;
+pub use self::biquad::Biquad1                             as Biquad1;
+pub use self::biquad::Biquad2                             as Biquad2;
+pub use self::biquad::BiquadCoefficients                  as BiquadCoefficients;
+pub use self::biquad::ErrorFeedback                       as ErrorFeedback;
+pub use self::cic::CicDecimator                           as CicDecimator;
+pub use self::cic::CicInterpolator                        as CicInterpolator;
+pub use self::comb::Comb                                  as Comb;
+pub use self::comb::CombMode                              as CombMode;
+pub use self::crossover::Crossover                        as Crossover;
+pub use self::direct_form::DirectForm2T                   as DirectForm2T;
+pub use self::dynamic_smoother::DynamicSmoother           as DynamicSmoother;
+pub use self::fast_convolver::FastConvolver               as FastConvolver;
+pub use self::fir::Fir                                    as Fir;
+pub use self::gammatone::GammatoneFilterbank              as GammatoneFilterbank;
+pub use self::ladder::Ladder                              as Ladder;
+pub use self::lattice::LatticeFir                         as LatticeFir;
+pub use self::lattice::LatticeLadder                      as LatticeLadder;
+pub use self::median::MedianFilter                        as Median;
+pub use self::median::MedianFilter                        as MedianFilter;
+pub use self::moving_average::MovingAverage               as MovingAverage;
+pub use self::one_pole::OnePole                           as OnePole;
+pub use self::one_pole_tpt::OnePoleTpt                    as OnePoleTpt;
+pub use self::one_zero::OneZero                           as OneZero;
+pub use self::parametric_eq::EqBandType                   as EqBandType;
+pub use self::parametric_eq::ParametricEq                 as ParametricEq;
+pub use self::partitioned_convolver::PartitionedConvolver as PartitionedConvolver;
+pub use self::pinking::PinkingFilter                      as PinkingFilter;
+pub use self::precision::Precision                        as Precision;
+pub use self::savitzky_golay::SavitzkyGolay               as SavitzkyGolay;
+pub use self::smoothed_biquad::SmoothedBiquad             as SmoothedBiquad;
+pub use self::sos_cascade::SosCascade                     as SosCascade;
+pub use self::state_variable::StateVariable               as StateVariable;
+pub use self::svf_tpt::SvfMode                            as SvfMode;
+pub use self::svf_tpt::SvfOutputs                         as SvfOutputs;
+pub use self::svf_tpt::SvfTpt                             as SvfTpt;
+pub use self::two_pole::TwoPole                           as TwoPole;
+pub use self::two_zero::TwoZero                           as TwoZero;
+
+pub use self::lattice::{polynomial_to_reflection, reflection_to_polynomial};
+pub use self::minimum_phase::to_minimum_phase;
+pub use self::response::{render_impulse_response, render_step_response};
+
+#[cfg(test)]
+mod tests {
+  use std::f32::consts::PI;
+  use traits::Processor;
+
+  /// Returns the steady state amplitude of a sine at `frequency`, for
+  /// filters without a `FrequencyResponse`, or to check one against the
+  /// processed output.
+  pub fn amplitude<P: Processor<f32>>(filter: &mut P, sample_rate: f32, frequency: f32) -> f32 {
+    filter.clear();
+    let mut peak = 0f32;
+    for n in 0..sample_rate as usize / 4 {
+      let output = filter.process((2f32 * PI * frequency * n as f32 / sample_rate).sin());
+      if n > sample_rate as usize / 8 {
+        peak = peak.max(output.abs());
+      }
+    }
+    peak
+  }
+}
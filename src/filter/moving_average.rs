@@ -0,0 +1,120 @@
+use num;
+use num::traits::Float;
+
+use traits::Processor;
+
+/// A moving average, or boxcar, filter.
+///
+/// The output is the mean of the last `length` samples, kept as a running
+/// sum so each sample costs one addition and one subtraction, however long
+/// the window is. Unlike the `LeakyIntegrator`, every sample in the window is
+/// weighted equally and samples leave the average completely once they are
+/// `length` samples old.
+///
+/// The running sum is recomputed from the window every `length` samples, so
+/// rounding errors do not accumulate over long signals.
+#[derive(Clone)]
+pub struct MovingAverage<T> {
+  memory: Vec<T>,
+  write_ptr: usize,
+  sum: T,
+  output: T
+}
+
+impl<T> MovingAverage<T> where T: Float {
+  /// Creates a new `MovingAverage` over `length` samples.
+  ///
+  /// `length` must be at least one. The window starts filled with zeros.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::MovingAverage;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut samples = vec![1f32; 6];
+  /// let mut filter = MovingAverage::new(4);
+  /// filter.process_block(&mut samples);
+  /// assert_eq!(samples, vec![0.25f32, 0.5f32, 0.75f32, 1f32, 1f32, 1f32]);
+  /// ```
+  pub fn new(length: usize) -> Self {
+    debug_assert!(length >= 1);
+    MovingAverage {
+      memory: vec![num::zero(); length],
+      write_ptr: 0,
+      sum: num::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Returns the length of the window, in samples.
+  pub fn len(&self) -> usize {
+    self.memory.len()
+  }
+
+  /// Returns `true` if the window holds no samples.
+  pub fn is_empty(&self) -> bool {
+    self.memory.is_empty()
+  }
+}
+
+impl<T> Processor<T> for MovingAverage<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.sum = self.sum + sample - self.memory[self.write_ptr];
+    self.memory[self.write_ptr] = sample;
+    self.write_ptr = (self.write_ptr + 1) % self.memory.len();
+    if self.write_ptr == 0 {
+      self.sum = self.memory.iter().fold(T::zero(), |sum, x| sum + *x);
+    }
+    self.output = self.sum / num::cast(self.memory.len()).unwrap();
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for sample in self.memory.iter_mut() {
+      *sample = num::zero();
+    }
+    self.sum = num::zero();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use ::traits::Processor;
+
+  #[test]
+  fn average() {
+    let input: Vec<f64> = (0..200).map(|n| ((n * 17 % 23) as f64 - 11f64) * 1e3f64).collect();
+    let mut filter = MovingAverage::new(5);
+    for (n, sample) in input.iter().enumerate() {
+      let start = n.saturating_sub(4);
+      let expected = input[start..n + 1].iter().sum::<f64>() / 5f64;
+      assert!((filter.process(*sample) - expected).abs() < 1e-9f64);
+    }
+  }
+
+  #[test]
+  fn no_drift() {
+    // Large values followed by small ones lose precision in a running sum
+    let mut filter = MovingAverage::new(3);
+    for _ in 0..10 {
+      filter.process(1e7f32);
+      filter.process(0.1f32);
+    }
+    // The error is removed once the window is summed again
+    for _ in 0..6 {
+      filter.process(0.5f32);
+    }
+    assert_eq!(filter.last_out(), 0.5f32);
+
+    filter.clear();
+    assert_eq!(filter.process(0f32), 0f32);
+    assert_eq!(filter.len(), 3);
+  }
+}
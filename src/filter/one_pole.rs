@@ -1,7 +1,11 @@
 use num;
+use num::Complex;
 use num::traits::Float;
 
-use traits::Processor;
+use filter::design::Band;
+use filter::response::{evaluate, zeros_and_poles};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
+use util;
 
 /// A single channel, one pole digital filter.
 ///
@@ -10,6 +14,7 @@ use traits::Processor;
 /// `y[n] = b0*x[n] - a1*y[n-1]`
 ///
 /// It has one feedback coefficient, `a1`. 
+#[derive(Clone)]
 pub struct OnePole<T: Float> {
   y_z1: T,
   pub b0: T,
@@ -50,10 +55,54 @@ impl<T> OnePole<T> where T: Float {
   }
 }
 
+impl<T> OnePole<T> where T: Float + FloatConst {
+  /// Sets the coefficients so the gain is -3 dB at `cutoff_frequency`, in
+  /// Hz, for a lowpass with unity gain at DC, or a highpass with unity gain
+  /// at Nyquist.
+  ///
+  /// The highpass mirrors the lowpass around a quarter of the sample rate,
+  /// with its pole on the negative real axis. Without a zero, it does not
+  /// reject DC, and only attenuates it as much as the mirrored lowpass
+  /// attenuates Nyquist, so it suits cutoffs above a quarter of the sample
+  /// rate. `cutoff_frequency` must be positive and below Nyquist, else the
+  /// coefficients are not updated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::OnePole;
+  /// use rasp::filter::design::Band;
+  /// use rasp::traits::FrequencyResponse;
+  ///
+  /// let mut filter = OnePole::new();
+  /// filter.set_cutoff(Band::LowPass, 44_100f32, 200f32);
+  /// let gain = filter.magnitude_at(200f32, 44_100f32);
+  /// assert!((gain - 0.5f32.sqrt()).abs() < 1e-4f32);
+  /// ```
+  pub fn set_cutoff(&mut self, band: Band, sample_rate: T, cutoff_frequency: T) {
+    if !(cutoff_frequency > T::zero() && cutoff_frequency < sample_rate / T::two()) {
+      return;
+    }
+    let frequency =
+      match band {
+        Band::LowPass => cutoff_frequency,
+        Band::HighPass => sample_rate / T::two() - cutoff_frequency
+      };
+
+    // Solves (1 - p)^2 / (1 - 2 p cos(w) + p^2) = 1 / 2 for the pole of the
+    // lowpass inside the unit circle
+    let k = T::two() - (T::two() * T::pi() * frequency / sample_rate).cos();
+    let pole = k - (k * k - T::one()).max(T::zero()).sqrt();
+    let pole = match band { Band::LowPass => pole, Band::HighPass => -pole };
+    self.b0 = T::one() - pole.abs();
+    self.a1 = -pole;
+  }
+}
+
 impl<T> Processor<T> for OnePole<T> where T: Float {
   fn process(&mut self, sample: T) -> T {
     let output = self.b0 * sample - self.a1 * self.y_z1;
-    self.y_z1 = output;
+    self.y_z1 = util::flush_denormal(output);
     output
   }
 
@@ -66,12 +115,62 @@ impl<T> Processor<T> for OnePole<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for OnePole<T> where T: Float {
+  type State = [T; 1];
+
+  fn snapshot(&self) -> Self::State {
+    [self.y_z1]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.y_z1 = state[0];
+  }
+}
+
+impl<T> FrequencyResponse<T> for OnePole<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0], &[T::one(), self.a1], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for OnePole<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0], &[T::one(), self.a1]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0], &[T::one(), self.a1]).0
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use std::f32::EPSILON;
   use ::traits::Processor;
 
+  #[test]
+  fn cutoff() {
+    let mut filter = OnePole::new();
+    for cutoff in [20f64, 1_000f64, 15_000f64].iter() {
+      filter.set_cutoff(Band::LowPass, 48_000f64, *cutoff);
+      assert!((filter.magnitude_at(0f64, 48_000f64) - 1f64).abs() < 1e-12f64);
+      assert!((filter.magnitude_at(*cutoff, 48_000f64) - 0.5f64.sqrt()).abs() < 1e-9f64);
+      assert!(filter.is_stable());
+    }
+
+    filter.set_cutoff(Band::HighPass, 48_000f64, 18_000f64);
+    assert!((filter.magnitude_at(24_000f64, 48_000f64) - 1f64).abs() < 1e-12f64);
+    assert!((filter.magnitude_at(18_000f64, 48_000f64) - 0.5f64.sqrt()).abs() < 1e-9f64);
+    assert!(filter.magnitude_at(0f64, 48_000f64) < 0.4f64);
+
+    // Invalid cutoffs are not updated
+    let (b0, a1) = (filter.b0, filter.a1);
+    filter.set_cutoff(Band::LowPass, 48_000f64, 0f64);
+    filter.set_cutoff(Band::LowPass, 48_000f64, 24_000f64);
+    assert_eq!((filter.b0, filter.a1), (b0, a1));
+  }
+
   #[test]
   fn process() {
     let input = vec![0.55f32, -0.55f32, 0.55f32, -0.55f32, 0.25f32];
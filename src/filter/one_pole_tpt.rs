@@ -0,0 +1,210 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::response::evaluate;
+use traits::{FloatConst, FrequencyResponse, Processor, StateSnapshot};
+use util;
+
+/// A zero-delay feedback one pole filter.
+///
+/// The analog one pole filter is discretized with a trapezoidal integrator,
+/// following the topology-preserving transform. Unlike `OnePole`, the cutoff
+/// is matched to its analog prototype up to Nyquist, and can be changed every
+/// sample without transients. Both the lowpass and the complementary
+/// highpass responses are available from the same state.
+#[derive(Clone)]
+pub struct OnePoleTpt<T> {
+  sample_rate: T,
+  highpass: bool,
+  // Integrator gain
+  g: T,
+  // Integrator memory
+  s: T,
+  output: T
+}
+
+impl<T> OnePoleTpt<T> where T: Float + FloatConst {
+  /// Creates a new `OnePoleTpt` filter in lowpass mode.
+  ///
+  /// The filter will be initialized in a state that outputs silence.
+  /// `set_coefficients()` must be called, with valid arguments, to make the
+  /// filter functional.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::filter::OnePoleTpt;
+  ///
+  /// let mut filter1: OnePoleTpt<f32> = OnePoleTpt::new();
+  /// let mut filter2: OnePoleTpt<f64> = OnePoleTpt::new();
+  /// let mut filter3 = OnePoleTpt::<f32>::new();
+  /// let mut filter4 = OnePoleTpt::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    OnePoleTpt {
+      sample_rate: num::one(),
+      highpass: false,
+      g: num::zero(),
+      s: num::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets filter coefficients from the `sample_rate` and
+  /// `cutoff_frequency`.
+  ///
+  /// The cutoff frequency is clipped to just below Nyquist. The filter
+  /// memory is not cleared, so this may be called while processing.
+  pub fn set_coefficients(&mut self, sample_rate: T, cutoff_frequency: T) {
+    self.sample_rate = sample_rate;
+    self.set_cutoff(cutoff_frequency);
+  }
+
+  fn set_cutoff(&mut self, cutoff_frequency: T) {
+    let nyquist: T = self.sample_rate * num::cast(0.499f64).unwrap();
+    let cutoff = cutoff_frequency.max(T::zero()).min(nyquist);
+
+    let g = (T::pi() * cutoff / self.sample_rate).tan();
+    self.g = g / (T::one() + g);
+  }
+
+  /// Sets whether `process()` outputs the highpass, rather than the
+  /// lowpass, response.
+  pub fn set_highpass(&mut self, highpass: bool) {
+    self.highpass = highpass;
+  }
+
+  /// Returns `true` if `process()` outputs the highpass response.
+  pub fn is_highpass(&self) -> bool {
+    self.highpass
+  }
+
+  /// Processes a sample and returns the lowpass and highpass responses.
+  pub fn tick(&mut self, sample: T) -> (T, T) {
+    let v = (sample - self.s) * self.g;
+    let lowpass = v + self.s;
+    self.s = util::flush_denormal(lowpass + v);
+
+    let highpass = sample - lowpass;
+    self.output = if self.highpass { highpass } else { lowpass };
+    (lowpass, highpass)
+  }
+
+  /// Processes a contiguous sequence of samples in place, using a separate
+  /// cutoff frequency for each sample.
+  ///
+  /// This is safe for audio rate modulation. `cutoffs` must be as long as
+  /// `samples`, and the last cutoff is kept after processing. Returns the
+  /// last processed sample.
+  pub fn modulate_cutoff(&mut self, samples: &mut [T], cutoffs: &[T]) -> T {
+    debug_assert_eq!(samples.len(), cutoffs.len());
+    for (sample, cutoff) in samples.iter_mut().zip(cutoffs.iter()) {
+      self.set_cutoff(*cutoff);
+      self.tick(*sample);
+      *sample = self.output;
+    }
+    self.output
+  }
+}
+
+impl<T> Processor<T> for OnePoleTpt<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.tick(sample);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.s = num::zero();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for OnePoleTpt<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    // The bilinear transform of the analog prototype, whose pole is at
+    // `2g - 1` with the integrator gain `g`
+    let g = self.g;
+    let denominator = [T::one(), T::two() * g - T::one()];
+    let numerator = if self.highpass { [T::one() - g, g - T::one()] } else { [g, g] };
+    evaluate(&numerator, &denominator, frequency, sample_rate)
+  }
+}
+
+impl<T> StateSnapshot for OnePoleTpt<T> where T: Float + FloatConst {
+  type State = [T; 2];
+
+  fn snapshot(&self) -> Self::State {
+    [self.s, self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.s = state[0];
+    self.output = state[1];
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::FRAC_1_SQRT_2;
+  use filter::tests::amplitude;
+
+  #[test]
+  fn responses() {
+    let sample_rate = 44_100f32;
+    let mut filter = OnePoleTpt::new();
+
+    // -3 dB at the cutoff, even close to Nyquist
+    for cutoff in [100f32, 1_000f32, 15_000f32].iter() {
+      filter.set_coefficients(sample_rate, *cutoff);
+      filter.set_highpass(false);
+      assert!((filter.magnitude_at(*cutoff, sample_rate) - FRAC_1_SQRT_2).abs() < 1e-4f32);
+      filter.set_highpass(true);
+      assert!((filter.magnitude_at(*cutoff, sample_rate) - FRAC_1_SQRT_2).abs() < 1e-4f32);
+    }
+  }
+
+  #[test]
+  fn response_matches_output() {
+    let sample_rate = 44_100f32;
+    let mut filter = OnePoleTpt::new();
+    filter.set_coefficients(sample_rate, 2_000f32);
+    for highpass in [false, true].iter() {
+      filter.set_highpass(*highpass);
+      for frequency in [200f32, 2_000f32, 12_000f32].iter() {
+        let measured = amplitude(&mut filter, sample_rate, *frequency);
+        assert!((filter.magnitude_at(*frequency, sample_rate) - measured).abs() < 1e-2f32);
+      }
+    }
+  }
+
+  #[test]
+  fn complementary() {
+    let mut filter = OnePoleTpt::new();
+    filter.set_coefficients(44_100f32, 500f32);
+    for n in 0..64 {
+      let sample = (n as f32 * 0.7f32).sin();
+      let (lowpass, highpass) = filter.tick(sample);
+      assert!((lowpass + highpass - sample).abs() < 1e-6f32);
+    }
+  }
+
+  #[test]
+  fn modulate_cutoff() {
+    let mut filter = OnePoleTpt::new();
+    filter.set_coefficients(44_100f32, 1_000f32);
+
+    let cutoffs: Vec<f32> = (0..4_410).map(|n| if n % 2 == 0 { 10f32 } else { 22_000f32 }).collect();
+    let mut samples = vec![1f32; 4_410];
+    filter.modulate_cutoff(&mut samples, &cutoffs);
+    for sample in samples.iter() {
+      assert!(sample.is_finite() && sample.abs() < 2f32);
+    }
+  }
+}
@@ -1,7 +1,10 @@
 use num;
+use num::Complex;
 use num::traits::Float;
 
-use traits::Processor;
+use filter::design::Band;
+use filter::response::{evaluate, zeros_and_poles};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
 
 /// A single channel, one zero digital filter.
 ///
@@ -10,6 +13,7 @@ use traits::Processor;
 /// `y[n] = b0*x[n] + b1*x[n-1]`
 ///
 /// It has one feedforward coefficient, `b1`. 
+#[derive(Clone)]
 pub struct OneZero<T: Float> {
   x_z1: T,
   output: T,
@@ -52,6 +56,54 @@ impl<T> OneZero<T> where T: Float {
   }
 }
 
+impl<T> OneZero<T> where T: Float + FloatConst {
+  /// Sets the coefficients so the gain is -3 dB at `cutoff_frequency`, in
+  /// Hz, for a lowpass with unity gain at DC, or a highpass with unity gain
+  /// at Nyquist.
+  ///
+  /// A single zero rolls off too gently to reach -3 dB below a quarter of
+  /// the sample rate for a lowpass, where it becomes the two sample average,
+  /// or above it for a highpass, which mirrors the lowpass. The zero is kept
+  /// inside the unit circle. `cutoff_frequency` must be within that range,
+  /// and below Nyquist, else the coefficients are not updated.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::OneZero;
+  /// use rasp::filter::design::Band;
+  /// use rasp::traits::FrequencyResponse;
+  ///
+  /// let mut filter = OneZero::new();
+  /// filter.set_cutoff(Band::HighPass, 44_100f32, 5_000f32);
+  /// let gain = filter.magnitude_at(5_000f32, 44_100f32);
+  /// assert!((gain - 0.5f32.sqrt()).abs() < 1e-4f32);
+  /// ```
+  pub fn set_cutoff(&mut self, band: Band, sample_rate: T, cutoff_frequency: T) {
+    let quarter = sample_rate / (T::two() * T::two());
+    let valid =
+      match band {
+        Band::LowPass => cutoff_frequency >= quarter && cutoff_frequency < sample_rate / T::two(),
+        Band::HighPass => cutoff_frequency > T::zero() && cutoff_frequency <= quarter
+      };
+    if !valid {
+      return;
+    }
+    let frequency =
+      match band {
+        Band::LowPass => cutoff_frequency,
+        Band::HighPass => sample_rate / T::two() - cutoff_frequency
+      };
+
+    // Solves (1 + 2 b cos(w) + b^2) / (1 + b)^2 = 1 / 2 for the zero of the
+    // lowpass inside the unit circle
+    let k = T::one() - T::two() * (T::two() * T::pi() * frequency / sample_rate).cos();
+    let zero = k - (k * k - T::one()).max(T::zero()).sqrt();
+    self.b0 = T::one() / (T::one() + zero);
+    self.b1 = match band { Band::LowPass => zero, Band::HighPass => -zero } / (T::one() + zero);
+  }
+}
+
 impl<T> Processor<T> for OneZero<T> where T: Float {
   fn process(&mut self, sample: T) -> T {
     self.output = self.b0 * sample + self.b1 * self.x_z1;
@@ -68,12 +120,70 @@ impl<T> Processor<T> for OneZero<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for OneZero<T> where T: Float {
+  type State = [T; 2];
+
+  fn snapshot(&self) -> Self::State {
+    [self.x_z1, self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.x_z1 = state[0];
+    self.output = state[1];
+  }
+}
+
+impl<T> FrequencyResponse<T> for OneZero<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0, self.b1], &[T::one()], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for OneZero<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1], &[T::one()]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1], &[T::one()]).0
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use std::f32::EPSILON;
   use ::traits::Processor;
 
+  #[test]
+  fn cutoff() {
+    let mut filter = OneZero::new();
+    for cutoff in [12_000f64, 18_000f64, 23_000f64].iter() {
+      filter.set_cutoff(Band::LowPass, 48_000f64, *cutoff);
+      assert!((filter.magnitude_at(0f64, 48_000f64) - 1f64).abs() < 1e-12f64);
+      assert!((filter.magnitude_at(*cutoff, 48_000f64) - 0.5f64.sqrt()).abs() < 1e-9f64);
+    }
+    assert!((filter.b0 - 0.5f64).abs() > 1e-3f64);
+
+    // At a quarter of the sample rate, the lowpass is the two sample average,
+    // and the highpass the two sample difference
+    filter.set_cutoff(Band::LowPass, 48_000f64, 12_000f64);
+    assert!((filter.b0 - 0.5f64).abs() < 1e-6f64 && (filter.b1 - 0.5f64).abs() < 1e-6f64);
+    filter.set_cutoff(Band::HighPass, 48_000f64, 12_000f64);
+    assert!((filter.b0 - 0.5f64).abs() < 1e-6f64 && (filter.b1 + 0.5f64).abs() < 1e-6f64);
+
+    filter.set_cutoff(Band::HighPass, 48_000f64, 4_000f64);
+    assert!((filter.magnitude_at(24_000f64, 48_000f64) - 1f64).abs() < 1e-12f64);
+    assert!((filter.magnitude_at(4_000f64, 48_000f64) - 0.5f64.sqrt()).abs() < 1e-9f64);
+    assert!(filter.zeros().iter().all(|zero| zero.norm() < 1f64));
+
+    // Cutoffs a single zero cannot reach are not updated
+    let (b0, b1) = (filter.b0, filter.b1);
+    filter.set_cutoff(Band::LowPass, 48_000f64, 1_000f64);
+    filter.set_cutoff(Band::HighPass, 48_000f64, 20_000f64);
+    assert_eq!((filter.b0, filter.b1), (b0, b1));
+  }
+
   #[test]
   fn process() {
     let input = vec![0.55f32, -0.55f32, 0.55f32, -0.55f32, 0.25f32];
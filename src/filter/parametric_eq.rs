@@ -0,0 +1,293 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use filter::rbj::{AllPass, BandPass2, BandStop, HighPass, HighShelf, LowPass, LowShelf, Peak};
+use traits::{FloatConst, FrequencyResponse, Processor};
+
+/// The RBJ filter of a band of a `ParametricEq`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EqBandType {
+  LowPass,
+  HighPass,
+  /// A band-pass with 0 dB gain at the center frequency.
+  BandPass,
+  BandStop,
+  AllPass,
+  Peak,
+  LowShelf,
+  HighShelf
+}
+
+/// One band of a `ParametricEq`.
+#[derive(Clone)]
+struct Band<T> {
+  band_type: EqBandType,
+  enabled: bool,
+  frequency: T,
+  gain: T,
+  q: T,
+  filter: Biquad2<T>
+}
+
+/// A multi-band parametric equalizer.
+///
+/// Each band is an RBJ biquad of any type, with its own frequency, gain and
+/// Q factor, and the bands are processed in series, in the order they were
+/// added. For shelves, the Q factor is used as the shelf slope, and the gain
+/// only affects peaks and shelves. Changing a parameter recomputes the
+/// coefficients of its band without clearing its memory, and a disabled band
+/// is bypassed, keeping its settings.
+///
+/// The frequency response is that of the whole equalizer, with only the
+/// enabled bands.
+#[derive(Clone)]
+pub struct ParametricEq<T> {
+  sample_rate: T,
+  bands: Vec<Band<T>>,
+  output: T
+}
+
+impl<T> ParametricEq<T> where T: Float + FloatConst {
+  /// Creates a new `ParametricEq` for signals at `sample_rate`, without any
+  /// bands, that does not alter the input signal.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::{EqBandType, ParametricEq};
+  /// use rasp::traits::{FrequencyResponse, Processor};
+  ///
+  /// let mut eq = ParametricEq::new(48_000f32);
+  /// eq.push_band(EqBandType::HighPass, 40f32, 0f32, 0.7071f32);
+  /// eq.push_band(EqBandType::Peak, 2_500f32, -4f32, 2f32);
+  /// eq.push_band(EqBandType::HighShelf, 8_000f32, 3f32, 1f32);
+  /// assert_eq!(eq.bands(), 3);
+  ///
+  /// eq.set_gain(1, -6f32);
+  /// eq.set_enabled(2, false);
+  /// let gain = eq.magnitude_at(2_500f32, 48_000f32);
+  /// assert!((gain - 0.5f32).abs() < 0.01f32);
+  ///
+  /// let output = eq.process(0.5f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    ParametricEq {
+      sample_rate,
+      bands: Vec::new(),
+      output: num::zero()
+    }
+  }
+
+  /// Appends an enabled band to the end of the equalizer.
+  ///
+  /// `frequency` must be positive and below Nyquist, and `q` positive.
+  pub fn push_band(&mut self, band_type: EqBandType, frequency: T, gain: T, q: T) {
+    debug_assert!(frequency > T::zero() && frequency < self.sample_rate / T::two() && q > T::zero());
+    let mut band = Band {
+      band_type,
+      enabled: true,
+      frequency,
+      gain,
+      q,
+      filter: Biquad2::new()
+    };
+    band.filter.load_coefficients(coefficients(&band, self.sample_rate));
+    self.bands.push(band);
+  }
+
+  /// Removes every band.
+  pub fn clear_bands(&mut self) {
+    self.bands.clear();
+  }
+
+  /// Returns the sample rate the bands are designed for.
+  pub fn get_sample_rate(&self) -> T {
+    self.sample_rate
+  }
+
+  /// Returns the number of bands.
+  pub fn bands(&self) -> usize {
+    self.bands.len()
+  }
+
+  /// Sets the filter type of a `band`.
+  pub fn set_band_type(&mut self, band: usize, band_type: EqBandType) {
+    self.bands[band].band_type = band_type;
+    self.update(band);
+  }
+
+  /// Returns the filter type of a `band`.
+  pub fn get_band_type(&self, band: usize) -> EqBandType {
+    self.bands[band].band_type
+  }
+
+  /// Sets whether a `band` is processed, or bypassed.
+  pub fn set_enabled(&mut self, band: usize, enabled: bool) {
+    self.bands[band].enabled = enabled;
+  }
+
+  /// Returns `true` if a `band` is processed.
+  pub fn is_enabled(&self, band: usize) -> bool {
+    self.bands[band].enabled
+  }
+
+  /// Sets the frequency, in Hz, of a `band`.
+  ///
+  /// `frequency` must be positive and below Nyquist, else it is not updated.
+  pub fn set_frequency(&mut self, band: usize, frequency: T) {
+    if frequency > T::zero() && frequency < self.sample_rate / T::two() {
+      self.bands[band].frequency = frequency;
+      self.update(band);
+    }
+  }
+
+  /// Returns the frequency, in Hz, of a `band`.
+  pub fn get_frequency(&self, band: usize) -> T {
+    self.bands[band].frequency
+  }
+
+  /// Sets the gain, in dB, of a `band`.
+  pub fn set_gain(&mut self, band: usize, gain: T) {
+    if gain.is_finite() {
+      self.bands[band].gain = gain;
+      self.update(band);
+    }
+  }
+
+  /// Returns the gain, in dB, of a `band`.
+  pub fn get_gain(&self, band: usize) -> T {
+    self.bands[band].gain
+  }
+
+  /// Sets the Q factor, or shelf slope, of a `band`.
+  ///
+  /// `q` must be positive, else it is not updated.
+  pub fn set_q(&mut self, band: usize, q: T) {
+    if q > T::zero() && q.is_finite() {
+      self.bands[band].q = q;
+      self.update(band);
+    }
+  }
+
+  /// Returns the Q factor, or shelf slope, of a `band`.
+  pub fn get_q(&self, band: usize) -> T {
+    self.bands[band].q
+  }
+
+  /// Returns the coefficients of a `band`.
+  pub fn coefficients(&self, band: usize) -> BiquadCoefficients<T> {
+    coefficients(&self.bands[band], self.sample_rate)
+  }
+
+  fn update(&mut self, band: usize) {
+    let coefficients = coefficients(&self.bands[band], self.sample_rate);
+    self.bands[band].filter.load_coefficients(coefficients);
+  }
+}
+
+/// Computes the coefficients of a band from its parameters.
+fn coefficients<T: Float + FloatConst>(band: &Band<T>, sample_rate: T) -> BiquadCoefficients<T> {
+  let (frequency, gain, q) = (band.frequency, band.gain, band.q);
+  match band.band_type {
+    EqBandType::LowPass => LowPass::coefficients(sample_rate, frequency, q),
+    EqBandType::HighPass => HighPass::coefficients(sample_rate, frequency, q),
+    EqBandType::BandPass => BandPass2::coefficients(sample_rate, frequency, q),
+    EqBandType::BandStop => BandStop::coefficients(sample_rate, frequency, q),
+    EqBandType::AllPass => AllPass::coefficients(sample_rate, frequency, q),
+    EqBandType::Peak => Peak::coefficients(sample_rate, frequency, gain, q),
+    EqBandType::LowShelf => LowShelf::coefficients(sample_rate, frequency, gain, q),
+    EqBandType::HighShelf => HighShelf::coefficients(sample_rate, frequency, gain, q)
+  }
+}
+
+impl<T> Processor<T> for ParametricEq<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.output = self.bands.iter_mut()
+      .filter(|band| band.enabled)
+      .fold(sample, |sample, band| band.filter.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for band in self.bands.iter_mut() {
+      band.filter.clear();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for ParametricEq<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    self.bands.iter()
+      .filter(|band| band.enabled)
+      .fold(Complex::new(T::one(), T::zero()), |response, band| response * band.filter.response_at(frequency, sample_rate))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use util;
+
+  #[test]
+  fn bands() {
+    let mut eq = ParametricEq::new(48_000f64);
+    assert_eq!(eq.process(0.5f64), 0.5f64);
+    assert_eq!(eq.magnitude_at(1_000f64, 48_000f64), 1f64);
+
+    eq.push_band(EqBandType::LowShelf, 100f64, 6f64, 1f64);
+    eq.push_band(EqBandType::Peak, 1_000f64, -9f64, 1f64);
+    let db = |eq: &ParametricEq<f64>, frequency: f64| util::to_db(eq.magnitude_at(frequency, 48_000f64));
+    assert!((db(&eq, 20f64) - 6f64).abs() < 0.1f64);
+    assert!((db(&eq, 1_000f64) + 9f64).abs() < 0.1f64);
+
+    // Each parameter changes only its own band
+    eq.set_gain(1, 3f64);
+    eq.set_frequency(1, 4_000f64);
+    eq.set_q(1, 4f64);
+    assert!((db(&eq, 4_000f64) - 3f64).abs() < 0.1f64);
+    assert!(db(&eq, 1_000f64).abs() < 0.1f64);
+    assert_eq!((eq.get_frequency(1), eq.get_gain(1), eq.get_q(1)), (4_000f64, 3f64, 4f64));
+
+    // Invalid parameters are not updated
+    eq.set_frequency(1, 30_000f64);
+    eq.set_q(1, 0f64);
+    assert_eq!((eq.get_frequency(1), eq.get_q(1)), (4_000f64, 4f64));
+
+    eq.set_enabled(0, false);
+    assert!(!eq.is_enabled(0));
+    assert!(db(&eq, 20f64).abs() < 0.1f64);
+
+    eq.set_band_type(1, EqBandType::LowPass);
+    assert_eq!(eq.get_band_type(1), EqBandType::LowPass);
+    assert!(db(&eq, 16_000f64) < -20f64);
+    assert_eq!(eq.coefficients(1), LowPass::coefficients(48_000f64, 4_000f64, 4f64));
+  }
+
+  #[test]
+  fn process() {
+    let mut eq = ParametricEq::new(48_000f64);
+    eq.push_band(EqBandType::HighPass, 200f64, 0f64, 0.5f64);
+    eq.push_band(EqBandType::Peak, 3_000f64, 6f64, 2f64);
+    let mut expected = (HighPass::new(), Peak::new());
+    expected.0.set_coefficients(48_000f64, 200f64, 0.5f64);
+    expected.1.set_coefficients(48_000f64, 3_000f64, 6f64, 2f64);
+
+    // Bands are processed in series, in the order they were added
+    for n in 0..64 {
+      let sample = (n as f64 * 0.37f64).sin();
+      let output = expected.1.process(expected.0.process(sample));
+      assert!((eq.process(sample) - output).abs() < 1e-12f64);
+    }
+    eq.clear();
+    assert_eq!(eq.last_out(), 0f64);
+    eq.clear_bands();
+    assert_eq!(eq.bands(), 0);
+  }
+}
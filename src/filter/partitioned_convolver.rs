@@ -0,0 +1,224 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use fft;
+use traits::{FloatConst, Processor};
+
+/// A finite impulse response filter, convolving with the taps split into
+/// partitions of equal length.
+///
+/// A `FastConvolver` is only efficient with blocks about as long as its
+/// taps, which delays its output as much. Here the taps are split into
+/// partitions of `block_size` samples, the spectrum of each input block is
+/// kept for as many blocks as there are partitions, and each output block is
+/// the sum of every partition multiplied with the spectrum of the input block
+/// it is aligned with, using overlap-save. The output is only delayed by `block_size` samples, however
+/// long the taps are, so impulse responses of reverbs several seconds long
+/// can be convolved with a small latency.
+///
+/// The cost of each block grows with the number of partitions, so larger
+/// blocks are more efficient for long responses. Every partition has the same
+/// length.
+pub struct PartitionedConvolver<T> {
+  block_size: usize,
+  taps: usize,
+  // Spectrum of each partition of the taps
+  partitions: Vec<Vec<Complex<T>>>,
+  // Spectra of the most recent input blocks, the newest at `newest`
+  spectra: Vec<Vec<Complex<T>>>,
+  newest: usize,
+  // The previous and current input blocks
+  input: Vec<T>,
+  buffer: Vec<Complex<T>>,
+  output: Vec<T>,
+  position: usize,
+  last: T
+}
+
+impl<T> PartitionedConvolver<T> where T: Float + FloatConst {
+  /// Creates a new `PartitionedConvolver` with the given `taps`, split into
+  /// partitions of `block_size` samples.
+  ///
+  /// `block_size` must be a power of two.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::PartitionedConvolver;
+  /// use rasp::traits::Processor;
+  ///
+  /// // A second long response, delayed by only 64 samples
+  /// let mut impulse = vec![0f32; 44_100];
+  /// impulse[0] = 1f32;
+  /// let mut convolver = PartitionedConvolver::new(&impulse, 64);
+  /// assert_eq!(convolver.latency(), 64);
+  /// assert_eq!(convolver.partitions(), 690);
+  ///
+  /// let mut block = vec![0f32; 128];
+  /// block[0] = 1f32;
+  /// convolver.process_block(&mut block);
+  /// assert!((block[64] - 1f32).abs() < 1e-6f32);
+  /// ```
+  pub fn new(taps: &[T], block_size: usize) -> Self {
+    debug_assert!(block_size.is_power_of_two());
+    let mut convolver = PartitionedConvolver {
+      block_size,
+      taps: 0,
+      partitions: Vec::new(),
+      spectra: Vec::new(),
+      newest: 0,
+      input: vec![num::zero(); 2 * block_size],
+      buffer: vec![Complex::new(T::zero(), T::zero()); 2 * block_size],
+      output: vec![num::zero(); block_size],
+      position: 0,
+      last: num::zero()
+    };
+    convolver.set_taps(taps);
+    convolver
+  }
+
+  /// Sets the taps of the filter, clearing the previous input.
+  pub fn set_taps(&mut self, taps: &[T]) {
+    let size = 2 * self.block_size;
+    self.taps = taps.len();
+    self.partitions = taps.chunks(self.block_size)
+      .map(|partition| {
+        let mut spectrum: Vec<Complex<T>> = (0..size)
+          .map(|n| Complex::new(partition.get(n).cloned().unwrap_or_else(T::zero), T::zero()))
+          .collect();
+        fft::forward(&mut spectrum);
+        spectrum
+      })
+      .collect();
+    self.spectra = vec![vec![Complex::new(T::zero(), T::zero()); size]; self.partitions.len()];
+    self.clear();
+  }
+
+  /// Returns the number of taps.
+  pub fn len(&self) -> usize {
+    self.taps
+  }
+
+  /// Returns `true` if the filter has no taps.
+  pub fn is_empty(&self) -> bool {
+    self.taps == 0
+  }
+
+  /// Returns the number of partitions the taps are split into.
+  pub fn partitions(&self) -> usize {
+    self.partitions.len()
+  }
+
+  /// Returns the delay of the output, in samples, which is the block size.
+  pub fn latency(&self) -> usize {
+    self.block_size
+  }
+
+  /// Convolves the collected block of input.
+  fn convolve(&mut self) {
+    let count = self.partitions.len();
+    if count == 0 {
+      return;
+    }
+
+    self.newest = (self.newest + count - 1) % count;
+    {
+      let spectrum = &mut self.spectra[self.newest];
+      for (value, sample) in spectrum.iter_mut().zip(self.input.iter()) {
+        *value = Complex::new(*sample, T::zero());
+      }
+      fft::forward(spectrum);
+    }
+
+    // The newest input is aligned with the first partition, and each older
+    // input with the following partitions
+    for value in self.buffer.iter_mut() {
+      *value = Complex::new(T::zero(), T::zero());
+    }
+    for (age, partition) in self.partitions.iter().enumerate() {
+      let spectrum = &self.spectra[(self.newest + age) % count];
+      for ((sum, x), h) in self.buffer.iter_mut().zip(spectrum.iter()).zip(partition.iter()) {
+        *sum = *sum + *x * *h;
+      }
+    }
+    fft::inverse(&mut self.buffer);
+
+    // The first half is aliased by the circular convolution
+    for (output, value) in self.output.iter_mut().zip(self.buffer[self.block_size..].iter()) {
+      *output = value.re;
+    }
+  }
+}
+
+impl<T> Processor<T> for PartitionedConvolver<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.input[self.block_size + self.position] = sample;
+    self.last = self.output[self.position];
+    self.position += 1;
+    if self.position == self.block_size {
+      self.convolve();
+      self.input.rotate_left(self.block_size);
+      self.position = 0;
+    }
+    self.last
+  }
+
+  fn clear(&mut self) {
+    for spectrum in self.spectra.iter_mut() {
+      for value in spectrum.iter_mut() {
+        *value = Complex::new(T::zero(), T::zero());
+      }
+    }
+    for sample in self.input.iter_mut().chain(self.output.iter_mut()) {
+      *sample = T::zero();
+    }
+    self.position = 0;
+    self.last = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.last
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::Fir;
+  use ::traits::Processor;
+
+  #[test]
+  fn matches_fir() {
+    let taps: Vec<f64> = (0..1_000).map(|n| ((n * 31 % 17) as f64 - 8f64) / 8f64 * (-(n as f64) / 300f64).exp()).collect();
+    let input: Vec<f64> = (0..3_000).map(|n| (n as f64 * 0.13f64).sin() + ((n * 7 % 5) as f64 - 2f64) * 0.1f64).collect();
+    for block_size in [1, 16, 128, 2_048].iter() {
+      let mut convolver = PartitionedConvolver::new(&taps, *block_size);
+      assert_eq!(convolver.partitions(), taps.len().div_ceil(*block_size));
+      let mut fir = Fir::new(&taps);
+      let expected: Vec<f64> = input.iter().map(|sample| fir.process(*sample)).collect();
+      let output: Vec<f64> = input.iter().map(|sample| convolver.process(*sample)).collect();
+      assert!(output[..*block_size].iter().all(|sample| *sample == 0f64));
+      for (sample, expected) in output[*block_size..].iter().zip(expected.iter()) {
+        assert!((sample - expected).abs() < 1e-9f64);
+      }
+    }
+  }
+
+  #[test]
+  fn set_taps() {
+    let mut convolver = PartitionedConvolver::new(&[], 4);
+    assert!(convolver.is_empty());
+    assert_eq!(convolver.process(1f32), 0f32);
+
+    convolver.set_taps(&[0f32, 0f32, 0f32, 0f32, 0f32, 2f32]);
+    assert_eq!(convolver.len(), 6);
+    let mut block = vec![0f32; 12];
+    block[0] = 1f32;
+    convolver.process_block(&mut block);
+    assert!((block[9] - 2f32).abs() < 1e-6f32);
+
+    convolver.clear();
+    assert_eq!(convolver.last_out(), 0f32);
+  }
+}
@@ -0,0 +1,138 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use traits::{FloatConst, FrequencyResponse, Processor};
+use util;
+
+/// The poles and gains of the parallel one-pole sections.
+const POLES: [f64; 6] = [0.99886f64, 0.99332f64, 0.969f64, 0.8665f64, 0.55f64, -0.7616f64];
+const GAINS: [f64; 6] = [0.0555179f64, 0.0750759f64, 0.153852f64, 0.3104856f64, 0.5329522f64, -0.016898f64];
+
+/// The gains of the direct path, and of the input delayed by one sample.
+const DIRECT: f64 = 0.5362f64;
+const DELAYED: f64 = 0.115926f64;
+
+/// A pinking filter, turning white noise into pink noise.
+///
+/// Pink noise has equal power in every octave, so its spectrum falls by 3 dB
+/// per octave, which no finite filter does exactly. This is Paul Kellet's
+/// refined approximation, a sum of six one-pole lowpasses, whose poles are
+/// spread so their slopes overlap, with a direct path and a one sample
+/// delay. At 44.1 kHz, it follows the -3 dB per octave slope within 0.05 dB
+/// above about 20 Hz, a 2000th of the sample rate, and flattens below.
+///
+/// The gain is about 13.5 dB at 1 kHz, so pink noise from uniform white noise
+/// peaks well above unity, and should be scaled to the level needed.
+#[derive(Clone)]
+pub struct PinkingFilter<T> {
+  state: [T; 6],
+  delayed: T,
+  output: T
+}
+
+impl<T> PinkingFilter<T> where T: Float {
+  /// Creates a new `PinkingFilter`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::PinkingFilter;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut pinking = PinkingFilter::new();
+  /// let mut noise = vec![0.5f32, -0.25f32, 0.75f32, -1f32];
+  /// pinking.process_block(&mut noise);
+  /// ```
+  pub fn new() -> Self {
+    PinkingFilter {
+      state: [num::zero(); 6],
+      delayed: num::zero(),
+      output: num::zero()
+    }
+  }
+}
+
+impl<T> Processor<T> for PinkingFilter<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    let mut sum = self.delayed + sample * num::cast(DIRECT).unwrap();
+    for ((state, pole), gain) in self.state.iter_mut().zip(POLES.iter()).zip(GAINS.iter()) {
+      *state = util::flush_denormal(*state * num::cast(*pole).unwrap() + sample * num::cast(*gain).unwrap());
+      sum = sum + *state;
+    }
+    self.delayed = sample * num::cast(DELAYED).unwrap();
+    self.output = sum;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.state = [T::zero(); 6];
+    self.delayed = T::zero();
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for PinkingFilter<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    let w = T::two() * T::pi() * frequency / sample_rate;
+    let z1 = Complex::from_polar(&T::one(), &-w);
+    let one = Complex::new(T::one(), T::zero());
+    let mut response = one * num::cast::<f64, T>(DIRECT).unwrap() + z1 * num::cast::<f64, T>(DELAYED).unwrap();
+    for (pole, gain) in POLES.iter().zip(GAINS.iter()) {
+      let (pole, gain): (T, T) = (num::cast(*pole).unwrap(), num::cast(*gain).unwrap());
+      response = response + one * gain / (one - z1 * pole);
+    }
+    response
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use util;
+
+  #[test]
+  fn slope() {
+    // Each octave falls by 3 dB
+    let pinking = PinkingFilter::<f64>::new();
+    let db = |frequency: f64| util::to_db(pinking.magnitude_at(frequency, 44_100f64));
+    let mut frequency = 40f64;
+    while frequency < 10_000f64 {
+      assert!((db(frequency) - db(2f64 * frequency) - 3.0103f64).abs() < 0.2f64);
+      frequency *= 2f64;
+    }
+    assert!((db(1_000f64) - 13.5f64).abs() < 0.1f64);
+  }
+
+  #[test]
+  fn impulse_response() {
+    // The response of the filter matches its processing
+    let mut pinking = PinkingFilter::new();
+    let response: Vec<f64> = (0..16_384).map(|n| pinking.process(if n == 0 { 1f64 } else { 0f64 })).collect();
+    assert!((response[0] - (DIRECT + GAINS.iter().sum::<f64>())).abs() < 1e-12f64);
+    let w = 2f64 * ::std::f64::consts::PI * 1_000f64 / 44_100f64;
+    let dft = response.iter().enumerate().fold(Complex::new(0f64, 0f64), |sum, (n, sample)| {
+      sum + Complex::from_polar(sample, &(-w * n as f64))
+    });
+    assert!((dft - pinking.response_at(1_000f64, 44_100f64)).norm() < 1e-3f64);
+
+    pinking.clear();
+    assert_eq!(pinking.last_out(), 0f64);
+  }
+
+  #[test]
+  #[cfg(feature = "denormal-protection")]
+  fn denormals() {
+    // The slowest pole decays into the subnormal range after an impulse,
+    // and is flushed to zero instead
+    let mut pinking = PinkingFilter::<f32>::new();
+    pinking.process(1f32);
+    let tail: Vec<f32> = (0..200_000).map(|_| pinking.process(0f32)).collect();
+    assert!(tail.iter().all(|sample| *sample == 0f32 || sample.is_normal()));
+    assert_eq!(pinking.last_out(), 0f32);
+  }
+}
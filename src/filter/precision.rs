@@ -0,0 +1,135 @@
+use std::marker::PhantomData;
+
+use num;
+use num::traits::Float;
+
+use traits::Processor;
+
+/// Runs a filter at a higher internal precision than its input and output.
+///
+/// Each sample is converted to the filter's precision, `P`, processed, and
+/// converted back to the input precision, `T`. Low frequency, high Q biquads
+/// lose accuracy in `f32`, since their poles lie very close to the unit
+/// circle, which is avoided by computing both the coefficients and the
+/// filter memory in `f64` while keeping `f32` input and output.
+pub struct Precision<T, P, F> {
+  filter: F,
+  output: T,
+  precision: PhantomData<P>
+}
+
+impl<T, P, F> Precision<T, P, F> where T: Float, P: Float, F: Processor<P> {
+  /// Creates a new `Precision` running `filter`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::Precision;
+  /// use rasp::filter::rbj::LowPass;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = Precision::<f32, f64, _>::new(LowPass::new());
+  /// filter.filter_mut().set_coefficients(96_000f64, 20f64, 10f64);
+  /// let output: f32 = filter.process(0.5f32);
+  /// ```
+  pub fn new(filter: F) -> Self {
+    Precision {
+      filter,
+      output: num::zero(),
+      precision: PhantomData
+    }
+  }
+
+  /// Returns a reference to the filter.
+  pub fn filter(&self) -> &F {
+    &self.filter
+  }
+
+  /// Returns a mutable reference to the filter, to set its coefficients.
+  pub fn filter_mut(&mut self) -> &mut F {
+    &mut self.filter
+  }
+}
+
+impl<T, P, F> Processor<T> for Precision<T, P, F> where T: Float, P: Float, F: Processor<P> {
+  fn process(&mut self, sample: T) -> T {
+    let output = self.filter.process(num::cast(sample).unwrap());
+    self.output = num::cast(output).unwrap();
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.filter.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::rbj::{LowPass, Peak};
+  use filter::Biquad2;
+  use ::traits::Processor;
+
+  /// Returns the steady state amplitude of a sine at `frequency`.
+  fn amplitude<F: Processor<f32>>(filter: &mut F, sample_rate: f32, frequency: f32) -> f32 {
+    let length = 4 * sample_rate as usize;
+    let mut peak = 0f32;
+    for n in 0..length {
+      let phase = 2f64 * ::std::f64::consts::PI * (frequency * n as f32 / sample_rate) as f64;
+      let output = filter.process(phase.sin() as f32);
+      if n > length / 2 {
+        peak = peak.max(output.abs());
+      }
+    }
+    peak
+  }
+
+  #[test]
+  fn same_precision() {
+    let mut filter = Precision::<f32, f32, _>::new(Biquad2::new());
+    filter.filter_mut().set_coefficients(0.5f32, 0f32, 0f32, 0f32, 0f32);
+    assert!((filter.process(1f32) - 0.5f32).abs() < ::std::f32::EPSILON);
+
+    filter.clear();
+    assert!(filter.last_out().abs() < ::std::f32::EPSILON);
+  }
+
+  #[test]
+  fn low_frequency_lowpass() {
+    // With a Q of 10, the gain at the cutoff is 20 dB
+    let sample_rate = 96_000f32;
+    for frequency in [20f32, 30f32, 50f32].iter() {
+      let mut single = LowPass::<f32>::new();
+      single.set_coefficients(sample_rate, *frequency, 10f32);
+      let mut double = Precision::<f32, f64, _>::new(LowPass::new());
+      double.filter_mut().set_coefficients(sample_rate as f64, *frequency as f64, 10f64);
+
+      let single_error = (amplitude(&mut single, sample_rate, *frequency) - 10f32).abs();
+      let double_error = (amplitude(&mut double, sample_rate, *frequency) - 10f32).abs();
+      assert!(double_error < 1e-3f32);
+      assert!(double_error < single_error);
+    }
+  }
+
+  #[test]
+  fn low_frequency_peak() {
+    let sample_rate = 96_000f32;
+    let expected = 0.251_188_64f32; // -12 dB
+    for frequency in [20f32, 30f32, 50f32].iter() {
+      let mut single = Peak::<f32>::new();
+      single.set_coefficients(sample_rate, *frequency, -12f32, 20f32);
+      let mut double = Precision::<f32, f64, _>::new(Peak::new());
+      double.filter_mut().set_coefficients(sample_rate as f64, *frequency as f64, -12f64, 20f64);
+
+      let single_error = (amplitude(&mut single, sample_rate, *frequency) - expected).abs();
+      let double_error = (amplitude(&mut double, sample_rate, *frequency) - expected).abs();
+      assert!(double_error < 1e-3f32);
+      assert!(single_error > 1e-2f32);
+    }
+  }
+}
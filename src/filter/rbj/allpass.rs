@@ -1,6 +1,7 @@
 use num::traits::Float;
 
-use filter::Biquad2;
+use filter::{Biquad2, BiquadCoefficients};
+use filter::rbj::bandwidth_to_q;
 use traits::{FloatConst, Processor};
 
 /// An all-pass biquad filter.
@@ -26,6 +27,36 @@ impl<T> AllPass<T> where T: Float + FloatConst {
                           sample_rate: T,
                           phase_frequency: T,
                           q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, phase_frequency, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Set filter coefficients from a bandwidth in octaves instead of a Q
+  /// factor.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `phase_frequency`, and `bandwidth`. These values are not
+  /// validated.
+  pub fn set_coefficients_bw(&mut self,
+                             sample_rate: T,
+                             phase_frequency: T,
+                             bandwidth: T)
+  {
+    let coefficients = Self::coefficients_bw(sample_rate, phase_frequency, bandwidth);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      phase_frequency: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
   {
     let one: T = T::one();
     let two: T = T::two();
@@ -47,8 +78,18 @@ impl<T> AllPass<T> where T: Float + FloatConst {
     a1 = a1 / a0;
     a2 = a2 / a0;
 
-    self.biquad.set_coefficients(b0, b1, b2, a1, a2);
-    self.clear();
+    BiquadCoefficients { b0, b1, b2, a1, a2 }
+  }
+
+  /// Calculates filter coefficients from a bandwidth in octaves without
+  /// applying them.
+  pub fn coefficients_bw(sample_rate: T,
+                         phase_frequency: T,
+                         bandwidth: T)
+                         -> BiquadCoefficients<T>
+  {
+    let q = bandwidth_to_q(sample_rate, phase_frequency, bandwidth);
+    Self::coefficients(sample_rate, phase_frequency, q)
   }
 }
 
@@ -1,6 +1,7 @@
 use num::traits::Float;
 
-use filter::Biquad2;
+use filter::{Biquad2, BiquadCoefficients};
+use filter::rbj::bandwidth_to_q;
 use traits::{FloatConst, Processor};
 
 /// A band-pass biquad filter.
@@ -29,6 +30,36 @@ impl<T> BandPass1<T> where T: Float + FloatConst {
                           sample_rate: T,
                           center_frequency: T,
                           q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, center_frequency, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Set filter coefficients from a bandwidth in octaves instead of a Q
+  /// factor.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `center_frequency`, and `bandwidth`. These values are not
+  /// validated.
+  pub fn set_coefficients_bw(&mut self,
+                             sample_rate: T,
+                             center_frequency: T,
+                             bandwidth: T)
+  {
+    let coefficients = Self::coefficients_bw(sample_rate, center_frequency, bandwidth);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      center_frequency: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
   {
     let one: T = T::one();
     let two: T = T::two();
@@ -50,9 +81,19 @@ impl<T> BandPass1<T> where T: Float + FloatConst {
     a1 = a1 / a0;
     a2 = a2 / a0;
 
-    self.biquad.set_coefficients(b0, b1, b2, a1, a2);
-    self.clear();
+    BiquadCoefficients { b0, b1, b2, a1, a2 }
   }  
+
+  /// Calculates filter coefficients from a bandwidth in octaves without
+  /// applying them.
+  pub fn coefficients_bw(sample_rate: T,
+                         center_frequency: T,
+                         bandwidth: T)
+                         -> BiquadCoefficients<T>
+  {
+    let q = bandwidth_to_q(sample_rate, center_frequency, bandwidth);
+    Self::coefficients(sample_rate, center_frequency, q)
+  }
 }
 
 impl<T> Processor<T> for BandPass1<T> where T: Float {
@@ -94,6 +135,36 @@ impl<T> BandPass2<T> where T: Float + FloatConst {
                           sample_rate: T,
                           center_frequency: T,
                           q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, center_frequency, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Set filter coefficients from a bandwidth in octaves instead of a Q
+  /// factor.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `center_frequency`, and `bandwidth`. These values are not
+  /// validated.
+  pub fn set_coefficients_bw(&mut self,
+                             sample_rate: T,
+                             center_frequency: T,
+                             bandwidth: T)
+  {
+    let coefficients = Self::coefficients_bw(sample_rate, center_frequency, bandwidth);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      center_frequency: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
   {
     let one: T = T::one();
     let two: T = T::two();
@@ -115,8 +186,18 @@ impl<T> BandPass2<T> where T: Float + FloatConst {
     a1 = a1 / a0;
     a2 = a2 / a0;
 
-    self.biquad.set_coefficients(b0, b1, b2, a1, a2);
-    self.clear();
+    BiquadCoefficients { b0, b1, b2, a1, a2 }
+  }
+
+  /// Calculates filter coefficients from a bandwidth in octaves without
+  /// applying them.
+  pub fn coefficients_bw(sample_rate: T,
+                         center_frequency: T,
+                         bandwidth: T)
+                         -> BiquadCoefficients<T>
+  {
+    let q = bandwidth_to_q(sample_rate, center_frequency, bandwidth);
+    Self::coefficients(sample_rate, center_frequency, q)
   }
 }
 
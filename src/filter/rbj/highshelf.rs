@@ -1,7 +1,7 @@
 use num;
 use num::traits::Float;
 
-use filter::Biquad2;
+use filter::{Biquad2, BiquadCoefficients};
 use traits::{FloatConst, Processor};
 
 /// A high-shelf biquad filter.
@@ -22,12 +22,43 @@ impl<T> HighShelf<T> where T: Float + FloatConst {
   /// `Biquad2` coefficients are calculated from the `sample_rate`,
   /// `cutoff_frequency`, `db_gain`, and `shelf_slope` factor. These values
   /// are not validated.
-  // TODO: Explain value ranges of parameters
+  ///
+  /// The shelf slope `S` sets the steepness of the transition, in place of
+  /// a Q factor. A slope of one is the steepest that stays monotonic, and
+  /// greater slopes overshoot either side of the transition.
   pub fn set_coefficients(&mut self,
                           sample_rate: T,
                           cutoff_frequency: T,
                           db_gain: T,
                           shelf_slope: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, cutoff_frequency, db_gain, shelf_slope);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Set filter coefficients from a shelf slope.
+  ///
+  /// This is the same as `set_coefficients()`, named for symmetry with the
+  /// bandwidth parameterization of the other cookbook filters.
+  pub fn set_coefficients_slope(&mut self,
+                                sample_rate: T,
+                                cutoff_frequency: T,
+                                db_gain: T,
+                                shelf_slope: T)
+  {
+    self.set_coefficients(sample_rate, cutoff_frequency, db_gain, shelf_slope);
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      cutoff_frequency: T,
+                      db_gain: T,
+                      shelf_slope: T)
+                      -> BiquadCoefficients<T>
   {
     let one: T = T::one();
     let two: T = T::two();
@@ -57,8 +88,7 @@ impl<T> HighShelf<T> where T: Float + FloatConst {
     a1 = a1 / a0;
     a2 = a2 / a0;
 
-    self.biquad.set_coefficients(b0, b1, b2, a1, a2);
-    self.clear();
+    BiquadCoefficients { b0, b1, b2, a1, a2 }
   }
 }
 
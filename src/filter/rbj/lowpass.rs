@@ -1,6 +1,6 @@
 use num::traits::Float;
 
-use filter::Biquad2;
+use filter::{Biquad2, BiquadCoefficients};
 use traits::{FloatConst, Processor};
 
 /// A low-pass biquad filter.
@@ -26,6 +26,20 @@ impl<T> LowPass<T> where T: Float + FloatConst {
                           sample_rate: T,
                           cutoff_frequency: T,
                           q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, cutoff_frequency, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      cutoff_frequency: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
   {
     let one: T = T::one();
     let two: T = T::two();
@@ -47,8 +61,7 @@ impl<T> LowPass<T> where T: Float + FloatConst {
     a1 = a1 / a0;
     a2 = a2 / a0;
 
-    self.biquad.set_coefficients(b0, b1, b2, a1, a2);
-    self.clear();
+    BiquadCoefficients { b0, b1, b2, a1, a2 }
   }
 }
 
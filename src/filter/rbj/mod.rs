@@ -34,3 +34,171 @@ pub use self::highshelf::HighShelf as HighShelf;
 pub use self::lowpass::LowPass     as LowPass;
 pub use self::lowshelf::LowShelf   as LowShelf;
 pub use self::peak::Peak           as Peak;
+
+use num;
+use num::traits::Float;
+
+use filter::BiquadCoefficients;
+use traits::FloatConst;
+
+/// Converts a `bandwidth` in octaves around `center_frequency` to the Q
+/// factor giving the same digital filter at `sample_rate`.
+///
+/// The bandwidth is measured between the -3 dB frequencies of band-pass and
+/// band-stop filters, and between the midpoint gain frequencies of peaking
+/// filters.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::rbj::{bandwidth_to_q, q_to_bandwidth};
+///
+/// let q = bandwidth_to_q(44_100f64, 1_000f64, 1f64);
+/// assert!((q_to_bandwidth(44_100f64, 1_000f64, q) - 1f64).abs() < 1e-12f64);
+/// ```
+pub fn bandwidth_to_q<T>(sample_rate: T, center_frequency: T, bandwidth: T) -> T
+  where T: Float + FloatConst
+{
+  let w0 = T::two() * T::pi() * center_frequency / sample_rate;
+  let ln_2 = T::two().ln();
+  T::one() / (T::two() * (ln_2 / T::two() * bandwidth * w0 / w0.sin()).sinh())
+}
+
+/// Converts a Q factor to the bandwidth in octaves around
+/// `center_frequency` giving the same digital filter at `sample_rate`.
+pub fn q_to_bandwidth<T>(sample_rate: T, center_frequency: T, q: T) -> T
+  where T: Float + FloatConst
+{
+  let w0 = T::two() * T::pi() * center_frequency / sample_rate;
+  let ln_2 = T::two().ln();
+  T::two() / ln_2 * (T::one() / (T::two() * q)).asinh() * w0.sin() / w0
+}
+
+/// Computes `steps` sets of coefficients for a ramp from the `start` to the
+/// `end` `(frequency, q)` parameters, using `design` to calculate each set.
+///
+/// The frequency is interpolated exponentially, so the ramp moves at a
+/// constant rate in octaves, while the Q factor is interpolated linearly.
+/// This allows a host to compute filter sweeps, such as one set of
+/// coefficients per block of an automation curve, ahead of processing.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::Biquad2;
+/// use rasp::filter::rbj::{ramp, LowPass};
+/// use rasp::traits::Processor;
+///
+/// let sample_rate = 44_100f32;
+/// let sweep = ramp((100f32, 0.7071f32), (10_000f32, 0.7071f32), 16,
+///                  |frequency, q| LowPass::coefficients(sample_rate, frequency, q));
+///
+/// let mut block = vec![0.5f32; 256];
+/// let mut biquad = Biquad2::new();
+/// for (coefficients, chunk) in sweep.iter().zip(block.chunks_mut(16)) {
+///   biquad.load_coefficients(*coefficients);
+///   biquad.process_block(chunk);
+/// }
+/// ```
+pub fn ramp<T, F>(start: (T, T), end: (T, T), steps: usize, design: F) -> Vec<BiquadCoefficients<T>>
+  where T: Float, F: Fn(T, T) -> BiquadCoefficients<T>
+{
+  let last: T = num::cast(steps.max(2) - 1).unwrap();
+  let ratio = end.0 / start.0;
+  (0..steps)
+    .map(|step| {
+      let position = num::cast::<usize, T>(step).unwrap() / last;
+      let frequency = start.0 * ratio.powf(position);
+      let q = start.1 + (end.1 - start.1) * position;
+      design(frequency, q)
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use traits::FrequencyResponse;
+
+  #[test]
+  fn ramp_endpoints() {
+    let sample_rate = 44_100f32;
+    let design = |frequency, q| LowPass::coefficients(sample_rate, frequency, q);
+    let sweep = ramp((100f32, 0.5f32), (10_000f32, 2f32), 5, design);
+    assert_eq!(sweep.len(), 5);
+    assert_eq!(sweep[0], LowPass::coefficients(sample_rate, 100f32, 0.5f32));
+    assert_eq!(sweep[4], LowPass::coefficients(sample_rate, 10_000f32, 2f32));
+
+    // Midway in octaves, and midway in Q
+    let middle = LowPass::coefficients(sample_rate, 1_000f32, 1.25f32);
+    assert!((sweep[2].b0 - middle.b0).abs() < 1e-6f32);
+    assert!((sweep[2].a1 - middle.a1).abs() < 1e-6f32);
+    assert!((sweep[2].a2 - middle.a2).abs() < 1e-6f32);
+
+    assert!(ramp((100f32, 1f32), (200f32, 1f32), 0, design).is_empty());
+  }
+
+  #[test]
+  fn coefficients_match_set_coefficients() {
+    use filter::Biquad2;
+    use traits::Processor;
+
+    let mut filter = Peak::new();
+    filter.set_coefficients(44_100f32, 1_000f32, 6f32, 2f32);
+    let mut biquad = Biquad2::new();
+    biquad.load_coefficients(Peak::coefficients(44_100f32, 1_000f32, 6f32, 2f32));
+
+    for n in 0..16 {
+      let sample = (n as f32 * 0.3f32).sin();
+      assert_eq!(filter.process(sample), biquad.process(sample));
+    }
+  }
+
+  #[test]
+  fn bandwidth() {
+    // An octave is close to a Q of 1.41 at low frequencies, where the
+    // digital and analog relations agree
+    let q = bandwidth_to_q(48_000f64, 100f64, 1f64);
+    assert!((q - 2f64.sqrt()).abs() < 1e-3f64);
+    assert!((q_to_bandwidth(48_000f64, 100f64, q) - 1f64).abs() < 1e-12f64);
+    // The bilinear transform compresses bands near Nyquist, so the same
+    // bandwidth needs a lower Q there
+    assert!(bandwidth_to_q(48_000f64, 10_000f64, 1f64) < q);
+
+    assert_eq!(BandPass2::coefficients_bw(48_000f64, 1_000f64, 2f64),
+               BandPass2::coefficients(48_000f64, 1_000f64, bandwidth_to_q(48_000f64, 1_000f64, 2f64)));
+
+    // The bandwidth of a peak spans the frequencies at half its gain in dB
+    let (low, high) = (1_000f64 / 2f64.sqrt(), 1_000f64 * 2f64.sqrt());
+    let gain = |frequency: f64| {
+      let coefficients = Peak::coefficients_bw(48_000f64, 1_000f64, 12f64, 1f64);
+      ::util::to_db(coefficients.magnitude_at(frequency, 48_000f64))
+    };
+    assert!((gain(1_000f64) - 12f64).abs() < 1e-9f64);
+    assert!((gain(low) - 6f64).abs() < 0.2f64 && (gain(high) - 6f64).abs() < 0.2f64);
+  }
+
+  #[test]
+  fn shelf_slope() {
+    use filter::Biquad2;
+    use traits::Processor;
+
+    // With a slope of one the shelf moves monotonically between its gains,
+    // and steeper slopes overshoot them
+    let magnitudes = |slope: f64| -> Vec<f64> {
+      let shelf = LowShelf::coefficients(48_000f64, 1_000f64, 12f64, slope);
+      (1..200).map(|n| ::util::to_db(shelf.magnitude_at(n as f64 * 100f64, 48_000f64))).collect()
+    };
+    assert!(magnitudes(1f64).windows(2).all(|pair| pair[1] <= pair[0] + 1e-9f64));
+    assert!(magnitudes(2f64).iter().any(|gain| *gain < -1e-3f64));
+
+    let mut filter = HighShelf::new();
+    filter.set_coefficients_slope(48_000f64, 1_000f64, -6f64, 0.5f64);
+    let mut biquad = Biquad2::new();
+    biquad.load_coefficients(HighShelf::coefficients(48_000f64, 1_000f64, -6f64, 0.5f64));
+    for n in 0..16 {
+      let sample = (n as f64 * 0.3f64).sin();
+      assert_eq!(filter.process(sample), biquad.process(sample));
+    }
+  }
+}
@@ -1,7 +1,8 @@
 use num;
 use num::traits::Float;
 
-use filter::Biquad2;
+use filter::{Biquad2, BiquadCoefficients};
+use filter::rbj::bandwidth_to_q;
 use traits::{FloatConst, Processor};
 
 /// A peaking biquad filter.
@@ -28,6 +29,38 @@ impl<T> Peak<T> where T: Float + FloatConst {
                           center_frequency: T,
                           db_gain: T,
                           q: T)
+  {
+    let coefficients = Self::coefficients(sample_rate, center_frequency, db_gain, q);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Set filter coefficients from a bandwidth in octaves instead of a Q
+  /// factor.
+  ///
+  /// `Biquad2` coefficients are calculated from the `sample_rate`,
+  /// `center_frequency`, `db_gain`, and `bandwidth`. These values are not
+  /// validated.
+  pub fn set_coefficients_bw(&mut self,
+                             sample_rate: T,
+                             center_frequency: T,
+                             db_gain: T,
+                             bandwidth: T)
+  {
+    let coefficients = Self::coefficients_bw(sample_rate, center_frequency, db_gain, bandwidth);
+    self.biquad.load_coefficients(coefficients);
+    self.clear();
+  }
+
+  /// Calculates filter coefficients without applying them.
+  ///
+  /// The parameters are the same as for `set_coefficients()`, so arrays of
+  /// coefficients can be computed ahead of processing.
+  pub fn coefficients(sample_rate: T,
+                      center_frequency: T,
+                      db_gain: T,
+                      q: T)
+                      -> BiquadCoefficients<T>
   {
     let one: T = T::one();
     let two: T = T::two();
@@ -52,8 +85,19 @@ impl<T> Peak<T> where T: Float + FloatConst {
     a1 = a1 / a0;
     a2 = a2 / a0;
 
-    self.biquad.set_coefficients(b0, b1, b2, a1, a2);
-    self.clear();
+    BiquadCoefficients { b0, b1, b2, a1, a2 }
+  }
+
+  /// Calculates filter coefficients from a bandwidth in octaves without
+  /// applying them.
+  pub fn coefficients_bw(sample_rate: T,
+                         center_frequency: T,
+                         db_gain: T,
+                         bandwidth: T)
+                         -> BiquadCoefficients<T>
+  {
+    let q = bandwidth_to_q(sample_rate, center_frequency, bandwidth);
+    Self::coefficients(sample_rate, center_frequency, db_gain, q)
   }
 }
 
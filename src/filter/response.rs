@@ -0,0 +1,235 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use traits::{FloatConst, Processor};
+
+/// Evaluates the transfer function with the given `numerator` and
+/// `denominator` coefficients, in increasing powers of `z^-1`, at
+/// `frequency`.
+pub fn evaluate<T>(numerator: &[T], denominator: &[T], frequency: T, sample_rate: T) -> Complex<T>
+  where T: Float + FloatConst
+{
+  let w = T::two() * T::pi() * frequency / sample_rate;
+  let z_inv = Complex::new(w.cos(), -w.sin());
+  let polynomial = |coefficients: &[T]| {
+    coefficients.iter().rev().fold(Complex::new(T::zero(), T::zero()), |sum, c| sum * z_inv + *c)
+  };
+  polynomial(numerator) / polynomial(denominator)
+}
+
+/// Returns the zeros and poles of the transfer function with the given
+/// `numerator` and `denominator` coefficients, in increasing powers of
+/// `z^-1`, up to second order.
+///
+/// Zeros at infinity, where the leading numerator coefficients are zero, are
+/// left out, as are the roots at the origin that cancel.
+pub fn zeros_and_poles<T: Float>(numerator: &[T], denominator: &[T]) -> (Vec<Complex<T>>, Vec<Complex<T>>) {
+  debug_assert!(numerator.len() <= 3 && denominator.len() <= 3);
+  let mut numerator = numerator.to_vec();
+  let mut denominator = denominator.to_vec();
+  let order = numerator.len().max(denominator.len());
+  numerator.resize(order, T::zero());
+  denominator.resize(order, T::zero());
+  while numerator.last() == Some(&T::zero()) && denominator.last() == Some(&T::zero()) {
+    numerator.pop();
+    denominator.pop();
+  }
+  (roots(&numerator), roots(&denominator))
+}
+
+/// Returns the roots of the polynomial in `z` with `coefficients` in
+/// decreasing powers, up to second order, leaving out roots at infinity.
+fn roots<T: Float>(coefficients: &[T]) -> Vec<Complex<T>> {
+  let zero = T::zero();
+  let two = T::one() + T::one();
+  let leading = coefficients.iter().position(|c| *c != zero).unwrap_or(coefficients.len());
+  match &coefficients[leading..] {
+    [b, c] => vec![Complex::new(-*c / *b, zero)],
+    [a, b, c] => {
+      let discriminant = *b * *b - two * two * *a * *c;
+      if discriminant < zero {
+        let re = -*b / (two * *a);
+        let im = (-discriminant).sqrt() / (two * *a);
+        vec![Complex::new(re, im), Complex::new(re, -im)]
+      }
+      else {
+        // Avoids cancellation between `b` and the root of the discriminant
+        let q = -(*b + b.signum() * discriminant.sqrt()) / two;
+        if q == zero {
+          vec![Complex::new(zero, zero); 2]
+        }
+        else {
+          vec![Complex::new(q / *a, zero), Complex::new(*c / q, zero)]
+        }
+      }
+    },
+    _ => Vec::new()
+  }
+}
+
+/// Renders the first `length` samples of the impulse response of
+/// `processor`.
+///
+/// The processor is cleared before rendering, so its previous input does not
+/// affect the response.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::{render_impulse_response, OnePole};
+///
+/// let mut filter = OnePole::new();
+/// filter.set_coefficients(0.5f32, -0.5f32);
+///
+/// let response = render_impulse_response(&mut filter, 3);
+/// assert_eq!(response, vec![0.5f32, 0.25f32, 0.125f32]);
+/// ```
+pub fn render_impulse_response<T, P>(processor: &mut P, length: usize) -> Vec<T>
+  where T: Float, P: Processor<T>
+{
+  processor.clear();
+  (0..length)
+    .map(|n| processor.process(if n == 0 { num::one() } else { num::zero() }))
+    .collect()
+}
+
+/// Renders the first `length` samples of the step response of `processor`.
+///
+/// The processor is cleared before rendering, so its previous input does not
+/// affect the response.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::{render_step_response, OnePole};
+///
+/// let mut filter = OnePole::new();
+/// filter.set_coefficients(0.5f32, -0.5f32);
+///
+/// let response = render_step_response(&mut filter, 3);
+/// assert_eq!(response, vec![0.5f32, 0.75f32, 0.875f32]);
+/// ```
+pub fn render_step_response<T, P>(processor: &mut P, length: usize) -> Vec<T>
+  where T: Float, P: Processor<T>
+{
+  processor.clear();
+  (0..length).map(|_| processor.process(num::one())).collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+  use delay::Delay;
+  use filter::{Biquad1, Biquad2, Fir, OnePole, OneZero, TwoPole, TwoZero};
+  use filter::BiquadCoefficients;
+  use filter::design::{elliptic, Band};
+  use ::traits::{FrequencyResponse, PoleZero, Processor};
+
+  /// Checks the response of `filter` against the transform of its impulse
+  /// response, which decays within 2000 samples.
+  fn check<P: Processor<f64> + FrequencyResponse<f64>>(filter: &mut P) {
+    let sample_rate = 48_000f64;
+    let impulse = render_impulse_response(filter, 2_000);
+    for frequency in [0f64, 100f64, 1_000f64, 11_000f64, 24_000f64].iter() {
+      let expected = impulse.iter().enumerate().fold(Complex::new(0f64, 0f64), |sum, (n, sample)| {
+        let w = 2f64 * ::std::f64::consts::PI * frequency * n as f64 / sample_rate;
+        sum + Complex::new(w.cos(), -w.sin()) * *sample
+      });
+      let response = filter.response_at(*frequency, sample_rate);
+      assert!((response - expected).norm() < 1e-9f64);
+      assert!((filter.magnitude_at(*frequency, sample_rate) - expected.norm()).abs() < 1e-9f64);
+    }
+  }
+
+  #[test]
+  fn impulse_response() {
+    let mut biquad = Biquad2::new();
+    biquad.set_coefficients(0.5f32, 0.4f32, 0.3f32, 0f32, 0f32);
+    biquad.process(1f32);
+
+    let response = render_impulse_response(&mut biquad, 5);
+    let expected = [0.5f32, 0.4f32, 0.3f32, 0f32, 0f32];
+    assert_eq!(response.len(), 5);
+    for (actual, expected) in response.iter().zip(expected.iter()) {
+      assert!((actual - expected).abs() < EPSILON);
+    }
+
+    assert!(render_impulse_response(&mut biquad, 0).is_empty());
+  }
+
+  #[test]
+  fn step_response() {
+    let mut delay = Delay::<f32>::new(2, 4);
+    let response = render_step_response(&mut delay, 4);
+    let expected = [0f32, 0f32, 1f32, 1f32];
+    for (actual, expected) in response.iter().zip(expected.iter()) {
+      assert!((actual - expected).abs() < EPSILON);
+    }
+  }
+
+  #[test]
+  fn frequency_response() {
+    let mut one_pole = OnePole::new();
+    one_pole.set_coefficients(0.3f64, -0.7f64);
+    check(&mut one_pole);
+    let mut one_zero = OneZero::new();
+    one_zero.set_coefficients(0.5f64, 0.5f64);
+    check(&mut one_zero);
+    let mut two_pole = TwoPole::new();
+    two_pole.set_coefficients(0.2f64, -1.2f64, 0.6f64);
+    check(&mut two_pole);
+    let mut two_zero = TwoZero::new();
+    two_zero.set_coefficients(0.25f64, -0.5f64, 0.25f64);
+    check(&mut two_zero);
+    let mut biquad1 = Biquad1::new();
+    biquad1.set_coefficients(0.2f64, 0.3f64, 0.1f64, -0.9f64, 0.4f64);
+    check(&mut biquad1);
+    let mut biquad2 = Biquad2::new();
+    biquad2.set_coefficients(0.2f64, 0.3f64, 0.1f64, -0.9f64, 0.4f64);
+    check(&mut biquad2);
+    check(&mut Fir::new(&[0.1f64, -0.4f64, 0.8f64, 0.3f64]));
+
+    // Averaging two samples delays by half a sample, an eighth of a period
+    // at a quarter of the sample rate
+    assert!((one_zero.phase_at(12_000f64, 48_000f64) + ::std::f64::consts::FRAC_PI_4).abs() < 1e-12f64);
+    let cascade = [one_zero.clone(), one_zero];
+    assert!((cascade.magnitude_at(12_000f64, 48_000f64) - 0.5f64).abs() < 1e-12f64);
+    assert!(cascade[..0].magnitude_at(12_000f64, 48_000f64) == 1f64);
+  }
+
+  #[test]
+  fn poles_and_zeros() {
+    // A resonator at a quarter of the sample rate, with zeros at DC and
+    // Nyquist
+    let resonator = BiquadCoefficients { b0: 1f64, b1: 0f64, b2: -1f64, a1: 0f64, a2: 0.64f64 };
+    let poles = resonator.poles();
+    assert!((poles[0] - Complex::new(0f64, 0.8f64)).norm() < 1e-12f64);
+    assert!((poles[1] - Complex::new(0f64, -0.8f64)).norm() < 1e-12f64);
+    let zeros = resonator.zeros();
+    assert!((zeros[0].re + 1f64).abs() < 1e-12f64 && (zeros[1].re - 1f64).abs() < 1e-12f64);
+    assert!(resonator.is_stable());
+    assert!(!BiquadCoefficients { a1: -2f64, a2: 1f64, ..resonator }.is_stable());
+
+    // A first-order section has one pole and one zero
+    let mut biquad = Biquad2::new();
+    biquad.set_coefficients(0.5f64, 0.5f64, 0f64, -0.2f64, 0f64);
+    assert_eq!(biquad.poles(), vec![Complex::new(0.2f64, 0f64)]);
+    assert_eq!(biquad.zeros(), vec![Complex::new(-1f64, 0f64)]);
+
+    let mut one_zero = OneZero::new();
+    one_zero.set_coefficients(0f64, 1f64);
+    assert!(one_zero.zeros().is_empty());
+    assert_eq!(one_zero.poles(), vec![Complex::new(0f64, 0f64)]);
+    let mut two_pole = TwoPole::new();
+    two_pole.set_coefficients(1f64, -1.5f64, 0.56f64);
+    assert_eq!(two_pole.zeros().len(), 2);
+    assert!((two_pole.poles()[0].re - 0.8f64).abs() < 1e-12f64);
+    assert!((two_pole.poles()[1].re - 0.7f64).abs() < 1e-12f64);
+
+    let (sections, _) = elliptic(Band::LowPass, 5, 48_000f64, 1_000f64, 1f64, 60f64).unwrap();
+    assert_eq!((sections.poles().len(), sections.zeros().len()), (5, 5));
+    assert!(sections.is_stable());
+  }
+}
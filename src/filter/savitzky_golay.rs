@@ -0,0 +1,224 @@
+use num;
+use num::traits::Float;
+
+/// A Savitzky-Golay smoothing filter.
+///
+/// Each sample is replaced by the value, at that sample, of a polynomial
+/// fitted by least squares to the window of samples around it. Peaks and
+/// slopes that the polynomial can follow keep their height and width, where a
+/// moving average of the same length flattens them, so it suits smoothing
+/// curves from an analysis, such as envelopes, pitch tracks, or spectra.
+///
+/// At either end of a curve, the window is kept inside the curve and the
+/// polynomial is evaluated off its center, so the output is as long as the
+/// input and is not pulled towards zero.
+pub struct SavitzkyGolay {
+  length: usize,
+  order: usize
+}
+
+impl SavitzkyGolay {
+  /// Creates a new `SavitzkyGolay` filter fitting a quadratic over 5
+  /// samples.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::SavitzkyGolay;
+  ///
+  /// // A quadratic is smoothed without any change
+  /// let curve: Vec<f32> = (0..16).map(|n| (n * n) as f32).collect();
+  /// let smoothed = SavitzkyGolay::new().smooth(&curve);
+  /// assert!(curve.iter().zip(smoothed.iter()).all(|(a, b)| (a - b).abs() < 1e-3f32));
+  /// ```
+  pub fn new() -> Self {
+    SavitzkyGolay {
+      length: 5,
+      order: 2
+    }
+  }
+
+  /// Sets the length of the window, in samples.
+  ///
+  /// `length` must be odd and greater than the polynomial order, else it is
+  /// not updated.
+  pub fn set_window(&mut self, length: usize) {
+    if length % 2 == 1 && length > self.order {
+      self.length = length;
+    }
+  }
+
+  /// Returns the length of the window, in samples.
+  pub fn get_window(&self) -> usize {
+    self.length
+  }
+
+  /// Sets the order of the fitted polynomial.
+  ///
+  /// `order` must be less than the length of the window, else it is not
+  /// updated. An order of zero or one is a moving average.
+  pub fn set_order(&mut self, order: usize) {
+    if order < self.length {
+      self.order = order;
+    }
+  }
+
+  /// Returns the order of the fitted polynomial.
+  pub fn get_order(&self) -> usize {
+    self.order
+  }
+
+  /// Returns the taps that smooth the center sample of a window.
+  ///
+  /// Loaded into a `Fir`, they smooth a stream of samples, delayed by half
+  /// the window length.
+  pub fn coefficients<T: Float>(&self) -> Vec<T> {
+    weights(self.length, self.order, self.length / 2).into_iter()
+      .map(|weight| num::cast(weight).unwrap())
+      .collect()
+  }
+
+  /// Returns a smoothed copy of `samples`.
+  ///
+  /// Curves shorter than the window are fitted over their whole length.
+  pub fn smooth<T: Float>(&self, samples: &[T]) -> Vec<T> {
+    let size = samples.len();
+    if size == 0 {
+      return Vec::new();
+    }
+    let length = self.length.min(size);
+    let order = self.order.min(length - 1);
+    let half = length / 2;
+    let center = weights(length, order, half);
+
+    (0..size)
+      .map(|n| {
+        let start = n.saturating_sub(half).min(size - length);
+        let position = n - start;
+        let edge;
+        let taps = if position == half {
+          &center
+        }
+        else {
+          edge = weights(length, order, position);
+          &edge
+        };
+        let value = taps.iter().zip(samples[start..start + length].iter())
+          .fold(0f64, |sum, (weight, sample)| sum + weight * num::cast::<T, f64>(*sample).unwrap());
+        num::cast(value).unwrap()
+      })
+      .collect()
+  }
+}
+
+/// Returns the weights that evaluate, at `position` in a window of `length`
+/// samples, the polynomial of `order` fitted to the window.
+#[allow(clippy::needless_range_loop)]
+fn weights(length: usize, order: usize, position: usize) -> Vec<f64> {
+  // Positions are centered and scaled to keep the normal equations well
+  // conditioned for long windows
+  let middle = (length - 1) as f64 / 2f64;
+  let scale = middle.max(1f64);
+  let x = |i: usize| (i as f64 - middle) / scale;
+  let terms = order + 1;
+
+  let mut matrix = vec![vec![0f64; terms]; terms];
+  for i in 0..length {
+    for j in 0..terms {
+      for k in 0..terms {
+        matrix[j][k] += x(i).powi((j + k) as i32);
+      }
+    }
+  }
+  let mut vector: Vec<f64> = (0..terms).map(|j| x(position).powi(j as i32)).collect();
+
+  // Cholesky decomposition, then forward and back substitution
+  for j in 0..terms {
+    for k in 0..j {
+      let l = matrix[j][k];
+      for i in j..terms {
+        matrix[i][j] -= matrix[i][k] * l;
+      }
+    }
+    let d = matrix[j][j].sqrt();
+    for i in j..terms {
+      matrix[i][j] /= d;
+    }
+  }
+  for i in 0..terms {
+    for k in 0..i {
+      vector[i] -= matrix[i][k] * vector[k];
+    }
+    vector[i] /= matrix[i][i];
+  }
+  for i in (0..terms).rev() {
+    for k in i + 1..terms {
+      vector[i] -= matrix[k][i] * vector[k];
+    }
+    vector[i] /= matrix[i][i];
+  }
+
+  (0..length)
+    .map(|i| (0..terms).fold(0f64, |sum, j| sum + x(i).powi(j as i32) * vector[j]))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn coefficients() {
+    // The classic quadratic window of 5 samples
+    let expected = [-3f64, 12f64, 17f64, 12f64, -3f64];
+    let taps: Vec<f64> = SavitzkyGolay::new().coefficients();
+    for (tap, expected) in taps.iter().zip(expected.iter()) {
+      assert!((tap - expected / 35f64).abs() < 1e-12f64);
+    }
+  }
+
+  #[test]
+  fn preserves_polynomials() {
+    let mut filter = SavitzkyGolay::new();
+    filter.set_window(11);
+    filter.set_order(3);
+    let curve: Vec<f64> = (0..40).map(|n| {
+      let x = n as f64 / 10f64;
+      0.5f64 - x + 0.25f64 * x * x - 0.1f64 * x * x * x
+    }).collect();
+    let smoothed = filter.smooth(&curve);
+    assert_eq!(smoothed.len(), curve.len());
+    assert!(curve.iter().zip(smoothed.iter()).all(|(a, b)| (a - b).abs() < 1e-9f64));
+
+    // Curves shorter than the window
+    let short = filter.smooth(&[1f64, 2f64, 4f64]);
+    assert!((short[0] - 1f64).abs() < 1e-9f64 && (short[2] - 4f64).abs() < 1e-9f64);
+    assert!(filter.smooth::<f64>(&[]).is_empty());
+  }
+
+  #[test]
+  fn preserves_peaks() {
+    let mut filter = SavitzkyGolay::new();
+    filter.set_window(9);
+    filter.set_order(4);
+    let peak: Vec<f64> = (0..41).map(|n| (-((n as f64 - 20f64) / 3f64).powi(2)).exp()).collect();
+    let smoothed = filter.smooth(&peak);
+
+    filter.set_order(0);
+    let averaged = filter.smooth(&peak);
+    assert!((smoothed[20] - 1f64).abs() < 0.02f64);
+    assert!(averaged[20] < 0.8f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut filter = SavitzkyGolay::new();
+    filter.set_window(4);
+    assert_eq!(filter.get_window(), 5);
+    filter.set_order(5);
+    assert_eq!(filter.get_order(), 2);
+    filter.set_order(1);
+    filter.set_window(1);
+    assert_eq!(filter.get_window(), 5);
+  }
+}
@@ -0,0 +1,250 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{Biquad1, BiquadCoefficients};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
+
+/// A biquad filter whose coefficients glide to new values.
+///
+/// Loading new coefficients into a biquad while it runs changes its output
+/// abruptly, which clicks, and sweeping a parameter in steps zippers. Here
+/// each new set of coefficients is reached by a linear ramp over a number of
+/// samples instead, starting from the current coefficients, even when a ramp
+/// is still under way.
+///
+/// The filter is a `Biquad1`, whose memory holds past inputs and outputs,
+/// so it stays consistent while the coefficients move. The feedback
+/// coefficients of stable biquads form a convex region, so every step of a
+/// ramp between two stable filters is stable too.
+#[derive(Clone)]
+pub struct SmoothedBiquad<T> {
+  filter: Biquad1<T>,
+  target: BiquadCoefficients<T>,
+  // The change of each coefficient per sample
+  step: BiquadCoefficients<T>,
+  ramp_length: usize,
+  remaining: usize
+}
+
+impl<T> SmoothedBiquad<T> where T: Float {
+  /// Creates a new `SmoothedBiquad`, ramping its coefficients over
+  /// `ramp_length` samples.
+  ///
+  /// The filter does not alter the input until coefficients are set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::SmoothedBiquad;
+  /// use rasp::filter::rbj::LowPass;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = SmoothedBiquad::new(64);
+  /// filter.load_coefficients(LowPass::coefficients(44_100f32, 1_000f32, 0.7071f32));
+  /// assert!(filter.is_ramping());
+  ///
+  /// let mut block = vec![0.5f32; 64];
+  /// filter.process_block(&mut block);
+  /// assert!(!filter.is_ramping());
+  /// ```
+  pub fn new(ramp_length: usize) -> Self {
+    let filter = Biquad1::new();
+    let target = BiquadCoefficients {
+      b0: filter.b0,
+      b1: filter.b1,
+      b2: filter.b2,
+      a1: filter.a1,
+      a2: filter.a2
+    };
+    SmoothedBiquad {
+      filter,
+      target,
+      step: BiquadCoefficients {
+        b0: num::zero(),
+        b1: num::zero(),
+        b2: num::zero(),
+        a1: num::zero(),
+        a2: num::zero()
+      },
+      ramp_length,
+      remaining: 0
+    }
+  }
+
+  /// Sets the number of samples each ramp takes, from the next set of
+  /// coefficients on. A length of zero changes coefficients at once.
+  pub fn set_ramp_length(&mut self, ramp_length: usize) {
+    self.ramp_length = ramp_length;
+  }
+
+  /// Returns the number of samples each ramp takes.
+  pub fn get_ramp_length(&self) -> usize {
+    self.ramp_length
+  }
+
+  /// Starts a ramp to new filter coefficients.
+  ///
+  /// `b1`, `b2` are feedforwards, or zeroes, and `a1`, `a2` are feedbacks,
+  /// or poles.
+  pub fn set_coefficients(&mut self, b0: T, b1: T, b2: T, a1: T, a2: T) {
+    self.load_coefficients(BiquadCoefficients { b0, b1, b2, a1, a2 });
+  }
+
+  /// Starts a ramp to the coefficients of a `BiquadCoefficients`.
+  pub fn load_coefficients(&mut self, coefficients: BiquadCoefficients<T>) {
+    self.target = coefficients;
+    if self.ramp_length == 0 {
+      self.filter.load_coefficients(coefficients);
+      self.remaining = 0;
+      return;
+    }
+
+    let length: T = num::cast(self.ramp_length).unwrap();
+    let current = self.get_coefficients();
+    self.step = BiquadCoefficients {
+      b0: (coefficients.b0 - current.b0) / length,
+      b1: (coefficients.b1 - current.b1) / length,
+      b2: (coefficients.b2 - current.b2) / length,
+      a1: (coefficients.a1 - current.a1) / length,
+      a2: (coefficients.a2 - current.a2) / length
+    };
+    self.remaining = self.ramp_length;
+  }
+
+  /// Returns the coefficients in use, which are between the previous and the
+  /// new coefficients while ramping.
+  pub fn get_coefficients(&self) -> BiquadCoefficients<T> {
+    BiquadCoefficients {
+      b0: self.filter.b0,
+      b1: self.filter.b1,
+      b2: self.filter.b2,
+      a1: self.filter.a1,
+      a2: self.filter.a2
+    }
+  }
+
+  /// Returns the coefficients being ramped to.
+  pub fn get_target(&self) -> BiquadCoefficients<T> {
+    self.target
+  }
+
+  /// Returns `true` while the coefficients are ramping.
+  pub fn is_ramping(&self) -> bool {
+    self.remaining > 0
+  }
+}
+
+impl<T> Processor<T> for SmoothedBiquad<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    if self.remaining > 0 {
+      self.remaining -= 1;
+      if self.remaining == 0 {
+        // The last step lands exactly on the target
+        self.filter.load_coefficients(self.target);
+      }
+      else {
+        let filter = &mut self.filter;
+        filter.b0 = filter.b0 + self.step.b0;
+        filter.b1 = filter.b1 + self.step.b1;
+        filter.b2 = filter.b2 + self.step.b2;
+        filter.a1 = filter.a1 + self.step.a1;
+        filter.a2 = filter.a2 + self.step.a2;
+      }
+    }
+    self.filter.process(sample)
+  }
+
+  fn clear(&mut self) {
+    self.filter.clear();
+  }
+
+  fn last_out(&self) -> T {
+    self.filter.last_out()
+  }
+}
+
+impl<T> StateSnapshot for SmoothedBiquad<T> where T: Float {
+  type State = [T; 6];
+
+  fn snapshot(&self) -> Self::State {
+    self.filter.snapshot()
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.filter.restore(state);
+  }
+}
+
+impl<T> FrequencyResponse<T> for SmoothedBiquad<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    self.get_coefficients().response_at(frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for SmoothedBiquad<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    self.get_coefficients().poles()
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    self.get_coefficients().zeros()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::rbj::LowPass;
+  use ::traits::Processor;
+
+  #[test]
+  fn ramp() {
+    let mut filter = SmoothedBiquad::new(4);
+    filter.set_coefficients(0f64, 0f64, 0f64, -0.4f64, 0f64);
+    assert_eq!(filter.get_coefficients().b0, 1f64);
+    let b0: Vec<f64> = (0..5).map(|_| {
+      filter.process(0f64);
+      filter.get_coefficients().b0
+    }).collect();
+    assert_eq!(b0, vec![0.75f64, 0.5f64, 0.25f64, 0f64, 0f64]);
+    assert_eq!(filter.get_coefficients(), filter.get_target());
+
+    // A new target ramps from wherever the coefficients are
+    filter.set_coefficients(1f64, 0f64, 0f64, 0f64, 0f64);
+    filter.process(0f64);
+    filter.process(0f64);
+    filter.set_coefficients(0f64, 0f64, 0f64, 0f64, 0f64);
+    filter.process(0f64);
+    assert!((filter.get_coefficients().b0 - 0.375f64).abs() < 1e-12f64);
+
+    filter.set_ramp_length(0);
+    filter.set_coefficients(2f64, 0f64, 0f64, 0f64, 0f64);
+    assert!(!filter.is_ramping());
+    assert_eq!(filter.process(1f64), 2f64);
+    assert_eq!(filter.get_ramp_length(), 0);
+  }
+
+  /// Returns the largest change between samples of a sine whose lowpass
+  /// cutoff is moved up halfway, ramping over `ramp_length` samples.
+  fn largest_jump(ramp_length: usize) -> f64 {
+    let sample_rate = 44_100f64;
+    let mut filter = SmoothedBiquad::new(0);
+    filter.load_coefficients(LowPass::coefficients(sample_rate, 200f64, 0.7f64));
+    filter.set_ramp_length(ramp_length);
+
+    let output: Vec<f64> = (0..4_000).map(|n| {
+      if n == 2_000 {
+        filter.load_coefficients(LowPass::coefficients(sample_rate, 5_000f64, 0.7f64));
+      }
+      filter.process((n as f64 * 0.05f64).sin())
+    }).collect();
+    output.windows(2).fold(0f64, |jump, pair| jump.max((pair[1] - pair[0]).abs()))
+  }
+
+  #[test]
+  fn click_free() {
+    // The ramp halves the step at the change of coefficients
+    assert!(largest_jump(512) < 0.6f64 * largest_jump(0));
+  }
+}
@@ -0,0 +1,203 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
+
+/// A cascade of second-order sections, processed in series, with an overall
+/// gain.
+///
+/// This is the form the IIR designers return higher order filters in. Each
+/// section is a `Biquad2`, and the gain is applied to the input of the first
+/// section. The frequency response, poles and zeros are those of the whole
+/// cascade.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SosCascade<T> {
+  sections: Vec<Biquad2<T>>,
+  gain: T,
+  output: T
+}
+
+impl<T> SosCascade<T> where T: Float {
+  /// Creates a new `SosCascade` without sections, and unity gain, that does
+  /// not alter the input signal.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::SosCascade;
+  /// use rasp::filter::rbj::LowPass;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut cascade = SosCascade::new();
+  /// cascade.push_section(LowPass::coefficients(48_000f32, 1_000f32, 0.54f32));
+  /// cascade.push_section(LowPass::coefficients(48_000f32, 1_000f32, 1.31f32));
+  /// cascade.set_gain(0.5f32);
+  ///
+  /// let output = cascade.process(1f32);
+  /// ```
+  pub fn new() -> Self {
+    SosCascade {
+      sections: Vec::new(),
+      gain: num::one(),
+      output: num::zero()
+    }
+  }
+
+  /// Appends a section with `coefficients` to the end of the cascade.
+  pub fn push_section(&mut self, coefficients: BiquadCoefficients<T>) {
+    let mut section = Biquad2::new();
+    section.load_coefficients(coefficients);
+    self.sections.push(section);
+  }
+
+  /// Sets the overall gain, as a linear multiplier.
+  pub fn set_gain(&mut self, gain: T) {
+    self.gain = gain;
+  }
+
+  /// Returns the overall gain, as a linear multiplier.
+  pub fn get_gain(&self) -> T {
+    self.gain
+  }
+
+  /// Returns the number of sections.
+  pub fn len(&self) -> usize {
+    self.sections.len()
+  }
+
+  /// Returns `true` if the cascade has no sections.
+  pub fn is_empty(&self) -> bool {
+    self.sections.is_empty()
+  }
+
+  /// Returns the sections, in processing order.
+  pub fn sections(&self) -> &[Biquad2<T>] {
+    &self.sections
+  }
+
+  /// Returns the coefficients of each section, in processing order, without
+  /// the overall gain.
+  pub fn coefficients(&self) -> Vec<BiquadCoefficients<T>> {
+    self.sections.iter()
+      .map(|section| BiquadCoefficients {
+        b0: section.b0,
+        b1: section.b1,
+        b2: section.b2,
+        a1: section.a1,
+        a2: section.a2
+      })
+      .collect()
+  }
+}
+
+impl<T> Default for SosCascade<T> where T: Float {
+  fn default() -> Self {
+    SosCascade::new()
+  }
+}
+
+impl<T> Processor<T> for SosCascade<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.output = self.sections.iter_mut().fold(sample * self.gain, |sample, section| section.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for section in self.sections.iter_mut() {
+      section.clear();
+    }
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> StateSnapshot for SosCascade<T> where T: Float {
+  type State = Vec<[T; 3]>;
+
+  fn snapshot(&self) -> Self::State {
+    self.sections.iter().map(|section| section.snapshot()).collect()
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    debug_assert!(state.len() == self.sections.len());
+    for (section, state) in self.sections.iter_mut().zip(state.iter()) {
+      section.restore(state);
+    }
+    self.output = self.sections.last().map(|section| section.last_out()).unwrap_or(self.output);
+  }
+}
+
+impl<T> FrequencyResponse<T> for SosCascade<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    self.sections[..].response_at(frequency, sample_rate) * self.gain
+  }
+}
+
+impl<T> PoleZero<T> for SosCascade<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    self.sections[..].poles()
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    self.sections[..].zeros()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::rbj::{HighPass, LowPass};
+
+  #[test]
+  fn cascade() {
+    let low = LowPass::coefficients(48_000f64, 1_000f64, 0.7f64);
+    let high = HighPass::coefficients(48_000f64, 100f64, 0.7f64);
+    let mut cascade = SosCascade::new();
+    assert!(cascade.is_empty());
+    assert_eq!(cascade.process(0.5f64), 0.5f64);
+    cascade.push_section(low);
+    cascade.push_section(high);
+    cascade.set_gain(2f64);
+    assert_eq!((cascade.len(), cascade.get_gain()), (2, 2f64));
+    assert_eq!(cascade.coefficients(), vec![low, high]);
+
+    // The cascade matches its sections processed in series
+    let mut first = Biquad2::new();
+    first.load_coefficients(low);
+    let mut second = Biquad2::new();
+    second.load_coefficients(high);
+    for n in 0..64 {
+      let sample = if n == 0 { 1f64 } else { 0f64 };
+      let expected = second.process(first.process(sample * 2f64));
+      assert!((cascade.process(sample) - expected).abs() < 1e-15f64);
+    }
+    assert_eq!(cascade.last_out(), second.last_out());
+
+    let state = cascade.snapshot();
+    let next = cascade.process(0f64);
+    cascade.restore(&state);
+    assert_eq!(cascade.process(0f64), next);
+    cascade.clear();
+    assert_eq!(cascade.process(0f64), 0f64);
+  }
+
+  #[test]
+  fn response() {
+    let mut cascade = SosCascade::new();
+    cascade.push_section(LowPass::coefficients(48_000f64, 1_000f64, 0.7f64));
+    cascade.push_section(HighPass::coefficients(48_000f64, 100f64, 0.7f64));
+    cascade.set_gain(0.5f64);
+    let sections = [cascade.sections()[0].clone(), cascade.sections()[1].clone()];
+    for frequency in [20f64, 300f64, 5_000f64].iter() {
+      let expected = sections[..].response_at(*frequency, 48_000f64) * 0.5f64;
+      assert!((cascade.response_at(*frequency, 48_000f64) - expected).norm() < 1e-15f64);
+    }
+    assert_eq!((cascade.poles().len(), cascade.zeros().len()), (4, 4));
+    assert!(cascade.is_stable());
+  }
+}
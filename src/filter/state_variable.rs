@@ -0,0 +1,211 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{SvfMode, SvfOutputs};
+use filter::response::evaluate;
+use traits::{FloatConst, FrequencyResponse, Processor, StateSnapshot};
+use util;
+
+/// A Chamberlin state variable filter.
+///
+/// The classic digital state variable filter, made of two integrators in a
+/// feedback loop, outputs simultaneous lowpass, bandpass, highpass, and notch
+/// responses from a single structure. It is cheap, but the cutoff is only
+/// accurate well below Nyquist, and the filter becomes unstable as the cutoff
+/// approaches a sixth of the sample rate at low Q. Use `SvfTpt` for high
+/// cutoff frequencies or audio rate modulation.
+///
+/// [Based on the derivation by Hal Chamberlin](http://www.musicdsp.org/showone.php?id=23)
+#[derive(Clone)]
+pub struct StateVariable<T> {
+  mode: SvfMode,
+  // Coefficients
+  f: T,
+  q1: T,
+  // Integrator memory
+  lowpass: T,
+  bandpass: T,
+  output: T
+}
+
+impl<T> StateVariable<T> where T: Float + FloatConst {
+  /// Creates a new `StateVariable` filter in lowpass mode.
+  ///
+  /// The filter will be initialized in a state that outputs silence.
+  /// `set_params()` must be called, with valid arguments, to make the filter
+  /// functional.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::filter::StateVariable;
+  ///
+  /// let mut filter1: StateVariable<f32> = StateVariable::new();
+  /// let mut filter2: StateVariable<f64> = StateVariable::new();
+  /// let mut filter3 = StateVariable::<f32>::new();
+  /// let mut filter4 = StateVariable::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    StateVariable {
+      mode: SvfMode::LowPass,
+      f: num::zero(),
+      q1: num::one(),
+      lowpass: num::zero(),
+      bandpass: num::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets filter coefficients from the `sample_rate`, `cutoff_frequency`,
+  /// and `q` factor.
+  ///
+  /// These values are not validated. The filter memory is not cleared, so
+  /// this may be called while processing.
+  pub fn set_params(&mut self, sample_rate: T, cutoff_frequency: T, q: T) {
+    self.f = T::two() * (T::pi() * cutoff_frequency / sample_rate).sin();
+    self.q1 = T::one() / q;
+  }
+
+  /// Sets the response output by `process()`.
+  pub fn set_mode(&mut self, mode: SvfMode) {
+    self.mode = mode;
+  }
+
+  /// Returns the response output by `process()`.
+  pub fn get_mode(&self) -> SvfMode {
+    self.mode
+  }
+
+  /// Processes a sample and returns every response.
+  pub fn tick(&mut self, sample: T) -> SvfOutputs<T> {
+    self.lowpass = util::flush_denormal(self.lowpass + self.f * self.bandpass);
+    let highpass = sample - self.lowpass - self.q1 * self.bandpass;
+    self.bandpass = util::flush_denormal(self.bandpass + self.f * highpass);
+
+    let outputs =
+      SvfOutputs {
+        lowpass: self.lowpass,
+        bandpass: self.bandpass,
+        highpass,
+        notch: highpass + self.lowpass
+      };
+
+    self.output =
+      match self.mode {
+        SvfMode::LowPass => outputs.lowpass,
+        SvfMode::BandPass => outputs.bandpass,
+        SvfMode::HighPass => outputs.highpass,
+        SvfMode::Notch => outputs.notch
+      };
+    outputs
+  }
+}
+
+impl<T> Processor<T> for StateVariable<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.tick(sample);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.lowpass = num::zero();
+    self.bandpass = num::zero();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for StateVariable<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    // The lowpass integrator reads the bandpass of the previous sample, which
+    // delays the lowpass response by a sample
+    let (f, fq, two) = (self.f, self.f * self.q1, T::two());
+    let f2 = f * f;
+    let denominator = [T::one(), f2 + fq - two, T::one() - fq];
+    match self.mode {
+      SvfMode::LowPass => evaluate(&[T::zero(), f2], &denominator, frequency, sample_rate),
+      SvfMode::BandPass => evaluate(&[f, -f], &denominator, frequency, sample_rate),
+      SvfMode::HighPass => evaluate(&[T::one(), -two, T::one()], &denominator, frequency, sample_rate),
+      SvfMode::Notch => evaluate(&[T::one(), f2 - two, T::one()], &denominator, frequency, sample_rate)
+    }
+  }
+}
+
+impl<T> StateSnapshot for StateVariable<T> where T: Float + FloatConst {
+  type State = [T; 3];
+
+  fn snapshot(&self) -> Self::State {
+    [self.lowpass, self.bandpass, self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.lowpass = state[0];
+    self.bandpass = state[1];
+    self.output = state[2];
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::tests::amplitude;
+  use ::traits::Processor;
+
+  #[test]
+  fn new() {
+    let mut filter = StateVariable::new();
+    assert!(filter.process(1f32).abs() < ::std::f32::EPSILON);
+  }
+
+  #[test]
+  fn responses() {
+    let sample_rate = 44_100f32;
+    let mut filter = StateVariable::new();
+    filter.set_params(sample_rate, 500f32, 1f32);
+
+    assert!(filter.magnitude_at(50f32, sample_rate) > 0.95f32);
+    assert!(filter.magnitude_at(5_000f32, sample_rate) < 0.02f32);
+
+    // At a Q of one, the cutoff is passed at unity gain
+    filter.set_mode(SvfMode::BandPass);
+    assert!((filter.magnitude_at(500f32, sample_rate) - 1f32).abs() < 2e-2f32);
+
+    filter.set_mode(SvfMode::HighPass);
+    assert!(filter.magnitude_at(50f32, sample_rate) < 0.02f32);
+    assert!(filter.magnitude_at(5_000f32, sample_rate) > 0.95f32);
+
+    filter.set_mode(SvfMode::Notch);
+    assert!(filter.magnitude_at(500f32, sample_rate) < 2e-2f32);
+  }
+
+  #[test]
+  fn response_matches_output() {
+    let sample_rate = 44_100f32;
+    let mut filter = StateVariable::new();
+    filter.set_params(sample_rate, 1_000f32, 2f32);
+    for mode in [SvfMode::LowPass, SvfMode::BandPass, SvfMode::HighPass, SvfMode::Notch].iter() {
+      filter.set_mode(*mode);
+      for frequency in [100f32, 950f32, 6_000f32].iter() {
+        let measured = amplitude(&mut filter, sample_rate, *frequency);
+        assert!((filter.magnitude_at(*frequency, sample_rate) - measured).abs() < 1e-2f32);
+      }
+    }
+  }
+
+  #[test]
+  fn simultaneous_outputs() {
+    let mut filter = StateVariable::new();
+    filter.set_params(44_100f32, 1_000f32, 0.7f32);
+    for n in 0..32 {
+      let sample = (n as f32 * 0.4f32).sin();
+      let outputs = filter.tick(sample);
+      assert!((outputs.notch - (outputs.lowpass + outputs.highpass)).abs() < 1e-6f32);
+      assert_eq!(filter.last_out(), outputs.lowpass);
+    }
+  }
+}
@@ -0,0 +1,356 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::response::evaluate;
+use traits::{FloatConst, FrequencyResponse, Processor, StateSnapshot};
+use util;
+
+/// The response output by a state variable filter's `process()`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SvfMode {
+  LowPass,
+  BandPass,
+  HighPass,
+  Notch
+}
+
+/// Every response of a state variable filter for one sample.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SvfOutputs<T> {
+  pub lowpass: T,
+  pub bandpass: T,
+  pub highpass: T,
+  pub notch: T
+}
+
+/// A zero-delay feedback state variable filter.
+///
+/// The analog state variable filter is discretized with trapezoidal
+/// integrators, following the topology-preserving transform. Its state is
+/// held as integrator memory rather than past outputs, so the cutoff can be
+/// changed every sample without transients or instability, and the cutoff
+/// is matched to its analog prototype up to Nyquist. It has the same outputs
+/// as the Chamberlin `StateVariable`, which is cheaper but becomes unstable at
+/// high cutoff frequencies.
+///
+/// [Based on the derivation by Andrew Simper](https://cytomic.com/files/dsp/SvfLinearTrapOptimised2.pdf)
+#[derive(Clone)]
+pub struct SvfTpt<T> {
+  sample_rate: T,
+  q: T,
+  mode: SvfMode,
+  // Coefficients
+  g: T,
+  k: T,
+  a1: T,
+  a2: T,
+  a3: T,
+  // Integrator memory
+  ic1eq: T,
+  ic2eq: T,
+  output: T
+}
+
+impl<T> SvfTpt<T> where T: Float + FloatConst {
+  /// Creates a new `SvfTpt` filter in lowpass mode.
+  ///
+  /// The filter will be initialized in a state that outputs silence.
+  /// `set_coefficients()` must be called, with valid arguments, to make the
+  /// filter functional.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::filter::SvfTpt;
+  ///
+  /// let mut filter1: SvfTpt<f32> = SvfTpt::new();
+  /// let mut filter2: SvfTpt<f64> = SvfTpt::new();
+  /// let mut filter3 = SvfTpt::<f32>::new();
+  /// let mut filter4 = SvfTpt::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    SvfTpt {
+      sample_rate: num::one(),
+      q: num::one(),
+      mode: SvfMode::LowPass,
+      g: num::zero(),
+      k: num::one(),
+      a1: num::one(),
+      a2: num::zero(),
+      a3: num::zero(),
+      ic1eq: num::zero(),
+      ic2eq: num::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets filter coefficients from the `sample_rate`, `cutoff_frequency`,
+  /// and `q` factor.
+  ///
+  /// The cutoff frequency is clipped to just below Nyquist. The filter
+  /// memory is not cleared, so this may be called while processing.
+  pub fn set_coefficients(&mut self, sample_rate: T, cutoff_frequency: T, q: T) {
+    self.sample_rate = sample_rate;
+    self.q = q;
+    self.set_cutoff(cutoff_frequency);
+  }
+
+  fn set_cutoff(&mut self, cutoff_frequency: T) {
+    let nyquist: T = self.sample_rate * num::cast(0.499f64).unwrap();
+    let cutoff = cutoff_frequency.max(T::zero()).min(nyquist);
+
+    let g = (T::pi() * cutoff / self.sample_rate).tan();
+    self.g = g;
+    self.k = T::one() / self.q;
+    self.a1 = T::one() / (T::one() + g * (g + self.k));
+    self.a2 = g * self.a1;
+    self.a3 = g * self.a2;
+  }
+
+  /// Sets the response output by `process()`.
+  pub fn set_mode(&mut self, mode: SvfMode) {
+    self.mode = mode;
+  }
+
+  /// Returns the response output by `process()`.
+  pub fn get_mode(&self) -> SvfMode {
+    self.mode
+  }
+
+  /// Processes a sample and returns every response.
+  pub fn tick(&mut self, sample: T) -> SvfOutputs<T> {
+    let two = T::two();
+    let v3 = sample - self.ic2eq;
+    let v1 = self.a1 * self.ic1eq + self.a2 * v3;
+    let v2 = self.ic2eq + self.a2 * self.ic1eq + self.a3 * v3;
+    self.ic1eq = util::flush_denormal(two * v1 - self.ic1eq);
+    self.ic2eq = util::flush_denormal(two * v2 - self.ic2eq);
+
+    let highpass = sample - self.k * v1 - v2;
+    let outputs =
+      SvfOutputs {
+        lowpass: v2,
+        bandpass: v1,
+        highpass,
+        notch: v2 + highpass
+      };
+
+    self.output =
+      match self.mode {
+        SvfMode::LowPass => outputs.lowpass,
+        SvfMode::BandPass => outputs.bandpass,
+        SvfMode::HighPass => outputs.highpass,
+        SvfMode::Notch => outputs.notch
+      };
+    outputs
+  }
+
+  /// Processes a contiguous sequence of samples in place, using a separate
+  /// cutoff frequency for each sample.
+  ///
+  /// This is safe for audio rate modulation. `cutoffs` must be as long as
+  /// `samples`, and the last cutoff is kept after processing. Returns the
+  /// last processed sample.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::SvfTpt;
+  ///
+  /// let sample_rate = 44_100f32;
+  /// let mut filter = SvfTpt::new();
+  /// filter.set_coefficients(sample_rate, 1_000f32, 4f32);
+  ///
+  /// // Sweep the cutoff from 100 Hz to 10 kHz
+  /// let cutoffs: Vec<f32> = (0..64).map(|n| 100f32 * 100f32.powf(n as f32 / 63f32)).collect();
+  /// let mut samples = vec![0.5f32; 64];
+  /// filter.modulate_cutoff(&mut samples, &cutoffs);
+  /// ```
+  pub fn modulate_cutoff(&mut self, samples: &mut [T], cutoffs: &[T]) -> T {
+    debug_assert_eq!(samples.len(), cutoffs.len());
+    for (sample, cutoff) in samples.iter_mut().zip(cutoffs.iter()) {
+      self.set_cutoff(*cutoff);
+      self.tick(*sample);
+      *sample = self.output;
+    }
+    self.output
+  }
+}
+
+impl<T> Processor<T> for SvfTpt<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    self.tick(sample);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.ic1eq = num::zero();
+    self.ic2eq = num::zero();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for SvfTpt<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    // The response of the current mode is the bilinear transform of the
+    // analog prototype, with the prewarped cutoff `g`
+    let (g, gk, two) = (self.g, self.g * self.k, T::two());
+    let g2 = g * g;
+    let denominator = [T::one() + gk + g2, two * (g2 - T::one()), T::one() - gk + g2];
+    let numerator =
+      match self.mode {
+        SvfMode::LowPass => [g2, two * g2, g2],
+        SvfMode::BandPass => [g, T::zero(), -g],
+        SvfMode::HighPass => [T::one(), -two, T::one()],
+        SvfMode::Notch => [T::one() + g2, two * (g2 - T::one()), T::one() + g2]
+      };
+    evaluate(&numerator, &denominator, frequency, sample_rate)
+  }
+}
+
+impl<T> StateSnapshot for SvfTpt<T> where T: Float + FloatConst {
+  type State = [T; 3];
+
+  fn snapshot(&self) -> Self::State {
+    [self.ic1eq, self.ic2eq, self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.ic1eq = state[0];
+    self.ic2eq = state[1];
+    self.output = state[2];
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+  use filter::StateVariable;
+  use filter::tests::amplitude;
+  use ::traits::Processor;
+
+  #[test]
+  fn responses() {
+    let sample_rate = 44_100f32;
+    let mut filter = SvfTpt::new();
+    filter.set_coefficients(sample_rate, 1_000f32, ::std::f32::consts::FRAC_1_SQRT_2);
+
+    // -3 dB at the cutoff for every response but the notch
+    let half_power = ::std::f32::consts::FRAC_1_SQRT_2;
+    assert!((filter.magnitude_at(1_000f32, sample_rate) - half_power).abs() < 1e-4f32);
+    assert!(filter.magnitude_at(10_000f32, sample_rate) < 0.02f32);
+
+    filter.set_mode(SvfMode::HighPass);
+    assert!((filter.magnitude_at(1_000f32, sample_rate) - half_power).abs() < 1e-4f32);
+    assert!(filter.magnitude_at(100f32, sample_rate) < 0.02f32);
+
+    filter.set_mode(SvfMode::BandPass);
+    filter.set_coefficients(sample_rate, 1_000f32, 1f32);
+    assert!((filter.magnitude_at(1_000f32, sample_rate) - 1f32).abs() < 1e-4f32);
+
+    filter.set_mode(SvfMode::Notch);
+    assert!(filter.magnitude_at(1_000f32, sample_rate) < 1e-4f32);
+  }
+
+  #[test]
+  fn response_matches_output() {
+    let sample_rate = 44_100f32;
+    let mut filter = SvfTpt::new();
+    filter.set_coefficients(sample_rate, 2_000f32, 4f32);
+    for mode in [SvfMode::LowPass, SvfMode::BandPass, SvfMode::HighPass, SvfMode::Notch].iter() {
+      filter.set_mode(*mode);
+      for frequency in [200f32, 1_900f32, 8_000f32].iter() {
+        let measured = amplitude(&mut filter, sample_rate, *frequency);
+        assert!((filter.magnitude_at(*frequency, sample_rate) - measured).abs() < 1e-2f32);
+      }
+    }
+  }
+
+  #[test]
+  fn cutoff_near_nyquist() {
+    // The cutoff is not warped at high frequencies
+    let sample_rate = 44_100f32;
+    let mut filter = SvfTpt::new();
+    filter.set_coefficients(sample_rate, 15_000f32, ::std::f32::consts::FRAC_1_SQRT_2);
+    let response = filter.magnitude_at(15_000f32, sample_rate);
+    assert!((response - ::std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-4f32);
+  }
+
+  #[test]
+  fn stable_where_chamberlin_is_not() {
+    let sample_rate = 44_100f32;
+    let mut tpt = SvfTpt::new();
+    tpt.set_coefficients(sample_rate, 15_000f32, 0.5f32);
+    let mut chamberlin = StateVariable::new();
+    chamberlin.set_params(sample_rate, 15_000f32, 0.5f32);
+
+    let mut impulse = 1f32;
+    for _ in 0..1_000 {
+      tpt.process(impulse);
+      chamberlin.process(impulse);
+      impulse = 0f32;
+    }
+    assert!(tpt.last_out().abs() < 1e-6f32);
+    // The Chamberlin output has grown without bound
+    let output = chamberlin.last_out();
+    assert!(output.is_nan() || output.abs() > 1e6f32);
+  }
+
+  #[test]
+  fn audio_rate_modulation_is_stable() {
+    let sample_rate = 44_100f32;
+    let length = sample_rate as usize;
+    let mut filter = SvfTpt::new();
+    filter.set_coefficients(sample_rate, 1_000f32, 20f32);
+
+    // Sweep the cutoff between 20 Hz and Nyquist at 3 kHz, with a resonant
+    // filter fed a full scale square wave
+    let cutoffs: Vec<f32> = (0..length)
+      .map(|n| {
+        let phase = (2f32 * PI * 3_000f32 * n as f32 / sample_rate).sin();
+        20f32 * 1_100f32.powf(0.5f32 + 0.5f32 * phase)
+      })
+      .collect();
+    let mut samples: Vec<f32> = (0..length)
+      .map(|n| if (n / 50) % 2 == 0 { 1f32 } else { -1f32 })
+      .collect();
+
+    for mode in [SvfMode::LowPass, SvfMode::BandPass, SvfMode::HighPass, SvfMode::Notch].iter() {
+      filter.set_mode(*mode);
+      filter.clear();
+      let mut block = samples.clone();
+      filter.modulate_cutoff(&mut block, &cutoffs);
+      for sample in block.iter() {
+        assert!(sample.is_finite() && sample.abs() < 100f32);
+      }
+    }
+
+    // The last cutoff is kept
+    filter.clear();
+    filter.modulate_cutoff(&mut samples[..1], &[100f32]);
+    filter.set_mode(SvfMode::LowPass);
+    assert!(filter.magnitude_at(5_000f32, sample_rate) < 1e-2f32);
+  }
+
+  #[test]
+  #[cfg(feature = "denormal-protection")]
+  fn denormals() {
+    // The integrators decay into the subnormal range after an impulse, and
+    // are flushed to zero instead
+    let mut filter = SvfTpt::new();
+    filter.set_coefficients(48_000f32, 1_000f32, 0.7f32);
+    filter.process(1f32);
+    for _ in 0..48_000 {
+      filter.process(0f32);
+      assert!(filter.snapshot()[..2].iter().all(|state| *state == 0f32 || state.is_normal()));
+    }
+    assert_eq!(filter.snapshot(), [0f32; 3]);
+  }
+}
@@ -1,7 +1,10 @@
 use num;
+use num::Complex;
 use num::traits::Float;
 
-use traits::Processor;
+use filter::response::{evaluate, zeros_and_poles};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
+use util;
 
 /// A single channel, two pole digital filter.
 ///
@@ -10,6 +13,7 @@ use traits::Processor;
 /// `y[n] = b0*x[n] - a1*y[n-1] - a2*x[n-2]`
 ///
 /// It has two feedback coefficients, `a1` and `a2`. 
+#[derive(Clone)]
 pub struct TwoPole<T> {
   y_z1: T,
   y_z2: T,
@@ -59,7 +63,7 @@ impl<T> Processor<T> for TwoPole<T> where T: Float {
   fn process(&mut self, sample: T) -> T {
     let output = self.b0 * sample - self.a1 * self.y_z1 - self.a2 * self.y_z2;
     self.y_z2 = self.y_z1;
-    self.y_z1 = output;
+    self.y_z1 = util::flush_denormal(output);
     output
   }
 
@@ -73,6 +77,35 @@ impl<T> Processor<T> for TwoPole<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for TwoPole<T> where T: Float {
+  type State = [T; 2];
+
+  fn snapshot(&self) -> Self::State {
+    [self.y_z1, self.y_z2]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.y_z1 = state[0];
+    self.y_z2 = state[1];
+  }
+}
+
+impl<T> FrequencyResponse<T> for TwoPole<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0], &[T::one(), self.a1, self.a2], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for TwoPole<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0], &[T::one(), self.a1, self.a2]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0], &[T::one(), self.a1, self.a2]).0
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -1,7 +1,9 @@
 use num;
+use num::Complex;
 use num::traits::Float;
 
-use traits::Processor;
+use filter::response::{evaluate, zeros_and_poles};
+use traits::{FloatConst, FrequencyResponse, PoleZero, Processor, StateSnapshot};
 
 /// A single channel, two zero digital filter.
 ///
@@ -10,6 +12,7 @@ use traits::Processor;
 /// `y[n] = b0*x[n] + b1*x[n-1] + b2*x[n-2]`
 ///
 /// It has two feedforward coefficients, `b1` and `b2`.
+#[derive(Clone)]
 pub struct TwoZero<T> {
   x_z1: T,
   x_z2: T,
@@ -75,6 +78,36 @@ impl<T> Processor<T> for TwoZero<T> where T: Float {
   }
 }
 
+impl<T> StateSnapshot for TwoZero<T> where T: Float {
+  type State = [T; 3];
+
+  fn snapshot(&self) -> Self::State {
+    [self.x_z1, self.x_z2, self.output]
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    self.x_z1 = state[0];
+    self.x_z2 = state[1];
+    self.output = state[2];
+  }
+}
+
+impl<T> FrequencyResponse<T> for TwoZero<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    evaluate(&[self.b0, self.b1, self.b2], &[T::one()], frequency, sample_rate)
+  }
+}
+
+impl<T> PoleZero<T> for TwoZero<T> where T: Float {
+  fn poles(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one()]).1
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    zeros_and_poles(&[self.b0, self.b1, self.b2], &[T::one()]).0
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -0,0 +1,171 @@
+use num;
+use num::Complex;
+use num::traits::Float;
+
+use filter::{Biquad1, BiquadCoefficients};
+use traits::{FloatConst, FrequencyResponse, Processor};
+
+/// The analog parameters of the shelf, `(frequency, gain, q)`, modelling the
+/// acoustic effect of the head.
+const SHELF: (f64, f64, f64) = (1_681.974450955533f64, 3.999843853973347f64, 0.7071752369554196f64);
+
+/// The exponent relating the gain of the shelf at its corner to its gain at
+/// high frequencies.
+const SHELF_CORNER: f64 = 0.4996667741545416f64;
+
+/// The analog parameters of the highpass, `(frequency, q)`, modelling the
+/// falling sensitivity of the ear to low frequencies.
+const HIGHPASS: (f64, f64) = (38.13547087602444f64, 0.5003270373238773f64);
+
+/// The K-weighting pre-filter of ITU-R BS.1770.
+///
+/// K-weighting is the first stage of every loudness measurement in LUFS: a
+/// high shelf of about +4 dB above 1.5 kHz, for the head, followed by a
+/// second-order highpass at 38 Hz, for the ear. The mean square of the
+/// weighted signal, summed over channels, is the loudness before its offset
+/// of -0.691 dB.
+///
+/// The standard only gives the coefficients at 48 kHz. At other sample
+/// rates, they are derived from the analog parameters the standard
+/// coefficients were designed from, which reproduces the standard
+/// coefficients at 48 kHz. The highpass is left unnormalized, as in the
+/// standard, so its gain at Nyquist is slightly above unity.
+pub struct KWeighting<T> {
+  shelf: Biquad1<T>,
+  highpass: Biquad1<T>,
+  output: T
+}
+
+impl<T> KWeighting<T> where T: Float + FloatConst {
+  /// Creates a new `KWeighting` for signals at `sample_rate`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::weighting::KWeighting;
+  /// use rasp::traits::{FrequencyResponse, Processor};
+  /// use rasp::util;
+  ///
+  /// let mut weighting = KWeighting::new(48_000f64);
+  /// assert!((util::to_db(weighting.magnitude_at(10_000f64, 48_000f64)) - 4f64).abs() < 0.5f64);
+  ///
+  /// let weighted = weighting.process(0.5f64);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut shelf = Biquad1::new();
+    shelf.load_coefficients(Self::shelf_coefficients(sample_rate));
+    let mut highpass = Biquad1::new();
+    highpass.load_coefficients(Self::highpass_coefficients(sample_rate));
+    KWeighting {
+      shelf,
+      highpass,
+      output: num::zero()
+    }
+  }
+
+  /// Returns the coefficients of the first stage, the high shelf, at
+  /// `sample_rate`.
+  pub fn shelf_coefficients(sample_rate: T) -> BiquadCoefficients<T> {
+    let (frequency, gain, q) = SHELF;
+    let k = (::std::f64::consts::PI * frequency / num::cast::<T, f64>(sample_rate).unwrap()).tan();
+    let high = 10f64.powf(gain / 20f64);
+    let corner = high.powf(SHELF_CORNER);
+    let a0 = 1f64 + k / q + k * k;
+    BiquadCoefficients {
+      b0: num::cast((high + corner * k / q + k * k) / a0).unwrap(),
+      b1: num::cast(2f64 * (k * k - high) / a0).unwrap(),
+      b2: num::cast((high - corner * k / q + k * k) / a0).unwrap(),
+      a1: num::cast(2f64 * (k * k - 1f64) / a0).unwrap(),
+      a2: num::cast((1f64 - k / q + k * k) / a0).unwrap()
+    }
+  }
+
+  /// Returns the coefficients of the second stage, the highpass, at
+  /// `sample_rate`.
+  pub fn highpass_coefficients(sample_rate: T) -> BiquadCoefficients<T> {
+    let (frequency, q) = HIGHPASS;
+    let k = (::std::f64::consts::PI * frequency / num::cast::<T, f64>(sample_rate).unwrap()).tan();
+    let a0 = 1f64 + k / q + k * k;
+    BiquadCoefficients {
+      b0: T::one(),
+      b1: -T::two(),
+      b2: T::one(),
+      a1: num::cast(2f64 * (k * k - 1f64) / a0).unwrap(),
+      a2: num::cast((1f64 - k / q + k * k) / a0).unwrap()
+    }
+  }
+}
+
+impl<T> Processor<T> for KWeighting<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.output = self.highpass.process(self.shelf.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.shelf.clear();
+    self.highpass.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+impl<T> FrequencyResponse<T> for KWeighting<T> where T: Float + FloatConst {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    self.shelf.response_at(frequency, sample_rate) * self.highpass.response_at(frequency, sample_rate)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use util;
+
+  #[test]
+  fn standard_coefficients() {
+    // The coefficients at 48 kHz, from table 1 and 2 of ITU-R BS.1770-4
+    let shelf = KWeighting::<f64>::shelf_coefficients(48_000f64);
+    let expected = [1.53512485958697f64, -2.69169618940638f64, 1.19839281085285f64, -1.69065929318241f64, 0.73248077421585f64];
+    for (a, b) in [shelf.b0, shelf.b1, shelf.b2, shelf.a1, shelf.a2].iter().zip(expected.iter()) {
+      assert!((a - b).abs() < 1e-9f64);
+    }
+
+    let highpass = KWeighting::<f64>::highpass_coefficients(48_000f64);
+    let expected = [1f64, -2f64, 1f64, -1.99004745483398f64, 0.99007225036621f64];
+    for (a, b) in [highpass.b0, highpass.b1, highpass.b2, highpass.a1, highpass.a2].iter().zip(expected.iter()) {
+      assert!((a - b).abs() < 1e-9f64);
+    }
+  }
+
+  #[test]
+  fn response() {
+    // The response is nearly the same at any sample rate, well below Nyquist
+    let reference = KWeighting::new(48_000f64);
+    for sample_rate in [44_100f64, 96_000f64].iter() {
+      let weighting = KWeighting::new(*sample_rate);
+      for frequency in [20f64, 100f64, 1_000f64, 4_000f64, 10_000f64].iter() {
+        let expected = util::to_db(reference.magnitude_at(*frequency, 48_000f64));
+        let db = util::to_db(weighting.magnitude_at(*frequency, *sample_rate));
+        assert!((db - expected).abs() < 0.1f64);
+      }
+    }
+
+    // The shelf lifts high frequencies, and the highpass cuts the lowest
+    let db = |frequency: f64| util::to_db(reference.magnitude_at(frequency, 48_000f64));
+    assert!(db(20f64) < -10f64);
+    assert!(db(1_000f64).abs() < 1f64);
+    assert!((db(10_000f64) - 4f64).abs() < 0.5f64);
+  }
+
+  #[test]
+  fn memory() {
+    let mut weighting = KWeighting::new(48_000f32);
+    let output = weighting.process(1f32);
+    assert_eq!(weighting.last_out(), output);
+    weighting.clear();
+    assert_eq!(weighting.last_out(), 0f32);
+  }
+}
@@ -0,0 +1,9 @@
+//! Standardized frequency weighting filters.
+//!
+//! These filters shape a signal to match a standard before it is measured,
+//! such as the K-weighting of loudness meters, rather than to shape its
+//! sound.
+
+mod k_weighting;
+
+pub use self::k_weighting::KWeighting as KWeighting;
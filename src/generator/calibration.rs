@@ -0,0 +1,280 @@
+use num;
+use num::traits::Float;
+
+use filter::{Biquad2, PinkingFilter};
+use filter::rbj::{HighPass, LowPass};
+use traits::{FloatConst, Generator, Processor};
+use util;
+
+/// The frequency of standard reference tones, in Hz.
+const REFERENCE_FREQUENCY: f64 = 1_000f64;
+
+/// The number of samples a noise signal is measured over to calibrate its
+/// level.
+const CALIBRATION_LENGTH: usize = 1 << 16;
+
+/// The initial state of the noise sequence.
+const SEED: u32 = 0x9e37_79b9;
+
+/// The signal of a `Calibration` generator.
+#[derive(Clone, Copy)]
+enum Signal<T> {
+  Sine(T),
+  PinkNoise,
+  BandNoise(T, T)
+}
+
+/// A test tone generator with calibrated levels.
+///
+/// Sines are generated at an exact peak level. Noise is generated from a
+/// seeded pseudo-random sequence, and its level is calibrated by measuring
+/// the RMS level of that sequence, so the same generator always produces the
+/// same noise at the requested level. Pink noise falls 3 dB per octave, and
+/// band-limited noise is white noise passed through fourth order Butterworth
+/// high-pass and low-pass filters.
+///
+/// Levels follow AES17, so a full-scale sine is at 0 dBFS, and a `Meter`
+/// reads the RMS level of a sine 3 dB below its level in dBFS.
+pub struct Calibration<T> {
+  sample_rate: T,
+  signal: Signal<T>,
+  level: T,
+  gain: T,
+  phase: T,
+  random: u32,
+  pink: PinkingFilter<T>,
+  band: [Biquad2<T>; 4],
+  output: T
+}
+
+impl<T> Calibration<T> where T: Float + FloatConst {
+  /// Creates a new `Calibration` generating a sine at `frequency`, in Hz,
+  /// and `level`, in dBFS.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::generator::Calibration;
+  /// use rasp::traits::Generator;
+  ///
+  /// let mut tone = Calibration::sine(48_000f64, 12_000f64, 0f64);
+  /// let block: Vec<f64> = (0..4).map(|_| tone.tick()).collect();
+  /// assert!((block[1] - 1f64).abs() < 1e-12f64);
+  /// ```
+  pub fn sine(sample_rate: T, frequency: T, level: T) -> Self {
+    Calibration::new(sample_rate, Signal::Sine(frequency), level)
+  }
+
+  /// Creates a new `Calibration` generating a 1 kHz sine at `level`, in
+  /// dBFS.
+  pub fn reference(sample_rate: T, level: T) -> Self {
+    Calibration::sine(sample_rate, num::cast(REFERENCE_FREQUENCY).unwrap(), level)
+  }
+
+  /// Creates a new `Calibration` generating the EBU line-up tone, a 1 kHz
+  /// sine at -18 dBFS.
+  pub fn ebu_reference(sample_rate: T) -> Self {
+    Calibration::reference(sample_rate, num::cast(-18f64).unwrap())
+  }
+
+  /// Creates a new `Calibration` generating the SMPTE reference tone, a
+  /// 1 kHz sine at -20 dBFS.
+  pub fn smpte_reference(sample_rate: T) -> Self {
+    Calibration::reference(sample_rate, num::cast(-20f64).unwrap())
+  }
+
+  /// Creates a new `Calibration` generating pink noise at `level`, in dBFS.
+  pub fn pink_noise(sample_rate: T, level: T) -> Self {
+    Calibration::new(sample_rate, Signal::PinkNoise, level)
+  }
+
+  /// Creates a new `Calibration` generating noise limited to the band
+  /// between `low` and `high` frequencies, in Hz, at `level`, in dBFS.
+  ///
+  /// `low` must be below `high`, and both below Nyquist.
+  pub fn band_noise(sample_rate: T, low: T, high: T, level: T) -> Self {
+    debug_assert!(low > T::zero() && low < high && high < sample_rate / T::two());
+    Calibration::new(sample_rate, Signal::BandNoise(low, high), level)
+  }
+
+  fn new(sample_rate: T, signal: Signal<T>, level: T) -> Self {
+    let mut band = [Biquad2::new(), Biquad2::new(), Biquad2::new(), Biquad2::new()];
+    if let Signal::BandNoise(low, high) = signal {
+      let q: T = num::cast(0.5f64.sqrt()).unwrap();
+      let highpass = HighPass::coefficients(sample_rate, low, q);
+      let lowpass = LowPass::coefficients(sample_rate, high, q);
+      for (n, biquad) in band.iter_mut().enumerate() {
+        biquad.load_coefficients(if n < 2 { highpass } else { lowpass });
+      }
+    }
+
+    let mut calibration = Calibration {
+      sample_rate,
+      signal,
+      level,
+      gain: num::one(),
+      phase: num::zero(),
+      random: SEED,
+      pink: PinkingFilter::new(),
+      band,
+      output: num::zero()
+    };
+    calibration.calibrate();
+    calibration.set_level(level);
+    calibration
+  }
+
+  /// Measures the RMS level of the noise sequence, scaling it so its RMS
+  /// level matches that of a full-scale sine.
+  fn calibrate(&mut self) {
+    if let Signal::Sine(_) = self.signal {
+      return;
+    }
+    self.gain = T::one();
+    let mut power = T::zero();
+    for _ in 0..CALIBRATION_LENGTH {
+      let sample = self.next_noise();
+      power = power + sample * sample;
+    }
+    let length: T = num::cast(CALIBRATION_LENGTH).unwrap();
+    let sine_rms: T = num::cast(0.5f64.sqrt()).unwrap();
+    self.gain = sine_rms / (power / length).sqrt();
+    self.clear();
+  }
+
+  /// Sets the level, in dBFS.
+  pub fn set_level(&mut self, level: T) {
+    self.level = level;
+  }
+
+  /// Returns the level, in dBFS.
+  pub fn get_level(&self) -> T {
+    self.level
+  }
+
+  /// Returns the next uniform sample, between -1 and 1, of the xorshift
+  /// sequence.
+  fn next_white(&mut self) -> T {
+    self.random ^= self.random << 13;
+    self.random ^= self.random >> 17;
+    self.random ^= self.random << 5;
+    let sample: T = num::cast(self.random).unwrap();
+    let scale: T = num::cast(u32::MAX).unwrap();
+    sample / scale * T::two() - T::one()
+  }
+
+  /// Returns the next noise sample, scaled by the calibrated gain.
+  fn next_noise(&mut self) -> T {
+    let white = self.next_white();
+    let noise =
+      match self.signal {
+        Signal::Sine(_) => white,
+        Signal::PinkNoise => self.pink.process(white),
+        Signal::BandNoise(_, _) => {
+          self.band.iter_mut().fold(white, |sample, biquad| biquad.process(sample))
+        }
+      };
+    noise * self.gain
+  }
+}
+
+impl<T> Generator<T> for Calibration<T> where T: Float + FloatConst {
+  fn tick(&mut self) -> T {
+    let amplitude = util::to_sample(self.level);
+    let sample =
+      match self.signal {
+        Signal::Sine(frequency) => {
+          let sample = (T::two() * T::pi() * self.phase).sin();
+          self.phase = (self.phase + frequency / self.sample_rate).fract();
+          sample
+        },
+        _ => self.next_noise()
+      };
+    self.output = sample * amplitude;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.phase = T::zero();
+    self.random = SEED;
+    self.pink.clear();
+    for biquad in self.band.iter_mut() {
+      biquad.clear();
+    }
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use bus::Meter;
+  use filter::Biquad2;
+  use filter::rbj::BandPass2;
+  use traits::StereoAnalyzer;
+
+  /// Returns the RMS level of the next second of `generator`, in dB.
+  fn rms(generator: &mut Calibration<f64>) -> f64 {
+    let power = (0..48_000).fold(0f64, |power, _| {
+      let sample = generator.tick();
+      power + sample * sample
+    });
+    util::to_db((power / 48_000f64).sqrt())
+  }
+
+  #[test]
+  fn sine_level() {
+    let mut tone = Calibration::ebu_reference(48_000f64);
+    let peak = (0..48_000).fold(0f64, |peak, _| peak.max(tone.tick().abs()));
+    assert!((util::to_db(peak) + 18f64).abs() < 1e-9f64);
+    assert!((rms(&mut Calibration::smpte_reference(48_000f64)) + 23.0103f64).abs() < 1e-3f64);
+
+    // The meter reads the RMS level of the tone, once it has settled
+    let mut meter = Meter::new(48_000f64);
+    let mut tone = Calibration::sine(48_000f64, 997f64, -18f64);
+    for _ in 0..144_000 {
+      let sample = tone.tick();
+      meter.process(sample, sample);
+    }
+    assert!((meter.reading().rms.0 + 21.0103f64).abs() < 0.05f64);
+  }
+
+  #[test]
+  fn noise_level() {
+    // Noise matches the RMS level of a sine at the same level
+    let mut pink = Calibration::pink_noise(48_000f64, -20f64);
+    assert!((rms(&mut pink) + 23.0103f64).abs() < 0.2f64);
+    let mut band = Calibration::band_noise(48_000f64, 500f64, 2_000f64, -20f64);
+    assert!((rms(&mut band) + 23.0103f64).abs() < 0.2f64);
+
+    // The sequence repeats once cleared
+    pink.clear();
+    let first: Vec<f64> = (0..8).map(|_| pink.tick()).collect();
+    pink.clear();
+    pink.set_level(-26f64);
+    assert_eq!(pink.get_level(), -26f64);
+    let second: Vec<f64> = (0..8).map(|_| pink.tick()).collect();
+    assert!(first.iter().zip(second.iter()).all(|(a, b)| (b / a - 0.5f64).abs() < 1e-2f64));
+  }
+
+  #[test]
+  fn pink_slope() {
+    // The spectral density falls by 3 dB per octave, so the power in bands
+    // an octave wide is the same at any frequency
+    let band_power = |center: f64| {
+      let mut noise = Calibration::pink_noise(48_000f64, 0f64);
+      let mut filter = Biquad2::new();
+      filter.load_coefficients(BandPass2::coefficients(48_000f64, center, 1.41f64));
+      let power = (0..192_000).fold(0f64, |power, _| {
+        let sample = filter.process(noise.tick());
+        power + sample * sample
+      });
+      10f64 * power.log10()
+    };
+    assert!((band_power(2_000f64) - band_power(250f64)).abs() < 0.5f64);
+  }
+}
@@ -0,0 +1,27 @@
+//! Signal generators.
+//!
+//! Generators produce samples without an input, one at a time with `tick()`,
+//! or a block at a time with `generate_block()`, from the `Generator` trait.
+//! Levels are given in dBFS, relative to a full-scale sine as in AES17, so a
+//! sine at -18 dBFS peaks at -18 dB, and noise at -18 dBFS has the same RMS
+//! level as that sine.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::generator::Calibration;
+//! use rasp::traits::Generator;
+//!
+//! // The EBU line-up tone, a 1 kHz sine at -18 dBFS
+//! let mut tone = Calibration::ebu_reference(48_000f32);
+//! let mut block = vec![0f32; 480];
+//! tone.generate_block(&mut block);
+//! assert!(block.iter().all(|sample| sample.abs() <= 0.126f32));
+//! ```
+
+mod calibration;
+mod oscillator;
+
+pub use self::calibration::Calibration as Calibration;
+pub use self::oscillator::Oscillator   as Oscillator;
+pub use self::oscillator::Waveform     as Waveform;
@@ -0,0 +1,229 @@
+use num;
+use num::traits::Float;
+
+use traits::{FloatConst, Generator};
+
+/// The waveform of an `Oscillator`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Waveform {
+  /// A pure tone, without any harmonics.
+  Sine,
+  /// Odd harmonics falling by 12 dB per octave.
+  Triangle,
+  /// Odd harmonics falling by 6 dB per octave.
+  Square,
+  /// Every harmonic, falling by 6 dB per octave.
+  Saw
+}
+
+/// Returns the polynomial correction of a unit step at the start of the
+/// cycle, at `phase`, for a phase increment of `increment`.
+fn blep<T: Float>(phase: T, increment: T) -> T {
+  if phase < increment {
+    let x = phase / increment;
+    x + x - x * x - T::one()
+  }
+  else if phase > T::one() - increment {
+    let x = (phase - T::one()) / increment;
+    x * x + x + x + T::one()
+  }
+  else {
+    T::zero()
+  }
+}
+
+/// A band-limited oscillator, for audio and low frequencies alike.
+///
+/// The discontinuities of the square and saw are smoothed over the samples
+/// either side of them with polynomial band-limited steps, or PolyBLEPs,
+/// which suppress most of the aliasing of the naive waveforms at little
+/// cost. The triangle, whose harmonics already fall by 12 dB per octave, and
+/// the sine are generated directly. Every waveform runs from -1 to 1, and
+/// starts its cycle rising through zero, except the square, which starts
+/// high, and the saw, which starts low.
+///
+/// The frequency can be changed every sample without any discontinuity, so
+/// the oscillator can be modulated, or used itself as an LFO.
+#[derive(Clone)]
+pub struct Oscillator<T> {
+  sample_rate: T,
+  waveform: Waveform,
+  frequency: T,
+  // The phase increment, in cycles per sample
+  increment: T,
+  // The phase, in cycles, from 0 to 1
+  phase: T,
+  output: T
+}
+
+impl<T> Oscillator<T> where T: Float + FloatConst {
+  /// Creates a new `Oscillator` generating `waveform` at `frequency`, in Hz.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::generator::{Oscillator, Waveform};
+  /// use rasp::traits::Generator;
+  ///
+  /// let mut oscillator = Oscillator::new(44_100f32, Waveform::Saw, 220f32);
+  /// oscillator.set_frequency(110f32);
+  ///
+  /// let mut block = vec![0f32; 64];
+  /// oscillator.generate_block(&mut block);
+  /// ```
+  pub fn new(sample_rate: T, waveform: Waveform, frequency: T) -> Self {
+    let mut oscillator = Oscillator {
+      sample_rate,
+      waveform,
+      frequency: T::zero(),
+      increment: T::zero(),
+      phase: T::zero(),
+      output: num::zero()
+    };
+    oscillator.set_frequency(frequency);
+    oscillator
+  }
+
+  /// Sets the waveform, keeping the phase.
+  pub fn set_waveform(&mut self, waveform: Waveform) {
+    self.waveform = waveform;
+  }
+
+  /// Returns the waveform.
+  pub fn get_waveform(&self) -> Waveform {
+    self.waveform
+  }
+
+  /// Sets the frequency, in Hz, clipped to between zero and just below
+  /// Nyquist.
+  pub fn set_frequency(&mut self, frequency: T) {
+    let nyquist: T = self.sample_rate * num::cast(0.499f64).unwrap();
+    self.frequency = frequency.max(T::zero()).min(nyquist);
+    self.increment = self.frequency / self.sample_rate;
+  }
+
+  /// Returns the frequency, in Hz.
+  pub fn get_frequency(&self) -> T {
+    self.frequency
+  }
+
+  /// Sets the phase of the next sample, in cycles, from 0 to 1.
+  ///
+  /// If `phase` is out of range, the phase is not updated.
+  pub fn set_phase(&mut self, phase: T) {
+    if phase >= T::zero() && phase < T::one() {
+      self.phase = phase;
+    }
+  }
+
+  /// Returns the phase of the next sample, in cycles.
+  pub fn get_phase(&self) -> T {
+    self.phase
+  }
+}
+
+impl<T> Generator<T> for Oscillator<T> where T: Float + FloatConst {
+  fn tick(&mut self) -> T {
+    let (phase, increment) = (self.phase, self.increment);
+    let half: T = num::cast(0.5f64).unwrap();
+    self.output =
+      match self.waveform {
+        Waveform::Sine => (T::two() * T::pi() * phase).sin(),
+        Waveform::Triangle => {
+          let (quarter, four): (T, T) = (num::cast(0.25f64).unwrap(), num::cast(4f64).unwrap());
+          T::one() - four * ((phase + quarter).fract() - half).abs()
+        },
+        Waveform::Square => {
+          let naive = if phase < half { T::one() } else { -T::one() };
+          naive + blep(phase, increment) - blep((phase + half).fract(), increment)
+        },
+        Waveform::Saw => T::two() * phase - T::one() - blep(phase, increment)
+      };
+    self.phase = (phase + increment).fract();
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.phase = T::zero();
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the amplitude of `frequency` in a second of `signal` at 48 kHz.
+  fn amplitude(signal: &[f64], frequency: f64) -> f64 {
+    let w = 2f64 * ::std::f64::consts::PI * frequency / 48_000f64;
+    let (re, im) = signal.iter().enumerate().fold((0f64, 0f64), |(re, im), (n, sample)| {
+      (re + sample * (w * n as f64).cos(), im - sample * (w * n as f64).sin())
+    });
+    2f64 * (re * re + im * im).sqrt() / signal.len() as f64
+  }
+
+  /// Returns a cycle of `waveform` at 1 kHz, 48 samples long.
+  fn cycle(waveform: Waveform) -> Vec<f64> {
+    let mut oscillator = Oscillator::new(48_000f64, waveform, 1_000f64);
+    (0..48).map(|_| oscillator.tick()).collect()
+  }
+
+  #[test]
+  fn waveforms() {
+    let sine = cycle(Waveform::Sine);
+    assert!(sine[0].abs() < 1e-12f64 && (sine[12] - 1f64).abs() < 1e-12f64);
+    let triangle = cycle(Waveform::Triangle);
+    assert_eq!((triangle[0], triangle[6], triangle[12]), (0f64, 0.5f64, 1f64));
+    assert!((triangle[36] + 1f64).abs() < 1e-12f64);
+
+    // The steps are only smoothed on the samples either side of them
+    let square = cycle(Waveform::Square);
+    assert!(square[1..23].iter().all(|sample| *sample == 1f64));
+    assert!(square[25..47].iter().all(|sample| *sample == -1f64));
+    let saw = cycle(Waveform::Saw);
+    assert!(saw[24].abs() < 1e-12f64);
+    assert!(saw[1..47].windows(2).all(|pair| pair[1] > pair[0]));
+
+    // Every waveform is centered on zero
+    for waveform in [Waveform::Sine, Waveform::Triangle, Waveform::Square, Waveform::Saw].iter() {
+      let samples = cycle(*waveform);
+      assert!((samples.iter().fold(0f64, |sum, sample| sum + sample) / 48f64).abs() < 1e-12f64);
+      assert!(samples.iter().all(|sample| sample.abs() <= 1f64));
+    }
+  }
+
+  #[test]
+  fn aliasing() {
+    // The ninth harmonic of a saw at 4.9 kHz folds back to 3.9 kHz, and is
+    // much quieter than for a naive saw
+    let mut oscillator = Oscillator::new(48_000f64, Waveform::Saw, 4_900f64);
+    let band_limited: Vec<f64> = (0..48_000).map(|_| oscillator.tick()).collect();
+    let naive: Vec<f64> = (0..48_000).map(|n| 2f64 * (4_900f64 * n as f64 / 48_000f64).fract() - 1f64).collect();
+    let alias = amplitude(&band_limited, 3_900f64);
+    assert!(alias < 0.25f64 * amplitude(&naive, 3_900f64));
+
+    // While the fundamental is kept
+    assert!((amplitude(&band_limited, 4_900f64) / amplitude(&naive, 4_900f64) - 1f64).abs() < 0.1f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut oscillator = Oscillator::new(44_100f32, Waveform::Sine, 30_000f32);
+    assert!(oscillator.get_frequency() < 22_050f32);
+    oscillator.set_frequency(-1f32);
+    assert_eq!(oscillator.get_frequency(), 0f32);
+    oscillator.set_waveform(Waveform::Square);
+    assert_eq!(oscillator.get_waveform(), Waveform::Square);
+
+    oscillator.set_phase(0.75f32);
+    oscillator.set_phase(1f32);
+    assert_eq!(oscillator.get_phase(), 0.75f32);
+    assert_eq!(oscillator.tick(), -1f32);
+    oscillator.clear();
+    assert_eq!((oscillator.get_phase(), oscillator.last_out()), (0f32, 0f32));
+  }
+}
@@ -1,8 +1,30 @@
+#![allow(clippy::new_without_default)]
+#![cfg_attr(test, allow(
+  clippy::excessive_precision,
+  clippy::legacy_numeric_constants,
+  clippy::needless_range_loop,
+  clippy::useless_vec
+))]
+
 extern crate num;
 
 pub mod analysis;
+pub mod bus;
 pub mod filter;
 pub mod delay;
+pub mod effects;
+pub mod envelope;
+pub mod generator;
+pub mod mastering;
+pub mod prelude;
+pub mod render;
+pub mod restore;
+pub mod sequencer;
+pub mod spatial;
 pub mod traits;
+pub mod transport;
 pub mod util;
+pub mod voice;
 pub mod window;
+
+mod fft;
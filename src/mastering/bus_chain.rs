@@ -0,0 +1,237 @@
+use num;
+use num::traits::Float;
+
+use filter::{EqBandType, ParametricEq};
+use mastering::{Compressor, Limiter};
+use traits::{FloatConst, Processor, StereoProcessor};
+
+/// The frequency of the low shelf of a `BusChain`, in Hz.
+const LOW_SHELF: f64 = 120f64;
+
+/// The frequency of the high shelf of a `BusChain`, in Hz.
+const HIGH_SHELF: f64 = 8_000f64;
+
+/// The settings of a `BusChain`.
+///
+/// Levels and gains are in dB, frequencies in Hz, and times in seconds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BusChainParams<T> {
+  /// The cutoff of the highpass removing rumble and DC
+  pub highpass: T,
+  /// The gain of the low shelf, at 120 Hz
+  pub low_shelf: T,
+  /// The gain of the high shelf, at 8 kHz
+  pub high_shelf: T,
+  /// The threshold of the compressor
+  pub threshold: T,
+  /// The ratio of the compressor
+  pub ratio: T,
+  /// The attack time of the compressor
+  pub attack: T,
+  /// The release time of the compressor
+  pub release: T,
+  /// The makeup gain after the compressor
+  pub makeup: T,
+  /// The ceiling of the limiter
+  pub ceiling: T,
+  /// The release time of the limiter
+  pub limiter_release: T
+}
+
+/// A preset mastering chain for a stereo bus.
+///
+/// Each channel runs through an equalizer, with a highpass at 30 Hz, and a
+/// low and a high shelf, then both run through a stereo linked `Compressor`,
+/// gently compressing at 2:1 from -18 dB, and a `Limiter` with a ceiling of
+/// -1 dB. Without any shelf gain, a quiet signal is only altered by the
+/// highpass, and no output sample exceeds the ceiling.
+///
+/// Every setting of the chain can be read and written at once as a
+/// `BusChainParams`, and the compressor and limiter can be reached to set
+/// them further, such as their knees.
+pub struct BusChain<T> {
+  eq: (ParametricEq<T>, ParametricEq<T>),
+  compressor: Compressor<T>,
+  limiter: Limiter<T>,
+  output: (T, T)
+}
+
+impl<T> BusChain<T> where T: Float + FloatConst {
+  /// Creates a new `BusChain` for signals at `sample_rate`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::mastering::BusChain;
+  /// use rasp::traits::StereoProcessor;
+  ///
+  /// let mut chain = BusChain::new(44_100f32);
+  /// let mut params = chain.params();
+  /// params.high_shelf = 2f32;
+  /// params.threshold = -24f32;
+  /// params.makeup = 4f32;
+  /// chain.set_params(&params);
+  /// assert_eq!(chain.params(), params);
+  ///
+  /// let (left, right) = chain.process(0.5f32, -0.25f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut eq = ParametricEq::new(sample_rate);
+    eq.push_band(EqBandType::HighPass, num::cast(30f64).unwrap(), T::zero(), num::cast(0.5f64.sqrt()).unwrap());
+    eq.push_band(EqBandType::LowShelf, num::cast(LOW_SHELF).unwrap(), T::zero(), T::one());
+    eq.push_band(EqBandType::HighShelf, num::cast(HIGH_SHELF).unwrap(), T::zero(), T::one());
+    BusChain {
+      eq: (eq.clone(), eq),
+      compressor: Compressor::new(sample_rate),
+      limiter: Limiter::new(sample_rate),
+      output: (T::zero(), T::zero())
+    }
+  }
+
+  /// Returns the current settings of the chain.
+  pub fn params(&self) -> BusChainParams<T> {
+    BusChainParams {
+      highpass: self.eq.0.get_frequency(0),
+      low_shelf: self.eq.0.get_gain(1),
+      high_shelf: self.eq.0.get_gain(2),
+      threshold: self.compressor.get_threshold(),
+      ratio: self.compressor.get_ratio(),
+      attack: self.compressor.get_attack(),
+      release: self.compressor.get_release(),
+      makeup: self.compressor.get_makeup(),
+      ceiling: self.limiter.get_ceiling(),
+      limiter_release: self.limiter.get_release()
+    }
+  }
+
+  /// Applies every setting of `params`.
+  ///
+  /// Each setting is checked as by the setter of its component, and invalid
+  /// settings are not updated.
+  pub fn set_params(&mut self, params: &BusChainParams<T>) {
+    for eq in [&mut self.eq.0, &mut self.eq.1].iter_mut() {
+      eq.set_frequency(0, params.highpass);
+      eq.set_gain(1, params.low_shelf);
+      eq.set_gain(2, params.high_shelf);
+    }
+    self.compressor.set_threshold(params.threshold);
+    self.compressor.set_ratio(params.ratio);
+    self.compressor.set_attack(params.attack);
+    self.compressor.set_release(params.release);
+    self.compressor.set_makeup(params.makeup);
+    self.limiter.set_ceiling(params.ceiling);
+    self.limiter.set_release(params.limiter_release);
+  }
+
+  /// Returns the compressor.
+  pub fn compressor(&self) -> &Compressor<T> {
+    &self.compressor
+  }
+
+  /// Returns the compressor, to set its knee.
+  pub fn compressor_mut(&mut self) -> &mut Compressor<T> {
+    &mut self.compressor
+  }
+
+  /// Returns the limiter.
+  pub fn limiter(&self) -> &Limiter<T> {
+    &self.limiter
+  }
+
+  /// Returns the limiter, to set its knee.
+  pub fn limiter_mut(&mut self) -> &mut Limiter<T> {
+    &mut self.limiter
+  }
+
+  /// Returns the current gain reduction of the compressor and the limiter,
+  /// in dB, as positive values.
+  pub fn get_gain_reduction(&self) -> (T, T) {
+    (self.compressor.get_gain_reduction(), self.limiter.get_gain_reduction())
+  }
+}
+
+impl<T> StereoProcessor<T> for BusChain<T> where T: Float + FloatConst {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    let (left, right) = (self.eq.0.process(left), self.eq.1.process(right));
+    let (left, right) = self.compressor.process(left, right);
+    self.output = self.limiter.process(left, right);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.eq.0.clear();
+    self.eq.1.clear();
+    self.compressor.clear();
+    self.limiter.clear();
+    self.output = (T::zero(), T::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use traits::FrequencyResponse;
+  use util;
+
+  #[test]
+  fn chain() {
+    // A loud tone is compressed, then limited under the ceiling
+    let sample_rate = 48_000f64;
+    let mut chain = BusChain::new(sample_rate);
+    let mut params = chain.params();
+    params.makeup = 12f64;
+    chain.set_params(&params);
+    let w = 2f64 * ::std::f64::consts::PI * 1_000f64 / sample_rate;
+    let ceiling = util::to_sample(chain.params().ceiling);
+    let mut peak = 0f64;
+    for n in 0..48_000 {
+      let sample = 2f64 * (w * n as f64).sin();
+      let (left, right) = chain.process(sample, sample);
+      assert!(left.abs() <= ceiling + 1e-12f64 && right == left);
+      if n >= 24_000 {
+        peak = peak.max(left.abs());
+      }
+    }
+    let (compressor, limiter) = chain.get_gain_reduction();
+    assert!(compressor > 5f64 && limiter > 0f64);
+    assert!(peak > 0.9f64 * ceiling);
+
+    // A quiet tone in the midrange passes through at its level
+    chain.clear();
+    params.makeup = 0f64;
+    chain.set_params(&params);
+    let mut peak = 0f64;
+    for n in 0..48_000 {
+      let (left, _) = chain.process(0.01f64 * (w * n as f64).sin(), 0f64);
+      if n >= 24_000 {
+        peak = peak.max(left.abs());
+      }
+    }
+    assert!((peak - 0.01f64).abs() < 1e-5f64);
+  }
+
+  #[test]
+  fn params() {
+    let mut chain = BusChain::new(44_100f32);
+    let defaults = chain.params();
+    assert_eq!((defaults.highpass, defaults.threshold, defaults.ratio), (30f32, -18f32, 2f32));
+    assert_eq!((defaults.ceiling, defaults.limiter_release), (-1f32, 0.05f32));
+
+    // Each setting reaches its component, and invalid settings are ignored
+    let params = BusChainParams { low_shelf: 3f32, ratio: 0f32, ceiling: -0.5f32, ..defaults };
+    chain.set_params(&params);
+    assert_eq!(chain.params(), BusChainParams { ratio: 2f32, ..params });
+    assert_eq!(chain.limiter().get_ceiling(), -0.5f32);
+    assert!((chain.eq.1.magnitude_at(20f32, 44_100f32) - chain.eq.0.magnitude_at(20f32, 44_100f32)).abs() < 1e-6f32);
+
+    chain.compressor_mut().set_knee(0f32);
+    chain.limiter_mut().set_knee(0f32);
+    assert_eq!((chain.compressor().get_knee(), chain.limiter().get_knee()), (0f32, 0f32));
+    chain.clear();
+    assert_eq!(chain.last_out(), (0f32, 0f32));
+  }
+}
@@ -0,0 +1,269 @@
+use num;
+use num::traits::Float;
+
+use mastering::{gain_reduction, time_coefficient};
+use traits::{FloatConst, StereoProcessor};
+use util;
+
+/// A stereo linked, soft knee compressor.
+///
+/// The level is the peak of both channels, so the stereo image does not
+/// shift as the gain changes. The gain reduction is computed from the level
+/// in dB by a soft knee curve, then smoothed by a one-pole rising over the
+/// attack time and falling over the release time, and applied to both
+/// channels along with the makeup gain.
+pub struct Compressor<T> {
+  sample_rate: T,
+  threshold: T,
+  ratio: T,
+  knee: T,
+  attack: T,
+  release: T,
+  makeup: T,
+  attack_coefficient: T,
+  release_coefficient: T,
+  // The smoothed gain reduction, in dB
+  reduction: T,
+  output: (T, T)
+}
+
+impl<T> Compressor<T> where T: Float + FloatConst {
+  /// Creates a new `Compressor` for signals at `sample_rate`, with a
+  /// threshold of -18 dB, a ratio of 2:1, a 6 dB knee, an attack of 10
+  /// milliseconds, a release of 100 milliseconds, and no makeup gain.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::mastering::Compressor;
+  /// use rasp::traits::StereoProcessor;
+  ///
+  /// let mut compressor = Compressor::new(44_100f32);
+  /// compressor.set_threshold(-24f32);
+  /// compressor.set_ratio(4f32);
+  /// compressor.set_makeup(6f32);
+  ///
+  /// let (left, right) = compressor.process(0.5f32, 0.25f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut compressor = Compressor {
+      sample_rate,
+      threshold: num::cast(-18f64).unwrap(),
+      ratio: T::two(),
+      knee: num::cast(6f64).unwrap(),
+      attack: num::cast(0.01f64).unwrap(),
+      release: num::cast(0.1f64).unwrap(),
+      makeup: T::zero(),
+      attack_coefficient: T::zero(),
+      release_coefficient: T::zero(),
+      reduction: T::zero(),
+      output: (T::zero(), T::zero())
+    };
+    compressor.attack_coefficient = time_coefficient(compressor.attack, sample_rate);
+    compressor.release_coefficient = time_coefficient(compressor.release, sample_rate);
+    compressor
+  }
+
+  /// Sets the level, in dB, above which the signal is compressed.
+  ///
+  /// `threshold` must be finite, else it is not updated.
+  pub fn set_threshold(&mut self, threshold: T) {
+    if threshold.is_finite() {
+      self.threshold = threshold;
+    }
+  }
+
+  /// Returns the level, in dB, above which the signal is compressed.
+  pub fn get_threshold(&self) -> T {
+    self.threshold
+  }
+
+  /// Sets the ratio of the change of the level above the threshold to the
+  /// change of the output.
+  ///
+  /// `ratio` must be at least one, else it is not updated. An infinite
+  /// ratio turns the compressor into a limiter.
+  pub fn set_ratio(&mut self, ratio: T) {
+    if ratio >= T::one() {
+      self.ratio = ratio;
+    }
+  }
+
+  /// Returns the ratio of the compression.
+  pub fn get_ratio(&self) -> T {
+    self.ratio
+  }
+
+  /// Sets the width of the knee, in dB, centered on the threshold.
+  ///
+  /// `knee` must be at least zero, else it is not updated. Without any
+  /// knee, the compression starts abruptly at the threshold.
+  pub fn set_knee(&mut self, knee: T) {
+    if knee >= T::zero() && knee.is_finite() {
+      self.knee = knee;
+    }
+  }
+
+  /// Returns the width of the knee, in dB.
+  pub fn get_knee(&self) -> T {
+    self.knee
+  }
+
+  /// Sets the time, in seconds, for the gain reduction to rise.
+  ///
+  /// `attack` must be positive, else it is not updated.
+  pub fn set_attack(&mut self, attack: T) {
+    if attack > T::zero() && attack.is_finite() {
+      self.attack = attack;
+      self.attack_coefficient = time_coefficient(attack, self.sample_rate);
+    }
+  }
+
+  /// Returns the attack time, in seconds.
+  pub fn get_attack(&self) -> T {
+    self.attack
+  }
+
+  /// Sets the time, in seconds, for the gain reduction to fall.
+  ///
+  /// `release` must be positive, else it is not updated.
+  pub fn set_release(&mut self, release: T) {
+    if release > T::zero() && release.is_finite() {
+      self.release = release;
+      self.release_coefficient = time_coefficient(release, self.sample_rate);
+    }
+  }
+
+  /// Returns the release time, in seconds.
+  pub fn get_release(&self) -> T {
+    self.release
+  }
+
+  /// Sets the gain, in dB, applied after the compression.
+  ///
+  /// `makeup` must be finite, else it is not updated.
+  pub fn set_makeup(&mut self, makeup: T) {
+    if makeup.is_finite() {
+      self.makeup = makeup;
+    }
+  }
+
+  /// Returns the gain, in dB, applied after the compression.
+  pub fn get_makeup(&self) -> T {
+    self.makeup
+  }
+
+  /// Returns the output level, in dB, of a steady signal at `level`, in dB,
+  /// before the makeup gain.
+  pub fn curve(&self, level: T) -> T {
+    level - gain_reduction(level, self.threshold, self.slope(), self.knee)
+  }
+
+  /// Returns the current gain reduction, in dB, as a positive value.
+  pub fn get_gain_reduction(&self) -> T {
+    self.reduction
+  }
+
+  fn slope(&self) -> T {
+    T::one() - self.ratio.recip()
+  }
+}
+
+impl<T> StereoProcessor<T> for Compressor<T> where T: Float + FloatConst {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    let level = util::to_db(left.abs().max(right.abs()));
+    let target = gain_reduction(level, self.threshold, self.slope(), self.knee);
+    let coefficient =
+      if target > self.reduction {
+        self.attack_coefficient
+      }
+      else {
+        self.release_coefficient
+      };
+    self.reduction = target + (self.reduction - target) * coefficient;
+
+    let gain = util::to_sample(self.makeup - self.reduction);
+    self.output = (left * gain, right * gain);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.reduction = T::zero();
+    self.output = (T::zero(), T::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn curve() {
+    let mut compressor = Compressor::new(48_000f64);
+    compressor.set_threshold(-20f64);
+    compressor.set_ratio(4f64);
+    compressor.set_knee(0f64);
+    assert_eq!(compressor.curve(-30f64), -30f64);
+    assert_eq!(compressor.curve(-4f64), -16f64);
+
+    // The steady gain reduction follows the curve
+    let sample = util::to_sample(-8f64);
+    for _ in 0..48_000 {
+      compressor.process(sample, -0.5f64 * sample);
+    }
+    assert!((compressor.get_gain_reduction() - 9f64).abs() < 1e-6f64);
+    let (left, right) = compressor.last_out();
+    assert!((util::to_db(left) - -17f64).abs() < 1e-6f64);
+    assert!((right / left + 0.5f64).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn ballistics() {
+    let sample_rate = 48_000f64;
+    let mut compressor = Compressor::new(sample_rate);
+    compressor.set_threshold(-40f64);
+    compressor.set_knee(0f64);
+    compressor.set_attack(0.01f64);
+    compressor.set_release(0.1f64);
+
+    // The reduction rises by 63% of its target over the attack time, and
+    // falls by as much over the release time
+    let steps = |compressor: &mut Compressor<f64>, sample: f64, time: f64| {
+      for _ in 0..(time * sample_rate) as usize {
+        compressor.process(sample, sample);
+      }
+      compressor.get_gain_reduction()
+    };
+    let target = 20f64;
+    let attacked = steps(&mut compressor, 1f64, 0.01f64);
+    assert!((attacked / target - (1f64 - (-1f64).exp())).abs() < 1e-3f64);
+    steps(&mut compressor, 1f64, 1f64);
+    let released = steps(&mut compressor, 0f64, 0.1f64);
+    assert!((released / target - (-1f64).exp()).abs() < 1e-3f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut compressor = Compressor::new(44_100f32);
+    compressor.set_threshold(::std::f32::NAN);
+    compressor.set_ratio(0.5f32);
+    compressor.set_knee(-1f32);
+    compressor.set_attack(0f32);
+    compressor.set_release(-1f32);
+    compressor.set_makeup(::std::f32::INFINITY);
+    assert_eq!((compressor.get_threshold(), compressor.get_ratio(), compressor.get_knee()), (-18f32, 2f32, 6f32));
+    assert_eq!((compressor.get_attack(), compressor.get_release()), (0.01f32, 0.1f32));
+    assert_eq!(compressor.get_makeup(), 0f32);
+
+    // Below the threshold, only the makeup gain is applied
+    compressor.set_makeup(6f32);
+    let (left, _) = compressor.process(0.01f32, 0f32);
+    assert!((left - 0.01f32 * util::to_sample(6f32)).abs() < 1e-6f32);
+    compressor.clear();
+    assert_eq!((compressor.last_out(), compressor.get_gain_reduction()), ((0f32, 0f32), 0f32));
+  }
+}
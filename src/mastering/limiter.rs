@@ -0,0 +1,177 @@
+use num;
+use num::traits::Float;
+
+use mastering::{gain_reduction, time_coefficient};
+use traits::{FloatConst, StereoProcessor};
+use util;
+
+/// A stereo linked, soft knee saturating peak limiter.
+///
+/// The gain reduction follows every peak at once, without any lookahead, so
+/// no sample of either channel ever leaves the limiter above the ceiling.
+/// The soft knee below the ceiling rounds off the peaks that are reduced
+/// within a single cycle, which then saturate smoothly like a soft clipper
+/// rather than being cut flat, while the release lets the reduction of
+/// longer passages recover without distorting them.
+pub struct Limiter<T> {
+  sample_rate: T,
+  ceiling: T,
+  knee: T,
+  release: T,
+  release_coefficient: T,
+  // The gain reduction, in dB
+  reduction: T,
+  output: (T, T)
+}
+
+impl<T> Limiter<T> where T: Float + FloatConst {
+  /// Creates a new `Limiter` for signals at `sample_rate`, with a ceiling
+  /// of -1 dB, a 3 dB knee, and a release of 50 milliseconds.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::mastering::Limiter;
+  /// use rasp::traits::StereoProcessor;
+  ///
+  /// let mut limiter = Limiter::new(44_100f32);
+  /// limiter.set_ceiling(-0.3f32);
+  ///
+  /// let (left, right) = limiter.process(2f32, -0.5f32);
+  /// assert!(left <= 1f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let release = num::cast(0.05f64).unwrap();
+    Limiter {
+      sample_rate,
+      ceiling: -T::one(),
+      knee: num::cast(3f64).unwrap(),
+      release,
+      release_coefficient: time_coefficient(release, sample_rate),
+      reduction: T::zero(),
+      output: (T::zero(), T::zero())
+    }
+  }
+
+  /// Sets the level, in dB, that the output never exceeds.
+  ///
+  /// `ceiling` must be finite, else it is not updated.
+  pub fn set_ceiling(&mut self, ceiling: T) {
+    if ceiling.is_finite() {
+      self.ceiling = ceiling;
+    }
+  }
+
+  /// Returns the level, in dB, that the output never exceeds.
+  pub fn get_ceiling(&self) -> T {
+    self.ceiling
+  }
+
+  /// Sets the width of the knee, in dB, centered on the ceiling.
+  ///
+  /// `knee` must be at least zero, else it is not updated. Without any
+  /// knee, the peaks are cut flat at the ceiling.
+  pub fn set_knee(&mut self, knee: T) {
+    if knee >= T::zero() && knee.is_finite() {
+      self.knee = knee;
+    }
+  }
+
+  /// Returns the width of the knee, in dB.
+  pub fn get_knee(&self) -> T {
+    self.knee
+  }
+
+  /// Sets the time, in seconds, for the gain reduction to recover.
+  ///
+  /// `release` must be positive, else it is not updated.
+  pub fn set_release(&mut self, release: T) {
+    if release > T::zero() && release.is_finite() {
+      self.release = release;
+      self.release_coefficient = time_coefficient(release, self.sample_rate);
+    }
+  }
+
+  /// Returns the release time, in seconds.
+  pub fn get_release(&self) -> T {
+    self.release
+  }
+
+  /// Returns the current gain reduction, in dB, as a positive value.
+  pub fn get_gain_reduction(&self) -> T {
+    self.reduction
+  }
+}
+
+impl<T> StereoProcessor<T> for Limiter<T> where T: Float + FloatConst {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    let level = util::to_db(left.abs().max(right.abs()));
+    let target = gain_reduction(level, self.ceiling, T::one(), self.knee);
+    self.reduction = target.max(target + (self.reduction - target) * self.release_coefficient);
+
+    let gain = util::to_sample(-self.reduction);
+    self.output = (left * gain, right * gain);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.reduction = T::zero();
+    self.output = (T::zero(), T::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ceiling() {
+    // No sample exceeds the ceiling, however loud the input
+    let mut limiter = Limiter::new(48_000f64);
+    let ceiling = util::to_sample(limiter.get_ceiling());
+    for n in 0..4_800 {
+      let sample = 8f64 * (0.05f64 * n as f64).sin() * (0.0013f64 * n as f64).cos();
+      let (left, right) = limiter.process(sample, -0.5f64 * sample);
+      assert!(left.abs() <= ceiling + 1e-12f64 && right.abs() <= ceiling + 1e-12f64);
+    }
+
+    // Quiet signals, below the knee, pass unaltered once the release is over
+    for _ in 0..48_000 {
+      limiter.process(0.5f64, 0.25f64);
+    }
+    let (left, right) = limiter.process(0.5f64, 0.25f64);
+    assert!((left - 0.5f64).abs() < 1e-6f64 && (right - 0.25f64).abs() < 1e-6f64);
+  }
+
+  #[test]
+  fn knee() {
+    // Within the knee, peaks are rounded off below the ceiling
+    let mut limiter = Limiter::new(48_000f64);
+    limiter.set_knee(6f64);
+    let (at_ceiling, _) = limiter.process(util::to_sample(-1f64), 0f64);
+    assert!((util::to_db(at_ceiling) - -1.75f64).abs() < 1e-9f64);
+
+    limiter.clear();
+    limiter.set_knee(0f64);
+    let (hard, _) = limiter.process(util::to_sample(-1f64), 0f64);
+    assert!((util::to_db(hard) - -1f64).abs() < 1e-9f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut limiter = Limiter::new(44_100f32);
+    limiter.set_ceiling(::std::f32::NAN);
+    limiter.set_knee(-1f32);
+    limiter.set_release(0f32);
+    assert_eq!((limiter.get_ceiling(), limiter.get_knee(), limiter.get_release()), (-1f32, 3f32, 0.05f32));
+
+    limiter.process(4f32, 4f32);
+    assert!(limiter.get_gain_reduction() > 13f32);
+    limiter.clear();
+    assert_eq!((limiter.last_out(), limiter.get_gain_reduction()), ((0f32, 0f32), 0f32));
+  }
+}
@@ -0,0 +1,70 @@
+//! Dynamics processing for the final stereo bus.
+//!
+//! A `Compressor` evens out the level of the mix, and a `Limiter` keeps its
+//! peaks under a ceiling. A `BusChain` combines them after an equalizer into
+//! a ready-made mastering chain, whose settings can be read and written as
+//! a whole with `BusChainParams`.
+//!
+//! Levels, thresholds and gains are given in dB, and times in seconds.
+
+use num;
+use num::traits::Float;
+
+mod bus_chain;
+mod compressor;
+mod limiter;
+
+pub use self::bus_chain::BusChain        as BusChain;
+pub use self::bus_chain::BusChainParams  as BusChainParams;
+pub use self::compressor::Compressor     as Compressor;
+pub use self::limiter::Limiter           as Limiter;
+
+/// Returns the gain reduction, in dB, of a gain computer at a `level`, in dB.
+///
+/// Above the `threshold`, the reduction grows by `slope` dB for each dB of
+/// level, which is `1 - 1 / ratio` for a compressor, and one for a limiter.
+/// The soft knee spreads the change of slope over `knee` dB centered on the
+/// threshold, following a quadratic.
+fn gain_reduction<T: Float>(level: T, threshold: T, slope: T, knee: T) -> T {
+  let two: T = num::cast(2f64).unwrap();
+  let overshoot = level - threshold;
+  if two * overshoot <= -knee {
+    T::zero()
+  }
+  else if two * overshoot < knee {
+    let depth = overshoot + knee / two;
+    slope * depth * depth / (two * knee)
+  }
+  else {
+    slope * overshoot
+  }
+}
+
+/// Returns the coefficient of a one-pole smoother settling over `time`
+/// seconds, for signals at `sample_rate`.
+fn time_coefficient<T: Float>(time: T, sample_rate: T) -> T {
+  (-T::one() / (time * sample_rate)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn knee() {
+    // A hard knee bends at the threshold
+    assert_eq!(gain_reduction(-30f64, -20f64, 0.5f64, 0f64), 0f64);
+    assert_eq!(gain_reduction(-10f64, -20f64, 0.5f64, 0f64), 5f64);
+
+    // A soft knee is continuous in level and slope at both of its ends
+    let (threshold, slope, knee) = (-20f64, 0.75f64, 10f64);
+    for edge in [-25f64, -15f64].iter() {
+      let below = gain_reduction(*edge - 1e-6f64, threshold, slope, knee);
+      let above = gain_reduction(*edge + 1e-6f64, threshold, slope, knee);
+      assert!((below - above).abs() < 1e-5f64);
+    }
+    assert_eq!(gain_reduction(-25f64, threshold, slope, knee), 0f64);
+    assert!((gain_reduction(-20f64, threshold, slope, knee) - 0.9375f64).abs() < 1e-12f64);
+    assert!((gain_reduction(-15f64, threshold, slope, knee) - 3.75f64).abs() < 1e-12f64);
+  }
+}
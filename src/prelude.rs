@@ -0,0 +1,44 @@
+//! The traits shared by every component, and the most used types, to import
+//! at once.
+//!
+//! The methods of the components, such as `process()`, belong to traits, so
+//! using any component takes a trait import besides its own. Importing the
+//! prelude brings every trait, along with the filters, delay, and transport
+//! that most chains start from.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::prelude::*;
+//!
+//! let sample_rate = 44_100f32;
+//! let mut lowpass = OnePole::new();
+//! lowpass.set_cutoff(Band::LowPass, sample_rate, 1_000f32);
+//! let mut delay = Delay::new(4, 8);
+//!
+//! let mut block = vec![1f32; 8];
+//! lowpass.process_block(&mut block);
+//! delay.process_block(&mut block);
+//! assert_eq!(block[..4], [0f32; 4]);
+//! assert!(lowpass.magnitude_at(1_000f32, sample_rate) < 1f32);
+//! ```
+
+pub use delay::Delay;
+pub use filter::design::Band;
+pub use filter::{Biquad1, Biquad2, BiquadCoefficients, EqBandType, OnePole, ParametricEq};
+pub use render::ProcessContext;
+pub use traits::{
+  Analyzer,
+  Envelope,
+  FloatConst,
+  FrequencyResponse,
+  Generator,
+  Panner,
+  PoleZero,
+  Processor,
+  StateSnapshot,
+  StereoAnalyzer,
+  StereoProcessor,
+  TappableDelayLine
+};
+pub use transport::Transport;
@@ -0,0 +1,366 @@
+//! Offline rendering of a signal chain.
+//!
+//! The renderer drives a chain block by block for a fixed duration, with a
+//! playing `Transport` that tempo synced components follow, as an audio host
+//! would in real time. The chain is any closure filling a block from the
+//! transport, so generators, sequencers, and processors are combined freely,
+//! and end to end tests compare its output directly. Chains can instead be
+//! given a `ProcessContext` for each block, with the sample rate, the length
+//! of the block, and the transport, which processors take through
+//! `Processor::process_context()`.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::envelope::Ar;
+//! use rasp::render;
+//! use rasp::sequencer::StepSequencer;
+//! use rasp::traits::Envelope;
+//!
+//! let mut sequencer = StepSequencer::new(44_100f32, 1, 4);
+//! sequencer.set_pattern(0, &[true, false]);
+//! let mut envelope = Ar::new();
+//!
+//! let output = render::offline(1f32, 44_100f32, |block: &mut [f32], transport| {
+//!   sequencer.sync(transport);
+//!   let events = sequencer.render(block.len());
+//!   envelope.render(block, &events[0]);
+//! });
+//! assert_eq!(output.len(), 44_100);
+//! assert!(output[0] > 0f32);
+//! ```
+
+use num;
+use num::traits::Float;
+
+use transport::Transport;
+
+/// The timing of a processed block, passed along a chain.
+///
+/// Components processing a block with a context read the sample rate, the
+/// length of the block, and the transport from it rather than from their own
+/// copies or from global state, so every component of a chain agrees on the
+/// timing of each block. The transport is `None` when the chain is not
+/// driven by a musical timeline, such as in a test. When `bypass` is set,
+/// `Processor::process_context()` leaves the block unaltered.
+#[derive(Clone, Copy)]
+pub struct ProcessContext<'a, T: 'a> {
+  /// The sample rate of the block
+  pub sample_rate: T,
+  /// The number of samples in the block
+  pub block_size: usize,
+  /// The transport at the start of the block, if any
+  pub transport: Option<&'a Transport<T>>,
+  /// Whether processing is bypassed for the block
+  pub bypass: bool
+}
+
+impl<'a, T> ProcessContext<'a, T> where T: Float {
+  /// Creates a new `ProcessContext` for a block of `block_size` samples at
+  /// `sample_rate`, without a transport and without bypass.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::OnePole;
+  /// use rasp::render::ProcessContext;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = OnePole::new();
+  /// filter.set_coefficients(0.5f32, 0.5f32);
+  ///
+  /// let mut block = vec![1f32; 4];
+  /// let mut context = ProcessContext::new(44_100f32, block.len());
+  /// context.bypass = true;
+  /// filter.process_context(&mut block, &context);
+  /// assert_eq!(block, vec![1f32; 4]);
+  /// ```
+  pub fn new(sample_rate: T, block_size: usize) -> Self {
+    ProcessContext {
+      sample_rate,
+      block_size,
+      transport: None,
+      bypass: false
+    }
+  }
+
+  /// Returns the length of the block, in seconds.
+  pub fn duration(&self) -> T {
+    num::cast::<usize, T>(self.block_size).unwrap() / self.sample_rate
+  }
+}
+
+/// The number of samples rendered at a time by `offline()`.
+pub const BLOCK_SIZE: usize = 64;
+
+/// Renders `duration` seconds of the chain `source` at `sample_rate`, in
+/// blocks of `BLOCK_SIZE` samples.
+///
+/// The transport starts playing from the first beat, at 120 beats per minute.
+/// Use an `OfflineRenderer` to change the block size or the transport.
+pub fn offline<T, F>(duration: T, sample_rate: T, source: F) -> Vec<T>
+  where T: Float, F: FnMut(&mut [T], &Transport<T>)
+{
+  OfflineRenderer::new(sample_rate).render(duration, source)
+}
+
+/// A renderer of signal chains, running its own transport.
+pub struct OfflineRenderer<T> {
+  sample_rate: T,
+  block_size: usize,
+  transport: Transport<T>
+}
+
+impl<T> OfflineRenderer<T> where T: Float {
+  /// Creates a new `OfflineRenderer` for signals at `sample_rate`, rendering
+  /// blocks of `BLOCK_SIZE` samples.
+  pub fn new(sample_rate: T) -> Self {
+    let mut transport = Transport::new(sample_rate);
+    transport.play();
+    OfflineRenderer {
+      sample_rate,
+      block_size: BLOCK_SIZE,
+      transport
+    }
+  }
+
+  /// Sets the number of samples rendered at a time.
+  ///
+  /// `block_size` must be at least one, else it is not updated.
+  pub fn set_block_size(&mut self, block_size: usize) {
+    if block_size >= 1 {
+      self.block_size = block_size;
+    }
+  }
+
+  /// Returns the number of samples rendered at a time.
+  pub fn get_block_size(&self) -> usize {
+    self.block_size
+  }
+
+  /// Returns the transport, to set its tempo, position, or loop region
+  /// before rendering.
+  pub fn transport(&mut self) -> &mut Transport<T> {
+    &mut self.transport
+  }
+
+  /// Returns the number of samples in `duration` seconds, rounded to the
+  /// nearest sample.
+  pub fn length(&self, duration: T) -> usize {
+    num::cast((duration * self.sample_rate).round()).unwrap_or(0)
+  }
+
+  /// Renders `duration` seconds of the chain `source`, which fills each
+  /// block it is given, and returns the rendered samples.
+  ///
+  /// The transport continues from where the last rendering stopped.
+  pub fn render<F>(&mut self, duration: T, source: F) -> Vec<T>
+    where F: FnMut(&mut [T], &Transport<T>)
+  {
+    let mut output = Vec::with_capacity(self.length(duration));
+    self.stream(duration, source, |block| output.extend_from_slice(block));
+    output
+  }
+
+  /// Renders `duration` seconds of the chain `source`, passing each block
+  /// to `sink` as it is rendered rather than keeping the whole signal.
+  ///
+  /// The last block is shorter when the length is not a multiple of the
+  /// block size.
+  pub fn stream<F, S>(&mut self, duration: T, mut source: F, sink: S)
+    where F: FnMut(&mut [T], &Transport<T>), S: FnMut(&[T])
+  {
+    self.stream_context(duration, |block, context| source(block, context.transport.unwrap()), sink);
+  }
+
+  /// Renders `duration` seconds of the chain `source`, which fills each
+  /// block it is given from the `ProcessContext` of the block, and returns
+  /// the rendered samples.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::OnePole;
+  /// use rasp::render::OfflineRenderer;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut filter = OnePole::new();
+  /// let mut renderer = OfflineRenderer::new(44_100f32);
+  /// let output = renderer.render_context(0.01f32, |block: &mut [f32], context| {
+  ///   for sample in block.iter_mut() {
+  ///     *sample = 1f32;
+  ///   }
+  ///   filter.process_context(block, context);
+  /// });
+  /// assert_eq!(output.len(), 441);
+  /// ```
+  pub fn render_context<F>(&mut self, duration: T, source: F) -> Vec<T>
+    where F: FnMut(&mut [T], &ProcessContext<T>)
+  {
+    let mut output = Vec::with_capacity(self.length(duration));
+    self.stream_context(duration, source, |block| output.extend_from_slice(block));
+    output
+  }
+
+  /// Renders `duration` seconds of the chain `source`, which fills each
+  /// block it is given from the `ProcessContext` of the block, passing each
+  /// block to `sink` as it is rendered.
+  ///
+  /// The context holds the transport at the start of the block, and the
+  /// length of the block, which is shorter for the last block when the
+  /// length is not a multiple of the block size.
+  pub fn stream_context<F, S>(&mut self, duration: T, mut source: F, mut sink: S)
+    where F: FnMut(&mut [T], &ProcessContext<T>), S: FnMut(&[T])
+  {
+    let mut remaining = self.length(duration);
+    let mut block = vec![T::zero(); self.block_size];
+    while remaining > 0 {
+      let length = remaining.min(self.block_size);
+      let block = &mut block[..length];
+      for sample in block.iter_mut() {
+        *sample = T::zero();
+      }
+      {
+        let context = ProcessContext {
+          sample_rate: self.sample_rate,
+          block_size: length,
+          transport: Some(&self.transport),
+          bypass: false
+        };
+        source(block, &context);
+      }
+      sink(block);
+      self.transport.advance(length);
+      remaining -= length;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use analysis::PeakEnvDetector;
+  use bus::{Tap, TapPoint};
+  use effects::Echo;
+  use filter::OnePole;
+  use traits::Processor;
+  use util::Guard;
+
+  #[test]
+  fn blocks() {
+    let mut renderer = OfflineRenderer::new(1_000f64);
+    renderer.set_block_size(0);
+    assert_eq!(renderer.get_block_size(), BLOCK_SIZE);
+    renderer.set_block_size(300);
+
+    let mut lengths = Vec::new();
+    renderer.stream(1f64, |block, _| lengths.push(block.len()), |_| {});
+    assert_eq!(lengths, vec![300, 300, 300, 100]);
+    assert_eq!(renderer.transport().get_samples(), 1_000);
+    assert_eq!(renderer.length(0.0004f64), 0);
+  }
+
+  #[test]
+  fn transport() {
+    // Each block starts at the transport position, in samples
+    let mut renderer = OfflineRenderer::new(1_000f64);
+    renderer.transport().set_tempo(60f64);
+    let output = renderer.render(0.5f64, |block, transport| {
+      for (n, sample) in block.iter_mut().enumerate() {
+        *sample = transport.beats_to_samples(transport.get_beats()).round() + n as f64;
+      }
+    });
+    assert!(output.iter().enumerate().all(|(n, sample)| *sample == n as f64));
+
+    let next = renderer.render(0.001f64, |block, transport| block[0] = transport.get_beats());
+    assert_eq!(next, vec![0.5f64]);
+  }
+
+  #[test]
+  fn context() {
+    let mut renderer = OfflineRenderer::new(1_000f64);
+    renderer.set_block_size(300);
+    let mut contexts = Vec::new();
+    renderer.render_context(1f64, |_, context| {
+      contexts.push((context.block_size, context.transport.unwrap().get_samples(), context.bypass));
+      assert_eq!(context.sample_rate, 1_000f64);
+    });
+    assert_eq!(contexts, vec![(300, 0, false), (300, 300, false), (300, 600, false), (100, 900, false)]);
+
+    let context = ProcessContext::new(48_000f64, 480);
+    assert!(context.transport.is_none() && !context.bypass);
+    assert!((context.duration() - 0.01f64).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn bypass() {
+    // A bypassed block leaves the samples and the memory alone, and returns
+    // the last output, even when empty
+    let onepole = || {
+      let mut filter = OnePole::new();
+      filter.set_coefficients(0.5f64, 0.5f64);
+      filter
+    };
+    let mut chain: Vec<Box<dyn Processor<f64>>> = vec![
+      Box::new(onepole()),
+      Box::new(Guard::new(Tap::new(onepole(), PeakEnvDetector::new(), TapPoint::Pre)))
+    ];
+    let mut context = ProcessContext::new(1_000f64, 2);
+    let mut reference = onepole();
+    for processor in chain.iter_mut() {
+      let last = reference.process_block(&mut [1f64, 1f64]);
+      assert_eq!(processor.process_context(&mut [1f64, 1f64], &context), last);
+
+      context.bypass = true;
+      let mut block = [2f64, 2f64];
+      assert_eq!(processor.process_context(&mut block, &context), last);
+      assert_eq!(block, [2f64, 2f64]);
+      assert_eq!(processor.process_context(&mut [], &context), last);
+
+      context.bypass = false;
+      assert_eq!(processor.process_context(&mut [1f64], &context), reference.process(1f64));
+      reference.clear();
+    }
+  }
+
+  #[test]
+  fn tempo_sync() {
+    // A synced echo in a chain follows tempo changes of the transport
+    let mut echo = Echo::new(1_000f64, 1f64);
+    echo.set_sync(Some(1f64));
+    echo.set_mix(1f64);
+    echo.set_feedback(0f64);
+    let tap = Tap::new(echo, PeakEnvDetector::new(), TapPoint::Post);
+    let mut chain: Vec<Box<dyn Processor<f64>>> = vec![Box::new(Guard::new(tap))];
+    let mut renderer = OfflineRenderer::new(1_000f64);
+    renderer.set_block_size(100);
+    let mut render = |renderer: &mut OfflineRenderer<f64>| {
+      let mut first = true;
+      renderer.render_context(0.6f64, |block, context| {
+        block[0] = if first { 1f64 } else { 0f64 };
+        first = false;
+        for processor in chain.iter_mut() {
+          processor.process_context(block, context);
+        }
+      })
+    };
+    let onset = |output: Vec<f64>| output.iter().skip(1).position(|sample| *sample != 0f64).unwrap() + 1;
+
+    assert_eq!(onset(render(&mut renderer)), 500);
+    renderer.transport().set_tempo(240f64);
+    assert_eq!(onset(render(&mut renderer)), 250);
+  }
+
+  #[test]
+  fn offline_render() {
+    let mut phase = 0f32;
+    let output = offline(0.01f32, 44_100f32, |block, _| {
+      for sample in block.iter_mut() {
+        *sample = phase;
+        phase += 1f32;
+      }
+    });
+    assert_eq!(output.len(), 441);
+    assert_eq!(output[440], 440f32);
+  }
+}
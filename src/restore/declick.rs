@@ -0,0 +1,253 @@
+use std::ops::Range;
+
+use num;
+use num::traits::Float;
+
+use restore::{interpolate, median, Interpolation};
+
+/// Detects and repairs clicks and pops in a recording.
+///
+/// Clicks are impulsive discontinuities, which stand out in the second
+/// difference of a signal far beyond the smooth changes of the music around
+/// them. The second difference is compared to a robust estimate of its
+/// deviation, from the median of its magnitude over a window, and samples
+/// exceeding the threshold, widened by a margin, are repaired by
+/// interpolation. Runs longer than the maximum length are not impulsive, so
+/// they are left unchanged.
+pub struct Declick<T> {
+  threshold: T,
+  window: usize,
+  margin: usize,
+  max_length: usize,
+  interpolation: Interpolation,
+  order: usize
+}
+
+impl<T> Declick<T> where T: Float {
+  /// Creates a new `Declick` using autoregressive interpolation of order 16.
+  ///
+  /// Samples are considered clicks when their second difference exceeds 10
+  /// times its deviation over a window of 256 samples. Clicks are widened by
+  /// 2 samples either side, and are at most 64 samples long.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::restore::Declick;
+  ///
+  /// let mut samples: Vec<f32> = (0..2_000).map(|n| (n as f32 * 0.05f32).sin()).collect();
+  /// samples[1_000] += 0.5f32;
+  ///
+  /// let declick = Declick::new();
+  /// assert_eq!(declick.repair(&mut samples), 1);
+  /// assert!((samples[1_000] - (1_000f32 * 0.05f32).sin()).abs() < 1e-2f32);
+  /// ```
+  pub fn new() -> Self {
+    Declick {
+      threshold: num::cast(10f64).unwrap(),
+      window: 256,
+      margin: 2,
+      max_length: 64,
+      interpolation: Interpolation::Autoregressive,
+      order: 16
+    }
+  }
+
+  /// Sets how many times the deviation of the second difference a sample
+  /// must exceed to be considered a click. Lower thresholds are more
+  /// sensitive.
+  ///
+  /// `threshold` must be positive, else it is not updated.
+  pub fn set_threshold(&mut self, threshold: T) {
+    if threshold > T::zero() && threshold.is_finite() {
+      self.threshold = threshold;
+    }
+  }
+
+  /// Returns how many times the deviation of the second difference a sample
+  /// must exceed to be considered a click.
+  pub fn get_threshold(&self) -> T {
+    self.threshold
+  }
+
+  /// Sets the length, in samples, of the windows the deviation is estimated
+  /// over.
+  ///
+  /// `window` must be positive, else it is not updated.
+  pub fn set_window(&mut self, window: usize) {
+    if window > 0 {
+      self.window = window;
+    }
+  }
+
+  /// Returns the length, in samples, of the windows the deviation is
+  /// estimated over.
+  pub fn get_window(&self) -> usize {
+    self.window
+  }
+
+  /// Sets the number of samples either side of a click that are also
+  /// repaired.
+  pub fn set_margin(&mut self, margin: usize) {
+    self.margin = margin;
+  }
+
+  /// Returns the number of samples either side of a click that are also
+  /// repaired.
+  pub fn get_margin(&self) -> usize {
+    self.margin
+  }
+
+  /// Sets the longest click, in samples, including its margins.
+  ///
+  /// `length` must be positive, else it is not updated.
+  pub fn set_max_length(&mut self, length: usize) {
+    if length > 0 {
+      self.max_length = length;
+    }
+  }
+
+  /// Returns the longest click, in samples, including its margins.
+  pub fn get_max_length(&self) -> usize {
+    self.max_length
+  }
+
+  /// Sets how clicks are reconstructed.
+  pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+    self.interpolation = interpolation;
+  }
+
+  /// Returns how clicks are reconstructed.
+  pub fn get_interpolation(&self) -> Interpolation {
+    self.interpolation
+  }
+
+  /// Sets the order of the autoregressive model.
+  ///
+  /// `order` must be positive, else it is not updated.
+  pub fn set_order(&mut self, order: usize) {
+    if order > 0 {
+      self.order = order;
+    }
+  }
+
+  /// Returns the order of the autoregressive model.
+  pub fn get_order(&self) -> usize {
+    self.order
+  }
+
+  /// Returns the clicks in `samples`, in order.
+  pub fn detect(&self, samples: &[T]) -> Vec<Range<usize>> {
+    let mut regions: Vec<Range<usize>> = Vec::new();
+    if samples.len() < 3 {
+      return regions;
+    }
+
+    let two = T::one() + T::one();
+    let mut difference = vec![T::zero(); samples.len()];
+    for n in 1..samples.len() - 1 {
+      difference[n] = (samples[n + 1] - two * samples[n] + samples[n - 1]).abs();
+    }
+
+    // The median absolute deviation of a normal distribution is 0.6745 of
+    // its standard deviation
+    let consistency: T = num::cast(0.6745f64).unwrap();
+    let blocks = samples.len().div_ceil(self.window);
+    let deviations: Vec<T> = (0..blocks)
+      .map(|block| {
+        let start = block.saturating_sub(1) * self.window;
+        let end = ((block + 2) * self.window).min(samples.len());
+        median(difference[start..end].to_vec()) / consistency
+      })
+      .collect();
+
+    for n in 1..samples.len() - 1 {
+      let limit = self.threshold * deviations[n / self.window];
+      if difference[n] <= limit {
+        continue;
+      }
+      let start = n.saturating_sub(self.margin);
+      let end = (n + 1 + self.margin).min(samples.len());
+      match regions.last_mut() {
+        Some(ref mut region) if start <= region.end => region.end = end,
+        _ => regions.push(start..end)
+      }
+    }
+    regions.retain(|region| region.len() <= self.max_length);
+    regions
+  }
+
+  /// Repairs the clicks in `samples` in place, and returns the number of
+  /// clicks repaired.
+  pub fn repair(&self, samples: &mut [T]) -> usize {
+    let regions = self.detect(samples);
+    for region in regions.iter() {
+      interpolate(samples, region.start, region.end, self.interpolation, self.order);
+    }
+    regions.len()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns a sine with quiet noise from a linear congruential generator.
+  fn signal(length: usize) -> Vec<f32> {
+    let mut state = 12_345u32;
+    (0..length)
+      .map(|n| {
+        state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+        let noise = (state >> 8) as f32 / (1u32 << 23) as f32 - 1f32;
+        0.5f32 * (n as f32 * 0.03f32).sin() + 1e-3f32 * noise
+      })
+      .collect()
+  }
+
+  #[test]
+  fn clean_signal() {
+    let declick = Declick::new();
+    assert!(declick.detect(&signal(8_192)).is_empty());
+    assert!(declick.detect(&[0f32; 2]).is_empty());
+  }
+
+  #[test]
+  fn repairs_clicks() {
+    let clean = signal(8_192);
+    let mut samples = clean.clone();
+    samples[1_000] += 0.3f32;
+    samples[4_000] -= 0.2f32;
+    for n in 6_000..6_004 {
+      samples[n] = 0.9f32;
+    }
+
+    let declick = Declick::new();
+    let regions = declick.detect(&samples);
+    assert_eq!(regions.len(), 3);
+    assert!(regions[0].start <= 1_000 && regions[0].end > 1_000);
+    assert!(regions[1].start <= 4_000 && regions[1].end > 4_000);
+    assert!(regions[2].start <= 6_000 && regions[2].end >= 6_004);
+
+    assert_eq!(declick.repair(&mut samples), 3);
+    for (repaired, clean) in samples.iter().zip(clean.iter()) {
+      assert!((repaired - clean).abs() < 1e-2f32);
+    }
+  }
+
+  #[test]
+  fn sensitivity() {
+    let mut samples = signal(4_096);
+    samples[2_000] += 0.006f32;
+
+    let mut declick = Declick::new();
+    assert!(declick.detect(&samples).is_empty());
+    declick.set_threshold(3f32);
+    assert_eq!(declick.detect(&samples).len(), 1);
+
+    // Bursts longer than the maximum length are left alone
+    for n in 1_000..1_100 {
+      samples[n] = if n % 2 == 0 { 0.9f32 } else { -0.9f32 };
+    }
+    assert!(declick.detect(&samples).iter().all(|region| region.end < 1_000 || region.start > 1_100));
+  }
+}
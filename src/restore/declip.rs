@@ -0,0 +1,201 @@
+use std::ops::Range;
+
+use num;
+use num::traits::Float;
+
+use restore::{interpolate, Interpolation};
+use traits::FloatConst;
+use util;
+
+/// Detects and repairs clipped regions of a recording.
+///
+/// Clipping flattens the peaks of a signal at the largest level it reaches,
+/// so runs of consecutive samples within a tolerance of the peak level of the
+/// block are considered clipped. Each run is reconstructed by interpolation,
+/// and each reconstructed sample is kept at or beyond the clipped sample, with
+/// its polarity, since the original signal must have exceeded it.
+pub struct Declip<T> {
+  tolerance: T,
+  min_length: usize,
+  interpolation: Interpolation,
+  order: usize
+}
+
+impl<T> Declip<T> where T: Float + FloatConst {
+  /// Creates a new `Declip` using autoregressive interpolation of order 16.
+  ///
+  /// Runs of at least 2 samples within 0.1 dB of the peak level are
+  /// considered clipped.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::restore::Declip;
+  ///
+  /// let mut samples: Vec<f32> = (0..1_000)
+  ///   .map(|n| (n as f32 * 0.05f32).sin().max(-0.8f32).min(0.8f32))
+  ///   .collect();
+  ///
+  /// let declip = Declip::new();
+  /// assert_eq!(declip.repair(&mut samples), 16);
+  /// assert!(samples.iter().any(|sample| sample.abs() > 0.9f32));
+  /// ```
+  pub fn new() -> Self {
+    Declip {
+      tolerance: num::cast(0.1f64).unwrap(),
+      min_length: 2,
+      interpolation: Interpolation::Autoregressive,
+      order: 16
+    }
+  }
+
+  /// Sets how far below the peak level, in dB, samples are considered
+  /// clipped.
+  ///
+  /// `tolerance` must be non-negative, else it is not updated.
+  pub fn set_tolerance(&mut self, tolerance: T) {
+    if tolerance >= T::zero() && tolerance.is_finite() {
+      self.tolerance = tolerance;
+    }
+  }
+
+  /// Returns how far below the peak level, in dB, samples are considered
+  /// clipped.
+  pub fn get_tolerance(&self) -> T {
+    self.tolerance
+  }
+
+  /// Sets the fewest consecutive samples at the peak level considered
+  /// clipped.
+  ///
+  /// Single samples at the peak level are usually genuine peaks, so `length`
+  /// must be at least 2, else it is not updated.
+  pub fn set_min_length(&mut self, length: usize) {
+    if length >= 2 {
+      self.min_length = length;
+    }
+  }
+
+  /// Returns the fewest consecutive samples at the peak level considered
+  /// clipped.
+  pub fn get_min_length(&self) -> usize {
+    self.min_length
+  }
+
+  /// Sets how clipped regions are reconstructed.
+  pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+    self.interpolation = interpolation;
+  }
+
+  /// Returns how clipped regions are reconstructed.
+  pub fn get_interpolation(&self) -> Interpolation {
+    self.interpolation
+  }
+
+  /// Sets the order of the autoregressive model.
+  ///
+  /// `order` must be positive, else it is not updated.
+  pub fn set_order(&mut self, order: usize) {
+    if order > 0 {
+      self.order = order;
+    }
+  }
+
+  /// Returns the order of the autoregressive model.
+  pub fn get_order(&self) -> usize {
+    self.order
+  }
+
+  /// Returns the clipped regions of `samples`, in order.
+  pub fn detect(&self, samples: &[T]) -> Vec<Range<usize>> {
+    let level = self.level(samples);
+    let mut regions = Vec::new();
+    if level <= T::zero() {
+      return regions;
+    }
+
+    let mut n = 0;
+    while n < samples.len() {
+      if samples[n].abs() < level {
+        n += 1;
+        continue;
+      }
+      // Runs end at a sample below the level, or a change in polarity
+      let start = n;
+      let polarity = samples[n].signum();
+      while n < samples.len() && samples[n].abs() >= level && samples[n].signum() == polarity {
+        n += 1;
+      }
+      if n - start >= self.min_length {
+        regions.push(start..n);
+      }
+    }
+    regions
+  }
+
+  /// Repairs the clipped regions of `samples` in place, and returns the
+  /// number of regions repaired.
+  pub fn repair(&self, samples: &mut [T]) -> usize {
+    let regions = self.detect(samples);
+    for region in regions.iter() {
+      let clipped = samples[region.clone()].to_vec();
+      interpolate(samples, region.start, region.end, self.interpolation, self.order);
+      for (sample, clipped) in samples[region.clone()].iter_mut().zip(clipped) {
+        let polarity = clipped.signum();
+        *sample = polarity * (*sample * polarity).max(clipped.abs());
+      }
+    }
+    regions.len()
+  }
+
+  /// Returns the level at and above which samples are considered clipped.
+  fn level(&self, samples: &[T]) -> T {
+    let peak = samples.iter().fold(T::zero(), |peak, sample| peak.max(sample.abs()));
+    peak * util::to_sample(-self.tolerance)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+
+  /// Returns a 50 Hz sine, sampled at 8 kHz, and a copy clipped at `level`.
+  fn clipped_sine(level: f32) -> (Vec<f32>, Vec<f32>) {
+    let sine: Vec<f32> = (0..800).map(|n| (2f32 * PI * 50f32 * n as f32 / 8_000f32).sin()).collect();
+    let clipped = sine.iter().map(|sample| sample.max(-level).min(level)).collect();
+    (sine, clipped)
+  }
+
+  fn error(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).fold(0f32, |error, (a, b)| error.max((a - b).abs()))
+  }
+
+  #[test]
+  fn detect() {
+    let declip = Declip::new();
+    let samples = [0f32, 0.5f32, 1f32, 1f32, 1f32, 0.5f32, -1f32, -0.3f32, -1f32, -1f32];
+    assert_eq!(declip.detect(&samples), vec![2..5, 8..10]);
+    assert!(declip.detect(&[0f32; 4]).is_empty());
+
+    // A single sample at the peak is not clipped
+    assert!(declip.detect(&[0.2f32, 1f32, 0.2f32]).is_empty());
+  }
+
+  #[test]
+  fn repair() {
+    let (sine, clipped) = clipped_sine(0.9f32);
+    let before = error(&sine, &clipped);
+    for interpolation in [Interpolation::Cubic, Interpolation::Autoregressive].iter() {
+      let mut declip = Declip::new();
+      declip.set_interpolation(*interpolation);
+      let mut samples = clipped.clone();
+      assert_eq!(declip.repair(&mut samples), 10);
+
+      // Peaks are restored beyond the clipping level
+      assert!(error(&sine, &samples) < 0.25f32 * before);
+      assert!(samples.iter().any(|sample| *sample > 0.98f32));
+      assert!(samples.iter().any(|sample| *sample < -0.98f32));
+    }
+  }
+}
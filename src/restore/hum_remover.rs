@@ -0,0 +1,323 @@
+use num;
+use num::traits::Float;
+
+use filter::{Biquad2, BiquadCoefficients};
+use filter::rbj::BandStop;
+use traits::{FloatConst, Processor};
+use util;
+
+/// The fraction of the nominal fundamental searched either side of it when
+/// tracking.
+const SEARCH_RANGE: f64 = 0.05f64;
+/// The number of harmonics whose power is summed when tracking.
+const TRACKED_HARMONICS: usize = 3;
+
+/// Returns the power of `samples` at `frequency` using the Goertzel
+/// algorithm.
+fn goertzel<T: Float + FloatConst>(samples: &[T], sample_rate: T, frequency: T) -> T {
+  let coefficient = T::two() * (T::two() * T::pi() * frequency / sample_rate).cos();
+  let (mut s1, mut s2) = (T::zero(), T::zero());
+  for sample in samples {
+    let s0 = *sample + coefficient * s1 - s2;
+    s2 = s1;
+    s1 = s0;
+  }
+  s1 * s1 + s2 * s2 - coefficient * s1 * s2
+}
+
+/// Removes mains hum from a recording.
+///
+/// Narrow cuts are placed at the fundamental of the hum, usually 50 Hz or
+/// 60 Hz, and its harmonics, each cutting by the same depth. Each cut mixes a
+/// `BandStop` notch with its complementary bandpass at the depth, so unlike a
+/// peaking cut, the bandwidth stays narrow however deep the cut is. When tracking
+/// is enabled, the input is analyzed every half second, and the fundamental is
+/// moved to the frequency, within 5% of the nominal fundamental, where the
+/// Goertzel algorithm finds the most power in the first harmonics. The cuts
+/// are retuned without clearing their memory, so tracking does not click.
+pub struct HumRemover<T> {
+  sample_rate: T,
+  nominal: T,
+  fundamental: T,
+  harmonics: usize,
+  depth: T,
+  q: T,
+  tracking: bool,
+  notches: Vec<Biquad2<T>>,
+  // Input analyzed when tracking
+  buffer: Vec<T>,
+  output: T
+}
+
+impl<T> HumRemover<T> where T: Float + FloatConst {
+  /// Creates a new `HumRemover` cutting 50 Hz hum and 7 harmonics by 40 dB,
+  /// with a q factor of 30, and tracking disabled.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::restore::HumRemover;
+  /// use rasp::traits::Processor;
+  ///
+  /// let mut hum_remover = HumRemover::new(44_100f32);
+  /// hum_remover.set_fundamental(60f32);
+  /// hum_remover.set_tracking(true);
+  /// let output = hum_remover.process(0.5f32);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut hum_remover =
+      HumRemover {
+        sample_rate,
+        nominal: num::cast(50f64).unwrap(),
+        fundamental: num::cast(50f64).unwrap(),
+        harmonics: 8,
+        depth: num::cast(40f64).unwrap(),
+        q: num::cast(30f64).unwrap(),
+        tracking: false,
+        notches: Vec::new(),
+        buffer: Vec::new(),
+        output: num::zero()
+      };
+    hum_remover.update();
+    hum_remover
+  }
+
+  /// Sets the nominal fundamental frequency of the hum, in Hz.
+  ///
+  /// `frequency` must be positive and below Nyquist, else it is not updated.
+  /// This also resets the tracked fundamental.
+  pub fn set_fundamental(&mut self, frequency: T) {
+    if frequency > T::zero() && frequency < self.sample_rate / T::two() {
+      self.nominal = frequency;
+      self.fundamental = frequency;
+      self.update();
+    }
+  }
+
+  /// Returns the fundamental frequency of the hum, in Hz, which follows the
+  /// hum when tracking.
+  pub fn get_fundamental(&self) -> T {
+    self.fundamental
+  }
+
+  /// Sets the number of harmonics cut, including the fundamental.
+  ///
+  /// Harmonics above Nyquist are not cut.
+  pub fn set_harmonics(&mut self, harmonics: usize) {
+    self.harmonics = harmonics;
+    self.update();
+  }
+
+  /// Returns the number of harmonics cut, including the fundamental.
+  pub fn get_harmonics(&self) -> usize {
+    self.harmonics
+  }
+
+  /// Sets the depth of each cut, in dB.
+  ///
+  /// `depth` must be non-negative, else it is not updated.
+  pub fn set_depth(&mut self, depth: T) {
+    if depth >= T::zero() && depth.is_finite() {
+      self.depth = depth;
+      self.update();
+    }
+  }
+
+  /// Returns the depth of each cut, in dB.
+  pub fn get_depth(&self) -> T {
+    self.depth
+  }
+
+  /// Sets the q factor of each cut.
+  ///
+  /// `q` must be positive, else it is not updated.
+  pub fn set_q(&mut self, q: T) {
+    if q > T::zero() && q.is_finite() {
+      self.q = q;
+      self.update();
+    }
+  }
+
+  /// Returns the q factor of each cut.
+  pub fn get_q(&self) -> T {
+    self.q
+  }
+
+  /// Sets whether the fundamental follows the hum in the input.
+  pub fn set_tracking(&mut self, tracking: bool) {
+    self.tracking = tracking;
+    self.buffer.clear();
+  }
+
+  /// Returns `true` if the fundamental follows the hum in the input.
+  pub fn is_tracking(&self) -> bool {
+    self.tracking
+  }
+
+  /// Tunes the cuts to the fundamental and its harmonics.
+  fn update(&mut self) {
+    let limit = self.sample_rate * num::cast(0.49f64).unwrap();
+    let mut count = 0;
+    for harmonic in 1..self.harmonics + 1 {
+      let frequency = self.fundamental * num::cast(harmonic).unwrap();
+      if frequency >= limit {
+        break;
+      }
+      if count == self.notches.len() {
+        self.notches.push(Biquad2::new());
+      }
+      let notch = BandStop::coefficients(self.sample_rate, frequency, self.q);
+      let gain = util::to_sample(-self.depth);
+      let mix = |a: T, b: T| gain * a + (T::one() - gain) * b;
+      self.notches[count].load_coefficients(BiquadCoefficients {
+        b0: mix(T::one(), notch.b0),
+        b1: mix(notch.a1, notch.b1),
+        b2: mix(notch.a2, notch.b2),
+        a1: notch.a1,
+        a2: notch.a2
+      });
+      count += 1;
+    }
+    self.notches.truncate(count);
+  }
+
+  /// Moves the fundamental to the strongest hum in the buffered input.
+  fn track(&mut self) {
+    let range = self.nominal * num::cast(SEARCH_RANGE).unwrap();
+    let power = |frequency: T| {
+      (1..TRACKED_HARMONICS + 1)
+        .map(|harmonic| frequency * num::cast(harmonic).unwrap())
+        .filter(|frequency| *frequency < self.sample_rate / T::two())
+        .fold(T::zero(), |sum, frequency| sum + goertzel(&self.buffer, self.sample_rate, frequency))
+    };
+
+    // A coarse search across the range, refined around the strongest
+    // candidate
+    let steps = 20;
+    let mut best = self.nominal;
+    let mut step = range * T::two() / num::cast(steps).unwrap();
+    let mut start = self.nominal - range;
+    let mut mean = T::zero();
+    for pass in 0..2 {
+      let candidates: Vec<(T, T)> = (0..steps + 1)
+        .map(|i| {
+          let frequency = start + step * num::cast(i).unwrap();
+          (frequency, power(frequency))
+        })
+        .collect();
+      if pass == 0 {
+        let total = candidates.iter().fold(T::zero(), |sum, candidate| sum + candidate.1);
+        mean = total / num::cast(candidates.len()).unwrap();
+      }
+      let strongest = (0..candidates.len())
+        .fold(0, |best, i| if candidates[i].1 > candidates[best].1 { i } else { best });
+
+      // Without a clear peak inside the range there is no hum to follow
+      let edge = strongest == 0 || strongest == steps;
+      if pass == 0 && (edge || candidates[strongest].1 < mean * T::two()) {
+        return;
+      }
+      best = candidates[strongest].0;
+      start = best - step;
+      step = step * T::two() / num::cast(steps).unwrap();
+    }
+
+    self.fundamental = best;
+    self.update();
+  }
+}
+
+impl<T> Processor<T> for HumRemover<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> T {
+    if self.tracking {
+      self.buffer.push(sample);
+      if self.buffer.len() >= num::cast::<T, usize>(self.sample_rate / T::two()).unwrap() {
+        self.track();
+        self.buffer.clear();
+      }
+    }
+
+    self.output = self.notches.iter_mut().fold(sample, |sample, notch| notch.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for notch in self.notches.iter_mut() {
+      notch.clear();
+    }
+    self.buffer.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::consts::PI;
+
+  /// Returns a 1 kHz tone with hum at `fundamental` and two harmonics.
+  fn humming(sample_rate: f32, fundamental: f32, length: usize) -> Vec<f32> {
+    (0..length)
+      .map(|n| {
+        let t = n as f32 / sample_rate;
+        let hum = (1..4).fold(0f32, |sum, h| sum + 0.2f32 * (2f32 * PI * fundamental * h as f32 * t).sin());
+        0.5f32 * (2f32 * PI * 1_000f32 * t).sin() + hum
+      })
+      .collect()
+  }
+
+  fn level(samples: &[f32], sample_rate: f32, frequency: f32) -> f32 {
+    goertzel(samples, sample_rate, frequency).sqrt()
+  }
+
+  #[test]
+  fn removes_hum() {
+    let sample_rate = 8_000f32;
+    let input = humming(sample_rate, 50f32, 16_000);
+    let mut hum_remover = HumRemover::new(sample_rate);
+    let output: Vec<f32> = input.iter().map(|x| hum_remover.process(*x)).collect();
+
+    let (input, output) = (&input[8_000..], &output[8_000..]);
+    for frequency in [50f32, 100f32, 150f32].iter() {
+      assert!(level(output, sample_rate, *frequency) < 0.05f32 * level(input, sample_rate, *frequency));
+    }
+    let tone = level(output, sample_rate, 1_000f32) / level(input, sample_rate, 1_000f32);
+    assert!((tone - 1f32).abs() < 1e-2f32);
+  }
+
+  #[test]
+  fn depth() {
+    let sample_rate = 8_000f32;
+    let mut hum_remover = HumRemover::new(sample_rate);
+    hum_remover.set_depth(0f32);
+    for sample in humming(sample_rate, 50f32, 1_000) {
+      assert!((hum_remover.process(sample) - sample).abs() < 1e-5f32);
+    }
+
+    // Harmonics above Nyquist are not cut
+    hum_remover.set_harmonics(100);
+    assert_eq!(hum_remover.notches.len(), 78);
+  }
+
+  #[test]
+  fn tracking() {
+    let sample_rate = 8_000f32;
+    let mut hum_remover = HumRemover::new(sample_rate);
+    hum_remover.set_tracking(true);
+    for sample in humming(sample_rate, 50.7f32, 16_000) {
+      hum_remover.process(sample);
+    }
+    assert!((hum_remover.get_fundamental() - 50.7f32).abs() < 0.05f32);
+
+    // Hum outside the range of the nominal fundamental is not followed
+    hum_remover.set_fundamental(60f32);
+    for sample in humming(sample_rate, 50f32, 16_000) {
+      hum_remover.process(sample);
+    }
+    assert_eq!(hum_remover.get_fundamental(), 60f32);
+  }
+}
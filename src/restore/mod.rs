@@ -0,0 +1,207 @@
+//! Offline restoration of damaged recordings.
+//!
+//! Restorers detect damaged regions in a block of samples, and repair them in
+//! place by interpolating across each region from the undamaged samples
+//! around it.
+
+use num;
+use num::traits::Float;
+
+mod declick;
+mod declip;
+mod hum_remover;
+
+pub use self::declick::Declick        as Declick;
+pub use self::declip::Declip          as Declip;
+pub use self::hum_remover::HumRemover as HumRemover;
+
+/// How damaged regions are reconstructed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Interpolation {
+  /// A cubic Hermite spline between the samples either side of the region,
+  /// matching their slopes.
+  Cubic,
+  /// Predictions of an autoregressive model, fitted to the samples before and
+  /// after the region, crossfaded across the region.
+  Autoregressive
+}
+
+/// Returns the median of `values`.
+fn median<T: Float>(mut values: Vec<T>) -> T {
+  values.sort_by(|a, b| a.partial_cmp(b).unwrap_or(::std::cmp::Ordering::Equal));
+  values[values.len() / 2]
+}
+
+/// Returns the coefficients of an autoregressive model of `order`, fitted to
+/// `context` by least squares.
+///
+/// Each sample is predicted as the sum of the previous samples weighted by the
+/// coefficients, starting from the most recent. The normal equations are
+/// solved in `f64`, with a little diagonal loading, since narrowband signals
+/// make them close to singular.
+#[allow(clippy::needless_range_loop)]
+fn autoregression<T: Float>(context: &[T], order: usize) -> Vec<T> {
+  let x: Vec<f64> = context.iter().map(|sample| num::cast(*sample).unwrap()).collect();
+  let mut matrix = vec![vec![0f64; order]; order];
+  let mut vector = vec![0f64; order];
+  for n in order..x.len() {
+    for i in 0..order {
+      vector[i] += x[n] * x[n - 1 - i];
+      for j in 0..order {
+        matrix[i][j] += x[n - 1 - i] * x[n - 1 - j];
+      }
+    }
+  }
+
+  let loading = 1e-9f64 * (0..order).fold(0f64, |sum, i| sum + matrix[i][i]) / order as f64;
+  for i in 0..order {
+    matrix[i][i] += loading + f64::MIN_POSITIVE;
+  }
+
+  // Cholesky decomposition, then forward and back substitution
+  for j in 0..order {
+    for k in 0..j {
+      let l = matrix[j][k];
+      for i in j..order {
+        matrix[i][j] -= matrix[i][k] * l;
+      }
+    }
+    let d = matrix[j][j].sqrt();
+    for i in j..order {
+      matrix[i][j] /= d;
+    }
+  }
+  for i in 0..order {
+    for k in 0..i {
+      vector[i] -= matrix[i][k] * vector[k];
+    }
+    vector[i] /= matrix[i][i];
+  }
+  for i in (0..order).rev() {
+    for k in i + 1..order {
+      vector[i] -= matrix[k][i] * vector[k];
+    }
+    vector[i] /= matrix[i][i];
+  }
+  vector.into_iter().map(|a| num::cast(a).unwrap()).collect()
+}
+
+/// Returns `length` samples predicted to follow `context` by the
+/// autoregressive model fitted to it.
+fn predict<T: Float>(context: &[T], order: usize, length: usize) -> Vec<T> {
+  let coefficients = autoregression(context, order);
+  let mut history: Vec<T> = context[context.len() - order..].to_vec();
+  (0..length)
+    .map(|_| {
+      let prediction = coefficients.iter().zip(history.iter().rev())
+        .fold(T::zero(), |sum, (a, x)| sum + *a * *x);
+      history.remove(0);
+      history.push(prediction);
+      prediction
+    })
+    .collect()
+}
+
+/// Replaces `samples[start..end]` by interpolating from the samples around
+/// it.
+///
+/// The autoregressive model uses up to `order` coefficients, fitted to eight
+/// times as many samples either side of the region. Regions with no samples on
+/// either side are left unchanged.
+fn interpolate<T: Float>(samples: &mut [T],
+                         start: usize,
+                         end: usize,
+                         interpolation: Interpolation,
+                         order: usize)
+{
+  let length = end - start;
+  if length == 0 || (start == 0 && end == samples.len()) {
+    return;
+  }
+
+  match interpolation {
+    Interpolation::Cubic => {
+      // Holds the edge of the signal when a side is missing
+      let before = if start > 0 { samples[start - 1] } else { samples[end] };
+      let after = if end < samples.len() { samples[end] } else { before };
+      let slope_before =
+        if start > 1 { samples[start - 1] - samples[start - 2] } else { T::zero() };
+      let slope_after =
+        if end + 1 < samples.len() { samples[end + 1] - samples[end] } else { T::zero() };
+
+      let span: T = num::cast(length + 1).unwrap();
+      let (two, three) = (T::one() + T::one(), T::one() + T::one() + T::one());
+      for i in 0..length {
+        let t: T = num::cast::<usize, T>(i + 1).unwrap() / span;
+        let (t2, t3) = (t * t, t * t * t);
+        samples[start + i] =
+          (two * t3 - three * t2 + T::one()) * before
+          + (t3 - two * t2 + t) * span * slope_before
+          + (three * t2 - two * t3) * after
+          + (t3 - t2) * span * slope_after;
+      }
+    },
+    Interpolation::Autoregressive => {
+      let context = 8 * order;
+      let before = &samples[start.saturating_sub(context)..start];
+      let after: Vec<T> = samples[end..(end + context).min(samples.len())].iter()
+        .rev().cloned().collect();
+
+      let forward =
+        if before.len() > order { Some(predict(before, order, length)) } else { None };
+      let backward =
+        if after.len() > order {
+          let mut prediction = predict(&after, order, length);
+          prediction.reverse();
+          Some(prediction)
+        }
+        else {
+          None
+        };
+
+      let (forward, backward) =
+        match (forward, backward) {
+          (Some(forward), Some(backward)) => (forward, backward),
+          (Some(forward), None) => (forward.clone(), forward),
+          (None, Some(backward)) => (backward.clone(), backward),
+          (None, None) => return interpolate(samples, start, end, Interpolation::Cubic, order)
+        };
+
+      let span: T = num::cast(length + 1).unwrap();
+      for i in 0..length {
+        let weight: T = num::cast::<usize, T>(i + 1).unwrap() / span;
+        samples[start + i] = (T::one() - weight) * forward[i] + weight * backward[i];
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn autoregression_of_sine() {
+    // A sine is predicted exactly by a second order model
+    let w = 0.3f64;
+    let sine: Vec<f64> = (0..400).map(|n| (w * n as f64).sin()).collect();
+    let coefficients = autoregression(&sine, 2);
+    assert!((coefficients[0] - 2f64 * w.cos()).abs() < 1e-2f64);
+    assert!((coefficients[1] + 1f64).abs() < 1e-2f64);
+  }
+
+  #[test]
+  fn interpolate_gap() {
+    let sine: Vec<f32> = (0..512).map(|n| (n as f32 * 0.05f32).sin()).collect();
+    for interpolation in [Interpolation::Cubic, Interpolation::Autoregressive].iter() {
+      let mut samples = sine.clone();
+      for sample in samples[200..210].iter_mut() {
+        *sample = 0f32;
+      }
+      interpolate(&mut samples, 200, 210, *interpolation, 8);
+      for n in 200..210 {
+        assert!((samples[n] - sine[n]).abs() < 1e-2f32);
+      }
+    }
+  }
+}
@@ -0,0 +1,351 @@
+use num::traits::Float;
+
+use render::ProcessContext;
+use super::{Clock, NoteEvent, Tick};
+use transport::Transport;
+
+/// The order an `Arpeggiator` plays the held notes in.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArpMode {
+  /// From the lowest note to the highest.
+  Up,
+  /// From the highest note to the lowest.
+  Down,
+  /// Up, then down, without repeating the highest and lowest notes.
+  UpDown,
+  /// Any held note, chosen at random for each step.
+  Random
+}
+
+/// An arpeggiator, playing the held notes one at a time in tempo.
+///
+/// Held notes are played in order of pitch, repeated an octave higher for
+/// each octave of the range, one note for each step. Each note ends after the
+/// gate length, and a note held for the whole step ends at the same offset
+/// the next note starts at. Notes released while the arpeggiator is playing
+/// are left out from the next step, and releasing every note starts the
+/// pattern over. The clock stops while no notes are held, so the first note
+/// held starts a step at the start of the next block.
+pub struct Arpeggiator<T> {
+  clock: Clock<T>,
+  mode: ArpMode,
+  octaves: usize,
+  // Held notes, in increasing order
+  held: Vec<u8>,
+  position: usize,
+  sounding: Option<u8>,
+  // State of the xorshift generator for the random mode
+  random: u32
+}
+
+impl<T> Arpeggiator<T> where T: Float {
+  /// Creates a new `Arpeggiator` for signals at `sample_rate`, playing up a
+  /// single octave.
+  ///
+  /// The arpeggiator plays sixteenth notes at 120 beats per minute, with
+  /// gates open for half of each step.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::sequencer::{Arpeggiator, NoteEvent};
+  ///
+  /// let mut arpeggiator = Arpeggiator::new(44_100f32);
+  /// arpeggiator.note_on(60);
+  /// arpeggiator.note_on(64);
+  /// arpeggiator.note_on(67);
+  ///
+  /// // Sixteenth notes are 5512.5 samples long at 120 beats per minute
+  /// let events = arpeggiator.render(8_000);
+  /// assert_eq!(events, vec![
+  ///   NoteEvent::on(0, 60),
+  ///   NoteEvent::off(2_757, 60),
+  ///   NoteEvent::on(5_513, 64)
+  /// ]);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    Arpeggiator {
+      clock: Clock::new(sample_rate),
+      mode: ArpMode::Up,
+      octaves: 1,
+      held: Vec::new(),
+      position: 0,
+      sounding: None,
+      random: 0x9e37_79b9
+    }
+  }
+
+  /// Holds `note`, a MIDI note number.
+  pub fn note_on(&mut self, note: u8) {
+    if let Err(index) = self.held.binary_search(&note) {
+      self.held.insert(index, note);
+    }
+  }
+
+  /// Releases `note`, a MIDI note number.
+  pub fn note_off(&mut self, note: u8) {
+    if let Ok(index) = self.held.binary_search(&note) {
+      self.held.remove(index);
+    }
+    if self.held.is_empty() {
+      self.position = 0;
+    }
+  }
+
+  /// Returns the held notes, in increasing order.
+  pub fn held(&self) -> &[u8] {
+    &self.held
+  }
+
+  /// Sets the order the held notes are played in.
+  pub fn set_mode(&mut self, mode: ArpMode) {
+    self.mode = mode;
+  }
+
+  /// Returns the order the held notes are played in.
+  pub fn get_mode(&self) -> ArpMode {
+    self.mode
+  }
+
+  /// Sets the number of octaves the held notes are repeated over.
+  ///
+  /// `octaves` must be at least one, else it is not updated. Notes repeated
+  /// above MIDI note 127 are left out.
+  pub fn set_octaves(&mut self, octaves: usize) {
+    if octaves >= 1 {
+      self.octaves = octaves;
+    }
+  }
+
+  /// Returns the number of octaves the held notes are repeated over.
+  pub fn get_octaves(&self) -> usize {
+    self.octaves
+  }
+
+  /// Sets the tempo, in beats per minute.
+  ///
+  /// `tempo` must be positive, else it is not updated.
+  pub fn set_tempo(&mut self, tempo: T) {
+    self.clock.set_tempo(tempo);
+  }
+
+  /// Returns the tempo, in beats per minute.
+  pub fn get_tempo(&self) -> T {
+    self.clock.tempo
+  }
+
+  /// Sets the number of notes in each beat, such as 4 for sixteenth notes.
+  ///
+  /// `steps_per_beat` must be at least one, else it is not updated.
+  pub fn set_steps_per_beat(&mut self, steps_per_beat: usize) {
+    self.clock.set_steps_per_beat(steps_per_beat);
+  }
+
+  /// Returns the number of notes in each beat.
+  pub fn get_steps_per_beat(&self) -> usize {
+    self.clock.steps_per_beat
+  }
+
+  /// Sets how long notes are held, as a fraction of a step.
+  ///
+  /// `gate_length` must be positive and at most one, else it is not updated.
+  pub fn set_gate_length(&mut self, gate_length: T) {
+    self.clock.set_gate_length(gate_length);
+  }
+
+  /// Returns how long notes are held, as a fraction of a step.
+  pub fn get_gate_length(&self) -> T {
+    self.clock.gate_length
+  }
+
+  /// Starts the pattern over on the next block, ending the sounding note
+  /// without an event.
+  pub fn reset(&mut self) {
+    self.clock.reset();
+    self.position = 0;
+    self.sounding = None;
+  }
+
+  /// Follows the tempo and position of `transport` for the next block,
+  /// placing the notes on its beats.
+  pub fn sync(&mut self, transport: &Transport<T>) {
+    self.clock.sync(transport);
+  }
+
+  /// Returns the notes of one cycle of the pattern, before the mode is
+  /// applied.
+  fn sequence(&self) -> Vec<u8> {
+    let mut notes: Vec<u8> = (0..self.octaves)
+      .flat_map(|octave| self.held.iter().map(move |note| *note as usize + 12 * octave))
+      .filter(|note| *note <= 127)
+      .map(|note| note as u8)
+      .collect();
+    match self.mode {
+      ArpMode::Up | ArpMode::Random => {},
+      ArpMode::Down => notes.reverse(),
+      ArpMode::UpDown => {
+        let descent: Vec<u8> = notes.iter().rev().skip(1).take(notes.len().saturating_sub(2)).cloned().collect();
+        notes.extend(descent);
+      }
+    }
+    notes
+  }
+
+  fn next_random(&mut self) -> u32 {
+    self.random ^= self.random << 13;
+    self.random ^= self.random >> 17;
+    self.random ^= self.random << 5;
+    self.random
+  }
+
+  /// Advances the arpeggiator by a block of `length` samples, returning the
+  /// note events within the block, sorted by offset.
+  pub fn render(&mut self, length: usize) -> Vec<NoteEvent> {
+    let mut events = Vec::new();
+    for tick in self.clock.advance(length) {
+      match tick {
+        Tick::Release(offset) => {
+          if let Some(note) = self.sounding.take() {
+            events.push(NoteEvent::off(offset, note));
+          }
+        },
+        Tick::Step(offset) => {
+          let notes = self.sequence();
+          if notes.is_empty() {
+            continue;
+          }
+          if let Some(note) = self.sounding.take() {
+            events.push(NoteEvent::off(offset, note));
+          }
+          let index =
+            match self.mode {
+              ArpMode::Random => self.next_random() as usize % notes.len(),
+              _ => self.position % notes.len()
+            };
+          self.position = (index + 1) % notes.len();
+          events.push(NoteEvent::on(offset, notes[index]));
+          self.sounding = Some(notes[index]);
+        }
+      }
+    }
+    if self.held.is_empty() {
+      self.clock.hold();
+    }
+    events
+  }
+
+  /// Advances the arpeggiator by the block of `context`, following its
+  /// transport, if any, as with `sync()`.
+  pub fn render_context(&mut self, context: &ProcessContext<T>) -> Vec<NoteEvent> {
+    if let Some(transport) = context.transport {
+      self.sync(transport);
+    }
+    self.render(context.block_size)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Returns the notes started over `steps` steps of 100 samples.
+  fn played(arpeggiator: &mut Arpeggiator<f32>, steps: usize) -> Vec<u8> {
+    arpeggiator.render(steps * 100).iter()
+      .filter(|event| event.on)
+      .map(|event| event.note)
+      .collect()
+  }
+
+  fn arpeggiator(mode: ArpMode) -> Arpeggiator<f32> {
+    let mut arpeggiator = Arpeggiator::new(1_000f32);
+    arpeggiator.set_tempo(150f32);
+    arpeggiator.set_mode(mode);
+    for note in [64, 60, 67].iter() {
+      arpeggiator.note_on(*note);
+    }
+    arpeggiator
+  }
+
+  #[test]
+  fn modes() {
+    assert_eq!(played(&mut arpeggiator(ArpMode::Up), 4), vec![60, 64, 67, 60]);
+    assert_eq!(played(&mut arpeggiator(ArpMode::Down), 4), vec![67, 64, 60, 67]);
+    assert_eq!(played(&mut arpeggiator(ArpMode::UpDown), 6), vec![60, 64, 67, 64, 60, 64]);
+
+    let random = played(&mut arpeggiator(ArpMode::Random), 32);
+    assert!(random.iter().all(|note| [60, 64, 67].contains(note)));
+    assert!([60, 64, 67].iter().all(|note| random.contains(note)));
+  }
+
+  #[test]
+  fn octaves() {
+    let mut arpeggiator = arpeggiator(ArpMode::Up);
+    arpeggiator.set_octaves(2);
+    assert_eq!(played(&mut arpeggiator, 6), vec![60, 64, 67, 72, 76, 79]);
+
+    arpeggiator.reset();
+    arpeggiator.note_on(120);
+    arpeggiator.note_off(60);
+    arpeggiator.note_off(64);
+    arpeggiator.note_off(67);
+    assert_eq!(arpeggiator.held(), &[120]);
+    assert_eq!(played(&mut arpeggiator, 2), vec![120, 120]);
+  }
+
+  #[test]
+  fn gates() {
+    let mut arpeggiator = arpeggiator(ArpMode::Up);
+    arpeggiator.set_gate_length(1f32);
+    assert_eq!(arpeggiator.render(150), vec![
+      NoteEvent::on(0, 60),
+      NoteEvent::off(100, 60),
+      NoteEvent::on(100, 64)
+    ]);
+
+    // Releasing every note starts the pattern over
+    for note in [60, 64, 67].iter() {
+      arpeggiator.note_off(*note);
+    }
+    assert_eq!(arpeggiator.render(100), vec![NoteEvent::off(50, 64)]);
+    arpeggiator.note_on(67);
+    arpeggiator.note_on(62);
+    assert_eq!(arpeggiator.render(100)[0], NoteEvent::on(0, 62));
+  }
+
+  #[test]
+  fn first_step() {
+    // The clock waits for the first note, however long nothing is held
+    let mut arpeggiator = arpeggiator(ArpMode::Up);
+    for note in [60, 64, 67].iter() {
+      arpeggiator.note_off(*note);
+    }
+    assert!(arpeggiator.render(130).is_empty());
+    arpeggiator.note_on(62);
+    assert_eq!(arpeggiator.render(10), vec![NoteEvent::on(0, 62)]);
+
+    // A note still sounding when the first note is held ends where it starts
+    arpeggiator.note_off(62);
+    assert!(arpeggiator.render(20).is_empty());
+    arpeggiator.note_on(65);
+    assert_eq!(arpeggiator.render(100), vec![
+      NoteEvent::off(0, 62),
+      NoteEvent::on(0, 65),
+      NoteEvent::off(50, 65)
+    ]);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut arpeggiator = Arpeggiator::new(44_100f32);
+    arpeggiator.set_octaves(0);
+    assert_eq!(arpeggiator.get_octaves(), 1);
+    arpeggiator.set_tempo(-1f32);
+    assert_eq!(arpeggiator.get_tempo(), 120f32);
+    arpeggiator.set_steps_per_beat(3);
+    assert_eq!(arpeggiator.get_steps_per_beat(), 3);
+    arpeggiator.set_gate_length(0f32);
+    assert_eq!(arpeggiator.get_gate_length(), 0.5f32);
+    assert_eq!(arpeggiator.get_mode(), ArpMode::Up);
+    assert!(arpeggiator.render(44_100).is_empty());
+  }
+}
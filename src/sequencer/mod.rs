@@ -0,0 +1,178 @@
+//! Tempo synced event sources.
+//!
+//! Sequencers emit events at sample offsets within each block, so notes
+//! start on the exact sample their step falls on, rather than at the next
+//! block boundary. The `GateEvent`s of a block are passed to
+//! `Envelope::render()`, or applied to any other gated component as a block is
+//! processed, and `NoteEvent`s are applied to voices in the same way.
+
+use num;
+use num::traits::Float;
+
+use transport::Transport;
+
+mod arpeggiator;
+mod step_sequencer;
+
+pub use self::arpeggiator::ArpMode           as ArpMode;
+pub use self::arpeggiator::Arpeggiator       as Arpeggiator;
+pub use self::step_sequencer::StepSequencer as StepSequencer;
+
+/// A note starting or ending at a sample offset within a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NoteEvent {
+  /// The offset, in samples, from the start of the block.
+  pub offset: usize,
+  /// The MIDI note number.
+  pub note: u8,
+  /// Whether the note starts (`true`) or ends (`false`).
+  pub on: bool
+}
+
+impl NoteEvent {
+  /// Creates an event that starts `note` at `offset`.
+  pub fn on(offset: usize, note: u8) -> Self {
+    NoteEvent {
+      offset,
+      note,
+      on: true
+    }
+  }
+
+  /// Creates an event that ends `note` at `offset`.
+  pub fn off(offset: usize, note: u8) -> Self {
+    NoteEvent {
+      offset,
+      note,
+      on: false
+    }
+  }
+}
+
+/// A step, or the release of the gates of the previous step, at a sample
+/// offset within a block.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Tick {
+  Step(usize),
+  Release(usize)
+}
+
+/// The timing shared by the sequencers.
+///
+/// The step times are kept as fractional sample positions, so the tempo does
+/// not drift however long the clock runs, and each tick falls on the first
+/// sample at or after its exact time. A clock synced to a `Transport` places
+/// its steps on the beats of the transport instead.
+struct Clock<T> {
+  sample_rate: T,
+  tempo: T,
+  steps_per_beat: usize,
+  gate_length: T,
+  // Times of the next step and release, relative to the next block
+  next_step: T,
+  next_release: Option<T>
+}
+
+impl<T> Clock<T> where T: Float {
+  /// Creates a clock of sixteenth notes at 120 beats per minute, with gates
+  /// open for half of each step.
+  fn new(sample_rate: T) -> Self {
+    Clock {
+      sample_rate,
+      tempo: num::cast(120f64).unwrap(),
+      steps_per_beat: 4,
+      gate_length: num::cast(0.5f64).unwrap(),
+      next_step: T::zero(),
+      next_release: None
+    }
+  }
+
+  fn set_tempo(&mut self, tempo: T) {
+    if tempo > T::zero() && tempo.is_finite() {
+      self.tempo = tempo;
+    }
+  }
+
+  fn set_steps_per_beat(&mut self, steps_per_beat: usize) {
+    if steps_per_beat >= 1 {
+      self.steps_per_beat = steps_per_beat;
+    }
+  }
+
+  fn set_gate_length(&mut self, gate_length: T) {
+    if gate_length > T::zero() && gate_length <= T::one() {
+      self.gate_length = gate_length;
+    }
+  }
+
+  fn step_length(&self) -> T {
+    let seconds: T = num::cast(60f64).unwrap();
+    let steps_per_beat: T = num::cast(self.steps_per_beat).unwrap();
+    self.sample_rate * seconds / (self.tempo * steps_per_beat)
+  }
+
+  /// Starts the next step at the start of the next block.
+  fn reset(&mut self) {
+    self.next_step = T::zero();
+    self.next_release = None;
+  }
+
+  /// Holds the next step at the start of the next block, leaving any
+  /// pending release to end the sounding gate.
+  fn hold(&mut self) {
+    self.next_step = T::zero();
+  }
+
+  /// Follows the tempo and position of `transport` for the next block,
+  /// returning the number of the next step, counted from the first beat.
+  fn sync(&mut self, transport: &Transport<T>) -> usize {
+    self.tempo = transport.get_tempo();
+    let samples_per_beat = transport.samples_per_beat();
+    let steps_per_beat: T = num::cast(self.steps_per_beat).unwrap();
+
+    // The first step that starts within the next block, or just before it at
+    // a time that rounds up to its start
+    let step = (steps_per_beat * (transport.get_beats() - samples_per_beat.recip())).floor() + T::one();
+    let step = step.max(T::zero());
+    self.next_step = transport.samples_until(step / steps_per_beat);
+    num::cast(step).unwrap_or(0)
+  }
+
+  /// Advances the clock by a block of `length` samples, returning its ticks
+  /// in order.
+  fn advance(&mut self, length: usize) -> Vec<Tick> {
+    let mut ticks = Vec::new();
+    let end: T = num::cast(length).unwrap();
+    let step_length = self.step_length();
+
+    loop {
+      // Releases come first, so gates held for a whole step can reopen
+      let release = self.next_release.filter(|time| *time <= self.next_step);
+      if let Some(time) = release {
+        if time.ceil() >= end {
+          break;
+        }
+        ticks.push(Tick::Release(Clock::offset(time)));
+        self.next_release = None;
+        continue;
+      }
+
+      if self.next_step.ceil() >= end {
+        break;
+      }
+      ticks.push(Tick::Step(Clock::offset(self.next_step)));
+      self.next_release = Some(self.next_step + step_length * self.gate_length);
+      self.next_step = self.next_step + step_length;
+    }
+
+    self.next_step = self.next_step - end;
+    self.next_release = self.next_release.map(|time| time - end);
+    ticks
+  }
+
+  /// Returns the first sample at or after `time`, which may be up to a
+  /// sample before the block.
+  fn offset(time: T) -> usize {
+    num::cast(time.ceil().max(T::zero())).unwrap()
+  }
+}
@@ -0,0 +1,320 @@
+use num::traits::Float;
+
+use envelope::GateEvent;
+use render::ProcessContext;
+use super::{Clock, Tick};
+use transport::Transport;
+
+/// A step sequencer, such as that of a drum machine.
+///
+/// Each track holds a pattern of steps that are either active or silent. The
+/// sequencer moves through the steps at the tempo, opening the gate of each
+/// track at its active steps, and closing it after the gate length. The step
+/// times are kept as fractional sample positions, so the tempo does not
+/// drift however long the sequencer runs, and each event falls on the first
+/// sample at or after its exact time.
+pub struct StepSequencer<T> {
+  clock: Clock<T>,
+  patterns: Vec<Vec<bool>>,
+  // The step started at the next step time
+  step: usize,
+  open: Vec<bool>
+}
+
+impl<T> StepSequencer<T> where T: Float {
+  /// Creates a new `StepSequencer` for signals at `sample_rate`, with
+  /// `tracks` silent patterns of `steps` steps.
+  ///
+  /// The sequencer plays sixteenth notes at 120 beats per minute, with gates
+  /// open for half of each step. `steps` must be at least one.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::envelope::Ar;
+  /// use rasp::sequencer::StepSequencer;
+  /// use rasp::traits::Envelope;
+  ///
+  /// let mut sequencer = StepSequencer::new(44_100f32, 2, 16);
+  /// sequencer.set_pattern(0, &[true, false, false, false]);
+  /// sequencer.set_pattern(1, &[false, false, true, false]);
+  ///
+  /// let mut kick = Ar::new();
+  /// let mut block = vec![0f32; 512];
+  /// let events = sequencer.render(block.len());
+  /// kick.render(&mut block, &events[0]);
+  /// assert!(block[0] > 0f32);
+  /// ```
+  pub fn new(sample_rate: T, tracks: usize, steps: usize) -> Self {
+    debug_assert!(steps >= 1);
+    StepSequencer {
+      clock: Clock::new(sample_rate),
+      patterns: vec![vec![false; steps]; tracks],
+      step: 0,
+      open: vec![false; tracks]
+    }
+  }
+
+  /// Returns the number of tracks.
+  pub fn tracks(&self) -> usize {
+    self.patterns.len()
+  }
+
+  /// Returns the number of steps in each pattern.
+  pub fn steps(&self) -> usize {
+    self.patterns.first().map(|pattern| pattern.len()).unwrap_or(0)
+  }
+
+  /// Sets whether `step` of `track` is active.
+  pub fn set_step(&mut self, track: usize, step: usize, active: bool) {
+    self.patterns[track][step] = active;
+  }
+
+  /// Returns whether `step` of `track` is active.
+  pub fn get_step(&self, track: usize, step: usize) -> bool {
+    self.patterns[track][step]
+  }
+
+  /// Sets the steps of `track` from `pattern`, repeating it to fill every
+  /// step.
+  pub fn set_pattern(&mut self, track: usize, pattern: &[bool]) {
+    if pattern.is_empty() {
+      return;
+    }
+    for (step, active) in self.patterns[track].iter_mut().zip(pattern.iter().cycle()) {
+      *step = *active;
+    }
+  }
+
+  /// Sets the tempo, in beats per minute.
+  ///
+  /// `tempo` must be positive, else it is not updated.
+  pub fn set_tempo(&mut self, tempo: T) {
+    self.clock.set_tempo(tempo);
+  }
+
+  /// Returns the tempo, in beats per minute.
+  pub fn get_tempo(&self) -> T {
+    self.clock.tempo
+  }
+
+  /// Sets the number of steps in each beat, such as 4 for sixteenth notes.
+  ///
+  /// `steps_per_beat` must be at least one, else it is not updated.
+  pub fn set_steps_per_beat(&mut self, steps_per_beat: usize) {
+    self.clock.set_steps_per_beat(steps_per_beat);
+  }
+
+  /// Returns the number of steps in each beat.
+  pub fn get_steps_per_beat(&self) -> usize {
+    self.clock.steps_per_beat
+  }
+
+  /// Sets how long gates stay open, as a fraction of a step.
+  ///
+  /// `gate_length` must be positive and at most one, else it is not updated.
+  /// Gates that are open for the whole step are closed and opened again when
+  /// the next step is active.
+  pub fn set_gate_length(&mut self, gate_length: T) {
+    self.clock.set_gate_length(gate_length);
+  }
+
+  /// Returns how long gates stay open, as a fraction of a step.
+  pub fn get_gate_length(&self) -> T {
+    self.clock.gate_length
+  }
+
+  /// Returns the length of a step, in samples.
+  pub fn step_length(&self) -> T {
+    self.clock.step_length()
+  }
+
+  /// Returns the step that starts next.
+  pub fn next_step(&self) -> usize {
+    self.step
+  }
+
+  /// Returns to the first step, closing every gate without an event.
+  pub fn reset(&mut self) {
+    self.clock.reset();
+    self.step = 0;
+    for open in self.open.iter_mut() {
+      *open = false;
+    }
+  }
+
+  /// Follows the tempo and position of `transport` for the next block.
+  ///
+  /// The first step of the patterns falls on the first beat of the
+  /// transport, and each pattern repeats from there. Called before each
+  /// block is rendered, the sequencer follows tempo changes, loops, and
+  /// moves of the position as they happen.
+  pub fn sync(&mut self, transport: &Transport<T>) {
+    self.step = self.clock.sync(transport) % self.steps();
+  }
+
+  /// Advances the sequencer by a block of `length` samples, returning the
+  /// events of each track within the block, sorted by offset.
+  pub fn render(&mut self, length: usize) -> Vec<Vec<GateEvent>> {
+    let mut events = vec![Vec::new(); self.tracks()];
+    for tick in self.clock.advance(length) {
+      match tick {
+        Tick::Release(offset) => {
+          for (track, open) in self.open.iter_mut().enumerate() {
+            if *open {
+              events[track].push(GateEvent::off(offset));
+              *open = false;
+            }
+          }
+        },
+        Tick::Step(offset) => {
+          for (track, pattern) in self.patterns.iter().enumerate() {
+            if pattern[self.step] {
+              events[track].push(GateEvent::on(offset));
+              self.open[track] = true;
+            }
+          }
+          self.step = (self.step + 1) % self.steps();
+        }
+      }
+    }
+    events
+  }
+
+  /// Advances the sequencer by the block of `context`, following its
+  /// transport, if any, as with `sync()`.
+  pub fn render_context(&mut self, context: &ProcessContext<T>) -> Vec<Vec<GateEvent>> {
+    if let Some(transport) = context.transport {
+      self.sync(transport);
+    }
+    self.render(context.block_size)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use render::OfflineRenderer;
+
+  #[test]
+  fn timing() {
+    // 8 steps of 100 samples
+    let mut sequencer = StepSequencer::new(1_000f64, 1, 8);
+    sequencer.set_tempo(150f64);
+    assert!((sequencer.step_length() - 100f64).abs() < 1e-9f64);
+    sequencer.set_pattern(0, &[true, false]);
+
+    let events = sequencer.render(450);
+    assert_eq!(events[0], vec![
+      GateEvent::on(0), GateEvent::off(50),
+      GateEvent::on(200), GateEvent::off(250),
+      GateEvent::on(400)
+    ]);
+    assert_eq!(sequencer.next_step(), 5);
+
+    // Offsets are relative to each block
+    let events = sequencer.render(400);
+    assert_eq!(events[0], vec![GateEvent::off(0), GateEvent::on(150), GateEvent::off(200), GateEvent::on(350)]);
+  }
+
+  #[test]
+  fn no_drift() {
+    // A step of 44100 * 60 / 560 = 4725 samples, and a bit
+    let sample_rate = 44_100f64;
+    let mut sequencer = StepSequencer::new(sample_rate, 1, 4);
+    sequencer.set_tempo(140.3f64);
+    sequencer.set_pattern(0, &[true]);
+    let step_length = sequencer.step_length();
+
+    let mut onsets = Vec::new();
+    for block in 0..2_000 {
+      for event in sequencer.render(256)[0].iter().filter(|event| event.on) {
+        onsets.push(block * 256 + event.offset);
+      }
+    }
+    for (n, onset) in onsets.iter().enumerate() {
+      assert_eq!(*onset, (n as f64 * step_length).ceil() as usize);
+    }
+  }
+
+  #[test]
+  fn full_gates() {
+    let mut sequencer = StepSequencer::new(1_000f32, 2, 2);
+    sequencer.set_tempo(300f32);
+    sequencer.set_gate_length(1f32);
+    sequencer.set_pattern(0, &[true]);
+    sequencer.set_step(1, 1, true);
+    assert!(sequencer.get_step(1, 1));
+
+    let events = sequencer.render(100);
+    assert_eq!(events[0], vec![GateEvent::on(0), GateEvent::off(50), GateEvent::on(50)]);
+    assert_eq!(events[1], vec![GateEvent::on(50)]);
+
+    sequencer.reset();
+    assert_eq!(sequencer.render(1)[1], vec![]);
+  }
+
+  #[test]
+  fn sync() {
+    // Steps of 100 samples, from the third beat
+    let mut transport = Transport::new(1_000f64);
+    transport.set_tempo(150f64);
+    transport.locate(2f64);
+    transport.play();
+
+    let mut sequencer = StepSequencer::new(1_000f64, 1, 16);
+    sequencer.set_pattern(0, &[true, false, false, false]);
+    sequencer.set_tempo(60f64);
+    sequencer.sync(&transport);
+    assert_eq!(sequencer.get_tempo(), 150f64);
+    assert_eq!(sequencer.next_step(), 8);
+
+    let mut onsets = Vec::new();
+    for block in 0..12 {
+      sequencer.sync(&transport);
+      for event in sequencer.render(128)[0].iter().filter(|event| event.on) {
+        onsets.push(block * 128 + event.offset);
+      }
+      transport.advance(128);
+    }
+    assert_eq!(onsets, vec![0, 400, 800, 1_200]);
+
+    // The transport of a context is followed in the same way
+    let mut renderer = OfflineRenderer::new(1_000f64);
+    renderer.set_block_size(128);
+    renderer.transport().set_tempo(150f64);
+    let mut onsets = Vec::new();
+    let mut start = 0;
+    renderer.stream_context(1.536f64, |block, context| {
+      for event in sequencer.render_context(context)[0].iter().filter(|event| event.on) {
+        onsets.push(start + event.offset);
+      }
+      start += block.len();
+    }, |_| {});
+    assert_eq!(onsets, vec![0, 400, 800, 1_200]);
+
+    // A step within the last sample of a block starts the next one
+    transport.locate(0f64);
+    transport.set_tempo(60f64 * 1_000f64 / 999.5f64 / 4f64);
+    sequencer.set_step(0, 1, true);
+    sequencer.sync(&transport);
+    assert_eq!(sequencer.render(1_000)[0], vec![GateEvent::on(0), GateEvent::off(500)]);
+    transport.advance(1_000);
+    sequencer.sync(&transport);
+    assert_eq!(sequencer.next_step(), 1);
+    assert_eq!(sequencer.render(1)[0], vec![GateEvent::on(0)]);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut sequencer = StepSequencer::new(44_100f32, 1, 16);
+    sequencer.set_tempo(0f32);
+    assert_eq!(sequencer.get_tempo(), 120f32);
+    sequencer.set_steps_per_beat(0);
+    assert_eq!(sequencer.get_steps_per_beat(), 4);
+    sequencer.set_gate_length(1.5f32);
+    assert_eq!(sequencer.get_gate_length(), 0.5f32);
+    assert_eq!(sequencer.tracks(), 1);
+    assert_eq!(sequencer.steps(), 16);
+  }
+}
@@ -0,0 +1,351 @@
+//! First-order ambisonics encoding and decoding.
+//!
+//! A mono source is encoded into a first-order B-format sound field, made of
+//! an omnidirectional component, `w`, and three figure-of-eight components,
+//! `x`, `y`, and `z`. The sound field can then be decoded to any layout of
+//! loudspeakers, or through head-related impulse responses for headphones.
+//!
+//! The `w` component is scaled by `1/sqrt(2)`, following the traditional
+//! B-format (FuMa) convention.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::spatial::ambisonics::{Decoder, Encoder};
+//!
+//! let mut encoder = Encoder::new();
+//! encoder.set_direction(30f32, 0f32);
+//!
+//! let decoder = Decoder::quad();
+//! let mut speakers = vec![0f32; decoder.len()];
+//! decoder.decode(encoder.encode(0.5f32), &mut speakers);
+//! ```
+
+use num;
+use num::traits::Float;
+
+use spatial::{direction, BinauralPanner};
+use traits::{FloatConst, Panner};
+
+/// A single first-order B-format sample frame.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BFormat<T> {
+  /// The omnidirectional component
+  pub w: T,
+  /// The front-back component
+  pub x: T,
+  /// The left-right component
+  pub y: T,
+  /// The up-down component
+  pub z: T
+}
+
+impl<T> BFormat<T> where T: Float {
+  /// Creates a silent `BFormat` frame.
+  pub fn zero() -> Self {
+    BFormat {
+      w: num::zero(),
+      x: num::zero(),
+      y: num::zero(),
+      z: num::zero()
+    }
+  }
+}
+
+/// Encodes a mono source at a direction into B-format.
+pub struct Encoder<T> {
+  gains: BFormat<T>
+}
+
+impl<T> Encoder<T> where T: Float + FloatConst {
+  /// Creates a new `Encoder` with the source straight ahead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::spatial::ambisonics::Encoder;
+  ///
+  /// let mut encoder1: Encoder<f32> = Encoder::new();
+  /// let mut encoder2: Encoder<f64> = Encoder::new();
+  /// let mut encoder3 = Encoder::<f32>::new();
+  /// let mut encoder4 = Encoder::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    let mut encoder = Encoder { gains: BFormat::zero() };
+    encoder.set_direction(num::zero(), num::zero());
+    encoder
+  }
+
+  /// Sets the source direction, `azimuth` and `elevation`, in degrees.
+  pub fn set_direction(&mut self, azimuth: T, elevation: T) {
+    let (x, y, z) = direction(azimuth, elevation);
+    self.gains = BFormat {
+      w: T::one() / T::two().sqrt(),
+      x,
+      y,
+      z
+    };
+  }
+
+  /// Encodes a mono sample into a B-format frame.
+  pub fn encode(&self, sample: T) -> BFormat<T> {
+    BFormat {
+      w: self.gains.w * sample,
+      x: self.gains.x * sample,
+      y: self.gains.y * sample,
+      z: self.gains.z * sample
+    }
+  }
+}
+
+/// Decodes B-format to a layout of loudspeakers.
+///
+/// Each loudspeaker is fed by a virtual microphone pointing in the
+/// loudspeaker's direction. The microphone pattern is set by its
+/// directivity, which ranges from omnidirectional at zero, through cardioid
+/// at one, to figure-of-eight at two.
+pub struct Decoder<T> {
+  // Decoding gains for each loudspeaker
+  speakers: Vec<BFormat<T>>,
+  directions: Vec<(T, T)>
+}
+
+impl<T> Decoder<T> where T: Float + FloatConst {
+  /// Creates a new `Decoder` for loudspeakers at the given `(azimuth,
+  /// elevation)` directions, in degrees, using virtual microphones of the
+  /// given `directivity`.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::spatial::ambisonics::Decoder;
+  ///
+  /// // A hexagon of supercardioids
+  /// let layout: Vec<(f32, f32)> =
+  ///   (0..6).map(|i| (i as f32 * 60f32, 0f32)).collect();
+  /// let decoder = Decoder::new(&layout, 1.5f32);
+  /// assert_eq!(decoder.len(), 6);
+  /// ```
+  pub fn new(directions: &[(T, T)], directivity: T) -> Self {
+    let two = T::two();
+    let half: T = num::cast(0.5f64).unwrap();
+    let speakers = directions.iter()
+      .map(|&(azimuth, elevation)| {
+        let (x, y, z) = direction(azimuth, elevation);
+        let d = directivity * half;
+        BFormat {
+          w: (two - directivity) * half * two.sqrt(),
+          x: d * x,
+          y: d * y,
+          z: d * z
+        }
+      })
+      .collect();
+
+    Decoder {
+      speakers,
+      directions: directions.to_vec()
+    }
+  }
+
+  /// Creates a stereo `Decoder`, using cardioids pointing left and right.
+  ///
+  /// The left loudspeaker is the first output.
+  pub fn stereo() -> Self {
+    let ninety: T = num::cast(90f64).unwrap();
+    Decoder::new(&[(ninety, T::zero()), (-ninety, T::zero())], T::one())
+  }
+
+  /// Creates a quadraphonic `Decoder`, using cardioids.
+  ///
+  /// The outputs are front left, back left, back right, and front right.
+  pub fn quad() -> Self {
+    let angle = |degrees: f64| -> (T, T) { (num::cast(degrees).unwrap(), T::zero()) };
+    Decoder::new(&[angle(45f64), angle(135f64), angle(-135f64), angle(-45f64)], T::one())
+  }
+
+  /// Returns the number of loudspeakers.
+  pub fn len(&self) -> usize {
+    self.speakers.len()
+  }
+
+  /// Returns `true` if there are no loudspeakers.
+  pub fn is_empty(&self) -> bool {
+    self.speakers.is_empty()
+  }
+
+  /// Returns the `(azimuth, elevation)` direction of each loudspeaker.
+  pub fn directions(&self) -> &[(T, T)] {
+    &self.directions
+  }
+
+  /// Decodes a B-format frame into one sample per loudspeaker.
+  ///
+  /// `out` must have one sample for each loudspeaker.
+  pub fn decode(&self, frame: BFormat<T>, out: &mut [T]) {
+    debug_assert_eq!(out.len(), self.speakers.len());
+    for (sample, gains) in out.iter_mut().zip(self.speakers.iter()) {
+      *sample = gains.w * frame.w + gains.x * frame.x
+              + gains.y * frame.y + gains.z * frame.z;
+    }
+  }
+}
+
+/// Decodes B-format to binaural stereo for headphones.
+///
+/// The sound field is decoded to a layout of virtual loudspeakers, and each
+/// loudspeaker is rendered through a `BinauralPanner` at its direction.
+pub struct BinauralDecoder<T> {
+  decoder: Decoder<T>,
+  panners: Vec<BinauralPanner<T>>,
+  speakers: Vec<T>,
+  output: (T, T)
+}
+
+impl<T> BinauralDecoder<T> where T: Float + FloatConst {
+  /// Creates a new `BinauralDecoder` using the virtual loudspeakers of
+  /// `decoder`.
+  ///
+  /// Until HRIR pairs are added, the decoder outputs silence.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::spatial::ambisonics::{BinauralDecoder, Decoder};
+  ///
+  /// let mut decoder = BinauralDecoder::new(Decoder::<f32>::quad());
+  /// decoder.add_hrir( 90f32, 0f32, &[1f32], &[0.2f32]);
+  /// decoder.add_hrir(-90f32, 0f32, &[0.2f32], &[1f32]);
+  /// ```
+  pub fn new(decoder: Decoder<T>) -> Self {
+    let panners = decoder.directions().iter()
+      .map(|&(azimuth, elevation)| {
+        let mut panner = BinauralPanner::new();
+        panner.set_position(azimuth, elevation);
+        panner
+      })
+      .collect();
+    let speakers = vec![num::zero(); decoder.len()];
+
+    BinauralDecoder {
+      decoder,
+      panners,
+      speakers,
+      output: (num::zero(), num::zero())
+    }
+  }
+
+  /// Adds an HRIR pair measured at `azimuth` and `elevation`, in degrees,
+  /// to every virtual loudspeaker.
+  pub fn add_hrir(&mut self, azimuth: T, elevation: T, left: &[T], right: &[T]) {
+    for panner in self.panners.iter_mut() {
+      panner.add_hrir(azimuth, elevation, left, right);
+    }
+  }
+
+  /// Processes a B-format frame and outputs the pair of binaural samples.
+  pub fn process(&mut self, frame: BFormat<T>) -> (T, T) {
+    self.decoder.decode(frame, &mut self.speakers);
+
+    let mut out_l = T::zero();
+    let mut out_r = T::zero();
+    for (panner, sample) in self.panners.iter_mut().zip(self.speakers.iter()) {
+      let (l, r) = panner.process(*sample);
+      out_l = out_l + l;
+      out_r = out_r + r;
+    }
+
+    self.output = (out_l, out_r);
+    self.output
+  }
+
+  /// Resets memory of all previous input and output to zero.
+  pub fn clear(&mut self) {
+    for panner in self.panners.iter_mut() {
+      panner.clear();
+    }
+    self.output = (num::zero(), num::zero());
+  }
+
+  /// Returns the last computed pair of output samples.
+  pub fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn encode() {
+    let mut encoder = Encoder::new();
+
+    let frame = encoder.encode(1f32);
+    assert!((frame.w - ::std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6f32);
+    assert!((frame.x - 1f32).abs() < 1e-6f32);
+    assert!(frame.y.abs() < 1e-6f32);
+    assert!(frame.z.abs() < 1e-6f32);
+
+    encoder.set_direction(90f32, 0f32);
+    let frame = encoder.encode(0.5f32);
+    assert!(frame.x.abs() < 1e-6f32);
+    assert!((frame.y - 0.5f32).abs() < 1e-6f32);
+
+    encoder.set_direction(0f32, 90f32);
+    let frame = encoder.encode(1f32);
+    assert!((frame.z - 1f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn decode() {
+    let mut encoder = Encoder::new();
+    let decoder = Decoder::stereo();
+    let mut speakers = vec![0f32; 2];
+
+    // A hard left source only reaches the left cardioid
+    encoder.set_direction(90f32, 0f32);
+    decoder.decode(encoder.encode(1f32), &mut speakers);
+    assert!((speakers[0] - 1f32).abs() < 1e-6f32);
+    assert!(speakers[1].abs() < 1e-6f32);
+
+    // A centered source is split evenly
+    encoder.set_direction(0f32, 0f32);
+    decoder.decode(encoder.encode(1f32), &mut speakers);
+    assert!((speakers[0] - 0.5f32).abs() < 1e-6f32);
+    assert!((speakers[1] - 0.5f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn decode_quad() {
+    let mut encoder = Encoder::new();
+    let decoder = Decoder::quad();
+    let mut speakers = vec![0f32; decoder.len()];
+
+    // The loudest loudspeaker is the one nearest to the source
+    encoder.set_direction(-30f32, 0f32);
+    decoder.decode(encoder.encode(1f32), &mut speakers);
+    for i in 0..3 {
+      assert!(speakers[3] > speakers[i]);
+    }
+  }
+
+  #[test]
+  fn binaural_decoder() {
+    let mut encoder = Encoder::new();
+    let mut decoder = BinauralDecoder::new(Decoder::stereo());
+    decoder.add_hrir( 90f32, 0f32, &[1f32], &[0f32]);
+    decoder.add_hrir(-90f32, 0f32, &[0f32], &[1f32]);
+
+    encoder.set_direction(90f32, 0f32);
+    let (left, right) = decoder.process(encoder.encode(1f32));
+    assert!((left - 1f32).abs() < 1e-6f32);
+    assert!(right.abs() < 1e-6f32);
+
+    decoder.clear();
+    let (left, right) = decoder.last_out();
+    assert!(left.abs() < 1e-6f32);
+    assert!(right.abs() < 1e-6f32);
+  }
+}
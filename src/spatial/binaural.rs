@@ -0,0 +1,291 @@
+use num;
+use num::traits::Float;
+
+use filter::Fir;
+use spatial::direction;
+use traits::{FloatConst, Panner, Processor};
+
+/// A measured head-related impulse response pair.
+struct Hrir<T> {
+  // Unit vector pointing at the measured direction
+  direction: (T, T, T),
+  left: Vec<T>,
+  right: Vec<T>
+}
+
+/// A binaural panner using head-related impulse responses (HRIRs).
+///
+/// A mono source is convolved with a pair of HRIRs to position it around the
+/// listener. The HRIR pair is selected from a user-provided set by the
+/// source direction, and is interpolated from the nearest measurements when
+/// there is no measurement for the exact direction. Each ear is convolved by
+/// a `filter::Fir`.
+pub struct BinauralPanner<T> {
+  hrirs: Vec<Hrir<T>>,
+  // The HRIR pair for the current direction, as long as the longest response
+  // in the set
+  taps: (Vec<T>, Vec<T>),
+  filters: (Fir<T>, Fir<T>),
+  azimuth: T,
+  elevation: T,
+  output: (T, T)
+}
+
+impl<T> BinauralPanner<T> where T: Float + FloatConst {
+  /// Creates a new `BinauralPanner` with an empty HRIR set.
+  ///
+  /// Until an HRIR pair is added, the panner outputs silence.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::spatial::BinauralPanner;
+  ///
+  /// let mut panner1: BinauralPanner<f32> = BinauralPanner::new();
+  /// let mut panner2: BinauralPanner<f64> = BinauralPanner::new();
+  /// let mut panner3 = BinauralPanner::<f32>::new();
+  /// let mut panner4 = BinauralPanner::<f64>::new();
+  /// ```
+  pub fn new() -> Self {
+    BinauralPanner {
+      hrirs: Vec::new(),
+      taps: (vec![num::zero()], vec![num::zero()]),
+      filters: (Fir::new(&[num::zero()]), Fir::new(&[num::zero()])),
+      azimuth: num::zero(),
+      elevation: num::zero(),
+      output: (num::zero(), num::zero())
+    }
+  }
+
+  /// Adds an HRIR pair measured at `azimuth` and `elevation`, in degrees.
+  ///
+  /// Impulse responses of different lengths are zero-padded to the length of
+  /// the longest response in the set. If `azimuth` or `elevation` is not
+  /// finite, the pair is not added.
+  pub fn add_hrir(&mut self, azimuth: T, elevation: T, left: &[T], right: &[T]) {
+    if !azimuth.is_finite() || !elevation.is_finite() {
+      return;
+    }
+    self.hrirs.push(
+      Hrir {
+        direction: direction(azimuth, elevation),
+        left: left.to_vec(),
+        right: right.to_vec()
+      });
+
+    let length = self.hrirs.iter()
+      .map(|hrir| hrir.left.len().max(hrir.right.len()))
+      .max()
+      .unwrap_or(1)
+      .max(1);
+    if length > self.taps.0.len() {
+      self.taps.0.resize(length, num::zero());
+      self.taps.1.resize(length, num::zero());
+    }
+
+    let (azimuth, elevation) = (self.azimuth, self.elevation);
+    self.set_position(azimuth, elevation);
+  }
+
+  /// Returns the number of HRIR pairs in the set.
+  pub fn len(&self) -> usize {
+    self.hrirs.len()
+  }
+
+  /// Returns `true` if the HRIR set is empty.
+  pub fn is_empty(&self) -> bool {
+    self.hrirs.is_empty()
+  }
+
+  /// Sets the source direction, `azimuth` and `elevation`, in degrees.
+  ///
+  /// If no HRIR pair was measured at this direction, the pair is built by
+  /// weighting the three nearest measurements by their inverse angular
+  /// distance. Changing the position does not crossfade between responses.
+  /// If `azimuth` or `elevation` is not finite, the position is not updated.
+  pub fn set_position(&mut self, azimuth: T, elevation: T) {
+    if !azimuth.is_finite() || !elevation.is_finite() {
+      return;
+    }
+    self.azimuth = azimuth;
+    self.elevation = elevation;
+
+    for tap in self.taps.0.iter_mut().chain(self.taps.1.iter_mut()) {
+      *tap = num::zero();
+    }
+    if !self.hrirs.is_empty() {
+      self.interpolate();
+    }
+    self.filters.0.set_taps(&self.taps.0);
+    self.filters.1.set_taps(&self.taps.1);
+  }
+
+  /// Adds the three nearest measurements to the zeroed taps, weighted by
+  /// their inverse angular distance to the current direction.
+  fn interpolate(&mut self) {
+    // The nearest measurements, by angular distance, kept in order without
+    // allocating
+    let target = direction(self.azimuth, self.elevation);
+    let mut nearest: [(usize, T); 3] = [(0, T::infinity()); 3];
+    for (i, hrir) in self.hrirs.iter().enumerate() {
+      let (x, y, z) = hrir.direction;
+      let dot = x * target.0 + y * target.1 + z * target.2;
+      let distance = dot.max(-T::one()).min(T::one()).acos();
+      if let Some(rank) = nearest.iter().position(|&(_, nearer)| distance < nearer) {
+        for slot in (rank + 1..3).rev() {
+          nearest[slot] = nearest[slot - 1];
+        }
+        nearest[rank] = (i, distance);
+      }
+    }
+    let count = self.hrirs.len().min(3);
+    let nearest = &nearest[..count];
+
+    let threshold: T = num::cast(1e-3f64).unwrap();
+    let exact = nearest[0].1 < threshold;
+    let weight = |distance: T| if exact { T::one() } else { T::one() / distance };
+    let nearest = if exact { &nearest[..1] } else { nearest };
+    let total = nearest.iter().fold(T::zero(), |sum, &(_, distance)| sum + weight(distance));
+
+    for &(i, distance) in nearest.iter() {
+      let gain = weight(distance) / total;
+      let hrir = &self.hrirs[i];
+      for (tap, h) in self.taps.0.iter_mut().zip(hrir.left.iter()) {
+        *tap = *tap + gain * *h;
+      }
+      for (tap, h) in self.taps.1.iter_mut().zip(hrir.right.iter()) {
+        *tap = *tap + gain * *h;
+      }
+    }
+  }
+
+  /// Returns the source azimuth, in degrees.
+  pub fn get_azimuth(&self) -> T {
+    self.azimuth
+  }
+
+  /// Returns the source elevation, in degrees.
+  pub fn get_elevation(&self) -> T {
+    self.elevation
+  }
+}
+
+impl<T> Panner<T> for BinauralPanner<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> (T, T) {
+    self.output = (self.filters.0.process(sample), self.filters.1.process(sample));
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.filters.0.clear();
+    self.filters.1.clear();
+    self.output = (num::zero(), num::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+
+  #[test]
+  fn new() {
+    let mut panner = BinauralPanner::<f32>::new();
+    assert!(panner.is_empty());
+
+    let (left, right) = panner.process(1f32);
+    assert!((left - 0f32).abs() < EPSILON);
+    assert!((right - 0f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn process() {
+    let hrir_l = vec![1f32, 0.5f32, 0.25f32];
+    let hrir_r = vec![0f32, 0.5f32];
+    let mut panner = BinauralPanner::new();
+    panner.add_hrir(0f32, 0f32, &hrir_l, &hrir_r);
+    assert_eq!(panner.len(), 1);
+
+    // The impulse response is the HRIR pair
+    let input = vec![1f32, 0f32, 0f32, 0f32];
+    let expected_l = vec![1f32, 0.5f32, 0.25f32, 0f32];
+    let expected_r = vec![0f32, 0.5f32, 0f32, 0f32];
+    let mut left = vec![0f32; 4];
+    let mut right = vec![0f32; 4];
+    panner.process_block(&input, &mut left, &mut right);
+
+    for i in 0..input.len() {
+      assert!((expected_l[i] - left[i]).abs() < EPSILON);
+      assert!((expected_r[i] - right[i]).abs() < EPSILON);
+    }
+  }
+
+  #[test]
+  fn set_position() {
+    let mut panner = BinauralPanner::new();
+    panner.add_hrir( 90f32, 0f32, &[1f32], &[0f32]);
+    panner.add_hrir(-90f32, 0f32, &[0f32], &[1f32]);
+
+    // Exact measurements are selected
+    panner.set_position(90f32, 0f32);
+    let (left, right) = panner.process(1f32);
+    assert!((left - 1f32).abs() < EPSILON);
+    assert!((right - 0f32).abs() < EPSILON);
+
+    // Between measurements, the pair is interpolated
+    panner.set_position(0f32, 0f32);
+    let (left, right) = panner.process(1f32);
+    assert!((left - 0.5f32).abs() < 1e-6f32);
+    assert!((right - 0.5f32).abs() < 1e-6f32);
+
+    panner.set_position(45f32, 0f32);
+    let (left, right) = panner.process(1f32);
+    assert!((left - 0.75f32).abs() < 1e-6f32);
+    assert!((right - 0.25f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn non_finite() {
+    let mut panner = BinauralPanner::new();
+    panner.add_hrir(90f32, 0f32, &[1f32], &[0f32]);
+    panner.add_hrir(::std::f32::NAN, 0f32, &[0f32], &[1f32]);
+    assert_eq!(panner.len(), 1);
+
+    panner.set_position(30f32, 10f32);
+    panner.set_position(::std::f32::NAN, 0f32);
+    panner.set_position(0f32, ::std::f32::INFINITY);
+    assert_eq!((panner.get_azimuth(), panner.get_elevation()), (30f32, 10f32));
+    assert_eq!(panner.process(1f32), (1f32, 0f32));
+  }
+
+  #[test]
+  fn moving_source() {
+    let mut panner = BinauralPanner::new();
+    panner.add_hrir( 90f32, 0f32, &[0f32, 1f32], &[0f32, 0f32]);
+    panner.add_hrir(-90f32, 0f32, &[0f32, 0f32], &[0f32, 1f32]);
+    panner.set_position(90f32, 0f32);
+
+    // The input is kept when the source moves, so a tail continues through
+    // the new responses
+    panner.process(1f32);
+    panner.set_position(-90f32, 0f32);
+    assert_eq!(panner.process(0f32), (0f32, 1f32));
+  }
+
+  #[test]
+  fn clear() {
+    let mut panner = BinauralPanner::new();
+    panner.add_hrir(0f32, 0f32, &[0f32, 1f32], &[0f32, 1f32]);
+    panner.process(1f32);
+    panner.clear();
+
+    let (left, right) = panner.process(0f32);
+    assert!((left - 0f32).abs() < EPSILON);
+    assert!((right - 0f32).abs() < EPSILON);
+  }
+}
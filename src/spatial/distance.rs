@@ -0,0 +1,178 @@
+use num;
+use num::traits::Float;
+
+use filter::OnePole;
+use traits::{FloatConst, Processor};
+
+/// The product of distance and air absorption cutoff frequency, in meters
+/// times hertz. At 100 meters, high frequencies are rolled off from 4 kHz.
+const AIR_ABSORPTION: f64 = 400_000f64;
+
+/// Distance attenuation with an air absorption approximation.
+///
+/// The source is attenuated with the inverse distance law, relative to a
+/// reference distance at which the gain is one. Air absorbs high frequencies
+/// more strongly over distance, which is approximated with a one-pole
+/// low-pass whose cutoff frequency is inversely proportional to the
+/// distance.
+pub struct Distance<T: Float> {
+  sample_rate: T,
+  distance: T,
+  reference_distance: T,
+  gain: T,
+  lowpass: OnePole<T>,
+  output: T
+}
+
+impl<T> Distance<T> where T: Float + FloatConst {
+  /// Creates a new `Distance` with the source at the reference distance of
+  /// one meter, where the input is not altered.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::spatial::Distance;
+  ///
+  /// let sample_rate = 44_100f32;
+  ///
+  /// let mut distance1: Distance<f32> = Distance::new(sample_rate);
+  /// let mut distance2: Distance<f64> = Distance::new(sample_rate as f64);
+  /// let mut distance3 = Distance::<f32>::new(sample_rate);
+  /// let mut distance4 = Distance::<f64>::new(sample_rate as f64);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let mut distance =
+      Distance {
+        sample_rate,
+        distance: num::one(),
+        reference_distance: num::one(),
+        gain: num::one(),
+        lowpass: OnePole::new(),
+        output: num::zero()
+      };
+    distance.set_distance(num::one());
+    distance
+  }
+
+  /// Sets the distance between the source and the listener, in meters.
+  ///
+  /// Sources closer than the reference distance are not amplified. Negative
+  /// or non-finite distances are ignored. The processing memory is not
+  /// cleared, so the distance may be changed while processing.
+  pub fn set_distance(&mut self, distance: T) {
+    if distance < T::zero() || !distance.is_finite() {
+      return;
+    }
+    self.distance = distance;
+    self.gain = self.reference_distance / distance.max(self.reference_distance);
+
+    let air_absorption: T = num::cast(AIR_ABSORPTION).unwrap();
+    let cutoff = air_absorption / distance;
+    let pole = (-T::two() * T::pi() * cutoff / self.sample_rate).exp();
+    self.lowpass.set_coefficients(T::one() - pole, -pole);
+  }
+
+  /// Returns the distance between the source and the listener, in meters.
+  pub fn get_distance(&self) -> T {
+    self.distance
+  }
+
+  /// Sets the reference distance, in meters, at which the gain is one.
+  ///
+  /// `reference_distance` must be greater than zero, else it is not updated.
+  pub fn set_reference_distance(&mut self, reference_distance: T) {
+    if reference_distance > T::zero() && reference_distance.is_finite() {
+      self.reference_distance = reference_distance;
+      let distance = self.distance;
+      self.set_distance(distance);
+    }
+  }
+
+  /// Returns the reference distance, in meters.
+  pub fn get_reference_distance(&self) -> T {
+    self.reference_distance
+  }
+
+  /// Returns the current distance gain.
+  pub fn get_gain(&self) -> T {
+    self.gain
+  }
+}
+
+impl<T> Processor<T> for Distance<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    self.output = self.gain * self.lowpass.process(sample);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.lowpass.clear();
+    self.output = num::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+  use ::traits::Processor;
+
+  #[test]
+  fn new() {
+    let mut distance = Distance::new(44_100f32);
+    assert!((distance.process(0.5f32) - 0.5f32).abs() < 1e-6f32);
+    assert!((distance.process(-0.5f32) - -0.5f32).abs() < 1e-6f32);
+  }
+
+  #[test]
+  fn inverse_distance_gain() {
+    let mut distance = Distance::new(44_100f32);
+
+    distance.set_distance(2f32);
+    assert!((distance.get_gain() - 0.5f32).abs() < EPSILON);
+    distance.set_distance(10f32);
+    assert!((distance.get_gain() - 0.1f32).abs() < EPSILON);
+
+    // Close sources are not amplified
+    distance.set_distance(0.5f32);
+    assert!((distance.get_gain() - 1f32).abs() < EPSILON);
+
+    distance.set_reference_distance(5f32);
+    distance.set_distance(10f32);
+    assert!((distance.get_gain() - 0.5f32).abs() < EPSILON);
+
+    // Invalid distances are ignored
+    distance.set_distance(-1f32);
+    assert!((distance.get_distance() - 10f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn air_absorption() {
+    let mut distance = Distance::new(44_100f32);
+    distance.set_distance(100f32);
+
+    // Low frequencies pass with the distance gain
+    let mut output = 0f32;
+    for _ in 0..4410 {
+      output = distance.process(1f32);
+    }
+    assert!((output - 0.01f32).abs() < 1e-6f32);
+
+    // High frequencies are attenuated further
+    distance.clear();
+    let mut peak = 0f32;
+    for n in 0..4410 {
+      let input = if n % 2 == 0 { 1f32 } else { -1f32 };
+      output = distance.process(input);
+      if n > 100 {
+        peak = peak.max(output.abs());
+      }
+    }
+    assert!(peak < 0.01f32 * 0.5f32);
+  }
+}
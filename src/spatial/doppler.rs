@@ -0,0 +1,205 @@
+use num;
+use num::traits::Float;
+
+use delay::LinearDelay;
+use spatial::SPEED_OF_SOUND;
+use traits::Processor;
+
+/// A Doppler shift simulation.
+///
+/// The source is delayed by the time it takes sound to travel from the
+/// source to the listener. As the distance changes, the delay time changes
+/// with it, which shifts the pitch of the source. The delay time is smoothed
+/// to avoid zipper artifacts when the distance is updated in steps.
+pub struct Doppler<T> {
+  delay: LinearDelay<T>,
+  sample_rate: T,
+  max_distance: T,
+  // Target distance, in meters
+  distance: T,
+  // Velocity away from the listener, in meters per second
+  velocity: T,
+  // Smoothed delay time, in samples
+  delay_samples: T,
+  // Feedback gain of the delay time smoothing integrator
+  alpha: T
+}
+
+impl<T> Doppler<T> where T: Float {
+  /// Creates a new `Doppler` for sources up to `max_distance` meters away.
+  ///
+  /// The source will be initialized at the listener, so the input is not
+  /// altered until the distance or velocity is set.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::spatial::Doppler;
+  ///
+  /// let sample_rate = 44_100f32;
+  /// let max_distance = 100f32; // meters
+  ///
+  /// let mut doppler1: Doppler<f32> = Doppler::new(sample_rate, max_distance);
+  /// let mut doppler2: Doppler<f64> = Doppler::new(sample_rate as f64, max_distance as f64);
+  /// let mut doppler3 = Doppler::<f32>::new(sample_rate, max_distance);
+  /// let mut doppler4 = Doppler::<f64>::new(sample_rate as f64, max_distance as f64);
+  /// ```
+  pub fn new(sample_rate: T, max_distance: T) -> Self {
+    let speed_of_sound: T = num::cast(SPEED_OF_SOUND).unwrap();
+    let max_delay: f32 = num::cast(max_distance / speed_of_sound * sample_rate).unwrap();
+
+    let mut doppler =
+      Doppler {
+        delay: LinearDelay::new(0f32, max_delay.ceil() as usize + 1),
+        sample_rate,
+        max_distance,
+        distance: num::zero(),
+        velocity: num::zero(),
+        delay_samples: num::zero(),
+        alpha: num::zero()
+      };
+    // 20 millisecond smoothing
+    doppler.set_smoothing(sample_rate * num::cast(0.02f64).unwrap());
+    doppler
+  }
+
+  /// Sets the smoothing time of the delay, in samples.
+  ///
+  /// `smoothing_length` must be greater than zero, else the smoothing is not
+  /// updated.
+  pub fn set_smoothing(&mut self, smoothing_length: T) {
+    if smoothing_length > num::zero() && smoothing_length.is_finite() {
+      self.alpha = (-T::one() / smoothing_length).exp();
+    }
+  }
+
+  /// Sets the distance between the source and the listener, in meters.
+  ///
+  /// The distance is clipped to the range `[0, max_distance]`.
+  pub fn set_distance(&mut self, distance: T) {
+    self.distance = distance.max(T::zero()).min(self.max_distance);
+  }
+
+  /// Returns the distance between the source and the listener, in meters.
+  pub fn get_distance(&self) -> T {
+    self.distance
+  }
+
+  /// Sets the velocity of the source away from the listener, in meters per
+  /// second.
+  ///
+  /// While the velocity is not zero, the distance is moved by the velocity
+  /// every sample. A negative velocity moves the source towards the
+  /// listener.
+  pub fn set_velocity(&mut self, velocity: T) {
+    self.velocity = velocity;
+  }
+
+  /// Returns the velocity of the source away from the listener, in meters
+  /// per second.
+  pub fn get_velocity(&self) -> T {
+    self.velocity
+  }
+
+  fn target_delay(&self) -> T {
+    let speed_of_sound: T = num::cast(SPEED_OF_SOUND).unwrap();
+    self.distance / speed_of_sound * self.sample_rate
+  }
+}
+
+impl<T> Processor<T> for Doppler<T> where T: Float {
+  fn process(&mut self, sample: T) -> T {
+    if self.velocity != T::zero() {
+      let distance = self.distance + self.velocity / self.sample_rate;
+      self.set_distance(distance);
+    }
+
+    let target = self.target_delay();
+    self.delay_samples = target + self.alpha * (self.delay_samples - target);
+    self.delay.set_delay(num::cast(self.delay_samples).unwrap());
+    self.delay.process(sample)
+  }
+
+  /// Resets memory of all previous input and output to zero, and moves the
+  /// delay time directly to the current distance.
+  fn clear(&mut self) {
+    self.delay.clear();
+    self.delay_samples = self.target_delay();
+  }
+
+  fn last_out(&self) -> T {
+    self.delay.last_out()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+  use std::f32::consts::PI;
+  use ::traits::Processor;
+
+  #[test]
+  fn new() {
+    let mut doppler = Doppler::new(44_100f32, 100f32);
+    assert!((doppler.process(0.5f32) - 0.5f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn set_distance() {
+    let mut doppler = Doppler::new(44_100f32, 100f32);
+    doppler.set_distance(200f32);
+    assert!((doppler.get_distance() - 100f32).abs() < EPSILON);
+    doppler.set_distance(-1f32);
+    assert!((doppler.get_distance() - 0f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn static_source_is_delayed() {
+    let sample_rate = 44_100f32;
+    let mut doppler = Doppler::new(sample_rate, 100f32);
+
+    // 3.43 meters is 10 milliseconds away
+    doppler.set_distance(3.43f32);
+    doppler.clear();
+
+    let delay = (0.01f32 * sample_rate).round() as usize;
+    for i in 0..delay + 1 {
+      let input = if i == 0 { 1f32 } else { 0f32 };
+      let output = doppler.process(input);
+      if i < delay {
+        assert!(output.abs() < 1e-4f32);
+      }
+      else {
+        assert!((output - 1f32).abs() < 1e-2f32);
+      }
+    }
+  }
+
+  #[test]
+  fn receding_source_is_lowered_in_pitch() {
+    let sample_rate = 44_100f32;
+    let frequency = 1_000f32;
+    let mut doppler = Doppler::new(sample_rate, 100f32);
+    doppler.set_distance(10f32);
+    doppler.clear();
+
+    // Receding at a tenth of the speed of sound lowers the pitch by a tenth
+    doppler.set_velocity(34.3f32);
+
+    let mut crossings = 0i32;
+    let mut previous = 0f32;
+    for n in 0..sample_rate as usize {
+      let input = (2f32 * PI * frequency * n as f32 / sample_rate).sin();
+      let output = doppler.process(input);
+      if n >= sample_rate as usize / 2 && previous < 0f32 && output >= 0f32 {
+        crossings += 1;
+      }
+      previous = output;
+    }
+
+    // Half a second at 900 Hz
+    assert!((crossings - 450).abs() <= 2);
+  }
+}
@@ -0,0 +1,36 @@
+//! Spatial audio processors for positioning sources around a listener.
+//!
+//! Directions are given as an azimuth and elevation in degrees. An azimuth
+//! of zero is straight ahead, and positive azimuths turn to the left.
+//! Distances are given in meters.
+
+pub mod ambisonics;
+mod binaural;
+mod distance;
+mod doppler;
+mod simple_panner;
+
+pub use self::binaural::BinauralPanner      as BinauralPanner;
+pub use self::distance::Distance            as Distance;
+pub use self::doppler::Doppler              as Doppler;
+pub use self::simple_panner::SimplePanner3D as SimplePanner3D;
+
+use num;
+use num::traits::Float;
+
+use traits::FloatConst;
+
+/// The speed of sound in air, in meters per second.
+pub const SPEED_OF_SOUND: f64 = 343f64;
+
+/// Returns the unit vector for an `azimuth` and `elevation`, in degrees.
+///
+/// The x axis points ahead, the y axis to the left, and the z axis up.
+fn direction<T: Float + FloatConst>(azimuth: T, elevation: T) -> (T, T, T) {
+  let degrees: T = num::cast(180f64).unwrap();
+  let azimuth = azimuth * T::pi() / degrees;
+  let elevation = elevation * T::pi() / degrees;
+  (elevation.cos() * azimuth.cos(),
+   elevation.cos() * azimuth.sin(),
+   elevation.sin())
+}
@@ -0,0 +1,207 @@
+use num;
+use num::traits::Float;
+
+use delay::LinearDelay;
+use filter::OnePole;
+use spatial::SPEED_OF_SOUND;
+use traits::{FloatConst, Panner, Processor};
+use util;
+
+/// The radius of an average head, in meters.
+const HEAD_RADIUS: f64 = 0.0875;
+/// The level difference, in dB, between the ears for a source at the side.
+const MAX_ILD: f64 = 6f64;
+/// The far ear cutoff frequency for a source at the side.
+const SIDE_CUTOFF: f64 = 1_500f64;
+
+/// The processing path to one ear.
+struct Ear<T: Float> {
+  delay: LinearDelay<T>,
+  lowpass: OnePole<T>,
+  gain: T
+}
+
+/// A 3D panner using interaural time and level differences.
+///
+/// A mono source is positioned by delaying, attenuating, and low-passing the
+/// signal reaching the ear farther from the source, approximating the head's
+/// acoustic shadow. The interaural time difference follows Woodworth's
+/// spherical head formula. This is much cheaper than HRIR convolution, but
+/// cannot resolve front from back or convey elevation.
+pub struct SimplePanner3D<T: Float> {
+  sample_rate: T,
+  azimuth: T,
+  left: Ear<T>,
+  right: Ear<T>,
+  output: (T, T)
+}
+
+impl<T> SimplePanner3D<T> where T: Float + FloatConst {
+  /// Creates a new `SimplePanner3D` with the source straight ahead.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// # #![allow(unused_mut)]
+  /// use rasp::spatial::SimplePanner3D;
+  ///
+  /// let sample_rate = 44_100f32;
+  ///
+  /// let mut panner1: SimplePanner3D<f32> = SimplePanner3D::new(sample_rate);
+  /// let mut panner2: SimplePanner3D<f64> = SimplePanner3D::new(sample_rate as f64);
+  /// let mut panner3 = SimplePanner3D::<f32>::new(sample_rate);
+  /// let mut panner4 = SimplePanner3D::<f64>::new(sample_rate as f64);
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    // Woodworth's formula peaks at about 0.66 milliseconds
+    let max_delay: f32 = num::cast(sample_rate * num::cast(0.001f64).unwrap()).unwrap();
+    let ear = || Ear {
+      delay: LinearDelay::new(0f32, max_delay.ceil() as usize + 1),
+      lowpass: OnePole::new(),
+      gain: num::one()
+    };
+
+    let mut panner =
+      SimplePanner3D {
+        sample_rate,
+        azimuth: num::zero(),
+        left: ear(),
+        right: ear(),
+        output: (num::zero(), num::zero())
+      };
+    panner.set_azimuth(num::zero());
+    panner
+  }
+
+  /// Sets the source `azimuth`, in degrees.
+  ///
+  /// Changing the azimuth does not clear the processing memory, so it may be
+  /// called while processing.
+  pub fn set_azimuth(&mut self, azimuth: T) {
+    self.azimuth = azimuth;
+
+    let one: T = T::one();
+    let degrees: T = num::cast(180f64).unwrap();
+    // The angle towards the left side, mirrored into [-90, 90] degrees
+    let lateral = (azimuth * T::pi() / degrees).sin().asin();
+    let shadow = lateral.sin().abs();
+
+    let head_radius: T = num::cast(HEAD_RADIUS).unwrap();
+    let speed_of_sound: T = num::cast(SPEED_OF_SOUND).unwrap();
+    let itd = head_radius / speed_of_sound * (lateral.abs() + shadow);
+    let delay: f32 = num::cast(itd * self.sample_rate).unwrap();
+
+    let max_ild: T = num::cast(MAX_ILD).unwrap();
+    let gain = util::to_sample(-max_ild * shadow);
+
+    // The far ear pole moves from zero, at the front, to the side cutoff
+    let side_cutoff: T = num::cast(SIDE_CUTOFF).unwrap();
+    let a1 = -shadow * (-T::two() * T::pi() * side_cutoff / self.sample_rate).exp();
+
+    let (near, far) =
+      if lateral >= T::zero() {
+        (&mut self.left, &mut self.right)
+      }
+      else {
+        (&mut self.right, &mut self.left)
+      };
+
+    near.delay.set_delay(0f32);
+    near.lowpass.set_coefficients(one, T::zero());
+    near.gain = one;
+
+    far.delay.set_delay(delay);
+    far.lowpass.set_coefficients(one + a1, a1);
+    far.gain = gain;
+  }
+
+  /// Returns the source azimuth, in degrees.
+  pub fn get_azimuth(&self) -> T {
+    self.azimuth
+  }
+}
+
+impl<T> Panner<T> for SimplePanner3D<T> where T: Float + FloatConst {
+  fn process(&mut self, sample: T) -> (T, T) {
+    let out_l = self.left.gain * self.left.lowpass.process(self.left.delay.process(sample));
+    let out_r = self.right.gain * self.right.lowpass.process(self.right.delay.process(sample));
+    self.output = (out_l, out_r);
+    self.output
+  }
+
+  fn clear(&mut self) {
+    for ear in [&mut self.left, &mut self.right].iter_mut() {
+      ear.delay.clear();
+      ear.lowpass.clear();
+    }
+    self.output = (num::zero(), num::zero());
+  }
+
+  fn last_out(&self) -> (T, T) {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::f32::EPSILON;
+
+  #[test]
+  fn new() {
+    // A source straight ahead reaches both ears unchanged
+    let mut panner = SimplePanner3D::new(44_100f32);
+    let (left, right) = panner.process(0.5f32);
+    assert!((left - 0.5f32).abs() < EPSILON);
+    assert!((right - 0.5f32).abs() < EPSILON);
+  }
+
+  #[test]
+  fn far_ear_is_delayed_and_attenuated() {
+    let sample_rate = 44_100f32;
+    let mut panner = SimplePanner3D::new(sample_rate);
+    panner.set_azimuth(90f32);
+
+    let mut input = vec![0f32; 64]; input[0] = 1f32;
+    let mut left = vec![0f32; 64];
+    let mut right = vec![0f32; 64];
+    panner.process_block(&input, &mut left, &mut right);
+
+    // The near (left) ear is unchanged
+    assert!((left[0] - 1f32).abs() < EPSILON);
+
+    // Woodworth's formula at 90 degrees
+    let itd = 0.0875f32 / 343f32 * (::std::f32::consts::FRAC_PI_2 + 1f32);
+    let delay = (itd * sample_rate).floor() as usize;
+    for sample in right[..delay].iter() {
+      assert!(sample.abs() < EPSILON);
+    }
+
+    let near_energy: f32 = left.iter().map(|x| x * x).sum();
+    let far_energy: f32 = right.iter().map(|x| x * x).sum();
+    assert!(far_energy > 0f32 && far_energy < near_energy);
+
+    // Turning back restores the far ear
+    input = vec![1f32; 64];
+    panner.set_azimuth(0f32);
+    panner.clear();
+    panner.process_block(&input, &mut left, &mut right);
+    assert!((left[63] - right[63]).abs() < EPSILON);
+  }
+
+  #[test]
+  fn mirrored_azimuths() {
+    let mut left_panner = SimplePanner3D::new(44_100f32);
+    let mut right_panner = SimplePanner3D::new(44_100f32);
+    left_panner.set_azimuth(40f32);
+    right_panner.set_azimuth(-40f32);
+
+    for i in 0..32 {
+      let sample = (i as f32 * 0.3f32).sin();
+      let (l1, r1) = left_panner.process(sample);
+      let (l2, r2) = right_panner.process(sample);
+      assert!((l1 - r2).abs() < 1e-6f32);
+      assert!((r1 - l2).abs() < 1e-6f32);
+    }
+  }
+}
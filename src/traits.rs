@@ -1,6 +1,48 @@
+//! The traits shared by the components.
+//!
+//! Every mono component with one input and one output, including the
+//! filters, delay lines, envelope detectors, and mono effects, implements
+//! `Processor`, so processing code can be written generically against one
+//! trait. Components with a stereo input and output implement
+//! `StereoProcessor`, panners with a mono input and a stereo output
+//! implement `Panner`, and components producing samples without an input
+//! implement `Generator`. Each of these shares `clear()` and `last_out()`.
+//! Analyzers, which measure their input without an output, implement
+//! `Analyzer` or `StereoAnalyzer`, and share `clear()`. The filters and
+//! delay lines also implement `StateSnapshot`, saving and restoring their
+//! memory.
+//!
+//! Components whose input or output is neither mono nor stereo, such as the
+//! `bus::Mixer`, the binaural decoder of `spatial::ambisonics`, and the rate
+//! changing CIC filters, have inherent `process()` methods instead.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::analysis::PeakEnvDetector;
+//! use rasp::delay::Delay;
+//! use rasp::filter::OnePole;
+//! use rasp::traits::Processor;
+//!
+//! let mut chain: Vec<Box<dyn Processor<f32>>> = vec![
+//!   Box::new(OnePole::new()),
+//!   Box::new(Delay::new(1, 1)),
+//!   Box::new(PeakEnvDetector::new())
+//! ];
+//!
+//! let mut block = vec![1f32, -1f32, 1f32];
+//! for processor in chain.iter_mut() {
+//!   processor.process_block(&mut block);
+//! }
+//! assert_eq!(block, vec![0f32, 1f32, 1f32]);
+//! ```
+
+use num::Complex;
 use num::traits::Float;
 
 use std;
+use envelope::GateEvent;
+use render::ProcessContext;
 
 /// Common floating point constants
 pub trait FloatConst {
@@ -43,6 +85,25 @@ pub trait Processor<T: Float> {
     *samples.last().unwrap()
   }
 
+  /// Processes a block with the timing of `context`, calling
+  /// `process_block()` unless the context is bypassed.
+  ///
+  /// A bypassed block leaves the samples unaltered and returns `last_out()`.
+  /// The memory of the component is frozen while bypassed, so processing
+  /// resumes from the state left by the last processed block, and a
+  /// bypassed empty block does nothing.
+  ///
+  /// Components synced to a tempo, or reading the transport, override this
+  /// to take their timing from the context.
+  fn process_context(&mut self, samples: &mut [T], context: &ProcessContext<T>) -> T {
+    if context.bypass {
+      self.last_out()
+    }
+    else {
+      self.process_block(samples)
+    }
+  }
+
   /// Resets memory of all previous input and output to zero.
   fn clear(&mut self);
 
@@ -50,6 +111,349 @@ pub trait Processor<T: Float> {
   fn last_out(&self) -> T;
 }
 
+impl<T, P> Processor<T> for Box<P> where T: Float, P: Processor<T> + ?Sized {
+  fn process(&mut self, sample: T) -> T {
+    (**self).process(sample)
+  }
+
+  fn process_block(&mut self, samples: &mut [T]) -> T {
+    (**self).process_block(samples)
+  }
+
+  fn process_context(&mut self, samples: &mut [T], context: &ProcessContext<T>) -> T {
+    (**self).process_context(samples, context)
+  }
+
+  fn clear(&mut self) {
+    (**self).clear();
+  }
+
+  fn last_out(&self) -> T {
+    (**self).last_out()
+  }
+}
+
+/// A linear filter whose frequency response can be evaluated.
+///
+/// The response is computed from the transfer function of the filter, at
+/// any frequency, rather than measured from its output. Slices of filters
+/// are cascades, whose response is the product of theirs.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::Biquad2;
+/// use rasp::filter::rbj::LowPass;
+/// use rasp::traits::FrequencyResponse;
+///
+/// let sample_rate = 44_100f64;
+/// let mut lowpass = Biquad2::new();
+/// lowpass.load_coefficients(LowPass::coefficients(sample_rate, 1_000f64, 0.5f64.sqrt()));
+///
+/// // Butterworth sections are 3 dB down at the cutoff
+/// assert!((lowpass.magnitude_at(1_000f64, sample_rate) - 0.5f64.sqrt()).abs() < 1e-9f64);
+///
+/// // Cascaded, they are 6 dB down
+/// let cascade = vec![lowpass.clone(), lowpass];
+/// assert!((cascade.magnitude_at(1_000f64, sample_rate) - 0.5f64).abs() < 1e-9f64);
+/// ```
+pub trait FrequencyResponse<T: Float + FloatConst> {
+  /// Returns the complex response at `frequency`, in Hz, for signals at
+  /// `sample_rate`.
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T>;
+
+  /// Returns the gain at `frequency`, as a linear magnitude.
+  fn magnitude_at(&self, frequency: T, sample_rate: T) -> T {
+    self.response_at(frequency, sample_rate).norm()
+  }
+
+  /// Returns the phase shift at `frequency`, in radians between `-pi` and
+  /// `pi`.
+  fn phase_at(&self, frequency: T, sample_rate: T) -> T {
+    self.response_at(frequency, sample_rate).arg()
+  }
+}
+
+impl<T, F> FrequencyResponse<T> for [F] where T: Float + FloatConst, F: FrequencyResponse<T> {
+  fn response_at(&self, frequency: T, sample_rate: T) -> Complex<T> {
+    self.iter().fold(Complex::new(T::one(), T::zero()), |response, filter| {
+      response * filter.response_at(frequency, sample_rate)
+    })
+  }
+}
+
+/// A linear recursive filter whose poles and zeros can be found.
+///
+/// Poles and zeros are the roots of the denominator and numerator of the
+/// transfer function in `z`. Zeros at infinity are left out, as are poles
+/// and zeros at the origin that cancel each other. Slices of filters are
+/// cascades, with the poles and zeros of every section.
+///
+/// A filter is stable if every pole is inside the unit circle, so
+/// coefficients entered by a user can be checked before they are loaded.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::BiquadCoefficients;
+/// use rasp::traits::PoleZero;
+///
+/// let resonator = BiquadCoefficients { b0: 1f64, b1: 0f64, b2: 0f64, a1: 0f64, a2: 0.81f64 };
+/// assert!(resonator.poles().iter().all(|pole| (pole.norm() - 0.9f64).abs() < 1e-12f64));
+/// assert!(resonator.is_stable());
+///
+/// let unstable = BiquadCoefficients { a2: 1.21f64, ..resonator };
+/// assert!(!unstable.is_stable());
+/// ```
+pub trait PoleZero<T: Float> {
+  /// Returns the poles of the filter.
+  fn poles(&self) -> Vec<Complex<T>>;
+
+  /// Returns the zeros of the filter.
+  fn zeros(&self) -> Vec<Complex<T>>;
+
+  /// Returns `true` if every pole is inside the unit circle.
+  fn is_stable(&self) -> bool {
+    self.poles().iter().all(|pole| pole.norm() < T::one())
+  }
+}
+
+impl<T, F> PoleZero<T> for [F] where T: Float, F: PoleZero<T> {
+  fn poles(&self) -> Vec<Complex<T>> {
+    self.iter().flat_map(|filter| filter.poles()).collect()
+  }
+
+  fn zeros(&self) -> Vec<Complex<T>> {
+    self.iter().flat_map(|filter| filter.zeros()).collect()
+  }
+}
+
+/// A stereo audio processor.
+pub trait StereoProcessor<T: Float> {
+  /// Processes and stores a pair of input samples into memory and outputs
+  /// the calculated pair of samples.
+  fn process(&mut self, left: T, right: T) -> (T, T);
+
+  /// Processes a contiguous sequence of sample pairs, calling `process()` on
+  /// each pair, and returns the last computed pair.
+  fn process_block(&mut self, left: &mut [T], right: &mut [T]) -> (T, T) {
+    debug_assert_eq!(left.len(), right.len());
+    for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+      let (out_l, out_r) = self.process(*l, *r);
+      *l = out_l;
+      *r = out_r;
+    }
+    self.last_out()
+  }
+
+  /// Resets memory of all previous input and output to zero.
+  fn clear(&mut self);
+
+  /// Returns the last computed pair of output samples.
+  fn last_out(&self) -> (T, T);
+}
+
+impl<T, P> StereoProcessor<T> for Box<P> where T: Float, P: StereoProcessor<T> + ?Sized {
+  fn process(&mut self, left: T, right: T) -> (T, T) {
+    (**self).process(left, right)
+  }
+
+  fn process_block(&mut self, left: &mut [T], right: &mut [T]) -> (T, T) {
+    (**self).process_block(left, right)
+  }
+
+  fn clear(&mut self) {
+    (**self).clear();
+  }
+
+  fn last_out(&self) -> (T, T) {
+    (**self).last_out()
+  }
+}
+
+/// A processor with a mono input and a stereo output, such as a panner.
+pub trait Panner<T: Float> {
+  /// Processes and stores a mono input sample into memory and outputs the
+  /// calculated pair of samples.
+  fn process(&mut self, sample: T) -> (T, T);
+
+  /// Processes a contiguous sequence of mono samples into the `left` and
+  /// `right` outputs, calling `process()` on each sample, and returns the
+  /// last computed pair.
+  fn process_block(&mut self, input: &[T], left: &mut [T], right: &mut [T]) -> (T, T) {
+    debug_assert!(input.len() == left.len() && input.len() == right.len());
+    for ((sample, l), r) in input.iter().zip(left.iter_mut()).zip(right.iter_mut()) {
+      let (out_l, out_r) = self.process(*sample);
+      *l = out_l;
+      *r = out_r;
+    }
+    self.last_out()
+  }
+
+  /// Resets memory of all previous input and output to zero.
+  fn clear(&mut self);
+
+  /// Returns the last computed pair of output samples.
+  fn last_out(&self) -> (T, T);
+}
+
+impl<T, P> Panner<T> for Box<P> where T: Float, P: Panner<T> + ?Sized {
+  fn process(&mut self, sample: T) -> (T, T) {
+    (**self).process(sample)
+  }
+
+  fn process_block(&mut self, input: &[T], left: &mut [T], right: &mut [T]) -> (T, T) {
+    (**self).process_block(input, left, right)
+  }
+
+  fn clear(&mut self) {
+    (**self).clear();
+  }
+
+  fn last_out(&self) -> (T, T) {
+    (**self).last_out()
+  }
+}
+
+/// An analyzer, measuring a mono signal without an output.
+///
+/// The measurements are read with the methods of each analyzer.
+pub trait Analyzer<T: Float> {
+  /// Measures a sample.
+  fn process(&mut self, sample: T);
+
+  /// Measures a contiguous sequence of samples, calling `process()` on each
+  /// sample.
+  fn process_block(&mut self, samples: &[T]) {
+    for sample in samples.iter() {
+      self.process(*sample);
+    }
+  }
+
+  /// Clears the memory of the analyzer and every measurement.
+  fn clear(&mut self);
+}
+
+impl<T, A> Analyzer<T> for Box<A> where T: Float, A: Analyzer<T> + ?Sized {
+  fn process(&mut self, sample: T) {
+    (**self).process(sample);
+  }
+
+  fn process_block(&mut self, samples: &[T]) {
+    (**self).process_block(samples);
+  }
+
+  fn clear(&mut self) {
+    (**self).clear();
+  }
+}
+
+/// An analyzer, measuring a stereo signal without an output.
+pub trait StereoAnalyzer<T: Float> {
+  /// Measures a pair of samples.
+  fn process(&mut self, left: T, right: T);
+
+  /// Measures contiguous sequences of sample pairs, calling `process()` on
+  /// each pair.
+  ///
+  /// `left` and `right` must be the same length.
+  fn process_block(&mut self, left: &[T], right: &[T]) {
+    debug_assert_eq!(left.len(), right.len());
+    for (l, r) in left.iter().zip(right.iter()) {
+      self.process(*l, *r);
+    }
+  }
+
+  /// Clears the memory of the analyzer and every measurement.
+  fn clear(&mut self);
+}
+
+impl<T, A> StereoAnalyzer<T> for Box<A> where T: Float, A: StereoAnalyzer<T> + ?Sized {
+  fn process(&mut self, left: T, right: T) {
+    (**self).process(left, right);
+  }
+
+  fn process_block(&mut self, left: &[T], right: &[T]) {
+    (**self).process_block(left, right);
+  }
+
+  fn clear(&mut self) {
+    (**self).clear();
+  }
+}
+
+/// An audio generator.
+///
+/// Unlike a `Processor`, a generator produces samples without an input
+/// signal.
+pub trait Generator<T: Float> {
+  /// Generates and outputs the next sample.
+  fn tick(&mut self) -> T;
+
+  /// Fills a contiguous sequence of samples, calling `tick()` for each
+  /// sample, and returns the last generated sample.
+  fn generate_block(&mut self, samples: &mut [T]) -> T {
+    for sample in samples.iter_mut() {
+      *sample = self.tick();
+    }
+    self.last_out()
+  }
+
+  /// Resets the generator to its initial state.
+  fn clear(&mut self);
+
+  /// Returns the last generated sample.
+  fn last_out(&self) -> T;
+}
+
+impl<T, G> Generator<T> for Box<G> where T: Float, G: Generator<T> + ?Sized {
+  fn tick(&mut self) -> T {
+    (**self).tick()
+  }
+
+  fn generate_block(&mut self, samples: &mut [T]) -> T {
+    (**self).generate_block(samples)
+  }
+
+  fn clear(&mut self) {
+    (**self).clear();
+  }
+
+  fn last_out(&self) -> T {
+    (**self).last_out()
+  }
+}
+
+/// A gated envelope generator.
+pub trait Envelope<T: Float>: Generator<T> {
+  /// Opens the gate, starting the attack stage.
+  fn gate_on(&mut self);
+
+  /// Closes the gate, starting the release stage.
+  fn gate_off(&mut self);
+
+  /// Renders a block of envelope samples into `out`.
+  ///
+  /// Each event in `events` is applied just before the sample at its
+  /// `offset` is generated, so gates are not quantized to block boundaries.
+  /// `events` must be sorted by offset, and events with an offset beyond the
+  /// end of the block are ignored.
+  fn render(&mut self, out: &mut [T], events: &[GateEvent]) {
+    let mut events = events.iter().peekable();
+    for (n, sample) in out.iter_mut().enumerate() {
+      while let Some(event) = events.next_if(|event| event.offset <= n) {
+        if event.on {
+          self.gate_on();
+        }
+        else {
+          self.gate_off();
+        }
+      }
+      *sample = self.tick();
+    }
+  }
+}
+
 /// A tappable delay line.
 ///
 /// A tappable delay line is able to access samples at a specified offset
@@ -67,3 +471,51 @@ pub trait TappableDelayLine<T: Float> {
   /// input.
   fn add_to(&mut self, value: T, tap_delay: usize) -> T;
 }
+
+/// A component whose memory can be saved and restored.
+///
+/// A snapshot holds the memory of a component, such as the contents of its
+/// delay lines and the states of its filters, but not its parameters, so it
+/// can be restored after the parameters change. Restoring a snapshot makes
+/// the component continue exactly as it would have from the moment the
+/// snapshot was taken, which lets offline renders resume from a checkpoint,
+/// and lets alternatives be compared from identical states.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::filter::OnePole;
+/// use rasp::traits::{Processor, StateSnapshot};
+///
+/// let mut filter = OnePole::new();
+/// filter.set_coefficients(0.5f32, -0.5f32);
+/// filter.process(1f32);
+/// let snapshot = filter.snapshot();
+///
+/// let first = filter.process(0f32);
+/// filter.restore(&snapshot);
+/// assert_eq!(filter.process(0f32), first);
+/// ```
+pub trait StateSnapshot {
+  /// The saved memory of the component.
+  type State: Clone;
+
+  /// Returns a copy of the memory.
+  fn snapshot(&self) -> Self::State;
+
+  /// Replaces the memory with a copy of `state`, leaving the parameters
+  /// unchanged.
+  fn restore(&mut self, state: &Self::State);
+}
+
+impl<S> StateSnapshot for Box<S> where S: StateSnapshot + ?Sized {
+  type State = S::State;
+
+  fn snapshot(&self) -> Self::State {
+    (**self).snapshot()
+  }
+
+  fn restore(&mut self, state: &Self::State) {
+    (**self).restore(state);
+  }
+}
@@ -0,0 +1,274 @@
+//! A musical timeline shared by tempo synced components.
+//!
+//! A `Transport` is advanced once for each processed block, and every
+//! component synced to it reads its tempo and position, so sequencers, and
+//! delay times or modulation rates set in beats, all follow the same clock.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::sequencer::StepSequencer;
+//! use rasp::transport::Transport;
+//!
+//! let mut transport = Transport::new(44_100f32);
+//! transport.set_tempo(90f32);
+//! transport.play();
+//!
+//! // A delay of a dotted eighth note
+//! let delay = transport.beats_to_samples(0.75f32);
+//! assert_eq!(delay, 22_050f32);
+//!
+//! let mut sequencer = StepSequencer::new(44_100f32, 1, 16);
+//! for _ in 0..8 {
+//!   sequencer.sync(&transport);
+//!   let _events = sequencer.render(512);
+//!   transport.advance(512);
+//! }
+//! ```
+
+use num;
+use num::traits::Float;
+
+/// The current position, tempo, and meter of a musical timeline.
+///
+/// The position is counted in samples from the last change of tempo or move
+/// of the position, so it does not drift however long the transport plays,
+/// and the tempo can change without the position jumping. When a loop is
+/// set, the position wraps from its end back to its start while playing.
+/// Components synced to the transport follow the wrap from the next block.
+pub struct Transport<T> {
+  sample_rate: T,
+  tempo: T,
+  beats_per_bar: usize,
+  beat_unit: usize,
+  playing: bool,
+  // The position at the last change of tempo or move, and the samples
+  // played since
+  origin: T,
+  elapsed: usize,
+  samples: usize,
+  loop_region: Option<(T, T)>
+}
+
+impl<T> Transport<T> where T: Float {
+  /// Creates a new, stopped `Transport` for signals at `sample_rate`, at the
+  /// first beat, at 120 beats per minute in 4/4 time.
+  pub fn new(sample_rate: T) -> Self {
+    Transport {
+      sample_rate,
+      tempo: num::cast(120f64).unwrap(),
+      beats_per_bar: 4,
+      beat_unit: 4,
+      playing: false,
+      origin: T::zero(),
+      elapsed: 0,
+      samples: 0,
+      loop_region: None
+    }
+  }
+
+  /// Starts advancing the position.
+  pub fn play(&mut self) {
+    self.playing = true;
+  }
+
+  /// Stops advancing the position, without moving it.
+  pub fn stop(&mut self) {
+    self.playing = false;
+  }
+
+  /// Returns `true` if the position is advancing.
+  pub fn is_playing(&self) -> bool {
+    self.playing
+  }
+
+  /// Sets the tempo, in beats per minute.
+  ///
+  /// `tempo` must be positive, else it is not updated.
+  pub fn set_tempo(&mut self, tempo: T) {
+    if tempo > T::zero() && tempo.is_finite() {
+      self.origin = self.get_beats();
+      self.elapsed = 0;
+      self.tempo = tempo;
+    }
+  }
+
+  /// Returns the tempo, in beats per minute.
+  pub fn get_tempo(&self) -> T {
+    self.tempo
+  }
+
+  /// Sets the time signature, as the number of beats in a bar, and the note
+  /// value of a beat, such as 4 for quarter notes.
+  ///
+  /// Both values must be at least one, else they are not updated.
+  pub fn set_time_signature(&mut self, beats_per_bar: usize, beat_unit: usize) {
+    if beats_per_bar >= 1 && beat_unit >= 1 {
+      self.beats_per_bar = beats_per_bar;
+      self.beat_unit = beat_unit;
+    }
+  }
+
+  /// Returns the time signature, as the number of beats in a bar, and the
+  /// note value of a beat.
+  pub fn get_time_signature(&self) -> (usize, usize) {
+    (self.beats_per_bar, self.beat_unit)
+  }
+
+  /// Sets the loop region, from the `start` to the `end` beat, or `None` to
+  /// play on without looping.
+  ///
+  /// `start` must not be negative and must be less than `end`, else the loop
+  /// region is not updated.
+  pub fn set_loop(&mut self, region: Option<(T, T)>) {
+    match region {
+      Some((start, end)) if !(start >= T::zero() && start < end && end.is_finite()) => {},
+      _ => self.loop_region = region
+    }
+  }
+
+  /// Returns the loop region, in beats, if any.
+  pub fn get_loop(&self) -> Option<(T, T)> {
+    self.loop_region
+  }
+
+  /// Moves the position to `beats`.
+  ///
+  /// `beats` must not be negative, else the position is not updated.
+  pub fn locate(&mut self, beats: T) {
+    if beats >= T::zero() && beats.is_finite() {
+      self.origin = beats;
+      self.elapsed = 0;
+      self.samples = num::cast((beats * self.samples_per_beat()).round()).unwrap_or(0);
+    }
+  }
+
+  /// Returns the position, in beats from the start of the timeline.
+  pub fn get_beats(&self) -> T {
+    self.origin + self.samples_to_beats(num::cast(self.elapsed).unwrap())
+  }
+
+  /// Returns the absolute position of the play head, in samples from the
+  /// start of the timeline.
+  pub fn get_samples(&self) -> usize {
+    self.samples
+  }
+
+  /// Returns the position as a bar, counted from zero, and the beat within
+  /// the bar.
+  pub fn get_bar(&self) -> (usize, T) {
+    let beats_per_bar: T = num::cast(self.beats_per_bar).unwrap();
+    let beats = self.get_beats();
+    let bar = (beats / beats_per_bar).floor();
+    (num::cast(bar).unwrap_or(0), beats - bar * beats_per_bar)
+  }
+
+  /// Returns the length of a beat, in samples.
+  pub fn samples_per_beat(&self) -> T {
+    let seconds: T = num::cast(60f64).unwrap();
+    self.sample_rate * seconds / self.tempo
+  }
+
+  /// Converts a duration in beats to samples, at the current tempo.
+  pub fn beats_to_samples(&self, beats: T) -> T {
+    beats * self.samples_per_beat()
+  }
+
+  /// Converts a duration in samples to beats, at the current tempo.
+  pub fn samples_to_beats(&self, samples: T) -> T {
+    samples / self.samples_per_beat()
+  }
+
+  /// Returns the time from the position until `beat`, in samples, at the
+  /// current tempo, which is negative for beats already played.
+  pub fn samples_until(&self, beat: T) -> T {
+    let elapsed: T = num::cast(self.elapsed).unwrap();
+    self.beats_to_samples(beat - self.origin) - elapsed
+  }
+
+  /// Advances the position by a block of `length` samples, if playing.
+  pub fn advance(&mut self, length: usize) {
+    if !self.playing {
+      return;
+    }
+    self.samples += length;
+    self.elapsed += length;
+    if let Some((start, end)) = self.loop_region {
+      let beats = self.get_beats();
+      if beats >= end {
+        let wrapped = start + (beats - end) % (end - start);
+        self.locate(wrapped);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn position() {
+    let mut transport = Transport::new(48_000f64);
+    transport.advance(24_000);
+    assert_eq!(transport.get_beats(), 0f64);
+
+    transport.play();
+    transport.advance(24_000);
+    assert!((transport.get_beats() - 1f64).abs() < 1e-12f64);
+    assert_eq!(transport.get_samples(), 24_000);
+
+    // The tempo applies from the current position
+    transport.set_tempo(60f64);
+    transport.advance(48_000);
+    assert!((transport.get_beats() - 2f64).abs() < 1e-12f64);
+
+    transport.set_time_signature(3, 4);
+    transport.locate(7.5f64);
+    let (bar, beat) = transport.get_bar();
+    assert_eq!(bar, 2);
+    assert!((beat - 1.5f64).abs() < 1e-12f64);
+    assert_eq!(transport.get_samples(), 360_000);
+
+    transport.stop();
+    transport.advance(1_000);
+    assert_eq!(transport.get_beats(), 7.5f64);
+  }
+
+  #[test]
+  fn looping() {
+    let mut transport = Transport::new(1_000f64);
+    transport.set_tempo(60f64);
+    transport.set_loop(Some((1f64, 3f64)));
+    transport.play();
+    transport.advance(2_500);
+    assert!((transport.get_beats() - 2.5f64).abs() < 1e-12f64);
+    transport.advance(1_000);
+    assert!((transport.get_beats() - 1.5f64).abs() < 1e-12f64);
+    assert_eq!(transport.get_samples(), 1_500);
+
+    transport.set_loop(Some((3f64, 1f64)));
+    assert_eq!(transport.get_loop(), Some((1f64, 3f64)));
+    transport.set_loop(None);
+    transport.advance(2_000);
+    assert!((transport.get_beats() - 3.5f64).abs() < 1e-12f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut transport = Transport::new(44_100f32);
+    assert!(!transport.is_playing());
+    transport.set_tempo(0f32);
+    assert_eq!(transport.get_tempo(), 120f32);
+    transport.set_time_signature(0, 8);
+    assert_eq!(transport.get_time_signature(), (4, 4));
+    transport.locate(-1f32);
+    assert_eq!(transport.get_beats(), 0f32);
+    assert_eq!(transport.beats_to_samples(1f32), 22_050f32);
+    assert_eq!(transport.samples_to_beats(11_025f32), 0.5f32);
+
+    transport.play();
+    transport.advance(100);
+    assert_eq!(transport.samples_until(1f32), 21_950f32);
+  }
+}
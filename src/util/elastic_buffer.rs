@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+
+use num::traits::Float;
+
+/// What an `ElasticBuffer` outputs when a frame is pulled with too few
+/// samples buffered.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Underrun {
+  /// The missing samples are silent.
+  Silence,
+  /// The missing samples hold the last sample pulled, which hides short
+  /// dropouts of slowly varying signals, such as control values.
+  Hold
+}
+
+/// What an `ElasticBuffer` discards when a chunk is pushed with too little
+/// room left.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Overrun {
+  /// The oldest buffered samples are dropped, keeping the latency bounded.
+  DropOldest,
+  /// The newest pushed samples are dropped, keeping the signal continuous
+  /// up to the dropout.
+  DropNewest
+}
+
+/// The statistics of an `ElasticBuffer`, counting samples unless noted.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ElasticStats {
+  /// The samples pushed, including those dropped
+  pub pushed: u64,
+  /// The samples pulled, including those filled
+  pub pulled: u64,
+  /// The number of frames pulled with too few samples buffered
+  pub underruns: u64,
+  /// The number of chunks pushed with too little room left
+  pub overruns: u64,
+  /// The samples filled by underruns and gaps between time stamps
+  pub filled: u64,
+  /// The samples dropped by overruns and overlapping time stamps
+  pub dropped: u64,
+  /// The fewest samples buffered after pulling a frame, from the capacity
+  pub min_fill: usize,
+  /// The most samples buffered after pushing a chunk
+  pub max_fill: usize
+}
+
+/// A buffer absorbing the jitter between a producer pushing chunks of any
+/// size and a consumer pulling frames of a fixed size.
+///
+/// Audio callbacks, network streams and hosts with variable block sizes
+/// deliver samples in bursts, while processing is usually driven at a
+/// steady rate. The buffer holds samples between the two, up to its
+/// capacity, and smooths the bursts by waiting for a prefill before
+/// releasing samples, so the consumer always finds enough buffered. The
+/// prefill is the latency added, and is waited for again after each
+/// underrun.
+///
+/// Every chunk is pushed with the time stamp of its first sample, in
+/// samples of the producer, so the buffer keeps the samples on time: a gap
+/// since the last chunk, such as a lost packet, is filled with silence, and
+/// samples already pushed, or already due, are dropped. Frames are pulled
+/// with the time stamp of their first sample, which advances over the
+/// samples filled by underruns, so late chunks are trimmed to stay in sync.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::util::{ElasticBuffer, Overrun, Underrun};
+///
+/// let mut buffer = ElasticBuffer::<f32>::new(1_024);
+/// buffer.set_prefill(256);
+/// buffer.set_overrun(Overrun::DropOldest);
+/// buffer.set_underrun(Underrun::Silence);
+///
+/// // The producer pushes bursts of any size
+/// buffer.push(0, &[0.5f32; 300]);
+///
+/// // The consumer pulls fixed frames
+/// let mut frame = [0f32; 128];
+/// assert_eq!(buffer.pull(&mut frame), Some(0));
+/// assert_eq!(buffer.pull(&mut frame), Some(128));
+/// assert_eq!(buffer.len(), 44);
+/// ```
+pub struct ElasticBuffer<T> {
+  memory: VecDeque<T>,
+  capacity: usize,
+  prefill: usize,
+  primed: bool,
+  underrun: Underrun,
+  overrun: Overrun,
+  // The time stamp of the oldest buffered sample
+  read_time: u64,
+  started: bool,
+  last: T,
+  stats: ElasticStats
+}
+
+impl<T> ElasticBuffer<T> where T: Float {
+  /// Creates a new `ElasticBuffer` holding up to `capacity` samples, without
+  /// any prefill, filling underruns with silence and dropping the oldest
+  /// samples on overruns.
+  ///
+  /// `capacity` must be at least one.
+  pub fn new(capacity: usize) -> Self {
+    debug_assert!(capacity >= 1);
+    ElasticBuffer {
+      memory: VecDeque::with_capacity(capacity),
+      capacity,
+      prefill: 0,
+      primed: true,
+      underrun: Underrun::Silence,
+      overrun: Overrun::DropOldest,
+      read_time: 0,
+      started: false,
+      last: T::zero(),
+      stats: ElasticStats { min_fill: capacity, ..ElasticStats::default() }
+    }
+  }
+
+  /// Returns the most samples the buffer holds.
+  pub fn capacity(&self) -> usize {
+    self.capacity
+  }
+
+  /// Returns the number of samples buffered.
+  pub fn len(&self) -> usize {
+    self.memory.len()
+  }
+
+  /// Returns `true` if no samples are buffered.
+  pub fn is_empty(&self) -> bool {
+    self.memory.is_empty()
+  }
+
+  /// Sets the number of samples to buffer before frames are released, at
+  /// the start and after each underrun.
+  ///
+  /// `prefill` is clipped to the capacity.
+  pub fn set_prefill(&mut self, prefill: usize) {
+    self.prefill = prefill.min(self.capacity);
+    self.primed = self.memory.len() >= self.prefill;
+  }
+
+  /// Returns the number of samples buffered before frames are released.
+  pub fn get_prefill(&self) -> usize {
+    self.prefill
+  }
+
+  /// Sets what is output when a frame is pulled with too few samples.
+  pub fn set_underrun(&mut self, underrun: Underrun) {
+    self.underrun = underrun;
+  }
+
+  /// Returns what is output when a frame is pulled with too few samples.
+  pub fn get_underrun(&self) -> Underrun {
+    self.underrun
+  }
+
+  /// Sets what is dropped when a chunk is pushed with too little room.
+  pub fn set_overrun(&mut self, overrun: Overrun) {
+    self.overrun = overrun;
+  }
+
+  /// Returns what is dropped when a chunk is pushed with too little room.
+  pub fn get_overrun(&self) -> Overrun {
+    self.overrun
+  }
+
+  /// Returns the statistics since the buffer was created or the statistics
+  /// were reset.
+  pub fn stats(&self) -> ElasticStats {
+    self.stats
+  }
+
+  /// Resets the statistics, with the fill levels at the current fill.
+  pub fn reset_stats(&mut self) {
+    self.stats = ElasticStats {
+      min_fill: self.memory.len(),
+      max_fill: self.memory.len(),
+      ..ElasticStats::default()
+    };
+  }
+
+  /// Pushes `samples`, whose first sample is at `timestamp`, in samples.
+  ///
+  /// The first chunk pushed sets the time of the buffer. Later chunks after
+  /// a gap are preceded by silence, and samples before the end of the
+  /// buffered samples are dropped.
+  pub fn push(&mut self, timestamp: u64, samples: &[T]) {
+    self.stats.pushed += samples.len() as u64;
+    if !self.started {
+      self.started = true;
+      self.read_time = timestamp;
+    }
+
+    let end = self.read_time + self.memory.len() as u64;
+    let (mut gap, mut samples) =
+      if timestamp >= end {
+        ((timestamp - end) as usize, samples)
+      }
+      else {
+        let late = ((end - timestamp) as usize).min(samples.len());
+        self.stats.dropped += late as u64;
+        (0, &samples[late..])
+      };
+
+    let incoming = gap.saturating_add(samples.len());
+    let room = self.capacity - self.memory.len();
+    if incoming > room {
+      self.stats.overruns += 1;
+      match self.overrun {
+        Overrun::DropOldest => {
+          if incoming > self.capacity {
+            // The chunk alone overflows, so only its newest samples are kept
+            let skip = incoming - self.capacity;
+            self.stats.dropped += (self.memory.len() + skip) as u64;
+            self.memory.clear();
+            self.read_time = end + skip as u64;
+            let gap_skip = skip.min(gap);
+            gap -= gap_skip;
+            samples = &samples[skip - gap_skip..];
+          }
+          else {
+            let excess = incoming - room;
+            self.memory.drain(..excess);
+            self.read_time += excess as u64;
+            self.stats.dropped += excess as u64;
+          }
+        },
+        Overrun::DropNewest => {
+          let kept_gap = gap.min(room);
+          let kept = (room - kept_gap).min(samples.len());
+          self.stats.dropped += (incoming - kept_gap - kept) as u64;
+          gap = kept_gap;
+          samples = &samples[..kept];
+        }
+      }
+    }
+
+    self.stats.filled += gap as u64;
+    for _ in 0..gap {
+      self.memory.push_back(T::zero());
+    }
+    self.memory.extend(samples.iter().cloned());
+    self.stats.max_fill = self.stats.max_fill.max(self.memory.len());
+    if self.memory.len() >= self.prefill {
+      self.primed = true;
+    }
+  }
+
+  /// Pulls a frame of samples, returning the time stamp of its first sample,
+  /// or `None` while waiting for the prefill, when the frame is silent.
+  pub fn pull(&mut self, frame: &mut [T]) -> Option<u64> {
+    self.stats.pulled += frame.len() as u64;
+    if !self.primed {
+      if self.memory.len() >= self.prefill.max(1) {
+        self.primed = true;
+      }
+      else {
+        for sample in frame.iter_mut() {
+          *sample = self.fill_value();
+        }
+        self.stats.filled += frame.len() as u64;
+        return None;
+      }
+    }
+
+    let timestamp = self.read_time;
+    let available = self.memory.len().min(frame.len());
+    for (sample, buffered) in frame.iter_mut().zip(self.memory.drain(..available)) {
+      *sample = buffered;
+    }
+    if available > 0 {
+      self.last = frame[available - 1];
+    }
+    if available < frame.len() {
+      self.stats.underruns += 1;
+      self.stats.filled += (frame.len() - available) as u64;
+      let fill = self.fill_value();
+      for sample in frame[available..].iter_mut() {
+        *sample = fill;
+      }
+      self.primed = self.prefill == 0;
+    }
+    self.read_time += frame.len() as u64;
+    self.stats.min_fill = self.stats.min_fill.min(self.memory.len());
+    Some(timestamp)
+  }
+
+  /// Empties the buffer, which waits for the prefill again, and restarts its
+  /// time at the next chunk pushed.
+  pub fn clear(&mut self) {
+    self.memory.clear();
+    self.primed = self.prefill == 0;
+    self.started = false;
+    self.read_time = 0;
+    self.last = T::zero();
+  }
+
+  fn fill_value(&self) -> T {
+    match self.underrun {
+      Underrun::Silence => T::zero(),
+      Underrun::Hold => self.last
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn jitter() {
+    // Bursts of any size are absorbed by the prefill
+    let mut buffer = ElasticBuffer::new(64);
+    buffer.set_prefill(24);
+    let bursts = [0, 20, 3, 0, 17, 8];
+    let mut frame = [0f64; 8];
+    let (mut time, mut expected, mut output) = (0u64, 0u64, Vec::new());
+    for burst in bursts.iter().cycle().take(120) {
+      let chunk: Vec<f64> = (time..time + *burst as u64).map(|t| t as f64).collect();
+      buffer.push(time, &chunk);
+      time += *burst as u64;
+      if let Some(timestamp) = buffer.pull(&mut frame) {
+        assert_eq!(timestamp, expected);
+        expected += 8;
+        output.extend_from_slice(&frame);
+      }
+    }
+    assert!(output.len() > 800);
+    assert_eq!(buffer.stats().underruns, 0);
+    assert!(output.iter().enumerate().all(|(n, sample)| *sample == n as f64));
+  }
+
+  #[test]
+  fn underrun() {
+    let mut buffer = ElasticBuffer::new(16);
+    buffer.push(0, &[1f32, 2f32, 3f32]);
+    let mut frame = [9f32; 4];
+    assert_eq!(buffer.pull(&mut frame), Some(0));
+    assert_eq!(frame, [1f32, 2f32, 3f32, 0f32]);
+
+    buffer.set_underrun(Underrun::Hold);
+    buffer.push(4, &[4f32]);
+    assert_eq!(buffer.pull(&mut frame), Some(4));
+    assert_eq!(frame, [4f32; 4]);
+    assert_eq!((buffer.stats().underruns, buffer.stats().filled), (2, 4));
+
+    // Samples whose time has passed are trimmed
+    buffer.push(6, &[6f32, 7f32, 8f32, 9f32]);
+    assert_eq!(buffer.len(), 2);
+    assert_eq!(buffer.stats().dropped, 2);
+  }
+
+  #[test]
+  fn prefill() {
+    let mut buffer = ElasticBuffer::new(16);
+    buffer.set_prefill(6);
+    let mut frame = [1f32; 4];
+    buffer.push(100, &[1f32; 4]);
+    assert_eq!(buffer.pull(&mut frame), None);
+    assert_eq!(frame, [0f32; 4]);
+    buffer.push(104, &[1f32; 4]);
+    assert_eq!(buffer.pull(&mut frame), Some(100));
+    assert_eq!(buffer.pull(&mut frame), Some(104));
+
+    // An underrun waits for the prefill again
+    assert_eq!(buffer.pull(&mut frame), Some(108));
+    assert_eq!(buffer.pull(&mut frame), None);
+  }
+
+  #[test]
+  fn gaps() {
+    // A lost chunk is replaced by silence, keeping later samples on time
+    let mut buffer = ElasticBuffer::new(16);
+    buffer.push(0, &[1f32, 1f32]);
+    buffer.push(4, &[2f32, 2f32]);
+    let mut frame = [9f32; 6];
+    assert_eq!(buffer.pull(&mut frame), Some(0));
+    assert_eq!(frame, [1f32, 1f32, 0f32, 0f32, 2f32, 2f32]);
+    assert_eq!(buffer.stats().filled, 2);
+  }
+
+  #[test]
+  fn overrun() {
+    let mut buffer = ElasticBuffer::new(4);
+    buffer.push(0, &[0f32, 1f32, 2f32]);
+    buffer.push(3, &[3f32, 4f32, 5f32]);
+    let mut frame = [0f32; 4];
+    assert_eq!(buffer.pull(&mut frame), Some(2));
+    assert_eq!(frame, [2f32, 3f32, 4f32, 5f32]);
+
+    // A chunk longer than the buffer keeps its newest samples
+    buffer.push(6, &[6f32, 7f32, 8f32, 9f32, 10f32, 11f32]);
+    assert_eq!(buffer.pull(&mut frame), Some(8));
+    assert_eq!(frame, [8f32, 9f32, 10f32, 11f32]);
+
+    buffer.clear();
+    buffer.set_overrun(Overrun::DropNewest);
+    buffer.push(0, &[0f32, 1f32, 2f32]);
+    buffer.push(3, &[3f32, 4f32, 5f32]);
+    assert_eq!(buffer.pull(&mut frame), Some(0));
+    assert_eq!(frame, [0f32, 1f32, 2f32, 3f32]);
+
+    let stats = buffer.stats();
+    assert_eq!((stats.overruns, stats.dropped, stats.max_fill), (3, 6, 4));
+    assert_eq!((stats.pushed, stats.pulled), (18, 12));
+    buffer.reset_stats();
+    assert_eq!(buffer.stats().pushed, 0);
+  }
+}
@@ -0,0 +1,219 @@
+use num;
+use num::traits::Float;
+
+use render::ProcessContext;
+use traits::Processor;
+
+/// A safety net around a processor, scrubbing non-finite samples.
+///
+/// An unstable filter setting, or a division by zero deep in a chain, turns
+/// its output to NaN or infinity, which then spreads to everything after it
+/// and never recovers, since the state of every recursive filter downstream
+/// is poisoned too. The guard replaces non-finite input samples with zeros
+/// before they reach the wrapped processor, and when the processor outputs a
+/// non-finite sample, or one above the limit, outputs a zero instead and
+/// clears the processor, so it starts again from silence at the next sample.
+///
+/// Every replaced sample is counted, so faults that are silenced in live use
+/// are still reported.
+pub struct Guard<T, P> {
+  processor: P,
+  limit: T,
+  faults: usize,
+  output: T
+}
+
+impl<T, P> Guard<T, P> where T: Float, P: Processor<T> {
+  /// Creates a new `Guard` around `processor`, without any limit on the
+  /// magnitude of its output.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::filter::OnePole;
+  /// use rasp::traits::Processor;
+  /// use rasp::util::Guard;
+  ///
+  /// // A pole outside of the unit circle
+  /// let mut filter = OnePole::new();
+  /// filter.set_coefficients(1f32, -2f32);
+  /// let mut guard = Guard::new(filter);
+  /// guard.set_limit(4f32);
+  ///
+  /// let outputs: Vec<f32> = (0..4).map(|_| guard.process(1f32)).collect();
+  /// assert_eq!(outputs, vec![1f32, 3f32, 0f32, 1f32]);
+  /// assert_eq!(guard.faults(), 1);
+  /// ```
+  pub fn new(processor: P) -> Self {
+    Guard {
+      processor,
+      limit: T::infinity(),
+      faults: 0,
+      output: num::zero()
+    }
+  }
+
+  /// Returns the wrapped processor.
+  pub fn processor(&self) -> &P {
+    &self.processor
+  }
+
+  /// Returns the wrapped processor, to set its parameters.
+  pub fn processor_mut(&mut self) -> &mut P {
+    &mut self.processor
+  }
+
+  /// Returns the wrapped processor, consuming the guard.
+  pub fn into_inner(self) -> P {
+    self.processor
+  }
+
+  /// Sets the magnitude above which an output sample is a fault, as if it
+  /// were not finite, to catch a processor blowing up before it overflows.
+  ///
+  /// `limit` must be positive, else it is not updated. An infinite limit
+  /// only catches non-finite samples.
+  pub fn set_limit(&mut self, limit: T) {
+    if limit > T::zero() {
+      self.limit = limit;
+    }
+  }
+
+  /// Returns the magnitude above which an output sample is a fault.
+  pub fn get_limit(&self) -> T {
+    self.limit
+  }
+
+  /// Returns the number of input and output samples replaced with zeros
+  /// since the guard was created, or since the count was reset.
+  pub fn faults(&self) -> usize {
+    self.faults
+  }
+
+  /// Resets the number of faults to zero.
+  pub fn reset_faults(&mut self) {
+    self.faults = 0;
+  }
+}
+
+impl<T, P> Processor<T> for Guard<T, P> where T: Float, P: Processor<T> {
+  fn process(&mut self, sample: T) -> T {
+    let sample = if sample.is_finite() {
+      sample
+    }
+    else {
+      self.faults += 1;
+      T::zero()
+    };
+
+    let output = self.processor.process(sample);
+    self.output = if output.is_finite() && output.abs() <= self.limit {
+      output
+    }
+    else {
+      self.faults += 1;
+      self.processor.clear();
+      T::zero()
+    };
+    self.output
+  }
+
+  fn process_context(&mut self, samples: &mut [T], context: &ProcessContext<T>) -> T {
+    if context.bypass {
+      return self.last_out();
+    }
+    for sample in samples.iter_mut() {
+      if !sample.is_finite() {
+        self.faults += 1;
+        *sample = T::zero();
+      }
+    }
+
+    // The processor takes the whole block with the context, so it is only
+    // cleared at the end of a block with a fault
+    self.processor.process_context(samples, context);
+    let mut fault = false;
+    for sample in samples.iter_mut() {
+      if !(sample.is_finite() && sample.abs() <= self.limit) {
+        self.faults += 1;
+        *sample = T::zero();
+        fault = true;
+      }
+    }
+    if fault {
+      self.processor.clear();
+    }
+    if let Some(last) = samples.last() {
+      self.output = *last;
+    }
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.processor.clear();
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use filter::OnePole;
+
+  #[test]
+  fn input() {
+    // Non-finite input is replaced before it reaches the processor
+    let mut filter = OnePole::new();
+    filter.set_coefficients(0.5f64, -0.5f64);
+    let mut guard = Guard::new(filter);
+    let mut block = vec![1f64, ::std::f64::NAN, ::std::f64::INFINITY, 0f64];
+    guard.process_block(&mut block);
+    assert_eq!(block, vec![0.5f64, 0.25f64, 0.125f64, 0.0625f64]);
+    assert_eq!(guard.faults(), 2);
+    assert_eq!(guard.processor().last_out(), 0.0625f64);
+  }
+
+  #[test]
+  fn output() {
+    // An unstable processor is cleared each time its output overflows
+    let mut filter = OnePole::new();
+    filter.set_coefficients(1f32, -10f32);
+    let mut guard = Guard::new(filter);
+    let mut block = vec![1f32; 200];
+    guard.process_block(&mut block);
+    assert!(block.iter().all(|sample| sample.is_finite()));
+    let faults = block.iter().filter(|sample| **sample == 0f32).count();
+    assert!(faults > 0);
+    assert_eq!(guard.faults(), faults);
+
+    // The processor is back in use after the fault
+    let fault = block.iter().position(|sample| *sample == 0f32).unwrap();
+    assert_eq!(block[fault + 1], 1f32);
+
+    guard.reset_faults();
+    assert_eq!(guard.faults(), 0);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut guard = Guard::new(OnePole::new());
+    assert_eq!(guard.get_limit(), ::std::f32::INFINITY);
+    guard.set_limit(0f32);
+    guard.set_limit(::std::f32::NAN);
+    assert_eq!(guard.get_limit(), ::std::f32::INFINITY);
+
+    // The coefficients are reached through the processor
+    guard.processor_mut().set_coefficients(1f32, 0f32);
+    guard.set_limit(0.5f32);
+    assert_eq!(guard.process(1f32), 0f32);
+    assert_eq!(guard.process(0.25f32), 0.25f32);
+
+    guard.clear();
+    assert_eq!(guard.last_out(), 0f32);
+    assert_eq!(guard.into_inner().last_out(), 0f32);
+  }
+}
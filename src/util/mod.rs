@@ -3,6 +3,17 @@ use num::traits::Float;
 
 use traits::FloatConst;
 
+pub mod scales;
+
+mod elastic_buffer;
+mod guard;
+
+pub use self::elastic_buffer::ElasticBuffer as ElasticBuffer;
+pub use self::elastic_buffer::ElasticStats  as ElasticStats;
+pub use self::elastic_buffer::Overrun       as Overrun;
+pub use self::elastic_buffer::Underrun      as Underrun;
+pub use self::guard::Guard                  as Guard;
+
 /// Converts a sample value to a dBFS value.
 ///
 /// If the sample value is really small, or if the sample is not finite, it
@@ -42,6 +53,85 @@ pub fn apply_gain<T: Float>(sample: T, ratio: T) -> T {
   sample * ratio
 }
 
+/// Flushes a subnormal `value` to zero, when the `denormal-protection`
+/// feature is enabled.
+///
+/// The decaying tails of recursive filters eventually fall below the
+/// smallest normal float, where most processors compute far slower, which
+/// drives the load up while the filter is nearly silent. The recursive
+/// filters pass their memory through this on each sample, which otherwise
+/// returns `value` unaltered, and compiles away.
+///
+/// # Examples
+///
+/// ```
+/// use rasp::util;
+///
+/// let tail = 1e-40f32;
+/// if cfg!(feature = "denormal-protection") {
+///   assert_eq!(util::flush_denormal(tail), 0f32);
+/// }
+/// else {
+///   assert_eq!(util::flush_denormal(tail), tail);
+/// }
+/// assert_eq!(util::flush_denormal(1e-30f32), 1e-30f32);
+/// ```
+#[inline]
+pub fn flush_denormal<T: Float>(value: T) -> T {
+  if cfg!(feature = "denormal-protection") && value != T::zero() && value.is_finite() && !value.is_normal() {
+    T::zero()
+  }
+  else {
+    value
+  }
+}
+
+/// Converts a MIDI note number to a frequency, in Hz.
+///
+/// Notes are equally tempered, with note 69, A4, tuned to 440 Hz. Fractional
+/// note numbers are detuned by cents.
+pub fn midi_to_frequency<T: Float>(note: T) -> T {
+  let a4: T = num::cast(440f64).unwrap();
+  let two: T = num::cast(2f64).unwrap();
+  let semitones: T = num::cast(12f64).unwrap();
+  a4 * two.powf((note - num::cast(69f64).unwrap()) / semitones)
+}
+
+/// Converts a frequency, in Hz, to a MIDI note number, which is fractional
+/// between equally tempered notes.
+pub fn frequency_to_midi<T: Float>(frequency: T) -> T {
+  let a4: T = num::cast(440f64).unwrap();
+  let semitones: T = num::cast(12f64).unwrap();
+  num::cast::<f64, T>(69f64).unwrap() + semitones * (frequency / a4).log2()
+}
+
+/// Parses a note name, such as `"C4"`, `"F#2"`, or `"Bb-1"`, to a MIDI note
+/// number.
+///
+/// The name is a letter from A to G, any number of sharps, `#`, or flats,
+/// `b`, and an octave number, where C4 is note 60. Returns `None` if the name
+/// is not a note.
+pub fn parse_note<T: Float>(name: &str) -> Option<T> {
+  let mut chars = name.trim().chars();
+  let pitch_class: i32 =
+    match chars.next()?.to_ascii_uppercase() {
+      'C' => 0,
+      'D' => 2,
+      'E' => 4,
+      'F' => 5,
+      'G' => 7,
+      'A' => 9,
+      'B' => 11,
+      _ => return None
+    };
+  let rest = chars.as_str();
+  let octave_start = rest.find(|c: char| c != '#' && c != 'b').unwrap_or(rest.len());
+  let (accidentals, octave) = rest.split_at(octave_start);
+  let alteration = accidentals.chars().fold(0i32, |sum, c| if c == '#' { sum + 1 } else { sum - 1 });
+  let octave: i32 = octave.parse().ok()?;
+  num::cast(12 * (octave + 1) + pitch_class + alteration)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -89,6 +179,23 @@ mod tests {
     assert!((to_db(NEG_INFINITY) - -120f32).abs() < EPSILON);
   }
 
+  #[test]
+  fn notes() {
+    assert!((midi_to_frequency(69f64) - 440f64).abs() < 1e-9f64);
+    assert!((midi_to_frequency(60f64) - 261.625_565f64).abs() < 1e-5f64);
+    assert!((frequency_to_midi(880f64) - 81f64).abs() < 1e-9f64);
+
+    assert_eq!(parse_note::<f32>("C4"), Some(60f32));
+    assert_eq!(parse_note::<f32>("a4"), Some(69f32));
+    assert_eq!(parse_note::<f32>("F#2"), Some(42f32));
+    assert_eq!(parse_note::<f32>("Bb-1"), Some(10f32));
+    assert_eq!(parse_note::<f32>("Cb4"), Some(59f32));
+    assert_eq!(parse_note::<f32>("E##3"), Some(54f32));
+    assert_eq!(parse_note::<f32>("H4"), None);
+    assert_eq!(parse_note::<f32>("C"), None);
+    assert_eq!(parse_note::<f32>(""), None);
+  }
+
   #[test]
   fn conversion_to_samples() {
     /* Below minimum */
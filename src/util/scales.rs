@@ -0,0 +1,89 @@
+//! Conversions between frequency, in Hz, and perceptual frequency scales.
+//!
+//! The Bark scale divides hearing into critical bands, each one Bark wide,
+//! and uses Traunmüller's approximation, which can be inverted. The ERB-rate
+//! scale counts equivalent rectangular bandwidths of the auditory filters, as
+//! measured by Glasberg and Moore. The mel scale is the common form used by
+//! speech analysis, with 1000 mel at 1 kHz.
+
+use num;
+use num::traits::Float;
+
+/// Converts a frequency, in Hz, to a critical band rate, in Bark.
+pub fn frequency_to_bark<T: Float>(frequency: T) -> T {
+  let scale: T = num::cast(26.81f64).unwrap();
+  let corner: T = num::cast(1_960f64).unwrap();
+  let offset: T = num::cast(0.53f64).unwrap();
+  scale * frequency / (corner + frequency) - offset
+}
+
+/// Converts a critical band rate, in Bark, to a frequency, in Hz.
+pub fn bark_to_frequency<T: Float>(bark: T) -> T {
+  let scale: T = num::cast(26.28f64).unwrap();
+  let corner: T = num::cast(1_960f64).unwrap();
+  let offset: T = num::cast(0.53f64).unwrap();
+  corner * (bark + offset) / (scale - bark)
+}
+
+/// Converts a frequency, in Hz, to an ERB rate, the number of equivalent
+/// rectangular bandwidths below it.
+pub fn frequency_to_erb<T: Float>(frequency: T) -> T {
+  let scale: T = num::cast(21.4f64).unwrap();
+  let slope: T = num::cast(0.00437f64).unwrap();
+  scale * (T::one() + slope * frequency).log10()
+}
+
+/// Converts an ERB rate to a frequency, in Hz.
+pub fn erb_to_frequency<T: Float>(erb: T) -> T {
+  let ten: T = num::cast(10f64).unwrap();
+  let scale: T = num::cast(21.4f64).unwrap();
+  let slope: T = num::cast(0.00437f64).unwrap();
+  (ten.powf(erb / scale) - T::one()) / slope
+}
+
+/// Returns the equivalent rectangular bandwidth, in Hz, of the auditory
+/// filter centered at `frequency`, in Hz.
+pub fn erb_bandwidth<T: Float>(frequency: T) -> T {
+  let minimum: T = num::cast(24.7f64).unwrap();
+  let slope: T = num::cast(0.00437f64).unwrap();
+  minimum * (slope * frequency + T::one())
+}
+
+/// Converts a frequency, in Hz, to mels.
+pub fn frequency_to_mel<T: Float>(frequency: T) -> T {
+  let scale: T = num::cast(2_595f64).unwrap();
+  let corner: T = num::cast(700f64).unwrap();
+  scale * (T::one() + frequency / corner).log10()
+}
+
+/// Converts mels to a frequency, in Hz.
+pub fn mel_to_frequency<T: Float>(mel: T) -> T {
+  let ten: T = num::cast(10f64).unwrap();
+  let scale: T = num::cast(2_595f64).unwrap();
+  let corner: T = num::cast(700f64).unwrap();
+  corner * (ten.powf(mel / scale) - T::one())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn scales() {
+    assert!((frequency_to_bark(1_000f64) - 8.53f64).abs() < 0.01f64);
+    assert!((frequency_to_erb(1_000f64) - 15.62f64).abs() < 0.01f64);
+    assert!((erb_bandwidth(1_000f64) - 132.64f64).abs() < 0.01f64);
+    assert!((frequency_to_mel(1_000f64) - 1_000f64).abs() < 0.1f64);
+
+    // Each conversion is reversed by its inverse
+    for frequency in [20f64, 440f64, 3_150f64, 18_000f64].iter() {
+      assert!((bark_to_frequency(frequency_to_bark(*frequency)) - frequency).abs() < 1e-9f64);
+      assert!((erb_to_frequency(frequency_to_erb(*frequency)) - frequency).abs() < 1e-9f64);
+      assert!((mel_to_frequency(frequency_to_mel(*frequency)) - frequency).abs() < 1e-9f64);
+    }
+
+    // The ERB rate is the integral of the inverse of the bandwidth
+    let rate = (0..1_000).fold(0f64, |sum, f| sum + 1f64 / erb_bandwidth(f as f64 + 0.5f64));
+    assert!((rate - frequency_to_erb(1_000f64)).abs() < 0.1f64);
+  }
+}
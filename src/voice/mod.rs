@@ -0,0 +1,29 @@
+//! Playable instruments, built from the generators, filters and envelopes.
+//!
+//! A voice is played with MIDI note numbers and velocities from 0 to 1, and
+//! generates samples like any other `Generator`. Envelope lengths are given
+//! in samples, as for the envelopes themselves, and every other time in
+//! seconds.
+//!
+//! # Examples
+//!
+//! ```
+//! use rasp::voice::MonoSynth;
+//! use rasp::traits::Generator;
+//!
+//! let mut synth = MonoSynth::new(44_100f32);
+//! synth.set_cutoff(1_200f32);
+//! synth.set_glide(0.05f32);
+//!
+//! let mut block = vec![0f32; 512];
+//! synth.note_on(60, 0.8f32);
+//! synth.generate_block(&mut block[..256]);
+//! synth.note_on(67, 0.8f32);
+//! synth.generate_block(&mut block[256..]);
+//! synth.note_off(67);
+//! synth.note_off(60);
+//! ```
+
+mod mono_synth;
+
+pub use self::mono_synth::MonoSynth as MonoSynth;
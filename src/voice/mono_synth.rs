@@ -0,0 +1,521 @@
+use num;
+use num::traits::Float;
+
+use envelope::Adsr;
+use filter::Ladder;
+use generator::{Oscillator, Waveform};
+use traits::{Envelope, FloatConst, Generator, Processor};
+use util;
+
+/// The highest resonance of the ladder filter.
+const MAX_RESONANCE: f64 = 1.25f64;
+
+/// A monophonic subtractive synthesizer voice.
+///
+/// Two band-limited oscillators, the second detuned by some cents, are mixed
+/// into a resonant `Ladder` lowpass. An amplitude envelope shapes the level
+/// of each note, scaled by its velocity, and a filter envelope sweeps the
+/// cutoff up by some octaves. A sine LFO, at 5 Hz until changed, can add
+/// vibrato and sweep the cutoff too. As in the analog ladder, resonance
+/// thins out the passband.
+///
+/// Only one note sounds at a time. The held notes are remembered, and the
+/// last one played has priority, so releasing it returns to the note still
+/// held before it, without retriggering the envelopes. Playing a note while
+/// the voice is still sounding glides to its pitch over the glide time,
+/// while a note played from silence starts at its pitch.
+pub struct MonoSynth<T> where T: Float {
+  sample_rate: T,
+  oscillators: (Oscillator<T>, Oscillator<T>),
+  detune: T,
+  mix: T,
+  filter: Ladder<T>,
+  cutoff: T,
+  resonance: T,
+  envelope_amount: T,
+  amp_envelope: Adsr<T>,
+  filter_envelope: Adsr<T>,
+  glide: T,
+  glide_coefficient: T,
+  lfo: Oscillator<T>,
+  pitch_modulation: T,
+  cutoff_modulation: T,
+  // The held notes, the last played at the end
+  notes: Vec<u8>,
+  // The note being played, and the gliding pitch, as note numbers
+  note: T,
+  pitch: T,
+  velocity: T,
+  output: T
+}
+
+impl<T> MonoSynth<T> where T: Float + FloatConst {
+  /// Creates a new `MonoSynth` for signals at `sample_rate`.
+  ///
+  /// The voice is initialized with two saws, detuned by 7 cents, into the
+  /// filter at 2 kHz with a resonance of 0.3, opened by 2 octaves by the
+  /// filter envelope, without any glide or LFO modulation. The amplitude
+  /// envelope has a 5 millisecond attack, 100 millisecond decay, sustain
+  /// level of 0.8, and 200 millisecond release, and the filter envelope a 5
+  /// millisecond attack, 300 millisecond decay, sustain level of 0.25, and
+  /// 200 millisecond release.
+  ///
+  /// # Examples
+  ///
+  /// ```
+  /// use rasp::generator::Waveform;
+  /// use rasp::voice::MonoSynth;
+  /// use rasp::traits::{Envelope, Generator};
+  ///
+  /// let sample_rate = 44_100f32;
+  /// let mut synth = MonoSynth::new(sample_rate);
+  /// synth.set_waveforms(Waveform::Saw, Waveform::Square);
+  /// synth.set_resonance(0.8f32);
+  /// synth.amp_envelope_mut().set_release(0.5f32 * sample_rate);
+  /// synth.lfo_mut().set_frequency(6f32);
+  /// synth.set_pitch_modulation(0.2f32);
+  ///
+  /// assert_eq!(synth.tick(), 0f32);
+  /// synth.note_on(64, 1f32);
+  /// assert_eq!(synth.get_note(), Some(64));
+  /// ```
+  pub fn new(sample_rate: T) -> Self {
+    let seconds = |time: f64| sample_rate * num::cast(time).unwrap();
+    let mut amp_envelope = Adsr::new();
+    amp_envelope.set_attack(seconds(0.005f64));
+    amp_envelope.set_decay(seconds(0.1f64));
+    amp_envelope.set_sustain(num::cast(0.8f64).unwrap());
+    amp_envelope.set_release(seconds(0.2f64));
+    let mut filter_envelope = Adsr::new();
+    filter_envelope.set_attack(seconds(0.005f64));
+    filter_envelope.set_decay(seconds(0.3f64));
+    filter_envelope.set_sustain(num::cast(0.25f64).unwrap());
+    filter_envelope.set_release(seconds(0.2f64));
+
+    let a4: T = num::cast(69f64).unwrap();
+    let oscillator = Oscillator::new(sample_rate, Waveform::Saw, util::midi_to_frequency(a4));
+    MonoSynth {
+      sample_rate,
+      oscillators: (oscillator.clone(), oscillator),
+      detune: num::cast(7f64).unwrap(),
+      mix: num::cast(0.5f64).unwrap(),
+      filter: Ladder::new(),
+      cutoff: num::cast(2_000f64).unwrap(),
+      resonance: num::cast(0.3f64).unwrap(),
+      envelope_amount: T::two(),
+      amp_envelope,
+      filter_envelope,
+      glide: T::zero(),
+      glide_coefficient: T::zero(),
+      lfo: Oscillator::new(sample_rate, Waveform::Sine, num::cast(5f64).unwrap()),
+      pitch_modulation: T::zero(),
+      cutoff_modulation: T::zero(),
+      notes: Vec::new(),
+      note: a4,
+      pitch: a4,
+      velocity: T::zero(),
+      output: num::zero()
+    }
+  }
+
+  /// Sets the waveforms of the two oscillators.
+  pub fn set_waveforms(&mut self, first: Waveform, second: Waveform) {
+    self.oscillators.0.set_waveform(first);
+    self.oscillators.1.set_waveform(second);
+  }
+
+  /// Returns the waveforms of the two oscillators.
+  pub fn get_waveforms(&self) -> (Waveform, Waveform) {
+    (self.oscillators.0.get_waveform(), self.oscillators.1.get_waveform())
+  }
+
+  /// Sets the detune of the second oscillator, in cents.
+  ///
+  /// `detune` must be finite, else it is not updated.
+  pub fn set_detune(&mut self, detune: T) {
+    if detune.is_finite() {
+      self.detune = detune;
+    }
+  }
+
+  /// Returns the detune of the second oscillator, in cents.
+  pub fn get_detune(&self) -> T {
+    self.detune
+  }
+
+  /// Sets the mix of the oscillators, from 0, only the first, to 1, only the
+  /// second.
+  ///
+  /// If `mix` is out of range, the mix is not updated.
+  pub fn set_mix(&mut self, mix: T) {
+    if mix >= T::zero() && mix <= T::one() {
+      self.mix = mix;
+    }
+  }
+
+  /// Returns the mix of the oscillators.
+  pub fn get_mix(&self) -> T {
+    self.mix
+  }
+
+  /// Sets the cutoff of the filter, in Hz, before any modulation.
+  ///
+  /// `cutoff` must be positive and finite, else it is not updated.
+  pub fn set_cutoff(&mut self, cutoff: T) {
+    if cutoff > T::zero() && cutoff.is_finite() {
+      self.cutoff = cutoff;
+    }
+  }
+
+  /// Returns the cutoff of the filter, in Hz.
+  pub fn get_cutoff(&self) -> T {
+    self.cutoff
+  }
+
+  /// Sets the resonance of the filter, from 0 to 1.25, where the filter
+  /// oscillates.
+  ///
+  /// If `resonance` is out of range, the resonance is not updated.
+  pub fn set_resonance(&mut self, resonance: T) {
+    if resonance >= T::zero() && resonance <= num::cast(MAX_RESONANCE).unwrap() {
+      self.resonance = resonance;
+    }
+  }
+
+  /// Returns the resonance of the filter.
+  pub fn get_resonance(&self) -> T {
+    self.resonance
+  }
+
+  /// Sets how far the filter envelope moves the cutoff at its peak, in
+  /// octaves, where negative amounts close the filter instead.
+  ///
+  /// `amount` must be finite, else it is not updated.
+  pub fn set_envelope_amount(&mut self, amount: T) {
+    if amount.is_finite() {
+      self.envelope_amount = amount;
+    }
+  }
+
+  /// Returns how far the filter envelope moves the cutoff, in octaves.
+  pub fn get_envelope_amount(&self) -> T {
+    self.envelope_amount
+  }
+
+  /// Returns the filter.
+  pub fn filter(&self) -> &Ladder<T> {
+    &self.filter
+  }
+
+  /// Returns the filter, to set its saturation and drive.
+  pub fn filter_mut(&mut self) -> &mut Ladder<T> {
+    &mut self.filter
+  }
+
+  /// Returns the amplitude envelope.
+  pub fn amp_envelope(&self) -> &Adsr<T> {
+    &self.amp_envelope
+  }
+
+  /// Returns the amplitude envelope, to set its segments.
+  pub fn amp_envelope_mut(&mut self) -> &mut Adsr<T> {
+    &mut self.amp_envelope
+  }
+
+  /// Returns the filter envelope.
+  pub fn filter_envelope(&self) -> &Adsr<T> {
+    &self.filter_envelope
+  }
+
+  /// Returns the filter envelope, to set its segments.
+  pub fn filter_envelope_mut(&mut self) -> &mut Adsr<T> {
+    &mut self.filter_envelope
+  }
+
+  /// Sets the time, in seconds, for the pitch to glide to a new note.
+  ///
+  /// `glide` must be at least zero, else it is not updated. Without any
+  /// glide, the pitch jumps to each new note.
+  pub fn set_glide(&mut self, glide: T) {
+    if glide >= T::zero() && glide.is_finite() {
+      self.glide = glide;
+      self.glide_coefficient =
+        if glide > T::zero() {
+          (-T::one() / (glide * self.sample_rate)).exp()
+        }
+        else {
+          T::zero()
+        };
+    }
+  }
+
+  /// Returns the glide time, in seconds.
+  pub fn get_glide(&self) -> T {
+    self.glide
+  }
+
+  /// Returns the LFO.
+  pub fn lfo(&self) -> &Oscillator<T> {
+    &self.lfo
+  }
+
+  /// Returns the LFO, to set its waveform and rate.
+  pub fn lfo_mut(&mut self) -> &mut Oscillator<T> {
+    &mut self.lfo
+  }
+
+  /// Sets how far the LFO moves the pitch, in semitones, for vibrato.
+  ///
+  /// `depth` must be at least zero, else it is not updated.
+  pub fn set_pitch_modulation(&mut self, depth: T) {
+    if depth >= T::zero() && depth.is_finite() {
+      self.pitch_modulation = depth;
+    }
+  }
+
+  /// Returns how far the LFO moves the pitch, in semitones.
+  pub fn get_pitch_modulation(&self) -> T {
+    self.pitch_modulation
+  }
+
+  /// Sets how far the LFO moves the cutoff, in octaves.
+  ///
+  /// `depth` must be at least zero, else it is not updated.
+  pub fn set_cutoff_modulation(&mut self, depth: T) {
+    if depth >= T::zero() && depth.is_finite() {
+      self.cutoff_modulation = depth;
+    }
+  }
+
+  /// Returns how far the LFO moves the cutoff, in octaves.
+  pub fn get_cutoff_modulation(&self) -> T {
+    self.cutoff_modulation
+  }
+
+  /// Plays `note`, a MIDI note number, at `velocity`, from 0 to 1.
+  ///
+  /// The envelopes are only retriggered if no other note is held. A
+  /// velocity of zero releases the note instead, as in MIDI.
+  pub fn note_on(&mut self, note: u8, velocity: T) {
+    if velocity <= T::zero() {
+      self.note_off(note);
+      return;
+    }
+    let legato = !self.notes.is_empty();
+    self.notes.retain(|held| *held != note);
+    self.notes.push(note);
+    self.note = num::cast(note).unwrap();
+    self.velocity = velocity.min(T::one());
+    if !self.amp_envelope.is_active() {
+      self.pitch = self.note;
+    }
+    if !legato {
+      self.amp_envelope.gate_on();
+      self.filter_envelope.gate_on();
+    }
+  }
+
+  /// Releases `note`, returning to the last note still held, if any.
+  pub fn note_off(&mut self, note: u8) {
+    let length = self.notes.len();
+    self.notes.retain(|held| *held != note);
+    if self.notes.len() == length {
+      return;
+    }
+    match self.notes.last() {
+      Some(held) => {
+        self.note = num::cast(*held).unwrap();
+      },
+      None => {
+        self.amp_envelope.gate_off();
+        self.filter_envelope.gate_off();
+      }
+    }
+  }
+
+  /// Releases every held note.
+  pub fn all_notes_off(&mut self) {
+    if !self.notes.is_empty() {
+      self.notes.clear();
+      self.amp_envelope.gate_off();
+      self.filter_envelope.gate_off();
+    }
+  }
+
+  /// Returns the note being played, or `None` if no note is held.
+  pub fn get_note(&self) -> Option<u8> {
+    self.notes.last().cloned()
+  }
+
+  /// Returns the frequency, in Hz, of the first oscillator, as it glides,
+  /// before any vibrato.
+  pub fn get_frequency(&self) -> T {
+    util::midi_to_frequency(self.pitch)
+  }
+
+  /// Returns `true` until the last note has fully released.
+  pub fn is_active(&self) -> bool {
+    self.amp_envelope.is_active()
+  }
+}
+
+impl<T> Generator<T> for MonoSynth<T> where T: Float + FloatConst {
+  fn tick(&mut self) -> T {
+    let lfo = self.lfo.tick();
+    self.pitch = self.note + (self.pitch - self.note) * self.glide_coefficient;
+    let pitch = self.pitch + lfo * self.pitch_modulation;
+    let cents: T = num::cast(100f64).unwrap();
+    self.oscillators.0.set_frequency(util::midi_to_frequency(pitch));
+    self.oscillators.1.set_frequency(util::midi_to_frequency(pitch + self.detune / cents));
+    let mixed = self.oscillators.0.tick() * (T::one() - self.mix) + self.oscillators.1.tick() * self.mix;
+
+    let octaves = self.envelope_amount * self.filter_envelope.tick() + lfo * self.cutoff_modulation;
+    self.filter.set_coefficients(self.sample_rate, self.cutoff * T::two().powf(octaves), self.resonance);
+    let level = self.amp_envelope.tick() * self.velocity;
+    self.output = self.filter.process(mixed) * level;
+    self.output
+  }
+
+  fn clear(&mut self) {
+    self.oscillators.0.clear();
+    self.oscillators.1.clear();
+    self.filter.clear();
+    self.amp_envelope.clear();
+    self.filter_envelope.clear();
+    self.lfo.clear();
+    self.notes.clear();
+    self.pitch = self.note;
+    self.output = T::zero();
+  }
+
+  fn last_out(&self) -> T {
+    self.output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use analysis::PitchDetector;
+  use traits::Analyzer;
+
+  /// Returns the peak of the next `length` samples of `synth`.
+  fn peak(synth: &mut MonoSynth<f64>, length: usize) -> f64 {
+    (0..length).fold(0f64, |peak, _| peak.max(synth.tick().abs()))
+  }
+
+  #[test]
+  fn pitch() {
+    let mut synth = MonoSynth::new(48_000f64);
+    synth.set_detune(0f64);
+    synth.set_cutoff(8_000f64);
+    assert_eq!(synth.tick(), 0f64);
+
+    // The voice plays the note, and its pitch is detected
+    synth.note_on(57, 1f64);
+    let mut detector = PitchDetector::new(48_000f64, 50f64, 2_000f64);
+    for _ in 0..9_600 {
+      detector.process(synth.tick());
+    }
+    assert!((synth.get_frequency() - 220f64).abs() < 1e-9f64);
+    assert!((detector.frequency().unwrap() / 220f64 - 1f64).abs() < 5e-3f64);
+  }
+
+  #[test]
+  fn notes() {
+    let mut synth = MonoSynth::new(44_100f64);
+    synth.note_on(60, 1f64);
+    synth.note_on(64, 0.5f64);
+    assert_eq!(synth.get_note(), Some(64));
+
+    // Releasing a note other than the last leaves it playing, and releasing
+    // the last returns to the one held before
+    synth.note_off(60);
+    assert_eq!(synth.get_note(), Some(64));
+    synth.note_on(67, 1f64);
+    synth.note_on(67, 0f64);
+    assert_eq!(synth.get_note(), Some(64));
+    assert!(peak(&mut synth, 4_410) > 0.05f64);
+
+    // Once every note is released, the voice falls silent after the release
+    synth.note_off(64);
+    assert_eq!(synth.get_note(), None);
+    peak(&mut synth, 8_820);
+    assert!(synth.is_active());
+    peak(&mut synth, 100);
+    assert!(!synth.is_active());
+    assert_eq!(synth.tick(), 0f64);
+
+    synth.note_on(48, 1f64);
+    synth.all_notes_off();
+    assert_eq!(synth.get_note(), None);
+  }
+
+  #[test]
+  fn glide() {
+    let mut synth = MonoSynth::new(48_000f64);
+    synth.set_glide(0.1f64);
+
+    // The first note starts at its pitch, and the next glides to its own
+    synth.note_on(60, 1f64);
+    synth.tick();
+    assert!((util::frequency_to_midi(synth.get_frequency()) - 60f64).abs() < 1e-9f64);
+    synth.note_on(72, 1f64);
+    for _ in 0..4_800 {
+      synth.tick();
+    }
+    let expected = 72f64 - 12f64 * (-1f64).exp();
+    assert!((util::frequency_to_midi(synth.get_frequency()) - expected).abs() < 1e-3f64);
+    for _ in 0..48_000 {
+      synth.tick();
+    }
+    assert!((util::frequency_to_midi(synth.get_frequency()) - 72f64).abs() < 1e-3f64);
+  }
+
+  #[test]
+  fn filter_envelope() {
+    // A note two octaves above the cutoff is filtered out, unless the
+    // filter envelope opens the filter
+    let mut synth = MonoSynth::new(48_000f64);
+    synth.set_cutoff(110f64);
+    synth.set_resonance(0f64);
+    synth.set_envelope_amount(0f64);
+    synth.filter_envelope_mut().set_sustain(1f64);
+    synth.note_on(69, 1f64);
+    peak(&mut synth, 4_800);
+    let closed = peak(&mut synth, 4_800);
+
+    synth.set_envelope_amount(5f64);
+    peak(&mut synth, 4_800);
+    let open = peak(&mut synth, 4_800);
+    assert!(closed < 0.05f64 && open > 0.3f64);
+  }
+
+  #[test]
+  fn parameters() {
+    let mut synth = MonoSynth::new(44_100f32);
+    synth.set_detune(::std::f32::NAN);
+    synth.set_mix(2f32);
+    synth.set_cutoff(0f32);
+    synth.set_resonance(2f32);
+    synth.set_envelope_amount(::std::f32::INFINITY);
+    synth.set_glide(-1f32);
+    synth.set_pitch_modulation(-1f32);
+    synth.set_cutoff_modulation(-1f32);
+    assert_eq!((synth.get_detune(), synth.get_mix(), synth.get_cutoff()), (7f32, 0.5f32, 2_000f32));
+    assert_eq!((synth.get_resonance(), synth.get_envelope_amount(), synth.get_glide()), (0.3f32, 2f32, 0f32));
+    assert_eq!((synth.get_pitch_modulation(), synth.get_cutoff_modulation()), (0f32, 0f32));
+    assert_eq!(synth.get_waveforms(), (Waveform::Saw, Waveform::Saw));
+    assert_eq!((synth.lfo().get_waveform(), synth.lfo().get_frequency()), (Waveform::Sine, 5f32));
+
+    synth.filter_mut().set_saturation(true);
+    assert!(synth.filter().get_saturation());
+    assert!(!synth.amp_envelope().is_active() && !synth.filter_envelope().is_active());
+    synth.amp_envelope_mut().set_sustain(0.5f32);
+
+    synth.note_on(60, 1f32);
+    synth.tick();
+    synth.clear();
+    assert_eq!((synth.get_note(), synth.is_active(), synth.last_out()), (None, false, 0f32));
+  }
+}
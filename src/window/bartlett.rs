@@ -15,7 +15,7 @@ impl<T> BartlettIter<T> where T: Float + FloatConst {
   pub fn new(size: usize) -> Self {
     BartlettIter {
       index: 0,
-      size: size,
+      size,
       phantom: PhantomData
     }
   }
@@ -17,7 +17,7 @@ impl<T> BlackmanIter<T> where T: Float + FloatConst {
   pub fn new(size: usize) -> Self {
     BlackmanIter {
       index: 0,
-      size: size,
+      size,
       phantom: PhantomData
     }
   }
@@ -17,7 +17,7 @@ impl<T> BlackmanHarrisIter<T> where T: Float + FloatConst {
   pub fn new(size: usize) -> Self {
     BlackmanHarrisIter {
       index: 0,
-      size: size,
+      size,
       phantom: PhantomData
     }
   }
@@ -15,7 +15,7 @@ impl<T> HammingIter<T> where T: Float + FloatConst {
   pub fn new(size: usize) -> Self {
     HammingIter {
       index: 0,
-      size: size,
+      size,
       phantom: PhantomData
     }
   }
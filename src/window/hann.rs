@@ -15,7 +15,7 @@ impl<T> HannIter<T> where T: Float + FloatConst {
   pub fn new(size: usize) -> Self {
     HannIter {
       index: 0,
-      size: size,
+      size,
       phantom: PhantomData
     }
   }
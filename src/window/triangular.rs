@@ -15,7 +15,7 @@ impl<T> TriangularIter<T> where T: Float + FloatConst {
   pub fn new(size: usize) -> Self {
     TriangularIter {
       index: 0,
-      size: size,
+      size,
       phantom: PhantomData
     }
   }
@@ -1,3 +1,5 @@
+#![allow(clippy::legacy_numeric_constants)]
+
 // Integration tests
 extern crate rasp;
 
@@ -5,32 +7,120 @@ extern crate rasp;
 mod api {
   mod analysis {
     use std::f32::EPSILON;
-    use rasp::traits::Processor;
+    use rasp::traits::{Analyzer, Processor, StereoAnalyzer};
     use rasp::analysis::{
+      AmDetector,
+      envelope_to_breakpoints,
+      Goniometer,
       LeakyIntegrator,
+      MaskingThreshold,
+      MelFilterbank,
+      OctaveFilterBank,
       PeakEnvDetector,
-      RmsEnvDetector
+      PitchDetector,
+      PolarityChecker,
+      RmsEnvDetector,
+      SplMeter
     };
 
     // No component here should alter the input until attack and relase are set
 
+    #[test]
+    fn am_detector() {
+      let mut detector = AmDetector::new(8_000f32);
+      detector.process(1f32);
+      assert_eq!(detector.depth(), 0f32);
+    }
+
+    #[test]
+    fn analyzers() {
+      // Analyzers can be driven generically, and boxed
+      let mut analyzers: Vec<Box<dyn Analyzer<f32>>> = vec![
+        Box::new(AmDetector::new(8_000f32)),
+        Box::new(OctaveFilterBank::new(44_100f32, 1)),
+        Box::new(PitchDetector::new(8_000f32, 80f32, 1_000f32)),
+        Box::new(SplMeter::new(44_100f32))
+      ];
+      for analyzer in analyzers.iter_mut() {
+        analyzer.process_block(&[0f32; 64]);
+        analyzer.clear();
+      }
+
+      let mut goniometer: Box<dyn StereoAnalyzer<f32>> = Box::new(Goniometer::new(16, 1));
+      goniometer.process_block(&[1f32], &[1f32]);
+    }
+
+    #[test]
+    fn breakpoints() {
+      let breakpoints = envelope_to_breakpoints(&[0f32, 0.5f32, 1f32], EPSILON);
+      assert_eq!(breakpoints, vec![(0, 0f32), (2, 1f32)]);
+    }
+
+    #[test]
+    fn goniometer() {
+      let mut goniometer = Goniometer::new(16, 1);
+      goniometer.process(1f32, 1f32);
+      assert!((goniometer.points()[0].1 - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn mel_filterbank() {
+      let mut filterbank = MelFilterbank::new(16_000f32, 256, 20);
+      let energies = filterbank.apply(&[0f32; 129]);
+      assert!(energies.iter().all(|energy| *energy == 0f32));
+    }
+
     #[test]
     fn leaky_integrator() {
       let mut integrator = LeakyIntegrator::new();
       assert!((integrator.process(1f32) - 1f32).abs() < EPSILON);
     }
 
+    #[test]
+    fn masking_threshold() {
+      let mut masking = MaskingThreshold::new(44_100f32, 256);
+      let threshold = masking.analyze(&[0f32; 256]).to_vec();
+      assert_eq!(threshold, masking.threshold());
+    }
+
+    #[test]
+    fn octave_filter_bank() {
+      let mut bank = OctaveFilterBank::new(44_100f32, 1);
+      bank.process(0f32);
+      assert_eq!(bank.levels().len(), bank.bands());
+    }
+
     #[test]
     fn peak_detector() {
       let mut detector = PeakEnvDetector::new();
       assert!((detector.process(1f32) - 1f32).abs() < EPSILON);
     }
 
+    #[test]
+    fn pitch_detector() {
+      let mut detector = PitchDetector::new(8_000f32, 80f32, 1_000f32);
+      detector.process(1f32);
+      assert_eq!(detector.frequency(), None);
+    }
+
+    #[test]
+    fn polarity_checker() {
+      let checker = PolarityChecker::<f32>::new(10);
+      assert!(checker.report().is_none());
+    }
+
     #[test]
     fn rms_detector() {
       let mut detector = RmsEnvDetector::new();
       assert!((detector.process(1f32) - 1f32).abs() < EPSILON);
     }
+
+    #[test]
+    fn spl_meter() {
+      let mut meter = SplMeter::new(44_100f32);
+      meter.process(1f32);
+      assert!(meter.leq() > meter.level());
+    }
   }
 
   mod filter {
@@ -38,11 +128,34 @@ mod api {
     use rasp::traits::Processor;
     use rasp::filter::{
       OnePole,
+      OnePoleTpt,
       OneZero,
       TwoPole,
       TwoZero,
       Biquad1,
-      Biquad2
+      Biquad2,
+      AllpassDelay,
+      Comb,
+      CombMode,
+      Crossover,
+      DirectForm2T,
+      DynamicSmoother,
+      Fir,
+      GammatoneFilterbank,
+      Ladder,
+      LatticeFir,
+      LatticeLadder,
+      Median,
+      MedianFilter,
+      MovingAverage,
+      ParametricEq,
+      PinkingFilter,
+      SavitzkyGolay,
+      SmoothedBiquad,
+      SosCascade,
+      StateVariable,
+      SvfTpt,
+      to_minimum_phase
     };
 
     // No component here should alter the input until coefficients are set
@@ -83,7 +196,216 @@ mod api {
       assert!((biquad.process(1f32) - 1f32).abs() < EPSILON);
     }
 
+    #[test]
+    fn allpass_delay() {
+      let mut allpass = AllpassDelay::new(1, 10);
+      assert!((allpass.process(1f32) - -0.5f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn comb() {
+      let mut comb = Comb::new(1, 10, CombMode::FeedForward);
+      assert!((comb.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn crossover() {
+      let mut crossover = Crossover::new(44_100f32, &[]);
+      assert!((crossover.tick(1f32)[0] - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn fir() {
+      let mut filter = Fir::new(&[1f32]);
+      assert!((filter.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn gammatone_filterbank() {
+      let mut bank = GammatoneFilterbank::new(16_000f32, 100f32, 4_000f32, 8);
+      assert_eq!(bank.tick(0f32), &[0f32; 8]);
+    }
+
+    #[test]
+    fn ladder() {
+      let mut filter = Ladder::new();
+      filter.set_coefficients(44_100f32, 1_000f32, 0.5f32);
+      assert!(filter.process(1f32).is_finite());
+    }
+
+    #[test]
+    fn lattice() {
+      let mut fir = LatticeFir::new(&[]);
+      assert!((fir.process(1f32) - 1f32).abs() < EPSILON);
+      let mut ladder = LatticeLadder::new(&[], &[1f32]);
+      assert!((ladder.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn minimum_phase() {
+      let taps = to_minimum_phase(&[1f32]);
+      assert!((taps[0] - 1f32).abs() < 1e-6f32);
+    }
+
+    #[test]
+    fn dynamic_smoother() {
+      let mut smoother = DynamicSmoother::new(44_100f32);
+      smoother.reset(1f32);
+      assert!((smoother.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn median() {
+      let mut filter = Median::new(3);
+      filter.set_length(5);
+      assert!((filter.process(1f32) - 0f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn median_filter() {
+      let mut filter = MedianFilter::new(1);
+      assert!((filter.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn moving_average() {
+      let mut filter = MovingAverage::new(1);
+      assert!((filter.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn one_pole_tpt() {
+      let mut filter = OnePoleTpt::new();
+      filter.set_coefficients(44_100f32, 1_000f32);
+      let (lowpass, highpass) = filter.tick(1f32);
+      assert!((lowpass + highpass - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn parametric_eq() {
+      let mut eq = ParametricEq::new(44_100f32);
+      assert!((eq.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn pinking_filter() {
+      // The first output is the sum of the gains of every path
+      let mut pinking = PinkingFilter::new();
+      assert!((pinking.process(1f32) - 1.6472f32).abs() < 1e-4f32);
+    }
+
+    #[test]
+    fn savitzky_golay() {
+      let smoothed = SavitzkyGolay::new().smooth(&[1f32]);
+      assert!((smoothed[0] - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn direct_form() {
+      let mut filter = DirectForm2T::new(&[1f32], &[1f32]);
+      assert!((filter.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn smoothed_biquad() {
+      let mut biquad = SmoothedBiquad::new(16);
+      assert!((biquad.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn sos_cascade() {
+      let mut cascade = SosCascade::new();
+      assert!((cascade.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn state_variable() {
+      let mut filter = StateVariable::new();
+      filter.set_params(44_100f32, 1_000f32, 0.71f32);
+      let outputs = filter.tick(1f32);
+      assert!((outputs.notch - outputs.lowpass - outputs.highpass).abs() < EPSILON);
+    }
+
+    #[test]
+    fn svf_tpt() {
+      let mut filter = SvfTpt::new();
+      filter.set_coefficients(44_100f32, 1_000f32, 0.71f32);
+      let mut samples = vec![1f32; 4];
+      filter.modulate_cutoff(&mut samples, &[100f32, 1_000f32, 10_000f32, 20_000f32]);
+      assert!(samples.iter().all(|sample| sample.is_finite()));
+    }
+
     #[cfg(test)]
+    mod design {
+      use rasp::filter::design::{bessel, bilinear, chebyshev1, chebyshev2, elliptic, fir_differentiator, fir_sinc, AnalogBiquad, Band};
+      use rasp::window::Window;
+
+      #[test]
+      fn bessel_design() {
+        assert_eq!(bessel(Band::LowPass, 3, 1_000f32, 10f32).unwrap().len(), 2);
+      }
+
+      #[test]
+      fn bilinear_transform() {
+        let analog = AnalogBiquad { b0: 1f32, b1: 0f32, b2: 0f32, a0: 1f32, a1: 0f32, a2: 0f32 };
+        assert_eq!(bilinear(44_100f32, &analog).b0, 1f32);
+      }
+
+      #[test]
+      fn chebyshev() {
+        assert_eq!(chebyshev1(Band::LowPass, 4, 44_100f32, 1_000f32, 1f32).unwrap().len(), 2);
+        assert_eq!(chebyshev2(Band::HighPass, 3, 44_100f32, 1_000f32, 40f32).unwrap().len(), 2);
+      }
+
+      #[test]
+      fn elliptic_design() {
+        let (sections, stopband) = elliptic(Band::LowPass, 5, 44_100f32, 1_000f32, 1f32, 60f32).unwrap();
+        assert_eq!(sections.len(), 3);
+        assert!(stopband > 1_000f32);
+      }
+
+      #[test]
+      fn fir_differentiator_design() {
+        assert_eq!(fir_differentiator(16, 44_100f32, Window::Hann).unwrap().len(), 16);
+      }
+
+      #[test]
+      fn fir_sinc_design() {
+        assert_eq!(fir_sinc(31, 44_100f32, 1_000f32, Window::Hann).unwrap().len(), 31);
+      }
+    }
+
+    mod ir {
+      use rasp::filter::ir::{self, Normalization};
+
+      #[test]
+      fn prepare() {
+        let mut response = ir::resample(&[0f32, 0.5f32, 0.25f32], 48_000f32, 48_000f32);
+        ir::fade_out(&mut response, 1);
+        assert!((ir::normalize(&mut response, Normalization::Peak) - 2f32).abs() < 1e-4f32);
+        assert_eq!(ir::trim_silence(&response, -60f32).len(), 1);
+      }
+    }
+
+    mod matched {
+      use rasp::traits::Processor;
+      use rasp::filter::matched::{LowPass, Peak};
+
+      #[test]
+      fn lowpass() {
+        let mut filter = LowPass::new();
+        filter.set_coefficients(44_100f32, 12_000f32, 0.71f32);
+        assert!(filter.process(0.1f32) != 0.1f32);
+      }
+
+      #[test]
+      fn peak() {
+        let mut filter = Peak::new();
+        filter.set_coefficients(44_100f32, 12_000f32, 3f32, 0.71f32);
+        assert!(filter.process(0.1f32) != 0.1f32);
+      }
+    }
+
     mod rbj {
       use rasp::traits::Processor;
       use rasp::filter::rbj::{
@@ -140,6 +462,13 @@ mod api {
         assert!(filter.process(0.1f32) != 0.1f32);
       }
 
+      #[test]
+      fn lowshelf_slope() {
+        let mut filter = LowShelf::new();
+        filter.set_coefficients_slope(44_100f32, 12_000f32, 3f32, 1f32);
+        assert!(filter.process(0.1f32) != 0.1f32);
+      }
+
       #[test]
       fn highshelf() {
         let mut filter = HighShelf::new();
@@ -160,6 +489,90 @@ mod api {
         filter.set_coefficients(44_100f32, 12_000f32, 3f32, 0.71f32);
         assert!(filter.process(0.1f32) != 0.1f32);
       }
+
+      #[test]
+      fn peak_bandwidth() {
+        let mut filter = Peak::new();
+        filter.set_coefficients_bw(44_100f32, 12_000f32, 3f32, 1f32);
+        assert!(filter.process(0.1f32) != 0.1f32);
+      }
+    }
+
+    mod weighting {
+      use rasp::traits::Processor;
+      use rasp::filter::weighting::KWeighting;
+
+      #[test]
+      fn k_weighting() {
+        let mut filter = KWeighting::new(48_000f32);
+        assert!(filter.process(0.1f32) != 0.1f32);
+      }
+    }
+  }
+
+  mod bus {
+    use rasp::bus::{
+      BassManagement,
+      ChannelMatrix,
+      Mixer,
+      MultiChannel,
+      PanLaw,
+      SpeakerAlign,
+      Tap,
+      TapPoint
+    };
+    use rasp::analysis::PeakEnvDetector;
+    use rasp::filter::OnePole;
+    use rasp::traits::Processor;
+
+    #[test]
+    fn bass_management() {
+      let mut manager = BassManagement::new(44_100f32, 2);
+      let mut outputs = [1f32; 3];
+      manager.process(&[0f32, 0f32], 0f32, &mut outputs);
+      assert_eq!(outputs, [0f32; 3]);
+    }
+
+    #[test]
+    fn channel_matrix() {
+      let mut matrix = ChannelMatrix::new(44_100f32, 2, 2);
+      let mut output = [0f32; 2];
+      matrix.process(&[1f32, -1f32], &mut output);
+      assert_eq!(output, [1f32, -1f32]);
+    }
+
+    // An empty mixer outputs silence
+
+    #[test]
+    fn mixer() {
+      let mut mixer = Mixer::<f32>::new(0);
+      mixer.set_pan_law(PanLaw::Linear);
+      assert!(mixer.is_empty());
+      assert_eq!(mixer.process(&[]), (0f32, 0f32));
+    }
+
+    #[test]
+    fn multi_channel() {
+      let mut filters = MultiChannel::new(OnePole::new());
+      let mut samples = vec![1f32; 4];
+      filters.process_interleaved(&mut samples, 4);
+      assert_eq!(filters.channels(), 4);
+      assert_eq!(samples, vec![1f32; 4]);
+    }
+
+    #[test]
+    fn speaker_align() {
+      let mut align = SpeakerAlign::new(44_100f32, 2);
+      let mut frame = [1f32, -1f32];
+      align.process(&mut frame);
+      assert_eq!(frame, [1f32, -1f32]);
+    }
+
+    #[test]
+    fn tap() {
+      let mut tap = Tap::new(OnePole::new(), PeakEnvDetector::new(), TapPoint::Post);
+      assert_eq!(tap.process(1f32), 1f32);
+      assert_eq!(tap.analyzer().last_out(), 1f32);
     }
   }
 
@@ -188,6 +601,344 @@ mod api {
     }
   }
 
+  mod effects {
+    use std::f32::EPSILON;
+    use rasp::traits::{Processor, StereoProcessor};
+    use rasp::effects::{CombChorusBank, Crossfeed, Dispersion, Ducker, Echo, GatedReverb, HarmonicComb, LinearPhaseEq, LoudnessCompensation, PitchShifter, Reverb, ShimmerReverb, StereoRotate};
+    use rasp::filter::ParametricEq;
+
+    // No component here should alter the input until parameters are set
+
+    #[test]
+    fn comb_chorus_bank() {
+      let mut bank = CombChorusBank::new(44_100f32);
+      assert!((bank.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn crossfeed() {
+      let mut crossfeed = Crossfeed::new();
+      let (left, right) = crossfeed.process(1f32, 0f32);
+      assert!((left - 1f32).abs() < EPSILON);
+      assert!((right - 0f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn dispersion() {
+      let mut dispersion = Dispersion::new(1, 1);
+      dispersion.set_amount(0f32);
+      assert!((dispersion.process(1f32) - 0f32).abs() < EPSILON);
+      assert!((dispersion.process(0f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn ducker() {
+      let mut ducker = Ducker::new(44_100f32);
+      assert!((ducker.process(1f32, 1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn echo() {
+      let mut echo = Echo::new(44_100f32, 1f32);
+      echo.set_mix(0f32);
+      assert!((echo.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn gated_reverb() {
+      let mut gated = GatedReverb::new(44_100f32);
+      gated.set_mix(0f32);
+      assert!((gated.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn harmonic_comb() {
+      let mut comb = HarmonicComb::new(44_100f32, 80f32, 1_000f32);
+      assert!((comb.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn linear_phase_eq() {
+      let mut eq = LinearPhaseEq::new(&ParametricEq::new(44_100f32), 3, 1);
+      assert_eq!(eq.latency(), 2);
+      eq.process(1f32);
+      eq.process(0f32);
+      assert!((eq.process(0f32) - 1f32).abs() < 1e-6f32);
+    }
+
+    #[test]
+    fn loudness_compensation() {
+      let mut loudness = LoudnessCompensation::new(44_100f32);
+      assert!((loudness.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn pitch_shifter() {
+      let mut shifter = PitchShifter::new(44_100f32);
+      assert!((shifter.process(1f32) - 0f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn reverb() {
+      let mut reverb = Reverb::new(44_100f32);
+      reverb.set_mix(0f32);
+      assert!((reverb.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn shimmer_reverb() {
+      let mut shimmer = ShimmerReverb::new(44_100f32);
+      shimmer.set_mix(0f32);
+      assert!((shimmer.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn stereo_rotate() {
+      let mut rotate = StereoRotate::new(44_100f32, &[]);
+      let (left, right) = rotate.process(1f32, 0f32);
+      assert!((left - 1f32).abs() < EPSILON);
+      assert!((right - 0f32).abs() < EPSILON);
+    }
+  }
+
+  mod envelope {
+    use std::f32::EPSILON;
+    use rasp::traits::{Envelope, Generator};
+    use rasp::envelope::{
+      Adsr,
+      Ar,
+      GateEvent
+    };
+
+    // No component here should generate a signal until the gate is opened
+
+    #[test]
+    fn adsr() {
+      let mut envelope = Adsr::<f32>::new();
+      assert!((envelope.tick() - 0f32).abs() < EPSILON);
+      envelope.gate_on();
+      assert!((envelope.tick() - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn ar() {
+      let mut envelope = Ar::<f32>::new();
+      assert!((envelope.tick() - 0f32).abs() < EPSILON);
+      envelope.gate_on();
+      assert!((envelope.tick() - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn render() {
+      let mut envelope = Adsr::new();
+      let mut block = vec![0f32; 4];
+      envelope.render(&mut block, &[GateEvent::on(1), GateEvent::off(3)]);
+      assert!((block[0] - 0f32).abs() < EPSILON);
+      assert!((block[1] - 1f32).abs() < EPSILON);
+      assert!((block[2] - 1f32).abs() < EPSILON);
+      assert!((block[3] - 0f32).abs() < EPSILON);
+    }
+  }
+
+  mod generator {
+    use rasp::generator::{Calibration, Oscillator, Waveform};
+    use rasp::traits::Generator;
+
+    #[test]
+    fn calibration() {
+      let mut tone = Calibration::reference(44_100f32, 0f32);
+      assert_eq!(tone.tick(), 0f32);
+      assert!(tone.tick() > 0f32);
+    }
+
+    #[test]
+    fn oscillator() {
+      let mut oscillator = Oscillator::new(44_100f32, Waveform::Sine, 440f32);
+      assert_eq!(oscillator.tick(), 0f32);
+      assert!(oscillator.tick() > 0f32);
+    }
+  }
+
+  mod mastering {
+    use rasp::mastering::{BusChain, Compressor, Limiter};
+    use rasp::traits::StereoProcessor;
+
+    #[test]
+    fn bus_chain() {
+      let mut chain = BusChain::new(44_100f32);
+      assert_eq!(chain.process(0f32, 0f32), (0f32, 0f32));
+    }
+
+    #[test]
+    fn compressor() {
+      let mut compressor = Compressor::new(44_100f32);
+      assert_eq!(compressor.process(0f32, 0f32), (0f32, 0f32));
+    }
+
+    #[test]
+    fn limiter() {
+      let mut limiter = Limiter::new(44_100f32);
+      assert_eq!(limiter.process(0f32, 0f32), (0f32, 0f32));
+    }
+  }
+
+  mod prelude {
+    use rasp::prelude::*;
+
+    #[test]
+    fn prelude() {
+      let mut filter = Biquad2::new();
+      filter.load_coefficients(BiquadCoefficients { b0: 0.5f32, b1: 0f32, b2: 0f32, a1: 0f32, a2: 0f32 });
+      assert_eq!(filter.process(1f32), 0.5f32);
+      assert_eq!(filter.magnitude_at(1_000f32, 44_100f32), 0.5f32);
+    }
+  }
+
+  mod render {
+    use rasp::render;
+
+    #[test]
+    fn offline() {
+      let output = render::offline(0.5f32, 1_000f32, |block: &mut [f32], _| {
+        for sample in block.iter_mut() {
+          *sample = 1f32;
+        }
+      });
+      assert_eq!(output, vec![1f32; 500]);
+    }
+
+    #[test]
+    fn process_context() {
+      let context = render::ProcessContext::new(1_000f32, 64);
+      assert_eq!(context.block_size, 64);
+      assert!(context.transport.is_none());
+    }
+  }
+
+  mod restore {
+    use rasp::traits::Processor;
+    use rasp::restore::{Declick, Declip, HumRemover};
+
+    #[test]
+    fn declick() {
+      let mut samples = vec![0f32; 64];
+      samples[32] = 1f32;
+      let declick = Declick::new();
+      assert_eq!(declick.repair(&mut samples), 1);
+      assert!(samples[32].abs() < 1f32);
+    }
+
+    #[test]
+    fn declip() {
+      let mut samples = vec![0f32, 0.5f32, 1f32, 1f32, 0.5f32, 0f32];
+      let declip = Declip::new();
+      assert_eq!(declip.repair(&mut samples), 1);
+      assert!(samples[2] >= 1f32 && samples[3] >= 1f32);
+    }
+
+    #[test]
+    fn hum_remover() {
+      let mut hum_remover = HumRemover::new(44_100f32);
+      hum_remover.set_depth(0f32);
+      assert!((hum_remover.process(1f32) - 1f32).abs() < 1e-6f32);
+    }
+  }
+
+  mod sequencer {
+    use rasp::envelope::GateEvent;
+    use rasp::sequencer::{Arpeggiator, NoteEvent, StepSequencer};
+
+    #[test]
+    fn arpeggiator() {
+      let mut arpeggiator = Arpeggiator::new(44_100f32);
+      assert!(arpeggiator.render(64).is_empty());
+      arpeggiator.note_on(60);
+      assert_eq!(arpeggiator.render(64), vec![NoteEvent::on(0, 60)]);
+      arpeggiator.note_off(60);
+      assert!(arpeggiator.held().is_empty());
+    }
+
+    #[test]
+    fn step_sequencer() {
+      let mut sequencer = StepSequencer::new(44_100f32, 1, 16);
+      assert_eq!(sequencer.render(64), vec![Vec::<GateEvent>::new()]);
+      sequencer.set_step(0, 0, true);
+      sequencer.reset();
+      assert_eq!(sequencer.render(64)[0], vec![GateEvent::on(0)]);
+    }
+  }
+
+  mod spatial {
+    use std::f32::EPSILON;
+    use rasp::traits::{Panner, Processor};
+    use rasp::spatial::{
+      BinauralPanner,
+      Distance,
+      Doppler,
+      SimplePanner3D
+    };
+
+    #[test]
+    fn binaural_panner() {
+      let mut panner = BinauralPanner::new();
+      panner.add_hrir(0f32, 0f32, &[1f32], &[1f32]);
+      let (left, right) = panner.process(1f32);
+      assert!((left - 1f32).abs() < EPSILON);
+      assert!((right - 1f32).abs() < EPSILON);
+    }
+
+    // Sources at the listener, or at the reference distance, are not altered
+
+    #[test]
+    fn distance() {
+      let mut distance = Distance::new(44_100f32);
+      assert!((distance.process(1f32) - 1f32).abs() < 1e-6f32);
+    }
+
+    #[test]
+    fn doppler() {
+      let mut doppler = Doppler::new(44_100f32, 100f32);
+      assert!((doppler.process(1f32) - 1f32).abs() < EPSILON);
+    }
+
+    #[test]
+    fn panners() {
+      // Panners can be driven generically, and boxed
+      let mut binaural = BinauralPanner::new();
+      binaural.add_hrir(0f32, 0f32, &[1f32], &[1f32]);
+      let mut panners: Vec<Box<dyn Panner<f32>>> = vec![
+        Box::new(binaural),
+        Box::new(SimplePanner3D::new(44_100f32))
+      ];
+      for panner in panners.iter_mut() {
+        let (mut left, mut right) = (vec![0f32; 4], vec![0f32; 4]);
+        let last = panner.process_block(&[1f32, 0f32, 0f32, 0f32], &mut left, &mut right);
+        assert_eq!(last, panner.last_out());
+        assert!((left[0] - 1f32).abs() < EPSILON && (right[0] - 1f32).abs() < EPSILON);
+      }
+    }
+
+    #[test]
+    fn simple_panner_3d() {
+      let mut panner = SimplePanner3D::new(44_100f32);
+      let (left, right) = panner.process(1f32);
+      assert!((left - 1f32).abs() < EPSILON);
+      assert!((right - 1f32).abs() < EPSILON);
+    }
+  }
+
+  mod transport {
+    use rasp::transport::Transport;
+
+    #[test]
+    fn transport() {
+      let mut transport = Transport::new(44_100f32);
+      transport.play();
+      transport.advance(22_050);
+      assert!((transport.get_beats() - 1f32).abs() < 1e-6f32);
+    }
+  }
+
   mod util {
     use rasp::util;
     use std::f32::EPSILON;
@@ -197,5 +948,51 @@ mod api {
       assert!((util::to_db(0f32) - -120f32).abs() < EPSILON);
       assert!((util::to_sample(-120f32) - 0f32).abs() < EPSILON);
     }
+
+    #[test]
+    fn guard() {
+      use rasp::filter::OnePole;
+      use rasp::traits::Processor;
+
+      let mut guard = util::Guard::new(OnePole::new());
+      assert_eq!(guard.process(::std::f32::NAN), 0f32);
+      assert_eq!(guard.faults(), 1);
+    }
+
+    #[test]
+    fn elastic_buffer() {
+      let mut buffer = util::ElasticBuffer::new(4);
+      buffer.push(0, &[1f32]);
+      let mut frame = [0f32; 2];
+      assert_eq!(buffer.pull(&mut frame), Some(0));
+      assert_eq!(frame, [1f32, 0f32]);
+    }
+
+    #[test]
+    fn notes() {
+      assert_eq!(util::parse_note::<f32>("A4"), Some(69f32));
+      assert!((util::midi_to_frequency(69f32) - 440f32).abs() < 1e-3f32);
+    }
+
+    #[test]
+    fn scales() {
+      assert!((util::scales::frequency_to_mel(1_000f32) - 1_000f32).abs() < 0.1f32);
+      assert!((util::scales::erb_to_frequency(util::scales::frequency_to_erb(440f32)) - 440f32).abs() < 1e-2f32);
+    }
+  }
+
+  mod voice {
+    use rasp::traits::Generator;
+    use rasp::voice::MonoSynth;
+
+    // The voice should not generate a signal until a note is played
+
+    #[test]
+    fn mono_synth() {
+      let mut synth = MonoSynth::new(44_100f32);
+      assert_eq!(synth.tick(), 0f32);
+      synth.note_on(60, 1f32);
+      assert!((0..64).any(|_| synth.tick() != 0f32));
+    }
   }
 }